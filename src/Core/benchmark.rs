@@ -0,0 +1,166 @@
+// src/core/benchmark.rs
+
+//! Repeated timing of whole runs for `--bench`. A single run is at the mercy of the scheduler and
+//! the page cache, so each case runs several times and regressions are judged on the median,
+//! which one slow trial can't move.
+
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use num::BigInt;
+use serde::{Serialize, Deserialize};
+use crate::core::error::GnfsError;
+
+pub const DEFAULT_TRIALS: usize = 3;
+/// A median this much slower than the baseline's, in percent, counts as a regression.
+pub const REGRESSION_THRESHOLD_PERCENT: f64 = 10.0;
+
+/// Timings of one case over every trial.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub case: String,
+    /// Wall-clock seconds of each trial, in the order they ran.
+    pub trials: Vec<f64>,
+    pub median: f64,
+    pub min: f64,
+    pub mean: f64,
+    /// Sample standard deviation; zero for a single trial.
+    pub stddev: f64,
+    /// Trials that ended with the factors found.
+    pub factored: usize,
+}
+
+impl BenchmarkResult {
+    pub fn from_trials(case: &str, trials: Vec<f64>, factored: usize) -> Self {
+        let mut sorted = trials.clone();
+        sorted.sort_by(f64::total_cmp);
+        let count = sorted.len();
+        let median = match count {
+            0 => 0.0,
+            _ if count % 2 == 1 => sorted[count / 2],
+            _ => (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0,
+        };
+        let mean = if count == 0 { 0.0 } else { sorted.iter().sum::<f64>() / count as f64 };
+        let stddev = if count < 2 {
+            0.0
+        } else {
+            (sorted.iter().map(|seconds| (seconds - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+        };
+        BenchmarkResult {
+            case: case.to_string(),
+            trials,
+            median,
+            min: sorted.first().copied().unwrap_or(0.0),
+            mean,
+            stddev,
+            factored,
+        }
+    }
+}
+
+impl Display for BenchmarkResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: median {:.3}s, min {:.3}s, mean {:.3}s ± {:.3}s over {} trials ({} factored)",
+            self.case, self.median, self.min, self.mean, self.stddev, self.trials.len(), self.factored
+        )
+    }
+}
+
+/// A case whose median got slower than the baseline's by more than the threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub case: String,
+    pub baseline_median: f64,
+    pub median: f64,
+    /// Slowdown relative to the baseline median, in percent.
+    pub percent: f64,
+}
+
+impl Display for Regression {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: median {:.3}s vs {:.3}s baseline (+{:.1}%)", self.case, self.median, self.baseline_median, self.percent)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub results: Vec<BenchmarkResult>,
+}
+
+impl BenchmarkReport {
+    /// Runs every case `trials` times. `run_once` performs a full run and returns its wall-clock
+    /// time and whether it found the factors.
+    pub fn run(
+        cases: &[BigInt],
+        trials: usize,
+        mut run_once: impl FnMut(&BigInt) -> Result<(Duration, bool), GnfsError>,
+    ) -> Result<Self, GnfsError> {
+        if trials == 0 {
+            return Err(GnfsError::InvalidParameter("a benchmark needs at least one trial".to_string()));
+        }
+        let mut results = Vec::with_capacity(cases.len());
+        for n in cases {
+            let mut seconds = Vec::with_capacity(trials);
+            let mut factored = 0;
+            for _ in 0..trials {
+                let (elapsed, found) = run_once(n)?;
+                seconds.push(elapsed.as_secs_f64());
+                factored += found as usize;
+            }
+            results.push(BenchmarkResult::from_trials(&n.to_string(), seconds, factored));
+        }
+        Ok(BenchmarkReport { results })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), GnfsError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, GnfsError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Cases in both reports whose median is more than `threshold_percent` slower than the
+    /// baseline's. Cases only one report has are ignored.
+    pub fn regressions(&self, baseline: &BenchmarkReport, threshold_percent: f64) -> Vec<Regression> {
+        self.results
+            .iter()
+            .filter_map(|result| {
+                let previous = baseline.results.iter().find(|previous| previous.case == result.case)?;
+                if previous.median <= 0.0 {
+                    return None;
+                }
+                let percent = (result.median / previous.median - 1.0) * 100.0;
+                (percent > threshold_percent).then(|| Regression {
+                    case: result.case.clone(),
+                    baseline_median: previous.median,
+                    median: result.median,
+                    percent,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_and_regressions_use_the_median() {
+        let noisy = BenchmarkResult::from_trials("45113", vec![1.0, 9.0, 1.2, 1.1], 4);
+        assert_eq!(noisy.median, 1.15);
+        assert_eq!(noisy.min, 1.0);
+        assert!((noisy.mean - 3.075).abs() < 1e-12);
+        assert!(noisy.stddev > 3.0);
+
+        let baseline = BenchmarkReport { results: vec![BenchmarkResult::from_trials("45113", vec![1.1, 1.1, 1.1], 3)] };
+        let current = BenchmarkReport { results: vec![noisy] };
+        assert!(current.regressions(&baseline, REGRESSION_THRESHOLD_PERCENT).is_empty());
+        assert_eq!(baseline.regressions(&BenchmarkReport { results: vec![BenchmarkResult::from_trials("45113", vec![0.5], 1)] }, 10.0).len(), 1);
+    }
+}
@@ -1,10 +1,15 @@
 // src/core/cancellation_token.rs
 
 use std::sync::{Arc, Mutex, Condvar};
+use std::time::Instant;
 
 pub struct CancellationToken {
     is_cancelled: Arc<Mutex<bool>>,
     condvar: Arc<Condvar>,
+    /// Soft stop time. Unlike [`CancellationToken::cancel`] it doesn't interrupt work in progress;
+    /// stages check it between sieve batches and before starting, so the run stops at a point
+    /// where its state is consistent.
+    deadline: Arc<Mutex<Option<Instant>>>,
 }
 
 impl CancellationToken {
@@ -12,9 +17,23 @@ impl CancellationToken {
         CancellationToken {
             is_cancelled: Arc::new(Mutex::new(false)),
             condvar: Arc::new(Condvar::new()),
+            deadline: Arc::new(Mutex::new(None)),
         }
     }
 
+    pub fn set_deadline(&self, deadline: Instant) {
+        *self.deadline.lock().unwrap() = Some(deadline);
+    }
+
+    pub fn deadline_reached(&self) -> bool {
+        self.deadline.lock().unwrap().is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// True when the run should stop at the next consistent point: cancelled, or past its deadline.
+    pub fn should_stop(&self) -> bool {
+        self.is_cancellation_requested() || self.deadline_reached()
+    }
+
     pub fn is_cancellation_requested(&self) -> bool {
         *self.is_cancelled.lock().unwrap()
     }
@@ -43,11 +62,18 @@ impl CancellationToken {
     }
 }
 
+impl Default for CancellationToken {
+    fn default() -> Self {
+        CancellationToken::new()
+    }
+}
+
 impl Clone for CancellationToken {
     fn clone(&self) -> Self {
         CancellationToken {
             is_cancelled: self.is_cancelled.clone(),
             condvar: self.condvar.clone(),
+            deadline: self.deadline.clone(),
         }
     }
 }
\ No newline at end of file
@@ -1,60 +1,223 @@
 // src/core/count_dictionary.rs
-use num::{BigInt, One, Zero};
+use num::{BigInt, ToPrimitive};
 use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct CountDictionary(pub BTreeMap<BigInt, BigInt>);
+/// Prime -> exponent counts for a factorization.
+///
+/// Nearly every key is a factor base prime, so keys that fit in a u64 live in a
+/// sorted vector (cheap to merge and to binary search), and only the rare
+/// negative or oversized keys fall back to a BigInt map.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CountDictionary {
+    small: Vec<(u64, u32)>,
+    large: BTreeMap<BigInt, u32>,
+}
 
 impl CountDictionary {
     pub fn new() -> Self {
-        CountDictionary(BTreeMap::new())
+        CountDictionary {
+            small: Vec::new(),
+            large: BTreeMap::new(),
+        }
     }
 
     pub fn add(&mut self, key: &BigInt) {
-        self.add_safe(key, BigInt::one());
+        self.add_count(key, 1);
+    }
+
+    pub fn add_count(&mut self, key: &BigInt, count: u32) {
+        match key.to_u64() {
+            Some(small_key) => self.add_small(small_key, count),
+            None => *self.large.entry(key.clone()).or_insert(0) += count,
+        }
+    }
+
+    fn add_small(&mut self, key: u64, count: u32) {
+        match self.small.binary_search_by_key(&key, |&(k, _)| k) {
+            Ok(index) => self.small[index].1 += count,
+            Err(index) => self.small.insert(index, (key, count)),
+        }
     }
 
-    fn add_safe(&mut self, key: &BigInt, value: BigInt) {
-        let entry = self.0.entry(key.clone()).or_insert_with(BigInt::zero);
-        *entry += value;
+    pub fn get(&self, key: &BigInt) -> u32 {
+        match key.to_u64() {
+            Some(small_key) => self.small
+                .binary_search_by_key(&small_key, |&(k, _)| k)
+                .map(|index| self.small[index].1)
+                .unwrap_or(0),
+            None => self.large.get(key).copied().unwrap_or(0),
+        }
     }
 
     pub fn combine(&mut self, other: &CountDictionary) {
-        for (key, value) in &other.0 {
-            self.add_safe(key, value.clone());
+        let mut merged = Vec::with_capacity(self.small.len() + other.small.len());
+        let (mut i, mut j) = (0, 0);
+        while i < self.small.len() && j < other.small.len() {
+            let (left, right) = (self.small[i], other.small[j]);
+            if left.0 < right.0 {
+                merged.push(left);
+                i += 1;
+            } else if right.0 < left.0 {
+                merged.push(right);
+                j += 1;
+            } else {
+                merged.push((left.0, left.1 + right.1));
+                i += 1;
+                j += 1;
+            }
+        }
+        merged.extend_from_slice(&self.small[i..]);
+        merged.extend_from_slice(&other.small[j..]);
+        self.small = merged;
+
+        for (key, value) in &other.large {
+            *self.large.entry(key.clone()).or_insert(0) += value;
         }
     }
 
+    /// Merges many dictionaries at once with a single sort, instead of
+    /// re-merging the accumulated result once per dictionary.
+    pub fn combine_many<'a, I>(dictionaries: I) -> CountDictionary
+    where
+        I: IntoIterator<Item = &'a CountDictionary>,
+    {
+        let mut result = CountDictionary::new();
+        let mut entries = Vec::new();
+        for dictionary in dictionaries {
+            entries.extend_from_slice(&dictionary.small);
+            for (key, value) in &dictionary.large {
+                *result.large.entry(key.clone()).or_insert(0) += value;
+            }
+        }
+        entries.sort_unstable_by_key(|&(key, _)| key);
+        for (key, value) in entries {
+            match result.small.last_mut() {
+                Some(last) if last.0 == key => last.1 += value,
+                _ => result.small.push((key, value)),
+            }
+        }
+        result
+    }
+
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.small.len() + self.large.len()
     }
 
-    pub fn to_dict(&self) -> BTreeMap<BigInt, BigInt> {
-        self.0.clone()
+    pub fn is_empty(&self) -> bool {
+        self.small.is_empty() && self.large.is_empty()
     }
 
-    pub fn clone_dict(&self) -> BTreeMap<BigInt, BigInt> {
-        self.0.clone()
+    /// Iterates entries in ascending key order.
+    pub fn iter(&self) -> impl Iterator<Item = (BigInt, u32)> + '_ {
+        let negative = self.large.iter().filter(|(key, _)| key.sign() == num::bigint::Sign::Minus);
+        let oversized = self.large.iter().filter(|(key, _)| key.sign() != num::bigint::Sign::Minus);
+        negative
+            .map(|(key, &value)| (key.clone(), value))
+            .chain(self.small.iter().map(|&(key, value)| (BigInt::from(key), value)))
+            .chain(oversized.map(|(key, &value)| (key.clone(), value)))
+    }
+
+    pub fn to_dict(&self) -> BTreeMap<BigInt, u32> {
+        self.iter().collect()
+    }
+
+    pub fn clone_dict(&self) -> BTreeMap<BigInt, u32> {
+        self.to_dict()
     }
 
     pub fn to_string(&self) -> String {
         let mut result = String::from("{\n");
-        for (key, value) in &self.0 {
+        for (key, value) in self.iter() {
             result.push_str(&format!("\t{:5}: {:5}\n", key, value));
         }
         result.push('}');
         result
     }
 
-    pub fn retain<F>(&mut self, predicate: F)
+    pub fn retain<F>(&mut self, mut predicate: F)
     where
-        F: FnMut(&BigInt, &mut BigInt) -> bool,
+        F: FnMut(&BigInt, u32) -> bool,
     {
-        self.0.retain(predicate);
+        self.small.retain(|&(key, value)| predicate(&BigInt::from(key), value));
+        self.large.retain(|key, value| predicate(key, *value));
     }
 
     pub fn format_string_as_factorization(&self) -> String {
-        let factors: Vec<String> = self.0.iter().map(|(key, value)| format!("{}^{}", key, value)).collect();
+        let factors: Vec<String> = self.iter().map(|(key, value)| format!("{}^{}", key, value)).collect();
         format!(" -> {{\t{}\t}};", factors.join(" * "))
     }
-}
\ No newline at end of file
+
+    /// Compact textual form used for serialization, e.g. `2^3*5*7^2`.
+    pub fn to_compact_string(&self) -> String {
+        self.iter()
+            .map(|(key, value)| if value == 1 { key.to_string() } else { format!("{}^{}", key, value) })
+            .collect::<Vec<String>>()
+            .join("*")
+    }
+
+    pub fn from_compact_string(input: &str) -> Result<Self, String> {
+        let mut result = CountDictionary::new();
+        for factor in input.split('*').filter(|s| !s.is_empty()) {
+            let (key, value) = match factor.split_once('^') {
+                Some((key, value)) => (key, value.parse::<u32>().map_err(|e| format!("Invalid exponent in '{}': {}", factor, e))?),
+                None => (factor, 1),
+            };
+            let key = key.parse::<BigInt>().map_err(|e| format!("Invalid factor in '{}': {}", factor, e))?;
+            result.add_count(&key, value);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(entries: &[(i64, u32)]) -> CountDictionary {
+        let mut result = CountDictionary::new();
+        for &(key, count) in entries {
+            result.add_count(&BigInt::from(key), count);
+        }
+        result
+    }
+
+    #[test]
+    fn add_keeps_small_and_large_keys_in_ascending_order() {
+        let oversized = BigInt::from(u64::MAX) * 3;
+        let mut counts = CountDictionary::new();
+        counts.add(&BigInt::from(7));
+        counts.add(&BigInt::from(2));
+        counts.add(&BigInt::from(7));
+        counts.add(&BigInt::from(-1));
+        counts.add_count(&oversized, 2);
+
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts.get(&BigInt::from(7)), 2);
+        assert_eq!(counts.get(&BigInt::from(3)), 0);
+        assert_eq!(counts.get(&oversized), 2);
+        let keys: Vec<BigInt> = counts.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec![BigInt::from(-1), BigInt::from(2), BigInt::from(7), oversized]);
+    }
+
+    #[test]
+    fn combine_and_combine_many_sum_shared_keys() {
+        let left = dictionary(&[(-1, 1), (2, 3), (5, 1)]);
+        let right = dictionary(&[(-1, 1), (3, 2), (5, 4)]);
+        let expected = dictionary(&[(-1, 2), (2, 3), (3, 2), (5, 5)]);
+
+        let mut combined = left.clone();
+        combined.combine(&right);
+        assert_eq!(combined, expected);
+        assert_eq!(CountDictionary::combine_many([&left, &right]), expected);
+        assert!(CountDictionary::combine_many(std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn compact_string_round_trips() {
+        let counts = dictionary(&[(-1, 1), (2, 3), (7, 2), (11, 1)]);
+        let text = counts.to_compact_string();
+        assert_eq!(text, "-1*2^3*7^2*11");
+        assert_eq!(CountDictionary::from_compact_string(&text), Ok(counts));
+        assert!(CountDictionary::from_compact_string("2^x").is_err());
+    }
+}
@@ -34,6 +34,14 @@ impl CPUInfo {
     pub fn l3_cache_line_size() -> Option<usize> {
         l3_cache_line_size()
     }
+
+    // Fetches the memory in bytes currently available to new allocations (Linux only).
+    pub fn available_memory() -> Option<usize> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+        let kilobytes: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+        kilobytes.checked_mul(1024)
+    }
 }
 
 
@@ -1,9 +1,29 @@
 // src/core/directory_locations.rs
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use num::BigInt;
 use serde::{Serialize, Deserialize};
+use crate::core::error::GnfsError;
+use crate::core::events::EVENTS_FILENAME;
+use crate::relation_sieve::storage::RelationFormat;
 const SHOW_DIGITS: usize = 22;
 const ELLIPSIS: &str = "[...]";
 
+/// Distinguishes temporary directories created by one process.
+static TEMPORARY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Deletes a temporary save directory once the last copy of the locations pointing at it is dropped.
+#[derive(Debug)]
+struct TemporaryDirectory(PathBuf);
+
+impl Drop for TemporaryDirectory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirectoryLocations {
     pub base_directory: String,
@@ -14,6 +34,9 @@ pub struct DirectoryLocations {
     pub parameters_filepath: String,
     pub smooth_relations_filepath: String,
     pub rough_relations_filepath: String,
+    /// Set for locations made by [`DirectoryLocations::temporary`]; shared between clones.
+    #[serde(skip)]
+    temporary: Option<Arc<TemporaryDirectory>>,
 }
 
 impl DirectoryLocations {
@@ -35,9 +58,30 @@ impl DirectoryLocations {
             parameters_filepath: gnfs_parameters_save_file,
             smooth_relations_filepath: smooth_relations_save_file,
             rough_relations_filepath: rough_relations_save_file,
+            temporary: None,
         }
     }
 
+    /// Locations in a fresh directory under the system temporary directory, deleted with
+    /// everything in it when the last clone of the returned value is dropped. For library users
+    /// and tests that run small factorizations without leaving an `<N>/` folder behind.
+    pub fn temporary(name: &str) -> Result<Self, GnfsError> {
+        let directory = std::env::temp_dir().join(format!(
+            "gnfs_{}_{}_{}",
+            std::process::id(),
+            TEMPORARY_COUNTER.fetch_add(1, Ordering::Relaxed),
+            name
+        ));
+        fs::create_dir_all(&directory)?;
+        let mut locations = DirectoryLocations::new(&directory.to_string_lossy());
+        locations.temporary = Some(Arc::new(TemporaryDirectory(directory)));
+        Ok(locations)
+    }
+
+    pub fn is_temporary(&self) -> bool {
+        self.temporary.is_some()
+    }
+
     pub fn set_base_directory(&mut self, path: &str) {
         self.base_directory = path.to_string();
     }
@@ -61,6 +105,18 @@ impl DirectoryLocations {
         }
     }
 
+    pub fn events_filepath(&self) -> String {
+        format!("{}/{}", self.save_directory, EVENTS_FILENAME)
+    }
+
+    pub fn square_root_progress_filepath(&self) -> String {
+        format!("{}/SquareRootProgress.json", self.save_directory)
+    }
+
+    pub fn sorted_relations_filepath(&self, format: RelationFormat) -> String {
+        format!("{}/{}", self.save_directory, format.sorted_store_filename())
+    }
+
     pub fn enumerate_free_relation_files(&self) -> Vec<String> {
         // Implement the logic to enumerate free relation files
         Vec::new()
@@ -78,6 +134,26 @@ impl Default for DirectoryLocations {
             parameters_filepath: "".to_string(),
             smooth_relations_filepath: "".to_string(),
             rough_relations_filepath: "".to_string(),
+            temporary: None,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn temporary_directory_outlives_clones_only() {
+        let locations = DirectoryLocations::temporary("45113").unwrap();
+        let directory = locations.save_directory.clone();
+        assert!(Path::new(&directory).is_dir());
+        fs::write(&locations.parameters_filepath, "{}").unwrap();
+
+        let copy = locations.clone();
+        drop(locations);
+        assert!(Path::new(&copy.parameters_filepath).exists());
+        drop(copy);
+        assert!(!Path::new(&directory).exists());
+    }
+}
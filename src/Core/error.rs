@@ -0,0 +1,42 @@
+// src/core/error.rs
+
+use thiserror::Error;
+
+/// Errors surfaced by the GNFS pipeline and the supporting algorithms.
+#[derive(Debug, Error)]
+pub enum GnfsError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Network error: {0}")]
+    Network(String),
+
+    #[error("Device error: {0}")]
+    Device(String),
+
+    #[error("Value out of range: {0}")]
+    Overflow(String),
+
+    #[error("Invalid parameter: {0}")]
+    InvalidParameter(String),
+
+    #[error("Invalid input {input:?}: {reason}")]
+    InvalidInput { input: String, reason: String },
+
+    #[error("{stage} failed: {reason}")]
+    StageFailed { stage: String, reason: String },
+
+    #[error("Cancelled by user")]
+    Cancelled,
+}
+
+impl GnfsError {
+    pub fn stage_failed(stage: &str, reason: impl Into<String>) -> Self {
+        GnfsError::StageFailed { stage: stage.to_string(), reason: reason.into() }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, GnfsError>;
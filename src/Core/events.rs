@@ -0,0 +1,136 @@
+// src/core/events.rs
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::fmt::Display;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use crate::core::error::GnfsError;
+use crate::relation_sieve::poly_relations_sieve_progress::SieveStatistics;
+
+pub const EVENTS_FILENAME: &str = "events.jsonl";
+
+/// Something that happened during a run, recorded for the status and report commands.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum GnfsEvent {
+    StageStarted { stage: String },
+    StageCompleted { stage: String, seconds: f64 },
+    ParameterChosen { name: String, value: String },
+    DependencyAttempt { index: usize, outcome: DependencyOutcome },
+    DependenciesExhausted { count: usize },
+    FactorsFound { p: String, q: String },
+    Cancelled { stage: String },
+    SieveProgress(SieveStatistics),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyOutcome {
+    Factored,
+    Trivial,
+    /// Rejected before any square root work: the product is a non-residue modulo `q` at root `s`.
+    NotASquare { q: u64, s: u64 },
+    Failed(String),
+}
+
+impl GnfsEvent {
+    pub fn parameter(name: &str, value: impl ToString) -> Self {
+        GnfsEvent::ParameterChosen { name: name.to_string(), value: value.to_string() }
+    }
+}
+
+impl Display for GnfsEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GnfsEvent::StageStarted { stage } => write!(f, "Stage started: {}", stage),
+            GnfsEvent::StageCompleted { stage, seconds } => write!(f, "Stage completed: {} ({:.3}s)", stage, seconds),
+            GnfsEvent::ParameterChosen { name, value } => write!(f, "{}: {}", name, value),
+            GnfsEvent::DependencyAttempt { index, outcome } => match outcome {
+                DependencyOutcome::Factored => write!(f, "Dependency #{}: non-trivial factors found", index + 1),
+                DependencyOutcome::Trivial => write!(f, "Dependency #{}: trivial square root, trying a different solution set", index + 1),
+                DependencyOutcome::NotASquare { q, s } => write!(f, "Dependency #{}: not a square under the quadratic character (q = {}, s = {}), skipped", index + 1, q, s),
+                DependencyOutcome::Failed(reason) => write!(f, "Dependency #{}: {}", index + 1, reason),
+            },
+            GnfsEvent::DependenciesExhausted { count } => write!(f, "All {} solution sets have been tried without finding non-trivial factors", count),
+            GnfsEvent::FactorsFound { p, q } => write!(f, "NON-TRIVIAL FACTORS FOUND! p: {}, q: {}", p, q),
+            GnfsEvent::Cancelled { stage } => write!(f, "Abort: {} canceled by user!", stage),
+            GnfsEvent::SieveProgress(statistics) => write!(f, "{}", statistics),
+        }
+    }
+}
+
+/// One line of `events.jsonl`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: GnfsEvent,
+}
+
+pub fn append(path: &Path, event: &GnfsEvent) -> Result<(), GnfsError> {
+    let record = EventRecord { timestamp: Utc::now(), event: event.clone() };
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(&record)?)?;
+    Ok(())
+}
+
+pub fn read(path: &Path) -> Result<Vec<EventRecord>, GnfsError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(GnfsError::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::directory_location::DirectoryLocations;
+
+    #[test]
+    fn every_event_round_trips_through_the_events_file() {
+        let locations = DirectoryLocations::temporary("events").unwrap();
+        let path = Path::new(&locations.save_directory).join(EVENTS_FILENAME);
+        assert_eq!(read(&path).unwrap(), Vec::new());
+
+        let events = vec![
+            GnfsEvent::StageStarted { stage: "sieve".to_string() },
+            GnfsEvent::StageCompleted { stage: "sieve".to_string(), seconds: 1.25 },
+            GnfsEvent::parameter("Prime bound", 200),
+            GnfsEvent::DependencyAttempt { index: 0, outcome: DependencyOutcome::Factored },
+            GnfsEvent::DependencyAttempt { index: 1, outcome: DependencyOutcome::Trivial },
+            GnfsEvent::DependencyAttempt { index: 2, outcome: DependencyOutcome::NotASquare { q: 1009, s: 17 } },
+            GnfsEvent::DependencyAttempt { index: 3, outcome: DependencyOutcome::Failed("no square root".to_string()) },
+            GnfsEvent::DependenciesExhausted { count: 4 },
+            GnfsEvent::FactorsFound { p: "197".to_string(), q: "229".to_string() },
+            GnfsEvent::Cancelled { stage: "matrix".to_string() },
+            GnfsEvent::SieveProgress(SieveStatistics { smooth_relations: 12, target_quantity: 65, acceptance_rate: 0.5, ..SieveStatistics::default() }),
+        ];
+        for event in &events {
+            append(&path, event).unwrap();
+        }
+
+        let records = read(&path).unwrap();
+        assert_eq!(records.iter().map(|record| record.event.clone()).collect::<Vec<GnfsEvent>>(), events);
+        assert!(records.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+        let first_line = fs::read_to_string(&path).unwrap().lines().next().unwrap().to_string();
+        assert!(first_line.contains(r#""event":"stage_started""#) && first_line.contains(r#""stage":"sieve""#), "{}", first_line);
+    }
+
+    #[test]
+    fn dependency_attempts_are_numbered_from_one() {
+        let attempt = |outcome| GnfsEvent::DependencyAttempt { index: 2, outcome }.to_string();
+        assert_eq!(attempt(DependencyOutcome::Factored), "Dependency #3: non-trivial factors found");
+        assert_eq!(
+            attempt(DependencyOutcome::NotASquare { q: 1009, s: 17 }),
+            "Dependency #3: not a square under the quadratic character (q = 1009, s = 17), skipped"
+        );
+        assert_eq!(GnfsEvent::StageCompleted { stage: "matrix".to_string(), seconds: 0.5 }.to_string(), "Stage completed: matrix (0.500s)");
+        assert_eq!(GnfsEvent::parameter("Prime bound", 200).to_string(), "Prime bound: 200");
+    }
+}
@@ -0,0 +1,97 @@
+// src/core/factordb.rs
+
+use num::BigInt;
+use serde::Deserialize;
+use std::str::FromStr;
+use std::time::Duration;
+use crate::core::error::GnfsError;
+
+const FACTORDB_URL: &str = "http://factordb.com";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How much factordb knows about a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FactorDbStatus {
+    Prime,
+    FullyFactored,
+    PartiallyFactored,
+    Composite,
+    Unknown,
+}
+
+impl FactorDbStatus {
+    fn from_code(code: &str) -> Self {
+        match code {
+            "P" | "PRP" => FactorDbStatus::Prime,
+            "FF" => FactorDbStatus::FullyFactored,
+            "CF" => FactorDbStatus::PartiallyFactored,
+            "C" => FactorDbStatus::Composite,
+            _ => FactorDbStatus::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FactorDbEntry {
+    pub status: FactorDbStatus,
+    /// Known factors with multiplicity, as reported by factordb.
+    pub factors: Vec<(BigInt, u32)>,
+}
+
+#[derive(Deserialize)]
+struct QueryResponse {
+    status: String,
+    factors: Vec<(String, u32)>,
+}
+
+pub struct FactorDbClient {
+    agent: ureq::Agent,
+    base_url: String,
+}
+
+impl FactorDbClient {
+    pub fn new() -> Self {
+        FactorDbClient::with_base_url(FACTORDB_URL)
+    }
+
+    pub fn with_base_url(base_url: &str) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+        FactorDbClient { agent, base_url: base_url.trim_end_matches('/').to_string() }
+    }
+
+    pub fn query(&self, n: &BigInt) -> Result<FactorDbEntry, GnfsError> {
+        let response: QueryResponse = self.agent
+            .get(&format!("{}/api", self.base_url))
+            .query("query", &n.to_string())
+            .call()
+            .map_err(|e| GnfsError::Network(e.to_string()))?
+            .into_json()?;
+
+        let factors = response.factors
+            .into_iter()
+            .map(|(factor, exponent)| {
+                BigInt::from_str(&factor)
+                    .map(|factor| (factor, exponent))
+                    .map_err(|e| GnfsError::Network(format!("factordb returned an invalid factor '{}': {}", factor, e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(FactorDbEntry { status: FactorDbStatus::from_code(&response.status), factors })
+    }
+
+    /// Reports factors of `n` found by this run.
+    pub fn submit(&self, n: &BigInt, factors: &[BigInt]) -> Result<(), GnfsError> {
+        let report = factors.iter().map(|factor| format!("{}={}", n, factor)).collect::<Vec<_>>().join("\n");
+        self.agent
+            .post(&format!("{}/report.php", self.base_url))
+            .send_form(&[("report", &report), ("format", "7")])
+            .map_err(|e| GnfsError::Network(e.to_string()))?;
+        Ok(())
+    }
+}
+
+impl Default for FactorDbClient {
+    fn default() -> Self {
+        FactorDbClient::new()
+    }
+}
@@ -0,0 +1,458 @@
+// src/core/factorizer.rs
+
+//! The whole factorization as a library call: small factors are trial-divided away, parameters
+//! come from the presets, and the stages run in order until the factors are found, the run is
+//! cancelled or a stage needs more work than it can do on its own.
+//!
+//! ```no_run
+//! use gnfs::Factorizer;
+//! use gnfs::core::cancellation_token::CancellationToken;
+//! use num::BigInt;
+//!
+//! let result = Factorizer::new(BigInt::from(45113))
+//!     .on_stage_finished(|stage, outcome| println!("{}: {:?}", stage, outcome))
+//!     .run(&CancellationToken::new())?;
+//! println!("{:?}", result.factors());
+//! # Ok::<(), gnfs::core::error::GnfsError>(())
+//! ```
+
+use std::path::Path;
+use std::time::Instant;
+use log::{error, info, warn};
+use num::{BigInt, One};
+use crate::algorithms::{self, Algorithm};
+use crate::core::cancellation_token::CancellationToken;
+use crate::core::directory_location::DirectoryLocations;
+use crate::core::error::GnfsError;
+use crate::core::gnfs::GNFS;
+use crate::core::gnfs_config::GnfsConfig;
+use crate::core::gnfs_presets::GnfsPreset;
+use crate::core::input::{self, NormalizedInput};
+use crate::core::progress::{ProgressHandle, ProgressReporter};
+use crate::core::serialization::checkpoint::MANIFEST_FILENAME;
+use crate::core::solution::{RunMetadata, Solution, SolutionParameters};
+use crate::core::stage::{Stage, StageOutcome};
+use crate::integer_math::factorization_factory::FactorizationFactory;
+use crate::polynomial::PolynomialSelection;
+
+/// Rational factor base bound for inputs without a preset.
+pub const DEFAULT_PRIME_BOUND: u64 = 100;
+const DEFAULT_POLYNOMIAL_BASE: u32 = 31;
+const DEFAULT_POLYNOMIAL_DEGREE: i32 = 3;
+const DEFAULT_RELATION_QUANTITY: usize = 1;
+const DEFAULT_RELATION_VALUE_RANGE: usize = 1000;
+
+type StageStartedCallback = Box<dyn FnMut(Stage) + Send>;
+type StageFinishedCallback = Box<dyn FnMut(Stage, StageOutcome) + Send>;
+
+/// Builds and drives a GNFS run for one N.
+pub struct Factorizer {
+    n: BigInt,
+    config: GnfsConfig,
+    save_locations: Option<DirectoryLocations>,
+    /// When set, N is factored with this method alone rather than GNFS.
+    algorithm: Option<Algorithm>,
+    /// Overrides of the preset parameters.
+    polynomial_degree: Option<usize>,
+    polynomial_base: Option<BigInt>,
+    prime_bound: Option<u64>,
+    stage_started: Vec<StageStartedCallback>,
+    stage_finished: Vec<StageFinishedCallback>,
+    /// Replaces the run's default console reporter when set.
+    progress: Option<ProgressHandle>,
+}
+
+/// How a [`Factorizer::run`] ended.
+#[derive(Debug, Clone)]
+pub struct FactorizationResult {
+    pub n: BigInt,
+    /// Primes found without GNFS, by trial division or the method set with
+    /// [`Factorizer::with_algorithm`], with their exponents, in increasing order.
+    pub small_factors: Vec<(BigInt, u32)>,
+    /// What trial division left: 1, a prime, or the composite handed to GNFS. It divides N
+    /// `cofactor_exponent` times when N's cofactor was a perfect power.
+    pub cofactor: BigInt,
+    pub cofactor_exponent: u32,
+    /// GNFS's split of the cofactor, once found.
+    pub solution: Option<Solution>,
+    pub outcome: StageOutcome,
+    /// What the stages recorded: the method, timings, relation counts and matrix size.
+    pub metadata: RunMetadata,
+    /// The GNFS parameters, once GNFS has run.
+    pub parameters: Option<SolutionParameters>,
+    /// Where the run checkpointed, for a later `gnfs resume`; `None` when GNFS never ran.
+    pub save_directory: Option<String>,
+}
+
+impl FactorizationResult {
+    /// What trial division alone found, before any stage has run.
+    pub fn for_input(input: &NormalizedInput) -> Self {
+        FactorizationResult {
+            n: input.n.clone(),
+            small_factors: input.small_factors.clone(),
+            cofactor: input.cofactor.clone(),
+            cofactor_exponent: input.cofactor_exponent,
+            solution: None,
+            outcome: StageOutcome::Completed,
+            metadata: RunMetadata { algorithm: "trial division".to_string(), ..Default::default() },
+            parameters: None,
+            save_directory: None,
+        }
+    }
+
+    /// Takes the solution, metadata and parameters of the GNFS run on the cofactor.
+    pub fn record_run(&mut self, gnfs: &GNFS) {
+        self.solution = gnfs.factorization.clone();
+        self.metadata = gnfs.run_metadata.clone();
+        if self.metadata.algorithm.is_empty() {
+            self.metadata.algorithm = "GNFS".to_string();
+        }
+        self.parameters = Some(gnfs.solution_parameters());
+        self.save_directory = Some(gnfs.save_locations.save_directory.clone());
+    }
+
+    /// True when every factor of N is known.
+    pub fn is_complete(&self) -> bool {
+        self.cofactor.is_one() || self.solution.is_some() || FactorizationFactory::is_probable_prime(&self.cofactor)
+    }
+
+    /// The factors of N found so far in ascending order, each prime power spelled out; the
+    /// cofactor stands in for the part GNFS hasn't split.
+    pub fn factors(&self) -> Vec<BigInt> {
+        let mut factors: Vec<BigInt> = self
+            .small_factors
+            .iter()
+            .flat_map(|(p, exponent)| std::iter::repeat_n(p.clone(), *exponent as usize))
+            .collect();
+        let power = self.cofactor_exponent as usize;
+        match &self.solution {
+            Some(solution) => factors.extend(solution.factors.iter().flat_map(|p| std::iter::repeat_n(p.clone(), power))),
+            None if !self.cofactor.is_one() => factors.extend(std::iter::repeat_n(self.cofactor.clone(), power)),
+            None => {}
+        }
+        factors.sort();
+        factors
+    }
+}
+
+impl Factorizer {
+    pub fn new(n: BigInt) -> Self {
+        Factorizer {
+            n,
+            config: GnfsConfig::default(),
+            save_locations: None,
+            algorithm: None,
+            polynomial_degree: None,
+            polynomial_base: None,
+            prime_bound: None,
+            stage_started: Vec::new(),
+            stage_finished: Vec::new(),
+            progress: None,
+        }
+    }
+
+    pub fn with_config(mut self, config: GnfsConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Saves under `save_locations` rather than a directory named after N in the working directory.
+    pub fn with_save_locations(mut self, save_locations: DirectoryLocations) -> Self {
+        self.save_locations = Some(save_locations);
+        self
+    }
+
+    /// Factors N completely with `algorithm`, splitting each composite piece with it alone, instead
+    /// of running GNFS. Nothing is saved.
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = Some(algorithm);
+        self
+    }
+
+    /// Uses a base-m polynomial of this degree instead of the preset's. Unless the base is also
+    /// given, it becomes ⌊N^(1/degree)⌋.
+    pub fn with_polynomial_degree(mut self, degree: usize) -> Self {
+        self.polynomial_degree = Some(degree);
+        self
+    }
+
+    /// Uses m = `base` for the base-m polynomial instead of the preset's.
+    pub fn with_polynomial_base(mut self, base: BigInt) -> Self {
+        self.polynomial_base = Some(base);
+        self
+    }
+
+    /// Bounds the rational factor base, and the trial division done first, by `prime_bound`
+    /// instead of the preset's bound.
+    pub fn with_prime_bound(mut self, prime_bound: u64) -> Self {
+        self.prime_bound = Some(prime_bound);
+        self
+    }
+
+    /// Calls `callback` before each stage runs, including stages re-run for more relations.
+    pub fn on_stage_started(mut self, callback: impl FnMut(Stage) + Send + 'static) -> Self {
+        self.stage_started.push(Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` with each stage's outcome once it returns. A stage that fails is reported
+    /// as cancelled.
+    pub fn on_stage_finished(mut self, callback: impl FnMut(Stage, StageOutcome) + Send + 'static) -> Self {
+        self.stage_finished.push(Box::new(callback));
+        self
+    }
+
+    /// Sends the progress of the stages, relations while sieving, iterations in the matrix stage
+    /// and dependencies tried in the square root stage, to `reporter` instead of the log.
+    pub fn with_progress_reporter(mut self, reporter: impl ProgressReporter + 'static) -> Self {
+        self.progress = Some(ProgressHandle::new(reporter));
+        self
+    }
+
+    pub fn config(&self) -> &GnfsConfig {
+        &self.config
+    }
+
+    /// Factors N: trial division first, then GNFS, or the method set with
+    /// [`Factorizer::with_algorithm`], on whatever composite is left.
+    pub fn run(&mut self, cancel_token: &CancellationToken) -> Result<FactorizationResult, GnfsError> {
+        let normalized = self.remove_small_factors()?;
+        let mut result = FactorizationResult::for_input(&normalized);
+        if normalized.is_fully_factored() {
+            return Ok(result);
+        }
+        if let Some(algorithm) = self.algorithm {
+            info!("Factoring {} with {}", normalized.cofactor, algorithm);
+            let started = Instant::now();
+            result.small_factors = algorithms::factor_completely_with(&normalized.n, Some(algorithm), &self.config)?;
+            result.cofactor = BigInt::one();
+            result.cofactor_exponent = 1;
+            result.metadata.algorithm = algorithm.name().to_string();
+            result.metadata.record_stage(algorithm.name(), started.elapsed());
+            return Ok(result);
+        }
+
+        let mut gnfs = self.create_gnfs(cancel_token, &normalized.cofactor)?;
+        result.outcome = self.run_stages(cancel_token, &mut gnfs, Stage::Sieve);
+        result.record_run(&gnfs);
+        Ok(result)
+    }
+
+    /// Trial-divides N by every prime up to the factor base bound GNFS would use, since such
+    /// primes must not divide N, and logs what it finds.
+    pub fn remove_small_factors(&self) -> Result<NormalizedInput, GnfsError> {
+        let limit = self
+            .prime_bound
+            .unwrap_or_else(|| GnfsPreset::for_n(&self.n).map_or(DEFAULT_PRIME_BOUND, |preset| preset.prime_bound));
+        let normalized = input::normalize(&self.n, limit)?;
+        if !normalized.small_factors.is_empty() {
+            let listed: Vec<String> = normalized
+                .small_factors
+                .iter()
+                .map(|(p, e)| if *e > 1 { format!("{}^{}", p, e) } else { p.to_string() })
+                .collect();
+            info!("Trial division up to {} found: {}; cofactor {}", limit, listed.join(" * "), normalized.cofactor);
+        }
+        if normalized.cofactor_exponent > 1 {
+            info!("The cofactor is {}^{}; factoring {} instead.", normalized.cofactor, normalized.cofactor_exponent, normalized.cofactor);
+        }
+        if normalized.is_fully_factored() {
+            info!("{} is fully factored without GNFS.", self.n);
+        }
+        Ok(normalized)
+    }
+
+    /// A new GNFS instance for `n` with parameters from its preset, or the defaults when there is
+    /// none, overridden where the builder says so, and this factorizer's configuration applied.
+    pub fn create_gnfs(&self, cancel_token: &CancellationToken, n: &BigInt) -> Result<GNFS, GnfsError> {
+        let save_locations = match &self.save_locations {
+            Some(save_locations) => save_locations.clone(),
+            None => {
+                let save_directory = DirectoryLocations::get_unique_name_from_n(n);
+                if Path::new(&save_directory).join(MANIFEST_FILENAME).exists() {
+                    warn!("{} holds a checkpoint for this N; starting over. Run `gnfs resume {}` to continue it instead.", save_directory, save_directory);
+                }
+                DirectoryLocations::new(&save_directory)
+            }
+        };
+
+        info!("Creating a new GNFS instance...");
+        let mut polynomial_base = BigInt::from(DEFAULT_POLYNOMIAL_BASE);
+        let mut poly_degree = DEFAULT_POLYNOMIAL_DEGREE;
+        let mut prime_bound = BigInt::from(DEFAULT_PRIME_BOUND);
+        let mut relation_quantity = DEFAULT_RELATION_QUANTITY;
+        let mut relation_value_range = DEFAULT_RELATION_VALUE_RANGE;
+        if let Some(preset) = GnfsPreset::for_n(n) {
+            info!("Using the {}", preset);
+            polynomial_base = preset.polynomial_base(n);
+            poly_degree = preset.degree as i32;
+            prime_bound = BigInt::from(preset.prime_bound);
+            relation_quantity = preset.relation_quantity;
+            relation_value_range = preset.value_range;
+        }
+        if let Some(degree) = self.polynomial_degree {
+            poly_degree = degree as i32;
+            polynomial_base = n.nth_root(degree as u32);
+        }
+        if let Some(base) = &self.polynomial_base {
+            polynomial_base = base.clone();
+        }
+        if let Some(bound) = self.prime_bound {
+            prime_bound = BigInt::from(bound);
+        }
+
+        info!("n: {}", n);
+        info!("Polynomial Base: {}", polynomial_base);
+        info!("Polynomial Degree: {}", poly_degree);
+        info!("Prime Bound: {}", prime_bound);
+        info!("Relation Target: {}", relation_quantity);
+        info!("Relation Value: {}", relation_value_range);
+
+        let mut config = self.config.clone();
+        if self.polynomial_base.is_some() {
+            // An explicit base only means something for the base-m expansion, and a translation
+            // would move it.
+            config.polynomial_selection.get_or_insert(PolynomialSelection::BaseM);
+            config.polynomial_optimization.get_or_insert(false);
+        }
+        let mut gnfs = GNFS::with_save_locations(
+            cancel_token,
+            save_locations,
+            config,
+            n,
+            &polynomial_base,
+            poly_degree,
+            &prime_bound,
+            relation_quantity,
+            relation_value_range,
+            true,
+        )?;
+        gnfs.set_config(self.config.clone());
+        if let Some(profile) = gnfs.config.profile {
+            info!("Using the {} profile", profile);
+        }
+        if let Some(seed) = gnfs.config.seed {
+            info!("Using random seed: {}", seed);
+        }
+        gnfs.current_relations_progress.tuning.log();
+        if gnfs.config.calibrate_prime_bound && gnfs.factorization.is_none() {
+            gnfs.calibrate_prime_bound(cancel_token);
+        }
+        Ok(gnfs)
+    }
+
+    /// Runs the stages from `start` on and returns how the run ended: `Completed` once the last
+    /// stage finishes, `NeedsMoreWork` when it stopped with work left that a resume can pick up,
+    /// and `Cancelled` otherwise. A matrix without usable dependencies sends the run back to
+    /// sieving with a larger target.
+    pub fn run_stages(&mut self, cancel_token: &CancellationToken, gnfs: &mut GNFS, start: Stage) -> StageOutcome {
+        if let Some(progress) = &self.progress {
+            gnfs.progress = progress.clone();
+        }
+        let mut stage = Some(start);
+        let mut outcome = StageOutcome::Completed;
+        while let Some(current) = stage {
+            self.stage_started.iter_mut().for_each(|callback| callback(current));
+            let result = gnfs.run_stage(current, cancel_token);
+            let reported = *result.as_ref().unwrap_or(&StageOutcome::Cancelled);
+            self.stage_finished.iter_mut().for_each(|callback| callback(current, reported));
+            stage = match result {
+                Ok(StageOutcome::Completed) => current.next(),
+                Ok(StageOutcome::NeedsMoreWork) if current == Stage::Matrix => {
+                    info!("No usable dependencies yet; sieving for more relations.");
+                    let extra = std::cmp::max(1, gnfs.current_relations_progress.smooth_relations_target_quantity / 10);
+                    gnfs.current_relations_progress.increase_target_quantity(extra);
+                    Some(Stage::Sieve)
+                }
+                Ok(StageOutcome::NeedsMoreWork) => {
+                    info!("Stage {} could not finish; sieve more relations and re-run the matrix step.", current);
+                    outcome = StageOutcome::NeedsMoreWork;
+                    None
+                }
+                Ok(StageOutcome::Cancelled) if !cancel_token.is_cancellation_requested() => {
+                    gnfs.checkpoint();
+                    info!(
+                        "Time limit reached at the {} stage with {} smooth relations; progress saved.",
+                        current, gnfs.current_relations_progress.smooth_relations_counter
+                    );
+                    info!("Continue with: gnfs resume {} [--max-hours H]", gnfs.save_locations.save_directory);
+                    outcome = StageOutcome::NeedsMoreWork;
+                    None
+                }
+                Ok(StageOutcome::Cancelled) => {
+                    info!("Stage {} cancelled.", current);
+                    info!("Saving progress...");
+                    info!("Relations found: {}", gnfs.current_relations_progress.smooth_relations_counter);
+                    gnfs.checkpoint();
+                    outcome = StageOutcome::Cancelled;
+                    None
+                }
+                Err(e) => {
+                    error!("Stage {} failed: {}", current, e);
+                    outcome = StageOutcome::Cancelled;
+                    None
+                }
+            };
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn factor(factorizer: Factorizer, name: &str) -> FactorizationResult {
+        let token = CancellationToken::new();
+        factorizer.with_save_locations(DirectoryLocations::temporary(name).unwrap()).run(&token).unwrap()
+    }
+
+    fn integers(values: &[u64]) -> Vec<BigInt> {
+        values.iter().map(|&v| BigInt::from(v)).collect()
+    }
+
+    #[test]
+    fn trial_division_alone_finishes_smooth_inputs() {
+        let result = factor(Factorizer::new(BigInt::from(2 * 2 * 7 * 13)), "factorizer_smooth");
+        assert_eq!(result.small_factors, vec![(BigInt::from(2), 2), (BigInt::from(7), 1), (BigInt::from(13), 1)]);
+        assert!(result.cofactor.is_one() && result.solution.is_none() && result.save_directory.is_none());
+        assert_eq!(result.metadata.algorithm, "trial division");
+        assert_eq!(result.factors(), integers(&[2, 2, 7, 13]));
+    }
+
+    #[test]
+    fn primes_are_returned_without_running_gnfs() {
+        let result = factor(Factorizer::new(BigInt::from(1_000_003)), "factorizer_prime");
+        assert!(result.small_factors.is_empty() && result.solution.is_none() && result.save_directory.is_none());
+        assert!(result.is_complete());
+        assert_eq!(result.factors(), integers(&[1_000_003]));
+    }
+
+    #[test]
+    fn perfect_powers_are_reduced_to_their_root() {
+        // 3 · 229²: trial division takes the 3, and the square of a prime needs no GNFS.
+        let result = factor(Factorizer::new(BigInt::from(3 * 229 * 229)), "factorizer_prime_power");
+        assert_eq!((result.cofactor.clone(), result.cofactor_exponent), (BigInt::from(229), 2));
+        assert!(result.is_complete() && result.save_directory.is_none());
+        assert_eq!(result.factors(), integers(&[3, 229, 229]));
+
+        // 45113² = (197 · 229)²: GNFS splits 45113 and each factor counts twice. The bound keeps
+        // trial division from finding 197 first.
+        let square = Factorizer::new(BigInt::from(45113u64 * 45113)).with_prime_bound(100);
+        let result = factor(square, "factorizer_square");
+        assert_eq!(result.outcome, StageOutcome::Completed);
+        assert_eq!(result.cofactor, BigInt::from(45113));
+        assert_eq!(result.factors(), integers(&[197, 197, 229, 229]));
+    }
+
+    #[test]
+    fn gnfs_splits_the_cofactor_left_by_trial_division() {
+        let result = factor(Factorizer::new(BigInt::from(3 * 45113)), "factorizer_cofactor");
+        assert_eq!(result.small_factors, vec![(BigInt::from(3), 1)]);
+        assert_eq!(result.cofactor, BigInt::from(45113));
+        assert_eq!(result.outcome, StageOutcome::Completed);
+        assert_eq!(result.metadata.algorithm, "GNFS");
+        assert!(result.save_directory.is_some());
+        assert_eq!(result.factors(), integers(&[3, 197, 229]));
+    }
+}
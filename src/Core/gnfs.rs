@@ -1,38 +1,73 @@
 // src/core/gnfs.rs
 
-use log::{debug, info};
-use num::{BigInt, ToPrimitive, Zero};
-use std::path::{Path,PathBuf};
-use std::sync::{atomic::AtomicBool, Arc};
+use log::{debug, info, warn};
+use rayon::prelude::*;
+use num::{BigInt, One, ToPrimitive, Zero};
+use num::bigint::ToBigInt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::iter::Iterator;
+use crate::algorithms;
 use crate::core::factor_base::FactorBase;
 use crate::factor::factor_pair_collection::{FactorPairCollection, Factory};
+use crate::polynomial::PolynomialSelection;
+use crate::polynomial::kleinjung::KleinjungSearch;
+use crate::polynomial::optimize::{self, MurphyRegion};
+use crate::polynomial::candidate_pool::{base_m_candidates, PolynomialCandidatePool};
+use crate::polynomial::quality::{self, PolynomialCandidate};
 use crate::polynomial::polynomial::Polynomial;
-use crate::polynomial::polynomial::Term;
-use crate::relation_sieve::poly_relations_sieve_progress::PolyRelationsSieveProgress;
+use crate::relation_sieve::poly_relations_sieve_progress::{PolyRelationsSieveProgress, RelationTarget};
+use crate::relation_sieve::rough_store::RoughRelationStore;
 use crate::relation_sieve::relation::Relation;
-use crate::core::solution::Solution;
+use crate::relation_sieve::bound_calibration;
+use crate::relation_sieve::yield_prediction::YieldPrediction;
+use crate::relation_sieve::relation_store::{self, SortSummary};
+use crate::relation_sieve::storage::{self, RelationFormat, RelationReader};
+use crate::core::solution::{RunMetadata, Solution, SolutionParameters};
 use crate::core::directory_location::DirectoryLocations;
+use crate::core::gnfs_config::GnfsConfig;
+use crate::core::sieve_tuning::SieveTuning;
+use crate::core::stage::{Stage, StageOutcome};
+use crate::matrix::matrix_solve::MatrixSolve;
+use crate::square_root::square_finder::SquareFinder;
+use crate::core::serialization::save;
 use crate::core::cancellation_token::CancellationToken;
+use crate::core::error::GnfsError;
+use crate::core::events::{self, GnfsEvent};
+use crate::core::profiling;
+use crate::core::progress::{ProgressHandle, ProgressReporter};
+use crate::core::memory_usage::{format_bytes, MemoryUsage, MEMORY_BUDGET_WARNING_PERCENT};
 use crate::integer_math::prime_factory::PrimeFactory;
+use crate::integer_math::factorization_factory::FactorizationFactory;
 
 #[derive(Debug, Clone)]
 pub struct GNFS {
     pub n: BigInt,
     pub factorization: Option<Solution>,
     pub polynomial_degree: usize,
+    /// The common root m of the two polynomials mod N.
     pub polynomial_base: BigInt,
     pub polynomial_collection: Vec<Polynomial>,
+    /// The algebraic polynomial ƒ.
     pub current_polynomial: Polynomial,
+    /// The rational polynomial g, sharing the root m with ƒ mod N. X - m unless a selection chose
+    /// otherwise; the rational norm of (a, b) is (-b)^deg g · g(-a/b), a + bm for X - m.
+    pub rational_polynomial: Polynomial,
     pub current_relations_progress: PolyRelationsSieveProgress,
     pub prime_factor_base: FactorBase,
     pub rational_factor_pair_collection: FactorPairCollection,
     pub algebraic_factor_pair_collection: FactorPairCollection,
     pub quadratic_factor_pair_collection: FactorPairCollection,
     pub save_locations: DirectoryLocations,
+    pub config: GnfsConfig,
+    pub run_metadata: RunMetadata,
+    /// Where the stages report how far they have got; not saved with the run.
+    pub progress: ProgressHandle,
 }
 
 impl GNFS {
+    /// Creates an instance saving to the `<N>/` directory under the working directory.
     pub fn new(
         cancel_token: &CancellationToken,
         n: &BigInt,
@@ -42,7 +77,29 @@ impl GNFS {
         relation_quantity: usize,
         relation_value_range: usize,
         created_new_data: bool,
-    ) -> Self {
+    ) -> Result<Self, GnfsError> {
+        let save_locations = DirectoryLocations::new(&DirectoryLocations::get_unique_name_from_n(n));
+        Self::with_save_locations(
+            cancel_token, save_locations, GnfsConfig::default(), n, polynomial_base, poly_degree, prime_bound, relation_quantity, relation_value_range, created_new_data,
+        )
+    }
+
+    /// Like [`GNFS::new`], saving to `save_locations` instead, such as those from
+    /// [`DirectoryLocations::temporary`], and built under `config`, which decides among other
+    /// things how the polynomial is selected.
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_save_locations(
+        cancel_token: &CancellationToken,
+        save_locations: DirectoryLocations,
+        config: GnfsConfig,
+        n: &BigInt,
+        polynomial_base: &BigInt,
+        poly_degree: i32,
+        prime_bound: &BigInt,
+        relation_quantity: usize,
+        relation_value_range: usize,
+        created_new_data: bool,
+    ) -> Result<Self, GnfsError> {
         let mut gnfs = GNFS {
             n: n.clone(),
             factorization: None,
@@ -50,38 +107,43 @@ impl GNFS {
             polynomial_base: polynomial_base.clone(),
             polynomial_collection: Vec::new(),
             current_polynomial: Polynomial::default(),
+            rational_polynomial: Polynomial::default(),
             current_relations_progress: PolyRelationsSieveProgress::default(),
             prime_factor_base: FactorBase::default(),
             rational_factor_pair_collection: FactorPairCollection::default(),
             algebraic_factor_pair_collection: FactorPairCollection::default(),
             quadratic_factor_pair_collection: FactorPairCollection::default(),
-            save_locations: DirectoryLocations::new(&DirectoryLocations::get_unique_name_from_n(&n)),
+            save_locations,
+            config,
+            run_metadata: RunMetadata::default(),
+            progress: ProgressHandle::default(),
         };
 
         if created_new_data || !Path::new(&gnfs.save_locations.save_directory).exists() {
             // New GNFS instance
             if !Path::new(&gnfs.save_locations.save_directory).exists() {
-                std::fs::create_dir_all(&gnfs.save_locations.save_directory).unwrap();
-                info!("Directory created: {:?}", gnfs.save_locations.save_directory);
+                std::fs::create_dir_all(&gnfs.save_locations.save_directory)?;
+                debug!("Directory created: {:?}", gnfs.save_locations.save_directory);
             } else {
                 if Path::new(&gnfs.save_locations.smooth_relations_filepath).exists() {
-                    std::fs::remove_file(&gnfs.save_locations.smooth_relations_filepath).unwrap();
+                    std::fs::remove_file(&gnfs.save_locations.smooth_relations_filepath)?;
                 }
                 if Path::new(&gnfs.save_locations.rough_relations_filepath).exists() {
-                    std::fs::remove_file(&gnfs.save_locations.rough_relations_filepath).unwrap();
+                    std::fs::remove_file(&gnfs.save_locations.rough_relations_filepath)?;
                 }
+                RoughRelationStore::remove_files(Path::new(&gnfs.save_locations.save_directory))?;
                 if Path::new(&gnfs.save_locations.rational_factor_pair_filepath).exists() {
-                    std::fs::remove_file(&gnfs.save_locations.rational_factor_pair_filepath).unwrap();
+                    std::fs::remove_file(&gnfs.save_locations.rational_factor_pair_filepath)?;
                 }
 
                 if Path::new(&gnfs.save_locations.algebraic_factor_pair_filepath).exists() {
-                    std::fs::remove_file(&gnfs.save_locations.algebraic_factor_pair_filepath).unwrap();
+                    std::fs::remove_file(&gnfs.save_locations.algebraic_factor_pair_filepath)?;
                 }
                 if Path::new(&gnfs.save_locations.quadratic_factor_pair_filepath).exists() {
-                    std::fs::remove_file(&gnfs.save_locations.quadratic_factor_pair_filepath).unwrap();
+                    std::fs::remove_file(&gnfs.save_locations.quadratic_factor_pair_filepath)?;
                 }
                 for free_relation_path in gnfs.save_locations.enumerate_free_relation_files() {
-                    std::fs::remove_file(free_relation_path).unwrap();
+                    std::fs::remove_file(free_relation_path)?;
                 }
             }
 
@@ -92,56 +154,279 @@ impl GNFS {
             }
 
             if cancel_token.is_cancellation_requested() {
-                return gnfs;
+                return Ok(gnfs);
             }
 
-            gnfs.construct_new_polynomial(polynomial_base, gnfs.polynomial_degree);
-            info!("Polynomial constructed: {}", gnfs.current_polynomial);
-            info!("Polynomial base: {}", gnfs.polynomial_base);
+            let candidates = gnfs.select_polynomial(cancel_token, polynomial_base, gnfs.polynomial_degree);
+            gnfs.log_event(GnfsEvent::parameter("Polynomial degree", gnfs.polynomial_degree));
+            gnfs.log_event(GnfsEvent::parameter("Polynomial base", &gnfs.polynomial_base));
+            gnfs.log_event(GnfsEvent::parameter("Polynomial", &gnfs.current_polynomial));
+            gnfs.log_event(GnfsEvent::parameter("Rational polynomial", &gnfs.rational_polynomial));
 
             if cancel_token.is_cancellation_requested() {
-                return gnfs;
+                return Ok(gnfs);
             }
 
             gnfs.calculate_prime_factor_base_bounds(prime_bound);
+            gnfs.log_event(GnfsEvent::parameter("Rational factor base bound", &gnfs.prime_factor_base.rational_factor_base_max));
+            gnfs.log_event(GnfsEvent::parameter("Algebraic factor base bound", &gnfs.prime_factor_base.algebraic_factor_base_max));
 
             if cancel_token.is_cancellation_requested() {
-                return gnfs;
+                return Ok(gnfs);
+            }
+
+            if gnfs.config.polynomial_optimization() {
+                gnfs.optimize_polynomial(&candidates, relation_value_range);
             }
 
             gnfs.set_prime_factor_bases();
 
             if cancel_token.is_cancellation_requested() {
-                return gnfs;
+                return Ok(gnfs);
             }
 
             gnfs.new_factor_pair_collections(cancel_token);
             info!("Factor bases populated.");
 
             if cancel_token.is_cancellation_requested() {
-                return gnfs;
+                return Ok(gnfs);
             }
 
             gnfs.current_relations_progress = PolyRelationsSieveProgress::new(
                 Arc::downgrade(&Arc::new(gnfs.clone())),
-                relation_quantity.try_into().unwrap(),
+                relation_quantity.try_into().map_err(|_| GnfsError::Overflow(format!("relation quantity {}", relation_quantity)))?,
                 relation_value_range.into(),
             );
-            info!("Relations container initialized. Target quantity: {}", relation_quantity);
+            gnfs.current_relations_progress.tuning = SieveTuning::detect(&gnfs.config);
+            info!("Relations container initialized. Target quantity: {}", gnfs.current_relations_progress.smooth_relations_target_quantity);
 
-            // TODO: Implement saving the state
-            // Serialization::save_all(&gnfs);
+            // The first checkpoint, so a run interrupted during its first sieve batch can resume.
+            save::all(&gnfs);
         }
 
-        gnfs
+        Ok(gnfs)
     }
 
-    pub fn log_message(&mut self, message: String) {
-        info!("{}", message);
+    /// Runs a single stage to completion or cancellation. Stages can be run one at a time and
+    /// interleaved with [`GNFS::checkpoint`], so callers don't need to mirror main.rs.
+    pub fn run_stage(&mut self, stage: Stage, cancel_token: &CancellationToken) -> Result<StageOutcome, GnfsError> {
+        if cancel_token.should_stop() {
+            return Ok(StageOutcome::Cancelled);
+        }
+
+        match stage {
+            Stage::Sieve => {
+                self.start_stage(stage.name());
+                let started = Instant::now();
+                let mut prediction = self.predict_yield();
+                let progress = &self.current_relations_progress;
+                let mut tracker = self.progress.tracker(stage, "relations", progress.smooth_relations_counter as u64, progress.smooth_relations_target_quantity as u64);
+                while self.current_relations_progress.smooth_relations_counter < self.current_relations_progress.smooth_relations_target_quantity
+                    && !cancel_token.should_stop()
+                {
+                    let _snapshot = self.attach_sieve_snapshot();
+                    self.current_relations_progress.generate_relations(cancel_token);
+                    if self.config.large_prime_recycling() {
+                        self.current_relations_progress.recycle_rough_relations();
+                    }
+                    if let Some(index) = self.current_relations_progress.leading_companion() {
+                        info!("Companion polynomial {} reached the relation target first; switching to it.", index);
+                        self.adopt_companion_polynomial(index, cancel_token);
+                    }
+                    let statistics = self.current_relations_progress.statistics();
+                    match prediction.take().and_then(|prediction| prediction.compare(self, &statistics)) {
+                        Some(mismatch) if mismatch.is_shortfall() => warn!("Yield prediction missed: {}", mismatch),
+                        Some(mismatch) => info!("Yield prediction was too cautious: {}", mismatch),
+                        None => {}
+                    }
+                    tracker.set_total(statistics.target_quantity as u64);
+                    tracker.update(statistics.smooth_relations as u64);
+                    self.log_event(GnfsEvent::SieveProgress(statistics));
+                    self.check_memory_budget();
+                    save::relations::smooth::append(self);
+                    save::relations::rough::all(self);
+                    save::gnfs(self);
+                    save::checkpoint_manifest(self);
+                    debug!("Sieving progress saved at: A = {}, B = {}", self.current_relations_progress.a, self.current_relations_progress.b);
+                }
+                let stopped_early = self.current_relations_progress.smooth_relations_counter < self.current_relations_progress.smooth_relations_target_quantity;
+                if !stopped_early && !self.save_locations.save_directory.is_empty() {
+                    self.sort_relation_store()?;
+                }
+                self.complete_stage(stage.name(), started.elapsed());
+                if stopped_early {
+                    return Ok(StageOutcome::Cancelled);
+                }
+            }
+            Stage::Matrix => {
+                MatrixSolve::gaussian_solve(cancel_token, self);
+                if !cancel_token.is_cancellation_requested() && self.current_relations_progress.relations.free_relations.is_empty() {
+                    return Ok(StageOutcome::NeedsMoreWork);
+                }
+            }
+            Stage::SquareRoot => {
+                if self.current_relations_progress.relations.free_relations.is_empty() {
+                    return Err(GnfsError::stage_failed(stage.name(), "no dependencies; run the matrix stage first"));
+                }
+                if !SquareFinder::solve(cancel_token, self) && !cancel_token.is_cancellation_requested() {
+                    return Ok(StageOutcome::NeedsMoreWork);
+                }
+            }
+        }
+
+        if cancel_token.is_cancellation_requested() {
+            Ok(StageOutcome::Cancelled)
+        } else {
+            Ok(StageOutcome::Completed)
+        }
     }
-    
-    pub fn log_message_slice(&mut self, message: &String) {
-        info!("{}", message);
+
+    /// Writes the full state to the save directory so a later run can resume.
+    pub fn checkpoint(&self) {
+        save::all(self);
+    }
+
+    /// Sends the progress of the stages to `reporter` instead of the log.
+    pub fn set_progress_reporter(&mut self, reporter: impl ProgressReporter + 'static) {
+        self.progress = ProgressHandle::new(reporter);
+    }
+
+    /// Replaces the run configuration and re-derives the settings that depend on it.
+    pub fn set_config(&mut self, config: GnfsConfig) {
+        self.config = config;
+        self.current_relations_progress.tuning = SieveTuning::detect(&self.config);
+        self.open_rough_store();
+        self.add_companion_polynomials();
+    }
+
+    /// Extends the polynomial collection to the configured number of sieve polynomials with
+    /// ƒ + k·g for k = 1, -1, .... Each keeps m as a root mod N, so all of them share the
+    /// rational side g and one rational factorization serves every polynomial.
+    fn add_companion_polynomials(&mut self) {
+        let count = self.config.sieve_polynomials();
+        if self.polynomial_collection.is_empty() {
+            return;
+        }
+        let mut k: i64 = 1;
+        let added = self.polynomial_collection.len() < count;
+        while self.polynomial_collection.len() < count {
+            let shift = Polynomial::multiply(&self.rational_polynomial, &Polynomial::from_term(BigInt::from(k), 0));
+            let mut companion = self.current_polynomial.clone() + shift;
+            companion.remove_zeros();
+            if companion.degree() == self.current_polynomial.degree() && !self.polynomial_collection.contains(&companion) {
+                self.log_event(GnfsEvent::parameter("Companion polynomial", &companion));
+                self.polynomial_collection.push(companion);
+            }
+            k = if k > 0 { -k } else { 1 - k };
+        }
+        let companions = self.polynomial_collection.len().min(count) - 1;
+        self.current_relations_progress.relations.companion_relations.resize(companions, Vec::new());
+        if added && !self.save_locations.save_directory.is_empty() {
+            save::polynomials(self);
+        }
+    }
+
+    /// Makes the companion polynomial at `index` in the collection the current one, swapping the
+    /// smooth relation sets and rebuilding the algebraic and quadratic factor pairs for it. The
+    /// previous polynomial becomes a companion, so its relations are kept. Rough and combined
+    /// relations were only ever collected for the current polynomial and are discarded.
+    pub fn adopt_companion_polynomial(&mut self, index: usize, cancel_token: &CancellationToken) {
+        let progress = &mut self.current_relations_progress;
+        let Some(companion) = progress.relations.companion_relations.get_mut(index - 1) else {
+            return;
+        };
+        let mut adopted = std::mem::take(companion);
+        adopted.iter_mut().for_each(|relation| {
+            relation.polynomial = 0;
+            relation.is_persisted = false;
+        });
+        let mut previous = std::mem::replace(&mut progress.relations.smooth_relations, adopted);
+        previous.iter_mut().for_each(|relation| relation.polynomial = index);
+        progress.relations.companion_relations[index - 1] = previous;
+        progress.smooth_relations_counter = progress.relations.smooth_relations.len();
+        progress.relations.rough_relations.clear();
+        progress.relations.combined_relations.clear();
+        if let Err(e) = progress.rough_store.clear() {
+            warn!("Unable to clear the rough relation store: {}", e);
+        }
+
+        self.polynomial_collection.swap(0, index);
+        self.current_polynomial = self.polynomial_collection[0].clone();
+        self.log_event(GnfsEvent::parameter("Polynomial", &self.current_polynomial));
+        self.algebraic_factor_pair_collection = Factory::build_algebraic_factor_pair_collection(cancel_token, self);
+        self.quadratic_factor_pair_collection = Factory::build_quadratic_factor_pair_collection(cancel_token, self);
+        self.update_relation_target();
+
+        if !self.save_locations.save_directory.is_empty() {
+            // The smooth relations file only holds the previous polynomial's relations.
+            if Path::new(&self.save_locations.smooth_relations_filepath).exists() {
+                if let Err(e) = std::fs::remove_file(&self.save_locations.smooth_relations_filepath) {
+                    warn!("Unable to remove the previous polynomial's smooth relations: {}", e);
+                }
+            }
+            save::all(self);
+        }
+    }
+
+    /// Opens the on-disk rough relation store when the configuration asks for one, or leaves
+    /// rough relations in memory.
+    fn open_rough_store(&mut self) {
+        let progress = &mut self.current_relations_progress;
+        progress.rough_store = RoughRelationStore::default();
+        if self.save_locations.save_directory.is_empty() || !self.config.large_prime_recycling() || !self.config.spill_rough_relations() || self.config.double_large_primes() {
+            return;
+        }
+        match RoughRelationStore::open(Path::new(&self.save_locations.save_directory), self.config.rough_relation_cap()) {
+            Ok(store) => progress.rough_store = store,
+            Err(e) => warn!("Unable to open the rough relation store ({}); keeping rough relations in memory.", e),
+        }
+    }
+
+    /// Logs the event and appends it to `events.jsonl` in the save directory.
+    pub fn log_event(&self, event: GnfsEvent) {
+        info!("{}", event);
+        if self.save_locations.save_directory.is_empty() {
+            return;
+        }
+        if let Err(e) = events::append(Path::new(&self.save_locations.events_filepath()), &event) {
+            warn!("Unable to record event: {}", e);
+        }
+    }
+
+    pub fn start_stage(&self, stage: &str) {
+        self.log_event(GnfsEvent::StageStarted { stage: stage.to_string() });
+        MemoryUsage::reset_peak();
+        profiling::stage_started(stage);
+    }
+
+    /// Adds the stage's elapsed time and the relation counts so far to the run metadata and
+    /// records its completion.
+    pub fn complete_stage(&mut self, stage: &str, elapsed: Duration) {
+        self.run_metadata.record_stage(stage, elapsed);
+        self.run_metadata.smooth_relation_count = self.current_relations_progress.smooth_relations_counter;
+        self.run_metadata.rough_relation_count = self.current_relations_progress.relations.rough_relations.len();
+        profiling::stage_completed(stage, &self.save_locations.save_directory);
+        if let Some(peak) = MemoryUsage::peak_resident() {
+            self.run_metadata.record_stage_memory(stage, peak);
+            debug!("Peak memory during {}: {}", stage, format_bytes(peak));
+        }
+        self.check_memory_budget();
+        self.log_event(GnfsEvent::StageCompleted { stage: stage.to_string(), seconds: elapsed.as_secs_f64() });
+    }
+
+    /// Warns when resident memory is within [`MEMORY_BUDGET_WARNING_PERCENT`] of the configured budget.
+    pub fn check_memory_budget(&self) {
+        let (Some(budget), Some(resident)) = (self.config.memory_budget, MemoryUsage::resident()) else {
+            return;
+        };
+        if resident >= budget / 100 * MEMORY_BUDGET_WARNING_PERCENT {
+            warn!(
+                "Resident memory {} is {:.0}% of the {} budget",
+                format_bytes(resident),
+                resident as f64 * 100.0 / budget as f64,
+                format_bytes(budget)
+            );
+        }
     }
 
     fn calculate_degree(n: &BigInt) -> usize {
@@ -187,19 +472,23 @@ impl GNFS {
         self.prime_factor_base.quadratic_base_count = Self::calculate_quadratic_base_size(self.polynomial_degree).to_i32().unwrap();
 
         self.prime_factor_base.quadratic_factor_base_min = &self.prime_factor_base.algebraic_factor_base_max + 20;
-        // TODO: Implement PrimeFactory::get_approximate_value_from_index
-        // self.prime_factor_base.quadratic_factor_base_max = PrimeFactory::get_approximate_value_from_index(
-        //     (self.prime_factor_base.quadratic_factor_base_min + self.prime_factor_base.quadratic_base_count) as u64,
-        // );
+        self.prime_factor_base.quadratic_factor_base_max = Self::approximate_quadratic_factor_base_max(
+            &self.prime_factor_base.quadratic_factor_base_min,
+            self.prime_factor_base.quadratic_base_count,
+        );
+
+        debug!("Rational  Factor Base Bounds: Min: - Max: {}", self.prime_factor_base.rational_factor_base_max);
+        debug!("Algebraic Factor Base Bounds: Min: - Max: {}", self.prime_factor_base.algebraic_factor_base_max);
+        debug!("Quadratic Factor Base Bounds: Min: {} Max: {}", self.prime_factor_base.quadratic_factor_base_min, self.prime_factor_base.quadratic_factor_base_max);
 
-        // TODO: Implement logging
-        // info!(format!("Rational  Factor Base Bounds: Min: - Max: {}", self.prime_factor_base.rational_factor_base_max));
-        // info!(format!("Algebraic Factor Base Bounds: Min: - Max: {}", self.prime_factor_base.algebraic_factor_base_max));
-        // info!(format!("Quadratic Factor Base Bounds: Min: {} Max: {}", self.prime_factor_base.quadratic_factor_base_min, self.prime_factor_base.quadratic_factor_base_max));
+        save::gnfs(self);
+    }
 
-        // TODO: Implement saving the state
-        // Serialization::save_all(self);
-        // info!("Saved prime factor base bounds.".to_string());
+    /// Upper bound on the value of the `count`-th prime above `min`: over-estimate the index of `min`
+    /// with the prime counting function, then map index + count back to a value.
+    fn approximate_quadratic_factor_base_max(min: &BigInt, count: i32) -> BigInt {
+        let min_index = PrimeFactory::get_approximate_index_from_value(min);
+        PrimeFactory::get_approximate_value_from_index(min_index + count as u64).to_bigint().unwrap()
     }
 
     pub fn is_factored(&self) -> bool {
@@ -211,22 +500,35 @@ impl GNFS {
     }
 
     pub fn set_prime_factor_bases(&mut self) {
-        info!("Constructing new prime bases (- of 3)...");
+        debug!("Constructing new prime bases (- of 3)...");
 
-        let mut prime_factory = PrimeFactory::new();
+        let prime_factory = PrimeFactory::new();
         debug!("Prime factory initialized.");
-        self.prime_factor_base.rational_factor_base = PrimeFactory::get_primes_to(&mut prime_factory, &self.prime_factor_base.rational_factor_base_max)
+        self.prime_factor_base.rational_factor_base = PrimeFactory::get_primes_to(&prime_factory, &self.prime_factor_base.rational_factor_base_max)
             .collect::<Vec<BigInt>>(); // Collect the iterator into a Vec<BigInt>
-        info!("Completed rational prime base (1 of 3).");
+        debug!("Completed rational prime base (1 of 3).");
 
-        self.prime_factor_base.algebraic_factor_base = PrimeFactory::get_primes_to(&mut prime_factory, &self.prime_factor_base.algebraic_factor_base_max)
+        self.prime_factor_base.algebraic_factor_base = PrimeFactory::get_primes_to(&prime_factory, &self.prime_factor_base.algebraic_factor_base_max)
             .collect::<Vec<BigInt>>(); // Collect the iterator into a Vec<BigInt>
-        info!("Completed algebraic prime base (2 of 3).");
+        debug!("Completed algebraic prime base (2 of 3).");
 
-        self.prime_factor_base.quadratic_factor_base = PrimeFactory::get_primes_from(&mut prime_factory, &self.prime_factor_base.quadratic_factor_base_min)
+        self.prime_factor_base.quadratic_factor_base = PrimeFactory::get_primes_from(&prime_factory, &self.prime_factor_base.quadratic_factor_base_min)
             .take(self.prime_factor_base.quadratic_base_count as usize) // Convert i32 to usize
             .collect::<Vec<BigInt>>(); // Collect the iterator into a Vec<BigInt>
-        info!("Completed quadratic prime base (3 of 3).");
+        debug!("Completed quadratic prime base (3 of 3).");
+        self.log_quadratic_factor_base_range();
+    }
+
+    fn log_quadratic_factor_base_range(&self) {
+        let base = &self.prime_factor_base;
+        if let (Some(first), Some(last)) = (base.quadratic_factor_base.first(), base.quadratic_factor_base.last()) {
+            debug!("Quadratic prime base realized: {} primes, Min: {} Max: {}", base.quadratic_factor_base.len(), first, last);
+            if last > &base.quadratic_factor_base_max {
+                warn!("Quadratic prime base exceeds its estimated bound {}.", base.quadratic_factor_base_max);
+            }
+        } else {
+            warn!("Quadratic prime base is empty (requested {} primes from {}).", base.quadratic_base_count, base.quadratic_factor_base_min);
+        }
     }
     
     fn calculate_quadratic_base_size(poly_degree: usize) -> usize {
@@ -239,30 +541,123 @@ impl GNFS {
         }
     }
 
-    pub fn construct_new_polynomial(&mut self, _polynomial_base: &BigInt, _poly_degree: usize) {
-        self.current_polynomial = Polynomial::new(vec![Term::new(self.n.clone(), 0)]);
+    /// Chooses the polynomial with the configured [`PolynomialSelection`], falling back on the
+    /// base-m expansion for `polynomial_base` when a search finds nothing. Returns the ranked
+    /// candidates the search kept, the chosen one first, for [`GNFS::optimize_polynomial`].
+    pub fn select_polynomial(&mut self, cancel_token: &CancellationToken, polynomial_base: &BigInt, poly_degree: usize) -> Vec<PolynomialCandidate> {
+        let pool = match self.config.polynomial_selection() {
+            PolynomialSelection::Kleinjung => KleinjungSearch {
+                max_leading_coefficient: self.config.polynomial_max_leading_coefficient(),
+                max_candidates: self.config.polynomial_search_candidates(),
+                pool_capacity: self.config.polynomial_candidates(),
+                ..KleinjungSearch::new(poly_degree, self.config.polynomial_search_budget())
+            }
+            .run(&self.n, cancel_token),
+            PolynomialSelection::BaseM if self.config.polynomial_base_range() > 0 => {
+                base_m_candidates(&self.n, poly_degree, polynomial_base, self.config.polynomial_base_range(), self.config.polynomial_candidates())
+            }
+            PolynomialSelection::BaseM => PolynomialCandidatePool::new(1),
+        };
+        let Some(best) = pool.best() else {
+            self.construct_new_polynomial(polynomial_base, poly_degree);
+            return Vec::new();
+        };
+        self.set_polynomial_pair(best.polynomial.clone(), best.m.clone());
+        self.polynomial_collection.push(self.current_polynomial.clone());
+        save::polynomials(self);
+        save::gnfs(self);
+        pool.into_candidates()
+    }
+
+    /// Rotates and translates the selected polynomial, and each other candidate the search kept,
+    /// toward the largest Murphy's E over the sieve region under the factor base bounds, and
+    /// replaces the polynomial and its base with the best result when that helps. Runs before the
+    /// factor bases are built, since their algebraic side depends on ƒ.
+    pub fn optimize_polynomial(&mut self, candidates: &[PolynomialCandidate], relation_value_range: usize) {
+        let region = MurphyRegion::new(
+            &self.prime_factor_base.algebraic_factor_base_max,
+            &self.prime_factor_base.rational_factor_base_max,
+            relation_value_range,
+        );
+        let selected = quality::coefficients(&self.current_polynomial);
+        let before = optimize::murphy_e(&selected, &self.polynomial_base, &region);
+        let mut starts: Vec<(Vec<BigInt>, BigInt)> = vec![(selected.clone(), self.polynomial_base.clone())];
+        starts.extend(candidates.iter().skip(1).map(|candidate| (quality::coefficients(&candidate.polynomial), candidate.m.clone())));
+        let Some((coefficients, m, after)) = starts
+            .into_par_iter()
+            .map(|(coefficients, m)| optimize::optimize(coefficients, m, &region))
+            .max_by(|x, y| x.2.total_cmp(&y.2))
+        else {
+            return;
+        };
+        if after <= before {
+            debug!("Polynomial optimization kept the selected polynomial (E = {:.3e}).", before);
+            return;
+        }
+        info!("Polynomial optimization raised Murphy's E from {:.3e} to {:.3e}.", before, after);
+        self.set_polynomial_pair(quality::from_coefficients(&coefficients), m);
+        match self.polynomial_collection.first_mut() {
+            Some(first) => *first = self.current_polynomial.clone(),
+            None => self.polynomial_collection.push(self.current_polynomial.clone()),
+        }
+        self.log_event(GnfsEvent::parameter("Optimized polynomial", &self.current_polynomial));
+        self.log_event(GnfsEvent::parameter("Optimized polynomial base", &self.polynomial_base));
+        save::polynomials(self);
+        save::gnfs(self);
+    }
+
+    /// Makes `algebraic` and X - m the polynomial pair, with m as its common root.
+    pub fn set_polynomial_pair(&mut self, algebraic: Polynomial, m: BigInt) {
+        self.current_polynomial = algebraic;
+        self.rational_polynomial = Polynomial::rational_side(&m);
+        self.polynomial_base = m;
+    }
+
+    /// The algebraic and rational polynomials.
+    pub fn polynomial_pair(&self) -> (&Polynomial, &Polynomial) {
+        (&self.current_polynomial, &self.rational_polynomial)
+    }
+
+    /// Builds the base-m polynomial for N. A base whose degree-th power exceeds N would leave ƒ
+    /// constant, so it is replaced by ⌊N^(1/degree)⌋.
+    pub fn construct_new_polynomial(&mut self, polynomial_base: &BigInt, poly_degree: usize) {
+        let degree = poly_degree.max(1);
+        let m = if polynomial_base <= &BigInt::one() || polynomial_base.pow(degree as u32) > self.n {
+            self.n.nth_root(degree as u32)
+        } else {
+            polynomial_base.clone()
+        };
+        self.set_polynomial_pair(Polynomial::from_base_m(&self.n, &m, degree), m);
 
         self.polynomial_collection.push(self.current_polynomial.clone());
-        // TODO: Implement saving the state
-        // Serialization::save_all(self);
+        save::polynomials(self);
+        save::gnfs(self);
     }
 
-    fn new_factor_pair_collections(&mut self, cancel_token: &CancellationToken) {
+    /// Builds whichever factor pair collections are still empty, saving each as it completes.
+    pub(crate) fn new_factor_pair_collections(&mut self, cancel_token: &CancellationToken) {
         if self.rational_factor_pair_collection.len() == 0 {
-            // TODO: Implement saving the state
-            // Serialization::save_factor_pair_algebraic(self);
-            info!("Completed algebraic factor base (2 of 3).");
+            self.rational_factor_pair_collection = Factory::build_rational_factor_pair_collection(self);
+            save::factor_pair::rational(self);
+            debug!("Completed rational factor base (1 of 3).");
+
+            if cancel_token.is_cancellation_requested() {
+                return;
+            }
+            if self.algebraic_factor_pair_collection.len() == 0 {
+                self.algebraic_factor_pair_collection = Factory::build_algebraic_factor_pair_collection(cancel_token, self);
+                save::factor_pair::algebraic(self);
+            }
+            debug!("Completed algebraic factor base (2 of 3).");
     
             if cancel_token.is_cancellation_requested() {
                 return;
             }
             if self.quadratic_factor_pair_collection.len() == 0 {
-                let cancel_token_arc = Arc::new(AtomicBool::new(cancel_token.is_cancellation_requested()));
-                self.quadratic_factor_pair_collection = Factory::build_quadratic_factor_pair_collection(&cancel_token_arc, self);
+                self.quadratic_factor_pair_collection = Factory::build_quadratic_factor_pair_collection(cancel_token, self);
+                save::factor_pair::quadratic(self);
             }
-            // TODO: Implement saving the state
-            // Serialization::save_factor_pair_quadratic(self);
-            info!("Completed quadratic factor base (3 of 3).");
+            debug!("Completed quadratic factor base (3 of 3).");
     
             if cancel_token.is_cancellation_requested() {
                 return;
@@ -270,57 +665,277 @@ impl GNFS {
         }
     }
 
-    pub fn group_rough_numbers(rough_numbers: &[Relation]) -> Vec<Vec<Relation>> {
-        let mut results = Vec::new();
-        let mut last_index: Option<usize> = None;
-    
-        for (index, pair) in rough_numbers.iter().enumerate() {
-            if let Some(last_idx) = last_index {
-                let last = &rough_numbers[last_idx];
-                if pair.algebraic_quotient == last.algebraic_quotient && pair.rational_quotient == last.rational_quotient {
-                    results.push(vec![pair.clone(), last.clone()]);
-                    last_index = None;  // Clear last_index as pair is grouped
-                } else {
-                    last_index = Some(index);  // Update last_index to current index
+    /// Raises the rational and algebraic prime bounds to `new_bound` without starting over.
+    /// Only primes above the old bounds are enumerated and their factor pairs appended, so every
+    /// relation found so far stays valid and sieving picks up from the current (A, B) position.
+    pub fn extend_factor_bases(&mut self, cancel_token: &CancellationToken, new_bound: &BigInt) {
+        let old_rational_max = self.prime_factor_base.rational_factor_base_max.clone();
+        if new_bound <= &old_rational_max {
+            warn!("New prime bound {} does not exceed the current bound {}; factor bases left unchanged.", new_bound, old_rational_max);
+            return;
+        }
+        let new_algebraic_max = new_bound * 3;
+
+        // New primes start above the largest one already in each base, whether or not the old
+        // bound was itself included.
+        let largest = |base: &[BigInt]| base.last().cloned().unwrap_or_default();
+        let rational_largest = largest(&self.prime_factor_base.rational_factor_base);
+        let algebraic_largest = largest(&self.prime_factor_base.algebraic_factor_base);
+        let prime_factory = PrimeFactory::new();
+        let new_rational_primes: Vec<BigInt> = PrimeFactory::get_primes_to(&prime_factory, new_bound)
+            .filter(|p| p > &rational_largest)
+            .collect();
+        let new_algebraic_primes: Vec<BigInt> = PrimeFactory::get_primes_to(&prime_factory, &new_algebraic_max)
+            .filter(|p| p > &algebraic_largest)
+            .collect();
+
+        self.prime_factor_base.rational_factor_base_max = new_bound.clone();
+        self.prime_factor_base.algebraic_factor_base_max = new_algebraic_max.clone();
+        self.prime_factor_base.rational_factor_base.extend(new_rational_primes.iter().cloned());
+        self.prime_factor_base.algebraic_factor_base.extend(new_algebraic_primes.iter().cloned());
+        info!("Extended rational prime base by {} primes and algebraic prime base by {} primes.", new_rational_primes.len(), new_algebraic_primes.len());
+
+        if cancel_token.is_cancellation_requested() {
+            return;
+        }
+
+        self.rational_factor_pair_collection.0.extend(Factory::rational_factor_pairs(&new_rational_primes, &self.rational_polynomial));
+        self.algebraic_factor_pair_collection.0.extend(Factory::find_polynomial_roots_in_range(
+            cancel_token,
+            &self.current_polynomial,
+            &new_algebraic_primes,
+            &BigInt::zero(),
+            &new_algebraic_max,
+            2000,
+        ));
+        self.algebraic_factor_pair_collection.0.extend(Factory::projective_roots(&self.current_polynomial, &new_algebraic_primes));
+
+        // Quadratic characters must use primes above the algebraic base, so move that window up.
+        self.prime_factor_base.quadratic_factor_base_min = &new_algebraic_max + 20;
+        self.prime_factor_base.quadratic_factor_base_max = Self::approximate_quadratic_factor_base_max(
+            &self.prime_factor_base.quadratic_factor_base_min,
+            self.prime_factor_base.quadratic_base_count,
+        );
+        self.prime_factor_base.quadratic_factor_base = PrimeFactory::get_primes_from(&prime_factory, &self.prime_factor_base.quadratic_factor_base_min)
+            .take(self.prime_factor_base.quadratic_base_count as usize)
+            .collect::<Vec<BigInt>>();
+        self.log_quadratic_factor_base_range();
+        self.quadratic_factor_pair_collection = Factory::build_quadratic_factor_pair_collection(cancel_token, self);
+
+        self.current_relations_progress.extend_max_b(new_algebraic_max);
+        self.update_relation_target();
+        save::factor_pair::rational(self);
+        save::factor_pair::algebraic(self);
+        save::factor_pair::quadratic(self);
+        save::gnfs(self);
+        info!(
+            "Factor bases extended to {}. Relations kept: {}. Target quantity: {}",
+            new_bound,
+            self.current_relations_progress.smooth_relations_counter,
+            self.current_relations_progress.smooth_relations_target_quantity
+        );
+    }
+
+    /// Merges the smooth relation file into a sorted store without duplicate (a, b) pairs, in runs
+    /// sized by the relation flush threshold so it works for relation sets larger than memory. The
+    /// store is written in the configured [`RelationFormat`].
+    pub fn sort_relation_store(&self) -> Result<SortSummary, GnfsError> {
+        let inputs = [PathBuf::from(&self.save_locations.smooth_relations_filepath)];
+        let format = self.config.relation_format();
+        let output = PathBuf::from(self.save_locations.sorted_relations_filepath(format));
+        let run_length = self.current_relations_progress.tuning.relation_flush_threshold;
+        match format {
+            RelationFormat::Binary => storage::sort_and_dedup(self, &inputs, &output, run_length),
+            RelationFormat::Jsonl => relation_store::sort_and_dedup(&inputs, &output, run_length),
+        }
+    }
+
+    /// Streams the smooth relations saved in the save directory, decoding one record at a time.
+    /// Reads the sorted store when it is at least as new as the smooth relations file, so the
+    /// relations come in (a, b) order without duplicates; otherwise reads the smooth relations
+    /// file in the order it was written. Relations not yet saved are not included.
+    pub fn relations_iter(&self) -> Result<Box<dyn Iterator<Item = Result<Relation, GnfsError>>>, GnfsError> {
+        let smooth = Path::new(&self.save_locations.smooth_relations_filepath);
+        let format = self.config.relation_format();
+        let sorted = PathBuf::from(self.save_locations.sorted_relations_filepath(format));
+        let modified = |path: &Path| path.metadata().and_then(|metadata| metadata.modified()).ok();
+        let sorted_is_current = match (modified(&sorted), modified(smooth)) {
+            (Some(sorted), Some(smooth)) => sorted >= smooth,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if sorted_is_current {
+            return match format {
+                RelationFormat::Binary => Ok(Box::new(RelationReader::open(&sorted, self)?)),
+                RelationFormat::Jsonl => Ok(Box::new(relation_store::read_sorted(&sorted)?.map(|record| record.map(Relation::from)))),
+            };
+        }
+        if !smooth.exists() {
+            return Ok(Box::new(std::iter::empty()));
+        }
+        Ok(Box::new(relation_store::RelationFileReader::open(smooth)?.map(|record| record.map(Relation::from))))
+    }
+
+    /// Forecasts relations and sieving time for a sieve that hasn't started yet, logging the
+    /// forecast and any parameter problems it shows. Resumed runs have real numbers instead.
+    fn predict_yield(&mut self) -> Option<YieldPrediction> {
+        if self.current_relations_progress.pairs_sieved > 0 || self.prime_factor_base.rational_factor_base.is_empty() {
+            return None;
+        }
+        let prediction = YieldPrediction::predict(self);
+        self.log_event(GnfsEvent::parameter("Yield prediction", &prediction));
+        for warning in prediction.warnings() {
+            warn!("Yield prediction: {}", warning);
+        }
+        Some(prediction)
+    }
+
+    /// Points the sieve progress at a snapshot of this instance taken without its relations. The
+    /// progress only holds a `Weak`, so the caller keeps the returned `Arc` alive while sieving.
+    pub(crate) fn attach_sieve_snapshot(&mut self) -> Arc<GNFS> {
+        let progress = std::mem::take(&mut self.current_relations_progress);
+        let snapshot = Arc::new(self.clone());
+        self.current_relations_progress = progress;
+        self.current_relations_progress.gnfs = Arc::downgrade(&snapshot);
+        snapshot
+    }
+
+    /// Recomputes the relation target from the current factor bases and raises the sieving target to match.
+    pub fn update_relation_target(&mut self) {
+        let target = RelationTarget::new(self);
+        let progress = &mut self.current_relations_progress;
+        progress.relation_target = target;
+        progress.smooth_relations_target_quantity = std::cmp::max(progress.smooth_relations_target_quantity, target.total());
+        info!("Relation target: {}", target);
+    }
+
+    /// Trial-sieves a small region with bounds around the current rational bound and, if another
+    /// candidate projects a shorter sieving time, rebuilds the factor bases with it.
+    pub fn calibrate_prime_bound(&mut self, cancel_token: &CancellationToken) {
+        let current_bound = self.prime_factor_base.rational_factor_base_max.clone();
+        let candidates = bound_calibration::candidate_bounds(&current_bound);
+        let trials = bound_calibration::calibrate(
+            cancel_token,
+            &self.current_polynomial,
+            &self.rational_polynomial,
+            &candidates,
+            self.prime_factor_base.quadratic_base_count.max(0) as usize,
+        );
+
+        for trial in &trials {
+            debug!(
+                "Bound {}: {} of {} sampled pairs smooth in {:.3}s, {} relations needed, projected {:.1}s",
+                trial.bound, trial.smooth, trial.sampled, trial.seconds, trial.required_relations, trial.projected_seconds
+            );
+        }
+
+        match trials.first() {
+            Some(best) if best.projected_seconds.is_finite() => {
+                self.log_event(GnfsEvent::parameter("Calibrated prime bound", &best.bound));
+                if best.bound != current_bound {
+                    self.rebuild_factor_bases(cancel_token, &best.bound.clone());
                 }
-            } else {
-                last_index = Some(index);  // Initialize last_index with the first index
             }
+            _ => warn!("Prime bound calibration found no smooth relations; keeping bound {}.", current_bound),
         }
-    
-        results
-    }    
+    }
 
-    pub fn multiply_like_rough_numbers(gnfs: &GNFS, like_rough_numbers_groups: &[Vec<Relation>]) -> Vec<Relation> {
-        let mut result = Vec::new();
-    
-        for like_pair in like_rough_numbers_groups {
-            let as_vec: Vec<BigInt> = like_pair.iter().map(|lp| lp.a.clone()).collect();
-            let bs_vec: Vec<BigInt> = like_pair.iter().map(|lp| lp.b.clone()).collect();
-    
-            let a = (as_vec[0].clone() + bs_vec[0].clone()) * (as_vec[0].clone() - bs_vec[0].clone());
-            let b = (as_vec[1].clone() + bs_vec[1].clone()) * (as_vec[1].clone() - bs_vec[1].clone());
-    
-            if a > BigInt::zero() && b > BigInt::zero() {
-                result.push(Relation::new(gnfs, &a, &b));
-            }
+    /// Recomputes every factor base and factor pair collection for a new rational bound. Any
+    /// relations already found are discarded, since they were checked against the old bases.
+    pub fn rebuild_factor_bases(&mut self, cancel_token: &CancellationToken, bound: &BigInt) {
+        self.calculate_prime_factor_base_bounds(bound);
+        self.set_prime_factor_bases();
+        self.rational_factor_pair_collection = FactorPairCollection::default();
+        self.algebraic_factor_pair_collection = FactorPairCollection::default();
+        self.quadratic_factor_pair_collection = FactorPairCollection::default();
+        self.new_factor_pair_collections(cancel_token);
+
+        let target = self.current_relations_progress.smooth_relations_target_quantity;
+        let value_range = self.current_relations_progress.value_range.clone();
+        let tuning = self.current_relations_progress.tuning;
+        if let Err(e) = self.current_relations_progress.rough_store.clear() {
+            warn!("Unable to clear the rough relation store: {}", e);
         }
-    
-        result
+        let snapshot = Arc::new(self.clone());
+        self.current_relations_progress = PolyRelationsSieveProgress::new(Arc::downgrade(&snapshot), target as isize, value_range);
+        self.current_relations_progress.tuning = tuning;
+        self.open_rough_store();
+        info!(
+            "Factor bases rebuilt for bound {}. Target quantity: {}",
+            bound, self.current_relations_progress.smooth_relations_target_quantity
+        );
     }
 
     pub fn set_factorization_solution(&mut self, p: &BigInt, q: &BigInt) -> bool {
+        self.set_factorization_solution_with_report(p, q, None)
+    }
+
+    /// Records p and q as the factorization when their product is N and writes `Solution.txt`
+    /// and `solution.json`. `square_root_report` is the square finder's summary of the dependency
+    /// that produced them, included in `Solution.txt`.
+    pub fn set_factorization_solution_with_report(&mut self, p: &BigInt, q: &BigInt, square_root_report: Option<String>) -> bool {
         let n = p * q;
         if n == self.n {
-            self.factorization = Some(Solution::new(p, q));
-            let _path = PathBuf::from(&self.save_locations.save_directory).join("Solution.txt");
-            // TODO: Implement writing the solution to a file
+            let relations = &self.current_relations_progress.relations;
+            if self.run_metadata.algorithm.is_empty() {
+                self.run_metadata.algorithm = "GNFS".to_string();
+            }
+            self.run_metadata.smooth_relation_count = relations.smooth_relations.len();
+            self.run_metadata.rough_relation_count = relations.rough_relations.len();
+
+            let mut solution = Solution::new(p, q);
+            solution.p_is_prime = FactorizationFactory::is_probable_prime(p);
+            solution.q_is_prime = FactorizationFactory::is_probable_prime(q);
+            if !(solution.p_is_prime && solution.q_is_prime) {
+                solution.factors = self.split_composite_factors(vec![p.clone(), q.clone()]);
+                info!("Composite factor found; full factorization: {}", solution.factors.iter().map(|f| f.to_string()).collect::<Vec<_>>().join(" * "));
+            }
+            solution.metadata = self.run_metadata.clone();
+            solution.parameters = Some(self.solution_parameters());
+            solution.square_root_report = square_root_report;
+            self.factorization = Some(solution);
+
+            save::solution(self);
             true
         } else {
             false
         }
     }
 
+    pub(crate) fn solution_parameters(&self) -> SolutionParameters {
+        SolutionParameters {
+            n: self.n.to_string(),
+            polynomial: self.current_polynomial.to_string(),
+            polynomial_base: self.polynomial_base.to_string(),
+            polynomial_degree: self.polynomial_degree,
+            rational_factor_base_max: self.prime_factor_base.rational_factor_base_max.to_string(),
+            algebraic_factor_base_max: self.prime_factor_base.algebraic_factor_base_max.to_string(),
+            quadratic_base_count: self.prime_factor_base.quadratic_base_count,
+            relation_target: self.current_relations_progress.smooth_relations_target_quantity,
+            seed: self.config.seed,
+        }
+    }
+
+    /// Splits composite entries into primes with [`algorithms::factor_completely`].
+    fn split_composite_factors(&self, pending: Vec<BigInt>) -> Vec<BigInt> {
+        let mut factors = Vec::new();
+        for value in pending.into_iter().filter(|value| !value.is_one()) {
+            match algorithms::factor_completely(&value, &self.config) {
+                Ok(primes) => {
+                    for (prime, exponent) in primes {
+                        factors.extend(std::iter::repeat_n(prime, exponent as usize));
+                    }
+                }
+                Err(e) => {
+                    warn!("Unable to split composite factor {}: {}", value, e);
+                    factors.push(value);
+                }
+            }
+        }
+        factors.sort();
+        factors
+    }
+
     // fn calculate_quadratic_base_size(poly_degree: usize) -> usize {
     //     match poly_degree {
     //         d if d <= 3 => 10,
@@ -343,15 +958,16 @@ impl ToString for GNFS {
 
         result.push_str(&format!("N = {}\n\n", self.n));
         result.push_str(&format!("Polynomial(degree: {}, base: {}):\n", self.polynomial_degree, self.polynomial_base));
-        result.push_str(&format!("ƒ(m) = {}\n\n", self.current_polynomial));
+        result.push_str(&format!("ƒ(m) = {}\n", self.current_polynomial));
+        result.push_str(&format!("g(m) = {}\n\n", self.rational_polynomial));
         result.push_str("Prime Factor Base Bounds:\n");
         result.push_str(&format!("RationalFactorBase : {}\n", self.prime_factor_base.rational_factor_base_max));
         result.push_str(&format!("AlgebraicFactorBase: {}\n", self.prime_factor_base.algebraic_factor_base_max));
         result.push_str(&format!("QuadraticPrimeBase Range: {} - {}\n", self.prime_factor_base.quadratic_factor_base_min, self.prime_factor_base.quadratic_factor_base_max));
         result.push_str(&format!("QuadraticPrimeBase Count: {}\n\n", self.prime_factor_base.quadratic_base_count));
-        result.push_str(&format!("RFB - Rational Factor Base - Count: {} - Array of (p, m % p) with prime p\n", self.rational_factor_pair_collection.len()));
+        result.push_str(&format!("RFB - Rational Factor Base - Count: {} - Array of (p, r) such that g(r) ≡ 0 (mod p) and p is prime\n", self.rational_factor_pair_collection.len()));
         result.push_str(&format!("{}\n\n", self.rational_factor_pair_collection.to_string()));
-        result.push_str(&format!("AFB - Algebraic Factor Base - Count: {} - Array of (p, r) such that ƒ(r) ≡ 0 (mod p) and p is prime\n", self.algebraic_factor_pair_collection.len()));
+        result.push_str(&format!("AFB - Algebraic Factor Base - Count: {} - Array of (p, r) such that ƒ(r) ≡ 0 (mod p) and p is prime; (p, p) is a projective root\n", self.algebraic_factor_pair_collection.len()));
         result.push_str(&format!("{}\n\n", self.algebraic_factor_pair_collection.to_string()));
         result.push_str(&format!("QFB - Quadratic Factor Base - Count: {} - Array of (p, r) such that ƒ(r) ≡ 0 (mod p) and p is prime\n", self.quadratic_factor_pair_collection.len()));
         result.push_str(&format!("{}\n\n", self.quadratic_factor_pair_collection.to_string()));
@@ -369,12 +985,16 @@ impl Default for GNFS {
             polynomial_base: BigInt::from(0),
             polynomial_collection: Vec::new(),
             current_polynomial: Polynomial::default(),
+            rational_polynomial: Polynomial::default(),
             current_relations_progress: PolyRelationsSieveProgress::default(),
             prime_factor_base: FactorBase::default(),
             rational_factor_pair_collection: FactorPairCollection::default(),
             algebraic_factor_pair_collection: FactorPairCollection::default(),
             quadratic_factor_pair_collection: FactorPairCollection::default(),
             save_locations: DirectoryLocations::default(),
+            config: GnfsConfig::default(),
+            run_metadata: RunMetadata::default(),
+            progress: ProgressHandle::default(),
         }
     }
 }
@@ -383,4 +1003,83 @@ impl AsRef<GNFS> for GNFS {
     fn as_ref(&self) -> &GNFS {
         self
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::integer_math::normal::Normal;
+
+    #[test]
+    fn extending_the_factor_bases_twice_adds_each_prime_once() {
+        let token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("extend_factor_bases").unwrap();
+        let (n, m) = (BigInt::from(45113), BigInt::from(31));
+        let mut gnfs = GNFS::with_save_locations(&token, locations, GnfsConfig::default(), &n, &m, 3, &BigInt::from(100), 65, 200, true).unwrap();
+        // 131 is prime, so the second extension starts from a bound that is itself a prime.
+        gnfs.extend_factor_bases(&token, &BigInt::from(131));
+        gnfs.extend_factor_bases(&token, &BigInt::from(200));
+
+        let bases = &gnfs.prime_factor_base;
+        for base in [&bases.rational_factor_base, &bases.algebraic_factor_base] {
+            assert_eq!(base.iter().collect::<HashSet<_>>().len(), base.len());
+            assert!(base.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+        assert_eq!(bases.rational_factor_base.last(), Some(&BigInt::from(199)));
+        assert_eq!(bases.rational_factor_base.iter().filter(|&p| p == &BigInt::from(131)).count(), 1);
+        for pairs in [&gnfs.rational_factor_pair_collection, &gnfs.algebraic_factor_pair_collection] {
+            assert_eq!(pairs.0.iter().map(|pair| (pair.p, pair.r)).collect::<HashSet<_>>().len(), pairs.0.len());
+        }
+    }
+
+    #[test]
+    fn companion_relations_follow_their_polynomial_through_adoption() {
+        let token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("companion_polynomials").unwrap();
+        let n = BigInt::from(45113);
+        let mut gnfs = GNFS::with_save_locations(&token, locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        gnfs.set_config(GnfsConfig { sieve_polynomials: Some(3), ..GnfsConfig::default() });
+        let m = gnfs.polynomial_base.clone();
+
+        assert_eq!(gnfs.polynomial_collection.len(), 3);
+        assert_eq!(gnfs.current_relations_progress.relations.companion_relations.len(), 2);
+        let collection = &gnfs.polynomial_collection;
+        assert!(collection[0] != collection[1] && collection[0] != collection[2] && collection[1] != collection[2]);
+        for polynomial in &gnfs.polynomial_collection {
+            assert_eq!(polynomial.degree(), 3);
+            assert!((polynomial.evaluate(&m) % &n).is_zero());
+        }
+
+        gnfs.run_stage(Stage::Sieve, &token).unwrap();
+        let on_their_polynomial = |gnfs: &GNFS| {
+            let relations = &gnfs.current_relations_progress.relations;
+            let companions = relations.companion_relations.iter().flatten();
+            relations.smooth_relations.iter().chain(companions).all(|relation| {
+                let polynomial = &gnfs.polynomial_collection[relation.polynomial];
+                relation.algebraic_norm == Normal::algebraic(&relation.a, &relation.b, polynomial)
+                    && relation.rational_norm == &relation.a + &relation.b * &m
+                    && relation.is_smooth()
+            })
+        };
+        assert!(on_their_polynomial(&gnfs));
+        assert!(gnfs.current_relations_progress.relations.companion_relations.iter().all(|relations| !relations.is_empty()));
+        assert!(gnfs.current_relations_progress.relations.smooth_relations.iter().all(|relation| relation.polynomial == 0));
+
+        let previous = gnfs.current_polynomial.clone();
+        let previous_count = gnfs.current_relations_progress.relations.smooth_relations.len();
+        let adopted = gnfs.polynomial_collection[2].clone();
+        let adopted_count = gnfs.current_relations_progress.relations.companion_relations[1].len();
+        gnfs.adopt_companion_polynomial(2, &token);
+
+        assert_eq!(gnfs.current_polynomial, adopted);
+        assert_eq!(gnfs.polynomial_collection[2], previous);
+        assert_eq!(gnfs.current_relations_progress.smooth_relations_counter, adopted_count);
+        assert_eq!(gnfs.current_relations_progress.relations.companion_relations[1].len(), previous_count);
+        assert!(on_their_polynomial(&gnfs));
+        for pair in &gnfs.algebraic_factor_pair_collection.0 {
+            let p = BigInt::from(pair.p);
+            assert!((adopted.evaluate(&BigInt::from(pair.r)) % &p).is_zero(), "{} is not a root of ƒ mod {}", pair.r, pair.p);
+        }
+    }
+}
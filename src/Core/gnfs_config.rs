@@ -0,0 +1,354 @@
+// src/core/gnfs_config.rs
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use crate::core::error::GnfsError;
+use crate::core::static_random::StaticRandom;
+use crate::matrix::MatrixSolver;
+use crate::polynomial::PolynomialSelection;
+use crate::polynomial::candidate_pool::DEFAULT_POOL_CAPACITY;
+use crate::relation_sieve::{SieveDevice, SieveStrategy};
+use crate::relation_sieve::partial_relation::DEFAULT_LARGE_PRIME_MULTIPLIER;
+use crate::relation_sieve::rough_store::DEFAULT_ROUGH_RELATION_CAP;
+use crate::relation_sieve::storage::RelationFormat;
+use crate::square_root::SquareRootMethod;
+
+/// Relation buffer settings for [`ConfigProfile::LowMemory`].
+const LOW_MEMORY_FLUSH_THRESHOLD: usize = 1_000;
+const LOW_MEMORY_RELATION_CAP: usize = 16 * 1024 * 1024;
+const LOW_MEMORY_BLOCK_LENGTH: usize = 16 * 1024;
+/// Buffered relations wait this long under [`ConfigProfile::Fast`], trading a longer replay after
+/// a crash for fewer writes.
+const FAST_FLUSH_INTERVAL_SECS: u64 = 900;
+/// Relations the matrix stage keeps decoded when it reads them from the relation store, a few
+/// hundred megabytes at most.
+pub const DEFAULT_RELATION_CACHE_CAPACITY: usize = 100_000;
+/// Polynomial search time for [`PolynomialSelection::Kleinjung`] when none is configured.
+pub const DEFAULT_POLYNOMIAL_SEARCH_SECS: u64 = 60;
+/// Candidates a [`PolynomialSelection::Kleinjung`] search scores before it stops when no limit is
+/// configured, a few seconds' worth for inputs of 20 digits.
+pub const DEFAULT_POLYNOMIAL_SEARCH_CANDIDATES: usize = 2_000;
+/// Most polynomials sieved together; each extra one adds an algebraic factorization per pair
+/// whose rational side is smooth.
+pub const MAX_SIEVE_POLYNOMIALS: usize = 3;
+
+/// A named bundle of settings for users who don't want to tune individual options. A profile only
+/// fills settings that weren't given explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfigProfile {
+    /// Every core, bound calibration, rough relation recycling and infrequent flushes.
+    Fast,
+    /// The detected defaults.
+    Balanced,
+    /// One thread, small sieve blocks, a small relation buffer flushed often, and no rough
+    /// relations kept in memory.
+    LowMemory,
+}
+
+impl ConfigProfile {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigProfile::Fast => "fast",
+            ConfigProfile::Balanced => "balanced",
+            ConfigProfile::LowMemory => "low-memory",
+        }
+    }
+}
+
+impl Display for ConfigProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ConfigProfile {
+    type Err = GnfsError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        [ConfigProfile::Fast, ConfigProfile::Balanced, ConfigProfile::LowMemory]
+            .into_iter()
+            .find(|profile| profile.name() == input)
+            .ok_or_else(|| GnfsError::InvalidParameter(format!("unknown profile {:?}; expected fast, balanced or low-memory", input)))
+    }
+}
+
+/// Run settings that are independent of the number being factored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GnfsConfig {
+    /// When set, every stochastic choice is drawn from this seed so a run can be reproduced.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Overrides the cache-derived sieve block length, in bytes.
+    #[serde(default)]
+    pub sieve_block_length: Option<usize>,
+    /// Overrides the cache-derived sieve bucket count.
+    #[serde(default)]
+    pub sieve_bucket_count: Option<usize>,
+    /// Overrides the memory-derived number of relations buffered before a flush.
+    #[serde(default)]
+    pub relation_flush_threshold: Option<usize>,
+    /// Longest buffered relations wait before being written, in seconds.
+    #[serde(default)]
+    pub relation_flush_interval_secs: Option<u64>,
+    /// How often partial relations are combined while a batch is being sieved, in seconds.
+    #[serde(default)]
+    pub relation_recycle_interval_secs: Option<u64>,
+    /// Bytes the relation buffer may occupy before it is spilled to disk.
+    #[serde(default)]
+    pub relation_memory_cap: Option<usize>,
+    /// Write a new smooth relation file as zstd frames, one per flush; see
+    /// [`compression`](crate::relation_sieve::compression). Off when unset.
+    #[serde(default)]
+    pub compress_relations: Option<bool>,
+    /// Resident memory the run is expected to stay within, in bytes; a warning is logged when
+    /// usage gets close.
+    #[serde(default)]
+    pub memory_budget: Option<usize>,
+    /// Ask factordb.com for known factors of N before sieving (requires the `factordb` feature).
+    #[serde(default)]
+    pub factordb_lookup: bool,
+    /// Report newly found factors to factordb.com after a successful run (requires the `factordb` feature).
+    #[serde(default)]
+    pub factordb_submit: bool,
+    /// Trial-sieve a few candidate prime bounds and keep the one with the lowest projected sieving time.
+    #[serde(default)]
+    pub calibrate_prime_bound: bool,
+    /// Extra relations collected beyond the matrix column count; defaults to the larger of 10 and 5% of the columns.
+    #[serde(default)]
+    pub relation_slack: Option<usize>,
+    /// Extra quadratic characters checked against each dependency before its square root is
+    /// attempted; 0 disables the check.
+    #[serde(default)]
+    pub quadratic_character_checks: Option<usize>,
+    /// Wall-clock limit for the run, in seconds. Once it passes, the current sieve batch or matrix
+    /// step finishes, everything is checkpointed and the run exits for a later `gnfs resume`.
+    #[serde(default)]
+    pub max_run_secs: Option<u64>,
+    /// Worker threads for parallel stages; all cores when unset.
+    #[serde(default)]
+    pub threads: Option<usize>,
+    /// Keep relations with a single large prime and combine pairs sharing it into full
+    /// relations; on when unset.
+    #[serde(default)]
+    pub large_prime_recycling: Option<bool>,
+    /// Large primes are kept up to this multiple of their side's factor base bound;
+    /// [`DEFAULT_LARGE_PRIME_MULTIPLIER`] when unset.
+    #[serde(default)]
+    pub large_prime_multiplier: Option<usize>,
+    /// Also keep relations with a large prime on each side and combine them through cycles in
+    /// the large prime graph. The spanning forest needs every partial at hand, so rough relations
+    /// stay in memory rather than spilling to disk. Off when unset.
+    #[serde(default)]
+    pub double_large_primes: Option<bool>,
+    /// Rough relations kept in the on-disk store before further ones are discarded.
+    #[serde(default)]
+    pub rough_relation_cap: Option<usize>,
+    /// Spill rough relations to an append-only file in the save directory instead of keeping
+    /// them in memory; on when unset.
+    #[serde(default)]
+    pub spill_rough_relations: Option<bool>,
+    /// Polynomials sieved together over the same region, the current one plus companions sharing
+    /// its rational side; 1 when unset, at most [`MAX_SIEVE_POLYNOMIALS`].
+    #[serde(default)]
+    pub sieve_polynomials: Option<usize>,
+    /// Sieve each line-sieve row with logarithms and factor only the pairs that pass, rather than
+    /// trial-dividing every coprime pair; on when unset.
+    #[serde(default)]
+    pub log_sieve: Option<bool>,
+    /// How the relation sieve covers the (a, b) plane; the line sieve when unset.
+    #[serde(default)]
+    pub sieve_strategy: Option<SieveStrategy>,
+    /// Where the line sieve runs; the CPU when unset, and whenever no GPU can be used.
+    #[serde(default)]
+    pub sieve_device: Option<SieveDevice>,
+    /// Special-q primes sieved by the lattice sieve, inclusive. Unset bounds cover the upper half of
+    /// the algebraic factor base. A special-q above the factor base bound is left in its relations
+    /// as a large prime.
+    #[serde(default)]
+    pub special_q_min: Option<u64>,
+    #[serde(default)]
+    pub special_q_max: Option<u64>,
+    /// How the polynomial is chosen; the base-m expansion when unset.
+    #[serde(default)]
+    pub polynomial_selection: Option<PolynomialSelection>,
+    /// Time allowed for a polynomial search, in seconds.
+    #[serde(default)]
+    pub polynomial_search_secs: Option<u64>,
+    /// Candidates a polynomial search scores before it stops, whatever time is left; 2000 when unset.
+    #[serde(default)]
+    pub polynomial_search_candidates: Option<usize>,
+    /// Polynomials kept from the search for the root optimization to choose among; 8 when unset.
+    #[serde(default)]
+    pub polynomial_candidates: Option<usize>,
+    /// Largest leading coefficient a Kleinjung search tries; no limit but time when unset.
+    #[serde(default)]
+    pub polynomial_max_leading_coefficient: Option<u64>,
+    /// Bases on either side of the chosen one also expanded and scored by a base-m selection; only
+    /// the chosen base when unset.
+    #[serde(default)]
+    pub polynomial_base_range: Option<u64>,
+    /// Rotate and translate the selected polynomial toward a larger Murphy's E before the factor
+    /// bases are built; on when unset.
+    #[serde(default)]
+    pub polynomial_optimization: Option<bool>,
+    /// Filter the relation sets before the matrix is built: singleton and clique removal, then
+    /// 2-way and 3-way merges; see [`filter`](crate::relation_sieve::filter). On when unset.
+    #[serde(default)]
+    pub relation_filter: Option<bool>,
+    /// How the sorted relation store is written; [`RelationFormat::Binary`] when unset.
+    #[serde(default)]
+    pub relation_format: Option<RelationFormat>,
+    /// Decoded relations the matrix stage keeps while it reads them from disk;
+    /// [`DEFAULT_RELATION_CACHE_CAPACITY`] when unset.
+    #[serde(default)]
+    pub relation_cache_capacity: Option<usize>,
+    /// Matrix stage solver; picked from the matrix size with [`MatrixSolver::select`] when unset.
+    #[serde(default)]
+    pub matrix_solver: Option<MatrixSolver>,
+    /// Square root stage method; [`SquareRootMethod::Montgomery`] when unset.
+    #[serde(default)]
+    pub square_root_method: Option<SquareRootMethod>,
+    /// The profile applied with [`GnfsConfig::apply_profile`], if any.
+    #[serde(default)]
+    pub profile: Option<ConfigProfile>,
+}
+
+impl GnfsConfig {
+    pub fn with_seed(seed: u64) -> Self {
+        GnfsConfig { seed: Some(seed), ..Default::default() }
+    }
+
+    /// Fills the settings still unset with the profile's values.
+    pub fn apply_profile(&mut self, profile: ConfigProfile) {
+        self.profile = Some(profile);
+        match profile {
+            ConfigProfile::Fast => {
+                self.calibrate_prime_bound = true;
+                self.large_prime_recycling.get_or_insert(true);
+                self.relation_flush_interval_secs.get_or_insert(FAST_FLUSH_INTERVAL_SECS);
+            }
+            ConfigProfile::Balanced => {}
+            ConfigProfile::LowMemory => {
+                self.threads.get_or_insert(1);
+                self.large_prime_recycling.get_or_insert(false);
+                self.sieve_block_length.get_or_insert(LOW_MEMORY_BLOCK_LENGTH);
+                self.relation_flush_threshold.get_or_insert(LOW_MEMORY_FLUSH_THRESHOLD);
+                self.relation_memory_cap.get_or_insert(LOW_MEMORY_RELATION_CAP);
+            }
+        }
+    }
+
+    pub fn large_prime_recycling(&self) -> bool {
+        self.large_prime_recycling.unwrap_or(true)
+    }
+
+    pub fn large_prime_multiplier(&self) -> usize {
+        self.large_prime_multiplier.unwrap_or(DEFAULT_LARGE_PRIME_MULTIPLIER).max(1)
+    }
+
+    pub fn double_large_primes(&self) -> bool {
+        self.large_prime_recycling() && self.double_large_primes.unwrap_or(false)
+    }
+
+    pub fn spill_rough_relations(&self) -> bool {
+        self.spill_rough_relations.unwrap_or(true)
+    }
+
+    pub fn compress_relations(&self) -> bool {
+        self.compress_relations.unwrap_or(false)
+    }
+
+    pub fn rough_relation_cap(&self) -> usize {
+        self.rough_relation_cap.unwrap_or(DEFAULT_ROUGH_RELATION_CAP)
+    }
+
+    pub fn sieve_polynomials(&self) -> usize {
+        self.sieve_polynomials.unwrap_or(1).clamp(1, MAX_SIEVE_POLYNOMIALS)
+    }
+
+    pub fn log_sieve(&self) -> bool {
+        self.log_sieve.unwrap_or(true)
+    }
+
+    pub fn relation_filter(&self) -> bool {
+        self.relation_filter.unwrap_or(true)
+    }
+
+    pub fn sieve_strategy(&self) -> SieveStrategy {
+        self.sieve_strategy.unwrap_or_default()
+    }
+
+    pub fn sieve_device(&self) -> SieveDevice {
+        self.sieve_device.unwrap_or_default()
+    }
+
+    pub fn relation_format(&self) -> RelationFormat {
+        self.relation_format.unwrap_or_default()
+    }
+
+    pub fn relation_cache_capacity(&self) -> usize {
+        self.relation_cache_capacity.unwrap_or(DEFAULT_RELATION_CACHE_CAPACITY)
+    }
+
+    /// The special-q range for an algebraic factor base bounded by `algebraic_factor_base_max`.
+    pub fn special_q_range(&self, algebraic_factor_base_max: u64) -> (u64, u64) {
+        let min = self.special_q_min.unwrap_or(algebraic_factor_base_max / 2).max(2);
+        let max = self.special_q_max.unwrap_or(algebraic_factor_base_max).max(min);
+        (min, max)
+    }
+
+    pub fn polynomial_selection(&self) -> PolynomialSelection {
+        self.polynomial_selection.unwrap_or_default()
+    }
+
+    pub fn polynomial_search_budget(&self) -> Duration {
+        Duration::from_secs(self.polynomial_search_secs.unwrap_or(DEFAULT_POLYNOMIAL_SEARCH_SECS))
+    }
+
+    pub fn polynomial_search_candidates(&self) -> usize {
+        self.polynomial_search_candidates.unwrap_or(DEFAULT_POLYNOMIAL_SEARCH_CANDIDATES).max(1)
+    }
+
+    pub fn polynomial_candidates(&self) -> usize {
+        self.polynomial_candidates.unwrap_or(DEFAULT_POOL_CAPACITY).max(1)
+    }
+
+    pub fn polynomial_max_leading_coefficient(&self) -> u64 {
+        self.polynomial_max_leading_coefficient.unwrap_or(u64::MAX)
+    }
+
+    pub fn polynomial_base_range(&self) -> u64 {
+        self.polynomial_base_range.unwrap_or(0)
+    }
+
+    pub fn polynomial_optimization(&self) -> bool {
+        self.polynomial_optimization.unwrap_or(true)
+    }
+
+    pub fn matrix_solver(&self, columns: usize) -> MatrixSolver {
+        self.matrix_solver.unwrap_or_else(|| MatrixSolver::select(columns))
+    }
+
+    pub fn square_root_method(&self) -> SquareRootMethod {
+        self.square_root_method.unwrap_or_default()
+    }
+
+    /// Returns the generator for one consumer of randomness. Each consumer passes its own
+    /// `stream` so that seeded runs don't hand the same sequence to unrelated choices.
+    pub fn random(&self, stream: u64) -> StaticRandom {
+        match self.seed {
+            Some(seed) => StaticRandom::from_seed(seed, stream),
+            None => StaticRandom::new(),
+        }
+    }
+}
+
+/// Stream identifiers passed to [`GnfsConfig::random`].
+pub mod random_stream {
+    pub const FREE_RELATION_ORDER: u64 = 1;
+    pub const FACTORIZATION: u64 = 2;
+    pub const MATRIX: u64 = 3;
+}
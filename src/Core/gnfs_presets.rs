@@ -0,0 +1,107 @@
+// src/core/gnfs_presets.rs
+
+//! Fixed parameters for small N. The general formulas are tuned for numbers well past 40 digits;
+//! below that they give factor bases too small to produce a dependency or too large to fill, so the
+//! fallback GNFS path takes its settings from this table instead. The factor bases are sized for
+//! runs that combine partial relations, as they do by default; without them the smooth yield of
+//! the smaller tiers can run out before the target.
+
+use num::BigInt;
+use std::fmt::Display;
+
+/// Settings for every N with up to `max_digits` decimal digits (and more than the previous row).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GnfsPreset {
+    pub max_digits: usize,
+    pub degree: usize,
+    /// Rational factor base bound; the algebraic bound is three times this.
+    pub prime_bound: u64,
+    pub relation_quantity: usize,
+    /// Largest |a| sieved on each row.
+    pub value_range: usize,
+}
+
+/// Smallest N the presets cover, in decimal digits.
+pub const MIN_PRESET_DIGITS: usize = 10;
+
+pub const PRESETS: [GnfsPreset; 7] = [
+    GnfsPreset { max_digits: 12, degree: 3, prime_bound: 200, relation_quantity: 50, value_range: 200 },
+    GnfsPreset { max_digits: 16, degree: 3, prime_bound: 500, relation_quantity: 100, value_range: 500 },
+    GnfsPreset { max_digits: 20, degree: 3, prime_bound: 1_000, relation_quantity: 150, value_range: 1_000 },
+    GnfsPreset { max_digits: 25, degree: 3, prime_bound: 3_000, relation_quantity: 300, value_range: 2_000 },
+    GnfsPreset { max_digits: 30, degree: 3, prime_bound: 8_000, relation_quantity: 500, value_range: 4_000 },
+    GnfsPreset { max_digits: 35, degree: 3, prime_bound: 20_000, relation_quantity: 800, value_range: 8_000 },
+    GnfsPreset { max_digits: 40, degree: 3, prime_bound: 40_000, relation_quantity: 1_200, value_range: 16_000 },
+];
+
+impl GnfsPreset {
+    /// The preset for `n`, or `None` outside the 10–40 digit range.
+    pub fn for_n(n: &BigInt) -> Option<GnfsPreset> {
+        let digits = n.magnitude().to_string().len();
+        if digits < MIN_PRESET_DIGITS {
+            return None;
+        }
+        PRESETS.iter().copied().find(|preset| digits <= preset.max_digits)
+    }
+
+    /// ⌊N^(1/degree)⌋, which keeps the base-m polynomial monic or nearly so.
+    pub fn polynomial_base(&self, n: &BigInt) -> BigInt {
+        n.nth_root(self.degree as u32)
+    }
+}
+
+impl Display for GnfsPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "preset for ≤{} digits: degree {}, prime bound {}, {} relations, value range {}",
+            self.max_digits, self.degree, self.prime_bound, self.relation_quantity, self.value_range
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polynomial::polynomial::Polynomial;
+
+    #[test]
+    fn presets_cover_the_range_and_give_usable_polynomials() {
+        assert!(PRESETS.windows(2).all(|pair| {
+            pair[0].max_digits < pair[1].max_digits
+                && pair[0].prime_bound < pair[1].prime_bound
+                && pair[0].relation_quantity < pair[1].relation_quantity
+        }));
+
+        for digits in MIN_PRESET_DIGITS..=40 {
+            let n = BigInt::from(10).pow(digits as u32 - 1) + 7;
+            let preset = GnfsPreset::for_n(&n).unwrap_or_else(|| panic!("no preset for {} digits", digits));
+            let m = preset.polynomial_base(&n);
+            let f = Polynomial::from_base_m(&n, &m, preset.degree);
+            assert_eq!(f.evaluate(&m), n);
+            assert_eq!(f.degree(), preset.degree);
+        }
+        assert!(GnfsPreset::for_n(&BigInt::from(45113)).is_none());
+        assert!(GnfsPreset::for_n(&BigInt::from(10).pow(40)).is_none());
+    }
+
+    #[test]
+    fn the_smallest_tiers_factor_a_semiprime_with_their_preset() {
+        use crate::core::cancellation_token::CancellationToken;
+        use crate::core::directory_location::DirectoryLocations;
+        use crate::core::factorizer::Factorizer;
+        use crate::core::stage::StageOutcome;
+
+        // Semiprimes near the top of each tier, whose factors lie well past the trial division
+        // bound, so the preset's GNFS run has to split them.
+        for (tier, p, q) in [(0, 999_979u64, 999_983u64), (1, 10_000_019, 10_000_079)] {
+            let n = BigInt::from(p) * q;
+            assert_eq!(GnfsPreset::for_n(&n), Some(PRESETS[tier]));
+            let locations = DirectoryLocations::temporary(&format!("preset_tier_{}", tier)).unwrap();
+            let result = Factorizer::new(n.clone()).with_save_locations(locations).run(&CancellationToken::new()).unwrap();
+            assert_eq!(result.outcome, StageOutcome::Completed, "{} did not complete", n);
+            assert_eq!(result.metadata.algorithm, "GNFS");
+            assert_eq!(result.factors(), vec![BigInt::from(p), BigInt::from(q)]);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// src/core/input.rs
+
+//! Checks shared by every entry point that accepts a number to factor. Input is parsed once,
+//! its sign dropped and values that can't be factored rejected; then trial division takes out
+//! the small primes, which covers even n and finishes tiny n outright, and a cofactor that is a
+//! perfect power is reduced to its root. The algorithms behind it can assume an odd n > 1 with
+//! no small factors that is not a perfect power.
+
+use num::{BigInt, One, Signed};
+use crate::algorithms::trial_division::trial_division;
+use crate::core::error::GnfsError;
+use crate::integer_math::factorization_factory::FactorizationFactory;
+
+/// Parses a decimal integer such as `-45113` or ` 1000003 `, ignoring surrounding whitespace
+/// and the sign, and rejects values whose absolute value is at most 1.
+pub fn parse(input: &str) -> Result<BigInt, GnfsError> {
+    let invalid = |reason: String| GnfsError::InvalidInput { input: input.to_string(), reason };
+    let trimmed = input.trim();
+    let digits = trimmed.strip_prefix(['-', '+']).unwrap_or(trimmed);
+    if digits.is_empty() {
+        return Err(invalid("no digits".to_string()));
+    }
+    if let Some((position, character)) = digits.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        return Err(invalid(format!("unexpected {:?} at position {}", character, position)));
+    }
+    let n = BigInt::parse_bytes(digits.as_bytes(), 10).ok_or_else(|| invalid("not a decimal integer".to_string()))?;
+    validate(&n)
+}
+
+/// The absolute value of `n`, or an error when it is 0 or 1.
+pub fn validate(n: &BigInt) -> Result<BigInt, GnfsError> {
+    let n = n.abs();
+    if n <= BigInt::one() {
+        return Err(GnfsError::InvalidInput { input: n.to_string(), reason: "only integers with |n| > 1 can be factored".to_string() });
+    }
+    Ok(n)
+}
+
+/// A validated input with its prime factors up to a trial division limit removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedInput {
+    /// |n|.
+    pub n: BigInt,
+    /// Primes up to the limit dividing n, with their exponents, in increasing order.
+    pub small_factors: Vec<(BigInt, u32)>,
+    /// What is left of n once the small factors are divided out is `cofactor^cofactor_exponent`;
+    /// the cofactor is 1, a prime, or a composite with no factor up to the limit that is not a
+    /// perfect power.
+    pub cofactor: BigInt,
+    pub cofactor_exponent: u32,
+}
+
+impl NormalizedInput {
+    pub fn is_prime(&self) -> bool {
+        match self.small_factors.as_slice() {
+            [] => self.cofactor_exponent == 1 && FactorizationFactory::is_probable_prime(&self.cofactor),
+            [(_, 1)] => self.cofactor.is_one(),
+            _ => false,
+        }
+    }
+
+    /// True when nothing composite is left for a factoring algorithm.
+    pub fn is_fully_factored(&self) -> bool {
+        self.cofactor.is_one() || FactorizationFactory::is_probable_prime(&self.cofactor)
+    }
+}
+
+/// Validates `n` and trial-divides it by every prime up to `limit`.
+pub fn normalize(n: &BigInt, limit: u64) -> Result<NormalizedInput, GnfsError> {
+    let n = validate(n)?;
+    let (small_factors, cofactor) = trial_division(&n, limit);
+    let (cofactor, cofactor_exponent) = perfect_power(&cofactor);
+    Ok(NormalizedInput { n, small_factors, cofactor, cofactor_exponent })
+}
+
+/// (r, k) with r^k = `n` for the largest such k, so r is not itself a perfect power; (`n`, 1)
+/// when `n` is not a perfect power. GNFS can't split a perfect power: every congruence of
+/// squares it finds for r² is a trivial one.
+pub fn perfect_power(n: &BigInt) -> (BigInt, u32) {
+    if n <= &BigInt::one() {
+        return (n.clone(), 1);
+    }
+    (2..=n.bits() as u32)
+        .rev()
+        .map(|k| (n.nth_root(k), k))
+        .find(|(root, k)| root > &BigInt::one() && &root.pow(*k) == n)
+        .unwrap_or_else(|| (n.clone(), 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_normalizes_inputs() {
+        assert_eq!(parse(" -45113\n").unwrap(), BigInt::from(45113));
+        for bad in ["", "-", "12a4", "4.5", "1", "-1", "0"] {
+            assert!(matches!(parse(bad), Err(GnfsError::InvalidInput { .. })), "{:?}", bad);
+        }
+
+        let even = normalize(&BigInt::from(-2 * 1_000_003), 100).unwrap();
+        assert_eq!(even.small_factors, vec![(BigInt::from(2), 1)]);
+        assert!(even.is_fully_factored() && !even.is_prime());
+
+        assert!(normalize(&BigInt::from(1_000_003), 1 << 20).unwrap().is_prime());
+        assert!(!normalize(&BigInt::from(45113), 100).unwrap().is_fully_factored());
+    }
+
+    #[test]
+    fn reduces_perfect_powers_to_their_root() {
+        assert_eq!(perfect_power(&BigInt::from(45113)), (BigInt::from(45113), 1));
+        assert_eq!(perfect_power(&BigInt::from(229).pow(2u32)), (BigInt::from(229), 2));
+        assert_eq!(perfect_power(&BigInt::from(45113).pow(6u32)), (BigInt::from(45113), 6));
+        assert_eq!(perfect_power(&BigInt::from(2).pow(64u32)), (BigInt::from(2), 64));
+
+        let square = normalize(&(BigInt::from(3) * BigInt::from(229).pow(2u32)), 100).unwrap();
+        assert_eq!((square.cofactor.clone(), square.cofactor_exponent), (BigInt::from(229), 2));
+        assert!(square.is_fully_factored() && !square.is_prime());
+        assert!(!normalize(&BigInt::from(229).pow(2u32), 100).unwrap().is_prime());
+    }
+}
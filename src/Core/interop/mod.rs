@@ -0,0 +1,5 @@
+// src/core/interop/mod.rs
+
+//! Reading and writing the files of other factoring tools.
+
+pub mod msieve;
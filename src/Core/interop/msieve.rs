@@ -0,0 +1,316 @@
+// src/core/interop/msieve.rs
+
+//! msieve's factor base (`.fb`) and relation (`.dat`) files, so that runs can trade stages with
+//! msieve and the GGNFS sievers that write the same formats.
+//!
+//! A factor base file holds N, the skew, the rational polynomial as `R0`, `R1` and the algebraic
+//! one as `A0` ... `Ad`, optionally followed by the factor bases: `FRNUM`/`FRMAX` and the rational
+//! entries, then `FANUM`/`FAMAX` and the algebraic ones, one prime per line with its roots. A
+//! relation file starts with `N <n>` and holds one `a,b:rational:algebraic` line per relation, the
+//! prime factors of each norm in hexadecimal with repeats, small ones possibly left out.
+//!
+//! msieve's (a, b) stands for a - bα where this crate's stands for a + bα, so a changes sign in
+//! both directions. Relations read from a `.dat` file are re-sieved from (a, b) with the run's own
+//! factor bases; the listed factors only serve to check that they came from the same polynomials.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use num::{BigInt, Integer, Num, One, Signed, ToPrimitive, Zero};
+use crate::core::error::GnfsError;
+use crate::core::gnfs::GNFS;
+use crate::factor::factor_pair::FactorPair;
+use crate::polynomial::polynomial::Polynomial;
+use crate::polynomial::quality;
+use crate::relation_sieve::relation::Relation;
+
+/// The names msieve gives the files by default.
+pub const FACTOR_BASE_FILENAME: &str = "msieve.fb";
+pub const RELATIONS_FILENAME: &str = "msieve.dat";
+
+/// The contents of an msieve `.fb` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsieveFactorBase {
+    pub n: BigInt,
+    pub skew: f64,
+    /// R0, R1.
+    pub rational: Vec<BigInt>,
+    /// A0, ..., Ad.
+    pub algebraic: Vec<BigInt>,
+    /// Empty when the file has only the polynomials.
+    pub rational_entries: Vec<FactorPair>,
+    pub algebraic_entries: Vec<FactorPair>,
+}
+
+fn invalid(input: &str, reason: impl Into<String>) -> GnfsError {
+    GnfsError::InvalidInput { input: input.to_string(), reason: reason.into() }
+}
+
+fn parse_integer(input: &str, radix: u32) -> Result<BigInt, GnfsError> {
+    BigInt::from_str_radix(input.trim(), radix).map_err(|e| invalid(input, e.to_string()))
+}
+
+/// Sets `coefficients[index]` to `value`, growing the list with zeros as needed.
+fn set_coefficient(coefficients: &mut Vec<BigInt>, index: usize, value: BigInt) {
+    if coefficients.len() <= index {
+        coefficients.resize(index + 1, BigInt::zero());
+    }
+    coefficients[index] = value;
+}
+
+impl MsieveFactorBase {
+    /// The polynomials and factor bases of `gnfs`.
+    pub fn from_gnfs(gnfs: &GNFS) -> Self {
+        let algebraic = quality::coefficients(&gnfs.current_polynomial);
+        MsieveFactorBase {
+            n: gnfs.n.clone(),
+            skew: quality::skewed_log_size(&algebraic).0,
+            rational: quality::coefficients(&gnfs.rational_polynomial),
+            algebraic,
+            rational_entries: gnfs.rational_factor_pair_collection.0.clone(),
+            algebraic_entries: gnfs.algebraic_factor_pair_collection.0.clone(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, GnfsError> {
+        let mut factor_base = MsieveFactorBase {
+            n: BigInt::zero(),
+            skew: 1.0,
+            rational: Vec::new(),
+            algebraic: Vec::new(),
+            rational_entries: Vec::new(),
+            algebraic_entries: Vec::new(),
+        };
+        // Which side the entry lines belong to, once a FRNUM or FANUM line has been read.
+        let mut side: Option<&mut Vec<FactorPair>> = None;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match key {
+                "N" => factor_base.n = parse_integer(value, 10)?,
+                "SKEW" => factor_base.skew = value.trim().parse().map_err(|_| invalid(line, "the skew is not a number"))?,
+                "FRNUM" => side = Some(&mut factor_base.rational_entries),
+                "FANUM" => side = Some(&mut factor_base.algebraic_entries),
+                "FRMAX" | "FAMAX" => {}
+                _ if key.starts_with('R') || key.starts_with('A') => {
+                    let index: usize = key[1..].parse().map_err(|_| invalid(line, "unknown key"))?;
+                    let coefficients = if key.starts_with('R') { &mut factor_base.rational } else { &mut factor_base.algebraic };
+                    set_coefficient(coefficients, index, parse_integer(value, 10)?);
+                }
+                _ => {
+                    let entries = side.as_deref_mut().ok_or_else(|| invalid(line, "factor base entry before FRNUM or FANUM"))?;
+                    let numbers = line
+                        .split(|c: char| c.is_whitespace() || c == ',')
+                        .filter(|field| !field.is_empty())
+                        .map(|field| field.parse::<u64>().map_err(|_| invalid(line, "factor base entries are a prime and its roots")))
+                        .collect::<Result<Vec<u64>, GnfsError>>()?;
+                    let (&p, roots) = numbers.split_first().ok_or_else(|| invalid(line, "empty factor base entry"))?;
+                    entries.extend(roots.iter().map(|&r| FactorPair::new(p, r)));
+                }
+            }
+        }
+        if factor_base.n.is_zero() || factor_base.rational.len() != 2 || factor_base.algebraic.len() < 2 {
+            return Err(invalid(text.lines().next().unwrap_or_default(), "an msieve factor base needs N, R0, R1 and A0 to Ad"));
+        }
+        Ok(factor_base)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, GnfsError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn rational_polynomial(&self) -> Polynomial {
+        quality::from_coefficients(&self.rational)
+    }
+
+    pub fn algebraic_polynomial(&self) -> Polynomial {
+        quality::from_coefficients(&self.algebraic)
+    }
+
+    /// Why relations found with these polynomials can't be used by `gnfs`, if they can't.
+    pub fn mismatch(&self, gnfs: &GNFS) -> Option<String> {
+        if self.n != gnfs.n {
+            Some(format!("it is for N = {}, the run for N = {}", self.n, gnfs.n))
+        } else if self.algebraic_polynomial() != gnfs.current_polynomial {
+            Some(format!("its algebraic polynomial {} is not the run's {}", self.algebraic_polynomial(), gnfs.current_polynomial))
+        } else if self.rational_polynomial() != gnfs.rational_polynomial {
+            Some(format!("its rational polynomial {} is not the run's {}", self.rational_polynomial(), gnfs.rational_polynomial))
+        } else {
+            None
+        }
+    }
+
+    /// The file text, with the factor bases when there are any.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "N {}", self.n);
+        let _ = writeln!(text, "SKEW {:.2}", self.skew);
+        for (index, coefficient) in self.rational.iter().enumerate() {
+            let _ = writeln!(text, "R{} {}", index, coefficient);
+        }
+        for (index, coefficient) in self.algebraic.iter().enumerate() {
+            let _ = writeln!(text, "A{} {}", index, coefficient);
+        }
+        for (key, entries) in [("R", &self.rational_entries), ("A", &self.algebraic_entries)] {
+            if entries.is_empty() {
+                continue;
+            }
+            let _ = writeln!(text, "F{}NUM {}", key, entries.len());
+            let _ = writeln!(text, "F{}MAX {}", key, entries.iter().map(|entry| entry.p).max().unwrap_or(0));
+            for entry in entries {
+                let _ = writeln!(text, "{} {}", entry.p, entry.r);
+            }
+        }
+        text
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), GnfsError> {
+        fs::write(path, self.to_text())?;
+        Ok(())
+    }
+}
+
+/// One line of an msieve `.dat` file, with a and b in msieve's sign convention.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MsieveRelation {
+    pub a: BigInt,
+    pub b: BigInt,
+    pub rational_factors: Vec<BigInt>,
+    pub algebraic_factors: Vec<BigInt>,
+}
+
+impl MsieveRelation {
+    pub fn parse(line: &str) -> Result<Self, GnfsError> {
+        let mut fields = line.trim().split(':');
+        let (Some(pair), Some(rational), Some(algebraic), None) = (fields.next(), fields.next(), fields.next(), fields.next()) else {
+            return Err(invalid(line, "an msieve relation is a,b:rational factors:algebraic factors"));
+        };
+        let (a, b) = pair.split_once(',').ok_or_else(|| invalid(line, "a and b are separated by a comma"))?;
+        let factors = |list: &str| list.split(',').filter(|factor| !factor.is_empty()).map(|factor| parse_integer(factor, 16)).collect::<Result<Vec<_>, _>>();
+        Ok(MsieveRelation { a: parse_integer(a, 10)?, b: parse_integer(b, 10)?, rational_factors: factors(rational)?, algebraic_factors: factors(algebraic)? })
+    }
+
+    /// The factors of each norm, repeats included, with the large primes of a rough relation.
+    pub fn from_relation(relation: &Relation) -> Self {
+        let factors = |factorization: &crate::core::count_dictionary::CountDictionary, quotient: &BigInt| {
+            let mut factors: Vec<BigInt> = factorization.iter().flat_map(|(prime, exponent)| std::iter::repeat_n(prime, exponent as usize)).collect();
+            if quotient > &BigInt::one() {
+                factors.push(quotient.clone());
+            }
+            factors
+        };
+        MsieveRelation {
+            a: -&relation.a,
+            b: relation.b.clone(),
+            rational_factors: factors(&relation.rational_factorization, &relation.rational_quotient),
+            algebraic_factors: factors(&relation.algebraic_factorization, &relation.algebraic_quotient),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        let hex = |factors: &[BigInt]| factors.iter().map(|factor| factor.to_str_radix(16)).collect::<Vec<_>>().join(",");
+        format!("{},{}:{}:{}", self.a, self.b, hex(&self.rational_factors), hex(&self.algebraic_factors))
+    }
+
+    /// The relation for this pair under the polynomials and factor bases of `gnfs`, or None when a
+    /// listed factor doesn't divide its norm, i.e. it was found with other polynomials.
+    pub fn to_relation(&self, gnfs: &GNFS) -> Option<Relation> {
+        let a = -&self.a;
+        let (algebraic_norm, rational_norm) = Relation::norms(&gnfs.current_polynomial, &gnfs.rational_polynomial, &a, &self.b);
+        let divides = |factors: &[BigInt], norm: &BigInt| factors.iter().all(|factor| factor > &BigInt::one() && norm.is_multiple_of(factor));
+        if algebraic_norm.is_zero() || !divides(&self.rational_factors, &rational_norm) || !divides(&self.algebraic_factors, &algebraic_norm) {
+            return None;
+        }
+        let mut relation = Relation::new(gnfs, &a, &self.b);
+        relation.sieve_with_norms(gnfs, algebraic_norm, rational_norm);
+        Some(relation)
+    }
+}
+
+/// Reads an msieve `.dat` file for the run of `gnfs` and returns the relations its lines stand
+/// for, re-sieved, and the number of lines that don't fit the run's polynomials.
+pub fn read_relations(gnfs: &GNFS, path: &Path) -> Result<(Vec<Relation>, usize), GnfsError> {
+    let text = fs::read_to_string(path)?;
+    let mut relations = Vec::new();
+    let mut foreign = 0;
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        if let Some(n) = line.strip_prefix("N ") {
+            let n = parse_integer(n, 10)?;
+            if n != gnfs.n {
+                return Err(invalid(&path.display().to_string(), format!("the relations are for N = {}, the run for N = {}", n, gnfs.n)));
+            }
+            continue;
+        }
+        match MsieveRelation::parse(line)?.to_relation(gnfs) {
+            Some(relation) => relations.push(relation),
+            None => foreign += 1,
+        }
+    }
+    Ok((relations, foreign))
+}
+
+/// Writes `relations` for N as an msieve `.dat` file and returns how many were written; those
+/// msieve can't hold are left out.
+pub fn write_relations<'a>(n: &BigInt, relations: impl IntoIterator<Item = &'a Relation>, path: &Path) -> Result<usize, GnfsError> {
+    let mut writer = std::io::BufWriter::new(fs::File::create(path)?);
+    writeln!(writer, "N {}", n)?;
+    let mut written = 0;
+    for relation in relations.into_iter().filter(|relation| fits(relation)) {
+        writeln!(writer, "{}", MsieveRelation::from_relation(relation).to_line())?;
+        written += 1;
+    }
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Whether `path` looks like an msieve relation file: a `.dat` extension or an `N` first line.
+pub fn is_relation_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "dat")
+        || fs::read_to_string(path).ok().and_then(|text| text.lines().next().map(|line| line.starts_with("N "))).unwrap_or(false)
+}
+
+/// Whether msieve can hold the pair: it keeps a in a signed 64-bit word and b in an unsigned 32-bit one.
+fn fits(relation: &Relation) -> bool {
+    relation.a.abs().to_i64().is_some() && relation.b.to_u32().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cancellation_token::CancellationToken;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::gnfs_config::GnfsConfig;
+
+    #[test]
+    fn factor_bases_and_relations_round_trip_through_msieve_files() {
+        let locations = DirectoryLocations::temporary("msieve").unwrap();
+        let n = BigInt::from(45113);
+        let gnfs = GNFS::with_save_locations(&CancellationToken::new(), locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+
+        let factor_base = MsieveFactorBase::from_gnfs(&gnfs);
+        let read = MsieveFactorBase::parse(&factor_base.to_text()).unwrap();
+        assert_eq!(read, MsieveFactorBase { skew: read.skew, ..factor_base.clone() });
+        assert_eq!(read.mismatch(&gnfs), None);
+        assert_eq!(read.rational, vec![-gnfs.polynomial_base.clone(), BigInt::one()]);
+
+        let relation = (1..200)
+            .map(BigInt::from)
+            .map(|a| {
+                let mut relation = Relation::new(&gnfs, &a, &BigInt::from(3));
+                relation.sieve(&gnfs);
+                relation
+            })
+            .find(|relation| relation.is_smooth() && relation.a.gcd(&relation.b).is_one())
+            .unwrap();
+        let line = MsieveRelation::from_relation(&relation).to_line();
+        assert!(line.starts_with(&format!("{},3:", -&relation.a)));
+        let read = MsieveRelation::parse(&line).unwrap().to_relation(&gnfs).unwrap();
+        assert_eq!((read.a, read.b, read.rational_factorization.to_dict()), (relation.a.clone(), relation.b.clone(), relation.rational_factorization.to_dict()));
+
+        // A factor that doesn't divide the norm means other polynomials.
+        let mut foreign = MsieveRelation::parse(&line).unwrap();
+        foreign.rational_factors.push(BigInt::from(1_000_003));
+        assert!(foreign.to_relation(&gnfs).is_none());
+        assert!(MsieveRelation::parse("12,5:2,3").is_err());
+    }
+}
@@ -0,0 +1,53 @@
+// src/core/memory_usage.rs
+
+//! Resident memory of the process, read from /proc/self/status. The kernel's high-water mark can be
+//! reset through /proc/self/clear_refs, so resetting it when a stage starts and reading it when the
+//! stage completes gives that stage's peak, including the transient peaks of sieving and
+//! elimination. Elsewhere these readings are unavailable and all of them return `None`.
+
+use std::fs;
+
+/// Share of the configured memory budget at which a warning is logged, in percent.
+pub const MEMORY_BUDGET_WARNING_PERCENT: usize = 90;
+
+pub struct MemoryUsage;
+
+impl MemoryUsage {
+    /// Current resident set size in bytes.
+    pub fn resident() -> Option<usize> {
+        Self::status_field("VmRSS:")
+    }
+
+    /// Largest resident set size in bytes since the process started or the mark was last reset.
+    pub fn peak_resident() -> Option<usize> {
+        Self::status_field("VmHWM:")
+    }
+
+    /// Resets the high-water mark to the current resident size. Returns false when the kernel
+    /// doesn't allow it, in which case [`MemoryUsage::peak_resident`] stays the peak of the whole run.
+    pub fn reset_peak() -> bool {
+        fs::write("/proc/self/clear_refs", "5").is_ok()
+    }
+
+    fn status_field(name: &str) -> Option<usize> {
+        let status = fs::read_to_string("/proc/self/status").ok()?;
+        let line = status.lines().find(|line| line.starts_with(name))?;
+        let kilobytes: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+        kilobytes.checked_mul(1024)
+    }
+}
+
+/// Formats a byte count with a binary unit, e.g. "12.3 MiB".
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
@@ -1,12 +1,28 @@
 // src/core/mod.rs
 
 pub mod gnfs;
+pub mod factorizer;
+pub mod benchmark;
+pub mod gnfs_config;
+pub mod gnfs_presets;
+pub mod input;
 pub mod sieve_range;
+pub mod sieve_tuning;
 pub mod factor_base;
 pub mod count_dictionary;
+pub mod report;
 pub mod solution;
+pub mod stage;
 pub mod static_random;
 pub mod cpu_info;
 pub mod directory_location;
 pub mod serialization;
 pub mod cancellation_token;
+pub mod error;
+pub mod events;
+pub mod profiling;
+pub mod progress;
+pub mod memory_usage;
+pub mod interop;
+#[cfg(feature = "factordb")]
+pub mod factordb;
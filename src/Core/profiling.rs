@@ -0,0 +1,77 @@
+// src/core/profiling.rs
+
+//! Per-stage CPU profiles behind the `profiling` feature. Each stage is sampled from
+//! `start_stage` to `complete_stage`, and the samples are written next to the run's other files as
+//! `profile-<stage>.svg` (a flamegraph) and `profile-<stage>.pb` (for `pprof`), so a slow run on
+//! someone else's machine can be investigated from its save directory alone. Without the feature
+//! these functions do nothing.
+
+#[cfg(feature = "profiling")]
+mod sampler {
+    use lazy_static::lazy_static;
+    use log::{info, warn};
+    use pprof::protos::Message;
+    use pprof::ProfilerGuard;
+    use std::fs::File;
+    use std::io::Write;
+    use std::sync::Mutex;
+    use crate::core::error::GnfsError;
+
+    /// Samples per second; high enough to resolve the sieve's inner loops without skewing them.
+    const SAMPLE_FREQUENCY: i32 = 199;
+
+    lazy_static! {
+        static ref ACTIVE: Mutex<Option<(String, ProfilerGuard<'static>)>> = Mutex::new(None);
+    }
+
+    pub fn stage_started(stage: &str) {
+        let mut active = ACTIVE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Only one profiler can run at a time; a stage that never completed is discarded.
+        *active = None;
+        match ProfilerGuard::new(SAMPLE_FREQUENCY) {
+            Ok(guard) => *active = Some((stage.to_string(), guard)),
+            Err(e) => warn!("Could not start the profiler for {}: {}", stage, e),
+        }
+    }
+
+    pub fn stage_completed(stage: &str, save_directory: &str) {
+        let mut active = ACTIVE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some((profiled_stage, guard)) = active.take() else {
+            return;
+        };
+        if profiled_stage != stage {
+            return;
+        }
+        match write_profiles(&guard, stage, save_directory) {
+            Ok(()) => info!("Profile for {} written to {}/profile-{}.svg", stage, save_directory, stage),
+            Err(e) => warn!("Could not write the profile for {}: {}", stage, e),
+        }
+    }
+
+    fn write_profiles(guard: &ProfilerGuard<'static>, stage: &str, save_directory: &str) -> Result<(), GnfsError> {
+        let profiler_error = |e: pprof::Error| GnfsError::stage_failed(stage, format!("profiler: {}", e));
+        let report = guard.report().build().map_err(profiler_error)?;
+
+        let flamegraph = File::create(format!("{}/profile-{}.svg", save_directory, stage))?;
+        report.flamegraph(flamegraph).map_err(profiler_error)?;
+
+        let profile = report.pprof().map_err(profiler_error)?;
+        let bytes = profile
+            .write_to_bytes()
+            .map_err(|e| GnfsError::stage_failed(stage, format!("profile encoding: {}", e)))?;
+        File::create(format!("{}/profile-{}.pb", save_directory, stage))?.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+/// Starts sampling `stage`.
+pub fn stage_started(_stage: &str) {
+    #[cfg(feature = "profiling")]
+    sampler::stage_started(_stage);
+}
+
+/// Stops sampling `stage` and writes its profiles into `save_directory`.
+pub fn stage_completed(_stage: &str, _save_directory: &str) {
+    #[cfg(feature = "profiling")]
+    sampler::stage_completed(_stage, _save_directory);
+}
@@ -0,0 +1,224 @@
+// src/core/progress.rs
+
+//! Progress of the long-running stages for whoever is watching the run: sieving counts smooth
+//! relations toward the target, the matrix stage its iterations, and the square root stage the
+//! dependencies it has tried. Each count goes to the run's [`ProgressReporter`] as a
+//! [`ProgressUpdate`], which works out the rate, the percentage done and the time left.
+//!
+//! [`ConsoleReporter`], the default, logs the updates at most every few seconds. Library users can
+//! register their own, to drive a GUI for instance, with
+//! [`Factorizer::with_progress_reporter`](crate::Factorizer::with_progress_reporter) or
+//! [`GNFS::set_progress_reporter`](crate::core::gnfs::GNFS::set_progress_reporter); any
+//! `Fn(&ProgressUpdate)` closure is a reporter.
+
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use log::info;
+use crate::core::stage::Stage;
+
+/// How often [`ConsoleReporter`] logs while a stage is under way.
+pub const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How far a stage has got.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressUpdate {
+    pub stage: Stage,
+    /// What `completed` and `total` count: relations, columns, products or dependencies.
+    pub unit: &'static str,
+    pub completed: u64,
+    /// The count at which the stage is done; it can grow, as when the relation target is raised.
+    pub total: u64,
+    /// The count when `elapsed` started, such as the relations found before a resume. The rate
+    /// and the ETA go by the work done since.
+    pub initial: u64,
+    pub elapsed: Duration,
+}
+
+impl ProgressUpdate {
+    /// The share of the stage done, from 0 to 1.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        (self.completed as f64 / self.total as f64).min(1.0)
+    }
+
+    /// Units done per second since `initial`.
+    pub fn rate(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds <= 0.0 {
+            return 0.0;
+        }
+        self.completed.saturating_sub(self.initial) as f64 / seconds
+    }
+
+    /// The time left at the current rate; `None` until something has been done.
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.rate();
+        if rate <= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(self.total.saturating_sub(self.completed) as f64 / rate))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.completed >= self.total
+    }
+}
+
+impl Display for ProgressUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}/{} {} ({:.1}%), {:.1} {}/s",
+            self.stage, self.completed, self.total, self.unit, self.fraction() * 100.0, self.rate(), self.unit
+        )?;
+        match self.eta() {
+            Some(eta) if !self.is_finished() => write!(f, ", ETA {}", format_duration(eta)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// "1h 02m", "3m 05s" or "12s".
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, _) => format!("{}h {:02}m", h, m),
+    }
+}
+
+/// Receives the progress of a run. Called from the thread running the stage, so a reporter that
+/// does more than record the update should hand it off rather than block.
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, update: &ProgressUpdate);
+}
+
+impl<F: Fn(&ProgressUpdate) + Send + Sync> ProgressReporter for F {
+    fn report(&self, update: &ProgressUpdate) {
+        self(update)
+    }
+}
+
+/// Logs updates at info level: the first of each stage, its last, and one every `interval`
+/// between them.
+#[derive(Debug)]
+pub struct ConsoleReporter {
+    interval: Duration,
+    last: Mutex<Option<(Stage, Instant)>>,
+}
+
+impl ConsoleReporter {
+    pub fn new(interval: Duration) -> Self {
+        ConsoleReporter { interval, last: Mutex::new(None) }
+    }
+}
+
+impl Default for ConsoleReporter {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPORT_INTERVAL)
+    }
+}
+
+impl ProgressReporter for ConsoleReporter {
+    fn report(&self, update: &ProgressUpdate) {
+        let mut last = self.last.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let due = match *last {
+            Some((stage, at)) => stage != update.stage || at.elapsed() >= self.interval || update.is_finished(),
+            None => true,
+        };
+        if due {
+            *last = Some((update.stage, Instant::now()));
+            info!("{}", update);
+        }
+    }
+}
+
+/// The reporter a run sends its progress to, shared by every copy of the run.
+#[derive(Clone)]
+pub struct ProgressHandle(Arc<dyn ProgressReporter>);
+
+impl ProgressHandle {
+    pub fn new(reporter: impl ProgressReporter + 'static) -> Self {
+        ProgressHandle(Arc::new(reporter))
+    }
+
+    pub fn report(&self, update: &ProgressUpdate) {
+        self.0.report(update);
+    }
+
+    /// Starts timing a part of `stage` that counts in `unit` from `initial` up to `total`.
+    pub fn tracker(&self, stage: Stage, unit: &'static str, initial: u64, total: u64) -> ProgressTracker {
+        ProgressTracker { handle: self.clone(), stage, unit, initial, total, started: Instant::now() }
+    }
+}
+
+impl Default for ProgressHandle {
+    fn default() -> Self {
+        Self::new(ConsoleReporter::default())
+    }
+}
+
+impl std::fmt::Debug for ProgressHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("ProgressHandle")
+    }
+}
+
+/// Turns the counts of one stage into timed updates for its reporter.
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    handle: ProgressHandle,
+    stage: Stage,
+    unit: &'static str,
+    initial: u64,
+    total: u64,
+    started: Instant,
+}
+
+impl ProgressTracker {
+    /// Moves the finish line, as when the relation target is raised or a solver learns how many
+    /// iterations it has left.
+    pub fn set_total(&mut self, total: u64) {
+        self.total = total;
+    }
+
+    /// Reports `completed` units done.
+    pub fn update(&self, completed: u64) {
+        self.handle.report(&ProgressUpdate {
+            stage: self.stage,
+            unit: self.unit,
+            completed,
+            total: self.total,
+            initial: self.initial,
+            elapsed: self.started.elapsed(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_carry_rate_and_eta_to_registered_reporters() {
+        let update = ProgressUpdate { stage: Stage::Sieve, unit: "relations", completed: 300, total: 500, initial: 100, elapsed: Duration::from_secs(20) };
+        assert_eq!(update.fraction(), 0.6);
+        assert_eq!(update.rate(), 10.0);
+        assert_eq!(update.eta(), Some(Duration::from_secs(20)));
+        assert_eq!(update.to_string(), "sieve: 300/500 relations (60.0%), 10.0 relations/s, ETA 20s");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1h 02m");
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let handle = ProgressHandle::new(move |update: &ProgressUpdate| sink.lock().unwrap().push((update.completed, update.total)));
+        let mut tracker = handle.tracker(Stage::Matrix, "columns", 0, 64);
+        tracker.update(32);
+        tracker.set_total(128);
+        tracker.update(128);
+        assert_eq!(*received.lock().unwrap(), [(32, 64), (128, 128)]);
+    }
+}
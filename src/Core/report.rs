@@ -0,0 +1,134 @@
+// src/core/report.rs
+
+//! A structured account of a factorization for scripts: the input, the method, the factors found
+//! and what each stage recorded in the run metadata along the way.
+
+use num::BigInt;
+use serde::{Serialize, Deserialize};
+use crate::core::error::GnfsError;
+use crate::core::factorizer::FactorizationResult;
+use crate::core::solution::SolutionParameters;
+use crate::core::stage::StageOutcome;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FactorizationReport {
+    pub n: String,
+    /// "trial division", "GNFS", "factordb", or the method the run was restricted to.
+    pub algorithm: String,
+    /// Prime factors in ascending order, each repeated per its exponent; a cofactor not yet split
+    /// is listed as it is.
+    pub factors: Vec<String>,
+    /// True when every entry of `factors` is prime.
+    pub complete: bool,
+    pub outcome: StageOutcome,
+    /// Stages in the order they first ran.
+    pub stages: Vec<StageReport>,
+    pub total_seconds: f64,
+    pub smooth_relations: usize,
+    pub rough_relations: usize,
+    /// None until the matrix stage has run.
+    pub matrix: Option<MatrixReport>,
+    /// The GNFS parameters, when GNFS ran.
+    pub parameters: Option<SolutionParameters>,
+    pub save_directory: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageReport {
+    pub stage: String,
+    pub seconds: f64,
+    /// Peak resident memory in bytes, where the platform reports it.
+    pub peak_memory: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatrixReport {
+    pub rows: usize,
+    pub cols: usize,
+    pub rank: usize,
+    pub nullity: usize,
+}
+
+impl FactorizationReport {
+    pub fn new(result: &FactorizationResult) -> Self {
+        let metadata = &result.metadata;
+        let stages = metadata
+            .stage_timings
+            .iter()
+            .map(|(stage, seconds)| StageReport {
+                stage: stage.clone(),
+                seconds: *seconds,
+                peak_memory: metadata.stage_peak_memory.iter().find(|(name, _)| name == stage).map(|(_, bytes)| *bytes),
+            })
+            .collect();
+        let matrix = (metadata.matrix_rows > 0).then_some(MatrixReport {
+            rows: metadata.matrix_rows,
+            cols: metadata.matrix_cols,
+            rank: metadata.matrix_rank,
+            nullity: metadata.matrix_nullity,
+        });
+        FactorizationReport {
+            n: result.n.to_string(),
+            algorithm: metadata.algorithm.clone(),
+            factors: result.factors().iter().map(BigInt::to_string).collect(),
+            complete: result.is_complete(),
+            outcome: result.outcome,
+            stages,
+            total_seconds: metadata.total_seconds(),
+            smooth_relations: metadata.smooth_relation_count,
+            rough_relations: metadata.rough_relation_count,
+            matrix,
+            parameters: result.parameters.clone(),
+            save_directory: result.save_directory.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, GnfsError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::input;
+
+    fn report(n: u64, limit: u64) -> FactorizationReport {
+        let input = input::normalize(&BigInt::from(n), limit).unwrap();
+        let mut result = FactorizationResult::for_input(&input);
+        result.metadata.stage_timings = vec![("sieve".to_string(), 2.5), ("matrix".to_string(), 0.5)];
+        result.metadata.stage_peak_memory = vec![("matrix".to_string(), 4096)];
+        FactorizationReport::new(&result)
+    }
+
+    #[test]
+    fn lists_factors_stages_and_an_unsplit_cofactor() {
+        // 4·45113²: trial division finds 2², leaving the composite 45113 squared.
+        let unsplit = report(4 * 45113 * 45113, 100);
+        assert_eq!(unsplit.factors, vec!["2", "2", "45113", "45113"]);
+        assert!(!unsplit.complete);
+        assert_eq!(unsplit.algorithm, "trial division");
+        assert_eq!(
+            unsplit.stages,
+            vec![
+                StageReport { stage: "sieve".to_string(), seconds: 2.5, peak_memory: None },
+                StageReport { stage: "matrix".to_string(), seconds: 0.5, peak_memory: Some(4096) },
+            ]
+        );
+        assert_eq!(unsplit.total_seconds, 3.0);
+        assert_eq!(unsplit.matrix, None);
+
+        let complete = report(12, 100);
+        assert_eq!(complete.factors, vec!["2", "2", "3"]);
+        assert!(complete.complete);
+    }
+
+    #[test]
+    fn json_round_trips_with_kebab_case_outcome() {
+        let report = report(4 * 45113 * 45113, 100);
+        let json = report.to_json().unwrap();
+        assert!(json.contains(r#""outcome": "completed""#), "{}", json);
+        assert!(json.contains(r#""matrix": null"#), "{}", json);
+        assert_eq!(serde_json::from_str::<FactorizationReport>(&json).unwrap(), report);
+    }
+}
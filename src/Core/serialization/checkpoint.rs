@@ -0,0 +1,278 @@
+// src/core/serialization/checkpoint.rs
+
+//! The checkpoint manifest: which files make up a saved run, their sizes and checksums, and the N
+//! and polynomial they belong to. It is rewritten whenever the pipeline saves, and `gnfs resume`
+//! checks it before loading anything, so a truncated or mixed-up save directory is reported up
+//! front instead of surfacing as a panic halfway through deserialization.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use num::Zero;
+use serde::{Serialize, Deserialize};
+use crate::core::error::GnfsError;
+use crate::core::gnfs::GNFS;
+use crate::core::stage::Stage;
+use crate::distributed::work_unit::WORK_UNITS_FILENAME;
+use crate::polynomial::polynomial::Polynomial;
+use crate::relation_sieve::rough_store::{ROUGH_INDEX_FILENAME, ROUGH_STORE_FILENAME};
+
+pub const MANIFEST_FILENAME: &str = "checkpoint.json";
+
+/// Files that only ever grow between checkpoints; a longer file whose recorded prefix still
+/// matches is accepted.
+const APPEND_ONLY_FILES: [&str; 2] = ["SmoothRelations.json", ROUGH_STORE_FILENAME];
+
+/// FNV-1a, 64-bit. Detects truncation and corruption; it isn't meant to resist tampering.
+pub fn checksum(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileRecord {
+    pub bytes: u64,
+    pub checksum: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointManifest {
+    pub n: String,
+    pub polynomial: String,
+    pub polynomial_base: String,
+    /// File name within the save directory to its size and checksum.
+    pub files: BTreeMap<String, FileRecord>,
+}
+
+impl CheckpointManifest {
+    /// Records every checkpoint file currently in the run's save directory.
+    pub fn capture(gnfs: &GNFS) -> Result<Self, GnfsError> {
+        let directory = Path::new(&gnfs.save_locations.save_directory);
+        let mut files = BTreeMap::new();
+        for entry in fs::read_dir(directory)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if !Self::is_checkpoint_file(&name) {
+                continue;
+            }
+            let contents = fs::read(directory.join(&name))?;
+            files.insert(name, FileRecord { bytes: contents.len() as u64, checksum: checksum(&contents) });
+        }
+        Ok(CheckpointManifest {
+            n: gnfs.n.to_string(),
+            polynomial: gnfs.current_polynomial.to_string(),
+            polynomial_base: gnfs.polynomial_base.to_string(),
+            files,
+        })
+    }
+
+    fn is_checkpoint_file(name: &str) -> bool {
+        matches!(
+            name,
+            "GNFS.json"
+                | "RationalFactorPairCollection.json"
+                | "AlgebraicFactorPairCollection.json"
+                | "QuadraticFactorPairCollection.json"
+                | "SmoothRelations.json"
+                | "RoughRelations.json"
+                | "SquareRootProgress.json"
+        ) || name == ROUGH_STORE_FILENAME
+            || name == ROUGH_INDEX_FILENAME
+            || name == WORK_UNITS_FILENAME
+            || name.starts_with("Polynomial.")
+    }
+
+    pub fn write(&self, directory: &Path) -> Result<(), GnfsError> {
+        fs::write(directory.join(MANIFEST_FILENAME), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn read(directory: &Path) -> Result<Self, GnfsError> {
+        let path = directory.join(MANIFEST_FILENAME);
+        if !path.exists() {
+            return Err(GnfsError::InvalidParameter(format!("{} has no {}; nothing to resume", directory.display(), MANIFEST_FILENAME)));
+        }
+        let manifest: CheckpointManifest = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if !manifest.files.contains_key("GNFS.json") {
+            return Err(GnfsError::InvalidParameter(format!("{} does not record the run parameters (GNFS.json)", MANIFEST_FILENAME)));
+        }
+        Ok(manifest)
+    }
+
+    /// Checks each recorded file against its size and checksum and returns one line per problem.
+    pub fn verify_files(&self, directory: &Path) -> Result<Vec<String>, GnfsError> {
+        let mut problems = Vec::new();
+        for (name, record) in &self.files {
+            let path = directory.join(name);
+            if !path.exists() {
+                problems.push(format!("{} is missing", name));
+                continue;
+            }
+            let contents = fs::read(&path)?;
+            let length = contents.len() as u64;
+            let appended = APPEND_ONLY_FILES.contains(&name.as_str()) && length > record.bytes;
+            let compared = if appended { &contents[..record.bytes as usize] } else { &contents[..] };
+            if length < record.bytes || (!appended && length != record.bytes) {
+                problems.push(format!("{} is {} bytes, expected {}", name, length, record.bytes));
+            } else if checksum(compared) != record.checksum {
+                problems.push(format!("{} does not match its checksum", name));
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Confirms the loaded run is the one the manifest describes and that both its polynomials
+    /// still have m as a root modulo N.
+    pub fn verify_parameters(&self, gnfs: &GNFS) -> Result<(), GnfsError> {
+        let mismatch = |what: &str, stored: &str, loaded: String| {
+            GnfsError::InvalidParameter(format!("checkpoint {} is {}, but the saved parameters give {}", what, stored, loaded))
+        };
+        if self.n != gnfs.n.to_string() {
+            return Err(mismatch("N", &self.n, gnfs.n.to_string()));
+        }
+        // Terms print in hash order, so the polynomial is compared parsed rather than as text.
+        if Polynomial::try_parse(&self.polynomial)? != gnfs.current_polynomial {
+            return Err(mismatch("polynomial", &self.polynomial, gnfs.current_polynomial.to_string()));
+        }
+        if self.polynomial_base != gnfs.polynomial_base.to_string() {
+            return Err(mismatch("polynomial base", &self.polynomial_base, gnfs.polynomial_base.to_string()));
+        }
+        if !(gnfs.current_polynomial.evaluate(&gnfs.polynomial_base) % &gnfs.n).is_zero() {
+            return Err(GnfsError::InvalidParameter(format!("ƒ(m) is not divisible by N for ƒ = {}", gnfs.current_polynomial)));
+        }
+        if !(gnfs.rational_polynomial.evaluate(&gnfs.polynomial_base) % &gnfs.n).is_zero() {
+            return Err(GnfsError::InvalidParameter(format!("g(m) is not divisible by N for g = {}", gnfs.rational_polynomial)));
+        }
+        Ok(())
+    }
+}
+
+/// Where a loaded run picks up and what is left to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumePlan {
+    /// None when the run already has its factors.
+    pub stage: Option<Stage>,
+    pub smooth_relations: usize,
+    pub target_quantity: usize,
+    pub dependencies: usize,
+}
+
+impl ResumePlan {
+    pub fn for_run(gnfs: &GNFS) -> Self {
+        let progress = &gnfs.current_relations_progress;
+        let dependencies = progress.relations.free_relations.len();
+        let stage = if gnfs.factorization.is_some() {
+            None
+        } else if dependencies > 0 {
+            Some(Stage::SquareRoot)
+        } else if progress.smooth_relations_counter >= progress.smooth_relations_target_quantity {
+            Some(Stage::Matrix)
+        } else {
+            Some(Stage::Sieve)
+        };
+        ResumePlan {
+            stage,
+            smooth_relations: progress.smooth_relations_counter,
+            target_quantity: progress.smooth_relations_target_quantity,
+            dependencies,
+        }
+    }
+}
+
+impl Display for ResumePlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.stage {
+            None => write!(f, "the run is already complete"),
+            Some(Stage::Sieve) => write!(
+                f,
+                "resuming the sieve: {} of {} smooth relations, {} still needed",
+                self.smooth_relations,
+                self.target_quantity,
+                self.target_quantity - self.smooth_relations
+            ),
+            Some(Stage::Matrix) => write!(f, "resuming at the matrix step with {} smooth relations", self.smooth_relations),
+            Some(Stage::SquareRoot) => write!(f, "resuming the square root with {} saved dependencies", self.dependencies),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+    use super::*;
+    use crate::core::cancellation_token::CancellationToken;
+    use crate::core::gnfs_config::GnfsConfig;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::serialization::load;
+
+    fn saved_run(name: &str) -> GNFS {
+        let locations = DirectoryLocations::temporary(name).unwrap();
+        let (n, m) = (BigInt::from(45113), BigInt::from(31));
+        GNFS::with_save_locations(&CancellationToken::new(), locations, GnfsConfig::default(), &n, &m, 3, &BigInt::from(100), 65, 200, true).unwrap()
+    }
+
+    #[test]
+    fn a_fresh_checkpoint_verifies_and_resumes_at_the_sieve() {
+        let gnfs = saved_run("checkpoint_fresh");
+        let directory = Path::new(&gnfs.save_locations.save_directory);
+        let manifest = CheckpointManifest::read(directory).unwrap();
+        assert!(manifest.files.contains_key("GNFS.json"));
+        assert_eq!(manifest.verify_files(directory).unwrap(), Vec::<String>::new());
+
+        let loaded = load::all(&gnfs.save_locations.parameters_filepath);
+        manifest.verify_parameters(&loaded).unwrap();
+        let plan = ResumePlan::for_run(&loaded);
+        assert_eq!(plan.stage, Some(Stage::Sieve));
+        assert_eq!(plan.to_string(), format!("resuming the sieve: 0 of {0} smooth relations, {0} still needed", plan.target_quantity));
+    }
+
+    #[test]
+    fn reports_truncated_corrupted_and_missing_files() {
+        let gnfs = saved_run("checkpoint_damaged");
+        let directory = Path::new(&gnfs.save_locations.save_directory);
+        let mut manifest = CheckpointManifest::read(directory).unwrap();
+        let parameters = directory.join("GNFS.json");
+        let contents = fs::read(&parameters).unwrap();
+
+        // Append-only files may grow past what was recorded; other files may not.
+        manifest.files.insert("SmoothRelations.json".to_string(), FileRecord { bytes: 2, checksum: checksum(b"[\n") });
+        fs::write(directory.join("SmoothRelations.json"), "[\n{}]").unwrap();
+        fs::write(&parameters, [&contents[..], b" "].concat()).unwrap();
+        assert_eq!(manifest.verify_files(directory).unwrap(), vec![format!("GNFS.json is {} bytes, expected {}", contents.len() + 1, contents.len())]);
+
+        let mut corrupted = contents.clone();
+        corrupted[0] ^= 1;
+        fs::write(&parameters, &corrupted).unwrap();
+        assert_eq!(manifest.verify_files(directory).unwrap(), vec!["GNFS.json does not match its checksum".to_string()]);
+
+        fs::remove_file(&parameters).unwrap();
+        assert_eq!(manifest.verify_files(directory).unwrap(), vec!["GNFS.json is missing".to_string()]);
+    }
+
+    #[test]
+    fn rejects_a_run_other_than_the_one_recorded() {
+        let gnfs = saved_run("checkpoint_mismatch");
+        let manifest = CheckpointManifest::read(Path::new(&gnfs.save_locations.save_directory)).unwrap();
+
+        let mut other_n = manifest.clone();
+        other_n.n = "45119".to_string();
+        assert!(matches!(other_n.verify_parameters(&gnfs), Err(GnfsError::InvalidParameter(message)) if message.contains("checkpoint N is 45119")));
+
+        let mut other_base = manifest.clone();
+        other_base.polynomial_base = "32".to_string();
+        assert!(other_base.verify_parameters(&gnfs).is_err());
+
+        let mut wrong_root = gnfs.clone();
+        wrong_root.n = BigInt::from(45119);
+        let mut relabelled = manifest.clone();
+        relabelled.n = "45119".to_string();
+        assert!(matches!(relabelled.verify_parameters(&wrong_root), Err(GnfsError::InvalidParameter(message)) if message.contains("not divisible by N")));
+
+        let empty = DirectoryLocations::temporary("checkpoint_empty").unwrap();
+        assert!(CheckpointManifest::read(Path::new(&empty.save_directory)).is_err());
+    }
+}
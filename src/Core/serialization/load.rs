@@ -3,18 +3,23 @@
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
+use log::warn;
 use crate::core::directory_location::DirectoryLocations;
 use crate::polynomial::polynomial::Polynomial;
 use serde_json;
+use crate::relation_sieve::compression;
 use crate::relation_sieve::relation::Relation;
 use crate::factor::factor_pair_collection::FactorPairCollection;
 use crate::core::gnfs::GNFS;
+use crate::core::cancellation_token::CancellationToken;
 use crate::core::serialization::save;
 use crate::core::serialization::load;
+use crate::core::serialization::migration;
 use crate::core::serialization::types::{
     SerializableGNFS, SerializablePolynomial, SerializableFactorPairCollection,
-    SerializableRelation,
+    SerializableRelation, SerializableSquareRootProgress,
 };
+use crate::square_root::square_finder::SquareRootProgress;
 
 pub fn generic<T: serde::de::DeserializeOwned>(filename: &str) -> T {
     let load_json = fs::read_to_string(filename).expect("Failed to read file");
@@ -27,19 +32,45 @@ pub fn generic_fixed_array<T: serde::de::DeserializeOwned>(filename: &str) -> T
     serde_json::from_str(&fixed_json).expect("Failed to deserialize JSON")
 }
 
+/// Relation files are written as comma-separated records without brackets so they can be appended
+/// to. Older builds bracketed the first record (`[{..}],{..}`) and whole-file writers produce a
+/// regular array; both are flattened back into one array. Relation records contain no arrays, so
+/// the first `]` is the stray closing bracket.
 fn fix_appended_json_arrays(input: &str) -> String {
-    format!("[{}]", input.trim_start_matches(','))
+    let body = input.trim().trim_start_matches(',');
+    match body.strip_prefix('[') {
+        Some(rest) => format!("[{}]", rest.replacen(']', "", 1)),
+        None => format!("[{}]", body),
+    }
+}
+
+/// Loads a relation file, compressed or not, upgrading records written by older versions.
+pub fn relation_array(filename: &str) -> Vec<SerializableRelation> {
+    let bytes = compression::read(Path::new(filename)).expect("Failed to read file");
+    let load_json = String::from_utf8(bytes).expect("Relation file is not UTF-8");
+    let records: Vec<serde_json::Value> = serde_json::from_str(&fix_appended_json_arrays(&load_json)).expect("Failed to deserialize JSON");
+    records
+        .into_iter()
+        .map(|record| {
+            let record = migration::relation(record).expect("Failed to migrate relation");
+            serde_json::from_value(record).expect("Failed to deserialize relation")
+        })
+        .collect()
 }
 
 pub fn all(filename: &str) -> GNFS {
     let load_json = fs::read_to_string(filename).expect("Failed to read file");
-    let serializable_gnfs: SerializableGNFS = serde_json::from_str(&load_json).expect("Failed to deserialize GNFS");
+    let raw_gnfs: serde_json::Value = serde_json::from_str(&load_json).expect("Failed to parse GNFS");
+    let raw_gnfs = migration::gnfs(raw_gnfs).expect("Failed to migrate GNFS parameters");
+    let serializable_gnfs: SerializableGNFS = serde_json::from_value(raw_gnfs).expect("Failed to deserialize GNFS");
     let mut gnfs = GNFS::from(serializable_gnfs);
 
     let directory_name = Path::new(filename).parent().unwrap();
     let directory_str = directory_name.to_str().expect("Failed to convert path to string");
     gnfs.save_locations = DirectoryLocations::new(directory_str);
 
+    // The polynomial files are authoritative; GNFS.json carries a copy of the collection too.
+    gnfs.polynomial_collection.clear();
     let mut counter = 0;
     let mut finished = false;
     while !finished {
@@ -60,6 +91,12 @@ pub fn all(filename: &str) -> GNFS {
     load::factor_pair::rational(&mut gnfs);
     load::factor_pair::algebraic(&mut gnfs);
     load::factor_pair::quadratic(&mut gnfs);
+    if gnfs.rational_factor_pair_collection.0.is_empty() {
+        // Older builds never wrote the factor pair files; they depend only on the parameters
+        // already loaded, so rebuild them.
+        warn!("{} has no factor pair collections; rebuilding them.", directory_str);
+        gnfs.new_factor_pair_collections(&CancellationToken::new());
+    }
 
     gnfs.current_relations_progress.gnfs = Arc::downgrade(&Arc::new(gnfs.clone()));
 
@@ -76,6 +113,15 @@ pub fn polynomial(filename: &str) -> Polynomial {
     Polynomial::from(serializable_poly)
 }
 
+pub fn square_root_progress(gnfs: &GNFS) -> Option<SquareRootProgress> {
+    let filepath = gnfs.save_locations.square_root_progress_filepath();
+    if gnfs.save_locations.save_directory.is_empty() || !Path::new(&filepath).exists() {
+        return None;
+    }
+    let serializable_progress: SerializableSquareRootProgress = load::generic(&filepath);
+    Some(SquareRootProgress::from(serializable_progress))
+}
+
 pub fn factor_base(gnfs: &mut GNFS) {
     gnfs.set_prime_factor_bases();
 }
@@ -110,7 +156,12 @@ pub mod relations {
 
     pub fn smooth(gnfs: &mut GNFS) {
         if Path::new(&gnfs.save_locations.smooth_relations_filepath).exists() {
-            let mut temp: Vec<SerializableRelation> = load::generic_fixed_array(&gnfs.save_locations.smooth_relations_filepath);
+            match compression::repair(Path::new(&gnfs.save_locations.smooth_relations_filepath)) {
+                Ok(0) => {}
+                Ok(removed) => warn!("Removed {} bytes of an interrupted write from the smooth relations file.", removed),
+                Err(e) => warn!("Unable to check the smooth relations file for an interrupted write: {}", e),
+            }
+            let mut temp = load::relation_array(&gnfs.save_locations.smooth_relations_filepath);
             
             // Filter out relations where any field is empty
             temp.retain(|rel| 
@@ -119,7 +170,8 @@ pub mod relations {
             
             let mut relations: Vec<Relation> = temp.into_iter().map(|rel| Relation::from(rel)).collect();
             relations.iter_mut().for_each(|rel| rel.is_persisted = true);
-            gnfs.current_relations_progress.smooth_relations_counter = relations.len();
+            // Combined relations are saved with the sieve progress and count as one relation each.
+            gnfs.current_relations_progress.smooth_relations_counter = relations.len() + gnfs.current_relations_progress.relations.combined_relations.len();
             gnfs.current_relations_progress.relations.smooth_relations = relations;
         }
     }
@@ -127,7 +179,7 @@ pub mod relations {
 
     pub fn rough(gnfs: &mut GNFS) {
         if Path::new(&gnfs.save_locations.rough_relations_filepath).exists() {
-            let temp: Vec<SerializableRelation> = load::generic_fixed_array(&gnfs.save_locations.rough_relations_filepath);
+            let temp = load::relation_array(&gnfs.save_locations.rough_relations_filepath);
             let mut relations: Vec<Relation> = temp.into_iter().map(|rel| Relation::from(rel)).collect();
             relations.iter_mut().for_each(|rel| rel.is_persisted = true);
             gnfs.current_relations_progress.relations.rough_relations = relations;
@@ -0,0 +1,127 @@
+// src/core/serialization/migration.rs
+
+//! Upgrades relation records and sieve progress written by older builds. Each saved record carries
+//! a `version`; records without one predate versioning and are treated as version 0. Loaders run
+//! the raw JSON through these converters before deserializing, so adding a field only needs a new
+//! step here rather than orphaning existing checkpoints.
+
+use num::BigInt;
+use serde_json::{Map, Value};
+use std::str::FromStr;
+use crate::core::count_dictionary::CountDictionary;
+use crate::core::error::GnfsError;
+
+/// Version written with every relation record.
+pub const RELATION_FORMAT_VERSION: u32 = 1;
+/// Version written with the sieve progress.
+pub const PROGRESS_FORMAT_VERSION: u32 = 1;
+
+fn version_of(value: &Value) -> u32 {
+    value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+fn as_object<'a>(value: &'a mut Value, what: &str) -> Result<&'a mut Map<String, Value>, GnfsError> {
+    value.as_object_mut().ok_or_else(|| GnfsError::InvalidParameter(format!("{} record is not a JSON object", what)))
+}
+
+fn check_supported(version: u32, current: u32, what: &str) -> Result<(), GnfsError> {
+    if version > current {
+        return Err(GnfsError::InvalidParameter(format!(
+            "{} format version {} is newer than the supported version {}",
+            what, version, current
+        )));
+    }
+    Ok(())
+}
+
+/// Brings a relation record up to [`RELATION_FORMAT_VERSION`].
+pub fn relation(mut value: Value) -> Result<Value, GnfsError> {
+    let version = version_of(&value);
+    check_supported(version, RELATION_FORMAT_VERSION, "Relation")?;
+
+    if version < 1 {
+        relation_v0_to_v1(as_object(&mut value, "Relation")?)?;
+    }
+    Ok(value)
+}
+
+/// Brings the sieve progress up to [`PROGRESS_FORMAT_VERSION`].
+pub fn progress(mut value: Value) -> Result<Value, GnfsError> {
+    let version = version_of(&value);
+    check_supported(version, PROGRESS_FORMAT_VERSION, "Sieve progress")?;
+
+    if version < 1 {
+        progress_v0_to_v1(as_object(&mut value, "Sieve progress")?)?;
+    }
+    Ok(value)
+}
+
+/// Migrates the progress nested in a saved GNFS parameters file.
+pub fn gnfs(mut value: Value) -> Result<Value, GnfsError> {
+    let object = as_object(&mut value, "GNFS")?;
+    if let Some(progress_value) = object.remove("current_relations_progress") {
+        object.insert("current_relations_progress".to_string(), progress(progress_value)?);
+    }
+    Ok(value)
+}
+
+/// Version 0 stored factorizations as `{"prime": "exponent"}` maps; version 1 uses the compact
+/// `2^3*5` string.
+fn relation_v0_to_v1(record: &mut Map<String, Value>) -> Result<(), GnfsError> {
+    for field in ["algebraic_factorization", "rational_factorization"] {
+        if let Some(Value::Object(map)) = record.get(field) {
+            let mut dict = CountDictionary::new();
+            for (prime, exponent) in map {
+                let prime = BigInt::from_str(prime)
+                    .map_err(|e| GnfsError::InvalidParameter(format!("{} prime {}: {}", field, prime, e)))?;
+                let exponent = match exponent {
+                    Value::String(text) => text.parse::<u32>().ok(),
+                    Value::Number(number) => number.as_u64().map(|n| n as u32),
+                    _ => None,
+                }
+                .ok_or_else(|| GnfsError::InvalidParameter(format!("{} exponent {}", field, exponent)))?;
+                dict.add_count(&prime, exponent);
+            }
+            record.insert(field.to_string(), Value::String(dict.to_compact_string()));
+        }
+    }
+    record.insert("version".to_string(), Value::from(1));
+    Ok(())
+}
+
+/// Version 0 had no lattice region bookkeeping; resume from the saved position, treating the rows
+/// before it as done.
+fn progress_v0_to_v1(record: &mut Map<String, Value>) -> Result<(), GnfsError> {
+    if !record.contains_key("region") {
+        let field = |name: &str| record.get(name).and_then(Value::as_str).unwrap_or("0").to_string();
+        let region = serde_json::json!({
+            "min_abs_a": "1",
+            "max_abs_a": field("value_range"),
+            "min_b": field("b"),
+            "max_b": field("max_b"),
+        });
+        record.insert("region".to_string(), region);
+    }
+    record.entry("sieved_regions").or_insert_with(|| Value::Array(Vec::new()));
+    record.entry("pending_regions").or_insert_with(|| Value::Array(Vec::new()));
+    record.insert("version".to_string(), Value::from(1));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_relation_factorizations_become_compact() {
+        let legacy = serde_json::json!({
+            "a": "3", "b": "1",
+            "algebraic_factorization": { "2": "3", "5": "1" },
+            "rational_factorization": {},
+        });
+        let migrated = relation(legacy).unwrap();
+        assert_eq!(migrated["version"], RELATION_FORMAT_VERSION);
+        assert_eq!(migrated["algebraic_factorization"], "2^3*5");
+        assert!(relation(serde_json::json!({ "version": RELATION_FORMAT_VERSION + 1 })).is_err());
+    }
+}
@@ -1,6 +1,8 @@
 // src/core/serialization/mod.rs
 
+pub mod checkpoint;
 pub mod json_converter;
 pub mod load;
+pub mod migration;
 pub mod save;
 pub mod types;
\ No newline at end of file
@@ -3,15 +3,22 @@
 use std::fs;
 use std::path::Path;
 use std::io::Write;
+use log::warn;
 use serde::Serialize;
 use serde_json;
 use crate::core::gnfs::GNFS;
 use crate::core::serialization::save;
+use crate::relation_sieve::compression;
 use crate::relation_sieve::relation::Relation;
 use crate::core::serialization::types::{
     SerializableGNFS, SerializablePolynomial, SerializableFactorPairCollection,
-    SerializableRelation
+    SerializableRelation, SerializableSolution, SerializableSquareRootProgress
 };
+use crate::square_root::square_finder::SquareRootProgress;
+use crate::core::serialization::checkpoint::CheckpointManifest;
+
+pub const SOLUTION_TEXT_FILENAME: &str = "Solution.txt";
+pub const SOLUTION_JSON_FILENAME: &str = "solution.json";
 
 pub fn object<T: Serialize>(obj: &T, filename: &str) {
     let save_json = serde_json::to_string_pretty(obj).expect("Failed to serialize object");
@@ -20,14 +27,7 @@ pub fn object<T: Serialize>(obj: &T, filename: &str) {
 
 pub fn all(gnfs: &GNFS) {
     save::gnfs(gnfs);
-
-    let mut counter = 1;
-    for poly in &gnfs.polynomial_collection {
-        let filename = format!("Polynomial.{:02}", counter);
-        let serializable_poly = SerializablePolynomial::from(poly.clone());
-        save::object(&serializable_poly, &format!("{}/{}", gnfs.save_locations.save_directory, filename));
-        counter += 1;
-    }
+    save::polynomials(gnfs);
 
     save::factor_pair::rational(gnfs);
     save::factor_pair::algebraic(gnfs);
@@ -35,34 +35,91 @@ pub fn all(gnfs: &GNFS) {
 
     let gnfs = &mut gnfs.clone();
     save::relations::smooth::append(gnfs);
-    save::relations::rough::append(gnfs);
+    save::relations::rough::all(gnfs);
     save::relations::free::all_solutions(gnfs);
+    save::checkpoint_manifest(gnfs);
+}
+
+/// Writes `Polynomial.01`, `Polynomial.02`, ... in collection order; the first is the current polynomial.
+pub fn polynomials(gnfs: &GNFS) {
+    if gnfs.save_locations.save_directory.is_empty() {
+        return;
+    }
+    for (counter, poly) in (1..).zip(&gnfs.polynomial_collection) {
+        let filename = format!("Polynomial.{:02}", counter);
+        let serializable_poly = SerializablePolynomial::from(poly.clone());
+        save::object(&serializable_poly, &format!("{}/{}", gnfs.save_locations.save_directory, filename));
+    }
+}
+
+/// Rewrites `checkpoint.json` to match the files now in the save directory.
+pub fn checkpoint_manifest(gnfs: &GNFS) {
+    if gnfs.save_locations.save_directory.is_empty() {
+        return;
+    }
+    let manifest = CheckpointManifest::capture(gnfs).expect("Failed to read checkpoint files");
+    manifest.write(Path::new(&gnfs.save_locations.save_directory)).expect("Failed to write checkpoint manifest");
 }
 
 pub fn gnfs(gnfs: &GNFS) {
+    if gnfs.save_locations.save_directory.is_empty() {
+        return;
+    }
     let serializable_gnfs = SerializableGNFS::from(gnfs.clone());
     save::object(&serializable_gnfs, &gnfs.save_locations.parameters_filepath);
 }
 
+/// Writes `contents` beside `path` and renames it into place, so readers never see a partial file.
+pub fn atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(".tmp");
+    fs::write(&staging, contents)?;
+    fs::rename(&staging, path)
+}
+
+/// Writes `Solution.txt` and `solution.json` to the save directory once a factorization is known.
+/// Both are replaced atomically, so a crash mid-write leaves the previous copy or none.
+pub fn solution(gnfs: &GNFS) {
+    if let Some(solution) = &gnfs.factorization {
+        if gnfs.save_locations.save_directory.is_empty() {
+            return;
+        }
+        let directory = Path::new(&gnfs.save_locations.save_directory);
+        let serializable_solution = SerializableSolution::from(solution.clone());
+        let json = serde_json::to_string_pretty(&serializable_solution).expect("Failed to serialize solution");
+        save::atomic(&directory.join(SOLUTION_JSON_FILENAME), json.as_bytes()).expect("Failed to write solution.json");
+        save::atomic(&directory.join(SOLUTION_TEXT_FILENAME), solution.report().as_bytes()).expect("Failed to write Solution.txt");
+    }
+}
+
+/// Records which dependencies the square root stage has tried so a restart can skip them.
+pub fn square_root_progress(gnfs: &GNFS, progress: &SquareRootProgress) {
+    if gnfs.save_locations.save_directory.is_empty() {
+        return;
+    }
+    let serializable_progress = SerializableSquareRootProgress::from(progress.clone());
+    save::object(&serializable_progress, &gnfs.save_locations.square_root_progress_filepath());
+}
+
 pub mod factor_pair {
     use super::*;
 
     pub fn rational(gnfs: &GNFS) {
-        if !gnfs.rational_factor_pair_collection.len() == 0 {
+        if !gnfs.save_locations.save_directory.is_empty() && !gnfs.rational_factor_pair_collection.0.is_empty() {
             let serializable_collection = SerializableFactorPairCollection::from(gnfs.rational_factor_pair_collection.clone());
             save::object(&serializable_collection, &gnfs.save_locations.rational_factor_pair_filepath);
         }
     }
 
     pub fn algebraic(gnfs: &GNFS) {
-        if !gnfs.algebraic_factor_pair_collection.len() == 0 {
+        if !gnfs.save_locations.save_directory.is_empty() && !gnfs.algebraic_factor_pair_collection.0.is_empty() {
             let serializable_collection = SerializableFactorPairCollection::from(gnfs.algebraic_factor_pair_collection.clone());
             save::object(&serializable_collection, &gnfs.save_locations.algebraic_factor_pair_filepath);
         }
     }
 
     pub fn quadratic(gnfs: &GNFS) {
-        if !gnfs.quadratic_factor_pair_collection.len() == 0 {
+        if !gnfs.save_locations.save_directory.is_empty() && !gnfs.quadratic_factor_pair_collection.0.is_empty() {
             let serializable_collection = SerializableFactorPairCollection::from(gnfs.quadratic_factor_pair_collection.clone());
             save::object(&serializable_collection, &gnfs.save_locations.quadratic_factor_pair_filepath);
         }
@@ -75,105 +132,44 @@ pub mod relations {
     pub mod smooth {
         use super::*;
 
+        /// Appends the smooth relations not yet saved to the smooth relations file in one write,
+        /// compressed when the file is; see [`compression::append`].
         pub fn append(gnfs: &mut GNFS) {
-            let mut relations_to_update = Vec::new();
-            let mut smooth_relations = Vec::new();
-        
-            // Extract the smooth relations into a separate vector
-            std::mem::swap(&mut gnfs.current_relations_progress.relations.smooth_relations, &mut smooth_relations);
-        
-            // Collect relations that need updating
-            for relation in &mut smooth_relations {
-                if !relation.is_persisted {
-                    relations_to_update.push((relation.a.clone(), relation.b.clone()));
-                }
-            }
-        
-            // Apply updates to each relation after collecting all necessary changes
-            for (a, b) in relations_to_update {
-                for relation in &mut smooth_relations {
-                    if relation.a == a && relation.b == b {
-                        append_relation(gnfs, relation);
-                    }
+            let filepath = gnfs.save_locations.smooth_relations_filepath.clone();
+            let mut started = Path::new(&filepath).exists();
+            let mut text = String::new();
+            let unsaved = gnfs.current_relations_progress.relations.smooth_relations.iter_mut().filter(|relation| relation.is_smooth() && !relation.is_persisted);
+            for relation in unsaved {
+                if started {
+                    text.push(',');
                 }
-            }
-        
-            // Swap the updated smooth relations back into GNFS
-            std::mem::swap(&mut gnfs.current_relations_progress.relations.smooth_relations, &mut smooth_relations);
-        }
-        
-        fn append_relation(gnfs: &mut GNFS, relation: &mut Relation) {
-            if relation.is_smooth() && !relation.is_persisted {
                 let serializable_relation = SerializableRelation::from(relation.clone());
-                let mut json = serde_json::to_string_pretty(&serializable_relation)
-                    .expect("Failed to serialize relation");
-        
-                let smooth_relations_filepath = &gnfs.save_locations.smooth_relations_filepath;
-        
-                if Path::new(smooth_relations_filepath).exists() {
-                    json.insert_str(0, ",");
-                    fs::OpenOptions::new()
-                        .write(true)
-                        .append(true)
-                        .open(smooth_relations_filepath)
-                        .expect("Failed to open smooth relations file for appending")
-                        .write_all(json.as_bytes())
-                        .expect("Failed to append smooth relation");
-                } else {
-                    fs::write(smooth_relations_filepath, format!("[{}]", json))
-                        .expect("Failed to write smooth relation");
-                }
-        
-                gnfs.current_relations_progress.smooth_relations_counter += 1;
+                text.push_str(&serde_json::to_string_pretty(&serializable_relation).expect("Failed to serialize relation"));
+                started = true;
+                // The sieve already counted this relation when it was found.
                 relation.is_persisted = true;
             }
+            if !text.is_empty() {
+                compression::append(Path::new(&filepath), text.as_bytes(), gnfs.config.compress_relations())
+                    .expect("Failed to append smooth relations");
+            }
         }
-        
     }
 
     pub mod rough {
         use super::*;
-    
-        pub fn append(gnfs: &mut GNFS) {
-            let mut relations_to_update = Vec::new();
-            let mut smooth_relations = Vec::new();
-            
-            // Extract the smooth relations into a separate vector
-            std::mem::swap(&mut gnfs.current_relations_progress.relations.smooth_relations, &mut smooth_relations);
-            
-            // Collect relations that need updating
-            for relation in &mut smooth_relations {
-                if !relation.is_persisted && relation.is_smooth() {
-                    relations_to_update.push((relation.a.clone(), relation.b.clone()));
-                }
-            }
-            
-            // Apply updates to each relation after collecting all necessary changes
-            for (a, b) in relations_to_update {
-                for relation in &mut smooth_relations {
-                    if relation.a == a && relation.b == b {
-                        append_relation(gnfs, relation);
-                    }
-                }
-            }
-            
-            // Swap the updated smooth relations back into GNFS
-            std::mem::swap(&mut gnfs.current_relations_progress.relations.smooth_relations, &mut smooth_relations);
-        }
-    
-        fn append_relation(gnfs: &mut GNFS, relation: &mut Relation) {
-            let serializable_relation = SerializableRelation::from(relation.clone());
-            let mut json = serde_json::to_string_pretty(&serializable_relation).expect("Failed to serialize relation");
-    
-            if Path::new(&gnfs.save_locations.smooth_relations_filepath).exists() {
-                json.insert_str(0, ",");
+
+        /// Rewrites the rough relations file. Recycling removes rough relations as it combines them,
+        /// so the file is replaced rather than appended to. The on-disk rough store, when there is
+        /// one, only needs its index written.
+        pub fn all(gnfs: &mut GNFS) {
+            if let Err(e) = gnfs.current_relations_progress.rough_store.save() {
+                warn!("Unable to save the rough relation store index: {}", e);
             }
-    
-            fs::write(&gnfs.save_locations.smooth_relations_filepath, json)
-                .expect("Failed to append smooth relation");
-    
-            gnfs.current_relations_progress.smooth_relations_counter += 1;
-            relation.is_persisted = true;
+            let rough_relations = &mut gnfs.current_relations_progress.relations.rough_relations;
+            let serializable: Vec<SerializableRelation> = rough_relations.iter().cloned().map(SerializableRelation::from).collect();
+            save::object(&serializable, &gnfs.save_locations.rough_relations_filepath);
+            rough_relations.iter_mut().for_each(|relation| relation.is_persisted = true);
         }
     }
 
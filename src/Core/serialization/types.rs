@@ -2,20 +2,28 @@
 
 use num::BigInt;
 use serde::{Serialize, Deserialize};
-use std::sync::Arc;
+use std::sync::Weak;
 use std::collections::BTreeMap;
 use std::str::FromStr;
 use crate::core::gnfs::GNFS;
+use crate::core::gnfs_config::GnfsConfig;
+use crate::core::sieve_tuning::SieveTuning;
+use crate::relation_sieve::rough_store::RoughRelationStore;
+use crate::relation_sieve::cycle_finder::CycleCounter;
+use crate::core::sieve_range::SieveRegion;
 use crate::core::directory_location::DirectoryLocations;
 use crate::core::factor_base::FactorBase;
-use crate::core::solution::Solution;
+use crate::core::progress::ProgressHandle;
+use crate::core::solution::{RunMetadata, Solution, SolutionParameters};
 use crate::factor::factor_pair::FactorPair;
 use crate::factor::factor_pair_collection::FactorPairCollection;
 use crate::polynomial::polynomial::{Term, Polynomial};
 use crate::relation_sieve::relation_container::RelationContainer;
-use crate::relation_sieve::poly_relations_sieve_progress::PolyRelationsSieveProgress;
+use crate::relation_sieve::poly_relations_sieve_progress::{PolyRelationsSieveProgress, RelationTarget};
 use crate::relation_sieve::relation::Relation;
 use crate::core::count_dictionary::CountDictionary;
+use crate::core::serialization::migration;
+use crate::square_root::square_finder::SquareRootProgress;
 
 #[derive(Serialize, Deserialize)]
 pub struct SerializableGNFS {
@@ -25,12 +33,19 @@ pub struct SerializableGNFS {
     pub polynomial_base: String,
     pub polynomial_collection: Vec<SerializablePolynomial>,
     pub current_polynomial: SerializablePolynomial,
+    /// Absent from runs saved before the rational side was stored; X - m is assumed for those.
+    #[serde(default)]
+    pub rational_polynomial: Option<SerializablePolynomial>,
     pub current_relations_progress: Box<SerializablePolyRelationsSieveProgress>,
     pub prime_factor_base: SerializableFactorBase,
     pub rational_factor_pair_collection: SerializableFactorPairCollection,
     pub algebraic_factor_pair_collection: SerializableFactorPairCollection,
     pub quadratic_factor_pair_collection: SerializableFactorPairCollection,
     pub save_locations: DirectoryLocations,
+    #[serde(default)]
+    pub config: GnfsConfig,
+    #[serde(default)]
+    pub run_metadata: RunMetadata,
 }
 
 impl From<GNFS> for SerializableGNFS {
@@ -42,31 +57,39 @@ impl From<GNFS> for SerializableGNFS {
             polynomial_base: gnfs.polynomial_base.to_string(),
             polynomial_collection: gnfs.polynomial_collection.into_iter().map(SerializablePolynomial::from).collect(),
             current_polynomial: SerializablePolynomial::from(gnfs.current_polynomial),
+            rational_polynomial: Some(SerializablePolynomial::from(gnfs.rational_polynomial)),
             current_relations_progress: Box::new(SerializablePolyRelationsSieveProgress::from(gnfs.current_relations_progress)),
             prime_factor_base: SerializableFactorBase::from(gnfs.prime_factor_base),
             rational_factor_pair_collection: SerializableFactorPairCollection::from(gnfs.rational_factor_pair_collection),
             algebraic_factor_pair_collection: SerializableFactorPairCollection::from(gnfs.algebraic_factor_pair_collection),
             quadratic_factor_pair_collection: SerializableFactorPairCollection::from(gnfs.quadratic_factor_pair_collection),
             save_locations: gnfs.save_locations,
+            config: gnfs.config,
+            run_metadata: gnfs.run_metadata,
         }
     }
 }
 
 impl From<SerializableGNFS> for GNFS {
     fn from(gnfs: SerializableGNFS) -> Self {
+        let polynomial_base = BigInt::parse_bytes(gnfs.polynomial_base.as_bytes(), 10).unwrap();
         GNFS {
             n: BigInt::parse_bytes(gnfs.n.as_bytes(), 10).unwrap(),
             factorization: gnfs.factorization.map(Solution::from),
             polynomial_degree: gnfs.polynomial_degree,
-            polynomial_base: BigInt::parse_bytes(gnfs.polynomial_base.as_bytes(), 10).unwrap(),
             polynomial_collection: gnfs.polynomial_collection.into_iter().map(Polynomial::from).collect(),
             current_polynomial: Polynomial::from(gnfs.current_polynomial),
+            rational_polynomial: gnfs.rational_polynomial.map(Polynomial::from).unwrap_or_else(|| Polynomial::rational_side(&polynomial_base)),
+            polynomial_base,
             current_relations_progress: PolyRelationsSieveProgress::from(*gnfs.current_relations_progress),
             prime_factor_base: FactorBase::from(gnfs.prime_factor_base),
             rational_factor_pair_collection: FactorPairCollection::from(gnfs.rational_factor_pair_collection),
             algebraic_factor_pair_collection: FactorPairCollection::from(gnfs.algebraic_factor_pair_collection),
             quadratic_factor_pair_collection: FactorPairCollection::from(gnfs.quadratic_factor_pair_collection),
             save_locations: gnfs.save_locations,
+            config: gnfs.config,
+            run_metadata: gnfs.run_metadata,
+            progress: ProgressHandle::default(),
         }
     }
 }
@@ -124,52 +147,148 @@ impl From<SerializablePolynomial> for Polynomial {
 
 #[derive(Serialize, Deserialize)]
 pub struct SerializablePolyRelationsSieveProgress {
+    #[serde(default)]
+    pub version: u32,
     pub a: String,
     pub b: String,
     pub smooth_relations_target_quantity: usize,
+    #[serde(default)]
+    pub relation_target: RelationTarget,
     pub value_range: String,
     pub relations: SerializableRelationContainer,
     pub max_b: String,
+    #[serde(default)]
+    pub region: Option<SerializableSieveRegion>,
+    #[serde(default)]
+    pub sieved_regions: Vec<SerializableSieveRegion>,
+    #[serde(default)]
+    pub pending_regions: Vec<SerializableSieveRegion>,
+    #[serde(default)]
+    pub special_q: u64,
     pub smooth_relations_counter: usize,
     pub free_relations_counter: usize,
-    // Assuming SerializableGNFS is defined
-    pub gnfs: Box<SerializableGNFS>,
+    #[serde(default)]
+    pub pairs_sieved: u64,
+    #[serde(default)]
+    pub recycled_relations_counter: usize,
+    /// Older saves embedded a copy of the whole run here. It is still accepted but never written:
+    /// the back-reference is re-attached on load.
+    #[serde(default, skip_serializing)]
+    pub gnfs: Option<Box<SerializableGNFS>>,
 }
 
 impl From<PolyRelationsSieveProgress> for SerializablePolyRelationsSieveProgress {
     fn from(progress: PolyRelationsSieveProgress) -> Self {
-        let gnfs = progress.gnfs.upgrade().map(|arc_gnfs| {
-            Box::new(SerializableGNFS::from((*arc_gnfs).clone()))
-        }).unwrap_or_else(|| Box::new(SerializableGNFS::default()));
-
         SerializablePolyRelationsSieveProgress {
+            version: migration::PROGRESS_FORMAT_VERSION,
             a: progress.a.to_string(),
             b: progress.b.to_string(),
             smooth_relations_target_quantity: progress.smooth_relations_target_quantity,
+            relation_target: progress.relation_target,
             value_range: progress.value_range.to_string(),
             relations: SerializableRelationContainer::from(progress.relations),
             max_b: progress.max_b.to_string(),
+            region: Some(SerializableSieveRegion::from(progress.region)),
+            sieved_regions: progress.sieved_regions.into_iter().map(SerializableSieveRegion::from).collect(),
+            pending_regions: progress.pending_regions.into_iter().map(SerializableSieveRegion::from).collect(),
+            special_q: progress.special_q,
             smooth_relations_counter: progress.smooth_relations_counter,
             free_relations_counter: progress.free_relations_counter,
-            gnfs: gnfs,
+            pairs_sieved: progress.pairs_sieved,
+            recycled_relations_counter: progress.recycled_relations_counter,
+            gnfs: None,
         }
     }
 }
 
 impl From<SerializablePolyRelationsSieveProgress> for PolyRelationsSieveProgress {
     fn from(progress: SerializablePolyRelationsSieveProgress) -> Self {
-        let gnfs = Arc::new(GNFS::from(*progress.gnfs));
+        let b = BigInt::parse_bytes(progress.b.as_bytes(), 10).unwrap();
+        let value_range = BigInt::parse_bytes(progress.value_range.as_bytes(), 10).unwrap();
+        let max_b = BigInt::parse_bytes(progress.max_b.as_bytes(), 10).unwrap();
+        // Files written before region bookkeeping carry only the position; resume from it.
+        let region = progress.region.map(SieveRegion::from).unwrap_or_else(|| {
+            SieveRegion::new(BigInt::from(1), value_range.clone(), b.clone(), max_b.clone())
+        });
 
         PolyRelationsSieveProgress {
             a: BigInt::parse_bytes(progress.a.as_bytes(), 10).unwrap(),
-            b: BigInt::parse_bytes(progress.b.as_bytes(), 10).unwrap(),
+            b,
             smooth_relations_target_quantity: progress.smooth_relations_target_quantity,
-            value_range: BigInt::parse_bytes(progress.value_range.as_bytes(), 10).unwrap(),
+            relation_target: progress.relation_target,
+            value_range,
             relations: RelationContainer::from(progress.relations),
-            max_b: BigInt::parse_bytes(progress.max_b.as_bytes(), 10).unwrap(),
+            max_b,
+            region,
+            sieved_regions: progress.sieved_regions.into_iter().map(SieveRegion::from).collect(),
+            pending_regions: progress.pending_regions.into_iter().map(SieveRegion::from).collect(),
+            special_q: progress.special_q,
             smooth_relations_counter: progress.smooth_relations_counter,
             free_relations_counter: progress.free_relations_counter,
-            gnfs: Arc::downgrade(&gnfs),
+            pairs_sieved: progress.pairs_sieved,
+            recycled_relations_counter: progress.recycled_relations_counter,
+            tuning: SieveTuning::default(),
+            rough_store: RoughRelationStore::default(),
+            pending_cycles: CycleCounter::new(),
+            gnfs: Weak::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SerializableSieveRegion {
+    pub min_abs_a: String,
+    pub max_abs_a: String,
+    pub min_b: String,
+    pub max_b: String,
+}
+
+impl From<SieveRegion> for SerializableSieveRegion {
+    fn from(region: SieveRegion) -> Self {
+        SerializableSieveRegion {
+            min_abs_a: region.min_abs_a.to_string(),
+            max_abs_a: region.max_abs_a.to_string(),
+            min_b: region.min_b.to_string(),
+            max_b: region.max_b.to_string(),
+        }
+    }
+}
+
+impl From<SerializableSieveRegion> for SieveRegion {
+    fn from(region: SerializableSieveRegion) -> Self {
+        SieveRegion::new(
+            BigInt::parse_bytes(region.min_abs_a.as_bytes(), 10).unwrap(),
+            BigInt::parse_bytes(region.max_abs_a.as_bytes(), 10).unwrap(),
+            BigInt::parse_bytes(region.min_b.as_bytes(), 10).unwrap(),
+            BigInt::parse_bytes(region.max_b.as_bytes(), 10).unwrap(),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SerializableSquareRootProgress {
+    pub dependency_fingerprint: u64,
+    pub tried_dependencies: Vec<usize>,
+    #[serde(default)]
+    pub last_prime: Option<String>,
+}
+
+impl From<SquareRootProgress> for SerializableSquareRootProgress {
+    fn from(progress: SquareRootProgress) -> Self {
+        SerializableSquareRootProgress {
+            dependency_fingerprint: progress.dependency_fingerprint,
+            tried_dependencies: progress.tried_dependencies,
+            last_prime: progress.last_prime.map(|prime| prime.to_string()),
+        }
+    }
+}
+
+impl From<SerializableSquareRootProgress> for SquareRootProgress {
+    fn from(progress: SerializableSquareRootProgress) -> Self {
+        SquareRootProgress {
+            dependency_fingerprint: progress.dependency_fingerprint,
+            tried_dependencies: progress.tried_dependencies,
+            last_prime: progress.last_prime.and_then(|prime| BigInt::parse_bytes(prime.as_bytes(), 10)),
         }
     }
 }
@@ -195,8 +314,8 @@ impl From<SerializableFactorPairCollection> for FactorPairCollection {
 
 #[derive(Serialize, Deserialize)]
 pub struct SerializableFactorPair {
-    pub p: i128,
-    pub r: i128,
+    pub p: u64,
+    pub r: u64,
 }
 
 impl From<FactorPair> for SerializableFactorPair {
@@ -271,6 +390,19 @@ impl From<SerializableFactorBase> for FactorBase {
 pub struct SerializableSolution {
     pub p: String,
     pub q: String,
+    #[serde(default)]
+    pub p_is_prime: bool,
+    #[serde(default)]
+    pub q_is_prime: bool,
+    /// Human-readable primality of p and q, e.g. "prime × composite (12 digits)".
+    #[serde(default)]
+    pub primality: String,
+    #[serde(default)]
+    pub factors: Vec<String>,
+    #[serde(default)]
+    pub metadata: RunMetadata,
+    #[serde(default)]
+    pub parameters: Option<SolutionParameters>,
 }
 
 impl From<Solution> for SerializableSolution {
@@ -278,6 +410,12 @@ impl From<Solution> for SerializableSolution {
         SerializableSolution {
             p: solution.p.to_string(),
             q: solution.q.to_string(),
+            p_is_prime: solution.p_is_prime,
+            q_is_prime: solution.q_is_prime,
+            primality: solution.primality_summary(),
+            factors: solution.factors.iter().map(|f| f.to_string()).collect(),
+            metadata: solution.metadata,
+            parameters: solution.parameters,
         }
     }
 }
@@ -287,6 +425,12 @@ impl From<SerializableSolution> for Solution {
         Solution {
             p: BigInt::parse_bytes(solution.p.as_bytes(), 10).unwrap(),
             q: BigInt::parse_bytes(solution.q.as_bytes(), 10).unwrap(),
+            p_is_prime: solution.p_is_prime,
+            q_is_prime: solution.q_is_prime,
+            factors: solution.factors.iter().map(|f| BigInt::parse_bytes(f.as_bytes(), 10).unwrap()).collect(),
+            metadata: solution.metadata,
+            parameters: solution.parameters,
+            square_root_report: None,
         }
     }
 }
@@ -296,6 +440,10 @@ pub struct SerializableRelationContainer {
     pub smooth_relations: Vec<SerializableRelation>,
     pub rough_relations: Vec<SerializableRelation>,
     pub free_relations: Vec<Vec<SerializableRelation>>,
+    #[serde(default)]
+    pub companion_relations: Vec<Vec<SerializableRelation>>,
+    #[serde(default)]
+    pub combined_relations: Vec<Vec<SerializableRelation>>,
 }
 
 impl From<RelationContainer> for SerializableRelationContainer {
@@ -306,6 +454,12 @@ impl From<RelationContainer> for SerializableRelationContainer {
             free_relations: container.free_relations.into_iter().map(|relations| {
                 relations.into_iter().map(SerializableRelation::from).collect()
             }).collect(),
+            companion_relations: container.companion_relations.into_iter().map(|relations| {
+                relations.into_iter().map(SerializableRelation::from).collect()
+            }).collect(),
+            combined_relations: container.combined_relations.into_iter().map(|relations| {
+                relations.into_iter().map(SerializableRelation::from).collect()
+            }).collect(),
         }
     }
 }
@@ -318,12 +472,20 @@ impl From<SerializableRelationContainer> for RelationContainer {
             free_relations: container.free_relations.into_iter().map(|relations| {
                 relations.into_iter().map(Relation::from).collect()
             }).collect(),
+            companion_relations: container.companion_relations.into_iter().map(|relations| {
+                relations.into_iter().map(Relation::from).collect()
+            }).collect(),
+            combined_relations: container.combined_relations.into_iter().map(|relations| {
+                relations.into_iter().map(Relation::from).collect()
+            }).collect(),
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializableRelation {
+    #[serde(default)]
+    pub version: u32,
     pub a: String,
     pub b: String,
     pub algebraic_norm: String,
@@ -333,11 +495,14 @@ pub struct SerializableRelation {
     pub algebraic_factorization: SerializableCountDictionary,
     pub rational_factorization: SerializableCountDictionary,
     pub is_persisted: bool,
+    #[serde(default)]
+    pub polynomial: usize,
 }
 
 impl From<Relation> for SerializableRelation {
     fn from(relation: Relation) -> Self {
         SerializableRelation {
+            version: migration::RELATION_FORMAT_VERSION,
             a: relation.a.to_string(),
             b: relation.b.to_string(),
             algebraic_norm: relation.algebraic_norm.to_string(),
@@ -347,6 +512,7 @@ impl From<Relation> for SerializableRelation {
             algebraic_factorization: SerializableCountDictionary::from(relation.algebraic_factorization),
             rational_factorization: SerializableCountDictionary::from(relation.rational_factorization),
             is_persisted: relation.is_persisted,
+            polynomial: relation.polynomial,
         }
     }
 }
@@ -363,31 +529,59 @@ impl From<SerializableRelation> for Relation {
             algebraic_factorization: CountDictionary::from(relation.algebraic_factorization),
             rational_factorization: CountDictionary::from(relation.rational_factorization),
             is_persisted: relation.is_persisted,
+            polynomial: relation.polynomial,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SerializableCountDictionary(BTreeMap<String, String>);
+/// Serialized in the compact `2^3*5*7^2` form. The older `{"2": "3", "5": "1"}` map layout is still
+/// accepted when loading, so existing relation files keep working; a malformed factorization in
+/// either layout fails to load rather than panicking later.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "CountDictionaryRepresentation", into = "String")]
+pub struct SerializableCountDictionary(pub CountDictionary);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CountDictionaryRepresentation {
+    Compact(String),
+    Legacy(BTreeMap<String, String>),
+}
+
+impl TryFrom<CountDictionaryRepresentation> for SerializableCountDictionary {
+    type Error = String;
+
+    fn try_from(representation: CountDictionaryRepresentation) -> Result<Self, Self::Error> {
+        match representation {
+            CountDictionaryRepresentation::Compact(compact) => CountDictionary::from_compact_string(&compact).map(SerializableCountDictionary),
+            CountDictionaryRepresentation::Legacy(map) => {
+                let mut dict = CountDictionary::new();
+                for (key, value) in map {
+                    let key = BigInt::from_str(&key).map_err(|e| format!("Invalid factor '{}': {}", key, e))?;
+                    let value = value.parse::<u32>().map_err(|e| format!("Invalid exponent '{}': {}", value, e))?;
+                    dict.add_count(&key, value);
+                }
+                Ok(SerializableCountDictionary(dict))
+            }
+        }
+    }
+}
+
+impl From<SerializableCountDictionary> for String {
+    fn from(dict: SerializableCountDictionary) -> Self {
+        dict.0.to_compact_string()
+    }
+}
 
 impl From<CountDictionary> for SerializableCountDictionary {
     fn from(dict: CountDictionary) -> Self {
-        let serializable_map = dict.0.into_iter()
-            .map(|(key, value)| (key.to_string(), value.to_string()))
-            .collect();
-        SerializableCountDictionary(serializable_map)
+        SerializableCountDictionary(dict)
     }
 }
 
 impl From<SerializableCountDictionary> for CountDictionary {
     fn from(dict: SerializableCountDictionary) -> Self {
-        let original_map = dict.0.into_iter()
-            .map(|(key, value)| (
-                BigInt::parse_bytes(key.as_bytes(), 10).unwrap(),
-                BigInt::parse_bytes(value.as_bytes(), 10).unwrap(),
-            ))
-            .collect();
-        CountDictionary(original_map)
+        dict.0
     }
 }
 
@@ -400,12 +594,15 @@ impl Default for SerializableGNFS {
             polynomial_base: String::default(),
             polynomial_collection: Vec::default(),
             current_polynomial: SerializablePolynomial::default(),
+            rational_polynomial: None,
             current_relations_progress: Box::new(SerializablePolyRelationsSieveProgress::default()),
             prime_factor_base: SerializableFactorBase::default(),
             rational_factor_pair_collection: SerializableFactorPairCollection::default(),
             algebraic_factor_pair_collection: SerializableFactorPairCollection::default(),
             quadratic_factor_pair_collection: SerializableFactorPairCollection::default(),
             save_locations: DirectoryLocations::default(),
+            config: GnfsConfig::default(),
+            run_metadata: RunMetadata::default(),
         }
     }
 }
@@ -413,15 +610,23 @@ impl Default for SerializableGNFS {
 impl Default for SerializablePolyRelationsSieveProgress {
     fn default() -> Self {
         SerializablePolyRelationsSieveProgress {
+            version: migration::PROGRESS_FORMAT_VERSION,
             a: String::default(),
             b: String::default(),
             smooth_relations_target_quantity: 0,
+            relation_target: RelationTarget::default(),
             value_range: String::default(),
             relations: SerializableRelationContainer::default(),
             max_b: String::default(),
+            region: None,
+            sieved_regions: Vec::new(),
+            pending_regions: Vec::new(),
+            special_q: 0,
             smooth_relations_counter: 0,
             free_relations_counter: 0,
-            gnfs: Box::new(SerializableGNFS::default()),
+            pairs_sieved: 0,
+            recycled_relations_counter: 0,
+            gnfs: None,
         }
     }
 }
@@ -461,6 +666,46 @@ impl Default for SerializableRelationContainer {
             smooth_relations: Vec::default(),
             rough_relations: Vec::default(),
             free_relations: Vec::default(),
+            companion_relations: Vec::default(),
+            combined_relations: Vec::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(entries: &[(u64, u32)]) -> CountDictionary {
+        let mut dict = CountDictionary::new();
+        for &(key, value) in entries {
+            dict.add_count(&BigInt::from(key), value);
         }
+        dict
+    }
+
+    #[test]
+    fn count_dictionaries_round_trip_in_compact_form() {
+        let dict = dictionary(&[(2, 3), (5, 1), (7, 2)]);
+        let json = serde_json::to_string(&SerializableCountDictionary::from(dict.clone())).unwrap();
+        assert_eq!(json, "\"2^3*5*7^2\"");
+        let loaded: SerializableCountDictionary = serde_json::from_str(&json).unwrap();
+        assert_eq!(CountDictionary::from(loaded), dict);
+
+        let empty: SerializableCountDictionary = serde_json::from_str("\"\"").unwrap();
+        assert_eq!(CountDictionary::from(empty), CountDictionary::new());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn count_dictionaries_load_from_the_legacy_map() {
+        let loaded: SerializableCountDictionary = serde_json::from_str(r#"{"2": "3", "5": "1", "7": "2"}"#).unwrap();
+        assert_eq!(CountDictionary::from(loaded), dictionary(&[(2, 3), (5, 1), (7, 2)]));
+    }
+
+    #[test]
+    fn malformed_count_dictionaries_fail_to_load() {
+        for malformed in [r#""2^x*5""#, r#""2^3*five""#, r#""2^-1""#, r#"{"2": "three"}"#, r#"{"two": "3"}"#, "17", "[2, 3]"] {
+            assert!(serde_json::from_str::<SerializableCountDictionary>(malformed).is_err(), "{}", malformed);
+        }
+    }
+}
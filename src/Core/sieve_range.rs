@@ -37,4 +37,69 @@ impl SieveRange {
             }
         })
     }
-}
\ No newline at end of file
+}
+impl SieveRange {
+    /// The value that follows `a` in sieve order (1, -1, 2, -2, ...).
+    pub fn next(a: &BigInt) -> BigInt {
+        if a.is_positive() {
+            -a
+        } else {
+            -a + 1
+        }
+    }
+}
+
+/// A rectangle of the (a, b) lattice: every `a` with `min_abs_a <= |a| <= max_abs_a`, taken with
+/// both signs, against every `b` in `min_b..=max_b`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SieveRegion {
+    pub min_abs_a: BigInt,
+    pub max_abs_a: BigInt,
+    pub min_b: BigInt,
+    pub max_b: BigInt,
+}
+
+impl SieveRegion {
+    pub fn new(min_abs_a: BigInt, max_abs_a: BigInt, min_b: BigInt, max_b: BigInt) -> Self {
+        SieveRegion { min_abs_a, max_abs_a, min_b, max_b }
+    }
+
+    /// Number of (a, b) pairs in the region, before the coprimality filter.
+    pub fn pair_count(&self) -> BigInt {
+        if self.max_abs_a < self.min_abs_a || self.max_b < self.min_b {
+            return BigInt::from(0);
+        }
+        (&self.max_abs_a - &self.min_abs_a + 1) * 2 * (&self.max_b - &self.min_b + 1)
+    }
+}
+
+impl std::fmt::Display for SieveRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "|a| in [{}, {}], b in [{}, {}]",
+            self.min_abs_a, self.max_abs_a, self.min_b, self.max_b
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continuation_covers_both_signs_without_gaps() {
+        let values: Vec<BigInt> = SieveRange::get_sieve_range(&BigInt::from(3)).collect();
+        let expected: Vec<BigInt> = [1, -1, 2, -2, 3, -3].iter().map(|&v| BigInt::from(v)).collect();
+        assert_eq!(values, expected);
+
+        let mut a = BigInt::from(1);
+        for value in &expected {
+            assert_eq!(&a, value);
+            a = SieveRange::next(&a);
+        }
+
+        let resumed: Vec<BigInt> = SieveRange::get_sieve_range_continuation(&BigInt::from(-2), &BigInt::from(3)).collect();
+        assert_eq!(resumed, expected[3..].to_vec());
+    }
+}
@@ -0,0 +1,106 @@
+// src/core/sieve_tuning.rs
+
+use log::info;
+use std::time::Duration;
+use crate::core::cpu_info::CPUInfo;
+use crate::core::gnfs_config::GnfsConfig;
+
+const DEFAULT_L1_CACHE_SIZE: usize = 32 * 1024;
+const DEFAULT_L2_CACHE_SIZE: usize = 256 * 1024;
+/// Rough in-memory footprint of one buffered relation, including its factorizations.
+const ESTIMATED_RELATION_BYTES: usize = 2 * 1024;
+/// Share of available memory the relation buffer may occupy before it is flushed.
+const RELATION_BUFFER_MEMORY_FRACTION: usize = 64;
+const MIN_RELATION_FLUSH_THRESHOLD: usize = 64;
+const MAX_RELATION_FLUSH_THRESHOLD: usize = 1_000_000;
+const DEFAULT_RELATION_FLUSH_INTERVAL_SECS: u64 = 300;
+/// Partials are combined far more often than relations are flushed, so the progress reported
+/// mid-batch includes them even on inputs that finish in seconds.
+const DEFAULT_RELATION_RECYCLE_INTERVAL_SECS: u64 = 5;
+/// Default memory cap, as a multiple of the flush threshold's footprint, leaving headroom for bursts.
+const RELATION_MEMORY_CAP_HEADROOM: usize = 2;
+/// Share of the memory cap, in percent, at which the buffer is spilled mid-row.
+const RELATION_HIGH_WATERMARK_PERCENT: usize = 90;
+
+/// Sieve sizing derived from the host's caches and memory. Any value set in [`GnfsConfig`]
+/// takes precedence over the detected one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SieveTuning {
+    /// Sieve block length in bytes; sized so a block stays resident in L1 (or half of L2 when
+    /// L1 is unknown).
+    pub block_length: usize,
+    /// Blocks per line-sieve region, each region bucket-sieved by one thread; sized so a region's
+    /// cells stay resident in L2.
+    pub bucket_count: usize,
+    /// Unsaved smooth and rough relations allowed in memory before they are written to disk.
+    pub relation_flush_threshold: usize,
+    /// Longest a buffered relation waits before it is written, so slow runs still reach disk.
+    pub relation_flush_interval: Duration,
+    /// How often the sieve combines partial relations without ending the batch.
+    pub relation_recycle_interval: Duration,
+    /// Bytes the relation buffer may occupy; it is spilled at the high watermark below this.
+    pub relation_memory_cap: usize,
+}
+
+impl SieveTuning {
+    pub fn detect(config: &GnfsConfig) -> Self {
+        let block_length = config.sieve_block_length.unwrap_or_else(|| {
+            CPUInfo::l1_cache_size()
+                .or_else(|| CPUInfo::l2_cache_size().map(|size| size / 2))
+                .unwrap_or(DEFAULT_L1_CACHE_SIZE)
+        });
+
+        let bucket_count = config.sieve_bucket_count.unwrap_or_else(|| {
+            let l2 = CPUInfo::l2_cache_size().unwrap_or(DEFAULT_L2_CACHE_SIZE);
+            (l2 / block_length.max(1)).max(1)
+        });
+
+        let relation_flush_threshold = config.relation_flush_threshold.unwrap_or_else(|| {
+            CPUInfo::available_memory()
+                .map(|bytes| bytes / RELATION_BUFFER_MEMORY_FRACTION / ESTIMATED_RELATION_BYTES)
+                .unwrap_or(MIN_RELATION_FLUSH_THRESHOLD)
+                .clamp(MIN_RELATION_FLUSH_THRESHOLD, MAX_RELATION_FLUSH_THRESHOLD)
+        });
+
+        let relation_flush_interval = Duration::from_secs(
+            config.relation_flush_interval_secs.unwrap_or(DEFAULT_RELATION_FLUSH_INTERVAL_SECS),
+        );
+
+        let relation_recycle_interval = Duration::from_secs(
+            config.relation_recycle_interval_secs.unwrap_or(DEFAULT_RELATION_RECYCLE_INTERVAL_SECS),
+        );
+
+        let relation_memory_cap = config.relation_memory_cap.unwrap_or(
+            relation_flush_threshold * ESTIMATED_RELATION_BYTES * RELATION_MEMORY_CAP_HEADROOM,
+        );
+
+        SieveTuning {
+            block_length,
+            bucket_count,
+            relation_flush_threshold,
+            relation_flush_interval,
+            relation_recycle_interval,
+            relation_memory_cap,
+        }
+    }
+
+    /// Buffered relations at which the sieve stops mid-row and hands the buffer over to be written.
+    pub fn relation_high_watermark(&self) -> usize {
+        (self.relation_memory_cap / ESTIMATED_RELATION_BYTES * RELATION_HIGH_WATERMARK_PERCENT / 100).max(1)
+    }
+
+    pub fn log(&self) {
+        info!("Sieve block length: {} bytes", self.block_length);
+        info!("Sieve bucket count: {}", self.bucket_count);
+        info!("Relation flush threshold: {} relations", self.relation_flush_threshold);
+        info!("Relation flush interval: {} seconds", self.relation_flush_interval.as_secs());
+        info!("Relation recycle interval: {} seconds", self.relation_recycle_interval.as_secs());
+        info!("Relation memory cap: {} bytes (spill at {} relations)", self.relation_memory_cap, self.relation_high_watermark());
+    }
+}
+
+impl Default for SieveTuning {
+    fn default() -> Self {
+        SieveTuning::detect(&GnfsConfig::default())
+    }
+}
@@ -1,12 +1,86 @@
 // src/core/solution.rs
 
 use num::BigInt;
+use serde::{Serialize, Deserialize};
 use std::fmt::Display;
+use std::time::Duration;
+use crate::core::memory_usage::format_bytes;
+
+/// Bookkeeping collected over a run and attached to the final solution.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunMetadata {
+    pub algorithm: String,
+    /// Wall-clock seconds per stage, in the order the stages first ran.
+    pub stage_timings: Vec<(String, f64)>,
+    pub smooth_relation_count: usize,
+    pub rough_relation_count: usize,
+    pub matrix_rows: usize,
+    pub matrix_cols: usize,
+    #[serde(default)]
+    pub matrix_rank: usize,
+    #[serde(default)]
+    pub matrix_nullity: usize,
+    pub dependency_index: Option<usize>,
+    /// Peak resident memory per stage in bytes, in the order the stages first ran.
+    #[serde(default)]
+    pub stage_peak_memory: Vec<(String, usize)>,
+}
+
+impl RunMetadata {
+    /// Adds `elapsed` to the named stage, so resumed stages accumulate.
+    pub fn record_stage(&mut self, stage: &str, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        match self.stage_timings.iter_mut().find(|(name, _)| name == stage) {
+            Some((_, total)) => *total += seconds,
+            None => self.stage_timings.push((stage.to_string(), seconds)),
+        }
+    }
+
+    /// Keeps the larger of the stage's recorded peak and `bytes`.
+    pub fn record_stage_memory(&mut self, stage: &str, bytes: usize) {
+        match self.stage_peak_memory.iter_mut().find(|(name, _)| name == stage) {
+            Some((_, peak)) => *peak = (*peak).max(bytes),
+            None => self.stage_peak_memory.push((stage.to_string(), bytes)),
+        }
+    }
+
+    pub fn peak_memory(&self) -> Option<usize> {
+        self.stage_peak_memory.iter().map(|(_, bytes)| *bytes).max()
+    }
+
+    pub fn total_seconds(&self) -> f64 {
+        self.stage_timings.iter().map(|(_, seconds)| seconds).sum()
+    }
+}
+
+/// The run parameters that produced a solution, so it can be reproduced.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SolutionParameters {
+    pub n: String,
+    pub polynomial: String,
+    pub polynomial_base: String,
+    pub polynomial_degree: usize,
+    pub rational_factor_base_max: String,
+    pub algebraic_factor_base_max: String,
+    pub quadratic_base_count: i32,
+    pub relation_target: usize,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
 
 #[derive(Debug, Clone)]
 pub struct Solution {
     pub p: BigInt,
     pub q: BigInt,
+    pub p_is_prime: bool,
+    pub q_is_prime: bool,
+    /// Prime factorization of N in ascending order. Composite p or q are split further, so this
+    /// can hold more than two entries; a factor that could not be split is kept as-is.
+    pub factors: Vec<BigInt>,
+    pub metadata: RunMetadata,
+    pub parameters: Option<SolutionParameters>,
+    /// The square finder's account of the dependency that produced the factors.
+    pub square_root_report: Option<String>,
 }
 
 impl Solution {
@@ -14,12 +88,82 @@ impl Solution {
         Solution {
             p: p.clone(),
             q: q.clone(),
+            p_is_prime: false,
+            q_is_prime: false,
+            factors: vec![p.clone(), q.clone()],
+            metadata: RunMetadata::default(),
+            parameters: None,
+            square_root_report: None,
         }
     }
+
+    /// "prime × prime", or "prime × composite (12 digits)" when more work is needed.
+    pub fn primality_summary(&self) -> String {
+        let describe = |factor: &BigInt, is_prime: bool| {
+            if is_prime {
+                "prime".to_string()
+            } else {
+                format!("composite ({} digits)", factor.magnitude().to_string().len())
+            }
+        };
+        format!("{} × {}", describe(&self.p, self.p_is_prime), describe(&self.q, self.q_is_prime))
+    }
+
+    /// Multi-line summary written to `Solution.txt`.
+    pub fn report(&self) -> String {
+        let mut result = String::new();
+        result.push_str(&format!("N = {}\n\n", &self.p * &self.q));
+        result.push_str(&format!("p = {} ({})\n", self.p, if self.p_is_prime { "prime" } else { "composite" }));
+        result.push_str(&format!("q = {} ({})\n", self.q, if self.q_is_prime { "prime" } else { "composite" }));
+        result.push_str(&format!("Factors: {}\n\n", self.primality_summary()));
+        if !(self.p_is_prime && self.q_is_prime) {
+            let factors: Vec<String> = self.factors.iter().map(|f| f.to_string()).collect();
+            result.push_str(&format!("Full factorization: {}\n\n", factors.join(" * ")));
+        }
+        if let Some(parameters) = &self.parameters {
+            result.push_str(&format!("Polynomial: {} (degree {}, base m = {})\n", parameters.polynomial, parameters.polynomial_degree, parameters.polynomial_base));
+            result.push_str(&format!(
+                "Factor bases: rational ≤ {}, algebraic ≤ {}, {} quadratic characters\n",
+                parameters.rational_factor_base_max, parameters.algebraic_factor_base_max, parameters.quadratic_base_count
+            ));
+            result.push_str(&format!("Relation target: {}\n", parameters.relation_target));
+            if let Some(seed) = parameters.seed {
+                result.push_str(&format!("Seed: {}\n", seed));
+            }
+            result.push('\n');
+        }
+        result.push_str(&format!("Algorithm: {}\n", self.metadata.algorithm));
+        result.push_str(&format!("Smooth relations: {}\n", self.metadata.smooth_relation_count));
+        result.push_str(&format!("Rough relations: {}\n", self.metadata.rough_relation_count));
+        result.push_str(&format!(
+            "Matrix: {} x {} (rank {}, nullity {})\n",
+            self.metadata.matrix_rows, self.metadata.matrix_cols, self.metadata.matrix_rank, self.metadata.matrix_nullity
+        ));
+        if let Some(index) = self.metadata.dependency_index {
+            result.push_str(&format!("Dependency index: {}\n", index));
+        }
+        result.push_str("\nStage timings:\n");
+        for (stage, seconds) in &self.metadata.stage_timings {
+            result.push_str(&format!("\t{:<12} {:>10.3}s\n", stage, seconds));
+        }
+        result.push_str(&format!("\t{:<12} {:>10.3}s\n", "total", self.metadata.total_seconds()));
+        if let Some(overall) = self.metadata.peak_memory() {
+            result.push_str("\nPeak memory:\n");
+            for (stage, bytes) in &self.metadata.stage_peak_memory {
+                result.push_str(&format!("\t{:<12} {:>10}\n", stage, format_bytes(*bytes)));
+            }
+            result.push_str(&format!("\t{:<12} {:>10}\n", "overall", format_bytes(overall)));
+        }
+        if let Some(report) = &self.square_root_report {
+            result.push_str("\nSquare root:\n");
+            result.push_str(report);
+        }
+        result
+    }
 }
 
 impl Display for Solution {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "p: {}, q: {}", self.p, self.q)
+        write!(f, "p: {}, q: {} ({})", self.p, self.q, self.primality_summary())
     }
-}
\ No newline at end of file
+}
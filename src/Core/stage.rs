@@ -0,0 +1,50 @@
+// src/core/stage.rs
+
+use std::fmt::Display;
+use serde::{Serialize, Deserialize};
+
+/// The resumable steps of a factorization after the polynomial and factor bases are built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Sieve,
+    Matrix,
+    SquareRoot,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 3] = [Stage::Sieve, Stage::Matrix, Stage::SquareRoot];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Sieve => "sieve",
+            Stage::Matrix => "matrix",
+            Stage::SquareRoot => "square_root",
+        }
+    }
+
+    pub fn next(&self) -> Option<Stage> {
+        match self {
+            Stage::Sieve => Some(Stage::Matrix),
+            Stage::Matrix => Some(Stage::SquareRoot),
+            Stage::SquareRoot => None,
+        }
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Result of one call to `GNFS::run_stage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StageOutcome {
+    /// The stage finished and the next stage can run.
+    Completed,
+    /// The stage ran but needs more input, e.g. more relations before the matrix has dependencies.
+    NeedsMoreWork,
+    /// The cancellation token fired or its deadline passed; the stage can be run again to resume.
+    Cancelled,
+}
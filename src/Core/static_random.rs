@@ -20,6 +20,14 @@ impl StaticRandom {
         StaticRandom { rng }
     }
 
+    /// Deterministic generator for reproducible runs. Different `stream` values give
+    /// independent sequences from the same seed.
+    pub fn from_seed(seed: u64, stream: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(stream);
+        StaticRandom { rng }
+    }
+
     pub fn next(&mut self) -> u32 {
         self.rng.gen()
     }
@@ -58,4 +66,21 @@ impl StaticRandom {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_sequences_repeat() {
+        let mut first = StaticRandom::from_seed(42, 1);
+        let mut second = StaticRandom::from_seed(42, 1);
+        let mut other_stream = StaticRandom::from_seed(42, 2);
+        let a: Vec<u32> = (0..8).map(|_| first.next()).collect();
+        let b: Vec<u32> = (0..8).map(|_| second.next()).collect();
+        let c: Vec<u32> = (0..8).map(|_| other_stream.next()).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
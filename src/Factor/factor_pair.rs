@@ -3,26 +3,57 @@
 use std::cmp::Eq;
 use std::hash::{Hash, Hasher};
 use serde::{Serialize, Deserialize};
-use num::BigInt;
+use num::{BigInt, Integer, Zero};
 use num::ToPrimitive;
 
+/// A (p, r) factor base entry. Both values fit in a machine word for any
+/// realistic factor base bound, so they are stored as packed u64s and only
+/// converted to BigInt at the call sites that need arbitrary precision.
+///
+/// An affine root has r < p. r = p marks the projective root, which exists when p divides the
+/// leading coefficient of ƒ and divides the norm of (a, b) exactly when p divides b.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct FactorPair {
-    pub p: i128,
-    pub r: i128,
+    pub p: u64,
+    pub r: u64,
 }
 
 impl FactorPair {
     pub fn new_from_bigint(p: &BigInt, r: &BigInt) -> Result<Self, String> {
-        let p = p.to_i128().ok_or("BigInt value for p is out of range for i128")?;
-        let r = r.to_i128().ok_or("BigInt value for r is out of range for i128")?;
+        let p = p.to_u64().ok_or("BigInt value for p is out of range for u64")?;
+        let r = r.to_u64().ok_or("BigInt value for r is out of range for u64")?;
         Ok(FactorPair { p, r })
-        }
-    
+    }
 
-    pub fn new(p: i128, r: i128) -> Self {
+    pub fn new(p: u64, r: u64) -> Self {
         FactorPair { p, r }
     }
+
+    pub fn projective(p: u64) -> Self {
+        FactorPair { p, r: p }
+    }
+
+    pub fn is_projective(&self) -> bool {
+        self.r == self.p
+    }
+
+    /// Whether the prime ideal this pair stands for divides a - bα, i.e. a + b·r ≡ 0 (mod p) for
+    /// an affine root and b ≡ 0 (mod p) for the projective one.
+    pub fn divides(&self, a: &BigInt, b: &BigInt) -> bool {
+        let p = self.p_bigint();
+        if self.is_projective() {
+            return b.mod_floor(&p).is_zero();
+        }
+        (a + b * self.r_bigint()).mod_floor(&p).is_zero()
+    }
+
+    pub fn p_bigint(&self) -> BigInt {
+        BigInt::from(self.p)
+    }
+
+    pub fn r_bigint(&self) -> BigInt {
+        BigInt::from(self.r)
+    }
 }
 
 impl Hash for FactorPair {
@@ -44,4 +75,4 @@ impl std::fmt::Display for FactorPair {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "({},{})", self.p, self.r)
     }
-}
\ No newline at end of file
+}
@@ -1,11 +1,10 @@
 // src/factor/factor_pair_collection.rs
 
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
 use log::warn;
-use num::BigInt;
+use num::{BigInt, Integer, Zero};
 use serde::{Deserialize, Serialize};
 use crate::core::gnfs::GNFS;
+use crate::core::cancellation_token::CancellationToken;
 use crate::polynomial::polynomial::Polynomial;
 use crate::factor::factor_pair::FactorPair;
 use num::ToPrimitive;
@@ -57,19 +56,43 @@ impl ExactSizeIterator for FactorPairCollection {
 pub struct Factory;
 
 impl Factory {
-    // array of (p, m % p) up to bound
+    // array of (p, r) where g(r) % p == 0 up to bound; (p, m % p) for g = X - m
     // quantity = phi(bound)
     pub fn build_rational_factor_pair_collection(gnfs: &GNFS) -> FactorPairCollection {
-        let result: Vec<FactorPair> = gnfs.prime_factor_base.rational_factor_base.iter()
-            .map(|p| FactorPair::new(p.to_i128().unwrap(), (&gnfs.polynomial_base % p).to_i128().unwrap())) // Convert BigInt to i128
-            .collect();
+        let result = Self::rational_factor_pairs(&gnfs.prime_factor_base.rational_factor_base, &gnfs.rational_polynomial);
         FactorPairCollection::from_collection(&result)
     }
 
-    // array of (p, r) where ƒ(r) % p == 0
+    /// (p, r) for the roots r of the rational polynomial g mod each of the given primes, and (p, p)
+    /// where p divides its leading coefficient. A linear g = g₁X + g₀ has the single root
+    /// -g₀/g₁ mod p, which is m mod p for X - m; a nonlinear one is searched like ƒ.
+    pub fn rational_factor_pairs(primes: &[BigInt], rational_polynomial: &Polynomial) -> Vec<FactorPair> {
+        let mut pairs = if rational_polynomial.degree() == 1 {
+            let (g1, g0) = (&rational_polynomial[1], &rational_polynomial[0]);
+            primes
+                .iter()
+                .filter_map(|p| {
+                    let g1 = g1.mod_floor(p);
+                    if g1.is_zero() {
+                        return None;
+                    }
+                    let inverse = g1.modpow(&(p - 2), p);
+                    Some(FactorPair::new(p.to_u64().unwrap(), (-g0 * inverse).mod_floor(p).to_u64().unwrap()))
+                })
+                .collect()
+        } else {
+            let bound = primes.last().map(|p| p + 1).unwrap_or_default();
+            Self::find_polynomial_roots_in_range(&CancellationToken::new(), rational_polynomial, primes, &BigInt::zero(), &bound, usize::MAX)
+        };
+        pairs.extend(Self::projective_roots(rational_polynomial, primes));
+        pairs.sort_by_key(|factor_pair| (factor_pair.p, factor_pair.r));
+        pairs
+    }
+
+    // array of (p, r) where ƒ(r) % p == 0, plus (p, p) for the projective roots
     // quantity = 2-3 times RFB.quantity
-    pub fn build_algebraic_factor_pair_collection(cancel_token: &Arc<AtomicBool>, gnfs: &GNFS) -> FactorPairCollection {
-        let roots = Self::find_polynomial_roots_in_range(
+    pub fn build_algebraic_factor_pair_collection(cancel_token: &CancellationToken, gnfs: &GNFS) -> FactorPairCollection {
+        let mut roots = Self::find_polynomial_roots_in_range(
             cancel_token,
             &gnfs.current_polynomial,
             &gnfs.prime_factor_base.algebraic_factor_base,
@@ -77,13 +100,29 @@ impl Factory {
             &gnfs.prime_factor_base.algebraic_factor_base_max,
             2000,
         );
+        roots.extend(Self::projective_roots(&gnfs.current_polynomial, &gnfs.prime_factor_base.algebraic_factor_base));
+        roots.sort_by_key(|factor_pair| (factor_pair.p, factor_pair.r));
         FactorPairCollection::from_collection(&roots)
     }
 
+    /// (p, p) for each prime dividing the leading coefficient of ƒ. Such a p divides the norm of
+    /// every (a, b) with p | b, independently of a, so it has no affine root to sieve by.
+    pub fn projective_roots(polynomial: &Polynomial, primes: &[BigInt]) -> Vec<FactorPair> {
+        let Some(leading) = polynomial.terms.get(&polynomial.degree()) else {
+            return Vec::new();
+        };
+        primes
+            .iter()
+            .filter(|p| leading.mod_floor(p).is_zero())
+            .filter_map(|p| p.to_u64())
+            .map(FactorPair::projective)
+            .collect()
+    }
+
     // array of (p, r) where ƒ(r) % p == 0
     // quantity =< 100
     // magnitude p > AFB.Last().p
-    pub fn build_quadratic_factor_pair_collection(cancel_token: &Arc<AtomicBool>, gnfs: &GNFS) -> FactorPairCollection {
+    pub fn build_quadratic_factor_pair_collection(cancel_token: &CancellationToken, gnfs: &GNFS) -> FactorPairCollection {
         let roots = Self::find_polynomial_roots_in_range(
             cancel_token,
             &gnfs.current_polynomial,
@@ -96,7 +135,7 @@ impl Factory {
     }
 
     pub fn find_polynomial_roots_in_range(
-        cancel_token: &Arc<AtomicBool>,
+        cancel_token: &CancellationToken,
         polynomial: &Polynomial,
         primes: &[BigInt],
         range_from: &BigInt,
@@ -107,10 +146,12 @@ impl Factory {
         let mut r = range_from.clone();
         let mod_list: Vec<BigInt> = primes.to_vec();
 
-        while !cancel_token.load(Ordering::SeqCst) && &r < range_to && result.len() < total_factor_pairs {
+        while !cancel_token.is_cancellation_requested() && &r < range_to && result.len() < total_factor_pairs {
             let roots = Self::get_roots_mod(polynomial, &r, &mod_list);
             if !roots.is_empty() {
-                roots.iter().filter_map(|p| {
+                // Only r < p is a distinct root; r + kp is the same one again, and r = p is reserved
+                // for projective roots.
+                roots.iter().filter(|p| &r < *p).filter_map(|p| {
                     match FactorPair::new_from_bigint(p, &r) {
                         Ok(pair) => Some(pair),
                         Err(e) => {
@@ -136,4 +177,38 @@ impl Factory {
             .collect();
         result
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::count_dictionary::CountDictionary;
+    use crate::integer_math::factorization_factory::FactorizationFactory;
+    use crate::relation_sieve::relation::Relation;
+
+    #[test]
+    fn projective_roots_follow_the_leading_coefficient() {
+        let f = Polynomial::parse("6X^3 + 5X + 7");
+        let primes: Vec<BigInt> = [2, 3, 5, 7, 11, 13, 17, 19, 23].into_iter().map(BigInt::from).collect();
+        assert_eq!(Factory::projective_roots(&f, &primes), vec![FactorPair::projective(2), FactorPair::projective(3)]);
+
+        let (a, b) = (BigInt::from(1), BigInt::from(3));
+        let (algebraic_norm, rational_norm) = Relation::norms(&f, &Polynomial::rational_side(&BigInt::from(10)), &a, &b);
+        let (algebraic_factorization, algebraic_quotient) = FactorizationFactory::factor_with_base(&algebraic_norm, &primes);
+        let relation = Relation {
+            a: a.clone(),
+            b: b.clone(),
+            algebraic_norm,
+            rational_norm,
+            algebraic_quotient,
+            rational_quotient: BigInt::from(1),
+            algebraic_factorization,
+            rational_factorization: CountDictionary::new(),
+            is_persisted: false,
+            polynomial: 0,
+        };
+        let ideals = relation.algebraic_ideals();
+        assert!(ideals.contains(&(FactorPair::projective(3), 1)));
+        assert!(ideals.contains(&(FactorPair::new(2, 1), 1)));
+        assert!(ideals.iter().all(|(ideal, _)| ideal.divides(&a, &b)));
+    }
+}
@@ -0,0 +1,84 @@
+// src/matrix/bit_vector.rs
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A fixed-length vector over GF(2), packed 64 entries to a word so that adding two rows is a
+/// word-wide XOR rather than a loop over individual flags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BitVector {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitVector {
+    pub fn new(len: usize) -> Self {
+        BitVector { words: vec![0; len.div_ceil(WORD_BITS)], len }
+    }
+
+    pub fn from_bools(bits: &[bool]) -> Self {
+        let mut vector = BitVector::new(bits.len());
+        for (index, _) in bits.iter().enumerate().filter(|(_, &bit)| bit) {
+            vector.set(index, true);
+        }
+        vector
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len, "bit index {} out of range for length {}", index, self.len);
+        self.words[index / WORD_BITS] >> (index % WORD_BITS) & 1 == 1
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.len, "bit index {} out of range for length {}", index, self.len);
+        let mask = 1u64 << (index % WORD_BITS);
+        if value {
+            self.words[index / WORD_BITS] |= mask;
+        } else {
+            self.words[index / WORD_BITS] &= !mask;
+        }
+    }
+
+    /// Adds `other` to this vector in GF(2).
+    pub fn xor_assign(&mut self, other: &BitVector) {
+        assert_eq!(self.len, other.len, "Both vectors must have the same length.");
+        for (word, other_word) in self.words.iter_mut().zip(&other.words) {
+            *word ^= other_word;
+        }
+    }
+
+    /// Index of the lowest set bit.
+    pub fn first_one(&self) -> Option<usize> {
+        self.words
+            .iter()
+            .position(|&word| word != 0)
+            .map(|index| index * WORD_BITS + self.words[index].trailing_zeros() as usize)
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn to_bools(&self) -> Vec<bool> {
+        (0..self.len).map(|index| self.get(index)).collect()
+    }
+}
+
+/// Adds row `source` into row `target` of `rows` without cloning either.
+pub fn xor_rows(rows: &mut [BitVector], target: usize, source: usize) {
+    assert_ne!(target, source, "a row cannot be added to itself");
+    if target < source {
+        let (head, tail) = rows.split_at_mut(source);
+        head[target].xor_assign(&tail[0]);
+    } else {
+        let (head, tail) = rows.split_at_mut(target);
+        tail[0].xor_assign(&head[source]);
+    }
+}
@@ -1,41 +1,98 @@
 // src/matrix/gaussian_matrix.rs
 
-use std::collections::HashMap;
+use std::fmt::Display;
+use crate::core::cancellation_token::CancellationToken;
+use crate::core::error::GnfsError;
 use crate::core::gnfs::GNFS;
+use crate::core::progress::ProgressHandle;
+use crate::core::stage::Stage;
 use crate::relation_sieve::relation::Relation;
+use crate::matrix::bit_vector::{self, BitVector};
 use crate::matrix::gaussian_row::GaussianRow;
+use crate::matrix::matrix_solution::{self, MatrixSolution};
+use crate::matrix::relation_sets::RelationSets;
+use crate::matrix::sparse::SparseGF2Matrix;
 use num::ToPrimitive;
 
+/// Extra dependencies beyond the expected count tolerated before the null space is flagged as
+/// suspicious. A few come from factor-base primes that never divide a relation.
+pub const NULLITY_SURPLUS_LIMIT: usize = 32;
+/// Pivot columns eliminated between cancellation checks and progress updates.
+pub const CANCELLATION_CHECK_COLUMNS: usize = 64;
+
+/// Shape of the relation matrix and its null space, checked before handing dependencies to the
+/// square root stage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NullSpaceReport {
+    /// Factor-base entries, i.e. the rows of the transposed matrix.
+    pub rows: usize,
+    /// Relations, one per column.
+    pub columns: usize,
+    pub rank: usize,
+    pub nullity: usize,
+    /// Dependencies guaranteed by the column surplus alone, over the factor-base entries that
+    /// some relation actually holds.
+    pub expected_dependencies: usize,
+}
+
+impl NullSpaceReport {
+    /// Describes what looks wrong with the null space, if anything.
+    pub fn problem(&self) -> Option<String> {
+        if self.nullity == 0 {
+            Some(format!(
+                "The {} x {} relation matrix has full column rank, so there are no dependencies to try. More relations are needed.",
+                self.rows, self.columns
+            ))
+        } else if self.nullity > self.expected_dependencies + NULLITY_SURPLUS_LIMIT {
+            Some(format!(
+                "Nullity {} is far above the {} dependencies expected from the column surplus. Check for duplicate relations or missing character columns.",
+                self.nullity, self.expected_dependencies
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for NullSpaceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Matrix {} x {}: rank {}, nullity {} ({} dependencies expected)",
+            self.rows, self.columns, self.rank, self.nullity, self.expected_dependencies
+        )
+    }
+}
+
 pub struct GaussianMatrix<'a> {
-    pub m: Vec<Vec<bool>>,
+    pub m: Vec<BitVector>,
     pub free_cols: Vec<bool>,
     pub elimination_step: bool,
     pub _gnfs: &'a mut GNFS,  // Apply the lifetime to this reference
-    pub relations: Vec<Relation>,
-    pub column_index_relation_dictionary: HashMap<usize, Relation>,
-    pub relation_matrix_tuple: Vec<(Relation, Vec<bool>)>,
+    /// The relation sets the rows are built from.
+    pub sets: RelationSets,
+    /// The sets behind each selected row, as indices into `sets`; the row stands for their product.
+    pub column_sets: Vec<Vec<u32>>,
+    /// One row per selected relation set, one column per factor-base entry.
+    pub relation_matrix: SparseGF2Matrix,
 }
 
 impl GaussianMatrix<'_> {
     
-    pub fn new<'a>(gnfs: &'a mut GNFS, rels: &[Relation]) -> GaussianMatrix<'a> {
-        let mut relation_matrix_tuple = Vec::new();
+    /// Builds one row per entry of `columns`, each the product of the relation sets of `sets` it
+    /// lists. The sets are fetched one row at a time and not kept.
+    pub fn new<'a>(gnfs: &'a mut GNFS, sets: &RelationSets, columns: &[Vec<u32>]) -> Result<GaussianMatrix<'a>, GnfsError> {
         let elimination_step = false;
         let free_cols = Vec::new();
         let m = Vec::new();
     
-        let relations = rels.to_vec();
-    
-        let mut relations_as_rows: Vec<GaussianRow> = relations
-            .iter()
-            .map(|rel| GaussianRow::new(&gnfs, rel.clone()))
-            .collect();
+        let required = gnfs.current_relations_progress.smooth_relations_required_for_matrix_step().to_usize().unwrap();
     
-        let mut selected_rows: Vec<GaussianRow> = relations_as_rows
-            .iter_mut()
-            .take(gnfs.current_relations_progress.smooth_relations_required_for_matrix_step().to_usize().unwrap())
-            .map(|row| row.to_owned())
-            .collect();
+        let mut selected_rows = Vec::with_capacity(required.min(columns.len()));
+        for column in columns.iter().take(required) {
+            let relations = matrix_solution::flatten(sets.get_all(column)?.iter());
+            selected_rows.push(GaussianRow::from_set(gnfs, &relations));
+        }
     
         let max_index_rat = selected_rows.iter().map(|row| row.last_index_of_rational().unwrap_or(0)).max().unwrap();
         let max_index_alg = selected_rows.iter().map(|row| row.last_index_of_algebraic().unwrap_or(0)).max().unwrap();
@@ -47,48 +104,37 @@ impl GaussianMatrix<'_> {
             row.resize_quadratic_part(max_index_qua);
         }
     
-        let example_row = selected_rows.first().unwrap();
-        let mut new_length = example_row.get_bool_array().len();
-    
-        new_length += 1;
-    
-        let selected_rows: Vec<GaussianRow> = selected_rows.into_iter().take(new_length).collect();
-    
-        for row in selected_rows {
-            relation_matrix_tuple.push((row.source_relation.clone(), row.get_bool_array()));
+        let width = selected_rows.first().unwrap().width();
+        let mut relation_matrix = SparseGF2Matrix::new(width);
+        for row in selected_rows.into_iter().take(width + 1) {
+            relation_matrix.push_row(row.columns());
         }
+        let column_sets = columns[..relation_matrix.rows()].to_vec();
     
-        GaussianMatrix {
+        Ok(GaussianMatrix {
             m,
             free_cols,
             elimination_step,
             _gnfs: gnfs,
-            relations,
-            column_index_relation_dictionary: HashMap::new(),
-            relation_matrix_tuple,
-        }
+            sets: sets.clone(),
+            column_sets,
+            relation_matrix,
+        })
     }
 
+    /// Builds the bit-packed transpose that elimination works on: one row per factor-base entry,
+    /// one column per relation plus a trailing zero column.
     pub fn transpose_append(&mut self) {
-        let mut result = Vec::new();
-        self.column_index_relation_dictionary = HashMap::new();
-
-        let num_rows = self.relation_matrix_tuple[0].1.len();
-        for index in 0..num_rows {
-            self.column_index_relation_dictionary.insert(index, self.relation_matrix_tuple[index].0.clone());
-
-            let mut new_row: Vec<bool> = self.relation_matrix_tuple.iter().map(|bv| bv.1[index]).collect();
-            new_row.push(false);
-            result.push(new_row);
-        }
-
-        self.m = result;
+        self.m = self.relation_matrix.transpose().to_dense(self.relation_matrix.rows() + 1);
         self.free_cols = vec![false; self.m.len()];
     }
 
-    pub fn elimination(&mut self) {
+    /// Reduces the matrix, checking for cancellation and reporting the columns done to `progress`
+    /// every [`CANCELLATION_CHECK_COLUMNS`] columns. A cancelled reduction leaves the matrix
+    /// part-way reduced and `elimination_step` unset.
+    pub fn elimination(&mut self, cancel_token: &CancellationToken, progress: &ProgressHandle) -> Result<(), GnfsError> {
         if self.elimination_step {
-            return;
+            return Ok(());
         }
     
         let num_rows = self.m.len();
@@ -96,112 +142,112 @@ impl GaussianMatrix<'_> {
     
         self.free_cols = vec![false; num_cols];
     
-        let mut h = 0;
-    
-        for i in 0..num_rows {
-            if h >= num_cols {
-                break;
-            }
-    
-            let mut next = false;
-    
-            if !self.m[i][h] {
-                let mut t = i + 1;
-    
-                while t < num_rows && !self.m[t][h] {
-                    t += 1;
-                }
-    
-                if t < num_rows {
-                    self.m.swap(i, t);
-                } else {
-                    self.free_cols[h] = true;
-                    next = true;
+        // Reduced row echelon form: a column without a pivot is free and the same row is tried
+        // against the next column, so every pivot is the leading bit of its row. The trailing
+        // zero column is left out; it belongs to no relation.
+        let mut pivot_row = 0;
+        let tracker = progress.tracker(Stage::Matrix, "columns", 0, num_cols.saturating_sub(1) as u64);
+        for h in 0..num_cols.saturating_sub(1) {
+            if h % CANCELLATION_CHECK_COLUMNS == 0 {
+                if cancel_token.is_cancellation_requested() {
+                    return Err(GnfsError::Cancelled);
                 }
+                tracker.update(h as u64);
             }
+            let Some(t) = (pivot_row..num_rows).find(|&t| self.m[t].get(h)) else {
+                self.free_cols[h] = true;
+                continue;
+            };
+            self.m.swap(pivot_row, t);
     
-            if !next {
-                for j in i + 1..num_rows {
-                    if self.m[j][h] {
-                        self.m[j] = Self::add(&self.m[j], &self.m[i]);
-                    }
-                }
-    
-                for j in 0..i {
-                    if self.m[j][h] {
-                        self.m[j] = Self::add(&self.m[j], &self.m[i]);
-                    }
+            // Clear column h everywhere but the pivot row; each row addition is a word-wide XOR.
+            for j in (0..num_rows).filter(|&j| j != pivot_row) {
+                if self.m[j].get(h) {
+                    bit_vector::xor_rows(&mut self.m, j, pivot_row);
                 }
             }
-    
-            h += 1;
+            pivot_row += 1;
         }
+        tracker.update(num_cols.saturating_sub(1) as u64);
     
         self.elimination_step = true;
+        Ok(())
     }
 
-    pub fn get_solution_set(&self, number_of_solutions: usize) -> Vec<Relation> {
-        let solution_set = self.get_solution_flags(number_of_solutions);
-
-        let mut index = 0;
-        let max = self.column_index_relation_dictionary.len();
+    /// Rank of the transposed matrix, computed on a copy so the stored reduction is untouched.
+    pub fn rank(&self) -> usize {
+        let mut rows = self.m.clone();
+        let num_cols = rows.first().map_or(0, |row| row.len());
+        let mut rank = 0;
 
-        let mut result = Vec::new();
-        while index < max {
-            if solution_set[index] {
-                result.push(self.column_index_relation_dictionary[&index].clone());
+        for column in 0..num_cols {
+            let Some(pivot) = (rank..rows.len()).find(|&row| rows[row].get(column)) else {
+                continue;
+            };
+            rows.swap(rank, pivot);
+            for row in rank + 1..rows.len() {
+                if rows[row].get(column) {
+                    bit_vector::xor_rows(&mut rows, row, rank);
+                }
             }
-            index += 1;
+            rank += 1;
         }
-
-        result
+        rank
     }
 
-    fn get_solution_flags(&self, num_solutions: usize) -> Vec<bool> {
-        if !self.elimination_step {
-            panic!("Must call elimination() method first!");
-        }
-
-        if num_solutions < 1 {
-            panic!("num_solutions must be greater than 1.");
-        }
-
-        let num_rows = self.m.len();
-        let num_cols = self.m[0].len();
-
-        if num_solutions >= num_cols {
-            panic!("num_solutions must be less than the column count.");
+    /// Rank and nullity over the relation columns. The trailing column added by
+    /// [`transpose_append`](Self::transpose_append) is always zero and is not counted.
+    pub fn null_space_report(&self) -> NullSpaceReport {
+        let rows = self.m.len();
+        let columns = self.m.first().map_or(0, |row| row.len().saturating_sub(1));
+        let rank = self.rank();
+        NullSpaceReport {
+            rows,
+            columns,
+            rank,
+            nullity: columns.saturating_sub(rank),
+            expected_dependencies: columns.saturating_sub(self.relation_matrix.occupied_columns()),
         }
+    }
 
-        let mut result = vec![false; num_cols];
+    pub fn get_solution_set(&self, number_of_solutions: usize) -> Result<Vec<Relation>, GnfsError> {
+        let solution_set = self.get_solution_flags(number_of_solutions);
 
-        let mut j = 0;
-        let mut i = num_solutions;
+        let selected: Vec<u32> = self.column_sets.iter().zip(solution_set).filter(|(_, selected)| *selected).flat_map(|(sets, _)| sets.iter().copied()).collect();
+        Ok(matrix_solution::flatten(self.sets.get_all(&selected)?.iter()))
+    }
 
-        while i > 0 {
-            while !self.free_cols[j] {
-                j += 1;
-            }
-            i -= 1;
-            j += 1;
+    fn get_solution_flags(&self, num_solutions: usize) -> Vec<bool> {
+        if !self.elimination_step {
+            panic!("Must call elimination() method first!");
         }
+        matrix_solution::solution_flags(&self.m, &self.free_cols, num_solutions)
+    }
 
-        result[j - 1] = true;
-
-        for i in 0..num_rows - 1 {
-            if self.m[i][j - 1] {
-                let mut h = i;
-                while h < j - 1 {
-                    if self.m[i][h] {
-                        result[h] = true;
-                        break;
-                    }
-                    h += 1;
-                }
-            }
+    /// Hands the reduced matrix over to a [`MatrixSolution`], releasing the borrow on the GNFS
+    /// instance. Columns are paired with their relation sets in index order.
+    pub fn into_solution(self) -> MatrixSolution {
+        if !self.elimination_step {
+            panic!("Must call elimination() method first!");
         }
+        let report = self.null_space_report();
+        MatrixSolution::new(self.m, self.free_cols, self.sets, self.column_sets, report)
+    }
 
-        result
+    /// Pairs dependencies found by a solver that doesn't reduce the matrix with their relations.
+    /// Without a reduction the rank isn't known, so the report counts the dependencies found as
+    /// the nullity; the true nullity is at least that.
+    pub fn into_found_solution(self, dependencies: Vec<BitVector>) -> MatrixSolution {
+        let rows = self.relation_matrix.columns();
+        let columns = self.relation_matrix.rows();
+        let report = NullSpaceReport {
+            rows,
+            columns,
+            rank: columns.saturating_sub(dependencies.len()),
+            nullity: dependencies.len(),
+            expected_dependencies: columns.saturating_sub(self.relation_matrix.occupied_columns()),
+        };
+        MatrixSolution::from_dependencies(dependencies, self.sets, self.column_sets, report)
     }
 
     pub fn add(left: &[bool], right: &[bool]) -> Vec<bool> {
@@ -228,7 +274,59 @@ impl GaussianMatrix<'_> {
     }
 
     pub fn to_string(&self) -> String {
-        Self::matrix_to_string(&self.m)
+        self.m.iter().map(|row| Self::vector_to_string(&row.to_bools())).collect::<Vec<String>>().join("\n")
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::BigInt;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::gnfs_config::GnfsConfig;
+
+    /// Reduces the matrix with one row per entry of `relations`, each listing its odd exponents,
+    /// and returns the dependencies read off it as flags over the relations.
+    fn dependencies(name: &str, columns: usize, relations: &[&[u32]]) -> (NullSpaceReport, Vec<Vec<bool>>) {
+        let locations = DirectoryLocations::temporary(name).unwrap();
+        let mut gnfs = GNFS::with_save_locations(&CancellationToken::new(), locations, GnfsConfig::default(), &BigInt::from(45113), &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let mut relation_matrix = SparseGF2Matrix::new(columns);
+        for relation in relations {
+            relation_matrix.push_row(relation.iter().copied());
+        }
+        let mut matrix = GaussianMatrix {
+            m: Vec::new(),
+            free_cols: Vec::new(),
+            elimination_step: false,
+            _gnfs: &mut gnfs,
+            sets: RelationSets::in_memory(Vec::new()),
+            column_sets: Vec::new(),
+            relation_matrix,
+        };
+        matrix.transpose_append();
+        matrix.elimination(&CancellationToken::new(), &ProgressHandle::default()).unwrap();
+
+        let report = matrix.null_space_report();
+        let found = (1..=report.nullity)
+            .map(|number| matrix_solution::solution_flags(&matrix.m, &matrix.free_cols, number)[..relations.len()].to_vec())
+            .collect();
+        (report, found)
+    }
+
+    #[test]
+    fn elimination_finds_the_one_null_vector() {
+        // The first three relations sum to zero; adding the fourth leaves an odd exponent of 0.
+        let (report, found) = dependencies("gaussian_null_vector", 3, &[&[0, 1], &[1, 2], &[0, 2], &[0]]);
+        assert_eq!((report.rank, report.nullity), (3, 1));
+        assert_eq!(report.problem(), None);
+        assert_eq!(found, vec![vec![true, true, true, false]]);
     }
 
+    #[test]
+    fn duplicate_relations_pair_up_as_a_dependency() {
+        let (report, found) = dependencies("gaussian_duplicates", 3, &[&[0, 2], &[1], &[0, 2]]);
+        assert_eq!((report.rank, report.nullity), (2, 1));
+        assert_eq!(found, vec![vec![true, false, true]]);
+    }
 }
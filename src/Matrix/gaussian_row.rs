@@ -1,90 +1,183 @@
 // src/matrix/gaussian_row.rs
 
+use std::cmp::Ordering;
 use num::{BigInt, Signed};
 use crate::core::gnfs::GNFS;
 use crate::relation_sieve::relation::Relation;
 use crate::core::count_dictionary::CountDictionary;
+use crate::factor::factor_pair::FactorPair;
 use crate::integer_math::prime_factory::PrimeFactory;
 use crate::integer_math::quadratic_residue::QuadraticResidue;
 
 #[derive(Clone)]
 pub struct GaussianRow {
+    /// The -1 "prime" of the rational norm, so dependencies have an even number of negative a + bm.
     pub sign: bool,
-    pub rational_part: Vec<bool>,
-    pub algebraic_part: Vec<bool>,
-    pub quadratic_part: Vec<bool>,
-    pub source_relation: Relation,
+    /// The same for the algebraic norm, whose product must be a positive square as well.
+    pub algebraic_sign: bool,
+    /// Indices of the rational factor-base primes with an odd exponent, increasing.
+    pub rational_part: Vec<u32>,
+    /// Indices into the algebraic factor base of the prime ideals with an odd exponent, increasing.
+    /// Primes are not enough here: two relations can share p through different roots r, and
+    /// their product is then no square.
+    pub algebraic_part: Vec<u32>,
+    /// Indices of the quadratic characters that are -1 for this relation, increasing.
+    pub quadratic_part: Vec<u32>,
+    /// Columns each part takes up in the matrix.
+    pub rational_len: usize,
+    pub algebraic_len: usize,
+    pub quadratic_len: usize,
 }
 
 impl GaussianRow {
-    pub fn new(gnfs: &GNFS, relation: Relation) -> Self {
+    pub fn new(gnfs: &GNFS, relation: &Relation) -> Self {
         let sign = relation.rational_norm.is_negative();
+        let algebraic_sign = relation.algebraic_norm.is_negative();
 
-        let qfb = gnfs.quadratic_factor_pair_collection.clone();
         let rational_max_value = &gnfs.prime_factor_base.rational_factor_base_max;
         let algebraic_max_value = &gnfs.prime_factor_base.algebraic_factor_base_max;
 
-        let rational_part = Self::get_vector(&relation.rational_factorization, rational_max_value);
-        let algebraic_part = Self::get_vector(&relation.algebraic_factorization, algebraic_max_value);
-        let quadratic_part = qfb.into_iter()
-            .map(|qf| QuadraticResidue::get_quadratic_character(&relation, &qf))
+        let (rational_part, rational_len) = Self::get_vector(&relation.rational_factorization, rational_max_value);
+        let (algebraic_part, algebraic_len) = Self::get_ideal_vector(relation, &gnfs.algebraic_factor_pair_collection.0, algebraic_max_value);
+        let quadratic_len = gnfs.quadratic_factor_pair_collection.0.len();
+        let quadratic_part = gnfs.quadratic_factor_pair_collection.0
+            .iter()
+            .enumerate()
+            .filter(|(_, qf)| QuadraticResidue::get_quadratic_character(relation, qf))
+            .map(|(index, _)| index as u32)
             .collect();
 
         GaussianRow {
             sign,
+            algebraic_sign,
             rational_part,
             algebraic_part,
             quadratic_part,
-            source_relation: relation,
+            rational_len,
+            algebraic_len,
+            quadratic_len,
         }
     }
 
-    fn get_vector(prime_factorization_dict: &CountDictionary, max_value: &BigInt) -> Vec<bool> {
-        let mut prime_factory = PrimeFactory::new();
-        let prime_index = prime_factory.get_index_from_value(max_value);
-        let mut result = vec![false; prime_index as usize];
-        if prime_factorization_dict.len() == 0 {
-            return result;
-        }
-        for (key, value) in prime_factorization_dict.to_dict() {
-            if key > *max_value || key == BigInt::from(-1) || value % 2 == BigInt::from(0) {
-                continue;
-            }
-            let index = prime_factory.get_index_from_value(&key);
-            result[index as usize] = true;
+    /// The row of a relation set's product: the sum of its relations' rows, as exponents add and
+    /// characters multiply.
+    pub fn from_set(gnfs: &GNFS, relations: &[Relation]) -> Self {
+        let mut relations = relations.iter();
+        let mut row = Self::new(gnfs, relations.next().expect("a relation set is never empty"));
+        for relation in relations {
+            let other = Self::new(gnfs, relation);
+            row.sign ^= other.sign;
+            row.algebraic_sign ^= other.algebraic_sign;
+            row.rational_part = symmetric_difference(&row.rational_part, &other.rational_part);
+            row.algebraic_part = symmetric_difference(&row.algebraic_part, &other.algebraic_part);
+            row.quadratic_part = symmetric_difference(&row.quadratic_part, &other.quadratic_part);
         }
-        result
+        row
+    }
+
+    /// The prime indices with an odd exponent and the number of columns the part spans: one per
+    /// prime up to `max_value`, indexed from 1 as [`PrimeFactory::get_index_from_value`] does.
+    fn get_vector(prime_factorization_dict: &CountDictionary, max_value: &BigInt) -> (Vec<u32>, usize) {
+        let mut prime_factory = PrimeFactory::new();
+        let len = prime_factory.get_index_from_value(max_value) as usize + 1;
+        let mut result: Vec<u32> = prime_factorization_dict
+            .iter()
+            .filter(|(key, value)| key <= max_value && *key != BigInt::from(-1) && *value % 2 != 0)
+            .map(|(key, _)| prime_factory.get_index_from_value(&key) as u32)
+            .collect();
+        result.sort_unstable();
+        (result, len)
+    }
+
+    /// The ideal indices with an odd exponent and the number of columns the part spans, one per
+    /// entry of the sorted factor base `pairs`.
+    fn get_ideal_vector(relation: &Relation, pairs: &[FactorPair], max_value: &BigInt) -> (Vec<u32>, usize) {
+        let mut result: Vec<u32> = relation
+            .algebraic_ideals()
+            .into_iter()
+            .filter(|(ideal, exponent)| exponent % 2 != 0 && BigInt::from(ideal.p) <= *max_value)
+            .filter_map(|(ideal, _)| pairs.binary_search_by_key(&(ideal.p, ideal.r), |pair| (pair.p, pair.r)).ok())
+            .map(|index| index as u32)
+            .collect();
+        result.sort_unstable();
+        (result, pairs.len())
     }
 
     pub fn last_index_of_rational(&self) -> Option<usize> {
-        self.rational_part.iter().rposition(|&x| x)
+        self.rational_part.last().map(|&index| index as usize)
     }
 
     pub fn last_index_of_algebraic(&self) -> Option<usize> {
-        self.algebraic_part.iter().rposition(|&x| x)
+        self.algebraic_part.last().map(|&index| index as usize)
     }
 
     pub fn last_index_of_quadratic(&self) -> Option<usize> {
-        self.quadratic_part.iter().rposition(|&x| x)
+        self.quadratic_part.last().map(|&index| index as usize)
+    }
+
+    /// Matrix columns the row spans: the two signs followed by the three parts.
+    pub fn width(&self) -> usize {
+        2 + self.rational_len + self.algebraic_len + self.quadratic_len
+    }
+
+    /// The columns set in the row, in increasing order.
+    pub fn columns(&self) -> Vec<u32> {
+        let rational_start = 2;
+        let algebraic_start = rational_start + self.rational_len as u32;
+        let quadratic_start = algebraic_start + self.algebraic_len as u32;
+        let signs = [(0, self.sign), (1, self.algebraic_sign)].into_iter().filter(|(_, set)| *set).map(|(column, _)| column);
+        signs
+            .chain(self.rational_part.iter().map(|index| rational_start + index))
+            .chain(self.algebraic_part.iter().map(|index| algebraic_start + index))
+            .chain(self.quadratic_part.iter().map(|index| quadratic_start + index))
+            .collect()
     }
 
     pub fn get_bool_array(&self) -> Vec<bool> {
-        let mut result = vec![self.sign];
-        result.extend_from_slice(&self.rational_part);
-        result.extend_from_slice(&self.algebraic_part);
-        result.extend_from_slice(&self.quadratic_part);
+        let mut result = vec![false; self.width()];
+        for column in self.columns() {
+            result[column as usize] = true;
+        }
         result
     }
 
     pub fn resize_rational_part(&mut self, size: usize) {
-        self.rational_part.truncate(size + 1);
+        self.rational_len = self.rational_len.min(size + 1);
+        self.rational_part.retain(|&index| (index as usize) < self.rational_len);
     }
 
     pub fn resize_algebraic_part(&mut self, size: usize) {
-        self.algebraic_part.truncate(size + 1);
+        self.algebraic_len = self.algebraic_len.min(size + 1);
+        self.algebraic_part.retain(|&index| (index as usize) < self.algebraic_len);
     }
 
     pub fn resize_quadratic_part(&mut self, size: usize) {
-        self.quadratic_part.truncate(size + 1);
+        self.quadratic_len = self.quadratic_len.min(size + 1);
+        self.quadratic_part.retain(|&index| (index as usize) < self.quadratic_len);
+    }
+}
+
+/// Indices in exactly one of two increasing lists, increasing.
+pub(crate) fn symmetric_difference(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len() + right.len());
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            Ordering::Less => {
+                result.push(left[i]);
+                i += 1;
+            }
+            Ordering::Greater => {
+                result.push(right[j]);
+                j += 1;
+            }
+            Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
     }
-}
\ No newline at end of file
+    result.extend_from_slice(&left[i..]);
+    result.extend_from_slice(&right[j..]);
+    result
+}
@@ -0,0 +1,151 @@
+// src/matrix/matrix_solution.rs
+
+use std::collections::HashMap;
+use crate::core::error::GnfsError;
+use crate::matrix::bit_vector::BitVector;
+use crate::matrix::gaussian_matrix::NullSpaceReport;
+use crate::matrix::relation_sets::RelationSets;
+use crate::relation_sieve::relation::Relation;
+
+/// Relations whose product is a square in both number fields.
+pub type DependencySet = Vec<Relation>;
+
+/// How the dependencies are held: as the reduced matrix they are read off, or as the vectors a
+/// solver that doesn't reduce the matrix found directly.
+#[derive(Debug, Clone)]
+enum Dependencies {
+    Reduced { m: Vec<BitVector>, free_cols: Vec<bool> },
+    Found(Vec<BitVector>),
+}
+
+/// The reduced relation matrix, kept after elimination so dependencies can be read off one at a
+/// time. Unlike [`GaussianMatrix`](crate::matrix::gaussian_matrix::GaussianMatrix) it does not
+/// borrow the GNFS instance, so it can be held onto while later stages run.
+#[derive(Debug, Clone)]
+pub struct MatrixSolution {
+    dependencies: Dependencies,
+    sets: RelationSets,
+    /// The relation sets behind each column, as indices into `sets`.
+    column_sets: Vec<Vec<u32>>,
+    pub report: NullSpaceReport,
+}
+
+impl MatrixSolution {
+    pub fn new(m: Vec<BitVector>, free_cols: Vec<bool>, sets: RelationSets, column_sets: Vec<Vec<u32>>, report: NullSpaceReport) -> Self {
+        MatrixSolution { dependencies: Dependencies::Reduced { m, free_cols }, sets, column_sets, report }
+    }
+
+    /// Wraps dependencies found without reducing the matrix, one flag per relation column each.
+    pub fn from_dependencies(dependencies: Vec<BitVector>, sets: RelationSets, column_sets: Vec<Vec<u32>>, report: NullSpaceReport) -> Self {
+        MatrixSolution { dependencies: Dependencies::Found(dependencies), sets, column_sets, report }
+    }
+
+    /// Number of dependencies that [`dependencies`](Self::dependencies) will yield: one per free
+    /// column, excluding the trailing zero column, or one per vector found.
+    pub fn dependency_count(&self) -> usize {
+        match &self.dependencies {
+            Dependencies::Reduced { m, free_cols } => {
+                let num_cols = m.first().map_or(0, |row| row.len());
+                free_cols.iter().filter(|&&free| free).count().min(num_cols.saturating_sub(1))
+            }
+            Dependencies::Found(vectors) => vectors.len(),
+        }
+    }
+
+    /// The dependency belonging to the `number`th free column, or the `number`th vector found,
+    /// counting from 1. Fails only when a stored relation can't be read.
+    pub fn dependency(&self, number: usize) -> Result<DependencySet, GnfsError> {
+        let flags = match &self.dependencies {
+            Dependencies::Reduced { m, free_cols } => solution_flags(m, free_cols, number),
+            Dependencies::Found(vectors) => vectors[number - 1].to_bools(),
+        };
+        let selected: Vec<u32> = self.column_sets.iter().zip(flags).filter(|(_, selected)| *selected).flat_map(|(sets, _)| sets.iter().copied()).collect();
+        Ok(flatten(self.sets.get_all(&selected)?.iter()))
+    }
+
+    /// Yields the independent dependencies in free-column order, each built only when requested.
+    pub fn dependencies(&self) -> impl Iterator<Item = Result<DependencySet, GnfsError>> + '_ {
+        (1..=self.dependency_count()).map(move |number| self.dependency(number))
+    }
+}
+
+/// The relations of the selected column sets, in column order. A relation in an even number of
+/// them is left out, as it contributes a square to the product either way.
+pub fn flatten<'a>(sets: impl Iterator<Item = &'a Vec<Relation>>) -> DependencySet {
+    let selected: Vec<&Relation> = sets.flatten().collect();
+    let mut counts: HashMap<&Relation, usize> = HashMap::new();
+    for &relation in &selected {
+        *counts.entry(relation).or_default() += 1;
+    }
+    selected.into_iter().filter(|relation| counts.remove(relation).is_some_and(|count| count % 2 == 1)).cloned().collect()
+}
+
+/// Marks the columns that sum to zero together with the `number`th free column of the reduced
+/// matrix `m`: the free column itself and the pivot column of every row that has a bit in it.
+/// `m` must be in reduced row echelon form, so each row's pivot is its leading bit.
+pub fn solution_flags(m: &[BitVector], free_cols: &[bool], number: usize) -> Vec<bool> {
+    if number < 1 {
+        panic!("num_solutions must be greater than 1.");
+    }
+
+    let num_cols = m[0].len();
+
+    if number >= num_cols {
+        panic!("num_solutions must be less than the column count.");
+    }
+
+    let mut result = vec![false; num_cols];
+    let free = free_cols
+        .iter()
+        .enumerate()
+        .filter(|(_, &is_free)| is_free)
+        .nth(number - 1)
+        .map(|(column, _)| column)
+        .expect("fewer free columns than the requested solution number");
+    result[free] = true;
+
+    for row in m.iter().filter(|row| row.get(free)) {
+        if let Some(pivot) = row.first_one() {
+            result[pivot] = true;
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+    use crate::core::cancellation_token::CancellationToken;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::gnfs::GNFS;
+    use crate::core::gnfs_config::GnfsConfig;
+    use crate::matrix::matrix_solve::MatrixSolve;
+    use crate::square_root::square_finder::is_square;
+    use super::*;
+
+    #[test]
+    fn dependencies_multiply_to_squares_on_both_sides() {
+        let token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("matrix_solution").unwrap();
+        let mut gnfs = GNFS::with_save_locations(&token, locations, GnfsConfig::default(), &BigInt::from(45113), &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let snapshot = gnfs.attach_sieve_snapshot();
+        while gnfs.current_relations_progress.smooth_relations_counter < gnfs.current_relations_progress.smooth_relations_target_quantity {
+            gnfs.current_relations_progress.generate_relations(&token);
+            gnfs.current_relations_progress.recycle_rough_relations();
+        }
+        drop(snapshot);
+
+        let sets = RelationSets::in_memory(gnfs.current_relations_progress.relations.matrix_columns());
+        let columns: Vec<Vec<u32>> = (0..sets.len() as u32).map(|index| vec![index]).collect();
+        let (solution, _) = MatrixSolve::solve(&token, &mut gnfs, &sets, &columns).unwrap();
+        assert!(solution.dependency_count() > 0);
+        for dependency in solution.dependencies() {
+            let relations = dependency.unwrap();
+            assert!(!relations.is_empty());
+            let rational: BigInt = relations.iter().map(|relation| &relation.rational_norm).product();
+            let algebraic: BigInt = relations.iter().map(|relation| &relation.algebraic_norm).product();
+            assert!(is_square(&rational) && is_square(&algebraic), "{} relations give norms {} and {}", relations.len(), rational, algebraic);
+        }
+    }
+}
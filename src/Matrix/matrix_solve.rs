@@ -1,69 +1,209 @@
 // src/matrix/matrix_solve.rs
 
+use log::{debug, info, warn};
+use std::path::Path;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 use num::{BigInt, ToPrimitive};
 use crate::core::gnfs::GNFS;
-use crate::core::count_dictionary::CountDictionary;
+use crate::core::cancellation_token::CancellationToken;
+use crate::core::error::GnfsError;
+use crate::core::events::GnfsEvent;
+use crate::core::gnfs_config::random_stream;
+use crate::matrix::MatrixSolver;
+use crate::matrix::wiedemann;
 use crate::matrix::gaussian_matrix::GaussianMatrix;
+use crate::matrix::matrix_solution::MatrixSolution;
+use crate::matrix::relation_sets::RelationSets;
+use crate::relation_sieve::relation::Relation;
+use crate::relation_sieve::filter::filter;
+use crate::relation_sieve::storage::{RelationFormat, RelationStore, RelationWriter};
+use crate::relation_sieve::verifier::verify_relations;
 use crate::core::serialization::save;
 use crate::core::serialization::load;
-use crate::relation_sieve::relation::Relation;
 use crate::square_root::square_finder::is_square;
+
+/// Written beside the run while the matrix stage reads the smooth relations from disk.
+pub const MATRIX_RELATIONS_FILENAME: &str = "MatrixRelations.bin";
+
 pub struct MatrixSolve;
 
 impl MatrixSolve {
-    pub fn gaussian_solve(cancel_token: &Arc<AtomicBool>, gnfs: &mut GNFS) {
+    pub fn gaussian_solve(cancel_token: &CancellationToken, gnfs: &mut GNFS) {
+        let started = Instant::now();
         save::relations::smooth::append(gnfs);
         load::relations::smooth(gnfs);
-    
-        let smooth_relations = gnfs.current_relations_progress.relations.smooth_relations.clone();
-        let smooth_count = smooth_relations.len();
+        let verification = verify_relations(gnfs);
+        if verification.dropped() > 0 {
+            warn!("{}", verification);
+        } else {
+            info!("{}", verification);
+        }
+
+        let sets = Self::relation_sets(gnfs);
+        let finished = Self::reduce(cancel_token, gnfs, &sets);
+        Self::restore_relations(gnfs, sets);
+        if finished {
+            gnfs.complete_stage("matrix", started.elapsed());
+        } else {
+            // The reduction is cheap to redo next run; only the relations need to be on disk.
+            gnfs.log_event(GnfsEvent::Cancelled { stage: "matrix".to_string() });
+            save::gnfs(gnfs);
+            save::checkpoint_manifest(gnfs);
+        }
+    }
+
+    /// Filters `sets`, reduces the matrix and keeps the dependencies that are squares on both
+    /// sides. Returns false when cancelled.
+    fn reduce(cancel_token: &CancellationToken, gnfs: &mut GNFS, sets: &RelationSets) -> bool {
+        let smooth_count = sets.len();
         let required_relations_count = &gnfs.current_relations_progress.smooth_relations_required_for_matrix_step();
     
-        gnfs.log_message_slice(&format!("Total relations count: {}", smooth_count));
-        gnfs.log_message_slice(&format!("Relations required to proceed: {}", required_relations_count));
-    
-        while smooth_relations.len() >= required_relations_count.to_usize().unwrap() {
-            let selected_relations = smooth_relations.clone();
-            let solution_count = 10; // This should be retrieved appropriately
-            let mut number = 0;
-    
-            while number <= solution_count {
-                let (relations, algebraic, rational) = {
-                    let gaussian_reduction = GaussianMatrix::new(gnfs, &selected_relations);
-                    let relations = gaussian_reduction.get_solution_set(number);
+        gnfs.start_stage("matrix");
+        gnfs.log_event(GnfsEvent::parameter("Total relations count", smooth_count));
+        gnfs.log_event(GnfsEvent::parameter("Relations required to proceed", required_relations_count));
     
-                    let algebraic: BigInt = relations.iter().map(|rel| &rel.algebraic_norm).product();
-                    let rational: BigInt = relations.iter().map(|rel| &rel.rational_norm).product();
-                    (relations, algebraic, rational)
-                };
-    
-                number += 1;
-    
-                let mut alg_count_dict = CountDictionary::new();
-                let mut solution: Vec<Vec<Relation>> = Vec::new();
-    
-                for rel in &relations {
-                    alg_count_dict.combine(&rel.algebraic_factorization);
-                }
-    
-                let is_algebraic_square = is_square(&algebraic);
-                let is_rational_square = is_square(&rational);
-    
-                if is_algebraic_square && is_rational_square {
-                    solution.push(relations.clone());
-                    gnfs.current_relations_progress.add_free_relation_solution(relations.clone());
+        if smooth_count < required_relations_count.to_usize().unwrap() {
+            return true;
+        }
+        let columns = if gnfs.config.relation_filter() {
+            let filtered = match filter(gnfs, sets) {
+                Ok(filtered) => filtered,
+                Err(e) => {
+                    warn!("Cannot filter the relation sets: {}", e);
+                    return true;
                 }
-    
-                if cancel_token.load(Ordering::SeqCst) {
+            };
+            info!("{}", filtered.report);
+            gnfs.log_event(GnfsEvent::parameter("Filtered relation sets", filtered.column_map.len()));
+            filtered.column_map
+        } else {
+            (0..smooth_count as u32).map(|index| vec![index]).collect()
+        };
+        if columns.is_empty() {
+            warn!("No relation sets are left after filtering; more relations are needed.");
+            return true;
+        }
+        let (solution, dimensions) = match Self::solve(cancel_token, gnfs, sets, &columns) {
+            Ok(solved) => solved,
+            Err(GnfsError::Cancelled) => return false,
+            Err(e) => {
+                warn!("Cannot build the relation matrix: {}", e);
+                return true;
+            }
+        };
+        gnfs.check_memory_budget();
+        let report = solution.report;
+        (gnfs.run_metadata.matrix_rows, gnfs.run_metadata.matrix_cols) = dimensions;
+        (gnfs.run_metadata.matrix_rank, gnfs.run_metadata.matrix_nullity) = (report.rank, report.nullity);
+
+        gnfs.log_event(GnfsEvent::parameter("Matrix rank", report.rank));
+        gnfs.log_event(GnfsEvent::parameter("Matrix nullity", report.nullity));
+        gnfs.log_event(GnfsEvent::parameter("Expected dependencies", report.expected_dependencies));
+        if let Some(problem) = report.problem() {
+            warn!("{}: {}", report, problem);
+        }
+
+        for relations in solution.dependencies() {
+            if cancel_token.is_cancellation_requested() {
+                break;
+            }
+            let relations = match relations {
+                Ok(relations) => relations,
+                Err(e) => {
+                    warn!("Cannot read a dependency's relations: {}", e);
                     break;
                 }
+            };
+            let algebraic: BigInt = relations.iter().map(|rel| &rel.algebraic_norm).product();
+            let rational: BigInt = relations.iter().map(|rel| &rel.rational_norm).product();
+
+            let is_algebraic_square = is_square(&algebraic);
+            let is_rational_square = is_square(&rational);
+
+            if is_algebraic_square && is_rational_square {
+                gnfs.current_relations_progress.add_free_relation_solution(relations);
             }
-    
-            if cancel_token.load(Ordering::SeqCst) {
-                break;
+        }
+        true
+    }
+
+    /// The relation sets the matrix is built from. With the binary relation format and a save
+    /// directory, the verified smooth relations are written to [`MATRIX_RELATIONS_FILENAME`] and
+    /// read back through a [`RelationStore`] instead of being held in memory alongside the matrix;
+    /// [`restore_relations`](Self::restore_relations) puts them back afterwards.
+    fn relation_sets(gnfs: &mut GNFS) -> RelationSets {
+        let relations = &gnfs.current_relations_progress.relations;
+        if gnfs.save_locations.save_directory.is_empty() || gnfs.config.relation_format() != RelationFormat::Binary {
+            return RelationSets::in_memory(relations.matrix_columns());
+        }
+        let path = Path::new(&gnfs.save_locations.save_directory).join(MATRIX_RELATIONS_FILENAME);
+        let stored = RelationWriter::create(&path, gnfs)
+            .and_then(|mut writer| {
+                for relation in &relations.smooth_relations {
+                    writer.write(relation)?;
+                }
+                writer.finish()
+            })
+            .and_then(|_| RelationStore::open(&path, gnfs, gnfs.config.relation_cache_capacity()));
+        match stored {
+            Ok(store) => {
+                let combined = Arc::new(relations.combined_relations.clone());
+                gnfs.current_relations_progress.relations.smooth_relations = Vec::new();
+                RelationSets::Stored { store: Arc::new(store), combined }
+            }
+            Err(e) => {
+                warn!("Keeping the relations in memory for the matrix: {}", e);
+                RelationSets::in_memory(relations.matrix_columns())
+            }
+        }
+    }
+
+    /// Reads the smooth relations taken out by [`relation_sets`](Self::relation_sets) back into
+    /// memory and removes their store.
+    fn restore_relations(gnfs: &mut GNFS, sets: RelationSets) {
+        let RelationSets::Stored { store, .. } = sets else {
+            return;
+        };
+        let (hits, misses) = store.cache_statistics();
+        debug!("Matrix relation cache: {} hits, {} misses", hits, misses);
+        match store.iter().collect::<Result<Vec<Relation>, GnfsError>>() {
+            Ok(relations) => gnfs.current_relations_progress.relations.smooth_relations = relations,
+            Err(e) => {
+                warn!("Cannot read the matrix relations back; reloading the smooth relations file: {}", e);
+                load::relations::smooth(gnfs);
             }
         }
-    }    
-}
\ No newline at end of file
+        drop(store);
+        let path = Path::new(&gnfs.save_locations.save_directory).join(MATRIX_RELATIONS_FILENAME);
+        if let Err(e) = std::fs::remove_file(&path) {
+            debug!("Unable to remove {}: {}", path.display(), e);
+        }
+    }
+
+    /// Builds and reduces the relation matrix, one row before transposing for each entry of
+    /// `columns`, the product of the sets of `sets` it lists, returning the reduction along with
+    /// the (relation sets, columns) shape of the matrix. Fails with [`GnfsError::Cancelled`], or
+    /// when a stored relation can't be read.
+    pub fn solve(cancel_token: &CancellationToken, gnfs: &mut GNFS, sets: &RelationSets, columns: &[Vec<u32>]) -> Result<(MatrixSolution, (usize, usize)), GnfsError> {
+        let config = gnfs.config.clone();
+        let progress = gnfs.progress.clone();
+        let mut gaussian_reduction = GaussianMatrix::new(gnfs, sets, columns)?;
+        let dimensions = (gaussian_reduction.relation_matrix.rows(), gaussian_reduction.relation_matrix.columns());
+        let solver = config.matrix_solver(dimensions.0);
+        info!("Solving the {} x {} matrix with the {} solver.", dimensions.0, dimensions.1, solver);
+        let solution = match solver {
+            MatrixSolver::Gaussian => {
+                gaussian_reduction.transpose_append();
+                gaussian_reduction.elimination(cancel_token, &progress)?;
+                gaussian_reduction.into_solution()
+            }
+            MatrixSolver::Wiedemann => {
+                let transposed = gaussian_reduction.relation_matrix.transpose();
+                let dependencies = wiedemann::solve(cancel_token, &transposed, &mut config.random(random_stream::MATRIX), &progress)?;
+                gaussian_reduction.into_found_solution(dependencies)
+            }
+        };
+        Ok((solution, dimensions))
+    }
+}
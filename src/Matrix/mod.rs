@@ -1,5 +1,68 @@
 // src/matrix/mod.rs
 
+pub mod bit_vector;
 pub mod gaussian_matrix;
 pub mod gaussian_row;
+pub mod matrix_solution;
 pub mod matrix_solve;
+pub mod relation_sets;
+pub mod sparse;
+pub mod wiedemann;
+
+use std::fmt::Display;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::core::error::GnfsError;
+
+/// Relation columns from which [`MatrixSolver::select`] prefers Wiedemann. Dense elimination
+/// costs grow with the cube of the column count, Wiedemann's with the column count times the
+/// matrix weight.
+pub const WIEDEMANN_MIN_COLUMNS: usize = 5_000;
+
+/// How the matrix stage finds dependencies among the relations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatrixSolver {
+    /// Dense Gaussian elimination to reduced row echelon form; yields every dependency.
+    Gaussian,
+    /// Wiedemann's algorithm on 64 scalar projections at once; yields up to 64 dependencies
+    /// without densifying the matrix, and its products split across threads. Runs saved under
+    /// the old `block-wiedemann` name still load.
+    #[serde(alias = "block-wiedemann")]
+    Wiedemann,
+}
+
+impl MatrixSolver {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MatrixSolver::Gaussian => "gaussian",
+            MatrixSolver::Wiedemann => "wiedemann",
+        }
+    }
+
+    /// The solver used when none is configured, chosen by the number of relation columns.
+    pub fn select(columns: usize) -> Self {
+        if columns >= WIEDEMANN_MIN_COLUMNS {
+            MatrixSolver::Wiedemann
+        } else {
+            MatrixSolver::Gaussian
+        }
+    }
+}
+
+impl Display for MatrixSolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for MatrixSolver {
+    type Err = GnfsError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        [MatrixSolver::Gaussian, MatrixSolver::Wiedemann]
+            .into_iter()
+            .find(|solver| solver.name() == input)
+            .ok_or_else(|| GnfsError::InvalidParameter(format!("unknown matrix solver {:?}; expected gaussian or wiedemann", input)))
+    }
+}
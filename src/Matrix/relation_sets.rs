@@ -0,0 +1,49 @@
+// src/matrix/relation_sets.rs
+
+use std::sync::Arc;
+use crate::core::error::GnfsError;
+use crate::relation_sieve::relation::Relation;
+use crate::relation_sieve::storage::RelationStore;
+
+/// The relation sets the matrix stage is built from, each a single smooth relation or a combined
+/// set taken as one. Filtering, the matrix rows and the dependencies refer to them by index and
+/// fetch them only while they are needed.
+#[derive(Debug, Clone)]
+pub enum RelationSets {
+    /// Every set held in memory.
+    Memory(Arc<Vec<Vec<Relation>>>),
+    /// The smooth relations of a [`RelationStore`], one set each, followed by the combined sets.
+    Stored { store: Arc<RelationStore>, combined: Arc<Vec<Vec<Relation>>> },
+}
+
+impl RelationSets {
+    pub fn in_memory(sets: Vec<Vec<Relation>>) -> Self {
+        RelationSets::Memory(Arc::new(sets))
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            RelationSets::Memory(sets) => sets.len(),
+            RelationSets::Stored { store, combined } => store.len() + combined.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Set `index`. A stored relation that can't be read is an error; the store was written by
+    /// this run, so that means the file was damaged since.
+    pub fn get(&self, index: usize) -> Result<Vec<Relation>, GnfsError> {
+        match self {
+            RelationSets::Memory(sets) => Ok(sets[index].clone()),
+            RelationSets::Stored { store, .. } if index < store.len() => Ok(vec![(*store.get(index)?).clone()]),
+            RelationSets::Stored { store, combined } => Ok(combined[index - store.len()].clone()),
+        }
+    }
+
+    /// The sets at `indices`, in that order.
+    pub fn get_all(&self, indices: &[u32]) -> Result<Vec<Vec<Relation>>, GnfsError> {
+        indices.iter().map(|&index| self.get(index as usize)).collect()
+    }
+}
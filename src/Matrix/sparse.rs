@@ -0,0 +1,136 @@
+// src/matrix/sparse.rs
+
+//! Matrices over GF(2) in compressed sparse row form. A relation has a few dozen odd exponents
+//! among tens of thousands of factor-base entries, so storing the set column indices of each row
+//! takes a fraction of even a bit-packed row. [`SparseGF2Matrix::to_dense`] gives the bit-packed
+//! rows that elimination works on, and [`SparseGF2Matrix::multiply_lanes`] is the product the
+//! iterative solvers are built from.
+
+use rayon::prelude::*;
+use crate::matrix::bit_vector::BitVector;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SparseGF2Matrix {
+    /// Row `i` is `column_indices[row_offsets[i]..row_offsets[i + 1]]`.
+    row_offsets: Vec<usize>,
+    column_indices: Vec<u32>,
+    columns: usize,
+}
+
+impl SparseGF2Matrix {
+    /// An empty matrix with `columns` columns; rows are added with [`push_row`](Self::push_row).
+    pub fn new(columns: usize) -> Self {
+        SparseGF2Matrix { row_offsets: vec![0], column_indices: Vec::new(), columns }
+    }
+
+    /// Appends a row with ones in `columns`, which must be increasing and in range.
+    pub fn push_row(&mut self, columns: impl IntoIterator<Item = u32>) {
+        let start = self.column_indices.len();
+        self.column_indices.extend(columns);
+        let row = &self.column_indices[start..];
+        debug_assert!(row.windows(2).all(|pair| pair[0] < pair[1]), "row columns must be increasing");
+        debug_assert!(row.last().is_none_or(|&last| (last as usize) < self.columns), "row column out of range");
+        self.row_offsets.push(self.column_indices.len());
+    }
+
+    /// Reads the first `columns` entries of each bit-packed row.
+    pub fn from_dense(rows: &[BitVector], columns: usize) -> Self {
+        let mut matrix = SparseGF2Matrix::new(columns);
+        for row in rows {
+            matrix.push_row((0..columns).filter(|&column| row.get(column)).map(|column| column as u32));
+        }
+        matrix
+    }
+
+    pub fn rows(&self) -> usize {
+        self.row_offsets.len() - 1
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Number of ones in the matrix.
+    pub fn weight(&self) -> usize {
+        self.column_indices.len()
+    }
+
+    /// Number of columns with a one in some row.
+    pub fn occupied_columns(&self) -> usize {
+        let mut occupied = vec![false; self.columns];
+        for &column in &self.column_indices {
+            occupied[column as usize] = true;
+        }
+        occupied.into_iter().filter(|&set| set).count()
+    }
+
+    /// The columns set in row `index`, in increasing order.
+    pub fn row(&self, index: usize) -> &[u32] {
+        &self.column_indices[self.row_offsets[index]..self.row_offsets[index + 1]]
+    }
+
+    pub fn iter_rows(&self) -> impl Iterator<Item = &[u32]> + '_ {
+        (0..self.rows()).map(move |index| self.row(index))
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut counts = vec![0usize; self.columns + 1];
+        for &column in &self.column_indices {
+            counts[column as usize + 1] += 1;
+        }
+        for index in 1..counts.len() {
+            counts[index] += counts[index - 1];
+        }
+        let row_offsets = counts.clone();
+        let mut column_indices = vec![0u32; self.column_indices.len()];
+        // Rows are visited in order, so every transposed row comes out increasing.
+        for (row, columns) in self.iter_rows().enumerate() {
+            for &column in columns {
+                column_indices[counts[column as usize]] = row as u32;
+                counts[column as usize] += 1;
+            }
+        }
+        SparseGF2Matrix { row_offsets, column_indices, columns: self.rows() }
+    }
+
+    /// Bit-packed rows of length `width`, which may exceed the column count to leave room for
+    /// trailing zero columns.
+    pub fn to_dense(&self, width: usize) -> Vec<BitVector> {
+        assert!(width >= self.columns, "width {} is below the column count {}", width, self.columns);
+        self.iter_rows()
+            .map(|columns| {
+                let mut row = BitVector::new(width);
+                for &column in columns {
+                    row.set(column as usize, true);
+                }
+                row
+            })
+            .collect()
+    }
+
+    /// The product with 64 column vectors at once: bit `l` of `x[j]` is entry j of vector l, and
+    /// likewise for the result, which has one word per row. Rows are split across threads.
+    pub fn multiply_lanes(&self, x: &[u64]) -> Vec<u64> {
+        assert!(x.len() >= self.columns, "vector of length {} for {} columns", x.len(), self.columns);
+        (0..self.rows()).into_par_iter().map(|index| self.row(index).iter().fold(0, |sum, &column| sum ^ x[column as usize])).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transpose_and_dense_agree() {
+        let mut matrix = SparseGF2Matrix::new(4);
+        matrix.push_row([0, 3]);
+        matrix.push_row([]);
+        matrix.push_row([1, 2, 3]);
+        let transposed = matrix.transpose();
+        assert_eq!((transposed.rows(), transposed.columns()), (4, 3));
+        assert_eq!(transposed.row(3), &[0, 2]);
+        assert_eq!(transposed.transpose(), matrix);
+        assert_eq!(SparseGF2Matrix::from_dense(&matrix.to_dense(5), 4), matrix);
+        assert_eq!(matrix.multiply_lanes(&[1, 2, 4, 8]), vec![9, 0, 14]);
+    }
+}
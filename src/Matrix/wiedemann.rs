@@ -0,0 +1,295 @@
+// src/matrix/wiedemann.rs
+
+//! Wiedemann's algorithm over GF(2), run on 64 independent projections at once. Each of the 64
+//! bit lanes of a `u64` carries its own random start vector z and projection u, so one sparse
+//! matrix-vector product advances every lane. For each lane the sequence uᵀBⁱ(Bz) is fed to
+//! Berlekamp–Massey; its minimal polynomial λ·f(λ) = λᵏ·g(λ) gives Bᵏ·g(B)z = 0, so the chain
+//! g(B)z, B·g(B)z, ... reaches zero within k steps, and combinations of the chain vectors give the
+//! kernel vectors. The matrix itself is never eliminated, so it stays sparse and the only dense
+//! state is a handful of 64-lane vectors. The products are split by row across threads.
+//!
+//! This is not Block Wiedemann: the lanes share only the products, and each runs scalar
+//! Berlekamp–Massey on its own sequence, so every lane needs about 2N terms rather than the 2N/64
+//! a matrix Berlekamp–Massey over all lanes would.
+
+use rayon::prelude::*;
+use crate::core::cancellation_token::CancellationToken;
+use crate::core::error::GnfsError;
+use crate::core::progress::{ProgressHandle, ProgressTracker};
+use crate::core::stage::Stage;
+use crate::core::static_random::StaticRandom;
+use crate::matrix::bit_vector::BitVector;
+use crate::matrix::sparse::SparseGF2Matrix;
+
+/// Projections run side by side, one per bit of a word.
+pub const LANES: usize = u64::BITS as usize;
+/// Sequence terms computed beyond twice the matrix rank bound, so Berlekamp–Massey has settled.
+const SEQUENCE_SURPLUS: usize = 64;
+/// Matrix-vector products between cancellation checks and progress updates.
+const CANCELLATION_CHECK_PRODUCTS: usize = 64;
+
+/// B·x for 64 vectors at once, B being `matrix` padded with zero rows to `dimension` square.
+fn apply(matrix: &SparseGF2Matrix, dimension: usize, x: &[u64]) -> Vec<u64> {
+    let mut y = matrix.multiply_lanes(x);
+    y.resize(dimension, 0);
+    y
+}
+
+fn random_lanes(dimension: usize, populated: usize, random: &mut StaticRandom) -> Vec<u64> {
+    (0..dimension).map(|index| if index < populated { (random.next() as u64) << 32 | random.next() as u64 } else { 0 }).collect()
+}
+
+/// Checks for cancellation and reports `done` products in all every
+/// [`CANCELLATION_CHECK_PRODUCTS`] products of a phase, `products` counting those.
+fn check_cancelled(cancel_token: &CancellationToken, tracker: &ProgressTracker, products: usize, done: usize) -> Result<(), GnfsError> {
+    if !products.is_multiple_of(CANCELLATION_CHECK_PRODUCTS) {
+        return Ok(());
+    }
+    if cancel_token.is_cancellation_requested() {
+        return Err(GnfsError::Cancelled);
+    }
+    tracker.update(done as u64);
+    Ok(())
+}
+
+/// Lanes whose vector in `x` has any entry set.
+fn nonzero_lanes(x: &[u64]) -> u64 {
+    x.iter().fold(0, |lanes, &word| lanes | word)
+}
+
+/// Parity of Σ c_j·s_(offset + j), with both operands packed 64 bits to a word.
+fn shifted_dot(c: &[u64], s: &[u64], offset: usize) -> bool {
+    let (skip, shift) = (offset / LANES, offset % LANES);
+    let mut sum = 0u64;
+    for (index, &word) in c.iter().enumerate() {
+        if word == 0 {
+            continue;
+        }
+        let low = s.get(skip + index).copied().unwrap_or(0);
+        let window = match shift {
+            0 => low,
+            _ => low >> shift | s.get(skip + index + 1).copied().unwrap_or(0) << (LANES - shift),
+        };
+        sum ^= word & window;
+    }
+    sum.count_ones() % 2 == 1
+}
+
+/// `c ^= b·λ^shift`, truncated to the length of `c`.
+fn xor_shifted(c: &mut [u64], b: &[u64], shift: usize) {
+    let (words, bits) = (shift / LANES, shift % LANES);
+    for (index, &word) in b.iter().enumerate().filter(|(_, &word)| word != 0) {
+        if let Some(target) = c.get_mut(index + words) {
+            *target ^= word << bits;
+        }
+        if bits > 0 {
+            if let Some(target) = c.get_mut(index + words + 1) {
+                *target ^= word >> (LANES - bits);
+            }
+        }
+    }
+}
+
+/// Berlekamp–Massey over GF(2). Returns the connection polynomial c₀ + c₁x + ... + c_Lx^L, with
+/// c₀ = 1, packed into words, and its length L: s_i = Σ_{j=1..L} c_j·s_(i-j) for every i ≥ L.
+fn berlekamp_massey(sequence: &[bool]) -> (Vec<u64>, usize) {
+    let words = sequence.len() / LANES + 2;
+    // Reversed, so the terms s_i, s_(i-1), ... sit at increasing bit positions.
+    let mut reversed = vec![0u64; words];
+    for (index, _) in sequence.iter().rev().enumerate().filter(|(_, &bit)| bit) {
+        reversed[index / LANES] |= 1 << (index % LANES);
+    }
+    let mut c = vec![0u64; words];
+    let mut b = vec![0u64; words];
+    c[0] = 1;
+    b[0] = 1;
+    let (mut length, mut gap) = (0usize, 1usize);
+    for i in 0..sequence.len() {
+        if !shifted_dot(&c, &reversed, sequence.len() - 1 - i) {
+            gap += 1;
+        } else if 2 * length <= i {
+            let previous = c.clone();
+            xor_shifted(&mut c, &b, gap);
+            length = i + 1 - length;
+            b = previous;
+            gap = 1;
+        } else {
+            xor_shifted(&mut c, &b, gap);
+            gap += 1;
+        }
+    }
+    (c, length)
+}
+
+/// Finds linearly independent non-zero vectors x with M·x = 0. Usually returns about [`LANES`] of them; fewer when
+/// the kernel is small or a lane's projection misses the minimal polynomial. Reports to `progress`
+/// in matrix-vector products.
+pub fn solve(cancel_token: &CancellationToken, matrix: &SparseGF2Matrix, random: &mut StaticRandom, progress: &ProgressHandle) -> Result<Vec<BitVector>, GnfsError> {
+    let (rows, columns) = (matrix.rows(), matrix.columns());
+    let dimension = rows.max(columns);
+    let z = random_lanes(dimension, columns, random);
+    let u = random_lanes(dimension, dimension, random);
+
+    // The minimal polynomial of B has degree at most rank(B) + 1.
+    let length = 2 * (rows.min(columns) + 1) + SEQUENCE_SURPLUS;
+    let mut sequence = Vec::with_capacity(length);
+    let mut tracker = progress.tracker(Stage::Matrix, "products", 0, length as u64);
+    let mut v = apply(matrix, dimension, &z);
+    for products in 0..length {
+        check_cancelled(cancel_token, &tracker, products, products)?;
+        sequence.push(u.iter().zip(&v).fold(0u64, |sum, (&u, &v)| sum ^ (u & v)));
+        v = apply(matrix, dimension, &v);
+    }
+
+    // For each lane, c_j ≠ 0 for j ≤ last and λ·f(λ) = Σ c_j·λ^(L + 1 - j), so
+    // g(λ) = Σ c_j·λ^(last - j) and k = L + 1 - last.
+    let polynomials: Vec<(Vec<u64>, usize, usize)> = (0..LANES)
+        .into_par_iter()
+        .map(|lane| {
+            let bits: Vec<bool> = sequence.iter().map(|&word| word >> lane & 1 == 1).collect();
+            let (c, length) = berlekamp_massey(&bits);
+            let last = (0..=length).rev().find(|&j| c[j / LANES] >> (j % LANES) & 1 == 1).unwrap_or(0);
+            (c, last, length + 1 - last)
+        })
+        .collect();
+    let degree = polynomials.iter().map(|(_, last, _)| *last).max().unwrap_or(0);
+    let steps = polynomials.iter().map(|(_, _, k)| *k).max().unwrap_or(0);
+    tracker.set_total((length + degree + 1 + steps) as u64);
+
+    // w = g(B)·z, every lane with its own coefficients.
+    let mut w = vec![0u64; dimension];
+    let mut power = z;
+    for exponent in 0..=degree {
+        check_cancelled(cancel_token, &tracker, exponent, length + exponent)?;
+        let mask = polynomials.iter().enumerate().fold(0u64, |mask, (lane, (c, last, _))| {
+            let selected = exponent <= *last && c[(last - exponent) / LANES] >> ((last - exponent) % LANES) & 1 == 1;
+            mask | (selected as u64) << lane
+        });
+        for (target, &word) in w.iter_mut().zip(&power) {
+            *target ^= word & mask;
+        }
+        if exponent < degree {
+            power = apply(matrix, dimension, &power);
+        }
+    }
+
+    // Each lane's chain w, Bw, B²w, ... ends in a kernel vector, but chains longer than one step
+    // end inside B's image, a small part of the kernel. Every vector of every chain is kept with
+    // its image instead, and combinations whose images cancel give kernel vectors from the whole
+    // span, w itself included.
+    let mut candidates = Echelon::default();
+    let mut kernel = Echelon::default();
+    let mut live = nonzero_lanes(&w);
+    let mut current = w;
+    for step in 0..=steps {
+        if live == 0 {
+            break;
+        }
+        check_cancelled(cancel_token, &tracker, step, length + degree + step)?;
+        let next = apply(matrix, dimension, &current);
+        for lane in (0..LANES).filter(|&lane| live >> lane & 1 == 1) {
+            if let Some(vector) = candidates.insert(lane_vector(&next, lane, rows), lane_vector(&current, lane, columns)) {
+                kernel.insert(vector.clone(), vector);
+            }
+        }
+        live &= nonzero_lanes(&next);
+        current = next;
+    }
+
+    tracker.update((length + degree + 1 + steps) as u64);
+    Ok(kernel.inputs)
+}
+
+/// Entries `0..len` of lane `lane`'s vector.
+fn lane_vector(x: &[u64], lane: usize, len: usize) -> BitVector {
+    let mut vector = BitVector::new(len);
+    for (index, &word) in x.iter().take(len).enumerate() {
+        vector.set(index, word >> lane & 1 == 1);
+    }
+    vector
+}
+
+/// Keys in row echelon form, each with the same combination of its inputs' payloads.
+#[derive(Default)]
+struct Echelon {
+    /// (pivot, key, payload), sorted by pivot; each key's pivot is its lowest set bit.
+    rows: Vec<(usize, BitVector, BitVector)>,
+    /// Payloads of the inputs that added a row, i.e. whose keys were independent.
+    inputs: Vec<BitVector>,
+}
+
+impl Echelon {
+    /// Adds `key` if it is independent of the keys so far. Otherwise returns `payload` plus the
+    /// payloads of the keys that sum to `key`, or nothing if that sum is zero.
+    fn insert(&mut self, key: BitVector, payload: BitVector) -> Option<BitVector> {
+        let (mut reduced_key, mut reduced_payload) = (key, payload.clone());
+        for (pivot, row_key, row_payload) in &self.rows {
+            if reduced_key.get(*pivot) {
+                reduced_key.xor_assign(row_key);
+                reduced_payload.xor_assign(row_payload);
+            }
+        }
+        match reduced_key.first_one() {
+            Some(pivot) => {
+                let position = self.rows.partition_point(|(row_pivot, _, _)| *row_pivot < pivot);
+                self.rows.insert(position, (pivot, reduced_key, reduced_payload));
+                self.inputs.push(payload);
+                None
+            }
+            None => reduced_payload.first_one().map(|_| reduced_payload),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_kernel_vectors_of_a_wide_matrix() {
+        let mut random = StaticRandom::from_seed(7, 0);
+        let (rows, columns) = (40, 60);
+        let mut m = SparseGF2Matrix::new(columns);
+        for _ in 0..rows {
+            let mut row: Vec<u32> = (0..4).map(|_| random.next_max(columns as u32)).collect();
+            row.sort_unstable();
+            row.dedup();
+            m.push_row(row);
+        }
+
+        let kernel = solve(&CancellationToken::new(), &m, &mut random, &ProgressHandle::default()).unwrap();
+        assert!(kernel.len() >= 20, "only {} kernel vectors found", kernel.len());
+        for vector in &kernel {
+            assert!(vector.first_one().is_some());
+            for row in m.iter_rows() {
+                let parity = row.iter().filter(|&&column| vector.get(column as usize)).count() % 2;
+                assert_eq!(parity, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn solves_a_matrix_past_the_selection_threshold() {
+        use crate::matrix::{MatrixSolver, WIEDEMANN_MIN_COLUMNS};
+
+        // One row per factor-base entry and one column per relation, as the matrix stage passes it.
+        let mut random = StaticRandom::from_seed(11, 0);
+        let columns = WIEDEMANN_MIN_COLUMNS + 200;
+        let rows = WIEDEMANN_MIN_COLUMNS;
+        assert_eq!(MatrixSolver::select(columns), MatrixSolver::Wiedemann);
+        let mut m = SparseGF2Matrix::new(columns);
+        for _ in 0..rows {
+            let mut row: Vec<u32> = (0..8).map(|_| random.next_max(columns as u32)).collect();
+            row.sort_unstable();
+            row.dedup();
+            m.push_row(row);
+        }
+
+        let kernel = solve(&CancellationToken::new(), &m, &mut random, &ProgressHandle::default()).unwrap();
+        assert!(kernel.len() >= 32, "only {} kernel vectors found", kernel.len());
+        for vector in &kernel {
+            assert!(vector.first_one().is_some());
+            assert!(m.iter_rows().all(|row| row.iter().filter(|&&column| vector.get(column as usize)).count() % 2 == 0));
+        }
+    }
+}
@@ -0,0 +1,110 @@
+// src/polynomial/candidate_pool.rs
+
+//! The best few polynomials a selection search has seen, ranked by [`PolynomialQuality::score`](crate::polynomial::quality::PolynomialQuality::score).
+//! Searches score every candidate they build and offer it here; later steps, such as the root
+//! optimization, work through the survivors rather than trusting the single best size.
+
+use num::{BigInt, One};
+use rayon::prelude::*;
+use crate::polynomial::polynomial::Polynomial;
+use crate::polynomial::quality::{coefficients, PolynomialCandidate};
+
+/// Candidates kept when no capacity is configured.
+pub const DEFAULT_POOL_CAPACITY: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolynomialCandidatePool {
+    capacity: usize,
+    /// Best score first.
+    candidates: Vec<PolynomialCandidate>,
+    offered: usize,
+}
+
+impl PolynomialCandidatePool {
+    pub fn new(capacity: usize) -> Self {
+        PolynomialCandidatePool { capacity: capacity.max(1), candidates: Vec::new(), offered: 0 }
+    }
+
+    /// Keeps `candidate` if it is among the best `capacity` seen so far and not already held.
+    pub fn offer(&mut self, candidate: PolynomialCandidate) {
+        self.offered += 1;
+        if self.candidates.iter().any(|held| held.polynomial == candidate.polynomial && held.m == candidate.m) {
+            return;
+        }
+        let score = candidate.quality.score();
+        let position = self.candidates.partition_point(|held| held.quality.score() <= score);
+        if position < self.capacity {
+            self.candidates.insert(position, candidate);
+            self.candidates.truncate(self.capacity);
+        }
+    }
+
+    pub fn best(&self) -> Option<&PolynomialCandidate> {
+        self.candidates.first()
+    }
+
+    /// The candidates held, best first.
+    pub fn candidates(&self) -> &[PolynomialCandidate] {
+        &self.candidates
+    }
+
+    pub fn into_candidates(self) -> Vec<PolynomialCandidate> {
+        self.candidates
+    }
+
+    /// Candidates offered over the pool's life, including those not kept.
+    pub fn offered(&self) -> usize {
+        self.offered
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+}
+
+impl Extend<PolynomialCandidate> for PolynomialCandidatePool {
+    fn extend<I: IntoIterator<Item = PolynomialCandidate>>(&mut self, candidates: I) {
+        for candidate in candidates {
+            self.offer(candidate);
+        }
+    }
+}
+
+/// Base-m expansions of N for the bases within `range` of `base`, scored in parallel. Bases
+/// whose degree-th power exceeds N would leave ƒ short of full degree and are skipped.
+pub fn base_m_candidates(n: &BigInt, degree: usize, base: &BigInt, range: u64, capacity: usize) -> PolynomialCandidatePool {
+    let degree = degree.max(1);
+    let highest = n.nth_root(degree as u32);
+    let bases: Vec<BigInt> = (0..=2 * range)
+        .map(|offset| base + offset - range)
+        .filter(|m| m > &BigInt::one() && m <= &highest)
+        .collect();
+    let mut pool = PolynomialCandidatePool::new(capacity);
+    pool.extend(
+        bases
+            .par_iter()
+            .map(|m| {
+                let polynomial = Polynomial::from_base_m(n, m, degree);
+                PolynomialCandidate::new(&coefficients(&polynomial), m.clone())
+            })
+            .filter(|candidate| candidate.polynomial.degree() == degree)
+            .collect::<Vec<_>>(),
+    );
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_keeps_the_best_scores_in_order() {
+        let n = BigInt::from(1_000_000_007u64) * BigInt::from(998_244_353u64);
+        let pool = base_m_candidates(&n, 3, &n.nth_root(3), 50, 4);
+        assert_eq!(pool.offered(), 51);
+        assert_eq!(pool.candidates().len(), 4);
+        let scores: Vec<f64> = pool.candidates().iter().map(|c| c.quality.score()).collect();
+        assert!(scores.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(pool.candidates().iter().all(|c| c.polynomial.evaluate(&c.m) == n));
+    }
+}
@@ -7,6 +7,7 @@ use std::ops::{Rem, Sub};
 use std::cmp::Ordering;
 use crate::polynomial::polynomial::Polynomial;
 use crate::polynomial::polynomial::Term;
+use crate::polynomial::optimized_exp::{windowed_exponentiate_mod, ModulusContext};
 
 pub fn gcd(left: &Polynomial, right: &Polynomial, modulus: &BigInt) -> Polynomial {
     let mut poly1 = left.clone();
@@ -111,6 +112,10 @@ pub fn pow_mod(poly: &Polynomial, exponent: &BigInt, modulus: &BigInt) -> Polyno
 }
 
 pub fn exponentiate_mod(start_poly: &Polynomial, exponent: &BigInt, f: &Polynomial, p: &BigInt) -> Polynomial {
+    if let Ok(context) = ModulusContext::cached(f, p) {
+        return windowed_exponentiate_mod(start_poly, exponent, &context);
+    }
+
     let mut result = Polynomial::one();
     if exponent == &BigInt::zero() {
         return result;
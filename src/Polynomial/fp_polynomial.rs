@@ -0,0 +1,185 @@
+// src/polynomial/fp_polynomial.rs
+
+//! Dense polynomials over 𝔽ₚ with machine-word coefficients, for primes below 2³² where every
+//! product of two residues fits in a u64. The square root stage runs a gcd against ƒ for each
+//! candidate prime; doing that Euclid on `Vec<u64>` instead of `HashMap<usize, BigInt>` removes
+//! the per-coefficient allocation that dominated the irreducibility screen.
+
+use num::{BigInt, Integer, ToPrimitive};
+use crate::polynomial::polynomial::Polynomial;
+use crate::square_root::finite_field_arithmetic::modular_multiplicative_inverse;
+
+/// Coefficients in ascending order of exponent, always trimmed so the last one is non-zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FpPolynomial {
+    coefficients: Vec<u64>,
+    prime: u64,
+}
+
+fn mul_mod(a: u64, b: u64, p: u64) -> u64 {
+    a * b % p
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64, p: u64) -> u64 {
+    let mut result = 1 % p;
+    base %= p;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, p);
+        }
+        base = mul_mod(base, base, p);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// a⁻¹ mod p by Fermat; p is prime and a is non-zero.
+fn inverse_mod(a: u64, p: u64) -> u64 {
+    pow_mod(a, p - 2, p)
+}
+
+impl FpPolynomial {
+    /// Reduces `polynomial` mod `prime`, or returns `None` when the prime is too large for the
+    /// word-sized path.
+    pub fn from_polynomial(polynomial: &Polynomial, prime: &BigInt) -> Option<Self> {
+        let p = prime.to_u64().filter(|&p| p >= 2 && p <= u32::MAX as u64)?;
+        let mut coefficients = vec![0u64; polynomial.degree() + 1];
+        for (&exponent, coefficient) in &polynomial.terms {
+            coefficients[exponent] = coefficient.mod_floor(prime).to_u64()?;
+        }
+        let mut result = FpPolynomial { coefficients, prime: p };
+        result.trim();
+        Some(result)
+    }
+
+    pub fn to_polynomial(&self) -> Polynomial {
+        if self.is_zero() {
+            return Polynomial::zero();
+        }
+        let mut polynomial = Polynomial::zero();
+        polynomial.terms.clear();
+        for (exponent, &coefficient) in self.coefficients.iter().enumerate().filter(|(_, &c)| c != 0) {
+            polynomial.terms.insert(exponent, BigInt::from(coefficient));
+        }
+        polynomial
+    }
+
+    fn trim(&mut self) {
+        while self.coefficients.last() == Some(&0) {
+            self.coefficients.pop();
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// Degree of the polynomial; the zero polynomial reports 0.
+    pub fn degree(&self) -> usize {
+        self.coefficients.len().saturating_sub(1)
+    }
+
+    /// Scales so the leading coefficient is 1.
+    pub fn make_monic(&mut self) {
+        if let Some(&leading) = self.coefficients.last() {
+            let inverse = inverse_mod(leading, self.prime);
+            for coefficient in &mut self.coefficients {
+                *coefficient = mul_mod(*coefficient, inverse, self.prime);
+            }
+        }
+    }
+
+    /// Replaces `self` with its remainder modulo `divisor`, which must be non-zero.
+    pub fn rem_assign(&mut self, divisor: &FpPolynomial) {
+        assert!(!divisor.is_zero(), "division by the zero polynomial");
+        let p = self.prime;
+        let divisor_degree = divisor.degree();
+        let inverse = inverse_mod(divisor.coefficients[divisor_degree], p);
+
+        while !self.is_zero() && self.degree() >= divisor_degree {
+            let shift = self.degree() - divisor_degree;
+            let factor = mul_mod(self.coefficients[self.degree()], inverse, p);
+            for (i, &d) in divisor.coefficients.iter().enumerate() {
+                let slot = &mut self.coefficients[shift + i];
+                *slot = (*slot + p - mul_mod(factor, d, p)) % p;
+            }
+            self.trim();
+        }
+    }
+
+    /// Monic gcd of `left` and `right` by Euclid's algorithm; gcd(0, 0) is 0.
+    pub fn gcd(left: &FpPolynomial, right: &FpPolynomial) -> FpPolynomial {
+        let mut a = left.clone();
+        let mut b = right.clone();
+        while !b.is_zero() {
+            a.rem_assign(&b);
+            std::mem::swap(&mut a, &mut b);
+        }
+        a.make_monic();
+        a
+    }
+}
+
+/// Remainder of `left` modulo `right` over 𝔽ₚ, for any non-zero `right`.
+fn rem_bigint(left: &Polynomial, right: &Polynomial, prime: &BigInt) -> Polynomial {
+    let divisor_degree = right.degree();
+    let inverse = modular_multiplicative_inverse(&right[divisor_degree].mod_floor(prime), prime)
+        .expect("leading coefficient is a unit mod a prime");
+    let mut remainder = normalize(left, prime);
+
+    while !remainder.is_zero() && remainder.degree() >= divisor_degree {
+        let degree = remainder.degree();
+        let shift = degree - divisor_degree;
+        let factor = (&remainder[degree] * &inverse).mod_floor(prime);
+        for (&exponent, coefficient) in &right.terms {
+            let slot = &mut remainder[shift + exponent];
+            *slot = (&*slot - &factor * coefficient).mod_floor(prime);
+        }
+        remainder.remove_zeros();
+    }
+    remainder
+}
+
+fn normalize(polynomial: &Polynomial, prime: &BigInt) -> Polynomial {
+    let mut reduced = polynomial.field_modulus(prime);
+    reduced.remove_zeros();
+    reduced
+}
+
+/// gcd over 𝔽ₚ for primes too large for [`FpPolynomial`]: the same monic Euclid on `BigInt`
+/// coefficients.
+pub fn gcd_bigint(left: &Polynomial, right: &Polynomial, prime: &BigInt) -> Polynomial {
+    let mut a = normalize(left, prime);
+    let mut b = normalize(right, prime);
+    while !b.is_zero() {
+        let remainder = rem_bigint(&a, &b, prime);
+        a = b;
+        b = remainder;
+    }
+    if a.is_zero() {
+        return a;
+    }
+    let inverse = modular_multiplicative_inverse(&a[a.degree()], prime).expect("leading coefficient is a unit mod a prime");
+    normalize(&Polynomial::multiply(&a, &Polynomial::from_term(inverse, 0)), prime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_and_bigint_paths_agree() {
+        let p = BigInt::from(13);
+        // (X + 2)(X + 5) and (X + 2)(2X + 7) share the factor X + 2.
+        let left = Polynomial::parse("X^2 + 7X + 10");
+        let right = Polynomial::parse("2X^2 + 11X + 14");
+
+        let fast = FpPolynomial::gcd(
+            &FpPolynomial::from_polynomial(&left, &p).unwrap(),
+            &FpPolynomial::from_polynomial(&right, &p).unwrap(),
+        );
+        assert_eq!(fast.to_polynomial(), Polynomial::parse("X + 2"));
+        assert_eq!(gcd_bigint(&left, &right, &p), Polynomial::parse("X + 2"));
+        assert!(FpPolynomial::from_polynomial(&left, &(BigInt::from(1u64) << 40)).is_none());
+    }
+}
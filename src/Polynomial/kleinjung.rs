@@ -0,0 +1,209 @@
+// src/polynomial/kleinjung.rs
+
+//! Polynomial selection after Kleinjung for inputs too large for the plain base-m expansion.
+//! Rather than a_d = 1 and m = ⌊N^(1/d)⌋, leading coefficients a_d with many small prime factors
+//! are tried in turn. Each fixes m near (N/a_d)^(1/d), N is expanded in base m under that leading
+//! coefficient with digits centred on zero, and the result is size-optimized by translations
+//! x → x + k, which move m to m - k, and rotations ƒ + (λx + μ)(x - m); neither changes ƒ(m) = N.
+//! Leading coefficients are searched in parallel and the candidates ranked by skewed size plus α,
+//! the best few kept in a pool, until the time budget runs out or enough candidates have been
+//! scored. The rational side stays X - m, since the rest of the pipeline expects a monic linear one.
+
+use std::time::{Duration, Instant};
+use log::{debug, info};
+use num::{BigInt, Integer, One, Signed, Zero};
+use rayon::prelude::*;
+use crate::core::cancellation_token::CancellationToken;
+use crate::polynomial::candidate_pool::{PolynomialCandidatePool, DEFAULT_POOL_CAPACITY};
+use crate::polynomial::quality::{skewed_log_size, PolynomialCandidate};
+
+/// Leading coefficients are multiples of this, so ƒ tends to have roots mod 2, 3 and 5.
+pub const DEFAULT_LEADING_COEFFICIENT_STEP: u64 = 60;
+/// Bases tried on each side of (N/a_d)^(1/d) for every leading coefficient.
+const M_OFFSETS: i64 = 4;
+/// Largest translation or rotation step tried first by the size optimization, as a power of two.
+const LARGEST_STEP_BITS: u32 = 24;
+
+#[derive(Debug, Clone)]
+pub struct KleinjungSearch {
+    pub degree: usize,
+    /// Leading coefficients tried are step, 2·step, ... up to `max_leading_coefficient`.
+    pub leading_coefficient_step: u64,
+    pub max_leading_coefficient: u64,
+    /// The search stops once this much time has passed.
+    pub budget: Duration,
+    /// Or once this many candidates have been scored, whichever comes first.
+    pub max_candidates: usize,
+    /// Candidates kept for ranking.
+    pub pool_capacity: usize,
+}
+
+impl KleinjungSearch {
+    pub fn new(degree: usize, budget: Duration) -> Self {
+        KleinjungSearch {
+            degree,
+            leading_coefficient_step: DEFAULT_LEADING_COEFFICIENT_STEP,
+            max_leading_coefficient: u64::MAX,
+            budget,
+            max_candidates: usize::MAX,
+            pool_capacity: DEFAULT_POOL_CAPACITY,
+        }
+    }
+
+    /// The best `pool_capacity` candidates found within the budget and the candidate limit; empty
+    /// if not even the first leading coefficients gave a polynomial of full degree. Leading
+    /// coefficients are searched a batch at a time, one per worker thread, and both limits are
+    /// checked between batches.
+    pub fn run(&self, n: &BigInt, cancel_token: &CancellationToken) -> PolynomialCandidatePool {
+        let started = Instant::now();
+        let step = self.leading_coefficient_step.max(1);
+        let batch = rayon::current_num_threads().max(1) as u64;
+        let mut pool = PolynomialCandidatePool::new(self.pool_capacity);
+        let mut leading = step;
+        while leading <= self.max_leading_coefficient
+            && (pool.offered() == 0 || (started.elapsed() < self.budget && pool.offered() < self.max_candidates))
+            && !cancel_token.should_stop()
+        {
+            let leading_coefficients: Vec<u64> = (0..batch)
+                .map_while(|i| leading.checked_add(i.checked_mul(step)?))
+                .take_while(|&a_d| a_d <= self.max_leading_coefficient)
+                .collect();
+            let candidates: Vec<PolynomialCandidate> = leading_coefficients
+                .par_iter()
+                .flat_map_iter(|&a_d| self.candidates_for(n, &BigInt::from(a_d)))
+                .collect();
+            for candidate in candidates {
+                if pool.best().is_none_or(|best| candidate.quality.score() < best.quality.score()) {
+                    debug!("Kleinjung: a_d = {} gives {}", candidate.polynomial[candidate.polynomial.degree()], candidate.quality);
+                }
+                pool.offer(candidate);
+            }
+            leading = match leading_coefficients.last().and_then(|last| last.checked_add(step)) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        if let Some(best) = pool.best() {
+            info!("Kleinjung selection kept {} of {} candidates in {:.1?}; the best has {}", pool.candidates().len(), pool.offered(), started.elapsed(), best.quality);
+        }
+        pool
+    }
+
+    /// Size-optimized polynomials with leading coefficient `leading` for the bases nearest
+    /// (N/a_d)^(1/d).
+    pub fn candidates_for(&self, n: &BigInt, leading: &BigInt) -> Vec<PolynomialCandidate> {
+        let degree = self.degree.max(2);
+        let m0 = (n / leading).nth_root(degree as u32);
+        (-M_OFFSETS..=M_OFFSETS)
+            .map(|offset| &m0 + offset)
+            .filter(|m| m > &BigInt::one())
+            .filter_map(|m| {
+                let coefficients = expand(n, &m, leading, degree)?;
+                let (coefficients, m) = size_optimize(coefficients, m);
+                Some(PolynomialCandidate::new(&coefficients, m))
+            })
+            .collect()
+    }
+}
+
+/// N in base m with a_d = `leading` and the lower digits centred on zero, or None when the
+/// remainder left for them doesn't fit below m^d.
+fn expand(n: &BigInt, m: &BigInt, leading: &BigInt, degree: usize) -> Option<Vec<BigInt>> {
+    let mut coefficients = vec![BigInt::zero(); degree + 1];
+    coefficients[degree] = leading.clone();
+    let mut remainder = n - leading * m.pow(degree as u32);
+    for exponent in (0..degree).rev() {
+        let power = m.pow(exponent as u32);
+        let digit = rounded_division(&remainder, &power);
+        remainder -= &digit * &power;
+        coefficients[exponent] = digit;
+    }
+    (remainder.is_zero() && coefficients[degree - 1].abs() <= *m).then_some(coefficients)
+}
+
+fn rounded_division(numerator: &BigInt, denominator: &BigInt) -> BigInt {
+    (numerator * BigInt::from(2) + denominator).div_floor(&(denominator * BigInt::from(2)))
+}
+
+/// ƒ(x + k).
+pub fn translate(coefficients: &[BigInt], k: &BigInt) -> Vec<BigInt> {
+    // Horner's rule on polynomials: ((a_d)(x + k) + a_{d-1})(x + k) + ...
+    let mut result: Vec<BigInt> = Vec::with_capacity(coefficients.len());
+    for coefficient in coefficients.iter().rev() {
+        let mut shifted = vec![BigInt::zero(); result.len() + 1];
+        for (i, c) in result.iter().enumerate() {
+            shifted[i + 1] += c;
+            shifted[i] += c * k;
+        }
+        shifted[0] += coefficient;
+        result = shifted;
+    }
+    result
+}
+
+/// ƒ + (λx + μ)(x - m).
+pub fn rotate(coefficients: &[BigInt], m: &BigInt, lambda: &BigInt, mu: &BigInt) -> Vec<BigInt> {
+    let mut result = coefficients.to_vec();
+    result[2] += lambda;
+    result[1] += mu - lambda * m;
+    result[0] -= mu * m;
+    result
+}
+
+/// Descends on the skewed size over translations and the two rotations, trying steps from
+/// 2^[`LARGEST_STEP_BITS`] down to 1 and keeping every move that helps.
+pub fn size_optimize(mut coefficients: Vec<BigInt>, mut m: BigInt) -> (Vec<BigInt>, BigInt) {
+    let mut size = skewed_log_size(&coefficients).1;
+    for bits in (0..=LARGEST_STEP_BITS).rev() {
+        let step = BigInt::one() << bits;
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for delta in [step.clone(), -&step] {
+                let moves = [
+                    (translate(&coefficients, &delta), &m - &delta),
+                    (rotate(&coefficients, &m, &delta, &BigInt::zero()), m.clone()),
+                    (rotate(&coefficients, &m, &BigInt::zero(), &delta), m.clone()),
+                ];
+                for (candidate, candidate_m) in moves {
+                    let candidate_size = skewed_log_size(&candidate).1;
+                    if candidate_size < size - 1e-9 && candidate.last().is_some_and(|leading| !leading.is_zero()) {
+                        (coefficients, m, size) = (candidate, candidate_m, candidate_size);
+                        improved = true;
+                    }
+                }
+            }
+        }
+    }
+    (coefficients, m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polynomial::quality::coefficients;
+
+    #[test]
+    fn candidates_keep_m_a_root_of_n() {
+        let n: BigInt = "1234567891011121314151617181920212223242526272829303132333435363738394041424344454647484950".parse().unwrap();
+        let search = KleinjungSearch { max_leading_coefficient: 240, ..KleinjungSearch::new(5, Duration::from_secs(5)) };
+        let pool = search.run(&n, &CancellationToken::new());
+        let best = pool.best().expect("no candidate");
+        assert_eq!(best.polynomial.evaluate(&best.m), n);
+        assert_eq!(coefficients(&best.polynomial).len(), 6);
+
+        let base_m = expand(&n, &n.nth_root(5), &BigInt::one(), 5).unwrap();
+        assert!(best.quality.log_size < skewed_log_size(&base_m).1);
+    }
+
+    #[test]
+    fn candidate_limit_ends_the_search_before_the_budget() {
+        let n = BigInt::from(1000000016000000063u64);
+        let search = KleinjungSearch { max_candidates: 100, ..KleinjungSearch::new(3, Duration::from_secs(600)) };
+        let started = Instant::now();
+        let pool = search.run(&n, &CancellationToken::new());
+        // The limit is checked between batches, one leading coefficient per thread.
+        assert!(pool.offered() >= 100 && pool.offered() < 100 + (2 * M_OFFSETS as usize + 1) * rayon::current_num_threads());
+        assert!(started.elapsed() < Duration::from_secs(60));
+    }
+}
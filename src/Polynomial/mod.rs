@@ -1,5 +1,55 @@
 // src/polynomial/mod.rs
 
 pub mod algorithms;
+pub mod candidate_pool;
 pub mod field;
-pub mod polynomial;
\ No newline at end of file
+pub mod fp_polynomial;
+pub mod kleinjung;
+pub mod optimize;
+pub mod optimized_exp;
+pub mod parser;
+pub mod polynomial;
+pub mod quality;
+
+use std::fmt::Display;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::core::error::GnfsError;
+
+/// How the algebraic polynomial is chosen. The base-m expansion unless another method is
+/// configured; Kleinjung's search pays for its time from about 90 digits on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PolynomialSelection {
+    /// The base-m expansion of N for the given base.
+    #[default]
+    BaseM,
+    /// A timed search over leading coefficients with size optimization; see [`kleinjung`].
+    Kleinjung,
+}
+
+impl PolynomialSelection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PolynomialSelection::BaseM => "base-m",
+            PolynomialSelection::Kleinjung => "kleinjung",
+        }
+    }
+}
+
+impl Display for PolynomialSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for PolynomialSelection {
+    type Err = GnfsError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        [PolynomialSelection::BaseM, PolynomialSelection::Kleinjung]
+            .into_iter()
+            .find(|selection| selection.name() == input)
+            .ok_or_else(|| GnfsError::InvalidParameter(format!("unknown polynomial selection {:?}; expected base-m or kleinjung", input)))
+    }
+}
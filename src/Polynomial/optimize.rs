@@ -0,0 +1,123 @@
+// src/polynomial/optimize.rs
+
+//! Root and size optimization of the selected pair ƒ, X - m. Rotations ƒ + (λx + μ)(x - m) and
+//! translations x → x + k, which move m to m - k, leave m a root of ƒ mod N but change how large
+//! and how smooth the values of ƒ are. The pass descends on Murphy's E over the sieve region, the
+//! mean probability that both sides are smooth around an ellipse of the region's area at ƒ's
+//! skewness, and keeps the best pair found.
+
+use num::{BigInt, Integer, One, Signed, ToPrimitive, Zero};
+use crate::polynomial::kleinjung::{rotate, translate};
+use crate::polynomial::quality::{alpha, skewed_log_size};
+use crate::relation_sieve::yield_prediction::dickman_rho;
+
+/// Angles sampled around the ellipse for each evaluation of E.
+const MURPHY_E_SAMPLES: usize = 100;
+/// Largest translation or rotation step tried, as a power of two; the bit length of m caps it.
+const LARGEST_STEP_BITS: u64 = 24;
+
+/// What Murphy's E is measured against: the factor base bounds and the sieve region.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MurphyRegion {
+    pub algebraic_bound: f64,
+    pub rational_bound: f64,
+    /// Pairs in the region; the sieve covers about 2·range·range of them.
+    pub area: f64,
+}
+
+impl MurphyRegion {
+    /// The region |a| ≤ `range`, 1 ≤ b ≤ `range` under the given factor base bounds.
+    pub fn new(algebraic_bound: &BigInt, rational_bound: &BigInt, range: usize) -> Self {
+        MurphyRegion {
+            algebraic_bound: algebraic_bound.to_f64().unwrap_or(f64::MAX).max(2.0),
+            rational_bound: rational_bound.to_f64().unwrap_or(f64::MAX).max(2.0),
+            area: 2.0 * (range.max(1) as f64).powi(2),
+        }
+    }
+}
+
+/// Murphy's E of ƒ with rational side X - m over `region`; larger is better. Only comparable
+/// between pairs for the same N and region.
+pub fn murphy_e(coefficients: &[BigInt], m: &BigInt, region: &MurphyRegion) -> f64 {
+    let (skewness, _) = skewed_log_size(coefficients);
+    let alpha_algebraic = alpha(coefficients);
+    let values: Vec<f64> = coefficients.iter().map(|c| c.to_f64().unwrap_or(f64::MAX)).collect();
+    let m = m.to_f64().unwrap_or(f64::MAX);
+    let radius = (region.area / std::f64::consts::PI).sqrt();
+    let (log_algebraic_bound, log_rational_bound) = (region.algebraic_bound.ln(), region.rational_bound.ln());
+
+    (0..MURPHY_E_SAMPLES)
+        .map(|i| {
+            let theta = std::f64::consts::PI * (i as f64 + 0.5) / MURPHY_E_SAMPLES as f64;
+            let (a, b) = (radius * skewness.sqrt() * theta.cos(), radius / skewness.sqrt() * theta.sin());
+            // F(a, b) = b^d ƒ(a/b) by Horner's rule in a and b.
+            let algebraic = values.iter().rev().fold(0.0, |value, c| value * a / b + c) * b.powi(values.len() as i32 - 1);
+            let rational = a - m * b;
+            let u_algebraic = ((algebraic.abs().max(1.0).ln() + alpha_algebraic) / log_algebraic_bound).max(0.0);
+            let u_rational = (rational.abs().max(1.0).ln() / log_rational_bound).max(0.0);
+            dickman_rho(u_algebraic) * dickman_rho(u_rational)
+        })
+        .sum::<f64>()
+        / MURPHY_E_SAMPLES as f64
+}
+
+/// The rotation and translation of (ƒ, m) with the largest Murphy's E that a descent over steps
+/// from 2^[`LARGEST_STEP_BITS`] down to 1 finds, with that E. The leading coefficient is kept,
+/// and so is a nonzero constant term and content 1, so ƒ stays of full degree and primitive.
+pub fn optimize(mut coefficients: Vec<BigInt>, mut m: BigInt, region: &MurphyRegion) -> (Vec<BigInt>, BigInt, f64) {
+    let mut best = murphy_e(&coefficients, &m, region);
+    if coefficients.len() < 3 {
+        return (coefficients, m, best);
+    }
+    for bits in (0..=LARGEST_STEP_BITS.min(m.bits())).rev() {
+        let step = BigInt::one() << bits;
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for delta in [step.clone(), -&step] {
+                let moves = [
+                    (translate(&coefficients, &delta), &m - &delta),
+                    (rotate(&coefficients, &m, &delta, &BigInt::zero()), m.clone()),
+                    (rotate(&coefficients, &m, &BigInt::zero(), &delta), m.clone()),
+                ];
+                for (candidate, candidate_m) in moves {
+                    if candidate_m <= BigInt::one() || !is_usable(&candidate) {
+                        continue;
+                    }
+                    let e = murphy_e(&candidate, &candidate_m, region);
+                    if e > best * (1.0 + 1e-9) {
+                        (coefficients, m, best) = (candidate, candidate_m, e);
+                        improved = true;
+                    }
+                }
+            }
+        }
+    }
+    (coefficients, m, best)
+}
+
+fn is_usable(coefficients: &[BigInt]) -> bool {
+    !coefficients[0].is_zero()
+        && coefficients.last().is_some_and(|leading| !leading.is_zero())
+        && coefficients.iter().fold(BigInt::zero(), |content, c| content.gcd(c)).abs().is_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polynomial::polynomial::Polynomial;
+    use crate::polynomial::quality::{coefficients, from_coefficients};
+
+    #[test]
+    fn optimization_keeps_m_a_root_of_n_and_never_lowers_e() {
+        let n: BigInt = "1522605027922533360535618378132637429718068114961380688657908494580122963258952897654000350692006139".parse().unwrap();
+        let m = n.nth_root(5);
+        let base_m = coefficients(&Polynomial::from_base_m(&n, &m, 5));
+        let region = MurphyRegion::new(&BigInt::from(3_000_000), &BigInt::from(1_000_000), 1 << 20);
+        let before = murphy_e(&base_m, &m, &region);
+
+        let (optimized, m, e) = optimize(base_m, m, &region);
+        assert_eq!(from_coefficients(&optimized).evaluate(&m), n);
+        assert!(e >= before);
+    }
+}
@@ -0,0 +1,224 @@
+// src/polynomial/optimized_exp.rs
+
+//! Sliding-window exponentiation in 𝔽ₚ[X]/(ƒ). A [`ModulusContext`] holds X^{d+i} mod ƒ for every
+//! power a product of two reduced polynomials can reach, so reducing a product is a fixed linear
+//! fold instead of a long division, and coefficients are kept below p throughout. Contexts are
+//! cached by (ƒ, p) because the square root stage exponentiates modulo the same pair many times.
+
+use lazy_static::lazy_static;
+use num::{BigInt, Integer, One, Zero};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::core::error::GnfsError;
+use crate::polynomial::polynomial::Polynomial;
+use crate::square_root::finite_field_arithmetic::modular_multiplicative_inverse;
+
+/// Contexts kept before the cache is emptied; the square root stage only needs a handful of primes
+/// at a time, but the irreducibility search walks through many.
+const CONTEXT_CACHE_LIMIT: usize = 64;
+
+type ContextKey = (Vec<(usize, BigInt)>, BigInt);
+
+lazy_static! {
+    static ref CONTEXT_CACHE: Mutex<HashMap<ContextKey, Arc<ModulusContext>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone)]
+pub struct ModulusContext {
+    prime: BigInt,
+    degree: usize,
+    /// `reductions[i]` is X^{degree + i} mod ƒ as `degree` dense coefficients, for i < degree - 1.
+    reductions: Vec<Vec<BigInt>>,
+}
+
+impl ModulusContext {
+    /// Precomputes the reduction table for `modulus` over 𝔽ₚ. The modulus need not be monic, but
+    /// its leading coefficient must be a unit mod p.
+    pub fn new(modulus: &Polynomial, prime: &BigInt) -> Result<Self, GnfsError> {
+        let mut modulus = modulus.field_modulus(prime);
+        modulus.remove_zeros();
+        let degree = modulus.degree();
+        let inverse = modular_multiplicative_inverse(&modulus[degree], prime)
+            .filter(|_| degree > 0 && !modulus[degree].is_zero())
+            .ok_or_else(|| GnfsError::InvalidParameter(format!("cannot reduce modulo {} over 𝔽_{}", modulus, prime)))?;
+
+        // X^d ≡ -(f₀ + f₁X + … + f_{d-1}X^{d-1}) / f_d.
+        let first: Vec<BigInt> = (0..degree).map(|i| (-&modulus[i] * &inverse).mod_floor(prime)).collect();
+        let mut reductions = vec![first.clone()];
+        for _ in 1..degree.saturating_sub(1) {
+            let previous = reductions.last().expect("table starts non-empty");
+            // Multiply by X: shift up one place and fold the overflowing X^d term back in.
+            let carry = previous[degree - 1].clone();
+            let mut next = vec![BigInt::zero(); degree];
+            for i in (1..degree).rev() {
+                next[i] = previous[i - 1].clone();
+            }
+            for (slot, reduction) in next.iter_mut().zip(&first) {
+                *slot = (&*slot + &carry * reduction).mod_floor(prime);
+            }
+            reductions.push(next);
+        }
+
+        Ok(ModulusContext { prime: prime.clone(), degree, reductions })
+    }
+
+    /// Returns the shared context for (`modulus`, `prime`), building it on first use.
+    pub fn cached(modulus: &Polynomial, prime: &BigInt) -> Result<Arc<Self>, GnfsError> {
+        let mut coefficients: Vec<(usize, BigInt)> = modulus
+            .terms
+            .iter()
+            .map(|(&exponent, coefficient)| (exponent, coefficient.mod_floor(prime)))
+            .filter(|(_, coefficient)| !coefficient.is_zero())
+            .collect();
+        coefficients.sort();
+        let key = (coefficients, prime.clone());
+
+        let mut cache = CONTEXT_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(context) = cache.get(&key) {
+            return Ok(Arc::clone(context));
+        }
+        let context = Arc::new(ModulusContext::new(modulus, prime)?);
+        if cache.len() >= CONTEXT_CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(key, Arc::clone(&context));
+        Ok(context)
+    }
+
+    /// Fully reduces an arbitrary polynomial to `degree` dense coefficients.
+    fn to_dense(&self, polynomial: &Polynomial) -> Vec<BigInt> {
+        let mut coefficients = vec![BigInt::zero(); (polynomial.degree() + 1).max(self.degree)];
+        for (&exponent, coefficient) in &polynomial.terms {
+            coefficients[exponent] = coefficient.mod_floor(&self.prime);
+        }
+        // Peel off one degree at a time using X^d mod ƒ until the table can finish the job.
+        while coefficients.len() > 2 * self.degree - 1 {
+            let top = coefficients.pop().expect("longer than the table");
+            let shift = coefficients.len() - self.degree;
+            for (i, reduction) in self.reductions[0].iter().enumerate() {
+                coefficients[shift + i] = (&coefficients[shift + i] + &top * reduction).mod_floor(&self.prime);
+            }
+        }
+        self.fold(coefficients)
+    }
+
+    /// Reduces up to 2d - 1 coefficients to d using the table.
+    fn fold(&self, mut coefficients: Vec<BigInt>) -> Vec<BigInt> {
+        for (i, high) in coefficients.split_off(self.degree.min(coefficients.len())).into_iter().enumerate() {
+            if high.is_zero() {
+                continue;
+            }
+            for (slot, reduction) in coefficients.iter_mut().zip(&self.reductions[i]) {
+                *slot += &high * reduction;
+            }
+        }
+        coefficients.resize(self.degree, BigInt::zero());
+        for coefficient in &mut coefficients {
+            *coefficient = coefficient.mod_floor(&self.prime);
+        }
+        coefficients
+    }
+
+    fn multiply(&self, left: &[BigInt], right: &[BigInt]) -> Vec<BigInt> {
+        let mut product = vec![BigInt::zero(); left.len() + right.len() - 1];
+        for (i, a) in left.iter().enumerate().filter(|(_, a)| !a.is_zero()) {
+            for (j, b) in right.iter().enumerate() {
+                product[i + j] += a * b;
+            }
+        }
+        self.fold(product)
+    }
+
+    fn to_polynomial(dense: &[BigInt]) -> Polynomial {
+        let mut polynomial = Polynomial::zero();
+        for (exponent, coefficient) in dense.iter().enumerate().filter(|(_, c)| !c.is_zero()) {
+            polynomial.terms.insert(exponent, coefficient.clone());
+        }
+        polynomial.remove_zeros();
+        polynomial
+    }
+}
+
+/// Window width for an exponent of `bits` bits, balancing the 2^{k-1} precomputed odd powers
+/// against the multiplications they save.
+fn window_width(bits: u64) -> usize {
+    match bits {
+        0..=7 => 1,
+        8..=23 => 2,
+        24..=79 => 3,
+        80..=239 => 4,
+        _ => 5,
+    }
+}
+
+/// base^exponent in 𝔽ₚ[X]/(ƒ) by left-to-right sliding windows over the exponent bits.
+pub fn windowed_exponentiate_mod(base: &Polynomial, exponent: &BigInt, context: &ModulusContext) -> Polynomial {
+    let one = {
+        let mut one = vec![BigInt::zero(); context.degree];
+        one[0] = BigInt::one();
+        context.fold(one)
+    };
+    if exponent <= &BigInt::zero() {
+        return ModulusContext::to_polynomial(&one);
+    }
+
+    let bits = exponent.bits();
+    let width = window_width(bits);
+    let base = context.to_dense(base);
+
+    // odd_powers[i] = base^(2i + 1)
+    let square = context.multiply(&base, &base);
+    let mut odd_powers = vec![base];
+    for i in 1..(1usize << (width - 1)) {
+        let next = context.multiply(&odd_powers[i - 1], &square);
+        odd_powers.push(next);
+    }
+
+    let mut result = one;
+    let mut bit = bits as i64 - 1;
+    while bit >= 0 {
+        if !exponent.bit(bit as u64) {
+            result = context.multiply(&result, &result);
+            bit -= 1;
+            continue;
+        }
+        // Longest window ending in a set bit, at most `width` bits wide.
+        let mut low = (bit - width as i64 + 1).max(0);
+        while !exponent.bit(low as u64) {
+            low += 1;
+        }
+        let mut value = 0usize;
+        for position in (low..=bit).rev() {
+            result = context.multiply(&result, &result);
+            value = (value << 1) | exponent.bit(position as u64) as usize;
+        }
+        result = context.multiply(&result, &odd_powers[value >> 1]);
+        bit = low - 1;
+    }
+
+    ModulusContext::to_polynomial(&result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polynomial::fp_polynomial::FpPolynomial;
+
+    #[test]
+    fn windowed_matches_repeated_multiplication() {
+        let f = Polynomial::parse("X^3 + 15X^2 + 29X + 8");
+        let p = BigInt::from(11);
+        let context = ModulusContext::cached(&f, &p).unwrap();
+        let base = Polynomial::parse("3X^2 + 5X + 7");
+
+        let mut expected = Polynomial::one();
+        for exponent in 0..=40u32 {
+            assert_eq!(windowed_exponentiate_mod(&base, &BigInt::from(exponent), &context), expected, "exponent {}", exponent);
+            let mut next = FpPolynomial::from_polynomial(&Polynomial::multiply(&expected, &base), &p).unwrap();
+            next.rem_assign(&FpPolynomial::from_polynomial(&f, &p).unwrap());
+            expected = next.to_polynomial();
+        }
+        // ƒ is irreducible mod 11, so every non-zero element has order dividing 11³ - 1.
+        assert_eq!(windowed_exponentiate_mod(&base, &BigInt::from(1330), &context), Polynomial::one());
+    }
+}
@@ -0,0 +1,184 @@
+// src/polynomial/parser.rs
+
+//! Tokenizer and parser for polynomials written the way people type them: `X^3 + 15X^2 - 7`,
+//! `2*x^2 - x`, or the `2X^3 + -4X^2` form produced by `Display`. Like terms are summed, and any
+//! unexpected character is reported with its position so bad input from the command line or a
+//! saved file is caught instead of silently misread.
+
+use std::collections::HashMap;
+use num::{BigInt, Zero};
+use crate::core::error::GnfsError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(BigInt),
+    Variable,
+    Caret,
+    Star,
+    Plus,
+    Minus,
+}
+
+/// A token and the character column it starts at, for error messages.
+type Spanned = (Token, usize);
+
+fn error(input: &str, column: usize, message: &str) -> GnfsError {
+    GnfsError::InvalidParameter(format!("cannot parse polynomial '{}' at column {}: {}", input, column + 1, message))
+}
+
+fn tokenize(input: &str) -> Result<Vec<Spanned>, GnfsError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        let token = match c {
+            _ if c.is_whitespace() => {
+                index += 1;
+                continue;
+            }
+            '0'..='9' => {
+                let start = index;
+                while index < chars.len() && chars[index].is_ascii_digit() {
+                    index += 1;
+                }
+                let digits: String = chars[start..index].iter().collect();
+                let value = digits.parse::<BigInt>().map_err(|e| error(input, start, &e.to_string()))?;
+                tokens.push((Token::Number(value), start));
+                continue;
+            }
+            'X' | 'x' => Token::Variable,
+            '^' => Token::Caret,
+            '*' => Token::Star,
+            '+' => Token::Plus,
+            '-' | '−' => Token::Minus,
+            _ => return Err(error(input, index, &format!("unexpected character '{}'", c))),
+        };
+        tokens.push((token, index));
+        index += 1;
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Spanned>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens.get(self.position).map_or(self.input.chars().count(), |(_, column)| *column)
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn number(&mut self) -> Option<BigInt> {
+        match self.peek() {
+            Some(Token::Number(value)) => {
+                let value = value.clone();
+                self.position += 1;
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// term := [number] ['*'] 'X' ['^' number] | number
+    fn term(&mut self) -> Result<(BigInt, usize), GnfsError> {
+        let coefficient = self.number();
+        let starred = coefficient.is_some() && self.eat(&Token::Star);
+
+        if !self.eat(&Token::Variable) {
+            return match coefficient {
+                Some(coefficient) if !starred => Ok((coefficient, 0)),
+                _ => Err(error(self.input, self.column(), "expected a coefficient or X")),
+            };
+        }
+
+        let exponent = if self.eat(&Token::Caret) {
+            let column = self.column();
+            let exponent = self.number().ok_or_else(|| error(self.input, column, "expected an exponent after '^'"))?;
+            usize::try_from(&exponent).map_err(|_| error(self.input, column, "exponent is too large"))?
+        } else {
+            1
+        };
+        Ok((coefficient.unwrap_or_else(|| BigInt::from(1)), exponent))
+    }
+
+    /// polynomial := [sign] term (('+' | '-') [sign] term)*
+    fn polynomial(&mut self) -> Result<HashMap<usize, BigInt>, GnfsError> {
+        if self.tokens.is_empty() {
+            return Err(error(self.input, 0, "the polynomial is empty"));
+        }
+
+        let mut terms: HashMap<usize, BigInt> = HashMap::new();
+        let mut negative = false;
+        loop {
+            // Accept a sign after the operator, so `+ -4X^2` from `Display` reads back.
+            while let Some(sign @ (Token::Plus | Token::Minus)) = self.peek() {
+                negative ^= *sign == Token::Minus;
+                self.position += 1;
+            }
+            let (coefficient, exponent) = self.term()?;
+            *terms.entry(exponent).or_insert_with(BigInt::zero) += if negative { -coefficient } else { coefficient };
+
+            match self.peek() {
+                None => break,
+                Some(Token::Plus) | Some(Token::Minus) => negative = false,
+                Some(_) => return Err(error(self.input, self.column(), "expected '+' or '-' between terms")),
+            }
+        }
+
+        terms.retain(|_, coefficient| !coefficient.is_zero());
+        if terms.is_empty() {
+            terms.insert(0, BigInt::zero());
+        }
+        Ok(terms)
+    }
+}
+
+/// Parses `input` into a map from exponent to coefficient.
+pub fn parse_terms(input: &str) -> Result<HashMap<usize, BigInt>, GnfsError> {
+    let tokens = tokenize(input)?;
+    Parser { input, tokens, position: 0 }.polynomial()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_typed_and_displayed_forms() {
+        let terms = parse_terms("2X^3 - 4x^2 + 3*X - 1 + X^3").unwrap();
+        assert_eq!(terms[&3], BigInt::from(3));
+        assert_eq!(terms[&2], BigInt::from(-4));
+        assert_eq!(terms[&1], BigInt::from(3));
+        assert_eq!(terms[&0], BigInt::from(-1));
+
+        let displayed = parse_terms("1X^2 + -5X^1 + 6X^0").unwrap();
+        assert_eq!(displayed[&1], BigInt::from(-5));
+        assert_eq!(displayed[&0], BigInt::from(6));
+    }
+
+    #[test]
+    fn reports_the_offending_column() {
+        let message = parse_terms("X^2 + 3Y").unwrap_err().to_string();
+        assert!(message.contains("column 8"), "{}", message);
+        assert!(parse_terms("X^ + 1").is_err());
+        assert!(parse_terms("2X 3").is_err());
+        assert!(parse_terms("").is_err());
+    }
+}
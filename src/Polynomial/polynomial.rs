@@ -6,6 +6,10 @@ use std::collections::HashMap;
 use num::{BigInt, Zero, One, Integer, Signed};
 use log::error;
 use std::fmt::{Display, Formatter, Result};
+use crate::core::error::GnfsError;
+use crate::polynomial::fp_polynomial::{self, FpPolynomial};
+use crate::polynomial::optimized_exp::{self, ModulusContext};
+use crate::polynomial::parser;
 use crate::square_root::finite_field_arithmetic::remainder;
 use lazy_static::lazy_static;
 
@@ -142,6 +146,28 @@ impl Polynomial {
         Polynomial::from_term(BigInt::one(), 0)
     }
 
+    /// The base-m expansion of `n`: the polynomial of the given degree whose coefficients are the
+    /// base-`m` digits of `n`, so that ƒ(m) = n. Anything above m^(degree + 1) stays in the leading
+    /// coefficient.
+    pub fn from_base_m(n: &BigInt, m: &BigInt, degree: usize) -> Self {
+        let mut terms = HashMap::new();
+        let mut remaining = n.clone();
+        for exponent in 0..degree {
+            let (quotient, digit) = remaining.div_mod_floor(m);
+            if !digit.is_zero() {
+                terms.insert(exponent, digit);
+            }
+            remaining = quotient;
+        }
+        terms.insert(degree, remaining);
+        Polynomial { terms }
+    }
+
+    /// X - m, the rational side paired with a polynomial that has m as a root mod N.
+    pub fn rational_side(m: &BigInt) -> Self {
+        Polynomial::from_roots(std::slice::from_ref(m))
+    }
+
     pub fn from_roots(roots: &[BigInt]) -> Self {
         let polys: Vec<Polynomial> = roots
             .iter()
@@ -154,42 +180,52 @@ impl Polynomial {
         polys.iter().fold(Polynomial::new(vec![Term::new(BigInt::one(), 0)]), |acc, poly| acc * poly.clone())
     }
 
-    pub fn mod_mod(to_reduce: &Polynomial, mod_poly: &Polynomial, prime_modulus: &BigInt) -> Polynomial {
+    /// `to_reduce` modulo `mod_poly` and `prime_modulus`; fails when `mod_poly` isn't monic mod the
+    /// prime.
+    pub fn mod_mod(to_reduce: &Polynomial, mod_poly: &Polynomial, prime_modulus: &BigInt) -> std::result::Result<Polynomial, GnfsError> {
         match mod_poly.cmp(to_reduce) {
-            Ordering::Greater => to_reduce.clone(),
-            Ordering::Equal => Polynomial::zero(),
+            Ordering::Greater => Ok(to_reduce.clone()),
+            Ordering::Equal => Ok(Polynomial::zero()),
             Ordering::Less => remainder(to_reduce, mod_poly, prime_modulus),
         }
     }
 
+    /// Monic gcd over 𝔽ₚ. Primes below 2³² take the word-sized path in [`FpPolynomial`]; a
+    /// constant gcd is reported as 1.
     pub fn field_gcd(left: &Polynomial, right: &Polynomial, modulus: &BigInt) -> Polynomial {
-        let mut polynomial = left.clone();
-        let mut polynomial2 = right.clone();
-        if polynomial2.degree() > polynomial.degree() {
-            std::mem::swap(&mut polynomial, &mut polynomial2);
-        }
-        while !polynomial2.is_zero() {
-            let to_reduce = polynomial.clone();
-            std::mem::swap(&mut polynomial, &mut polynomial2);
-            polynomial2 = Polynomial::mod_mod(&to_reduce, &polynomial2.clone(), modulus);
-        }
-        if polynomial.degree() == 0 {
+        let gcd = match (FpPolynomial::from_polynomial(left, modulus), FpPolynomial::from_polynomial(right, modulus)) {
+            (Some(left), Some(right)) => FpPolynomial::gcd(&left, &right).to_polynomial(),
+            _ => fp_polynomial::gcd_bigint(left, right, modulus),
+        };
+        if gcd.degree() == 0 {
             return Polynomial::one();
         }
-        polynomial
+        gcd
     }
 
+    /// Parses a polynomial such as `X^3 + 15X^2 - 7`, logging malformed input and returning the
+    /// zero polynomial for it. Use [`try_parse`](Self::try_parse) where the caller can report the error.
     pub fn parse(input: &str) -> Self {
-        let input = input.replace(" ", "").replace("−", "-").replace("-", "+-");
-        let terms: Vec<Term> = input
-            .split('+')
-            .filter(|s| !s.is_empty())
-            .map(Term::parse)
-            .collect();
+        Self::try_parse(input).unwrap_or_else(|e| {
+            error!("{}", e);
+            Polynomial::zero()
+        })
+    }
+
+    pub fn try_parse(input: &str) -> std::result::Result<Self, GnfsError> {
+        parser::parse_terms(input).map(|terms| Polynomial { terms })
+    }
+
+    /// X^p - X, built directly rather than through the parser.
+    pub fn x_pow_minus_x(p: usize) -> Self {
+        let mut terms = HashMap::new();
+        terms.insert(p, BigInt::one());
+        *terms.entry(1).or_insert_with(BigInt::zero) -= 1;
+        terms.retain(|_, coefficient: &mut BigInt| !coefficient.is_zero());
         if terms.is_empty() {
-            error!("Invalid input: {}", input);
+            return Polynomial::zero();
         }
-        Polynomial::new(terms)
+        Polynomial { terms }
     }
 
     pub fn degree(&self) -> usize {
@@ -325,27 +361,29 @@ impl Polynomial {
         Polynomial { terms }
     }
 
-    pub fn field_modulus_from_polynomial(&self, mod_poly: &Polynomial) -> Polynomial {
+    /// The remainder of `self` divided by the monic `mod_poly` over the integers; fails when
+    /// `mod_poly` isn't monic.
+    pub fn field_modulus_from_polynomial(&self, mod_poly: &Polynomial) -> std::result::Result<Polynomial, GnfsError> {
         let compare = mod_poly.cmp(self);
         if compare == Ordering::Greater {
-            self.clone()
+            Ok(self.clone())
         } else if compare == Ordering::Equal {
-            Polynomial::zero()
+            Ok(Polynomial::zero())
         } else {
             Polynomial::remainder(self, mod_poly)
         }
     }
 
-    fn remainder(left: &Polynomial, right: &Polynomial) -> Polynomial {
+    fn remainder(left: &Polynomial, right: &Polynomial) -> std::result::Result<Polynomial, GnfsError> {
         if right.degree() > left.degree() || right.cmp(left) == Ordering::Greater {
-            Polynomial::zero()
+            Ok(Polynomial::zero())
         } else {
             let right_degree = right.degree();
             let quotient_degree = left.degree() - right_degree + 1;
     
             let leading_coefficient = right[right_degree].clone();
             if leading_coefficient != BigInt::one() {
-                panic!("This method expects only monomials (leading coefficient is 1) for the right-hand-side polynomial.");
+                return Err(GnfsError::InvalidParameter(format!("reducing modulo {} needs a monic modulus", right)));
             }
     
             let mut rem = left.clone();
@@ -361,13 +399,13 @@ impl Polynomial {
             }
     
             let terms: Vec<Term> = rem.terms.into_iter().map(|(exponent, coefficient)| Term::new(coefficient, exponent)).collect();
-            Polynomial::new(terms)
+            Ok(Polynomial::new(terms))
         }
     }
     
 
     pub fn multiply(left: &Polynomial, right: &Polynomial) -> Self {
-        let mut terms = vec![Term::new(BigInt::zero(), 0); left.degree() + right.degree() + 1];
+        let mut terms: Vec<Term> = (0..=left.degree() + right.degree()).map(|exponent| Term::new(BigInt::zero(), exponent)).collect();
         for i in 0..=left.degree() {
             for j in 0..=right.degree() {
                 let coefficient = &left[i] * &right[j];
@@ -377,26 +415,19 @@ impl Polynomial {
         Polynomial::new(terms)
     }
 
-    // pub fn make_monic(&mut self, polynomial_base: &BigInt) {
-    //     let degree = self.degree();
-    //     if self[degree].abs() > BigInt::one() {
-    //         let factor = (&self[degree] - BigInt::one()) * polynomial_base;
-    //         self[degree] = BigInt::one();
-    //         self[degree - 1] += factor;
-    //     }
-    // }
-
-    pub fn make_monic(&self, polynomial_base: &BigInt) -> Polynomial {
+    /// The monic F(X) = c^(d-1) ƒ(X/c) for ƒ of degree d with leading coefficient c. Its root cα is
+    /// an algebraic integer even when α isn't, and F(cm) = c^(d-1) ƒ(m), so cm is a root mod N
+    /// wherever m is. ƒ itself when it is already monic.
+    pub fn make_monic(&self) -> Polynomial {
         let degree = self.degree();
-        let mut result = self.clone();
-
-        if result[degree].abs() > BigInt::one() {
-            let factor = (&result[degree] - BigInt::one()) * polynomial_base;
-            result[degree] = BigInt::one();
-            result[degree - 1] += factor;
-        }
-
-        result
+        let leading = &self[degree];
+        let terms = (0..=degree)
+            .map(|i| match i {
+                i if i == degree => Term::new(BigInt::one(), i),
+                i => Term::new(&self[i] * leading.pow((degree - 1 - i) as u32), i),
+            })
+            .collect();
+        Polynomial::new(terms)
     }
 
     pub fn get_derivative_polynomial(&self) -> Self {
@@ -409,7 +440,13 @@ impl Polynomial {
         Polynomial { terms }
     }
 
-    pub fn exponentiate_mod(base: &Polynomial, exponent: &BigInt, modulus: &Polynomial, prime: &BigInt) -> Polynomial {
+    /// base^exponent mod (modulus, prime), through the cached sliding-window path when the
+    /// modulus has a unit leading coefficient; fails when it isn't monic mod the prime.
+    pub fn exponentiate_mod(base: &Polynomial, exponent: &BigInt, modulus: &Polynomial, prime: &BigInt) -> std::result::Result<Polynomial, GnfsError> {
+        if let Ok(context) = ModulusContext::cached(modulus, prime) {
+            return Ok(optimized_exp::windowed_exponentiate_mod(base, exponent, &context));
+        }
+
         let mut result = Polynomial::new(vec![Term::new(BigInt::one(), 0)]);
         let mut base = base.clone();
         let mut exponent = exponent.clone();
@@ -417,14 +454,14 @@ impl Polynomial {
         while exponent > BigInt::zero() {
             if exponent.is_odd() {
                 result = Polynomial::multiply(&result, &base);
-                result = Polynomial::mod_mod(&result, modulus, prime);
+                result = Polynomial::mod_mod(&result, modulus, prime)?;
             }
             base = base.square();
-            base = Polynomial::mod_mod(&base, modulus, prime);
+            base = Polynomial::mod_mod(&base, modulus, prime)?;
             exponent /= 2;
         }
 
-        result
+        Ok(result)
     }
 }
 
@@ -563,6 +600,14 @@ impl Display for Polynomial {
     }
 }
 
+impl std::str::FromStr for Polynomial {
+    type Err = GnfsError;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        Polynomial::try_parse(input)
+    }
+}
+
 impl Default for Polynomial {
     fn default() -> Self {
         Polynomial::zero()
@@ -667,4 +712,28 @@ mod tests {
         assert_eq!(derivative[1], BigInt::from(-8));
         assert_eq!(derivative[0], BigInt::from(3));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_make_monic() {
+        let poly = Polynomial::parse("2X^3 + X^2 + 15X + 5");
+        let monic = poly.make_monic();
+
+        assert_eq!((monic[3].clone(), monic[2].clone(), monic[1].clone(), monic[0].clone()), (BigInt::from(1), BigInt::from(1), BigInt::from(30), BigInt::from(20)));
+        // F(cm) = c^(d-1) ƒ(m), here with m = 28 and c = 2.
+        assert_eq!(monic.evaluate(&BigInt::from(56)), BigInt::from(4) * poly.evaluate(&BigInt::from(28)));
+    }
+
+    #[test]
+    fn test_reduction_needs_a_monic_modulus() {
+        let monic = Polynomial::parse("X^3 + 15X^2 + 29X + 8");
+        let non_monic = Polynomial::parse("2X^3 - 4X^2 + 3X - 1");
+        let poly = Polynomial::parse("X^4 + 1");
+
+        let reduced = Polynomial::field_modulus_from_polynomial(&poly, &monic).unwrap();
+        assert_eq!((reduced[2].clone(), reduced[1].clone(), reduced[0].clone()), (BigInt::from(196), BigInt::from(427), BigInt::from(121)));
+        assert!(matches!(Polynomial::field_modulus_from_polynomial(&poly, &non_monic), Err(GnfsError::InvalidParameter(_))));
+        assert!(matches!(Polynomial::mod_mod(&poly, &non_monic, &BigInt::from(7)), Err(GnfsError::InvalidParameter(_))));
+        // A leading coefficient of 1 mod the prime is as good as monic.
+        assert!(Polynomial::mod_mod(&poly, &Polynomial::parse("8X^3 + 1"), &BigInt::from(7)).is_ok());
+    }
+}
@@ -0,0 +1,124 @@
+// src/polynomial/quality.rs
+
+//! Measures for comparing candidate polynomials. Over a sieve region of skewness s, where a
+//! ranges over s times the width of b, the values of ƒ are about its skewed norm; Murphy's α
+//! says how much more often they are smooth than random integers of that size. Their sum is the
+//! log of the effective norm size, so lower is better.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use num::{BigInt, Integer, ToPrimitive, Zero};
+use crate::polynomial::polynomial::Polynomial;
+use crate::relation_sieve::yield_prediction::{self, ln_abs};
+
+/// Primes summed for α when ranking candidates. Only differences between candidates matter, and
+/// those come mostly from the smallest primes.
+const SELECTION_ALPHA_PRIME_BOUND: u64 = 200;
+/// Halvings of the bracket around the optimal ln s.
+const SKEWNESS_SEARCH_STEPS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PolynomialQuality {
+    /// The skewness at which `log_size` is smallest.
+    pub skewness: f64,
+    /// ln of the skewed L2 norm.
+    pub log_size: f64,
+    pub alpha: f64,
+}
+
+impl PolynomialQuality {
+    pub fn of(coefficients: &[BigInt]) -> Self {
+        let (skewness, log_size) = skewed_log_size(coefficients);
+        PolynomialQuality { skewness, log_size, alpha: alpha(coefficients) }
+    }
+
+    /// The log of the effective value size; lower is better.
+    pub fn score(&self) -> f64 {
+        self.log_size + self.alpha
+    }
+}
+
+impl Display for PolynomialQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "skewness {:.1}, size {:.2}, α {:.2}, score {:.2}", self.skewness, self.log_size, self.alpha, self.score())
+    }
+}
+
+/// A selected algebraic polynomial ƒ with its rational side X - m, where ƒ(m) ≡ 0 (mod N).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolynomialCandidate {
+    pub polynomial: Polynomial,
+    pub m: BigInt,
+    pub quality: PolynomialQuality,
+}
+
+impl PolynomialCandidate {
+    pub fn new(coefficients: &[BigInt], m: BigInt) -> Self {
+        PolynomialCandidate { polynomial: from_coefficients(coefficients), m, quality: PolynomialQuality::of(coefficients) }
+    }
+}
+
+/// a_0, ..., a_d of ƒ, with zeros where ƒ has no term.
+pub fn coefficients(polynomial: &Polynomial) -> Vec<BigInt> {
+    (0..=polynomial.degree()).map(|exponent| polynomial[exponent].clone()).collect()
+}
+
+pub fn from_coefficients(coefficients: &[BigInt]) -> Polynomial {
+    let mut polynomial = Polynomial::zero();
+    for (exponent, coefficient) in coefficients.iter().enumerate().filter(|(_, c)| !c.is_zero()) {
+        polynomial[exponent] = coefficient.clone();
+    }
+    polynomial
+}
+
+/// The skewness s minimizing ln √(Σ (a_i s^(i - d/2))²), and that minimum. Each term's log is
+/// linear in ln s, so the log of the sum is convex in ln s and a ternary search finds it.
+pub fn skewed_log_size(coefficients: &[BigInt]) -> (f64, f64) {
+    let degree = coefficients.len().saturating_sub(1) as f64;
+    let logs: Vec<(f64, f64)> = coefficients
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !c.is_zero())
+        .map(|(i, c)| (ln_abs(c), i as f64 - degree / 2.0))
+        .collect();
+    let size = |ln_s: f64| {
+        let terms: Vec<f64> = logs.iter().map(|(ln_a, power)| 2.0 * (ln_a + power * ln_s)).collect();
+        let largest = terms.iter().copied().fold(f64::MIN, f64::max);
+        (largest + terms.iter().map(|t| (t - largest).exp()).sum::<f64>().ln()) / 2.0
+    };
+
+    let (mut low, mut high) = (-20.0f64, 200.0f64);
+    for _ in 0..SKEWNESS_SEARCH_STEPS {
+        let (left, right) = (low + (high - low) / 3.0, high - (high - low) / 3.0);
+        if size(left) <= size(right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+    let ln_s = (low + high) / 2.0;
+    (ln_s.exp(), size(ln_s))
+}
+
+/// Murphy's α over the primes up to [`SELECTION_ALPHA_PRIME_BOUND`], counting the roots of ƒ
+/// mod p by evaluation and a projective root where p divides the leading coefficient.
+pub fn alpha(coefficients: &[BigInt]) -> f64 {
+    let primes: Vec<BigInt> = (2..=SELECTION_ALPHA_PRIME_BOUND).filter(|&p| is_small_prime(p)).map(BigInt::from).collect();
+    let roots: HashMap<u64, usize> = primes
+        .iter()
+        .map(|p| {
+            let p_u64 = p.to_u64().unwrap();
+            let reduced: Vec<u64> = coefficients.iter().map(|c| c.mod_floor(p).to_u64().unwrap()).collect();
+            let affine = (0..p_u64)
+                .filter(|&x| reduced.iter().rev().fold(0, |value, &c| (value * x + c) % p_u64) == 0)
+                .count();
+            let projective = usize::from(reduced.last().is_some_and(|&leading| leading == 0));
+            (p_u64, affine + projective)
+        })
+        .collect();
+    yield_prediction::alpha(&primes, &roots)
+}
+
+fn is_small_prime(n: u64) -> bool {
+    n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d))
+}
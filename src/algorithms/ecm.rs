@@ -0,0 +1,189 @@
+// src/algorithms/ecm.rs
+
+//! Lenstra's elliptic curve method on Montgomery curves By² = x³ + Ax² + x, in x/z coordinates so
+//! no inversions are needed along the way. Each curve comes from Suyama's parametrization by a
+//! random σ, which gives a group order divisible by 12. Stage 1 multiplies the starting point by
+//! every prime power up to B1; if the group order modulo some p | n is B1-smooth, the result is
+//! the identity mod p and gcd(z, n) reveals p. Stage 2 catches orders with one further prime q up
+//! to B2 by baby-step giant-step: q = m·D ± j, and qQ is the identity exactly when mDQ and jQ
+//! share an x coordinate, so the cross products x(mDQ)·z(jQ) - x(jQ)·z(mDQ) are multiplied
+//! together and one gcd at the end tests them all. Curves are independent and run in parallel.
+
+use rayon::prelude::*;
+use num::{BigInt, Integer, One, Zero};
+use crate::core::static_random::StaticRandom;
+use crate::integer_math::fast_prime_sieve::FastPrimeSieve;
+
+/// (B1, curves) for factors of about 20, 25, 30 and 35 digits, after GMP-ECM's table.
+pub const ECM_LEVELS: [(u64, usize); 4] = [(11_000, 86), (50_000, 214), (250_000, 430), (1_000_000, 904)];
+/// Digits of the factor each level of [`ECM_LEVELS`] targets.
+const ECM_LEVEL_DIGITS: [usize; 4] = [20, 25, 30, 35];
+/// B2 as a multiple of B1.
+pub const STAGE_TWO_MULTIPLIER: u64 = 100;
+/// Giant step of stage 2, in multiples of the stage 1 point: 2·3·5·7.
+const GIANT_STEP: u64 = 210;
+
+/// A point in x/z coordinates; the identity has z = 0.
+#[derive(Debug, Clone)]
+struct Point {
+    x: BigInt,
+    z: BigInt,
+}
+
+/// A curve modulo n, held as (A + 2)/4, the only constant the x/z formulas need.
+struct Curve<'a> {
+    n: &'a BigInt,
+    a24: BigInt,
+}
+
+impl<'a> Curve<'a> {
+    /// The Suyama curve for σ and its starting point, or a factor of n when one of the
+    /// denominators shares one with it.
+    fn suyama(n: &'a BigInt, sigma: &BigInt) -> Result<(Curve<'a>, Point), BigInt> {
+        let u = (sigma * sigma - BigInt::from(5)).mod_floor(n);
+        let v = (sigma * BigInt::from(4)).mod_floor(n);
+        let u3 = u.modpow(&BigInt::from(3), n);
+        let denominator = (BigInt::from(16) * &u3 * &v).mod_floor(n);
+        let inverse = mod_inverse(&denominator, n)?;
+        let difference = (&v - &u).mod_floor(n);
+        let a24 = (difference.modpow(&BigInt::from(3), n) * (&u * BigInt::from(3) + &v) * inverse).mod_floor(n);
+        let start = Point { x: u3, z: v.modpow(&BigInt::from(3), n) };
+        Ok((Curve { n, a24 }, start))
+    }
+
+    fn double(&self, p: &Point) -> Point {
+        let n = self.n;
+        let sum = (&p.x + &p.z).pow(2) % n;
+        let difference = (&p.x - &p.z).pow(2) % n;
+        let t = &sum - &difference;
+        Point { x: (&sum * &difference).mod_floor(n), z: (&t * (&difference + &self.a24 * &t)).mod_floor(n) }
+    }
+
+    /// P + Q, given P - Q.
+    fn add(&self, p: &Point, q: &Point, difference: &Point) -> Point {
+        let n = self.n;
+        let u = (&p.x - &p.z) * (&q.x + &q.z) % n;
+        let v = (&p.x + &p.z) * (&q.x - &q.z) % n;
+        Point { x: (&difference.z * (&u + &v).pow(2)).mod_floor(n), z: (&difference.x * (&u - &v).pow(2)).mod_floor(n) }
+    }
+
+    /// k·P by the Montgomery ladder, which keeps the two running multiples one P apart.
+    fn multiply(&self, p: &Point, k: u64) -> Point {
+        if k == 0 {
+            return Point { x: BigInt::one(), z: BigInt::zero() };
+        }
+        let (mut low, mut high) = (p.clone(), self.double(p));
+        for bit in (0..63 - k.leading_zeros()).rev() {
+            if k >> bit & 1 == 1 {
+                low = self.add(&high, &low, p);
+                high = self.double(&high);
+            } else {
+                high = self.add(&high, &low, p);
+                low = self.double(&low);
+            }
+        }
+        low
+    }
+}
+
+fn mod_inverse(a: &BigInt, n: &BigInt) -> Result<BigInt, BigInt> {
+    let extended = a.extended_gcd(n);
+    if extended.gcd.is_one() {
+        Ok(extended.x.mod_floor(n))
+    } else {
+        Err(extended.gcd)
+    }
+}
+
+/// A proper factor of n found in `value` by gcd, if any.
+fn proper_factor(value: &BigInt, n: &BigInt) -> Option<BigInt> {
+    let g = value.gcd(n);
+    (!g.is_one() && &g != n).then_some(g)
+}
+
+/// Runs one curve through both stages. `primes` are the primes up to B1.
+fn run_curve(n: &BigInt, sigma: &BigInt, primes: &[u64], b1: u64, b2: u64) -> Option<BigInt> {
+    let (curve, mut q) = match Curve::suyama(n, sigma) {
+        Ok(curve) => curve,
+        Err(g) => return (&g != n).then_some(g),
+    };
+
+    for &p in primes {
+        let mut power = p;
+        while power <= b1 / p {
+            power *= p;
+        }
+        q = curve.multiply(&q, power);
+    }
+    if let Some(factor) = proper_factor(&q.z, n) {
+        return Some(factor);
+    }
+    if q.z.is_zero() {
+        return None;
+    }
+
+    // Baby steps: jQ for odd j < D/2 coprime to D.
+    let double = curve.double(&q);
+    let mut babies = Vec::new();
+    let (mut previous, mut current) = (q.clone(), curve.add(&double, &q, &q));
+    babies.push((1, q.clone()));
+    let mut j = 3;
+    while j < GIANT_STEP / 2 {
+        if j.gcd(&GIANT_STEP) == 1 {
+            babies.push((j, current.clone()));
+        }
+        let next = curve.add(&current, &double, &previous);
+        previous = std::mem::replace(&mut current, next);
+        j += 2;
+    }
+
+    // Giant steps: mDQ from just below B1 up past B2.
+    let giant = curve.multiply(&q, GIANT_STEP);
+    let first = (b1 / GIANT_STEP).max(2);
+    let mut behind = curve.multiply(&q, (first - 1) * GIANT_STEP);
+    let mut at = curve.multiply(&q, first * GIANT_STEP);
+    let mut product = BigInt::one();
+    for _ in first..=b2 / GIANT_STEP + 1 {
+        for (_, baby) in &babies {
+            product = (product * ((&at.x * &baby.z) - (&baby.x * &at.z))).mod_floor(n);
+        }
+        let next = curve.add(&at, &giant, &behind);
+        behind = std::mem::replace(&mut at, next);
+    }
+    proper_factor(&product, n)
+}
+
+/// Runs `curves` curves with bounds B1 and B2 in parallel and returns the first proper factor of
+/// the odd composite `n` found.
+pub fn ecm_with_bounds(n: &BigInt, b1: u64, b2: u64, curves: usize, random: &mut StaticRandom) -> Option<BigInt> {
+    let primes: Vec<u64> = FastPrimeSieve::new().primes().take_while(|&p| p <= b1).collect();
+    // σ ∉ {0, ±1, ±3, ±5} keeps the curve non-singular; drawing from [6, 2³²) avoids them all.
+    let sigmas: Vec<BigInt> = (0..curves).map(|_| random.next_bigint(&BigInt::from(6), &BigInt::from(u32::MAX))).collect();
+    sigmas.par_iter().find_map_any(|sigma| run_curve(n, sigma, &primes, b1, b2))
+}
+
+/// Tries the levels of [`ECM_LEVELS`] in turn, up to the first that covers factors of half n's
+/// digits, and returns the first proper factor of the odd composite `n` found.
+pub fn ecm(n: &BigInt, random: &mut StaticRandom) -> Option<BigInt> {
+    let digits = n.to_string().len();
+    ECM_LEVELS
+        .into_iter()
+        .enumerate()
+        .take_while(|&(level, _)| level == 0 || ECM_LEVEL_DIGITS[level - 1] < digits / 2)
+        .find_map(|(_, (b1, curves))| ecm_with_bounds(n, b1, b1 * STAGE_TWO_MULTIPLIER, curves, random))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_a_ten_digit_factor() {
+        let p = BigInt::from(1_000_000_007u64);
+        let q = BigInt::from(2_305_843_009_213_693_951u64);
+        let n = &p * &q;
+        let mut random = StaticRandom::from_seed(3, 0);
+        let factor = ecm_with_bounds(&n, 2_000, 200_000, 64, &mut random).expect("no factor found");
+        assert!(factor == p || factor == q);
+    }
+}
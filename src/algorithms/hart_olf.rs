@@ -0,0 +1,47 @@
+// src/algorithms/hart_olf.rs
+
+use num::{BigInt, Integer, One, Zero};
+
+/// Multipliers tried by Hart's method before giving up.
+pub const HART_OLF_ITERATIONS: u64 = 1 << 14;
+
+/// Hart's one-line factoring: for i = 1, 2, …, take s = ⌈√(n·i)⌉ and stop when s² mod n is a
+/// perfect square t², so that gcd(s - t, n) splits n. It finds factors of n = pq quickly when
+/// p/q is close to a ratio of small integers, including the near-square case rho is slowest on.
+/// Returns None if no split appears within `iterations` multipliers.
+pub fn hart_one_line(n: &BigInt, iterations: u64) -> Option<BigInt> {
+    if n <= &BigInt::one() {
+        return None;
+    }
+    for i in 1..=iterations {
+        let ni = n * i;
+        let mut s = ni.sqrt();
+        if &s * &s != ni {
+            s += 1;
+        }
+        let m = (&s * &s).mod_floor(n);
+        let t = m.sqrt();
+        if &t * &t == m {
+            let g = (&s - &t).gcd(n);
+            if !g.is_one() && !g.is_zero() && &g != n {
+                return Some(g);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_near_square_semiprimes() {
+        for (p, q) in [(1_000_003u64, 1_000_033u64), (4_294_967_291, 4_294_967_311), (999_983, 3_000_017)] {
+            let n = BigInt::from(p) * BigInt::from(q);
+            let factor = hart_one_line(&n, HART_OLF_ITERATIONS).unwrap_or_else(|| panic!("no factor of {}", n));
+            assert!(factor == BigInt::from(p) || factor == BigInt::from(q));
+        }
+        assert!(hart_one_line(&BigInt::from(1_000_003), 100).is_none());
+    }
+}
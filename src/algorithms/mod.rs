@@ -0,0 +1,207 @@
+// src/algorithms/mod.rs
+
+pub mod ecm;
+pub mod hart_olf;
+pub mod montgomery;
+pub mod pollard_rho;
+pub mod primality;
+pub mod squfof;
+pub mod trial_division;
+
+use std::fmt::Display;
+use std::str::FromStr;
+use num::{BigInt, Integer, One};
+use log::{debug, warn};
+use crate::core::error::GnfsError;
+use crate::core::gnfs_config::{random_stream, GnfsConfig};
+use crate::core::input;
+use self::ecm::ecm;
+use self::hart_olf::{hart_one_line, HART_OLF_ITERATIONS};
+use self::pollard_rho::pollard_rho;
+use self::primality::{certify, Primality};
+use self::squfof::squfof;
+use self::trial_division::TRIAL_DIVISION_LIMIT;
+
+/// Random polynomials tried by Pollard rho before reporting failure.
+const POLLARD_RHO_ATTEMPTS: usize = 16;
+/// Up to this size SQUFOF's (kn)^¼ steps on machine words beat Pollard rho.
+const SQUFOF_MAX_BITS: u64 = 62;
+/// Above this size Pollard rho's √p steps lose to ECM on the factors left after trial division.
+const ECM_MIN_BITS: u64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    TrialDivision,
+    Squfof,
+    PollardRho,
+    Ecm,
+}
+
+impl Algorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::TrialDivision => "trial-division",
+            Algorithm::Squfof => "squfof",
+            Algorithm::PollardRho => "pollard-rho",
+            Algorithm::Ecm => "ecm",
+        }
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = GnfsError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        [Algorithm::TrialDivision, Algorithm::Squfof, Algorithm::PollardRho, Algorithm::Ecm]
+            .into_iter()
+            .find(|algorithm| algorithm.name() == input)
+            .ok_or_else(|| GnfsError::InvalidParameter(format!("unknown algorithm {:?}; expected trial-division, squfof, pollard-rho or ecm", input)))
+    }
+}
+
+/// Picks the cheapest method likely to split `n`.
+pub fn choose_algorithm(n: &BigInt) -> Algorithm {
+    match n.bits() {
+        0..=40 => Algorithm::TrialDivision,
+        41..=SQUFOF_MAX_BITS => Algorithm::Squfof,
+        bits if bits <= ECM_MIN_BITS => Algorithm::PollardRho,
+        _ => Algorithm::Ecm,
+    }
+}
+
+/// Splits the composite |n| into a non-trivial pair (p, q) with p * q = |n|.
+pub fn factor(n: &BigInt, config: &GnfsConfig) -> Result<(BigInt, BigInt), GnfsError> {
+    factor_with(n, None, config)
+}
+
+/// [`factor`] with the method fixed. After trial division and the perfect square check, only
+/// `algorithm` is tried, with no fallback; `None` picks one with [`choose_algorithm`].
+pub fn factor_with(n: &BigInt, algorithm: Option<Algorithm>, config: &GnfsConfig) -> Result<(BigInt, BigInt), GnfsError> {
+    let input = input::normalize(n, TRIAL_DIVISION_LIMIT)?;
+    if input.is_prime() {
+        return Err(GnfsError::InvalidParameter(format!("{} is prime", input.n)));
+    }
+    let n = &input.n;
+    if let Some((p, _)) = input.small_factors.first() {
+        return Ok((p.clone(), n.div_floor(p)));
+    }
+
+    let root = n.sqrt();
+    if &root * &root == *n {
+        return Ok((root.clone(), root));
+    }
+    let mut random = config.random(random_stream::FACTORIZATION);
+    if let Some(algorithm) = algorithm {
+        let found = match algorithm {
+            Algorithm::TrialDivision => None,
+            Algorithm::Squfof => squfof(n),
+            Algorithm::PollardRho => pollard_rho(n, &mut random, POLLARD_RHO_ATTEMPTS),
+            Algorithm::Ecm => ecm(n, &mut random),
+        };
+        return found
+            .map(|p| {
+                let q = n.div_floor(&p);
+                (p, q)
+            })
+            .ok_or_else(|| GnfsError::stage_failed(algorithm.name(), format!("no factor of {}", n)));
+    }
+    if let Some(p) = hart_one_line(n, HART_OLF_ITERATIONS) {
+        debug!("Hart's one-line factoring split {}", n);
+        let q = n.div_floor(&p);
+        return Ok((p, q));
+    }
+
+    // Each method falls back on the next when it gives up: SQUFOF on Pollard rho, Pollard rho on
+    // ECM, and ECM on Pollard rho.
+    let (found, tried) = match choose_algorithm(n) {
+        Algorithm::TrialDivision => return Err(GnfsError::stage_failed("Trial division", format!("no factor of {}", n))),
+        Algorithm::Squfof => (squfof(n).or_else(|| pollard_rho(n, &mut random, POLLARD_RHO_ATTEMPTS)), "SQUFOF and Pollard rho"),
+        Algorithm::PollardRho => (pollard_rho(n, &mut random, POLLARD_RHO_ATTEMPTS).or_else(|| ecm(n, &mut random)), "Pollard rho and ECM"),
+        Algorithm::Ecm => (ecm(n, &mut random).or_else(|| pollard_rho(n, &mut random, POLLARD_RHO_ATTEMPTS)), "ECM and Pollard rho"),
+    };
+    found
+        .map(|p| {
+            let q = n.div_floor(&p);
+            (p, q)
+        })
+        .ok_or_else(|| GnfsError::stage_failed(tried, format!("no factor of {}", n)))
+}
+
+/// The prime-power decomposition of |n| > 1 in increasing order of prime. Small primes come
+/// from trial division; the cofactor is split with [`factor`] and the pieces split again until
+/// each one certifies as prime. A piece that passes the probable prime test without a proof is
+/// kept with a warning; one that is composite but won't split is an error.
+pub fn factor_completely(n: &BigInt, config: &GnfsConfig) -> Result<Vec<(BigInt, u32)>, GnfsError> {
+    factor_completely_with(n, None, config)
+}
+
+/// [`factor_completely`] splitting every composite piece with [`factor_with`] and `algorithm`.
+pub fn factor_completely_with(n: &BigInt, algorithm: Option<Algorithm>, config: &GnfsConfig) -> Result<Vec<(BigInt, u32)>, GnfsError> {
+    let input = input::normalize(n, TRIAL_DIVISION_LIMIT)?;
+    let mut primes: Vec<BigInt> = Vec::new();
+    let mut pending = vec![input.cofactor];
+    while let Some(value) = pending.pop() {
+        if value.is_one() {
+            continue;
+        }
+        match certify(&value) {
+            Primality::Proven => primes.push(value),
+            Primality::Probable => {
+                warn!("{} is a probable prime; n - 1 couldn't be factored far enough to prove it.", value);
+                primes.push(value);
+            }
+            Primality::Composite => {
+                let (p, q) = factor_with(&value, algorithm, config)?;
+                pending.push(p);
+                pending.push(q);
+            }
+        }
+    }
+
+    let mut factors = input.small_factors;
+    primes.sort();
+    for prime in primes {
+        match factors.last_mut() {
+            Some((last, exponent)) if *last == prime => *exponent += input.cofactor_exponent,
+            _ => factors.push((prime, input.cofactor_exponent)),
+        }
+    }
+    factors.sort();
+    Ok(factors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::One;
+
+    #[test]
+    fn test_factor_splits_semiprimes() {
+        let config = GnfsConfig::with_seed(1);
+        for (p, q) in [(1_000_003u64, 1_000_033u64), (4_294_967_311, 4_294_967_357)] {
+            let n = BigInt::from(p) * BigInt::from(q);
+            let (a, b) = factor(&n, &config).unwrap();
+            assert_eq!(&a * &b, n);
+            assert!(!a.is_one() && !b.is_one());
+        }
+        let square = BigInt::from(4_294_967_311u64).pow(2);
+        assert_eq!(factor(&square, &config).unwrap(), (BigInt::from(4_294_967_311u64), BigInt::from(4_294_967_311u64)));
+        assert!(factor(&BigInt::from(1_000_003), &config).is_err());
+    }
+
+    #[test]
+    fn test_factor_completely_returns_prime_powers() {
+        let config = GnfsConfig::with_seed(1);
+        let (p, q) = (BigInt::from(4_294_967_311u64), BigInt::from(4_294_967_357u64));
+        let n = BigInt::from(12) * p.pow(2) * &q;
+        let expected = vec![(BigInt::from(2), 2), (BigInt::from(3), 1), (p, 2), (q, 1)];
+        assert_eq!(factor_completely(&n, &config).unwrap(), expected);
+        assert_eq!(factor_completely(&BigInt::from(97), &config).unwrap(), vec![(BigInt::from(97), 1)]);
+    }
+}
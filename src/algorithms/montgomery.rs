@@ -0,0 +1,140 @@
+// src/algorithms/montgomery.rs
+
+//! Montgomery arithmetic modulo an odd n < 2¹²⁶ with R = 2¹²⁸. Products are reduced by REDC
+//! instead of a division, and with n below 2¹²⁶ the 256-bit sum in REDC can't overflow, so only
+//! one conditional subtraction is ever needed.
+
+/// Largest modulus, in bits, the 128-bit path accepts.
+pub const MONTGOMERY_MAX_BITS: u64 = 126;
+
+/// Full 256-bit product of `a` and `b` as (high, low) halves.
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+
+    let cross = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let low = (cross << 64) | (lo_lo & MASK);
+    let high = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (cross >> 64);
+    (high, low)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Montgomery128 {
+    n: u128,
+    /// -n⁻¹ mod R.
+    n_prime: u128,
+    /// R² mod n.
+    r_squared: u128,
+    /// R mod n, the Montgomery form of 1.
+    one: u128,
+}
+
+impl Montgomery128 {
+    /// Returns `None` unless `n` is odd, greater than 1 and below 2¹²⁶.
+    pub fn new(n: u128) -> Option<Self> {
+        if n < 3 || n & 1 == 0 || n >> MONTGOMERY_MAX_BITS != 0 {
+            return None;
+        }
+
+        // Newton's iteration doubles the correct low bits each round; n·n ≡ 1 mod 8 gives three.
+        let mut inverse = n;
+        for _ in 0..6 {
+            inverse = inverse.wrapping_mul(2u128.wrapping_sub(n.wrapping_mul(inverse)));
+        }
+
+        let one = (u128::MAX % n + 1) % n;
+        let mut r_squared = one;
+        for _ in 0..128 {
+            r_squared = Self::add_mod(r_squared, r_squared, n);
+        }
+
+        Some(Montgomery128 { n, n_prime: inverse.wrapping_neg(), r_squared, one })
+    }
+
+    fn add_mod(a: u128, b: u128, n: u128) -> u128 {
+        // a, b < n < 2¹²⁶, so the sum can't overflow.
+        let sum = a + b;
+        if sum >= n { sum - n } else { sum }
+    }
+
+    /// (high·R + low)·R⁻¹ mod n, for inputs below n·R.
+    fn redc(&self, high: u128, low: u128) -> u128 {
+        let m = low.wrapping_mul(self.n_prime);
+        let (mn_high, mn_low) = mul_wide(m, self.n);
+        let carry = low.overflowing_add(mn_low).1 as u128;
+        let t = high + mn_high + carry;
+        if t >= self.n { t - self.n } else { t }
+    }
+
+    pub fn modulus(&self) -> u128 {
+        self.n
+    }
+
+    pub fn one(&self) -> u128 {
+        self.one
+    }
+
+    pub fn to_montgomery(&self, x: u128) -> u128 {
+        let (high, low) = mul_wide(x % self.n, self.r_squared);
+        self.redc(high, low)
+    }
+
+    pub fn from_montgomery(&self, x: u128) -> u128 {
+        self.redc(0, x)
+    }
+
+    pub fn mul(&self, a: u128, b: u128) -> u128 {
+        let (high, low) = mul_wide(a, b);
+        self.redc(high, low)
+    }
+
+    pub fn add(&self, a: u128, b: u128) -> u128 {
+        Self::add_mod(a, b, self.n)
+    }
+}
+
+/// Binary gcd on 128-bit words.
+pub fn gcd_u128(mut a: u128, mut b: u128) -> u128 {
+    if a == 0 {
+        return b;
+    }
+    if b == 0 {
+        return a;
+    }
+    let shift = (a | b).trailing_zeros();
+    a >>= a.trailing_zeros();
+    while b != 0 {
+        b >>= b.trailing_zeros();
+        if a > b {
+            std::mem::swap(&mut a, &mut b);
+        }
+        b -= a;
+    }
+    a << shift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::BigInt;
+
+    #[test]
+    fn montgomery_products_match_bigint() {
+        let n: u128 = (1 << 125) + 0x1234_5678_9abc_def1;
+        let context = Montgomery128::new(n).unwrap();
+        let big_n = BigInt::from(n);
+        for (a, b) in [(3u128, 5u128), (n - 1, n - 1), (1 << 100, (1 << 124) + 77), (0, 12345)] {
+            let product = context.from_montgomery(context.mul(context.to_montgomery(a), context.to_montgomery(b)));
+            assert_eq!(BigInt::from(product), BigInt::from(a) * BigInt::from(b) % &big_n);
+        }
+        assert_eq!(context.from_montgomery(context.one()), 1);
+        assert!(Montgomery128::new(1 << 126 | 1).is_none());
+        assert_eq!(gcd_u128(2u128.pow(70) * 3 * 7, 2u128.pow(65) * 7 * 11), 2u128.pow(65) * 7);
+    }
+}
@@ -0,0 +1,213 @@
+// src/algorithms/pollard_rho.rs
+
+use num::{BigInt, Integer, One, Signed, ToPrimitive, Zero};
+use crate::algorithms::montgomery::{gcd_u128, Montgomery128};
+use crate::core::static_random::StaticRandom;
+
+/// Iterations per polynomial before giving up and trying a new constant.
+const MAX_ITERATIONS: usize = 1 << 20;
+/// Number of differences multiplied together between gcd computations.
+const BATCH_SIZE: usize = 128;
+
+/// Brent's variant of Pollard's rho. Returns a non-trivial factor of the odd composite `n`, or
+/// None if `attempts` random polynomials x² + c all failed. Below 2¹²⁶ the iteration runs on
+/// 128-bit Montgomery residues; larger n use `BigInt`.
+pub fn pollard_rho(n: &BigInt, random: &mut StaticRandom, attempts: usize) -> Option<BigInt> {
+    if n.is_even() {
+        return Some(BigInt::from(2));
+    }
+
+    let montgomery = n.to_u128().and_then(Montgomery128::new);
+    let upper = n - 1;
+    for _ in 0..attempts {
+        let c = random.next_bigint(&BigInt::one(), &upper);
+        let start = random.next_bigint(&BigInt::zero(), &upper);
+        let factor = match &montgomery {
+            Some(context) => brent_u128(context, start.to_u128()?, c.to_u128()?).map(BigInt::from),
+            None => brent(n, &start, &c),
+        };
+        if factor.is_some() {
+            return factor;
+        }
+    }
+    None
+}
+
+/// [`brent`] with x, y, c and the accumulated product held in Montgomery form. The differences
+/// are multiplied in without converting back: that scales the product by a power of R, which is
+/// coprime to n and leaves the gcd unchanged.
+fn brent_u128(context: &Montgomery128, start: u128, c: u128) -> Option<u128> {
+    let n = context.modulus();
+    let c = context.to_montgomery(c);
+    let step = |x: u128| context.add(context.mul(x, x), c);
+
+    let mut y = context.to_montgomery(start);
+    let mut x = y;
+    let mut saved = y;
+    let mut product = context.one();
+    let mut g = 1;
+    let mut cycle_length = 1;
+    let mut iterations = 0;
+
+    while g == 1 {
+        x = y;
+        for _ in 0..cycle_length {
+            y = step(y);
+        }
+
+        let mut k = 0;
+        while k < cycle_length && g == 1 {
+            saved = y;
+            for _ in 0..BATCH_SIZE.min(cycle_length - k) {
+                y = step(y);
+                product = context.mul(product, x.abs_diff(y));
+            }
+            g = gcd_u128(product, n);
+            k += BATCH_SIZE;
+        }
+
+        iterations += cycle_length;
+        if iterations > MAX_ITERATIONS {
+            return None;
+        }
+        cycle_length *= 2;
+    }
+
+    if g == n {
+        loop {
+            saved = step(saved);
+            g = gcd_u128(x.abs_diff(saved), n);
+            if g != 1 {
+                break;
+            }
+        }
+    }
+
+    if g == n {
+        None
+    } else {
+        Some(g)
+    }
+}
+
+fn brent(n: &BigInt, start: &BigInt, c: &BigInt) -> Option<BigInt> {
+    let step = |x: &BigInt| (x * x + c) % n;
+
+    let mut y = start.clone();
+    let mut x = y.clone();
+    let mut saved = y.clone();
+    let mut product = BigInt::one();
+    let mut g = BigInt::one();
+    let mut cycle_length = 1;
+    let mut iterations = 0;
+
+    while g.is_one() {
+        x = y.clone();
+        for _ in 0..cycle_length {
+            y = step(&y);
+        }
+
+        let mut k = 0;
+        while k < cycle_length && g.is_one() {
+            saved = y.clone();
+            for _ in 0..BATCH_SIZE.min(cycle_length - k) {
+                y = step(&y);
+                product = (product * (&x - &y).abs()) % n;
+            }
+            g = product.gcd(n);
+            k += BATCH_SIZE;
+        }
+
+        iterations += cycle_length;
+        if iterations > MAX_ITERATIONS {
+            return None;
+        }
+        cycle_length *= 2;
+    }
+
+    // The batch overshot; step back one difference at a time from the last saved point.
+    if &g == n {
+        loop {
+            saved = step(&saved);
+            g = (&x - &saved).abs().gcd(n);
+            if !g.is_one() {
+                break;
+            }
+        }
+    }
+
+    if &g == n {
+        None
+    } else {
+        Some(g)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn splits(n: &BigInt, factor: Option<BigInt>) -> bool {
+        factor.is_some_and(|factor| !factor.is_one() && &factor != n && (n % factor).is_zero())
+    }
+
+    /// Whether the first gcd Brent's batches take that isn't 1 is n itself, in which case the
+    /// factor has to come from stepping back through the batch.
+    fn batch_gcd_is_n(n: u128, start: u128, c: u128) -> bool {
+        let step = |x: u128| (x * x + c) % n;
+        let (mut y, mut product, mut cycle_length) = (start, 1u128, 1usize);
+        loop {
+            let x = y;
+            for _ in 0..cycle_length {
+                y = step(y);
+            }
+            for k in (0..cycle_length).step_by(BATCH_SIZE) {
+                for _ in 0..BATCH_SIZE.min(cycle_length - k) {
+                    y = step(y);
+                    product = product * x.abs_diff(y) % n;
+                }
+                match gcd_u128(product, n) {
+                    1 => {}
+                    g => return g == n,
+                }
+            }
+            cycle_length *= 2;
+        }
+    }
+
+    #[test]
+    fn splits_semiprimes_in_montgomery_form() {
+        let mut random = StaticRandom::from_seed(7, 0);
+        for (p, q) in [("1000003", "1000033"), ("1073741827", "1180591620717411303449")] {
+            let n = BigInt::from_str(p).unwrap() * BigInt::from_str(q).unwrap();
+            assert!(n.bits() < 126);
+            assert!(splits(&n, pollard_rho(&n, &mut random, 4)), "{} * {}", p, q);
+        }
+    }
+
+    #[test]
+    fn splits_semiprimes_past_montgomery_range_with_bigints() {
+        let mut random = StaticRandom::from_seed(7, 0);
+        let n = BigInt::from(16_777_259) * BigInt::from_str("1329227995784915872903807060280345027").unwrap();
+        assert!(n.bits() > 128);
+        assert!(splits(&n, pollard_rho(&n, &mut random, 4)));
+    }
+
+    #[test]
+    fn steps_back_when_a_batch_collects_both_factors() {
+        // With factors this small both cycles close inside one batch for most constants.
+        let n = 1009u128 * 1013;
+        let context = Montgomery128::new(n).unwrap();
+        let mut stepped_back = 0;
+        for c in (1..200).filter(|&c| batch_gcd_is_n(n, 2, c)) {
+            let (montgomery, bigint) = (brent_u128(&context, 2, c), brent(&BigInt::from(n), &BigInt::from(2), &BigInt::from(c)));
+            assert_eq!(montgomery.map(BigInt::from), bigint, "c = {}", c);
+            if let Some(factor) = montgomery {
+                assert!(factor == 1009 || factor == 1013, "c = {}", c);
+                stepped_back += 1;
+            }
+        }
+        assert!(stepped_back > 0);
+    }
+}
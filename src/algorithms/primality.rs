@@ -0,0 +1,71 @@
+// src/algorithms/primality.rs
+
+use num::{BigInt, Integer, One};
+use crate::algorithms::trial_division::{trial_division, TRIAL_DIVISION_LIMIT};
+use crate::integer_math::factorization_factory::FactorizationFactory;
+
+/// Below this, the strong probable prime test to the first 13 prime bases, which
+/// [`FactorizationFactory::is_probable_prime`] covers, has no pseudoprimes and so proves primality.
+const DETERMINISTIC_MILLER_RABIN_BOUND: u128 = 3_317_044_064_679_887_385_961_981;
+/// Witnesses tried for each prime of n - 1 before the Pocklington test gives up.
+const POCKLINGTON_WITNESSES: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primality {
+    /// Shown to be prime.
+    Proven,
+    /// Passed the probable prime test, but n - 1 couldn't be factored far enough for a proof.
+    Probable,
+    Composite,
+}
+
+/// Certifies `n`. Small n are settled by the deterministic Miller–Rabin bound. Larger ones get a
+/// Pocklington proof: if F | n - 1 with F > √n is fully factored, and for each prime q | F some
+/// a has a^(n-1) ≡ 1 and gcd(a^((n-1)/q) - 1, n) = 1, every prime factor of n is 1 mod F and so
+/// exceeds √n, making n prime. The primes of F come from trial division of n - 1 and from a
+/// cofactor that itself certifies.
+pub fn certify(n: &BigInt) -> Primality {
+    if !FactorizationFactory::is_probable_prime(n) {
+        return Primality::Composite;
+    }
+    if *n < BigInt::from(DETERMINISTIC_MILLER_RABIN_BOUND) {
+        return Primality::Proven;
+    }
+
+    let n_minus_one = n - 1;
+    let (mut primes, cofactor) = trial_division(&n_minus_one, TRIAL_DIVISION_LIMIT);
+    let mut factored = n_minus_one.div_floor(&cofactor);
+    if !cofactor.is_one() && certify(&cofactor) == Primality::Proven {
+        primes.push((cofactor.clone(), 1));
+        factored = n_minus_one.clone();
+    }
+    if &factored * &factored <= *n {
+        return Primality::Probable;
+    }
+
+    for (q, _) in &primes {
+        let exponent = n_minus_one.div_floor(q);
+        let witnessed = (2..2 + POCKLINGTON_WITNESSES).map(BigInt::from).any(|a| {
+            a.modpow(&n_minus_one, n).is_one() && (a.modpow(&exponent, n) - BigInt::one()).gcd(n).is_one()
+        });
+        if !witnessed {
+            return Primality::Probable;
+        }
+    }
+    Primality::Proven
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn certifies_primes_past_the_deterministic_bound() {
+        // 2¹²⁷ - 1 and (2¹²⁷ - 1)·(2⁶¹ - 1).
+        let mersenne = (BigInt::one() << 127) - 1;
+        assert_eq!(certify(&mersenne), Primality::Proven);
+        assert_eq!(certify(&(&mersenne * ((BigInt::one() << 61) - 1))), Primality::Composite);
+        assert_eq!(certify(&BigInt::from(1_000_003)), Primality::Proven);
+        assert_eq!(certify(&BigInt::from(1)), Primality::Composite);
+    }
+}
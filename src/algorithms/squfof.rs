@@ -0,0 +1,98 @@
+// src/algorithms/squfof.rs
+
+use num::{BigInt, Integer, ToPrimitive};
+use num::integer::Roots;
+
+/// Square-free multipliers k tried in turn, products of 3, 5, 7 and 11, after Gower and Wagstaff.
+/// Running on kn rather than n changes the continued fraction, and so which forms turn up square.
+const MULTIPLIERS: [u64; 16] = [1, 3, 5, 7, 11, 15, 21, 33, 35, 55, 77, 105, 165, 231, 385, 1155];
+/// Forward steps allowed per multiplier, as a multiple of (kn)^¼.
+const FORWARD_STEP_FACTOR: i128 = 4;
+
+/// Shanks' square forms factorization. Returns a non-trivial factor of the odd composite `n`,
+/// which must fit in a u64, or None if every multiplier ran out of steps.
+pub fn squfof(n: &BigInt) -> Option<BigInt> {
+    let n = n.to_u64()?;
+    if n.is_even() {
+        return Some(BigInt::from(2));
+    }
+    MULTIPLIERS.iter().find_map(|&k| with_multiplier(n, k)).map(BigInt::from)
+}
+
+fn perfect_square_root(value: i128) -> Option<i128> {
+    let root = value.sqrt();
+    (root * root == value).then_some(root)
+}
+
+/// Walks the continued fraction of √(kn) until a form at an even step has a square Q = r², then
+/// walks back from the reduced square root form until P repeats; gcd(n, P) then usually splits n.
+/// A square that only gives a trivial gcd is passed over and the forward walk goes on.
+fn with_multiplier(n: u64, k: u64) -> Option<u64> {
+    let kn = k as i128 * n as i128;
+    let p0 = kn.sqrt();
+    let mut q_previous: i128 = 1;
+    let mut q = kn - p0 * p0;
+    if q == 0 {
+        return proper_factor(n, p0);
+    }
+    let mut p = p0;
+
+    let steps = FORWARD_STEP_FACTOR * p0.sqrt().max(1);
+    for step in 1..steps {
+        let b = (p0 + p) / q;
+        let p_next = b * q - p;
+        let q_next = q_previous + b * (p - p_next);
+        q_previous = q;
+        q = q_next;
+        p = p_next;
+        if step % 2 == 1 {
+            if let Some(root) = perfect_square_root(q) {
+                if let Some(factor) = reverse(n, kn, p0, p, root, steps) {
+                    return Some(factor);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The reverse cycle from the square root of the form (Q, P) with Q = root².
+fn reverse(n: u64, kn: i128, p0: i128, p: i128, root: i128, steps: i128) -> Option<u64> {
+    let b = (p0 - p) / root;
+    let mut p = b * root + p;
+    let mut q_previous = root;
+    let mut q = (kn - p * p) / q_previous;
+    for _ in 0..steps {
+        if q == 0 {
+            return None;
+        }
+        let b = (p0 + p) / q;
+        let p_next = b * q - p;
+        if p_next == p {
+            return proper_factor(n, p);
+        }
+        let q_next = q_previous + b * (p - p_next);
+        q_previous = q;
+        q = q_next;
+        p = p_next;
+    }
+    None
+}
+
+fn proper_factor(n: u64, value: i128) -> Option<u64> {
+    let g = (value.unsigned_abs() as u64).gcd(&n);
+    (g != 1 && g != n).then_some(g)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_sixty_bit_semiprimes() {
+        for (p, q) in [(1_000_003u64, 1_000_033u64), (1_073_741_827, 1_073_741_831), (11, 1_000_000_007)] {
+            let factor = squfof(&BigInt::from(p * q)).expect("no factor found");
+            assert!(factor == BigInt::from(p) || factor == BigInt::from(q), "{} * {}", p, q);
+        }
+    }
+}
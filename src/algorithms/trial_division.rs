@@ -0,0 +1,78 @@
+// src/algorithms/trial_division.rs
+
+use num::{BigInt, One, ToPrimitive, Zero};
+
+/// Divisors above this are left to the other algorithms.
+pub const TRIAL_DIVISION_LIMIT: u64 = 1 << 20;
+
+/// Divides every prime p ≤ `limit` out of `n` and returns the primes found with their exponents,
+/// in increasing order, together with the remaining cofactor. The cofactor is 1 when `n` is
+/// fully factored, and otherwise has no prime factor up to `limit`; if it is below `limit`²
+/// it is itself prime.
+pub fn trial_division(n: &BigInt, limit: u64) -> (Vec<(BigInt, u32)>, BigInt) {
+    let mut factors = Vec::new();
+    if n <= &BigInt::one() {
+        return (factors, n.clone());
+    }
+
+    let mut cofactor = n.clone();
+    let two = cofactor.trailing_zeros().unwrap_or(0);
+    if two > 0 && limit >= 2 {
+        factors.push((BigInt::from(2), two as u32));
+        cofactor >>= two;
+    }
+
+    let mut divisor = 3u64;
+    while divisor <= limit {
+        // Stay in machine words once the cofactor fits; this is the common case.
+        if let Some(small) = cofactor.to_u64() {
+            if divisor.saturating_mul(divisor) > small {
+                break;
+            }
+            let mut exponent = 0;
+            let mut small = small;
+            while small % divisor == 0 {
+                small /= divisor;
+                exponent += 1;
+            }
+            if exponent > 0 {
+                factors.push((BigInt::from(divisor), exponent));
+                cofactor = BigInt::from(small);
+            }
+        } else if (&cofactor % divisor).is_zero() {
+            let mut exponent = 0;
+            while (&cofactor % divisor).is_zero() {
+                cofactor /= divisor;
+                exponent += 1;
+            }
+            factors.push((BigInt::from(divisor), exponent));
+        }
+        divisor += 2;
+    }
+
+    // A cofactor left below divisor² is prime; report it unless it exceeds the limit.
+    if !cofactor.is_one() && cofactor.to_u64().is_some_and(|small| small <= limit) {
+        factors.push((cofactor.clone(), 1));
+        cofactor = BigInt::one();
+    }
+    (factors, cofactor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_every_small_factor_and_the_cofactor() {
+        let large = BigInt::from(1_000_000_007u64);
+        let n = BigInt::from(2u32.pow(3) * 3 * 3 * 197) * &large;
+        let (factors, cofactor) = trial_division(&n, 1000);
+        assert_eq!(factors, vec![(BigInt::from(2), 3), (BigInt::from(3), 2), (BigInt::from(197), 1)]);
+        assert_eq!(cofactor, large);
+
+        let (factors, cofactor) = trial_division(&BigInt::from(45113), 300);
+        assert_eq!(factors, vec![(BigInt::from(197), 1), (BigInt::from(229), 1)]);
+        assert!(cofactor.is_one());
+        assert!(trial_division(&BigInt::from(45113), 100).0.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+// src/backends/gpu.rs
+
+//! The line sieve's log accumulation and threshold scan on an OpenCL GPU. The factor-base
+//! entries of both sides are uploaded once; for each row only b, the row's half-width and the
+//! per-block thresholds go to the device, and a byte of flags per cell comes back, the same
+//! flags [`LineSieve`](crate::relation_sieve::log_sieve::LineSieve) computes on the CPU, so the
+//! CPU goes on to factor exactly the candidates it would have.
+//!
+//! One work item per entry walks its residue class across the row, adding its log to the cells
+//! with atomics, then one work item per cell compares both sides' sums with the cell's block
+//! thresholds and clears the sums for the next row. Projective roots hit every cell or none, so
+//! they stay on the host as a constant added during the scan.
+
+use log::info;
+use num::{BigInt, ToPrimitive};
+use opencl3::command_queue::CommandQueue;
+use opencl3::context::Context;
+use opencl3::device::{Device, CL_DEVICE_TYPE_GPU};
+use opencl3::kernel::{ExecuteKernel, Kernel};
+use opencl3::memory::{Buffer, CL_MEM_READ_ONLY, CL_MEM_READ_WRITE};
+use opencl3::platform::get_platforms;
+use opencl3::program::Program;
+use opencl3::types::{cl_uchar, cl_uint, cl_ulong, CL_BLOCKING};
+use crate::core::error::GnfsError;
+use crate::relation_sieve::log_sieve::SieveEntry;
+
+const SOURCE: &str = r#"
+__kernel void accumulate(__global const ulong *primes, __global const ulong *roots, __global const uchar *logs,
+                         const ulong b, const ulong max, const ulong width, __global uint *sums) {
+    size_t entry = get_global_id(0);
+    ulong p = primes[entry];
+    ulong r = roots[entry];
+    if (r == p) {
+        return;
+    }
+    ulong a = (p - (b % p) * r % p) % p;
+    uint log = logs[entry];
+    for (ulong cell = (a + max % p) % p; cell < width; cell += p) {
+        atomic_add(&sums[cell], log);
+    }
+}
+
+__kernel void scan(__global uint *rational, __global uint *algebraic, __global const uchar *thresholds,
+                   const uint rational_constant, const uint algebraic_constant, const ulong block_cells,
+                   __global uchar *flags) {
+    size_t cell = get_global_id(0);
+    size_t block = cell / block_cells;
+    uchar flag = 0;
+    if (min(rational[cell] + rational_constant, 255u) >= thresholds[2 * block]) {
+        flag |= 1;
+    }
+    if (min(algebraic[cell] + algebraic_constant, 255u) >= thresholds[2 * block + 1]) {
+        flag |= 2;
+    }
+    flags[cell] = flag;
+    rational[cell] = 0;
+    algebraic[cell] = 0;
+}
+"#;
+
+/// One side's factor base on the device, with its projective entries kept on the host.
+struct DeviceSide {
+    count: usize,
+    primes: Buffer<cl_ulong>,
+    roots: Buffer<cl_ulong>,
+    logs: Buffer<cl_uchar>,
+    sums: Option<Buffer<cl_uint>>,
+    projective: Vec<(u64, u8)>,
+}
+
+/// A GPU holding both factor bases of a [`LineSieve`](crate::relation_sieve::log_sieve::LineSieve).
+pub struct GpuLineSieve {
+    device_name: String,
+    context: Context,
+    queue: CommandQueue,
+    accumulate: Kernel,
+    scan: Kernel,
+    rational: DeviceSide,
+    algebraic: DeviceSide,
+    /// Cells the row buffers hold; they grow with the rows.
+    width: usize,
+    flags: Option<Buffer<cl_uchar>>,
+}
+
+fn device_error(context: &str, error: impl std::fmt::Display) -> GnfsError {
+    GnfsError::Device(format!("{}: {}", context, error))
+}
+
+impl GpuLineSieve {
+    /// Builds the kernels on the first GPU of any OpenCL platform and uploads the entries, which
+    /// must have p < 2³² so that b·r mod p fits the device's 64-bit arithmetic.
+    pub(crate) fn new(rational: &[SieveEntry], algebraic: &[SieveEntry]) -> Result<Self, GnfsError> {
+        if rational.iter().chain(algebraic).any(|&(p, _, _)| p > u32::MAX as u64) {
+            return Err(GnfsError::Device("factor base primes above 2^32 are not supported".to_string()));
+        }
+        let device_id = get_platforms()
+            .map_err(|e| device_error("no OpenCL platform", e))?
+            .iter()
+            .find_map(|platform| platform.get_devices(CL_DEVICE_TYPE_GPU).ok().and_then(|devices| devices.first().copied()))
+            .ok_or_else(|| GnfsError::Device("no OpenCL GPU found".to_string()))?;
+        let device = Device::new(device_id);
+        let device_name = device.name().unwrap_or_else(|_| "unnamed GPU".to_string());
+        let context = Context::from_device(&device).map_err(|e| device_error("creating a context", e))?;
+        #[allow(deprecated)]
+        let queue = CommandQueue::create(&context, device_id, 0).map_err(|e| device_error("creating a command queue", e))?;
+        let program = Program::create_and_build_from_source(&context, SOURCE, "").map_err(|log| device_error("building the sieve kernels", log))?;
+        let accumulate = Kernel::create(&program, "accumulate").map_err(|e| device_error("accumulate kernel", e))?;
+        let scan = Kernel::create(&program, "scan").map_err(|e| device_error("scan kernel", e))?;
+
+        let rational = DeviceSide::upload(&context, &queue, rational)?;
+        let algebraic = DeviceSide::upload(&context, &queue, algebraic)?;
+        info!("Line sieve offloaded to {} ({} rational and {} algebraic entries).", device_name, rational.count, algebraic.count);
+        Ok(GpuLineSieve { device_name, context, queue, accumulate, scan, rational, algebraic, width: 0, flags: None })
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// Sieves the 2·`max` + 1 cells of row `b`, blocks of `block_cells` cells each, and returns
+    /// each cell's flags: 1 when the rational sum reaches its block's threshold, 2 when the
+    /// algebraic one does. `thresholds` holds the (rational, algebraic) threshold of each block.
+    pub(crate) fn row(&mut self, b: &BigInt, max: i64, block_cells: usize, thresholds: &[(u8, u8)]) -> Result<Vec<u8>, GnfsError> {
+        let b = b.to_u64().ok_or_else(|| GnfsError::Device(format!("row b = {} is beyond 64 bits", b)))?;
+        let width = (2 * max + 1) as usize;
+        if width > self.width {
+            self.rational.sums = Some(zeroed(&self.context, &self.queue, width)?);
+            self.algebraic.sums = Some(zeroed(&self.context, &self.queue, width)?);
+            self.flags = Some(Buffer::create(&self.context, CL_MEM_READ_WRITE, width, std::ptr::null_mut()).map_err(|e| device_error("allocating flags", e))?);
+            self.width = width;
+        }
+
+        for side in [&self.rational, &self.algebraic] {
+            if side.count == 0 {
+                continue;
+            }
+            ExecuteKernel::new(&self.accumulate)
+                .set_arg(&side.primes)
+                .set_arg(&side.roots)
+                .set_arg(&side.logs)
+                .set_arg(&(b as cl_ulong))
+                .set_arg(&(max as cl_ulong))
+                .set_arg(&(width as cl_ulong))
+                .set_arg(side.sums.as_ref().expect("row buffers are allocated"))
+                .set_global_work_size(side.count)
+                .enqueue_nd_range(&self.queue)
+                .map_err(|e| device_error("accumulating logs", e))?;
+        }
+
+        let packed: Vec<cl_uchar> = thresholds.iter().flat_map(|&(rational, algebraic)| [rational, algebraic]).collect();
+        let mut threshold_buffer = Buffer::<cl_uchar>::create(&self.context, CL_MEM_READ_ONLY, packed.len(), std::ptr::null_mut())
+            .map_err(|e| device_error("allocating thresholds", e))?;
+        self.queue.enqueue_write_buffer(&mut threshold_buffer, CL_BLOCKING, 0, &packed, &[]).map_err(|e| device_error("uploading thresholds", e))?;
+        let flags = self.flags.as_ref().expect("row buffers are allocated");
+        ExecuteKernel::new(&self.scan)
+            .set_arg(self.rational.sums.as_ref().expect("row buffers are allocated"))
+            .set_arg(self.algebraic.sums.as_ref().expect("row buffers are allocated"))
+            .set_arg(&threshold_buffer)
+            .set_arg(&(self.rational.constant(b) as cl_uint))
+            .set_arg(&(self.algebraic.constant(b) as cl_uint))
+            .set_arg(&(block_cells as cl_ulong))
+            .set_arg(flags)
+            .set_global_work_size(width)
+            .enqueue_nd_range(&self.queue)
+            .map_err(|e| device_error("scanning the row", e))?;
+
+        let mut result = vec![0u8; width];
+        self.queue.enqueue_read_buffer(flags, CL_BLOCKING, 0, &mut result, &[]).map_err(|e| device_error("reading flags", e))?;
+        Ok(result)
+    }
+}
+
+impl DeviceSide {
+    fn upload(context: &Context, queue: &CommandQueue, entries: &[SieveEntry]) -> Result<Self, GnfsError> {
+        let primes: Vec<cl_ulong> = entries.iter().map(|&(p, _, _)| p).collect();
+        let roots: Vec<cl_ulong> = entries.iter().map(|&(_, r, _)| r).collect();
+        let logs: Vec<cl_uchar> = entries.iter().map(|&(_, _, log)| log).collect();
+        let projective = entries.iter().filter(|&&(p, r, _)| p == r).map(|&(p, _, log)| (p, log)).collect();
+        Ok(DeviceSide {
+            count: entries.len(),
+            primes: upload(context, queue, &primes)?,
+            roots: upload(context, queue, &roots)?,
+            logs: upload(context, queue, &logs)?,
+            sums: None,
+            projective,
+        })
+    }
+
+    /// The logs of the projective primes dividing b, saturated as the CPU sieve does.
+    fn constant(&self, b: u64) -> u8 {
+        self.projective.iter().filter(|&&(p, _)| b.is_multiple_of(p)).fold(0u8, |sum, &(_, log)| sum.saturating_add(log))
+    }
+}
+
+fn upload<T>(context: &Context, queue: &CommandQueue, data: &[T]) -> Result<Buffer<T>, GnfsError> {
+    // Zero-sized buffers are invalid in OpenCL; an empty side is never launched.
+    let mut buffer = Buffer::create(context, CL_MEM_READ_ONLY, data.len().max(1), std::ptr::null_mut()).map_err(|e| device_error("allocating factor base", e))?;
+    if !data.is_empty() {
+        queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, data, &[]).map_err(|e| device_error("uploading factor base", e))?;
+    }
+    Ok(buffer)
+}
+
+fn zeroed(context: &Context, queue: &CommandQueue, width: usize) -> Result<Buffer<cl_uint>, GnfsError> {
+    let mut buffer = Buffer::create(context, CL_MEM_READ_WRITE, width, std::ptr::null_mut()).map_err(|e| device_error("allocating row sums", e))?;
+    queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &vec![0; width], &[]).map_err(|e| device_error("clearing row sums", e))?;
+    Ok(buffer)
+}
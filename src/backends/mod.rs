@@ -0,0 +1,6 @@
+// src/backends/mod.rs
+
+//! Sieving offloaded from the CPU, each device behind its own cargo feature.
+
+#[cfg(feature = "gpu")]
+pub mod gpu;
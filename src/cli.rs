@@ -0,0 +1,657 @@
+// src/cli.rs
+
+//! The `gnfs` command line. Parsing lives in the library so it can be tested without running the
+//! binary; `main.rs` only dispatches on the parsed [`Command`].
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use clap::{Args, Parser, Subcommand};
+use num::BigInt;
+use crate::algorithms::Algorithm;
+use crate::core::benchmark::DEFAULT_TRIALS;
+use crate::core::error::GnfsError;
+use crate::core::factorizer::{FactorizationResult, Factorizer};
+use crate::core::gnfs_config::{ConfigProfile, GnfsConfig};
+use crate::core::input;
+use crate::core::report::FactorizationReport;
+use crate::distributed::work_unit::{DEFAULT_UNIT_ROWS, DEFAULT_UNIT_SPECIAL_Q};
+use crate::matrix::MatrixSolver;
+use crate::polynomial::PolynomialSelection;
+use crate::relation_sieve::{SieveDevice, SieveStrategy};
+use crate::relation_sieve::storage::RelationFormat;
+use crate::square_root::SquareRootMethod;
+
+/// Number factored when none is given.
+pub const DEFAULT_N: &str = "45113";
+/// Where `bench` writes its report unless `--output` says otherwise.
+const BENCHMARK_FILENAME: &str = "benchmark.json";
+/// First arguments clap handles itself; anything else starts an implicit `factor` command.
+const COMMAND_WORDS: [&str; 17] = [
+    "factor", "bench", "resume", "serve", "worker", "export-workunits", "import-relations", "export-msieve", "convert-relations", "status",
+    "verify-relations", "clean",
+    "help", "-h", "--help", "-V", "--version",
+];
+/// Flags of [`Cli`] itself, which may come before the subcommand.
+const GLOBAL_FLAGS: [&str; 1] = ["--quiet"];
+
+#[derive(Debug, Parser)]
+#[command(name = "gnfs", version, about = "Factors integers with the general number field sieve")]
+pub struct Cli {
+    /// Log at warn rather than info; the factors are still printed.
+    #[arg(long, global = true)]
+    pub quiet: bool,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    /// Parses `args`, program name first. A command line that doesn't start with a subcommand is
+    /// read as `factor`, so `gnfs 45113` and `gnfs --n 45113` still work, and the old `--bench`
+    /// flag is read as `bench`.
+    pub fn try_parse_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        Cli::try_parse_from(with_default_command(args.into_iter().map(Into::into).collect()))
+    }
+}
+
+fn with_default_command(mut args: Vec<OsString>) -> Vec<OsString> {
+    // The subcommand, if there is one, follows the program name and any global flags.
+    let at = args.iter().skip(1).position(|arg| !GLOBAL_FLAGS.iter().any(|flag| arg == flag)).map_or(args.len(), |i| i + 1);
+    if let Some(position) = args.iter().position(|arg| arg == "--bench") {
+        args.remove(position);
+        args.insert(at.min(position), "bench".into());
+    } else if !args.get(at).and_then(|arg| arg.to_str()).is_some_and(|arg| COMMAND_WORDS.contains(&arg)) {
+        args.insert(at, "factor".into());
+    }
+    args
+}
+
+/// What `factor` prints once it is done: N and all its factors, those from trial division
+/// included. A run that stopped early shows the part it didn't split.
+pub fn answer(result: &FactorizationResult) -> String {
+    let factors: Vec<String> = result.factors().iter().map(ToString::to_string).collect();
+    if result.is_complete() {
+        format!("{} = {}", result.n, factors.join(" * "))
+    } else {
+        format!("{} = {} ({} is not split yet)", result.n, factors.join(" * "), result.cofactor)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Factor N; the default when no subcommand is given.
+    Factor(FactorArgs),
+    /// Time full runs and compare them against an earlier report.
+    Bench(BenchArgs),
+    /// Continue a checkpointed run from its save directory.
+    Resume(ResumeArgs),
+    /// Continue a checkpointed run, handing its sieving out to workers over TCP.
+    Serve(ServeArgs),
+    /// Sieve work units for a `gnfs serve` coordinator, or one exported work-unit file.
+    Worker(WorkerArgs),
+    /// Write the next sieve work units of a checkpointed run as self-contained files.
+    ExportWorkunits(ExportWorkunitsArgs),
+    /// Merge relation files sieved elsewhere, ours or msieve's, into a checkpointed run.
+    ImportRelations(ImportRelationsArgs),
+    /// Write a run's polynomials, factor bases and relations as msieve .fb and .dat files.
+    ExportMsieve(ExportMsieveArgs),
+    /// Rewrite a relation file or store of a run in the binary or the JSONL format.
+    ConvertRelations(ConvertRelationsArgs),
+    /// Show what a save directory holds and where a resume would pick up.
+    Status(StatusArgs),
+    /// Re-check the stored relations of a save directory and report what the matrix would drop.
+    VerifyRelations(VerifyRelationsArgs),
+    /// Delete save directories.
+    Clean(CleanArgs),
+}
+
+/// What `factor` runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Gnfs,
+    /// Factor completely with this method alone.
+    Direct(Algorithm),
+}
+
+impl FromStr for Method {
+    type Err = GnfsError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input == "gnfs" {
+            return Ok(Method::Gnfs);
+        }
+        input.parse().map(Method::Direct).map_err(|_| {
+            GnfsError::InvalidParameter(format!("unknown algorithm {:?}; expected gnfs, trial-division, squfof, pollard-rho or ecm", input))
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct FactorArgs {
+    /// The number to factor.
+    #[arg(value_name = "N", value_parser = input::parse)]
+    n: Option<BigInt>,
+    /// The number to factor, for scripts written before N was positional.
+    #[arg(long = "n", value_name = "N", value_parser = input::parse, conflicts_with = "n", hide = true)]
+    n_option: Option<BigInt>,
+    /// gnfs, or one of trial-division, squfof, pollard-rho and ecm to factor N completely with
+    /// that method alone.
+    #[arg(long, default_value = "gnfs")]
+    pub algorithm: Method,
+    /// Degree of the base-m polynomial; the preset's when unset.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(2..))]
+    pub degree: Option<u32>,
+    /// The base m of the polynomial; the preset's, or ⌊N^(1/degree)⌋ with --degree, when unset.
+    #[arg(long, value_parser = input::parse)]
+    pub base: Option<BigInt>,
+    /// Bound of the rational factor base and of the trial division done first.
+    #[arg(long)]
+    pub prime_bound: Option<u64>,
+    /// Print a JSON report of the run to stdout instead of the factors; logs still go to stderr.
+    #[arg(long)]
+    pub json: bool,
+    /// Also write the JSON report to this file.
+    #[arg(long, value_name = "PATH")]
+    pub output_json: Option<PathBuf>,
+    /// Follow the run on a terminal dashboard (requires the `tui` feature).
+    #[arg(long)]
+    pub tui: bool,
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+impl FactorArgs {
+    pub fn n(&self) -> BigInt {
+        self.n.clone().or_else(|| self.n_option.clone()).unwrap_or_else(|| DEFAULT_N.parse().unwrap())
+    }
+
+    /// A factorizer for N with the method, parameter overrides and configuration given.
+    pub fn factorizer(&self) -> Result<Factorizer, GnfsError> {
+        let mut factorizer = Factorizer::new(self.n()).with_config(self.config.to_config()?);
+        if let Method::Direct(algorithm) = self.algorithm {
+            factorizer = factorizer.with_algorithm(algorithm);
+        }
+        if let Some(degree) = self.degree {
+            factorizer = factorizer.with_polynomial_degree(degree as usize);
+        }
+        if let Some(base) = &self.base {
+            factorizer = factorizer.with_polynomial_base(base.clone());
+        }
+        if let Some(prime_bound) = self.prime_bound {
+            factorizer = factorizer.with_prime_bound(prime_bound);
+        }
+        Ok(factorizer)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct BenchArgs {
+    /// Numbers to time; the default N when none are given.
+    #[arg(value_name = "N", value_parser = input::parse)]
+    n: Vec<BigInt>,
+    #[arg(long = "n", value_name = "N", value_parser = input::parse, hide = true)]
+    n_option: Vec<BigInt>,
+    /// Timed runs of each number.
+    #[arg(long, default_value_t = DEFAULT_TRIALS)]
+    pub trials: usize,
+    /// Where the report is written.
+    #[arg(long, alias = "bench-output", value_name = "PATH", default_value = BENCHMARK_FILENAME)]
+    pub output: PathBuf,
+    /// An earlier report to compare medians against.
+    #[arg(long, alias = "bench-baseline", value_name = "PATH")]
+    pub baseline: Option<PathBuf>,
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+impl BenchArgs {
+    pub fn cases(&self) -> Vec<BigInt> {
+        let cases: Vec<BigInt> = self.n.iter().chain(&self.n_option).cloned().collect();
+        if cases.is_empty() {
+            vec![DEFAULT_N.parse().unwrap()]
+        } else {
+            cases
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ResumeArgs {
+    /// The save directory of the run.
+    pub directory: PathBuf,
+    /// Resume even when checkpoint files fail verification.
+    #[arg(long)]
+    pub force: bool,
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// The save directory of the run; its work-unit ledger is kept there too.
+    pub directory: PathBuf,
+    /// Address to accept workers on.
+    #[arg(long, value_name = "ADDR", default_value = "0.0.0.0:7878")]
+    pub listen: String,
+    /// b rows per line-sieve work unit.
+    #[arg(long, value_name = "ROWS", default_value_t = DEFAULT_UNIT_ROWS)]
+    pub rows_per_unit: u64,
+    /// Span of special-q values per lattice-sieve work unit.
+    #[arg(long, value_name = "Q", default_value_t = DEFAULT_UNIT_SPECIAL_Q)]
+    pub special_q_per_unit: u64,
+    /// Serve even when checkpoint files fail verification.
+    #[arg(long)]
+    pub force: bool,
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkerArgs {
+    /// Address of the coordinator, as host:port.
+    #[arg(long, value_name = "ADDR", required_unless_present = "workunit", conflicts_with = "workunit")]
+    pub connect: Option<String>,
+    /// Sieve this file written by `gnfs export-workunits` instead of connecting.
+    #[arg(long, value_name = "FILE")]
+    pub workunit: Option<PathBuf>,
+    /// Where to write the relations of `--workunit`; beside it, as .relations.jsonl, when unset.
+    #[arg(long, value_name = "FILE", requires = "workunit")]
+    pub output: Option<PathBuf>,
+    /// Name the coordinator reports this worker's work under; the host name and process id when unset.
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Threads for the line sieve's regions; all cores when unset.
+    #[arg(long)]
+    pub threads: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportWorkunitsArgs {
+    /// The save directory of the run; its work-unit ledger records what was exported.
+    pub directory: PathBuf,
+    /// Number of work units to write.
+    #[arg(long, default_value_t = 1)]
+    pub count: usize,
+    /// Directory to write the work-unit files to; `workunits` in the save directory when unset.
+    #[arg(long, value_name = "DIR")]
+    pub out: Option<PathBuf>,
+    /// b rows per line-sieve work unit, for a run without a ledger yet.
+    #[arg(long, value_name = "ROWS", default_value_t = DEFAULT_UNIT_ROWS)]
+    pub rows_per_unit: u64,
+    /// Span of special-q values per lattice-sieve work unit, for a run without a ledger yet.
+    #[arg(long, value_name = "Q", default_value_t = DEFAULT_UNIT_SPECIAL_Q)]
+    pub special_q_per_unit: u64,
+    /// Export even when checkpoint files fail verification.
+    #[arg(long)]
+    pub force: bool,
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportRelationsArgs {
+    /// The save directory of the run to merge into.
+    pub directory: PathBuf,
+    /// Relation files, e.g. written by `gnfs worker --workunit` or copied from another run's
+    /// save directory.
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    /// Import even when checkpoint files fail verification.
+    #[arg(long)]
+    pub force: bool,
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportMsieveArgs {
+    /// The save directory of the run.
+    pub directory: PathBuf,
+    /// Directory to write msieve.fb and msieve.dat to; the save directory when unset.
+    #[arg(long, value_name = "DIR")]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertRelationsArgs {
+    /// The save directory of the run the relations belong to.
+    pub directory: PathBuf,
+    /// A binary relation store or a JSON relation file.
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// binary or jsonl; jsonl for a .jsonl or .json output, binary otherwise, when unset.
+    #[arg(long)]
+    pub to: Option<RelationFormat>,
+}
+
+#[derive(Debug, Args)]
+pub struct StatusArgs {
+    pub directory: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyRelationsArgs {
+    /// The save directory whose relations to check.
+    pub directory: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct CleanArgs {
+    /// Save directories to delete; each must hold a checkpoint.
+    #[arg(required = true)]
+    pub directories: Vec<PathBuf>,
+}
+
+/// Run settings shared by the commands that run or continue a factorization, each mapping onto a [`GnfsConfig`] field.
+#[derive(Debug, Default, Args)]
+pub struct ConfigArgs {
+    /// A GnfsConfig JSON file to start from; the options below override it.
+    #[arg(long = "config", value_name = "PATH")]
+    pub config_file: Option<PathBuf>,
+    /// fast, balanced or low-memory; only fills settings not given otherwise.
+    #[arg(long)]
+    pub profile: Option<ConfigProfile>,
+    /// Worker threads for parallel stages; all cores when unset.
+    #[arg(long)]
+    pub threads: Option<usize>,
+    /// Draw every random choice from this seed, so the run can be reproduced.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Checkpoint and exit once this many hours have passed.
+    #[arg(long, value_parser = parse_hours)]
+    pub max_hours: Option<f64>,
+    /// base-m or kleinjung; base-m when unset.
+    #[arg(long)]
+    pub polynomial_selection: Option<PolynomialSelection>,
+    /// Seconds the polynomial search may take.
+    #[arg(long)]
+    pub polynomial_search_secs: Option<u64>,
+    /// Candidates the polynomial search scores before it stops.
+    #[arg(long)]
+    pub polynomial_search_candidates: Option<usize>,
+    /// Polynomials kept from the search and root-optimized.
+    #[arg(long)]
+    pub polynomial_candidates: Option<usize>,
+    /// Largest leading coefficient the kleinjung search tries.
+    #[arg(long)]
+    pub polynomial_max_leading_coefficient: Option<u64>,
+    /// Bases on either side of the base-m base also scored.
+    #[arg(long)]
+    pub polynomial_base_range: Option<u64>,
+    /// Keep the selected polynomial as it is rather than rotating and translating it.
+    #[arg(long)]
+    pub no_polynomial_optimization: bool,
+    /// Sieve block length in bytes.
+    #[arg(long)]
+    pub sieve_block_length: Option<usize>,
+    /// Sieve blocks per region, each region sieved by one thread.
+    #[arg(long)]
+    pub sieve_bucket_count: Option<usize>,
+    /// line or lattice.
+    #[arg(long)]
+    pub sieve_strategy: Option<SieveStrategy>,
+    /// cpu or gpu; gpu needs a build with the gpu feature.
+    #[arg(long)]
+    pub sieve_device: Option<SieveDevice>,
+    /// Smallest special-q the lattice sieve uses; half the algebraic factor base bound when unset.
+    #[arg(long)]
+    pub special_q_min: Option<u64>,
+    /// Largest special-q the lattice sieve uses; the algebraic factor base bound when unset.
+    #[arg(long)]
+    pub special_q_max: Option<u64>,
+    /// Polynomials sieved together, sharing their rational side.
+    #[arg(long)]
+    pub polynomials: Option<usize>,
+    /// Trial-divide every coprime pair instead of sieving rows with logarithms.
+    #[arg(long)]
+    pub no_log_sieve: bool,
+    /// Drop relations with a large prime instead of combining them into full ones.
+    #[arg(long)]
+    pub no_large_primes: bool,
+    /// Keep large primes up to this multiple of their side's factor base bound.
+    #[arg(long)]
+    pub large_prime_multiplier: Option<usize>,
+    /// Also combine relations with a large prime on each side; keeps them all in memory.
+    #[arg(long)]
+    pub double_large_primes: bool,
+    /// Rough relations kept before further ones are discarded.
+    #[arg(long)]
+    pub rough_relation_cap: Option<usize>,
+    /// Keep rough relations in memory rather than in a file.
+    #[arg(long)]
+    pub no_rough_spill: bool,
+    /// Relations buffered before they are written; derived from the memory available when unset.
+    #[arg(long)]
+    pub relation_flush_threshold: Option<usize>,
+    /// Longest buffered relations wait before being written, in seconds.
+    #[arg(long)]
+    pub relation_flush_interval: Option<u64>,
+    /// How often partial relations are combined during sieving, in seconds.
+    #[arg(long)]
+    pub relation_recycle_interval: Option<u64>,
+    /// Bytes of buffered relations before they spill to disk.
+    #[arg(long)]
+    pub relation_memory_cap: Option<usize>,
+    /// Write the smooth relation file zstd-compressed.
+    #[arg(long)]
+    pub compress_relations: bool,
+    /// Resident memory in bytes to warn near.
+    #[arg(long)]
+    pub memory_budget: Option<usize>,
+    /// binary or jsonl; how the sorted relation store is written.
+    #[arg(long)]
+    pub relation_format: Option<RelationFormat>,
+    /// Decoded relations the matrix stage keeps cached while it reads them from disk.
+    #[arg(long)]
+    pub relation_cache: Option<usize>,
+    /// Extra relations beyond the matrix column count.
+    #[arg(long)]
+    pub relation_slack: Option<usize>,
+    /// Build the matrix from every relation set, without singleton removal or merges.
+    #[arg(long)]
+    pub no_filter: bool,
+    /// gaussian or wiedemann.
+    #[arg(long)]
+    pub matrix_solver: Option<MatrixSolver>,
+    /// montgomery or inert-primes.
+    #[arg(long)]
+    pub square_root: Option<SquareRootMethod>,
+    /// Extra quadratic characters checked per dependency.
+    #[arg(long)]
+    pub character_checks: Option<usize>,
+    /// Trial-sieve a few prime bounds and keep the fastest.
+    #[arg(long)]
+    pub calibrate_bounds: bool,
+    /// Ask factordb.com for known factors first.
+    #[arg(long)]
+    pub factordb: bool,
+    /// Report new factors to factordb.com.
+    #[arg(long)]
+    pub factordb_submit: bool,
+}
+
+fn parse_hours(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|hours: &f64| *hours > 0.0)
+        .ok_or_else(|| format!("expected a positive number of hours, got {:?}", value))
+}
+
+impl ConfigArgs {
+    /// The `--config` file, or the defaults, with every option given applied over it.
+    pub fn to_config(&self) -> Result<GnfsConfig, GnfsError> {
+        let mut config: GnfsConfig = match &self.config_file {
+            Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+            None => GnfsConfig::default(),
+        };
+        config.threads = self.threads.or(config.threads);
+        config.seed = self.seed.or(config.seed);
+        if let Some(hours) = self.max_hours {
+            config.max_run_secs = Some((hours * 3600.0).round() as u64);
+        }
+        config.polynomial_selection = self.polynomial_selection.or(config.polynomial_selection);
+        config.polynomial_search_secs = self.polynomial_search_secs.or(config.polynomial_search_secs);
+        config.polynomial_search_candidates = self.polynomial_search_candidates.or(config.polynomial_search_candidates);
+        config.polynomial_candidates = self.polynomial_candidates.or(config.polynomial_candidates);
+        config.polynomial_max_leading_coefficient = self.polynomial_max_leading_coefficient.or(config.polynomial_max_leading_coefficient);
+        config.polynomial_base_range = self.polynomial_base_range.or(config.polynomial_base_range);
+        if self.no_polynomial_optimization {
+            config.polynomial_optimization = Some(false);
+        }
+        config.sieve_block_length = self.sieve_block_length.or(config.sieve_block_length);
+        config.sieve_bucket_count = self.sieve_bucket_count.or(config.sieve_bucket_count);
+        config.sieve_strategy = self.sieve_strategy.or(config.sieve_strategy);
+        config.sieve_device = self.sieve_device.or(config.sieve_device);
+        config.special_q_min = self.special_q_min.or(config.special_q_min);
+        config.special_q_max = self.special_q_max.or(config.special_q_max);
+        config.sieve_polynomials = self.polynomials.or(config.sieve_polynomials);
+        if self.no_log_sieve {
+            config.log_sieve = Some(false);
+        }
+        if self.no_large_primes {
+            config.large_prime_recycling = Some(false);
+        }
+        config.large_prime_multiplier = self.large_prime_multiplier.or(config.large_prime_multiplier);
+        if self.double_large_primes {
+            config.double_large_primes = Some(true);
+        }
+        config.rough_relation_cap = self.rough_relation_cap.or(config.rough_relation_cap);
+        if self.no_rough_spill {
+            config.spill_rough_relations = Some(false);
+        }
+        config.relation_flush_threshold = self.relation_flush_threshold.or(config.relation_flush_threshold);
+        config.relation_flush_interval_secs = self.relation_flush_interval.or(config.relation_flush_interval_secs);
+        config.relation_recycle_interval_secs = self.relation_recycle_interval.or(config.relation_recycle_interval_secs);
+        config.relation_memory_cap = self.relation_memory_cap.or(config.relation_memory_cap);
+        if self.compress_relations {
+            config.compress_relations = Some(true);
+        }
+        config.memory_budget = self.memory_budget.or(config.memory_budget);
+        config.relation_slack = self.relation_slack.or(config.relation_slack);
+        config.relation_format = self.relation_format.or(config.relation_format);
+        config.relation_cache_capacity = self.relation_cache.or(config.relation_cache_capacity);
+        if self.no_filter {
+            config.relation_filter = Some(false);
+        }
+        config.matrix_solver = self.matrix_solver.or(config.matrix_solver);
+        config.square_root_method = self.square_root.or(config.square_root_method);
+        config.quadratic_character_checks = self.character_checks.or(config.quadratic_character_checks);
+        config.calibrate_prime_bound |= self.calibrate_bounds;
+        config.factordb_lookup |= self.factordb;
+        config.factordb_submit |= self.factordb_submit;
+        // Applied last so explicit options win over the profile.
+        if let Some(profile) = self.profile.or(config.profile) {
+            config.apply_profile(profile);
+        }
+        Ok(config)
+    }
+}
+
+/// Writes the report of `result` to `path` as JSON.
+pub fn write_json(path: &Path, result: &FactorizationResult) -> Result<(), GnfsError> {
+    fs::write(path, FactorizationReport::new(result).to_json()?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_arguments_are_a_factor_command() {
+        let cli = Cli::try_parse_args(["gnfs", "--quiet", "1000003", "--threads", "2", "--no-log-sieve"]).unwrap();
+        let Command::Factor(args) = cli.command else { panic!("expected factor") };
+        assert!(cli.quiet);
+        assert_eq!(args.n(), BigInt::from(1_000_003));
+        assert_eq!(args.algorithm, Method::Gnfs);
+        let config = args.config.to_config().unwrap();
+        assert_eq!((config.threads, config.log_sieve), (Some(2), Some(false)));
+
+        let cli = Cli::try_parse_args(["gnfs", "45113", "--sieve-device", "gpu"]).unwrap();
+        let Command::Factor(args) = cli.command else { panic!("expected factor") };
+        assert_eq!(args.config.to_config().unwrap().sieve_device(), SieveDevice::Gpu);
+        assert!(Cli::try_parse_args(["gnfs", "45113", "--sieve-device", "tpu"]).is_err());
+
+        let cli = Cli::try_parse_args(["gnfs", "--bench", "--n", "45113", "--trials", "1"]).unwrap();
+        let Command::Bench(args) = cli.command else { panic!("expected bench") };
+        assert_eq!((args.cases(), args.trials), (vec![BigInt::from(45113)], 1));
+
+        let cli = Cli::try_parse_args(["gnfs", "factor", "--n", "91", "--algorithm", "squfof"]).unwrap();
+        let Command::Factor(args) = cli.command else { panic!("expected factor") };
+        assert_eq!((args.n(), args.algorithm), (BigInt::from(91), Method::Direct(Algorithm::Squfof)));
+        assert!(Cli::try_parse_args(["gnfs", "factor", "--algorithm", "qs"]).is_err());
+
+        let cli = Cli::try_parse_args(["gnfs", "verify-relations", "45113"]).unwrap();
+        let Command::VerifyRelations(args) = cli.command else { panic!("expected verify-relations") };
+        assert_eq!(args.directory, PathBuf::from("45113"));
+
+        let cli = Cli::try_parse_args(["gnfs", "serve", "45113", "--listen", "127.0.0.1:9000", "--rows-per-unit", "20"]).unwrap();
+        let Command::Serve(args) = cli.command else { panic!("expected serve") };
+        assert_eq!((args.listen.as_str(), args.rows_per_unit, args.special_q_per_unit), ("127.0.0.1:9000", 20, DEFAULT_UNIT_SPECIAL_Q));
+
+        let cli = Cli::try_parse_args(["gnfs", "worker", "--connect", "coordinator:7878"]).unwrap();
+        let Command::Worker(args) = cli.command else { panic!("expected worker") };
+        assert_eq!((args.connect.as_deref(), args.name), (Some("coordinator:7878"), None));
+        assert!(Cli::try_parse_args(["gnfs", "worker"]).is_err());
+        assert!(Cli::try_parse_args(["gnfs", "worker", "--workunit", "workunit-00001.json"]).is_ok());
+        assert!(Cli::try_parse_args(["gnfs", "worker", "--connect", "coordinator:7878", "--workunit", "workunit-00001.json"]).is_err());
+
+        let cli = Cli::try_parse_args(["gnfs", "import-relations", "45113", "a.jsonl", "b.jsonl"]).unwrap();
+        let Command::ImportRelations(args) = cli.command else { panic!("expected import-relations") };
+        assert_eq!(args.files, vec![PathBuf::from("a.jsonl"), PathBuf::from("b.jsonl")]);
+        assert!(Cli::try_parse_args(["gnfs", "import-relations", "45113"]).is_err());
+
+        let cli = Cli::try_parse_args(["gnfs", "export-msieve", "45113", "--out", "msieve"]).unwrap();
+        let Command::ExportMsieve(args) = cli.command else { panic!("expected export-msieve") };
+        assert_eq!(args.out, Some(PathBuf::from("msieve")));
+
+        let cli = Cli::try_parse_args(["gnfs", "convert-relations", "45113", "in.bin", "out.jsonl"]).unwrap();
+        let Command::ConvertRelations(args) = cli.command else { panic!("expected convert-relations") };
+        assert_eq!((args.output, args.to), (PathBuf::from("out.jsonl"), None));
+        let cli = Cli::try_parse_args(["gnfs", "factor", "45113", "--relation-format", "jsonl"]).unwrap();
+        let Command::Factor(args) = cli.command else { panic!("expected factor") };
+        assert_eq!(args.config.to_config().unwrap().relation_format(), RelationFormat::Jsonl);
+    }
+
+    #[test]
+    fn global_flags_may_come_before_the_subcommand() {
+        let cli = Cli::try_parse_args(["gnfs", "--quiet", "factor", "45113"]).unwrap();
+        let Command::Factor(args) = cli.command else { panic!("expected factor") };
+        assert!(cli.quiet);
+        assert_eq!(args.n(), BigInt::from(45113));
+
+        let cli = Cli::try_parse_args(["gnfs", "--quiet", "status", "45113"]).unwrap();
+        assert!(cli.quiet && matches!(cli.command, Command::Status(_)));
+
+        let cli = Cli::try_parse_args(["gnfs", "--quiet", "--bench", "--trials", "1"]).unwrap();
+        assert!(cli.quiet && matches!(cli.command, Command::Bench(_)));
+
+        let cli = Cli::try_parse_args(["gnfs", "--quiet"]).unwrap();
+        let Command::Factor(args) = cli.command else { panic!("expected factor") };
+        assert_eq!(args.n(), DEFAULT_N.parse::<BigInt>().unwrap());
+    }
+
+    #[test]
+    fn the_answer_lists_every_factor_of_n() {
+        let factor = |n: u64| {
+            let locations = crate::core::directory_location::DirectoryLocations::temporary("answer").unwrap();
+            let result = Factorizer::new(BigInt::from(n)).with_save_locations(locations).run(&crate::core::cancellation_token::CancellationToken::new()).unwrap();
+            answer(&result)
+        };
+        // Trial division alone, a prime, and a small factor next to the ones GNFS finds.
+        assert_eq!(factor(91), "91 = 7 * 13");
+        assert_eq!(factor(1000003), "1000003 = 1000003");
+        assert_eq!(factor(135339), "135339 = 3 * 197 * 229");
+
+        let input = input::normalize(&BigInt::from(135339), 100).unwrap();
+        let mut result = FactorizationResult::for_input(&input);
+        result.outcome = crate::core::stage::StageOutcome::NeedsMoreWork;
+        assert_eq!(answer(&result), "135339 = 3 * 45113 (45113 is not split yet)");
+    }
+}
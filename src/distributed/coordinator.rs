@@ -0,0 +1,241 @@
+// src/distributed/coordinator.rs
+
+//! The coordinator: accepts workers, hands them work units from the [`WorkUnitLedger`] and adds
+//! what they send to the run. Every connection has its own thread; the run, the ledger and the
+//! relations already known sit behind one lock. After each completed unit the relations, the run
+//! and the ledger are saved, so stopping the coordinator at any point loses at most the units that
+//! were out, and those are issued again on restart.
+
+use std::collections::HashMap;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use log::{debug, info, warn};
+use crate::core::cancellation_token::CancellationToken;
+use crate::core::error::GnfsError;
+use crate::core::gnfs::GNFS;
+use crate::core::events::GnfsEvent;
+use crate::core::serialization::save;
+use crate::distributed::import::{Intake, RelationIntake};
+use crate::distributed::protocol::{self, Connection, Message, PROTOCOL_VERSION};
+use crate::distributed::work_unit::WorkUnitLedger;
+use crate::relation_sieve::relation::Relation;
+
+/// How often the listener and idle connections look at the cancellation token and the target.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The state every connection shares.
+struct Run {
+    gnfs: GNFS,
+    ledger: WorkUnitLedger,
+    directory: PathBuf,
+    /// Keeps a pair sent twice, e.g. by a reissued unit, once.
+    intake: RelationIntake,
+    /// Relations accepted per unit since it was assigned.
+    received: HashMap<u64, u64>,
+}
+
+impl Run {
+    fn target_reached(&self) -> bool {
+        let progress = &self.gnfs.current_relations_progress;
+        progress.smooth_relations_counter >= progress.smooth_relations_target_quantity
+    }
+
+    /// Adds the relations of unit `unit` that pass verification and aren't already held.
+    fn accept(&mut self, unit: u64, relations: Vec<Relation>) {
+        let mut accepted = 0;
+        for relation in relations {
+            let (a, b) = (relation.a.clone(), relation.b.clone());
+            match self.intake.add(&mut self.gnfs, relation) {
+                Intake::Smooth | Intake::Rough => accepted += 1,
+                Intake::Rejected(defect) => warn!("Dropped relation ({}, {}) of unit {}: {:?}", a, b, unit, defect),
+                Intake::Duplicate | Intake::Unused => {}
+            }
+        }
+        *self.received.entry(unit).or_default() += accepted;
+    }
+
+    /// Closes unit `unit` for `worker`, recycles the rough relations and saves everything.
+    fn complete(&mut self, unit: u64, worker: &str, pairs_sieved: u64) -> Result<(), GnfsError> {
+        let relations = self.received.remove(&unit).unwrap_or(0);
+        if !self.ledger.complete(unit, worker, relations, pairs_sieved) {
+            debug!("Unit {} was no longer assigned to {}; its relations are kept all the same.", unit, worker);
+        }
+        self.gnfs.current_relations_progress.pairs_sieved += pairs_sieved;
+        if self.gnfs.config.large_prime_recycling() {
+            let _snapshot = self.gnfs.attach_sieve_snapshot();
+            self.gnfs.current_relations_progress.recycle_rough_relations();
+        }
+        self.gnfs.log_event(GnfsEvent::SieveProgress(self.gnfs.current_relations_progress.statistics()));
+        self.save()
+    }
+
+    fn save(&mut self) -> Result<(), GnfsError> {
+        save::relations::smooth::append(&mut self.gnfs);
+        save::relations::rough::all(&mut self.gnfs);
+        save::gnfs(&self.gnfs);
+        self.ledger.write(&self.directory)?;
+        save::checkpoint_manifest(&self.gnfs);
+        Ok(())
+    }
+}
+
+/// Hands out the sieving of `gnfs` to workers connecting to `listener` until the relation target
+/// is reached or `cancel_token` stops it, and returns whether the target was reached. The ledger
+/// saved beside the run is picked up where it left off; a new one gives out `rows_per_unit` rows
+/// or `special_q_per_unit` special-q at a time. Either way the local sieve position of `gnfs` is
+/// moved past everything handed out, and everything is saved, before returning.
+pub fn serve(cancel_token: &CancellationToken, gnfs: &mut GNFS, listener: TcpListener, rows_per_unit: u64, special_q_per_unit: u64) -> Result<bool, GnfsError> {
+    let directory = PathBuf::from(&gnfs.save_locations.save_directory);
+    let ledger = match WorkUnitLedger::read(&directory)? {
+        Some(ledger) => {
+            info!("Resuming work units: {}", ledger);
+            ledger
+        }
+        None => WorkUnitLedger::new(gnfs, rows_per_unit, special_q_per_unit)?,
+    };
+    let intake = RelationIntake::new(gnfs);
+    let job = Message::job(gnfs);
+    let run = Mutex::new(Run { gnfs: std::mem::take(gnfs), ledger, directory, intake, received: Default::default() });
+
+    listener.set_nonblocking(true)?;
+    info!("Coordinator listening on {}", listener.local_addr()?);
+    let listened = thread::scope(|scope| -> Result<(), GnfsError> {
+        loop {
+            if cancel_token.should_stop() || run.lock().unwrap().target_reached() {
+                return Ok(());
+            }
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    let (run, job) = (&run, &job);
+                    scope.spawn(move || {
+                        if let Err(e) = converse(cancel_token, run, job, stream, peer) {
+                            warn!("Worker at {} dropped: {}", peer, e);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    });
+
+    let mut run = run.into_inner().unwrap();
+    let reached = run.target_reached();
+    run.ledger.advance_local_sieve(&mut run.gnfs);
+    info!("Coordinator stopped: {}", run.ledger);
+    for (worker, totals) in &run.ledger.workers {
+        info!("  {}: {} units, {} relations, {} pairs sieved", worker, totals.units, totals.relations, totals.pairs_sieved);
+    }
+    let saved = run.save();
+    *gnfs = run.gnfs;
+    listened?;
+    saved?;
+    Ok(reached)
+}
+
+/// Talks to one worker until it leaves or the run is over, then hands its unfinished units back.
+fn converse(cancel_token: &CancellationToken, run: &Mutex<Run>, job: &Message, stream: TcpStream, peer: SocketAddr) -> Result<(), GnfsError> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    let mut connection = Connection::new(stream)?;
+    let worker = loop {
+        match connection.receive() {
+            Ok(Some(Message::Hello { worker, version })) if version == PROTOCOL_VERSION => break format!("{}@{}", worker, peer),
+            Ok(Some(Message::Hello { version, .. })) => {
+                return Err(GnfsError::Network(format!("worker speaks protocol {}, this coordinator {}", version, PROTOCOL_VERSION)));
+            }
+            Ok(Some(other)) => return Err(protocol::unexpected(&other, "hello")),
+            Ok(None) => return Ok(()),
+            Err(e) if protocol::timed_out(&e) && !cancel_token.should_stop() => continue,
+            Err(e) => return Err(e),
+        }
+    };
+    info!("Worker {} joined.", worker);
+    connection.send(job)?;
+
+    let result = serve_worker(cancel_token, run, &mut connection, &worker);
+    let released = run.lock().unwrap().ledger.release(&worker);
+    if released > 0 {
+        info!("Worker {} left with {} units unfinished; they will be handed out again.", worker, released);
+    } else {
+        info!("Worker {} left.", worker);
+    }
+    result
+}
+
+fn serve_worker(cancel_token: &CancellationToken, run: &Mutex<Run>, connection: &mut Connection, worker: &str) -> Result<(), GnfsError> {
+    loop {
+        let message = match connection.receive() {
+            Ok(Some(message)) => message,
+            Ok(None) => return Ok(()),
+            Err(e) if protocol::timed_out(&e) => {
+                // A worker busy with a unit is left to finish it unless the run is over.
+                if cancel_token.should_stop() || run.lock().unwrap().target_reached() {
+                    let _ = connection.send(&Message::Finished);
+                    return Ok(());
+                }
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        match message {
+            Message::Request => {
+                let mut run = run.lock().unwrap();
+                if cancel_token.should_stop() || run.target_reached() {
+                    connection.send(&Message::Finished)?;
+                    return Ok(());
+                }
+                let Run { gnfs, ledger, .. } = &mut *run;
+                let unit = ledger.assign(gnfs, worker);
+                debug!("Assigned {} to {}", unit, worker);
+                connection.send(&Message::Assign { unit })?;
+            }
+            Message::Relations { unit, relations } => {
+                run.lock().unwrap().accept(unit, relations.into_iter().map(Relation::from).collect());
+            }
+            Message::Completed { unit, pairs_sieved } => {
+                run.lock().unwrap().complete(unit, worker, pairs_sieved)?;
+            }
+            other => return Err(protocol::unexpected(&other, "request, relations or completed")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::gnfs_config::GnfsConfig;
+    use std::path::Path;
+    use num::BigInt;
+    use crate::distributed::worker;
+    use crate::distributed::work_unit::WORK_UNITS_FILENAME;
+    use crate::relation_sieve::verifier;
+
+    #[test]
+    fn a_worker_sieves_until_the_coordinator_reaches_its_target() {
+        let cancel_token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("coordinator").unwrap();
+        let n = BigInt::from(45113);
+        let mut gnfs = GNFS::with_save_locations(&cancel_token, locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let summary = thread::scope(|scope| {
+            let worker = scope.spawn(|| worker::run(&cancel_token, &address, "test"));
+            assert!(serve(&cancel_token, &mut gnfs, listener, 10, 100).unwrap());
+            worker.join().unwrap().unwrap()
+        });
+
+        let progress = &gnfs.current_relations_progress;
+        assert!(progress.smooth_relations_counter >= progress.smooth_relations_target_quantity);
+        assert!(summary.units > 0 && summary.relations as usize >= progress.smooth_relations_target_quantity);
+        assert!(progress.relations.smooth_relations.iter().all(|relation| verifier::check(&gnfs, relation, true).is_ok()));
+        let ledger = WorkUnitLedger::read(Path::new(&gnfs.save_locations.save_directory)).unwrap().unwrap();
+        assert!(ledger.workers.keys().all(|worker| worker.starts_with("test@")));
+        assert!(Path::new(&gnfs.save_locations.save_directory).join(WORK_UNITS_FILENAME).exists());
+    }
+}
@@ -0,0 +1,205 @@
+// src/distributed/import.rs
+
+//! Bringing relations sieved elsewhere into a run: those streamed by workers, and relation files
+//! from exported work units, from independent runs over the same polynomial or, in msieve's
+//! `.dat` format, from msieve and GGNFS. Every relation is checked against the run's polynomial
+//! and factor bases and kept once per (a, b).
+
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::PathBuf;
+use log::warn;
+use num::BigInt;
+use crate::core::error::GnfsError;
+use crate::core::gnfs::GNFS;
+use crate::core::interop::msieve::{self, MsieveFactorBase};
+use crate::core::serialization::save;
+use crate::relation_sieve::relation::Relation;
+use crate::relation_sieve::relation_store::{RelationFileReader, SortSummary};
+use crate::relation_sieve::verifier::{self, Defect};
+
+/// What became of a relation offered to a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intake {
+    Smooth,
+    /// Kept for large-prime recycling.
+    Rough,
+    Duplicate,
+    Rejected(Defect),
+    /// Neither smooth nor worth recycling under the run's configuration.
+    Unused,
+}
+
+/// The (a, b) of every relation a run holds in memory, alone or combined, so that each pair is
+/// added once.
+pub struct RelationIntake {
+    known: HashSet<(BigInt, BigInt)>,
+}
+
+impl RelationIntake {
+    pub fn new(gnfs: &GNFS) -> Self {
+        let relations = &gnfs.current_relations_progress.relations;
+        let known = relations
+            .smooth_relations
+            .iter()
+            .chain(&relations.rough_relations)
+            .chain(relations.combined_relations.iter().flatten())
+            .map(|relation| (relation.a.clone(), relation.b.clone()))
+            .collect();
+        RelationIntake { known }
+    }
+
+    /// Adds `relation` to the unsaved relations of `gnfs` when it passes [`verifier::check`] and
+    /// isn't held already. Smooth relations count toward the target; rough ones are kept only
+    /// when large primes are recycled.
+    pub fn add(&mut self, gnfs: &mut GNFS, mut relation: Relation) -> Intake {
+        let smooth = relation.is_smooth();
+        if let Err(defect) = verifier::check(gnfs, &relation, smooth) {
+            return Intake::Rejected(defect);
+        }
+        if !smooth && !gnfs.config.large_prime_recycling() {
+            return Intake::Unused;
+        }
+        if !self.known.insert((relation.a.clone(), relation.b.clone())) {
+            return Intake::Duplicate;
+        }
+        relation.is_persisted = false;
+        let progress = &mut gnfs.current_relations_progress;
+        if smooth {
+            progress.relations.smooth_relations.push(relation);
+            progress.smooth_relations_counter += 1;
+            Intake::Smooth
+        } else {
+            progress.relations.rough_relations.push(relation);
+            Intake::Rough
+        }
+    }
+}
+
+/// What [`import_relations`] read and kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub files: usize,
+    pub read: usize,
+    pub smooth: usize,
+    pub rough: usize,
+    pub duplicates: usize,
+    pub rejected: usize,
+    pub unused: usize,
+    /// The relation store written afterwards.
+    pub store: SortSummary,
+}
+
+impl Display for ImportReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Imported {} smooth and {} rough relations of {} read from {} files; {} duplicate, {} failing verification, {} unused. The relation store holds {} relations.",
+            self.smooth, self.rough, self.read, self.files, self.duplicates, self.rejected, self.unused, self.store.unique_relations
+        )
+    }
+}
+
+impl ImportReport {
+    fn record(&mut self, path: &std::path::Path, intake: Intake) {
+        match intake {
+            Intake::Smooth => self.smooth += 1,
+            Intake::Rough => self.rough += 1,
+            Intake::Duplicate => self.duplicates += 1,
+            Intake::Rejected(defect) => {
+                warn!("{}: record {} failed verification: {:?}", path.display(), self.read, defect);
+                self.rejected += 1;
+            }
+            Intake::Unused => self.unused += 1,
+        }
+    }
+}
+
+/// Merges the relation files `files` into the run of `gnfs`, saves it, and rewrites its sorted
+/// relation store, the one-relation-per-line file the matrix stage streams, with every relation
+/// of the run once. Files in msieve's format are told apart by [`msieve::is_relation_file`]; an
+/// msieve factor base beside one, with the same name and `.fb` in place of `.dat`, must match the
+/// run. Any other file can be in any layout [`RelationFileReader`] reads.
+pub fn import_relations(gnfs: &mut GNFS, files: &[PathBuf]) -> Result<ImportReport, GnfsError> {
+    let mut report = ImportReport { files: files.len(), ..Default::default() };
+    let mut intake = RelationIntake::new(gnfs);
+    for path in files {
+        if msieve::is_relation_file(path) {
+            let factor_base = path.with_extension("fb");
+            if factor_base.exists() {
+                if let Some(mismatch) = MsieveFactorBase::read(&factor_base)?.mismatch(gnfs) {
+                    return Err(GnfsError::InvalidParameter(format!("{} doesn't fit the run: {}", factor_base.display(), mismatch)));
+                }
+            }
+            let (relations, foreign) = msieve::read_relations(gnfs, path)?;
+            if foreign > 0 {
+                warn!("{}: {} relations don't fit the run's polynomials.", path.display(), foreign);
+            }
+            report.read += foreign;
+            report.rejected += foreign;
+            for relation in relations {
+                report.read += 1;
+                let added = intake.add(gnfs, relation);
+                report.record(path, added);
+            }
+            continue;
+        }
+        for record in RelationFileReader::open(path)? {
+            report.read += 1;
+            let added = intake.add(gnfs, Relation::from(record?));
+            report.record(path, added);
+        }
+    }
+
+    if gnfs.config.large_prime_recycling() {
+        let _snapshot = gnfs.attach_sieve_snapshot();
+        gnfs.current_relations_progress.recycle_rough_relations();
+    }
+    save::relations::smooth::append(gnfs);
+    save::relations::rough::all(gnfs);
+    save::gnfs(gnfs);
+    save::checkpoint_manifest(gnfs);
+    report.store = gnfs.sort_relation_store()?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use crate::core::cancellation_token::CancellationToken;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::gnfs_config::GnfsConfig;
+    use crate::distributed::work_unit::{self, WorkUnitFile, WorkUnitLedger};
+    use crate::distributed::worker;
+
+    #[test]
+    fn exported_units_sieved_apart_import_once() {
+        let cancel_token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("import").unwrap();
+        let n = BigInt::from(45113);
+        let mut gnfs = GNFS::with_save_locations(&cancel_token, locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let directory = Path::new(&gnfs.save_locations.save_directory).to_path_buf();
+        let mut ledger = WorkUnitLedger::new(&gnfs, 5, 100).unwrap();
+
+        let mut outputs = Vec::new();
+        for unit in ledger.export(&gnfs, 2) {
+            let path = directory.join(WorkUnitFile::filename(unit.id));
+            WorkUnitFile { unit, job: Box::new(work_unit::job(&gnfs)) }.write(&path).unwrap();
+            let output = path.with_extension("relations.jsonl");
+            worker::sieve_file(&cancel_token, &path, &output).unwrap();
+            outputs.push(output);
+        }
+
+        let first = import_relations(&mut gnfs, &outputs).unwrap();
+        assert!(first.smooth > 0 && first.rejected == 0);
+        assert_eq!(first.store.unique_relations, first.smooth);
+        // Recycled rough relations count toward the target too.
+        let counter = gnfs.current_relations_progress.smooth_relations_counter;
+        assert!(counter >= first.smooth);
+
+        let again = import_relations(&mut gnfs, &outputs).unwrap();
+        assert_eq!((again.smooth, again.rough, again.duplicates), (0, 0, first.smooth + first.rough));
+        assert_eq!(gnfs.current_relations_progress.smooth_relations_counter, counter);
+    }
+}
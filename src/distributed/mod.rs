@@ -0,0 +1,13 @@
+// src/distributed/mod.rs
+
+//! Sieving spread over several machines. A coordinator holding the run hands out work units,
+//! ranges of special-q or of b rows, to workers that connect over TCP; the workers sieve them with
+//! the run's polynomial and factor bases and stream the relations back, and the coordinator
+//! verifies, deduplicates and checkpoints them as a local sieve would. Units can also travel as
+//! files, for machines the coordinator can't reach, with the relations imported afterwards.
+
+pub mod coordinator;
+pub mod import;
+pub mod protocol;
+pub mod work_unit;
+pub mod worker;
@@ -0,0 +1,95 @@
+// src/distributed/protocol.rs
+
+//! What coordinator and workers say to each other: one JSON message per line over TCP.
+//!
+//! A worker opens with [`Message::Hello`] and is sent the [`Message::Job`]; from then on it asks
+//! for units with [`Message::Request`], streams what it finds as [`Message::Relations`] and closes
+//! each unit with [`Message::Completed`]. [`Message::Finished`] answers a request once the
+//! coordinator has enough relations.
+
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::net::TcpStream;
+use serde::{Serialize, Deserialize};
+use crate::core::error::GnfsError;
+use crate::core::gnfs::GNFS;
+use crate::core::serialization::types::{SerializableGNFS, SerializableRelation};
+use crate::distributed::work_unit::{self, WorkUnit};
+
+/// Bumped whenever a message changes shape; both sides must agree.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Message {
+    Hello { worker: String, version: u32 },
+    /// The run's parameters, polynomials and factor bases, without its relations.
+    Job { gnfs: Box<SerializableGNFS> },
+    Request,
+    Assign { unit: WorkUnit },
+    Relations { unit: u64, relations: Vec<SerializableRelation> },
+    Completed { unit: u64, pairs_sieved: u64 },
+    Finished,
+}
+
+impl Message {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Message::Hello { .. } => "hello",
+            Message::Job { .. } => "job",
+            Message::Request => "request",
+            Message::Assign { .. } => "assign",
+            Message::Relations { .. } => "relations",
+            Message::Completed { .. } => "completed",
+            Message::Finished => "finished",
+        }
+    }
+
+    /// The job for `gnfs`, as [`work_unit::job`] describes it.
+    pub fn job(gnfs: &GNFS) -> Self {
+        Message::Job { gnfs: Box::new(work_unit::job(gnfs)) }
+    }
+}
+
+/// One end of a coordinator-worker connection.
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    /// A message read in part when a read timed out.
+    pending: String,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Result<Self, GnfsError> {
+        Ok(Connection { reader: BufReader::new(stream.try_clone()?), writer: BufWriter::new(stream), pending: String::new() })
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), GnfsError> {
+        // Serialized first so a dropped connection surfaces as an I/O error.
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// The next message, or None once the other side has closed the connection. When the stream
+    /// has a read timeout and it passes, the I/O error is returned and whatever part of a message
+    /// had arrived is kept for the next call.
+    pub fn receive(&mut self) -> Result<Option<Message>, GnfsError> {
+        if self.reader.read_line(&mut self.pending)? == 0 {
+            return Ok(None);
+        }
+        let line = std::mem::take(&mut self.pending);
+        Ok(Some(serde_json::from_str(&line)?))
+    }
+}
+
+/// Whether `error` is only a read timeout.
+pub fn timed_out(error: &GnfsError) -> bool {
+    matches!(error, GnfsError::Io(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut))
+}
+
+/// The error for a message that doesn't belong at this point of the conversation.
+pub fn unexpected(message: &Message, expected: &str) -> GnfsError {
+    GnfsError::Network(format!("expected {}, received {}", expected, message.name()))
+}
@@ -0,0 +1,358 @@
+// src/distributed/work_unit.rs
+
+//! Work units: the slices of the sieve a coordinator hands out, how a worker sieves one, and the
+//! ledger of which units are out, done or due to be handed out again. The ledger is saved as
+//! `WorkUnits.json` beside the checkpoint files, so a coordinator restarted on the same save
+//! directory continues where it stopped; units that were out when it stopped are issued again.
+//! Units can also be exported as self-contained [`WorkUnitFile`]s and sieved without a
+//! connection; the ledger only records that they left.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use num::{BigInt, ToPrimitive};
+use serde::{Serialize, Deserialize};
+use crate::core::cancellation_token::CancellationToken;
+use crate::core::error::GnfsError;
+use crate::core::directory_location::DirectoryLocations;
+use crate::core::gnfs::GNFS;
+use crate::core::serialization::save;
+use crate::core::serialization::types::SerializableGNFS;
+use crate::core::sieve_tuning::SieveTuning;
+use crate::core::sieve_range::SieveRange;
+use crate::integer_math::gcd::GCD;
+use crate::relation_sieve::lattice_sieve::{self, LatticeSieve, SpecialQ};
+use crate::relation_sieve::log_sieve::LineSieve;
+use crate::relation_sieve::poly_relations_sieve_progress::PolyRelationsSieveProgress;
+use crate::relation_sieve::relation::Relation;
+use crate::relation_sieve::row_norms::RowNorms;
+use crate::relation_sieve::SieveStrategy;
+
+pub const WORK_UNITS_FILENAME: &str = "WorkUnits.json";
+/// b rows per line-sieve unit when not configured.
+pub const DEFAULT_UNIT_ROWS: u64 = 50;
+/// Special-q values, primes or not, spanned by a lattice-sieve unit when not configured.
+pub const DEFAULT_UNIT_SPECIAL_Q: u64 = 500;
+
+/// The part of the (a, b) plane a unit covers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum WorkRange {
+    /// Rows min_b..=max_b of the line sieve, every a with 1 ≤ |a| ≤ max_abs_a.
+    Rows { min_b: u64, max_b: u64, max_abs_a: u64 },
+    /// The special-q primes in q_min..=q_max. Pairs that a special-q from `range_min` up to q_min
+    /// also holds are left to that special-q's unit.
+    SpecialQ { q_min: u64, q_max: u64, range_min: u64 },
+}
+
+impl Display for WorkRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WorkRange::Rows { min_b, max_b, max_abs_a } => write!(f, "b {}..={}, |a| ≤ {}", min_b, max_b, max_abs_a),
+            WorkRange::SpecialQ { q_min, q_max, .. } => write!(f, "special-q {}..={}", q_min, q_max),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkUnit {
+    pub id: u64,
+    pub range: WorkRange,
+}
+
+impl Display for WorkUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "unit {} ({})", self.id, self.range)
+    }
+}
+
+/// What a worker needs of `gnfs` to sieve: its parameters, polynomials and factor pairs, with the
+/// relations, sieve position and save directory left out.
+pub fn job(gnfs: &GNFS) -> SerializableGNFS {
+    let mut job = gnfs.clone();
+    job.current_relations_progress = PolyRelationsSieveProgress::default();
+    job.save_locations = DirectoryLocations::default();
+    SerializableGNFS::from(job)
+}
+
+/// The run a job describes, with its factor bases rebuilt and the sieve tuned for this machine.
+pub fn job_gnfs(job: SerializableGNFS) -> GNFS {
+    let mut gnfs = GNFS::from(job);
+    gnfs.set_prime_factor_bases();
+    gnfs.current_relations_progress.tuning = SieveTuning::detect(&gnfs.config);
+    gnfs
+}
+
+/// A unit together with the job it belongs to, so that it can be sieved on a machine that has
+/// nothing else of the run.
+#[derive(Serialize, Deserialize)]
+pub struct WorkUnitFile {
+    pub unit: WorkUnit,
+    pub job: Box<SerializableGNFS>,
+}
+
+impl WorkUnitFile {
+    /// The file name unit `id` is exported under.
+    pub fn filename(id: u64) -> String {
+        format!("workunit-{:05}.json", id)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, GnfsError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), GnfsError> {
+        save::atomic(path, serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Sieves `unit` over the polynomial and factor bases of `gnfs`, passing the relations worth
+/// keeping to `emit` one row or special-q at a time: the smooth ones, and the rough ones when large
+/// primes are recycled. Returns the coprime pairs factored.
+pub fn sieve(cancel_token: &CancellationToken, gnfs: &GNFS, unit: &WorkUnit, mut emit: impl FnMut(Vec<Relation>) -> Result<(), GnfsError>) -> Result<u64, GnfsError> {
+    let kept = |relation: &Relation| relation.is_smooth() || (gnfs.config.large_prime_recycling() && relation.is_rough(gnfs));
+    let mut pairs_sieved = 0;
+    match unit.range {
+        WorkRange::Rows { min_b, max_b, max_abs_a } => {
+            let max_abs_a = BigInt::from(max_abs_a);
+            let line_sieve = gnfs.config.log_sieve().then(|| LineSieve::new(gnfs, &gnfs.current_relations_progress.tuning));
+            for b in (min_b..=max_b).map(BigInt::from) {
+                if cancel_token.is_cancellation_requested() {
+                    return Err(GnfsError::Cancelled);
+                }
+                let candidates = line_sieve.as_ref().and_then(|sieve| sieve.row(gnfs, &b, &max_abs_a));
+                let mut row_norms = RowNorms::new(&gnfs.current_polynomial, &gnfs.rational_polynomial, &b);
+                let mut found = Vec::new();
+                for a in SieveRange::get_sieve_range(&max_abs_a) {
+                    let norms = match &candidates {
+                        Some(candidates) => candidates.passes(&a, false).then(|| Relation::norms(&gnfs.current_polynomial, &gnfs.rational_polynomial, &a, &b)),
+                        None => Some(row_norms.norms(&a)),
+                    };
+                    if let Some((algebraic_norm, rational_norm)) = norms.filter(|_| GCD::are_coprime(&[a.clone(), b.clone()])) {
+                        let mut relation = Relation::new(gnfs, &a, &b);
+                        relation.sieve_with_norms(gnfs, algebraic_norm, rational_norm);
+                        pairs_sieved += 1;
+                        if kept(&relation) {
+                            found.push(relation);
+                        }
+                    }
+                }
+                emit(found)?;
+            }
+        }
+        WorkRange::SpecialQ { q_min, q_max, range_min } => {
+            let sieve = LatticeSieve::new(gnfs);
+            for q in lattice_sieve::special_q_primes(q_min, q_max) {
+                if cancel_token.is_cancellation_requested() {
+                    return Err(GnfsError::Cancelled);
+                }
+                let mut found = Vec::new();
+                for special_q in SpecialQ::ideals(&gnfs.current_polynomial, q) {
+                    for (a, b) in sieve.candidates(gnfs, &special_q) {
+                        if !GCD::are_coprime(&[a.clone(), b.clone()]) {
+                            continue;
+                        }
+                        let mut relation = Relation::new(gnfs, &a, &b);
+                        relation.sieve(gnfs);
+                        pairs_sieved += 1;
+                        if kept(&relation) && !PolyRelationsSieveProgress::found_by_earlier_special_q(&relation, range_min, q) {
+                            found.push(relation);
+                        }
+                    }
+                }
+                emit(found)?;
+            }
+        }
+    }
+    Ok(pairs_sieved)
+}
+
+/// A unit handed to a worker and not yet reported done.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub unit: WorkUnit,
+    pub worker: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerTotals {
+    pub units: u64,
+    pub relations: u64,
+    pub pairs_sieved: u64,
+}
+
+/// Which units have been handed out and which are done, and where the next new unit starts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkUnitLedger {
+    pub next_id: u64,
+    /// First row no unit has covered yet.
+    pub next_b: u64,
+    /// First special-q no unit has covered yet; past the range once the lattice units are used up.
+    pub next_special_q: u64,
+    pub rows_per_unit: u64,
+    pub special_q_per_unit: u64,
+    /// Units to hand out again before any new ones, left over from workers that went away.
+    pub reissue: VecDeque<WorkUnit>,
+    pub assigned: BTreeMap<u64, Assignment>,
+    /// Units written out as files; their relations come back through an import, if at all.
+    #[serde(default)]
+    pub exported: BTreeMap<u64, WorkUnit>,
+    pub completed_units: u64,
+    /// What each worker, by the name it gave, has reported.
+    pub workers: BTreeMap<String, WorkerTotals>,
+}
+
+impl WorkUnitLedger {
+    /// A ledger starting where the sieve of `gnfs` stands: at its next special-q when sieving with
+    /// lattices, and at its current row otherwise.
+    pub fn new(gnfs: &GNFS, rows_per_unit: u64, special_q_per_unit: u64) -> Result<Self, GnfsError> {
+        let progress = &gnfs.current_relations_progress;
+        let next_b = progress.b.to_u64().ok_or_else(|| GnfsError::Overflow(format!("row b = {} does not fit a work unit", progress.b)))?;
+        Ok(WorkUnitLedger {
+            next_b,
+            next_special_q: progress.special_q,
+            rows_per_unit: rows_per_unit.max(1),
+            special_q_per_unit: special_q_per_unit.max(1),
+            ..Default::default()
+        })
+    }
+
+    /// The ledger saved in `directory`, or None when there is none.
+    pub fn read(directory: &Path) -> Result<Option<Self>, GnfsError> {
+        let path = directory.join(WORK_UNITS_FILENAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mut ledger: WorkUnitLedger = serde_json::from_str(&fs::read_to_string(path)?)?;
+        // Nobody is working on anything while the coordinator is down.
+        let stranded: Vec<WorkUnit> = std::mem::take(&mut ledger.assigned).into_values().map(|assignment| assignment.unit).collect();
+        ledger.reissue.extend(stranded);
+        Ok(Some(ledger))
+    }
+
+    pub fn write(&self, directory: &Path) -> Result<(), GnfsError> {
+        save::atomic(&directory.join(WORK_UNITS_FILENAME), serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Hands `worker` the next unit: one left over by another worker, else the next special-q
+    /// range while the configured range of `gnfs` lasts, else the next rows.
+    pub fn assign(&mut self, gnfs: &GNFS, worker: &str) -> WorkUnit {
+        let unit = self.next_unit(gnfs);
+        self.assigned.insert(unit.id, Assignment { unit: unit.clone(), worker: worker.to_string() });
+        unit
+    }
+
+    /// Takes the next `count` units, in the order [`assign`](Self::assign) would hand them out,
+    /// to be written out as files.
+    pub fn export(&mut self, gnfs: &GNFS, count: usize) -> Vec<WorkUnit> {
+        let units: Vec<WorkUnit> = (0..count).map(|_| self.next_unit(gnfs)).collect();
+        self.exported.extend(units.iter().map(|unit| (unit.id, unit.clone())));
+        units
+    }
+
+    fn next_unit(&mut self, gnfs: &GNFS) -> WorkUnit {
+        self.reissue.pop_front().unwrap_or_else(|| {
+            let range = self.next_range(gnfs);
+            self.next_id += 1;
+            WorkUnit { id: self.next_id, range }
+        })
+    }
+
+    fn next_range(&mut self, gnfs: &GNFS) -> WorkRange {
+        if gnfs.config.sieve_strategy() == SieveStrategy::Lattice {
+            let algebraic_factor_base_max = gnfs.prime_factor_base.algebraic_factor_base_max.to_u64().unwrap_or(u64::MAX);
+            let (range_min, range_max) = gnfs.config.special_q_range(algebraic_factor_base_max);
+            let q_min = self.next_special_q.max(range_min);
+            if q_min <= range_max {
+                let q_max = range_max.min(q_min.saturating_add(self.special_q_per_unit - 1));
+                self.next_special_q = q_max + 1;
+                return WorkRange::SpecialQ { q_min, q_max, range_min };
+            }
+        }
+        let max_abs_a = gnfs.current_relations_progress.value_range.to_u64().unwrap_or(u64::MAX);
+        let min_b = self.next_b;
+        self.next_b += self.rows_per_unit;
+        WorkRange::Rows { min_b, max_b: self.next_b - 1, max_abs_a }
+    }
+
+    /// Records unit `id` as done by `worker`; false when it wasn't out, e.g. because it was
+    /// reissued after the worker was presumed gone.
+    pub fn complete(&mut self, id: u64, worker: &str, relations: u64, pairs_sieved: u64) -> bool {
+        if self.assigned.get(&id).is_none_or(|assignment| assignment.worker != worker) {
+            return false;
+        }
+        self.assigned.remove(&id);
+        self.completed_units += 1;
+        let totals = self.workers.entry(worker.to_string()).or_default();
+        totals.units += 1;
+        totals.relations += relations;
+        totals.pairs_sieved += pairs_sieved;
+        true
+    }
+
+    /// Queues the units `worker` still holds to be handed out again.
+    pub fn release(&mut self, worker: &str) -> usize {
+        let ids: Vec<u64> = self.assigned.iter().filter(|(_, assignment)| assignment.worker == worker).map(|(&id, _)| id).collect();
+        for id in &ids {
+            if let Some(assignment) = self.assigned.remove(id) {
+                self.reissue.push_back(assignment.unit);
+            }
+        }
+        ids.len()
+    }
+
+    /// Moves the local sieve of `gnfs` past everything the units have covered, so a later local
+    /// run doesn't sieve it again. Units still to be reissued are left to the ledger.
+    pub fn advance_local_sieve(&self, gnfs: &mut GNFS) {
+        let progress = &mut gnfs.current_relations_progress;
+        progress.special_q = progress.special_q.max(self.next_special_q);
+        if BigInt::from(self.next_b) > progress.b {
+            progress.skip_rows_before(BigInt::from(self.next_b));
+        }
+    }
+}
+
+impl Display for WorkUnitLedger {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} work units completed, {} out, {} exported, {} to reissue; next row {}, next special-q {}",
+            self.completed_units,
+            self.assigned.len(),
+            self.exported.len(),
+            self.reissue.len(),
+            self.next_b,
+            self.next_special_q
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_held_by_a_lost_worker_are_reissued_first() {
+        let gnfs = GNFS::default();
+        let mut ledger = WorkUnitLedger { next_b: 1, rows_per_unit: 10, special_q_per_unit: 100, ..Default::default() };
+        let first = ledger.assign(&gnfs, "alpha");
+        let second = ledger.assign(&gnfs, "beta");
+        assert!(matches!(first.range, WorkRange::Rows { min_b: 1, max_b: 10, .. }));
+        assert!(matches!(second.range, WorkRange::Rows { min_b: 11, max_b: 20, .. }));
+
+        assert!(!ledger.complete(first.id, "beta", 3, 100));
+        assert!(ledger.complete(first.id, "alpha", 3, 100));
+        assert_eq!(ledger.release("beta"), 1);
+        assert_eq!(ledger.assign(&gnfs, "gamma"), second);
+        assert!(matches!(ledger.assign(&gnfs, "gamma").range, WorkRange::Rows { min_b: 21, .. }));
+        assert_eq!(ledger.workers["alpha"], WorkerTotals { units: 1, relations: 3, pairs_sieved: 100 });
+
+        let exported = ledger.export(&gnfs, 2);
+        assert!(matches!(exported[0].range, WorkRange::Rows { min_b: 31, max_b: 40, .. }));
+        assert_eq!(ledger.exported.len(), 2);
+        assert!(matches!(ledger.assign(&gnfs, "alpha").range, WorkRange::Rows { min_b: 51, .. }));
+    }
+}
@@ -0,0 +1,127 @@
+// src/distributed/worker.rs
+
+//! The worker: joins a coordinator's run and sieves the units it is handed until told the run is
+//! finished. It keeps nothing on disk; a unit it doesn't complete is handed to someone else. It
+//! can also sieve an exported [`WorkUnitFile`] into a relation file, one relation per line, for
+//! `gnfs import-relations` to merge into the run.
+
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use log::info;
+use crate::core::cancellation_token::CancellationToken;
+use crate::core::error::GnfsError;
+use crate::core::gnfs::GNFS;
+use crate::core::serialization::types::SerializableRelation;
+use crate::distributed::protocol::{self, Connection, Message, PROTOCOL_VERSION};
+use crate::distributed::work_unit::{self, WorkUnitFile};
+
+/// What a worker did before it stopped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkerSummary {
+    pub units: u64,
+    pub relations: u64,
+    pub pairs_sieved: u64,
+}
+
+impl Display for WorkerSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} units sieved, {} relations sent, {} pairs sieved", self.units, self.relations, self.pairs_sieved)
+    }
+}
+
+/// Connects to the coordinator at `address` as `name` and sieves units until the coordinator
+/// finishes or goes away, or `cancel_token` stops the worker part way through a unit.
+pub fn run(cancel_token: &CancellationToken, address: &str, name: &str) -> Result<WorkerSummary, GnfsError> {
+    let stream = TcpStream::connect(address).map_err(|e| GnfsError::Network(format!("cannot reach coordinator at {}: {}", address, e)))?;
+    let mut connection = Connection::new(stream)?;
+    connection.send(&Message::Hello { worker: name.to_string(), version: PROTOCOL_VERSION })?;
+    let gnfs = match connection.receive()? {
+        Some(Message::Job { gnfs }) => work_unit::job_gnfs(*gnfs),
+        Some(other) => return Err(protocol::unexpected(&other, "job")),
+        None => return Err(GnfsError::Network("the coordinator closed the connection before sending the job".to_string())),
+    };
+    info!("Joined the run for N = {} with ƒ(x) = {}", gnfs.n, gnfs.current_polynomial);
+
+    let mut summary = WorkerSummary::default();
+    loop {
+        match exchange(cancel_token, &gnfs, &mut connection, &mut summary) {
+            Ok(true) => continue,
+            Ok(false) => return Ok(summary),
+            Err(GnfsError::Cancelled) => {
+                info!("Worker stopped; the unfinished unit will be handed out again.");
+                return Ok(summary);
+            }
+            // The coordinator hangs up on workers once it has enough relations.
+            Err(GnfsError::Io(e)) => {
+                info!("The coordinator closed the connection ({}).", e);
+                return Ok(summary);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Asks for a unit and sieves it; false once there is nothing more to do.
+fn exchange(cancel_token: &CancellationToken, gnfs: &GNFS, connection: &mut Connection, summary: &mut WorkerSummary) -> Result<bool, GnfsError> {
+    connection.send(&Message::Request)?;
+    let unit = match connection.receive()? {
+        Some(Message::Assign { unit }) => unit,
+        Some(Message::Finished) | None => {
+            info!("The coordinator has finished the run.");
+            return Ok(false);
+        }
+        Some(other) => return Err(protocol::unexpected(&other, "assign")),
+    };
+    info!("Sieving {}", unit);
+    let mut relations = 0;
+    let pairs_sieved = work_unit::sieve(cancel_token, gnfs, &unit, |found| {
+        if found.is_empty() {
+            return Ok(());
+        }
+        relations += found.len() as u64;
+        connection.send(&Message::Relations { unit: unit.id, relations: found.into_iter().map(SerializableRelation::from).collect() })
+    })?;
+    connection.send(&Message::Completed { unit: unit.id, pairs_sieved })?;
+    info!("Completed {}: {} relations from {} pairs.", unit, relations, pairs_sieved);
+    summary.units += 1;
+    summary.relations += relations;
+    summary.pairs_sieved += pairs_sieved;
+    Ok(true)
+}
+
+/// Sieves the unit in the work-unit file at `unit_path` and writes what it finds to `output`. The
+/// relations go to a temporary file beside it that is renamed once the unit is done, so `output`
+/// only ever holds a whole unit.
+pub fn sieve_file(cancel_token: &CancellationToken, unit_path: &Path, output: &Path) -> Result<WorkerSummary, GnfsError> {
+    let WorkUnitFile { unit, job } = WorkUnitFile::read(unit_path)?;
+    let gnfs = work_unit::job_gnfs(*job);
+    info!("Sieving {} of the run for N = {}", unit, gnfs.n);
+
+    let partial = output.with_extension("partial");
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    let mut relations = 0;
+    let sieved = work_unit::sieve(cancel_token, &gnfs, &unit, |found| {
+        relations += found.len() as u64;
+        for relation in found {
+            serde_json::to_writer(&mut writer, &SerializableRelation::from(relation))?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    });
+    let pairs_sieved = match sieved {
+        Ok(pairs_sieved) => pairs_sieved,
+        Err(e) => {
+            drop(writer);
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&partial, output)?;
+    info!("Completed {}: {} relations from {} pairs written to {}", unit, relations, pairs_sieved, output.display());
+    Ok(WorkerSummary { units: 1, relations, pairs_sieved })
+}
@@ -1,6 +1,6 @@
 // src/integer_math/factorization_factory.rs
 
-use num::{BigInt, One, Zero};
+use num::{BigInt, One, Signed, Zero};
 use crate::core::count_dictionary::CountDictionary;
 
 pub struct FactorizationFactory;
@@ -9,12 +9,17 @@ impl FactorizationFactory {
     const PRIME_CHECK_BASES: [i64; 15] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47];
 
     pub fn is_probable_prime(input: &BigInt) -> bool {
-        if input == &BigInt::from(2) || input == &BigInt::from(3) {
-            return true;
-        }
-        if input < &BigInt::from(2) || input % 2 == BigInt::from(0) {
+        if input < &BigInt::from(2) {
             return false;
         }
+        for &base in &Self::PRIME_CHECK_BASES {
+            if input == &BigInt::from(base) {
+                return true;
+            }
+            if input % base == BigInt::zero() {
+                return false;
+            }
+        }
 
         let mut d = input - 1;
         let mut s = 0;
@@ -49,6 +54,9 @@ impl FactorizationFactory {
     pub fn factor(input: &BigInt) -> (CountDictionary, BigInt) {
         let mut factorization = CountDictionary::new();
         let mut quotient = input.clone();
+        if quotient.is_zero() {
+            return (factorization, quotient);
+        }
 
         let two = BigInt::from(2);
         while &quotient % &two == BigInt::zero() {
@@ -73,4 +81,25 @@ impl FactorizationFactory {
         (factorization, quotient)
     }
     
-}
\ No newline at end of file
+
+    /// Divides out every prime of `base` from |input| and returns the exponents found together with
+    /// what is left, which is 1 exactly when the input is smooth over `base`. Zero has no
+    /// factorization and is returned unchanged.
+    pub fn factor_with_base(input: &BigInt, base: &[BigInt]) -> (CountDictionary, BigInt) {
+        let mut factorization = CountDictionary::new();
+        let mut quotient = input.abs();
+        if quotient.is_zero() {
+            return (factorization, quotient);
+        }
+        for prime in base {
+            if quotient.is_one() {
+                break;
+            }
+            while (&quotient % prime).is_zero() {
+                factorization.add(prime);
+                quotient /= prime;
+            }
+        }
+        (factorization, quotient)
+    }
+}
@@ -1,237 +1,202 @@
 // src/integer_math/fast_prime_sieve.rs
 
-use std::mem::size_of;
-use log::debug;
-use num::Zero;
-use num::{BigUint, ToPrimitive};
-use serde::de;
+//! A segmented sieve of Eratosthenes over odd numbers only, one bit per odd number packed into
+//! `u64` words. Bit `b` of the sieve stands for `2b + 1`; a set bit means prime. Segments start
+//! with every bit set and have the odd multiples of each base prime cleared, and primes are read
+//! back a word at a time with `trailing_zeros`, so nothing larger than a word is ever built per
+//! prime.
+
+use num::BigUint;
 use crate::core::cpu_info;
-use std::cell::RefCell;
 
-pub struct FastPrimeSieve {
-    page_size: usize,
-    buffer_bits: usize,
-    buffer_bits_next: usize,
+/// Odd numbers covered by one word.
+pub const ODD_NUMBERS_PER_WORD: u64 = 64;
+/// Integers covered by one word.
+pub const NUMBERS_PER_WORD: u64 = 2 * ODD_NUMBERS_PER_WORD;
+/// L1 data cache size assumed when the CPU doesn't report one.
+const DEFAULT_CACHE_BYTES: usize = 32 * 1024;
+
+/// Clears the bits of every odd composite in `words`, whose first word is word `first_word` of the
+/// whole sieve. `base_primes` must yield the odd primes in increasing order, at least up to the
+/// square root of the segment's last number; primes past that are ignored.
+pub fn sieve_segment(words: &mut [u64], first_word: u64, base_primes: impl IntoIterator<Item = u64>) {
+    let first_bit = first_word * ODD_NUMBERS_PER_WORD;
+    let bit_count = words.len() as u64 * ODD_NUMBERS_PER_WORD;
+    let low = 2 * first_bit + 1;
+    let high = 2 * (first_bit + bit_count);
+    if first_bit == 0 && !words.is_empty() {
+        words[0] &= !1;
+    }
+    for p in base_primes {
+        if p.saturating_mul(p) >= high {
+            break;
+        }
+        let mut cofactor = low.div_ceil(p).max(p);
+        if cofactor.is_multiple_of(2) {
+            cofactor += 1;
+        }
+        let mut bit = (cofactor * p - 1) / 2 - first_bit;
+        while bit < bit_count {
+            words[(bit / ODD_NUMBERS_PER_WORD) as usize] &= !(1u64 << (bit % ODD_NUMBERS_PER_WORD));
+            bit += p;
+        }
+    }
 }
 
-impl FastPrimeSieve {
-    pub fn new() -> Self {
-        let mut cache_size = 393216;
-        let cache_sizes = cpu_info::CPUInfo::l1_cache_size();
-        if cache_sizes.unwrap() != 0 {
-            cache_size = cache_sizes.unwrap() * 1024;
+/// A sieve of every odd number below `limit`, rounded up to a whole word, sieved by its own primes.
+pub fn sieve_to(limit: u64) -> Vec<u64> {
+    let mut words = vec![u64::MAX; limit.div_ceil(NUMBERS_PER_WORD).max(1) as usize];
+    words[0] &= !1;
+    let bit_count = words.len() as u64 * ODD_NUMBERS_PER_WORD;
+    let mut bit = 1;
+    while (2 * bit + 1) * (2 * bit + 1) < 2 * bit_count {
+        if words[(bit / ODD_NUMBERS_PER_WORD) as usize] >> (bit % ODD_NUMBERS_PER_WORD) & 1 == 1 {
+            let p = 2 * bit + 1;
+            let mut multiple = (p * p - 1) / 2;
+            while multiple < bit_count {
+                words[(multiple / ODD_NUMBERS_PER_WORD) as usize] &= !(1u64 << (multiple % ODD_NUMBERS_PER_WORD));
+                multiple += p;
+            }
         }
+        bit += 1;
+    }
+    words
+}
 
-        let page_size = cache_size;
-        let buffer_bits = page_size * 8;
-        let buffer_bits_next = buffer_bits * 2;
+/// The odd primes marked in a run of sieve words, in increasing order.
+pub struct OddPrimeBits<W: AsRef<[u64]>> {
+    words: W,
+    first_word: u64,
+    index: usize,
+    remaining: u64,
+}
+
+impl<W: AsRef<[u64]>> OddPrimeBits<W> {
+    /// Reads `words`, the sieve's words from word `first_word` onwards.
+    pub fn new(words: W, first_word: u64) -> Self {
+        let remaining = words.as_ref().first().copied().unwrap_or(0);
+        OddPrimeBits { words, first_word, index: 0, remaining }
+    }
+}
+
+impl<W: AsRef<[u64]>> Iterator for OddPrimeBits<W> {
+    type Item = u64;
 
-        FastPrimeSieve {
-            page_size,
-            buffer_bits,
-            buffer_bits_next,
+    fn next(&mut self) -> Option<u64> {
+        let words = self.words.as_ref();
+        while self.remaining == 0 {
+            self.index += 1;
+            self.remaining = *words.get(self.index)?;
         }
+        let offset = self.remaining.trailing_zeros() as u64;
+        self.remaining &= self.remaining - 1;
+        Some(2 * ((self.first_word + self.index as u64) * ODD_NUMBERS_PER_WORD + offset) + 1)
+    }
+}
+
+pub struct FastPrimeSieve {
+    segment_words: usize,
+}
+
+impl Default for FastPrimeSieve {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FastPrimeSieve {
+    /// A sieve whose segments fit the L1 data cache.
+    pub fn new() -> Self {
+        let cache_bytes = cpu_info::CPUInfo::l1_cache_size().filter(|&bytes| bytes != 0).unwrap_or(DEFAULT_CACHE_BYTES);
+        FastPrimeSieve { segment_words: (cache_bytes / std::mem::size_of::<u64>()).max(1) }
     }
 
+    /// Primes between `floor` and `ceiling` inclusive, in increasing order.
     pub fn get_range<'a>(floor: &'a BigUint, ceiling: &'a BigUint) -> impl Iterator<Item = BigUint> + 'a {
-        debug!("In fast_prime_sieve get_range with floor: {}, ceiling: {}", floor, ceiling);
-        let primes_paged = FastPrimeSieve::new();
-        debug!("primes_paged: {:?}", primes_paged.page_size);
-        let mut enumerator = primes_paged.iterator();
-        debug!("enumerator created.");
-        
-        debug!("enumerator.next: {:?}", enumerator.next());
-        while let Some(current) = enumerator.next() {
-            if &current >= floor {
-                debug!("current: {:?}", current);
-                break;
-            }
-        }
-        
-        debug!("Creating iterator.");
-        std::iter::from_fn(move || {
-            if let Some(current) = enumerator.next() {
-                if &current > ceiling {
-                    None
-                } else {
-                    Some(current)
-                }
-            } else {
-                None
-            }
-        })
+        FastPrimeSieve::new()
+            .primes()
+            .map(BigUint::from)
+            .skip_while(move |p| p < floor)
+            .take_while(move |p| p <= ceiling)
     }
 
-    fn iterator(&self) -> FastPrimeSieveIterator {
+    /// Every prime in increasing order, without end.
+    pub fn primes(&self) -> FastPrimeSieveIterator {
         FastPrimeSieveIterator {
-            base_primes_array: RefCell::new(vec![]),
-            buffer_bits: self.buffer_bits,
-            buffer_bits_next: self.buffer_bits_next,
-            low: 0,
-            bottom_item: 0,
-            cull_buffer: vec![0u32; self.page_size / size_of::<u32>()],
-            base_primes: None,
+            segment_words: self.segment_words,
+            base_primes: Vec::new(),
+            base_limit: 0,
+            next_word: 0,
+            segment: OddPrimeBits::new(Vec::new(), 0),
+            yielded_two: false,
         }
     }
 }
 
 impl IntoIterator for FastPrimeSieve {
-    type Item = BigUint;
+    type Item = u64;
     type IntoIter = FastPrimeSieveIterator;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.iterator()
+        self.primes()
     }
 }
 
-struct BasePrimes {
-    primes: std::iter::Flatten<std::iter::Once<FastPrimeSieve>>,
+pub struct FastPrimeSieveIterator {
+    segment_words: usize,
+    /// Odd primes below `base_limit`, used to sieve each segment.
+    base_primes: Vec<u64>,
+    base_limit: u64,
+    next_word: u64,
+    segment: OddPrimeBits<Vec<u64>>,
+    yielded_two: bool,
 }
 
-impl Iterator for BasePrimes {
-    type Item = BigUint;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.primes.next()
+impl FastPrimeSieveIterator {
+    /// Sieves the next segment, first growing the base primes past its square root.
+    fn advance(&mut self) {
+        let high = (self.next_word + self.segment_words as u64) * NUMBERS_PER_WORD;
+        if self.base_limit.saturating_mul(self.base_limit) < high {
+            self.base_limit = (high.isqrt() + 1).max(self.base_limit * 2);
+            self.base_primes = OddPrimeBits::new(sieve_to(self.base_limit), 0).take_while(|&p| p < self.base_limit).collect();
+        }
+        let mut words = vec![u64::MAX; self.segment_words];
+        sieve_segment(&mut words, self.next_word, self.base_primes.iter().copied());
+        self.segment = OddPrimeBits::new(words, self.next_word);
+        self.next_word += self.segment_words as u64;
     }
 }
 
-impl BasePrimesTrait for BasePrimes {}
-
-trait BasePrimesTrait: Iterator<Item = BigUint> {}
-
-pub struct FastPrimeSieveIterator {
-    base_primes_array: RefCell<Vec<u32>>,
-    base_primes: Option<Box<dyn BasePrimesTrait>>,
-    buffer_bits: usize,
-    buffer_bits_next: usize,
-    low: u32,
-    bottom_item: usize,
-    cull_buffer: Vec<u32>,
-}
-
-
-// TODO: Remove this and replace with a more efficient implementation
 impl Iterator for FastPrimeSieveIterator {
-    type Item = BigUint;
-
-    fn next(&mut self) -> Option<Self::Item> {
-      //  debug!("In FastPrimeSieveIterator next.");
-        while self.bottom_item < self.buffer_bits {
-           // debug!("In FastPrimeSieveIterator next while loop.");
-            if self.bottom_item < 1 {
-                //debug!("In FastPrimeSieveIterator next while loop if statement.");
-                if self.bottom_item <= 0 {
-                   // debug!("In FastPrimeSieveIterator next while loop if statement bottom_item <= 0.");
-                    self.bottom_item = 0;
-                    return Some(BigUint::from(2u32));
-                }
-
-                debug!("In FastPrimeSieveIterator next while loop if statement bottom_item > 0.");
-                let next = BigUint::from(3u32) + BigUint::from(self.low) + BigUint::from(self.low) + BigUint::from(self.buffer_bits_next);
-                if BigUint::from(self.low) <= BigUint::zero() {
-                    // cull very first page
-                    let mut i = 0;
-                    let mut sqr = 9;
-                    let mut p = 3;
-                    debug!("In FastPrimeSieveIterator next while loop if statement bottom_item > 0 if statement.");
-                    while sqr < next.to_usize().expect("next is too large") {
-                        if (self.cull_buffer[i >> 5] & (1 << (i & 31))) == 0 {
-                            let mut j = (sqr - 3) >> 1;
-                            while j < self.buffer_bits {
-                                self.cull_buffer[j >> 5] |= 1 << j;
-                                j += p;
-                            }
-                        }
-                        i += 1;
-                        p += 2;
-                        sqr = p * p;
-                    }
-                    debug!("In FastPrimeSieveIterator next while loop if statement bottom_item > 0 while loop end.");
-                    // while sqr < next.to_usize().expect("next is too large") {
-                    //     if (self.cull_buffer[i >> 5] & (1 << (i & 31))) == 0 {
-                    //         let mut j = (sqr - 3) >> 1;
-                    //         while j < self.buffer_bits {
-                    //             self.cull_buffer[j >> 5] |= 1 << j;
-                    //             j += p;
-                    //         }
-                    //     }
-                    //     i += 1;
-                    //     p += 2;
-                    //     sqr = p * p;
-                    // }
-                } else {
-                    // Cull for the rest of the pages
-                    self.cull_buffer.fill(0);
-
-                    debug!("In FastPrimeSieveIterator next while loop if statement bottom_item > 0 else statement (base_primes_array).");
-                    if self.base_primes_array.borrow().is_empty() {
-                        // Init second base primes stream
-                        self.base_primes = Some(Box::new(BasePrimes {
-                            primes: std::iter::once(FastPrimeSieve::new()).flatten(),
-                        }));
-                        self.base_primes.as_mut().unwrap().next();
-                        self.base_primes.as_mut().unwrap().next();
-                        let prime = self.base_primes.as_mut().unwrap().next().unwrap().to_u32().expect("base prime is too large");
-                        self.base_primes_array.borrow_mut().push(prime); // Add 3 to base primes array
-                        self.base_primes.as_mut().unwrap().next();
-                    }
-
-                    // Make sure base_primes_array contains enough base primes...
-                    debug!("Make sure base_primes_array contains enough base primes.");
-                    let mut p = BigUint::from(self.base_primes_array.borrow()[self.base_primes_array.borrow().len() - 1]);
-                    let mut square = &p * &p;
-                    debug!("In FastPrimeSieveIterator next while loop if statement bottom_item > 0 else statement (base_primes_array).");
-                    while square < next {
-                        p = self.base_primes.as_mut().unwrap().next().unwrap();
-                        square = &p * &p;
-                        self.base_primes_array.borrow_mut().push(p.to_u32().expect("base prime is too large"));
-                    }
-
-                    let limit = self.base_primes_array.borrow().len() - 1;
-                    debug!("Entering for loop up to limit {}.", limit);
-                    for i in 0..limit {
-                        let p = BigUint::from(self.base_primes_array.borrow()[i]);
-                        let start = (&p * &p - BigUint::from(3u32)).to_usize().expect("start is too large") >> 1;
-
-                        // adjust start index based on page lower limit...
-                        let mut start = if start >= self.low as usize {
-                            start - self.low as usize
-                        } else {
-                            let r = (self.low as usize - start) % p.to_usize().expect("p is too large");
-                            if r != 0 {
-                                p.to_usize().expect("p is too large") - r
-                            } else {
-                                0
-                            }
-                        };
-                        debug!("Entering while loop with start {}.", start);
-                        while start < self.buffer_bits {
-                            self.cull_buffer[start >> 5] |= 1 << start;
-                            start += p.to_usize().expect("p is too large");
-                        }
-                        debug!("Exiting while loop.");
-                    }
-                }
-            }
-            debug!("Exiting if statement.");
-            debug!("Entering while loop with criteria self.bottom_item < self.buffer_bits with values {} < {}.", self.bottom_item, self.buffer_bits);
-            while self.bottom_item < self.buffer_bits && (self.cull_buffer[self.bottom_item >> 5] & (1 << (self.bottom_item & 31))) != 0 {
-                self.bottom_item += 1;
-            }
-            debug!("Exiting while loop.");
-            debug!("Entering if statement with criteria self.bottom_item < self.buffer_bits with values {}, {}.", self.bottom_item, self.buffer_bits);
-            if self.bottom_item < self.buffer_bits {
-                let result = BigUint::from(3u32) + (BigUint::from(self.bottom_item) + BigUint::from(self.low)) * BigUint::from(2u32);
-                self.bottom_item += 1;
-                return Some(result);
-            } else {
-                debug!("Entering else statement with self.low at {}.", self.low);
-                self.low += 1;
-                self.bottom_item = 0;
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if !self.yielded_two {
+            self.yielded_two = true;
+            return Some(2);
+        }
+        loop {
+            if let Some(prime) = self.segment.next() {
+                return Some(prime);
             }
+            self.advance();
         }
+    }
+}
 
-        None
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_agree_with_a_single_sieve() {
+        let sieve = FastPrimeSieve { segment_words: 3 };
+        let segmented: Vec<u64> = sieve.primes().take_while(|&p| p < 100_000).collect();
+        let mut whole = vec![2];
+        whole.extend(OddPrimeBits::new(sieve_to(100_000), 0).take_while(|&p| p < 100_000));
+        assert_eq!(segmented.len(), 9_592);
+        assert_eq!(segmented, whole);
+        let range: Vec<BigUint> = FastPrimeSieve::get_range(&BigUint::from(7u32), &BigUint::from(13u32)).collect();
+        assert_eq!(range, vec![BigUint::from(7u32), BigUint::from(11u32), BigUint::from(13u32)]);
     }
 }
@@ -1,6 +1,6 @@
 // src/factors/normal.rs
 
-use num::{BigInt, BigRational, Zero};
+use num::{BigInt, One, ToPrimitive};
 use crate::polynomial::polynomial::Polynomial;
 
 pub struct Normal;
@@ -18,30 +18,60 @@ impl Normal {
 
     /// ƒ(b) ≡ 0 (mod a)
     ///
-    /// Calculated as:
-    /// ƒ(-a/b) * -b^deg
+    /// Calculated as the homogenized form (-b)^deg · ƒ(-a/b) = Σ fᵢ·aⁱ·(-b)^(deg-i), by Horner's
+    /// scheme in `i128` with checked arithmetic, falling back to `BigInt` when a value doesn't fit
+    /// or an intermediate overflows.
     pub fn algebraic(a: &BigInt, b: &BigInt, poly: &Polynomial) -> BigInt {
-        let a_d = BigRational::from(a.clone());
-        let b_d = BigRational::from(b.clone());
-        let ab = -a_d / b_d;
-        let left = Self::polynomial_evaluate_big_rational(poly, &ab);
-        let right = BigRational::from((-b).pow(poly.degree() as u32));
-        let product = right * left;
-        let fractional_part = product.fract();
-        if fractional_part != BigRational::zero() {
-            // GNFS::log_function(&format!("{} failed to result in an integer. This shouldn't happen.", "Algebraic"));
+        Self::algebraic_i128(a, b, poly).map(BigInt::from).unwrap_or_else(|| Self::algebraic_bigint(a, b, poly))
+    }
+
+    fn algebraic_i128(a: &BigInt, b: &BigInt, poly: &Polynomial) -> Option<i128> {
+        let a = a.to_i128()?;
+        let y = b.to_i128()?.checked_neg()?;
+        let degree = poly.degree();
+        let mut result = poly[degree].to_i128()?;
+        let mut y_power: i128 = 1;
+        for i in (0..degree).rev() {
+            y_power = y_power.checked_mul(y)?;
+            let term = poly[i].to_i128()?.checked_mul(y_power)?;
+            result = result.checked_mul(a)?.checked_add(term)?;
         }
-        product.to_integer()
+        Some(result)
     }
 
-    fn polynomial_evaluate_big_rational(polynomial: &Polynomial, indeterminate_value: &BigRational) -> BigRational {
-        let mut num = polynomial.degree();
-        let mut result = BigRational::from(polynomial[num].clone());
-        while num > 0 {
-            num -= 1;
-            result *= indeterminate_value;
-            result += BigRational::from(polynomial[num].clone());
+    fn algebraic_bigint(a: &BigInt, b: &BigInt, poly: &Polynomial) -> BigInt {
+        let y = -b;
+        let degree = poly.degree();
+        let mut result = poly[degree].clone();
+        let mut y_power = BigInt::one();
+        for i in (0..degree).rev() {
+            y_power *= &y;
+            result = result * a + &poly[i] * &y_power;
         }
         result
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::BigRational;
+
+    #[test]
+    fn horner_matches_rational_evaluation() {
+        let f = Polynomial::parse("X^3 + 15X^2 + 29X + 8");
+        let rational = |a: i64, b: i64| {
+            let x = BigRational::new(BigInt::from(-a), BigInt::from(b));
+            let value = (0..=f.degree()).rev().fold(BigRational::from(BigInt::from(0)), |acc, i| acc * &x + BigRational::from(f[i].clone()));
+            (value * BigRational::from(BigInt::from(-b).pow(f.degree() as u32))).to_integer()
+        };
+        for (a, b) in [(1, 1), (-53, 1), (17, 4), (-999, 299), (0, 7)] {
+            assert_eq!(Normal::algebraic(&BigInt::from(a), &BigInt::from(b), &f), rational(a, b), "a = {}, b = {}", a, b);
+        }
+        // Large enough that the i128 path overflows and the BigInt path takes over.
+        let a = BigInt::from(i64::MAX) * 1000;
+        let b = BigInt::from(3);
+        assert_eq!(Normal::algebraic(&a, &b, &f), Normal::algebraic_bigint(&a, &b, &f));
+        assert!(Normal::algebraic_i128(&a, &b, &f).is_none());
+    }
+}
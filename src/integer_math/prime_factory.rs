@@ -1,67 +1,158 @@
 // src/integer_math/prime_factory.rs
 
 use log::debug;
-use num::{BigInt, BigUint, FromPrimitive, Integer, Signed};
+use num::{BigInt, BigUint, FromPrimitive, Integer, Signed, ToPrimitive};
 use num::bigint::{ToBigInt, ToBigUint};
-use std::cmp::{max, min};
-use std::ops::Range;
-use crate::integer_math::fast_prime_sieve::FastPrimeSieve;
+use std::sync::{OnceLock, RwLock};
 use crate::integer_math::factorization_factory::FactorizationFactory;
+use crate::integer_math::fast_prime_sieve::{self, OddPrimeBits, NUMBERS_PER_WORD, ODD_NUMBERS_PER_WORD};
 
-pub struct PrimeFactory {
-    max_value: BigInt,
-    primes_count: usize,
-    primes_last: BigInt,
-    primes: Vec<BigInt>,
+/// Integers sieved when the table is first used.
+const INITIAL_LIMIT: u64 = 1 << 16;
+/// Integers covered by one sieve segment when the table grows.
+const SEGMENT_LENGTH: u64 = 1 << 18;
+/// Sieve words copied out of the table per lock acquisition while iterating.
+const CHUNK_WORDS: u64 = 64;
+/// Words per entry of the rank index.
+const RANK_BLOCK: usize = 16;
+
+/// A bit-packed sieve of every odd number below `sieved_to`, one bit each (see
+/// [`fast_prime_sieve`]). Shared by all [`PrimeFactory`] handles in the process and only ever
+/// extended, so a prime is sieved once however many handles ask for it. `ranks[i]` counts the
+/// odd primes in the words before block `i`, so prime counts don't rescan the whole table.
+struct PrimeTable {
+    words: Vec<u64>,
+    ranks: Vec<u64>,
+}
+
+static PRIME_TABLE: OnceLock<RwLock<PrimeTable>> = OnceLock::new();
+
+impl PrimeTable {
+    fn shared() -> &'static RwLock<PrimeTable> {
+        PRIME_TABLE.get_or_init(|| {
+            let mut table = PrimeTable { words: fast_prime_sieve::sieve_to(INITIAL_LIMIT), ranks: Vec::new() };
+            table.update_ranks();
+            RwLock::new(table)
+        })
+    }
+
+    fn sieved_to(&self) -> u64 {
+        self.words.len() as u64 * NUMBERS_PER_WORD
+    }
+
+    /// Adds rank entries for blocks whose preceding words are now all present.
+    fn update_ranks(&mut self) {
+        while self.ranks.len() * RANK_BLOCK < self.words.len() {
+            let block = self.ranks.len();
+            let rank = match block {
+                0 => 0,
+                _ => self.ranks[block - 1] + count_ones(&self.words[(block - 1) * RANK_BLOCK..block * RANK_BLOCK]),
+            };
+            self.ranks.push(rank);
+        }
+    }
+
+    /// Sieves further segments until every prime below `limit` is in the table. The table's own
+    /// primes serve as the sieving primes, which suffices because each segment ends below the
+    /// square of the current bound.
+    fn extend_to(&mut self, limit: u64) {
+        while self.sieved_to() < limit {
+            let low = self.sieved_to();
+            let high = low.saturating_add(SEGMENT_LENGTH).min(low.saturating_mul(low));
+            let mut segment = vec![u64::MAX; ((high - low) / NUMBERS_PER_WORD) as usize];
+            fast_prime_sieve::sieve_segment(&mut segment, self.words.len() as u64, OddPrimeBits::new(&self.words[..], 0));
+            self.words.extend(segment);
+        }
+        self.update_ranks();
+    }
+
+    fn is_prime(&self, value: u64) -> bool {
+        if value < 3 || value.is_multiple_of(2) {
+            return value == 2;
+        }
+        let bit = value / 2;
+        self.words[(bit / ODD_NUMBERS_PER_WORD) as usize] >> (bit % ODD_NUMBERS_PER_WORD) & 1 == 1
+    }
+
+    /// π(value − 1), the number of primes below `value`.
+    fn count_below(&self, value: u64) -> u64 {
+        let bit = value / 2;
+        let word = (bit / ODD_NUMBERS_PER_WORD) as usize;
+        let block = word / RANK_BLOCK;
+        let partial = self.words[word] & ((1u64 << (bit % ODD_NUMBERS_PER_WORD)) - 1);
+        let two = u64::from(value > 2);
+        two + self.ranks[block] + count_ones(&self.words[block * RANK_BLOCK..word]) + partial.count_ones() as u64
+    }
+}
+
+fn count_ones(words: &[u64]) -> u64 {
+    words.iter().map(|word| word.count_ones() as u64).sum()
 }
 
+/// A cheap handle over the process-wide prime table. Creating one costs nothing; the table grows
+/// on demand behind a lock, so handles can be used from any thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrimeFactory;
+
 impl PrimeFactory {
     pub fn new() -> Self {
-        let mut factory = PrimeFactory {
-            max_value: BigInt::from(10),
-            primes_count: 0,
-            primes_last: BigInt::from(0),
-            primes: vec![],
-        };
-        factory.set_common_primes();
-        factory
+        PrimeFactory
     }
 
-    fn set_common_primes(&mut self) {
-        self.primes = vec![BigInt::from(2), BigInt::from(3), BigInt::from(5), BigInt::from(7), BigInt::from(11), BigInt::from(13), BigInt::from(17), BigInt::from(19), BigInt::from(23), BigInt::from(29), BigInt::from(31), BigInt::from(37), BigInt::from(41), BigInt::from(43), BigInt::from(47), BigInt::from(53), BigInt::from(59), BigInt::from(61), BigInt::from(67), BigInt::from(71), BigInt::from(73), BigInt::from(79), BigInt::from(83), BigInt::from(89), BigInt::from(97), BigInt::from(101), BigInt::from(103), BigInt::from(107), BigInt::from(109), BigInt::from(113), BigInt::from(127), BigInt::from(131), BigInt::from(137), BigInt::from(139), BigInt::from(149), BigInt::from(151), BigInt::from(157), BigInt::from(163), BigInt::from(167), BigInt::from(173), BigInt::from(179), BigInt::from(181), BigInt::from(191), BigInt::from(193), BigInt::from(197), BigInt::from(199), BigInt::from(211), BigInt::from(223), BigInt::from(227), BigInt::from(229), BigInt::from(233), BigInt::from(239), BigInt::from(241), BigInt::from(251), BigInt::from(257), BigInt::from(263), BigInt::from(269), BigInt::from(271), BigInt::from(277), BigInt::from(281), BigInt::from(283), BigInt::from(293), BigInt::from(307), BigInt::from(311), BigInt::from(313), BigInt::from(317), BigInt::from(331), BigInt::from(337), BigInt::from(347), BigInt::from(349), BigInt::from(353), BigInt::from(359), BigInt::from(367), BigInt::from(373), BigInt::from(379), BigInt::from(383), BigInt::from(389), BigInt::from(397), BigInt::from(401), BigInt::from(409), BigInt::from(419), BigInt::from(421), BigInt::from(431), BigInt::from(433), BigInt::from(439), BigInt::from(443), BigInt::from(449)];
-        self.primes_count = self.primes.len();
-        self.primes_last = self.primes.last().unwrap().clone();
+    /// Makes sure the table holds every prime up to `value`.
+    fn ensure(value: u64) -> &'static RwLock<PrimeTable> {
+        let table = PrimeTable::shared();
+        if table.read().unwrap().sieved_to() <= value {
+            let mut writable = table.write().unwrap();
+            let limit = value.saturating_add(1).max(writable.sieved_to().saturating_mul(2));
+            writable.extend_to(limit);
+            debug!("Prime table extended to {} ({} words).", writable.sieved_to(), writable.words.len());
+        }
+        table
     }
 
-    fn set_primes(&mut self) {
-        self.primes = FastPrimeSieve::get_range(&BigUint::from(1u32), &BigUint::from(8192u32))
-            .map(|bi| bi.to_bigint().unwrap())
-            .collect();
-        self.primes_count = self.primes.len();
-        self.primes_last = self.primes.last().unwrap().clone();
+    /// Primes in `[min_value, max_value)` in increasing order, or without end when there is no
+    /// maximum. Words are copied out a chunk at a time, so the lock isn't held between calls to
+    /// `next` and the table can grow while the iterator is alive.
+    fn primes_between(min_value: u64, max_value: Option<u64>) -> impl Iterator<Item = u64> {
+        let end_word = max_value.map(|max| (max / 2).div_ceil(ODD_NUMBERS_PER_WORD));
+        let mut next_word = min_value / 2 / ODD_NUMBERS_PER_WORD;
+        let chunks = std::iter::from_fn(move || {
+            let last_word = end_word.map_or(next_word + CHUNK_WORDS, |end| end.min(next_word + CHUNK_WORDS));
+            if last_word <= next_word {
+                return None;
+            }
+            let table = Self::ensure(last_word * NUMBERS_PER_WORD - 1).read().unwrap();
+            let chunk = table.words[next_word as usize..last_word as usize].to_vec();
+            let first_word = next_word;
+            next_word = last_word;
+            Some(OddPrimeBits::new(chunk, first_word))
+        });
+        let two = std::iter::once(2).filter(move |_| min_value <= 2);
+        two.chain(chunks.flatten())
+            .skip_while(move |&p| p < min_value)
+            .take_while(move |&p| max_value.is_none_or(|max| p < max))
     }
 
-    pub fn get_prime_enumerator(&self, start_index: usize, stop_index: Option<usize>) -> Range<usize> {
-        debug!("In prime_factory get_prime_enumerator with start_index: {}, stop_index: {:?}", start_index, stop_index);
-        let max_index = stop_index.unwrap_or(self.primes_count - 1);
-        start_index..max_index
+    fn to_u64(value: &BigInt) -> u64 {
+        if value.is_negative() {
+            return 0;
+        }
+        value.to_u64().unwrap_or_else(|| panic!("{} is beyond the prime table", value))
     }
 
     pub fn increase_max_value(&mut self, new_max_value: &BigInt) {
-        let temp = max(new_max_value + 1000, &self.max_value + 100000);
-        self.max_value = min(temp, BigInt::from(i32::max_value() - 1));
-        self.set_primes();
+        Self::ensure(Self::to_u64(new_max_value));
     }
 
+    /// One more than the number of primes below `value`, i.e. the 1-based index of the first
+    /// prime ≥ `value`; -1 for -1, which stands for the sign in factorizations.
     pub fn get_index_from_value(&mut self, value: &BigInt) -> i32 {
         if value == &BigInt::from(-1) {
             return -1;
         }
-        if &self.primes_last < value {
-            self.increase_max_value(value);
-        }
-        let prime_index = self.primes.iter().position(|p| p >= value).unwrap();
-        prime_index as i32 + 1
+        let value = Self::to_u64(value);
+        Self::ensure(value).read().unwrap().count_below(value) as i32 + 1
     }
 
     pub fn get_approximate_value_from_index(n: u64) -> BigUint {
@@ -86,28 +177,34 @@ impl PrimeFactory {
         BigUint::from_f64(upper.ceil()).unwrap()
     }
 
-    pub fn get_primes_from<'a>(&'a mut self, min_value: &'a BigInt) -> impl Iterator<Item = BigInt> + 'a {
-        let start_index = self.get_index_from_value(min_value) as usize;
-        self.get_prime_enumerator(start_index, None)
-            .map(move |i| self.primes[i].clone())
+    /// Upper bound on π(x), the number of primes ≤ x: π(x) < x/ln x · (1 + 1.2762/ln x) for x > 1 (Dusart).
+    pub fn get_approximate_index_from_value(value: &BigInt) -> u64 {
+        let x = value.to_f64().unwrap_or(f64::MAX);
+        if x < 2.0 {
+            return 0;
+        }
+        let flogx = x.ln();
+        let upper = x / flogx * (1.0 + 1.2762 / flogx);
+        if upper >= u64::MAX as f64 {
+            panic!("{} > {}", upper, u64::MAX);
+        }
+        upper.ceil() as u64
     }
 
-    pub fn get_primes_to<'a>(&'a mut self, max_value: &'a BigInt) -> impl Iterator<Item = BigInt> + 'a {
-        debug!("In prime_factory get_primes_to with max_value: {}", max_value);
-        if &self.primes_last < max_value {
-            self.increase_max_value(max_value);
-        }
-        debug!("In prime_factory get_primes_to after increase_max_value");
-        let primes = &self.primes;
-        debug!("In prime_factory get_primes_to after primes, starting enumerator");
-        self.get_prime_enumerator(0, None)
-            .take_while(move |&i| &primes[i] < max_value)
-            .map(move |i| primes[i].clone())
+    /// Primes ≥ `min_value` in increasing order, without end; the table grows as the iterator
+    /// is consumed.
+    pub fn get_primes_from(&self, min_value: &BigInt) -> impl Iterator<Item = BigInt> {
+        Self::primes_between(Self::to_u64(min_value), None).map(BigInt::from)
+    }
+
+    /// Primes below `max_value` in increasing order.
+    pub fn get_primes_to(&self, max_value: &BigInt) -> impl Iterator<Item = BigInt> {
+        Self::primes_between(0, Some(Self::to_u64(max_value))).map(BigInt::from)
     }
 
     pub fn is_prime(&self, value: &BigInt) -> bool {
-        let abs_value = value.abs();
-        self.primes.contains(&abs_value)
+        let value = Self::to_u64(&value.abs());
+        Self::ensure(value).read().unwrap().is_prime(value)
     }
 
     pub fn get_next_prime(from_value: &BigInt) -> BigInt {
@@ -131,4 +228,18 @@ impl PrimeFactory {
         }
         result.to_bigint().unwrap()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_table_grows_across_segments() {
+        let factory = PrimeFactory::new();
+        assert_eq!(factory.get_primes_to(&BigInt::from(1_000_000)).count(), 78_498);
+        let above: Vec<BigInt> = factory.get_primes_from(&BigInt::from(999_983)).take(2).collect();
+        assert_eq!(above, vec![BigInt::from(999_983), BigInt::from(1_000_003)]);
+        assert!(factory.is_prime(&BigInt::from(-7919)));
+        assert_eq!(PrimeFactory::new().get_index_from_value(&BigInt::from(7)), 4);
+    }
+}
@@ -1,8 +1,7 @@
 // src/integer_math/quadratic_residue.rs
 
-use num::{BigInt, One, Signed};
+use num::{BigInt, Integer, One, Zero};
 use crate::factor::factor_pair::FactorPair;
-use crate::integer_math::legendre::Legendre;
 use crate::relation_sieve::relation::Relation;
 
 pub struct QuadraticResidue;
@@ -15,10 +14,11 @@ impl QuadraticResidue {
         mod_pow == BigInt::one()
     }
 
+    /// Whether a + b·s is a non-residue mod q for the quadratic character (q, s), the value
+    /// a + bθ takes under the map θ ↦ s onto 𝔽_q.
     pub fn get_quadratic_character(rel: &Relation, quadratic_factor: &FactorPair) -> bool {
-        let ab = &rel.a + &rel.b;
-        let abp = (ab * &BigInt::from(quadratic_factor.p)).abs();
-        let legendre_symbol = Legendre::symbol(&abp, &BigInt::from(quadratic_factor.r));
-        legendre_symbol != 1
+        let q = quadratic_factor.p_bigint();
+        let value = (&rel.a + &rel.b * quadratic_factor.r_bigint()).mod_floor(&q);
+        !value.is_zero() && !Self::is_quadratic_residue(&value, &q)
     }
 }
\ No newline at end of file
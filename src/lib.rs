@@ -2,10 +2,19 @@
 
 #![recursion_limit = "512"]
 
+pub mod algorithms;
+pub mod backends;
+#[cfg(feature = "cli")]
+pub mod cli;
 pub mod core;
+pub mod distributed;
 pub mod factor;
 pub mod polynomial;
 pub mod integer_math;
 pub mod matrix;
 pub mod relation_sieve;
-pub mod square_root;
\ No newline at end of file
+pub mod square_root;
+#[cfg(feature = "tui")]
+pub mod tui;
+
+pub use crate::core::factorizer::{FactorizationResult, Factorizer};
@@ -1,125 +1,481 @@
-use gnfs::integer_math::prime_factory;
 // src/main.rs
-use log::{debug, info};
+use log::{error, info, log_enabled, warn, Level};
 use env_logger::Env;
-use gnfs::core::cpu_info::CPUInfo;
+use gnfs::{FactorizationResult, Factorizer};
+use gnfs::cli::{self, BenchArgs, Cli, Command, ConvertRelationsArgs, ExportMsieveArgs, ExportWorkunitsArgs, FactorArgs, ImportRelationsArgs, Method, ServeArgs, WorkerArgs};
 use gnfs::core::gnfs::GNFS;
+use gnfs::core::report::FactorizationReport;
+use gnfs::core::benchmark::{BenchmarkReport, REGRESSION_THRESHOLD_PERCENT};
 use gnfs::core::cancellation_token::CancellationToken;
-use num::BigInt;
-use std::path::Path;
+use gnfs::core::gnfs_config::GnfsConfig;
+use gnfs::core::error::GnfsError;
+use gnfs::core::stage::{Stage, StageOutcome};
+use gnfs::core::directory_location::DirectoryLocations;
+use gnfs::core::serialization::checkpoint::{CheckpointManifest, ResumePlan, MANIFEST_FILENAME};
+use gnfs::core::interop::msieve::{self, MsieveFactorBase};
+use gnfs::core::serialization::load;
+use gnfs::distributed::{coordinator, import, worker};
+use gnfs::distributed::work_unit::{self, WorkUnitFile, WorkUnitLedger};
+use gnfs::relation_sieve::verifier;
+use gnfs::relation_sieve::storage::{self, RelationFormat};
+#[cfg(feature = "factordb")]
+use gnfs::core::factordb::{FactorDbClient, FactorDbStatus};
+use std::fmt::Display;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Exit status of a run that stopped with work left, e.g. at its `--max-hours` limit; the save
+/// directory can be picked up with `gnfs resume`.
+const EXIT_NEEDS_MORE_WORK: i32 = 3;
+/// Exit status of `bench` when a case's median regressed against `--baseline`.
+const EXIT_BENCHMARK_REGRESSION: i32 = 4;
+/// Log filter in env_logger syntax, e.g. `GNFS_LOG=debug` or `GNFS_LOG=warn,gnfs::matrix=trace`.
+const LOG_ENV: &str = "GNFS_LOG";
+/// `always`, `auto` or `never`.
+const LOG_STYLE_ENV: &str = "GNFS_LOG_STYLE";
+/// Former names of the two variables above, honoured when the new ones are unset.
+const LEGACY_LOG_ENV: &str = "MY_LOG_LEVEL";
+const LEGACY_LOG_STYLE_ENV: &str = "MY_LOG_STYLE";
 
 fn main() {
-    // Initialize the logger
-    let env = Env::default()
-        .filter_or("MY_LOG_LEVEL", "info")
-        .write_style_or("MY_LOG_STYLE", "always");
-    env_logger::Builder::from_env(env).init();
-
-    // Fetching cache information
-    let l1_cache_line_size = CPUInfo::l1_cache_line_size().unwrap_or(0);
-    let l1_cache_size = CPUInfo::l1_cache_size().unwrap_or(0);
-    let l2_cache_line_size = CPUInfo::l2_cache_line_size().unwrap_or(0);
-    let l2_cache_size = CPUInfo::l2_cache_size().unwrap_or(0);
-    let l3_cache_line_size = CPUInfo::l3_cache_line_size().unwrap_or(0);
-    let l3_cache_size = CPUInfo::l3_cache_size().unwrap_or(0);
-
-    // Logging cache sizes and line sizes
-    info!("L1 cache size: {} bytes", l1_cache_size);
-    info!("L1 cache line size: {} bytes", l1_cache_line_size);
-    info!("L2 cache size: {} bytes", l2_cache_size);
-    info!("L2 cache line size: {} bytes", l2_cache_line_size);
-    info!("L3 cache size: {} bytes", l3_cache_size);
-    info!("L3 cache line size: {} bytes", l3_cache_line_size);
-
-    let prime_factory = prime_factory::PrimeFactory::new();
-    let is_prime = prime_factory.is_prime(&BigInt::from(5));
-    info!("Is 5 prime? {}", is_prime);
-    // Create or load GNFS instance
-    let n = BigInt::from(45113); // RNumber to test.
-    let mut gnfs = create_or_load_gnfs(&n);
-
-    // Start the factorization process
+    let process_started = Instant::now();
+    let cli = Cli::try_parse_args(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    // The dashboard owns the terminal, so log lines would only garble it; it shows events instead.
+    let dashboard = matches!(&cli.command, Command::Factor(args) if args.tui);
+    if !dashboard || cfg!(not(feature = "tui")) {
+        init_logging(cli.quiet);
+    }
+
     let cancel_token = CancellationToken::new();
-    gnfs = find_relations(&cancel_token, gnfs, false);
+    handle_interrupts(&cancel_token);
 
+    let (result, failure) = match &cli.command {
+        Command::Factor(args) => (factor(&cancel_token, args, process_started), "Factorization failed"),
+        Command::Bench(args) => (
+            benchmark(&cancel_token, args).map(|passed| if passed { 0 } else { EXIT_BENCHMARK_REGRESSION }),
+            "Benchmark failed",
+        ),
+        Command::Resume(args) => (
+            args.config.to_config().and_then(|config| resume(&cancel_token, &args.directory, args.force, config, process_started)).map(exit_status),
+            "Cannot resume",
+        ),
+        Command::Serve(args) => (
+            args.config.to_config().and_then(|config| serve(&cancel_token, args, config, process_started)).map(exit_status),
+            "Coordinator failed",
+        ),
+        Command::Worker(args) => (work(&cancel_token, args).map(|_| 0), "Worker failed"),
+        Command::ExportWorkunits(args) => (
+            args.config.to_config().and_then(|config| export_workunits(args, config)).map(|_| 0),
+            "Cannot export work units",
+        ),
+        Command::ImportRelations(args) => (
+            args.config.to_config().and_then(|config| import_relations(args, config)).map(|_| 0),
+            "Cannot import relations",
+        ),
+        Command::ExportMsieve(args) => (export_msieve(args).map(|_| 0), "Cannot export for msieve"),
+        Command::ConvertRelations(args) => (convert_relations(args).map(|_| 0), "Cannot convert the relations"),
+        Command::Status(args) => (status(&args.directory).map(|_| 0), "Cannot read the checkpoint"),
+        Command::VerifyRelations(args) => (verify_relations(&args.directory).map(|_| 0), "Cannot verify the relations"),
+        Command::Clean(args) => (clean(&args.directories).map(|_| 0), "Cannot clean"),
+    };
+    match result {
+        Ok(0) => {}
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            error!("{}: {}", failure, e);
+            std::process::exit(1);
+        }
+    }
 }
 
-fn create_or_load_gnfs(n: &BigInt) -> GNFS {
-    let save_directory = format!("gnfs_data_{}", n);
-    let save_path = Path::new(&save_directory);
+fn exit_status(outcome: StageOutcome) -> i32 {
+    if outcome == StageOutcome::NeedsMoreWork {
+        EXIT_NEEDS_MORE_WORK
+    } else {
+        0
+    }
+}
 
-    if save_path.exists() {
-        // Load existing GNFS instance
-        info!("Loading existing GNFS instance...");
-        // TODO: Implement loading GNFS instance from file
-        // For now, create a new instance
-        create_new_gnfs(n)
+/// Runs `gnfs factor` and returns the exit status.
+fn factor(cancel_token: &CancellationToken, args: &FactorArgs, process_started: Instant) -> Result<i32, GnfsError> {
+    let mut factorizer = args.factorizer()?;
+    configure_threads(factorizer.config());
+    arm_deadline(cancel_token, factorizer.config(), process_started);
+    let result = match args.algorithm {
+        Method::Direct(_) => factorizer.run(cancel_token)?,
+        Method::Gnfs => factor_with_gnfs(cancel_token, &mut factorizer, args.tui)?,
+    };
+    if args.json {
+        println!("{}", FactorizationReport::new(&result).to_json()?);
     } else {
-        // Create a new GNFS instance
-        create_new_gnfs(n)
+        print_answer(cli::answer(&result));
+    }
+    if let Some(path) = &args.output_json {
+        cli::write_json(path, &result)?;
+        info!("Result written to {}", path.display());
     }
+    Ok(exit_status(result.outcome))
 }
 
-fn create_new_gnfs(n: &BigInt) -> GNFS {
-    info!("Creating a new GNFS instance...");
-    let cancel_token = CancellationToken::new();
-    let polynomial_base = BigInt::from(31);
-    let poly_degree = 3;
-    let prime_bound = BigInt::from(100); // Adjust the prime bound as needed
-    let relation_quantity = 1; // Adjust the relation quantity as needed
-    let relation_value_range = 1000; // Adjust the relation value range as needed
-    let created_new_data = true;
-
-    info!("n: {}", n);
-    info!("Polynomial Base: {}", polynomial_base.clone());
-    info!("Polynomial Degree: {}", poly_degree);
-    info!("Prime Bound: {}", prime_bound.clone());
-    info!("Relation Target: {}", relation_quantity);
-    info!("Relation Value: {}", relation_value_range);
-    info!("GNFS: {}", created_new_data);
-
-    GNFS::new(
-        &cancel_token,
-        n,
-        &polynomial_base,
-        poly_degree,
-        &prime_bound,
-        relation_quantity,
-        relation_value_range,
-        created_new_data,
-    )
-}
-
-fn find_relations(cancel_token: &CancellationToken, mut gnfs: GNFS, one_round: bool) -> GNFS {
-    info!("Sieving for relations...");
-    while !cancel_token.is_cancellation_requested() {
-        if gnfs.current_relations_progress.smooth_relations_counter >= gnfs.current_relations_progress.smooth_relations_target_quantity {
-            gnfs.current_relations_progress.increase_target_quantity(1);
+/// Trial division, then GNFS on the cofactor unless factordb already knows its factors.
+fn factor_with_gnfs(cancel_token: &CancellationToken, factorizer: &mut Factorizer, dashboard: bool) -> Result<FactorizationResult, GnfsError> {
+    let normalized = factorizer.remove_small_factors()?;
+    let mut result = FactorizationResult::for_input(&normalized);
+    if normalized.is_fully_factored() {
+        return Ok(result);
+    }
+    let mut gnfs = factorizer.create_gnfs(cancel_token, &normalized.cofactor)?;
+
+    if gnfs.config.factordb_lookup && lookup_known_factors(&mut gnfs) {
+        info!("Factors already known to factordb; skipping sieving.");
+    } else {
+        result.outcome = if dashboard {
+            run_with_dashboard(cancel_token, factorizer, &mut gnfs)
+        } else {
+            factorizer.run_stages(cancel_token, &mut gnfs, Stage::Sieve)
+        };
+        if gnfs.config.factordb_submit && gnfs.factorization.is_some() {
+            submit_factors(&gnfs);
         }
+    }
+    result.record_run(&gnfs);
+    Ok(result)
+}
+
+/// Logs at info by default and at warn with `--quiet`; GNFS_LOG, when set, takes precedence over both.
+fn init_logging(quiet: bool) {
+    let legacy = |name: &str, legacy: &str| std::env::var(legacy).ok().filter(|_| std::env::var_os(name).is_none());
+    let legacy_filter = legacy(LOG_ENV, LEGACY_LOG_ENV);
+    let default_filter = legacy_filter.clone().unwrap_or_else(|| if quiet { "warn" } else { "info" }.to_string());
+    let default_style = legacy(LOG_STYLE_ENV, LEGACY_LOG_STYLE_ENV).unwrap_or_else(|| "always".to_string());
+    env_logger::Builder::from_env(Env::default().filter_or(LOG_ENV, default_filter).write_style_or(LOG_STYLE_ENV, default_style)).init();
+    if legacy_filter.is_some() {
+        warn!("{} is deprecated; set {} instead.", LEGACY_LOG_ENV, LOG_ENV);
+    }
+}
+
+/// The first Ctrl-C cancels the token, so the running stage stops and the run checkpoints as it
+/// would at its time limit; a second one exits immediately.
+fn handle_interrupts(cancel_token: &CancellationToken) {
+    let token = cancel_token.clone();
+    let installed = ctrlc::set_handler(move || {
+        if token.is_cancellation_requested() {
+            std::process::exit(130);
+        }
+        eprintln!("Interrupted; saving progress. Press Ctrl-C again to exit without saving.");
+        token.cancel();
+    });
+    if let Err(e) = installed {
+        warn!("Unable to install the Ctrl-C handler: {}", e);
+    }
+}
+
+/// Sizes the global thread pool from the configuration; it can only be set once per process.
+fn configure_threads(config: &GnfsConfig) {
+    if let Some(threads) = config.threads {
+        if let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(threads).build_global() {
+            warn!("Unable to limit the thread pool to {} threads: {}", threads, e);
+        }
+    }
+}
+
+/// Sets the token's deadline from the configured wall-clock limit, counted from process start so
+/// that setup time comes out of the same allowance.
+fn arm_deadline(cancel_token: &CancellationToken, config: &GnfsConfig, process_started: Instant) {
+    if let Some(seconds) = config.max_run_secs {
+        cancel_token.set_deadline(process_started + Duration::from_secs(seconds));
+        info!("Run limited to {:?}; it will checkpoint and exit once the limit passes.", Duration::from_secs(seconds));
+    }
+}
+
+/// Verifies the checkpoint in `directory` and loads it with `config`. With `force`, files that
+/// fail their checksum are only warned about.
+fn load_checkpoint(directory: &Path, force: bool, config: GnfsConfig) -> Result<(GNFS, Factorizer), GnfsError> {
+    let manifest = CheckpointManifest::read(directory)?;
+    let problems = manifest.verify_files(directory)?;
+    for problem in &problems {
+        warn!("Checkpoint: {}", problem);
+    }
+    if !problems.is_empty() && !force {
+        return Err(GnfsError::InvalidParameter(format!(
+            "{} checkpoint file(s) failed verification; pass --force to resume anyway",
+            problems.len()
+        )));
+    }
+
+    let mut gnfs = load::all(&directory.join("GNFS.json").to_string_lossy());
+    manifest.verify_parameters(&gnfs)?;
+    gnfs.set_config(config.clone());
+    configure_threads(&gnfs.config);
+    let factorizer = Factorizer::new(gnfs.n.clone()).with_config(config);
+    info!("Checkpoint for N = {} verified ({} files); {}.", gnfs.n, manifest.files.len(), ResumePlan::for_run(&gnfs));
+    Ok((gnfs, factorizer))
+}
+
+/// Loads the checkpoint in `directory` and runs the remaining stages.
+fn resume(cancel_token: &CancellationToken, directory: &Path, force: bool, config: GnfsConfig, process_started: Instant) -> Result<StageOutcome, GnfsError> {
+    let (mut gnfs, mut factorizer) = load_checkpoint(directory, force, config)?;
+    let Some(stage) = ResumePlan::for_run(&gnfs).stage else {
+        return Ok(StageOutcome::Completed);
+    };
+    arm_deadline(cancel_token, &gnfs.config, process_started);
+    Ok(run_stages(cancel_token, &mut factorizer, &mut gnfs, stage))
+}
+
+/// Resumes the run in the save directory with its sieving handed out to workers, then runs the
+/// later stages here once the relation target is reached. A run past sieving just resumes.
+fn serve(cancel_token: &CancellationToken, args: &ServeArgs, config: GnfsConfig, process_started: Instant) -> Result<StageOutcome, GnfsError> {
+    let (mut gnfs, mut factorizer) = load_checkpoint(&args.directory, args.force, config)?;
+    let Some(stage) = ResumePlan::for_run(&gnfs).stage else {
+        return Ok(StageOutcome::Completed);
+    };
+    arm_deadline(cancel_token, &gnfs.config, process_started);
+    if stage == Stage::Sieve {
+        let listener = TcpListener::bind(&args.listen).map_err(|e| GnfsError::Network(format!("cannot listen on {}: {}", args.listen, e)))?;
+        if !coordinator::serve(cancel_token, &mut gnfs, listener, args.rows_per_unit, args.special_q_per_unit)? {
+            info!("Stopped before the relation target; `gnfs serve` or `gnfs resume` continues from {}.", args.directory.display());
+            return Ok(StageOutcome::NeedsMoreWork);
+        }
+    }
+    Ok(run_stages(cancel_token, &mut factorizer, &mut gnfs, stage))
+}
+
+/// Sieves for the coordinator at `--connect` until it has what it needs, or sieves the
+/// `--workunit` file.
+fn work(cancel_token: &CancellationToken, args: &WorkerArgs) -> Result<(), GnfsError> {
+    configure_threads(&GnfsConfig { threads: args.threads, ..Default::default() });
+    if let Some(path) = &args.workunit {
+        let output = args.output.clone().unwrap_or_else(|| path.with_extension("relations.jsonl"));
+        worker::sieve_file(cancel_token, path, &output)?;
+        return Ok(());
+    }
+    let address = args.connect.as_deref().expect("clap requires --connect without --workunit");
+    let name = args.name.clone().unwrap_or_else(|| {
+        let host = std::env::var("HOSTNAME").ok().filter(|host| !host.is_empty()).unwrap_or_else(|| "worker".to_string());
+        format!("{}-{}", host, std::process::id())
+    });
+    let summary = worker::run(cancel_token, address, &name)?;
+    info!("Worker {} done: {}", name, summary);
+    Ok(())
+}
+
+/// Writes the next `--count` work units of the run in the save directory as files, and records
+/// them in its work-unit ledger so neither a coordinator nor a later local sieve repeats them.
+fn export_workunits(args: &ExportWorkunitsArgs, config: GnfsConfig) -> Result<(), GnfsError> {
+    let (mut gnfs, _) = load_checkpoint(&args.directory, args.force, config)?;
+    if ResumePlan::for_run(&gnfs).stage != Some(Stage::Sieve) {
+        return Err(GnfsError::InvalidParameter(format!("the run in {} is past sieving", args.directory.display())));
+    }
+    let out = args.out.clone().unwrap_or_else(|| args.directory.join("workunits"));
+    std::fs::create_dir_all(&out)?;
+    let mut ledger = match WorkUnitLedger::read(&args.directory)? {
+        Some(ledger) => ledger,
+        None => WorkUnitLedger::new(&gnfs, args.rows_per_unit, args.special_q_per_unit)?,
+    };
+    for unit in ledger.export(&gnfs, args.count) {
+        let path = out.join(WorkUnitFile::filename(unit.id));
+        WorkUnitFile { unit: unit.clone(), job: Box::new(work_unit::job(&gnfs)) }.write(&path)?;
+        info!("Exported {} to {}", unit, path.display());
+    }
+    ledger.write(&args.directory)?;
+    ledger.advance_local_sieve(&mut gnfs);
+    gnfs.checkpoint();
+    info!("{}", ledger);
+    Ok(())
+}
+
+/// Merges relation files into the run in the save directory.
+fn import_relations(args: &ImportRelationsArgs, config: GnfsConfig) -> Result<(), GnfsError> {
+    let (mut gnfs, _) = load_checkpoint(&args.directory, args.force, config)?;
+    println!("{}", import::import_relations(&mut gnfs, &args.files)?);
+    println!("Next: {}", ResumePlan::for_run(&gnfs));
+    Ok(())
+}
 
-        gnfs.current_relations_progress.generate_relations(cancel_token);
+/// Writes the run in the save directory as msieve.fb and msieve.dat: the polynomials, the factor
+/// bases and every relation held, smooth, rough or part of a combined set, for msieve to filter
+/// and combine itself.
+fn export_msieve(args: &ExportMsieveArgs) -> Result<(), GnfsError> {
+    if !args.directory.join("GNFS.json").exists() {
+        return Err(GnfsError::InvalidParameter(format!("{} is not a gnfs save directory", args.directory.display())));
+    }
+    let gnfs = load::all(&args.directory.join("GNFS.json").to_string_lossy());
+    let out = args.out.clone().unwrap_or_else(|| args.directory.clone());
+    std::fs::create_dir_all(&out)?;
+    MsieveFactorBase::from_gnfs(&gnfs).write(&out.join(msieve::FACTOR_BASE_FILENAME))?;
+    let relations = &gnfs.current_relations_progress.relations;
+    let all = relations.smooth_relations.iter().chain(&relations.rough_relations).chain(relations.combined_relations.iter().flatten());
+    let written = msieve::write_relations(&gnfs.n, all, &out.join(msieve::RELATIONS_FILENAME))?;
+    println!("Wrote {} and {} relations to {}", msieve::FACTOR_BASE_FILENAME, written, out.join(msieve::RELATIONS_FILENAME).display());
+    Ok(())
+}
 
-        debug!("");
-        debug!("Sieving progress saved at:");
-        debug!(" A = {}", gnfs.current_relations_progress.a);
-        debug!(" B = {}", gnfs.current_relations_progress.b);
-        debug!("");
+fn convert_relations(args: &ConvertRelationsArgs) -> Result<(), GnfsError> {
+    if !args.directory.join("GNFS.json").exists() {
+        return Err(GnfsError::InvalidParameter(format!("{} is not a gnfs save directory", args.directory.display())));
+    }
+    let gnfs = load::all(&args.directory.join("GNFS.json").to_string_lossy());
+    let format = args.to.unwrap_or_else(|| RelationFormat::for_path(&args.output));
+    let written = storage::convert(&gnfs, &args.input, &args.output, format)?;
+    println!("Wrote {} relations to {} as {}", written, args.output.display(), format);
+    Ok(())
+}
 
-        if one_round {
-            break;
+/// Prints what the save directory holds: N, the polynomial, whether the checkpoint files verify
+/// and, when they do, where `gnfs resume` would pick up.
+fn status(directory: &Path) -> Result<(), GnfsError> {
+    let manifest = CheckpointManifest::read(directory)?;
+    let problems = manifest.verify_files(directory)?;
+    println!("N = {}", manifest.n);
+    println!("ƒ(x) = {}, m = {}", manifest.polynomial, manifest.polynomial_base);
+    println!("{} checkpoint files, {} failing verification", manifest.files.len(), problems.len());
+    for problem in &problems {
+        println!("  {}", problem);
+    }
+    if problems.is_empty() {
+        let gnfs = load::all(&directory.join("GNFS.json").to_string_lossy());
+        match &gnfs.factorization {
+            Some(solution) => println!("{}", solution),
+            None => println!("Next: {}", ResumePlan::for_run(&gnfs)),
         }
+    }
+    Ok(())
+}
 
-        if gnfs.current_relations_progress.smooth_relations_counter >= gnfs.current_relations_progress.smooth_relations_target_quantity {
-            break;
+/// Loads the save directory and runs the relation verifier over it, as the matrix stage would.
+/// The files are left as they are.
+fn verify_relations(directory: &Path) -> Result<(), GnfsError> {
+    if !directory.join("GNFS.json").exists() {
+        return Err(GnfsError::InvalidParameter(format!("{} is not a gnfs save directory", directory.display())));
+    }
+    let mut gnfs = load::all(&directory.join("GNFS.json").to_string_lossy());
+    println!("{}", verifier::verify_relations(&mut gnfs));
+    Ok(())
+}
+
+/// Deletes the save directories. Every one must hold a checkpoint manifest or GNFS.json, so a
+/// mistyped path can't remove anything else; nothing is deleted unless all of them do.
+fn clean(directories: &[PathBuf]) -> Result<(), GnfsError> {
+    if let Some(directory) = directories.iter().find(|directory| !directory.join(MANIFEST_FILENAME).exists() && !directory.join("GNFS.json").exists()) {
+        return Err(GnfsError::InvalidParameter(format!("{} is not a gnfs save directory", directory.display())));
+    }
+    for directory in directories {
+        std::fs::remove_dir_all(directory)?;
+        info!("Removed {}", directory.display());
+    }
+    Ok(())
+}
+
+/// Times `--trials` full runs of every N given (or the default N), writes the report and, with
+/// `--baseline`, compares medians against an earlier report. Returns false when a case regressed.
+fn benchmark(cancel_token: &CancellationToken, args: &BenchArgs) -> Result<bool, GnfsError> {
+    let config = args.config.to_config()?;
+    configure_threads(&config);
+
+    let report = BenchmarkReport::run(&args.cases(), args.trials, |n| {
+        if cancel_token.is_cancellation_requested() {
+            return Err(GnfsError::Cancelled);
         }
+        let started = Instant::now();
+        // Trials run in a throwaway directory so they don't overwrite a real run's checkpoint.
+        let result = Factorizer::new(n.clone())
+            .with_config(config.clone())
+            .with_save_locations(DirectoryLocations::temporary(&DirectoryLocations::get_unique_name_from_n(n))?)
+            .run(cancel_token)?;
+        Ok((started.elapsed(), result.solution.is_some()))
+    })?;
+    for result in &report.results {
+        info!("Benchmark {}", result);
+    }
+    report.write(&args.output)?;
+    info!("Benchmark report written to {}", args.output.display());
+
+    let Some(baseline) = &args.baseline else {
+        return Ok(true);
+    };
+    let regressions = report.regressions(&BenchmarkReport::read(baseline)?, REGRESSION_THRESHOLD_PERCENT);
+    for regression in &regressions {
+        warn!("Benchmark regression: {}", regression);
     }
-    if cancel_token.is_cancellation_requested() {
-        info!("Sieving cancelled.");
-        info!("Saving progress...");
-        info!("Relations found: {}", gnfs.current_relations_progress.smooth_relations_counter);
+    Ok(regressions.is_empty())
+}
+
+/// Logs the answer at info, or prints it when --quiet has switched info logging off.
+fn print_answer(answer: impl Display) {
+    if log_enabled!(Level::Info) {
+        info!("{}", answer);
     } else {
-        info!("Sieving complete.");
+        println!("{}", answer);
     }
+}
+
+/// Runs the stages from `start` on with the factorizer and prints the factors once found.
+fn run_stages(cancel_token: &CancellationToken, factorizer: &mut Factorizer, gnfs: &mut GNFS, start: Stage) -> StageOutcome {
+    let outcome = factorizer.run_stages(cancel_token, gnfs, start);
+    if let Some(solution) = &gnfs.factorization {
+        print_answer(solution);
+    }
+    outcome
+}
+
+#[cfg(feature = "tui")]
+fn run_with_dashboard(cancel_token: &CancellationToken, factorizer: &mut Factorizer, gnfs: &mut GNFS) -> StageOutcome {
+    let events_path = std::path::PathBuf::from(gnfs.save_locations.events_filepath());
+    let (shown, outcome) = std::thread::scope(|scope| {
+        let worker = scope.spawn(|| factorizer.run_stages(cancel_token, gnfs, Stage::Sieve));
+        let shown = gnfs::tui::run(&events_path, cancel_token, || worker.is_finished());
+        (shown, worker.join().expect("pipeline thread panicked"))
+    });
+    if let Err(e) = shown {
+        eprintln!("Dashboard failed: {}", e);
+    }
+    outcome
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_with_dashboard(cancel_token: &CancellationToken, factorizer: &mut Factorizer, gnfs: &mut GNFS) -> StageOutcome {
+    warn!("--tui requires building with the `tui` feature.");
+    factorizer.run_stages(cancel_token, gnfs, Stage::Sieve)
+}
+
+#[cfg(feature = "factordb")]
+fn lookup_known_factors(gnfs: &mut GNFS) -> bool {
+    match FactorDbClient::new().query(&gnfs.n) {
+        Ok(entry) if entry.status == FactorDbStatus::FullyFactored && !entry.factors.is_empty() => {
+            let p = entry.factors[0].0.clone();
+            let q = &gnfs.n / &p;
+            gnfs.run_metadata.algorithm = "factordb".to_string();
+            gnfs.set_factorization_solution(&p, &q)
+        }
+        Ok(entry) => {
+            info!("factordb status for N: {:?}", entry.status);
+            false
+        }
+        Err(e) => {
+            warn!("factordb lookup failed: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(not(feature = "factordb"))]
+fn lookup_known_factors(_gnfs: &mut GNFS) -> bool {
+    warn!("--factordb requires building with the `factordb` feature.");
+    false
+}
 
-    gnfs
-}
\ No newline at end of file
+#[cfg(feature = "factordb")]
+fn submit_factors(gnfs: &GNFS) {
+    if let Some(solution) = &gnfs.factorization {
+        match FactorDbClient::new().submit(&gnfs.n, &solution.factors) {
+            Ok(()) => info!("Reported factors to factordb."),
+            Err(e) => warn!("factordb submission failed: {}", e),
+        }
+    }
+}
+
+#[cfg(not(feature = "factordb"))]
+fn submit_factors(_gnfs: &GNFS) {
+    warn!("--factordb-submit requires building with the `factordb` feature.");
+}
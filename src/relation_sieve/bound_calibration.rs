@@ -0,0 +1,155 @@
+// src/relation_sieve/bound_calibration.rs
+
+use std::time::Instant;
+use num::{BigInt, Integer, One, Signed, Zero};
+use crate::core::cancellation_token::CancellationToken;
+use crate::integer_math::prime_factory::PrimeFactory;
+use crate::polynomial::polynomial::Polynomial;
+use crate::relation_sieve::relation::Relation;
+
+/// Half-width of the sampled `a` range.
+pub const SAMPLE_A_RANGE: i64 = 200;
+/// Number of `b` lines in the sample region.
+pub const SAMPLE_B_LINES: i64 = 8;
+
+/// Measured yield for one candidate prime bound.
+#[derive(Debug, Clone)]
+pub struct BoundTrial {
+    pub bound: BigInt,
+    pub sampled: usize,
+    pub smooth: usize,
+    pub seconds: f64,
+    pub required_relations: usize,
+    /// Estimated time to collect `required_relations` at the sampled rate; infinite when nothing was smooth.
+    pub projected_seconds: f64,
+}
+
+/// Candidate bounds bracketing the digit-based default.
+pub fn candidate_bounds(default_bound: &BigInt) -> Vec<BigInt> {
+    let half = default_bound / 2;
+    let mut candidates = Vec::new();
+    if half > BigInt::from(10) {
+        candidates.push(half);
+    }
+    candidates.push(default_bound.clone());
+    candidates.push(default_bound * 2);
+    candidates
+}
+
+/// Sieves the same small region once per candidate bound and returns the trials, best
+/// (lowest projected sieving time) first. `quadratic_count` is added to the relation target.
+pub fn calibrate(
+    cancel_token: &CancellationToken,
+    polynomial: &Polynomial,
+    rational_polynomial: &Polynomial,
+    candidates: &[BigInt],
+    quadratic_count: usize,
+) -> Vec<BoundTrial> {
+    let prime_factory = PrimeFactory::new();
+    let mut trials = Vec::new();
+
+    for bound in candidates {
+        if cancel_token.is_cancellation_requested() {
+            break;
+        }
+
+        let algebraic_bound = bound * 3;
+        let rational_primes: Vec<BigInt> = PrimeFactory::get_primes_to(&prime_factory, bound).collect();
+        let algebraic_primes: Vec<BigInt> = PrimeFactory::get_primes_to(&prime_factory, &algebraic_bound).collect();
+
+        let started = Instant::now();
+        let mut sampled = 0;
+        let mut smooth = 0;
+        for b in 1..=SAMPLE_B_LINES {
+            let b = BigInt::from(b);
+            for a in -SAMPLE_A_RANGE..=SAMPLE_A_RANGE {
+                let a = BigInt::from(a);
+                if !a.gcd(&b).is_one() {
+                    continue;
+                }
+                sampled += 1;
+                let (algebraic_norm, rational_norm) = Relation::norms(polynomial, rational_polynomial, &a, &b);
+                if is_smooth_over(&rational_norm, &rational_primes) && is_smooth_over(&algebraic_norm, &algebraic_primes) {
+                    smooth += 1;
+                }
+            }
+        }
+        let seconds = started.elapsed().as_secs_f64().max(f64::EPSILON);
+
+        let required_relations = rational_primes.len() + algebraic_primes.len() + quadratic_count + 3;
+        let projected_seconds = if smooth == 0 {
+            f64::INFINITY
+        } else {
+            required_relations as f64 / (smooth as f64 / seconds)
+        };
+
+        trials.push(BoundTrial { bound: bound.clone(), sampled, smooth, seconds, required_relations, projected_seconds });
+    }
+
+    trials.sort_by(|left, right| left.projected_seconds.total_cmp(&right.projected_seconds));
+    trials
+}
+
+fn is_smooth_over(value: &BigInt, primes: &[BigInt]) -> bool {
+    let mut remaining = value.abs();
+    if remaining.is_zero() {
+        return false;
+    }
+    for p in primes {
+        if remaining.is_one() {
+            break;
+        }
+        while (&remaining % p).is_zero() {
+            remaining /= p;
+        }
+    }
+    remaining.is_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::pow;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::gnfs::GNFS;
+    use crate::core::gnfs_config::GnfsConfig;
+    use crate::core::gnfs_presets::{GnfsPreset, MIN_PRESET_DIGITS};
+
+    #[test]
+    fn candidates_bracket_the_preset_bound_and_grow_with_n() {
+        let mut previous: Option<Vec<BigInt>> = None;
+        for digits in MIN_PRESET_DIGITS..=40 {
+            let n = pow(BigInt::from(10), digits - 1) + 7;
+            let bound = BigInt::from(GnfsPreset::for_n(&n).unwrap().prime_bound);
+            let candidates = candidate_bounds(&bound);
+            assert!(candidates.contains(&bound));
+            assert!(candidates.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(candidates.iter().all(|candidate| candidate >= &(&bound / 2) && candidate <= &(&bound * 2)));
+            if let Some(previous) = &previous {
+                assert!(previous.first() <= candidates.first() && previous.last() <= candidates.last(), "{} digits", digits);
+            }
+            previous = Some(candidates);
+        }
+        let smallest = candidate_bounds(&BigInt::from(GnfsPreset::for_n(&pow(BigInt::from(10), MIN_PRESET_DIGITS - 1)).unwrap().prime_bound));
+        assert!(previous.unwrap().first() > smallest.last());
+    }
+
+    #[test]
+    fn trials_cover_the_candidates_and_yield_grows_with_the_bound() {
+        let token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("bound_calibration").unwrap();
+        let gnfs = GNFS::with_save_locations(&token, locations, GnfsConfig::default(), &BigInt::from(45113), &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let candidates = candidate_bounds(&BigInt::from(100));
+
+        let mut trials = calibrate(&token, &gnfs.current_polynomial, &gnfs.rational_polynomial, &candidates, 4);
+        assert!(trials.windows(2).all(|pair| pair[0].projected_seconds <= pair[1].projected_seconds));
+        trials.sort_by(|left, right| left.bound.cmp(&right.bound));
+        assert_eq!(trials.iter().map(|trial| trial.bound.clone()).collect::<Vec<BigInt>>(), candidates);
+        for pair in trials.windows(2) {
+            assert_eq!(pair[0].sampled, pair[1].sampled);
+            assert!(pair[0].smooth <= pair[1].smooth);
+            assert!(pair[0].required_relations < pair[1].required_relations);
+        }
+        assert!(trials.last().unwrap().smooth > 0);
+    }
+}
@@ -0,0 +1,124 @@
+// src/relation_sieve/compression.rs
+
+//! zstd compression for the smooth relation file. Each flush is compressed on its own and appended
+//! as one complete zstd frame, so the file stays a valid zstd stream that grows the way the plain
+//! file does, and a resumed run keeps appending frames to it. A file is compressed or not from its
+//! first write on; [`open`] and [`read`] tell the two apart by the zstd magic number, so every
+//! reader takes either.
+//!
+//! A write cut short leaves a partial frame at the end. [`read`] drops it, and [`repair`]
+//! truncates it before anything more is appended.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use log::warn;
+use zstd::zstd_safe;
+use crate::core::error::GnfsError;
+
+/// The first four bytes of every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+/// zstd's own default; relation text compresses well enough that higher levels buy little.
+pub const COMPRESSION_LEVEL: i32 = 3;
+
+/// Whether the file at `path` starts with a zstd frame.
+pub fn is_compressed(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && magic == ZSTD_MAGIC
+}
+
+/// Appends `text` to the file at `path`, creating it when missing. An existing file keeps its
+/// format; a new one is compressed when `compress` is set.
+pub fn append(path: &Path, text: &[u8], compress: bool) -> Result<(), GnfsError> {
+    let exists = path.metadata().is_ok_and(|metadata| metadata.len() > 0);
+    let compress = if exists { is_compressed(path) } else { compress };
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if compress {
+        file.write_all(&zstd::bulk::compress(text, COMPRESSION_LEVEL)?)?;
+    } else {
+        file.write_all(text)?;
+    }
+    Ok(())
+}
+
+/// Streams the contents of the file at `path`, decompressed when it is compressed.
+pub fn open(path: &Path) -> Result<Box<dyn Read>, GnfsError> {
+    let file = File::open(path)?;
+    if is_compressed(path) {
+        return Ok(Box::new(zstd::stream::read::Decoder::new(file)?));
+    }
+    Ok(Box::new(file))
+}
+
+/// The length of the complete frames at the start of `bytes`.
+fn complete_frames(bytes: &[u8]) -> usize {
+    let mut length = 0;
+    while length < bytes.len() {
+        match zstd_safe::find_frame_compressed_size(&bytes[length..]) {
+            Ok(frame) => length += frame,
+            Err(_) => break,
+        }
+    }
+    length
+}
+
+/// Reads the whole file at `path`, decompressed when it is compressed. A partial frame at the end
+/// is left out with a warning.
+pub fn read(path: &Path) -> Result<Vec<u8>, GnfsError> {
+    let bytes = fs::read(path)?;
+    if !bytes.starts_with(&ZSTD_MAGIC) {
+        return Ok(bytes);
+    }
+    let length = complete_frames(&bytes);
+    if length < bytes.len() {
+        warn!("{} ends in {} bytes of an unfinished frame; they are ignored.", path.display(), bytes.len() - length);
+    }
+    Ok(zstd::stream::decode_all(&bytes[..length])?)
+}
+
+/// Cuts a partial frame off the end of a compressed file at `path`, left there by a write that was
+/// interrupted, so frames appended later can be read. Returns the number of bytes removed.
+pub fn repair(path: &Path) -> Result<u64, GnfsError> {
+    if !is_compressed(path) {
+        return Ok(0);
+    }
+    let bytes = fs::read(path)?;
+    let length = complete_frames(&bytes);
+    let removed = (bytes.len() - length) as u64;
+    if removed > 0 {
+        OpenOptions::new().write(true).open(path)?.set_len(length as u64)?;
+    }
+    Ok(removed)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::relation_sieve::relation_store::RelationFileReader;
+
+    #[test]
+    fn appended_frames_read_back_as_one_file_after_an_interrupted_write() {
+        let locations = DirectoryLocations::temporary("compression").unwrap();
+        let path = Path::new(&locations.save_directory).join("SmoothRelations.json");
+        let record = |a: i64| format!(r#"{{"a":"{}","b":"1","algebraic_norm":"1","rational_norm":"1","algebraic_quotient":"1","rational_quotient":"1","algebraic_factorization":"","rational_factorization":"","is_persisted":true}}"#, a);
+
+        append(&path, record(1).as_bytes(), true).unwrap();
+        append(&path, format!(",{},{}", record(2), record(3)).as_bytes(), false).unwrap();
+        assert!(is_compressed(&path));
+        let complete = fs::metadata(&path).unwrap().len();
+        // Half of a third frame, as a crash mid-write would leave it.
+        let frame = zstd::bulk::compress(format!(",{}", record(4)).as_bytes(), COMPRESSION_LEVEL).unwrap();
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(&frame[..frame.len() / 2]).unwrap();
+
+        let text = String::from_utf8(read(&path).unwrap()).unwrap();
+        assert_eq!(text, format!("{},{},{}", record(1), record(2), record(3)));
+        assert_eq!(repair(&path).unwrap(), (frame.len() / 2) as u64);
+        assert_eq!(fs::metadata(&path).unwrap().len(), complete);
+
+        append(&path, format!(",{}", record(5)).as_bytes(), false).unwrap();
+        let pairs: Vec<String> = RelationFileReader::open(&path).unwrap().map(|relation| relation.unwrap().a).collect();
+        assert_eq!(pairs, ["1", "2", "3", "5"]);
+    }
+}
@@ -0,0 +1,229 @@
+// src/relation_sieve/cycle_finder.rs
+
+//! Cycles in the large prime graph. Its vertices are the large primes plus a vertex standing for
+//! 1, and each partial relation is an edge: from 1 to its large prime when it has one, between its
+//! two large primes when it has one on each side. Every vertex on a cycle is the endpoint of two of
+//! its edges, so the product of the relations around a cycle has each large prime to an even power
+//! and stands for a full relation. A union-find over the vertices tells when a new edge closes a
+//! cycle; the cycle is then that edge plus the path between its endpoints in the spanning forest
+//! of the edges kept so far.
+
+use std::collections::{HashMap, VecDeque};
+use crate::relation_sieve::partial_relation::LargePrime;
+use crate::relation_sieve::relation::Relation;
+
+#[derive(Debug, Clone, Default)]
+pub struct CycleFinder {
+    graph: LargePrimeGraph,
+    /// Forest edges at each vertex, as (neighbour, index into `relations`).
+    adjacency: Vec<Vec<(usize, usize)>>,
+    /// The relations making up the spanning forest.
+    relations: Vec<Relation>,
+}
+
+/// Counts the cycles partial relations close without keeping the relations, so the sieve knows how
+/// many combined relations a [`CycleFinder`] would form from what it has found so far.
+#[derive(Debug, Clone, Default)]
+pub struct CycleCounter {
+    graph: LargePrimeGraph,
+    cycles: usize,
+}
+
+/// The union-find over large prime vertices that both of the above share.
+#[derive(Debug, Clone, Default)]
+struct LargePrimeGraph {
+    /// Vertex numbers; `None` is the vertex 1.
+    vertices: HashMap<Option<LargePrime>, usize>,
+    /// Union-find parent of each vertex, and the size of the tree under each root.
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl CycleFinder {
+    pub fn new() -> Self {
+        CycleFinder::default()
+    }
+
+    /// Adds the partial relation whose large primes are `large_primes`, one or two of them. When it
+    /// closes a cycle, returns the relations around it, itself last, and leaves the forest as it
+    /// was; otherwise the relation joins the forest. Relations with no large primes, or more than
+    /// two, and repeats of a forest edge are dropped.
+    pub fn add(&mut self, relation: Relation, large_primes: Vec<LargePrime>) -> Option<Vec<Relation>> {
+        let (u, v) = self.graph.edge(large_primes)?;
+        self.adjacency.resize_with(self.graph.parent.len(), Vec::new);
+        if self.graph.join(u, v) {
+            let index = self.relations.len();
+            self.relations.push(relation);
+            self.adjacency[u].push((v, index));
+            self.adjacency[v].push((u, index));
+            return None;
+        }
+
+        let path = self.path(u, v);
+        if path.iter().any(|&index| self.relations[index] == relation) {
+            return None;
+        }
+        let mut cycle: Vec<Relation> = path.into_iter().map(|index| self.relations[index].clone()).collect();
+        cycle.push(relation);
+        Some(cycle)
+    }
+
+    /// Relations in the spanning forest.
+    pub fn len(&self) -> usize {
+        self.relations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relations.is_empty()
+    }
+
+    /// The relations in the spanning forest, in (a, b) order. Adding them to a new finder rebuilds
+    /// the same forest without closing any cycle.
+    pub fn into_relations(self) -> Vec<Relation> {
+        let mut relations = self.relations;
+        relations.sort();
+        relations
+    }
+
+    /// The forest edges, as indices into `relations`, on the path from `from` to `to`, which must
+    /// be in the same tree.
+    fn path(&self, from: usize, to: usize) -> Vec<usize> {
+        let mut reached_by: HashMap<usize, (usize, usize)> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(vertex) = queue.pop_front() {
+            if vertex == to {
+                break;
+            }
+            for &(neighbour, edge) in &self.adjacency[vertex] {
+                if neighbour != from && !reached_by.contains_key(&neighbour) {
+                    reached_by.insert(neighbour, (vertex, edge));
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        let mut edges = Vec::new();
+        let mut vertex = to;
+        while vertex != from {
+            let (previous, edge) = reached_by[&vertex];
+            edges.push(edge);
+            vertex = previous;
+        }
+        edges.reverse();
+        edges
+    }
+}
+
+impl CycleCounter {
+    pub fn new() -> Self {
+        CycleCounter::default()
+    }
+
+    /// Adds a partial relation's large primes, as for [`CycleFinder::add`], and returns whether it
+    /// closes a cycle. Repeats of a relation aren't recognized and count again.
+    pub fn add(&mut self, large_primes: Vec<LargePrime>) -> bool {
+        let closes = self.graph.edge(large_primes).is_some_and(|(u, v)| !self.graph.join(u, v));
+        self.cycles += closes as usize;
+        closes
+    }
+
+    /// Cycles closed since the counter was made or last had its cycles taken.
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    /// Forgets the cycles counted so far, once they have been formed, and keeps the graph.
+    pub fn take_cycles(&mut self) -> usize {
+        std::mem::take(&mut self.cycles)
+    }
+}
+
+impl LargePrimeGraph {
+    /// The vertices a partial relation joins: 1 and its large prime, or its two large primes.
+    fn edge(&mut self, large_primes: Vec<LargePrime>) -> Option<(usize, usize)> {
+        let mut large_primes = large_primes.into_iter();
+        match (large_primes.next(), large_primes.next(), large_primes.next()) {
+            (Some(p), None, None) => Some((self.vertex(None), self.vertex(Some(p)))),
+            (Some(p), Some(q), None) => Some((self.vertex(Some(p)), self.vertex(Some(q)))),
+            _ => None,
+        }
+    }
+
+    /// Merges the trees of `u` and `v`; false when they were already one tree, so that an edge
+    /// between them closes a cycle.
+    fn join(&mut self, u: usize, v: usize) -> bool {
+        let (root_u, root_v) = (self.find(u), self.find(v));
+        if root_u == root_v {
+            return false;
+        }
+        let (large, small) = if self.size[root_u] >= self.size[root_v] { (root_u, root_v) } else { (root_v, root_u) };
+        self.parent[small] = large;
+        self.size[large] += self.size[small];
+        true
+    }
+
+    fn vertex(&mut self, large_prime: Option<LargePrime>) -> usize {
+        let next = self.parent.len();
+        let vertex = *self.vertices.entry(large_prime).or_insert(next);
+        if vertex == next {
+            self.parent.push(next);
+            self.size.push(1);
+        }
+        vertex
+    }
+
+    fn find(&mut self, vertex: usize) -> usize {
+        let mut root = vertex;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut current = vertex;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::BigInt;
+    use crate::core::gnfs::GNFS;
+    use crate::relation_sieve::partial_relation::LargePrimeSide;
+
+    fn rational(prime: u32) -> LargePrime {
+        LargePrime { side: LargePrimeSide::Rational, prime: BigInt::from(prime), root: BigInt::from(0) }
+    }
+
+    #[test]
+    fn closes_cycles_through_double_large_prime_relations() {
+        let gnfs = GNFS::default();
+        let relation = |a: i64| Relation::new(&gnfs, &BigInt::from(a), &BigInt::from(1));
+        let mut finder = CycleFinder::new();
+        assert_eq!(finder.add(relation(1), vec![rational(101)]), None);
+        assert_eq!(finder.add(relation(2), vec![rational(101), rational(103)]), None);
+        assert_eq!(finder.add(relation(3), vec![rational(107), rational(109)]), None);
+        assert_eq!(finder.add(relation(2), vec![rational(101), rational(103)]), None);
+        let cycle = finder.add(relation(4), vec![rational(103)]).unwrap();
+        assert_eq!(cycle.iter().map(|relation| relation.a.clone()).collect::<Vec<_>>(), [1, 2, 4].map(BigInt::from));
+        assert_eq!(finder.len(), 3);
+    }
+
+    #[test]
+    fn counts_the_cycles_the_finder_closes() {
+        let gnfs = GNFS::default();
+        let relation = |a: i64| Relation::new(&gnfs, &BigInt::from(a), &BigInt::from(1));
+        let edges = [vec![rational(101)], vec![rational(103), rational(107)], vec![rational(101), rational(103)], vec![rational(107)], vec![rational(101)], vec![]];
+        let mut finder = CycleFinder::new();
+        let mut counter = CycleCounter::new();
+        for (a, large_primes) in (1..).zip(edges) {
+            let closed = finder.add(relation(a), large_primes.clone()).is_some();
+            assert_eq!(counter.add(large_primes), closed);
+        }
+        assert_eq!(counter.take_cycles(), 2);
+        assert_eq!(counter.cycles(), 0);
+        assert!(counter.add(vec![rational(103)]));
+    }
+}
@@ -0,0 +1,281 @@
+// src/relation_sieve/filter.rs
+
+//! Shrinks the relation sets before they become matrix columns. Only the sparse rows take part:
+//! the rational primes and algebraic prime ideals with an odd exponent. The signs and quadratic
+//! characters are dense, so they are left to the matrix.
+//!
+//! - Duplicate sets, with the same relations once pairs cancel, are dropped.
+//! - A set holding an ideal no other set has, a singleton, can't be in any dependency; it goes,
+//!   which may leave new singletons behind, so this repeats until none are left.
+//! - While the surplus of sets over ideals is well above what the square root needs, cliques go:
+//!   groups of sets linked through ideals that only two sets share, largest first. Each one
+//!   removes about one set of surplus and usually frees up several ideals.
+//! - An ideal shared by two sets is cancelled by merging them into one; one shared by three
+//!   merges the lightest into the other two. Both remove a set and an ideal from the matrix.
+//!
+//! The result is a list of sets that stand for combinations of the input sets, as combined
+//! relations already do, so a dependency of the reduced matrix flattens into input relations
+//! for the square root stage with no further bookkeeping.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use num::BigInt;
+use crate::core::error::GnfsError;
+use crate::core::gnfs::GNFS;
+use crate::matrix::gaussian_row::{symmetric_difference, GaussianRow};
+use crate::matrix::matrix_solution::flatten;
+use crate::matrix::relation_sets::RelationSets;
+
+/// Dependencies wanted beyond the dense rows once cliques are removed.
+pub const TARGET_SURPLUS: usize = 64;
+/// Ideals a merged set may keep; heavier merges would only fill in the matrix.
+pub const MAX_MERGED_WEIGHT: usize = 32;
+
+/// What [`filter`] removed and merged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FilterReport {
+    pub input_sets: usize,
+    pub duplicates: usize,
+    pub singletons: usize,
+    /// Sets removed with their cliques.
+    pub clique_sets: usize,
+    pub two_way_merges: usize,
+    pub three_way_merges: usize,
+    pub output_sets: usize,
+    /// Sparse rows still occupied by some set.
+    pub ideals: usize,
+}
+
+impl FilterReport {
+    /// Sets beyond the sparse rows, which bounds the dependencies the matrix can find.
+    pub fn surplus(&self) -> isize {
+        self.output_sets as isize - self.ideals as isize
+    }
+}
+
+impl Display for FilterReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Filtered {} relation sets to {} over {} ideals (surplus {}): {} duplicates, {} singletons, {} in cliques removed; {} 2-way and {} 3-way merges",
+            self.input_sets,
+            self.output_sets,
+            self.ideals,
+            self.surplus(),
+            self.duplicates,
+            self.singletons,
+            self.clique_sets,
+            self.two_way_merges,
+            self.three_way_merges
+        )
+    }
+}
+
+/// The reduced relation sets, one matrix column each.
+#[derive(Debug, Clone, Default)]
+pub struct FilteredRelations {
+    /// The input sets each column combines, as increasing indices. A column's relations are those
+    /// of its input sets, less any in an even number of them.
+    pub column_map: Vec<Vec<u32>>,
+    pub report: FilterReport,
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+    /// Sparse rows with an odd exponent, increasing.
+    ideals: Vec<u32>,
+    sources: Vec<u32>,
+}
+
+impl Column {
+    fn merge(&self, other: &Column) -> Column {
+        Column {
+            ideals: symmetric_difference(&self.ideals, &other.ideals),
+            sources: symmetric_difference(&self.sources, &other.sources),
+        }
+    }
+}
+
+/// Filters the relation sets `sets`, as given to the matrix stage, keeping about
+/// [`TARGET_SURPLUS`] dependencies beyond the dense rows of `gnfs`. Each set is fetched once to
+/// find its ideals, and once more if it ends up in a column. Fails only when a stored relation
+/// can't be read.
+pub fn filter(gnfs: &GNFS, sets: &RelationSets) -> Result<FilteredRelations, GnfsError> {
+    let mut report = FilterReport { input_sets: sets.len(), ..Default::default() };
+    let mut columns: Vec<Option<Column>> = Vec::with_capacity(sets.len());
+    let mut seen = HashSet::new();
+    let mut dense_rows = 0;
+    for index in 0..sets.len() {
+        let set = sets.get(index)?;
+        let relations = flatten(std::iter::once(&set));
+        let mut key: Vec<(BigInt, BigInt)> = relations.iter().map(|relation| (relation.a.clone(), relation.b.clone())).collect();
+        key.sort();
+        if relations.is_empty() || !seen.insert(key) {
+            report.duplicates += 1;
+            columns.push(None);
+            continue;
+        }
+        let row = GaussianRow::from_set(gnfs, &relations);
+        let sparse = 2..(2 + row.rational_len + row.algebraic_len) as u32;
+        dense_rows = 2 + row.quadratic_len;
+        columns.push(Some(Column {
+            ideals: row.columns().into_iter().filter(|column| sparse.contains(column)).collect(),
+            sources: vec![index as u32],
+        }));
+    }
+
+    report.singletons += remove_singletons(&mut columns);
+    let target = (dense_rows + TARGET_SURPLUS) as isize;
+    loop {
+        let surplus = live(&columns) as isize - occupancy(&columns).len() as isize;
+        if surplus <= target {
+            break;
+        }
+        let removed = remove_cliques(&mut columns, (surplus - target) as usize);
+        if removed == 0 {
+            break;
+        }
+        report.clique_sets += removed;
+        report.singletons += remove_singletons(&mut columns);
+    }
+    loop {
+        let (two_way, three_way) = merge(&mut columns);
+        if two_way + three_way == 0 {
+            break;
+        }
+        report.two_way_merges += two_way;
+        report.three_way_merges += three_way;
+        report.singletons += remove_singletons(&mut columns);
+    }
+
+    report.ideals = occupancy(&columns).len();
+    let mut filtered = FilteredRelations { report, ..Default::default() };
+    for column in columns.into_iter().flatten() {
+        if !flatten(sets.get_all(&column.sources)?.iter()).is_empty() {
+            filtered.column_map.push(column.sources);
+        }
+    }
+    filtered.report.output_sets = filtered.column_map.len();
+    Ok(filtered)
+}
+
+fn live(columns: &[Option<Column>]) -> usize {
+    columns.iter().flatten().count()
+}
+
+/// The live columns holding each occupied sparse row.
+fn occupancy(columns: &[Option<Column>]) -> HashMap<u32, Vec<usize>> {
+    let mut occupancy: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (index, column) in columns.iter().enumerate() {
+        for &ideal in column.iter().flat_map(|column| &column.ideals) {
+            occupancy.entry(ideal).or_default().push(index);
+        }
+    }
+    occupancy
+}
+
+/// Removes columns holding a singleton until there are none; returns how many went.
+fn remove_singletons(columns: &mut [Option<Column>]) -> usize {
+    let mut removed = 0;
+    loop {
+        let doomed: HashSet<usize> = occupancy(columns).into_values().filter(|holders| holders.len() == 1).map(|holders| holders[0]).collect();
+        if doomed.is_empty() {
+            return removed;
+        }
+        removed += doomed.len();
+        for index in doomed {
+            columns[index] = None;
+        }
+    }
+}
+
+/// Removes up to `count` cliques, the largest first, and returns the columns removed.
+fn remove_cliques(columns: &mut [Option<Column>], count: usize) -> usize {
+    let mut parent: Vec<usize> = (0..columns.len()).collect();
+    fn root(parent: &mut [usize], mut index: usize) -> usize {
+        while parent[index] != index {
+            parent[index] = parent[parent[index]];
+            index = parent[index];
+        }
+        index
+    }
+    for holders in occupancy(columns).into_values().filter(|holders| holders.len() == 2) {
+        let (left, right) = (root(&mut parent, holders[0]), root(&mut parent, holders[1]));
+        parent[left] = right;
+    }
+
+    let mut cliques: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in (0..columns.len()).filter(|&index| columns[index].is_some()) {
+        cliques.entry(root(&mut parent, index)).or_default().push(index);
+    }
+    let mut cliques: Vec<Vec<usize>> = cliques.into_values().collect();
+    cliques.sort_by_key(|clique| (std::cmp::Reverse(clique.len()), clique[0]));
+
+    let mut removed = 0;
+    for clique in cliques.into_iter().take(count) {
+        removed += clique.len();
+        for index in clique {
+            columns[index] = None;
+        }
+    }
+    removed
+}
+
+/// One pass of 2-way and 3-way merges over ideals whose columns no earlier merge of the pass
+/// touched; returns the number of each.
+fn merge(columns: &mut [Option<Column>]) -> (usize, usize) {
+    let mut occupied: Vec<(u32, Vec<usize>)> = occupancy(columns).into_iter().filter(|(_, holders)| (2..=3).contains(&holders.len())).collect();
+    occupied.sort_by_key(|(ideal, holders)| (holders.len(), *ideal));
+
+    let mut touched = HashSet::new();
+    let (mut two_way, mut three_way) = (0, 0);
+    for (_, mut holders) in occupied {
+        if holders.iter().any(|index| touched.contains(index)) {
+            continue;
+        }
+        let weight = |index: &usize| columns[*index].as_ref().map_or(0, |column| column.ideals.len());
+        holders.sort_by_key(weight);
+        let pivot = columns[holders[0]].clone().expect("occupied columns are live");
+        let merged: Vec<Column> = holders[1..].iter().map(|&index| columns[index].as_ref().expect("occupied columns are live").merge(&pivot)).collect();
+        if merged.iter().any(|column| column.ideals.len() > MAX_MERGED_WEIGHT) {
+            continue;
+        }
+        touched.extend(holders.iter().copied());
+        columns[holders[0]] = None;
+        for (&index, column) in holders[1..].iter().zip(merged) {
+            columns[index] = Some(column);
+        }
+        if holders.len() == 2 {
+            two_way += 1;
+        } else {
+            three_way += 1;
+        }
+    }
+    (two_way, three_way)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(ideals: &[u32], source: u32) -> Option<Column> {
+        Some(Column { ideals: ideals.to_vec(), sources: vec![source] })
+    }
+
+    #[test]
+    fn singletons_go_until_none_are_left() {
+        // Removing the column with ideal 9 leaves ideal 3 in only one column, and so on down.
+        let mut columns = vec![column(&[1, 2], 0), column(&[1, 2], 1), column(&[2, 3], 2), column(&[3, 9], 3)];
+        assert_eq!(remove_singletons(&mut columns), 2);
+        assert_eq!(live(&columns), 2);
+    }
+
+    #[test]
+    fn merges_cancel_ideals_shared_by_two_or_three_columns() {
+        let mut columns = vec![column(&[1, 2], 0), column(&[1, 3], 1), column(&[4, 5], 2), column(&[4, 6], 3), column(&[4, 7], 4)];
+        assert_eq!(merge(&mut columns), (1, 1));
+        assert_eq!(live(&columns), 3);
+        let merged: Vec<(Vec<u32>, Vec<u32>)> = columns.into_iter().flatten().map(|column| (column.ideals, column.sources)).collect();
+        assert_eq!(merged, vec![(vec![2, 3], vec![0, 1]), (vec![5, 6], vec![2, 3]), (vec![5, 7], vec![2, 4])]);
+    }
+}
@@ -0,0 +1,345 @@
+// src/relation_sieve/lattice_sieve.rs
+
+//! Special-q lattice sieving. For a prime ideal (q, r) on the algebraic side, the pairs with
+//! a + b·r ≡ 0 (mod q) form a lattice, and every algebraic norm on it is divisible by q. Reducing
+//! the basis (q, 0), (-r, 1) gives two short vectors v₁ and v₂, and (a, b) = i·v₁ + j·v₂ over a
+//! small rectangle of (i, j) reaches pairs whose norms have a known factor of q. A factor-base
+//! prime divides the norm on a sublattice of the (i, j) plane, one residue class of i per row j
+//! that is a multiple of j by a root worked out once per special-q, so each row is sieved with
+//! logarithms and only the cells whose sums come close to the size of both norms are factored.
+
+use num::{BigInt, Integer, Signed, ToPrimitive, Zero};
+use crate::core::gnfs::GNFS;
+use crate::integer_math::fast_prime_sieve::FastPrimeSieve;
+use crate::polynomial::polynomial::Polynomial;
+use crate::relation_sieve::log_sieve::{self, SieveEntry};
+use crate::relation_sieve::relation::Relation;
+
+/// Cells sieved on each side of i = 0: i runs over [-I, I).
+pub const LATTICE_HALF_WIDTH: i64 = 256;
+/// Rows sieved per special-q: j runs over 1..=J. Rows with j < 0 give the same pairs negated.
+pub const LATTICE_ROWS: i64 = 256;
+/// A special-q ideal with its reduced lattice basis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpecialQ {
+    pub q: u64,
+    pub r: u64,
+    /// v₁ = (a₁, b₁) and v₂ = (a₂, b₂), shortest first.
+    pub basis: [(i64, i64); 2],
+}
+
+impl SpecialQ {
+    pub fn new(q: u64, r: u64) -> Self {
+        SpecialQ { q, r, basis: reduce_basis(q, r) }
+    }
+
+    /// The ideals (q, r) with ƒ(r) ≡ 0 (mod q), found by evaluating ƒ at every residue. Only
+    /// affine roots are returned.
+    pub fn ideals(polynomial: &Polynomial, q: u64) -> Vec<SpecialQ> {
+        let modulus = BigInt::from(q);
+        let coefficients: Vec<u128> = (0..=polynomial.degree())
+            .rev()
+            .map(|degree| polynomial.terms.get(&degree).map_or(0, |c| c.mod_floor(&modulus).to_u128().unwrap_or(0)))
+            .collect();
+        let q_wide = q as u128;
+        (0..q)
+            .filter(|&r| coefficients.iter().fold(0u128, |sum, &c| (sum * r as u128 + c) % q_wide) == 0)
+            .map(|r| SpecialQ::new(q, r))
+            .collect()
+    }
+
+    /// The pair at lattice point (i, j), negated if need be so that b ≥ 0.
+    pub fn pair(&self, i: i64, j: i64) -> (BigInt, BigInt) {
+        let [(a1, b1), (a2, b2)] = self.basis;
+        let a = i as i128 * a1 as i128 + j as i128 * a2 as i128;
+        let b = i as i128 * b1 as i128 + j as i128 * b2 as i128;
+        if b < 0 {
+            (BigInt::from(-a), BigInt::from(-b))
+        } else {
+            (BigInt::from(a), BigInt::from(b))
+        }
+    }
+}
+
+/// Lagrange–Gauss reduction of the lattice {(a, b) : a + b·r ≡ 0 (mod q)}.
+pub fn reduce_basis(q: u64, r: u64) -> [(i64, i64); 2] {
+    let norm = |v: (i128, i128)| v.0 * v.0 + v.1 * v.1;
+    let (mut u, mut v) = ((q as i128, 0i128), (-(r as i128), 1i128));
+    if norm(u) < norm(v) {
+        std::mem::swap(&mut u, &mut v);
+    }
+    loop {
+        let (dot, length) = (u.0 * v.0 + u.1 * v.1, norm(v));
+        let k = (2 * dot + length).div_euclid(2 * length);
+        let w = (u.0 - k * v.0, u.1 - k * v.1);
+        if norm(w) >= length {
+            return [(v.0 as i64, v.1 as i64), (w.0 as i64, w.1 as i64)];
+        }
+        u = v;
+        v = w;
+    }
+}
+
+/// The special-q primes in [min, max], in increasing order.
+pub fn special_q_primes(min: u64, max: u64) -> impl Iterator<Item = u64> {
+    FastPrimeSieve::new().primes().skip_while(move |&p| p < min).take_while(move |&p| p <= max)
+}
+
+/// The factor bases in the form the lattice sieve walks them.
+pub struct LatticeSieve {
+    rational: Vec<SieveEntry>,
+    algebraic: Vec<SieveEntry>,
+    algebraic_factor_base_max: u64,
+    rational_slack: u64,
+    algebraic_slack: u64,
+}
+
+impl LatticeSieve {
+    pub fn new(gnfs: &GNFS) -> Self {
+        LatticeSieve {
+            rational: log_sieve::sieve_entries(&gnfs.rational_factor_pair_collection),
+            algebraic: log_sieve::sieve_entries(&gnfs.algebraic_factor_pair_collection),
+            algebraic_factor_base_max: gnfs.prime_factor_base.algebraic_factor_base_max.to_u64().unwrap_or(u64::MAX),
+            rational_slack: log_sieve::threshold_slack(gnfs, &gnfs.prime_factor_base.rational_factor_base_max),
+            algebraic_slack: log_sieve::threshold_slack(gnfs, &gnfs.prime_factor_base.algebraic_factor_base_max),
+        }
+    }
+
+    /// The pairs (a, b), b > 0, of the special-q lattice whose sieved logarithms come within the
+    /// slack of the size of both norms.
+    pub fn candidates(&self, gnfs: &GNFS, special_q: &SpecialQ) -> Vec<(BigInt, BigInt)> {
+        let width = 2 * LATTICE_HALF_WIDTH as usize;
+        // A special-q inside the factor base is sieved with the rest; one above it is known to
+        // divide every algebraic norm.
+        let q_log = if special_q.q > self.algebraic_factor_base_max { log_sieve::scaled_log(special_q.q) } else { 0 };
+        let rational_roots = lattice_roots(&self.rational, special_q);
+        let algebraic_roots = lattice_roots(&self.algebraic, special_q);
+        let mut rational_logs = vec![0u8; width];
+        let mut algebraic_logs = vec![0u8; width];
+        let mut candidates = Vec::new();
+        for j in 1..=LATTICE_ROWS {
+            rational_logs.fill(0);
+            algebraic_logs.fill(q_log);
+            for root in &rational_roots {
+                sieve_row(&mut rational_logs, root, j as u64);
+            }
+            for root in &algebraic_roots {
+                sieve_row(&mut algebraic_logs, root, j as u64);
+            }
+
+            let (rational_bits, algebraic_bits) = row_norm_bits(gnfs, special_q, j);
+            let rational_threshold = log_sieve::threshold(rational_bits, self.rational_slack);
+            let algebraic_threshold = log_sieve::threshold(algebraic_bits, self.algebraic_slack);
+            for index in 0..width {
+                if rational_logs[index] >= rational_threshold && algebraic_logs[index] >= algebraic_threshold {
+                    let (a, b) = special_q.pair(index as i64 - LATTICE_HALF_WIDTH, j);
+                    if !b.is_zero() {
+                        candidates.push((a, b));
+                    }
+                }
+            }
+        }
+        candidates
+    }
+}
+
+/// Where a factor-base prime p divides the norms of one special-q's lattice. It divides the norm
+/// at (a, b) = i·v₁ + j·v₂ when p | a + b·r for an affine root, or p | b for the projective one;
+/// in lattice coordinates that is i·u + j·w ≡ 0 (mod p).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LatticeRoot {
+    p: u64,
+    log: u8,
+    step: RowStep,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowStep {
+    /// u ≢ 0: p divides the norm at i ≡ j·s (mod p) with s = -w/u.
+    Residue(u64),
+    /// u ≡ 0: p divides every norm of the rows with j·w ≡ 0 (mod p) and none of the others.
+    WholeRow(u64),
+}
+
+/// The roots of every entry in the special-q's lattice, worked out once per special-q so each row
+/// only has to scale them by j.
+fn lattice_roots(entries: &[SieveEntry], special_q: &SpecialQ) -> Vec<LatticeRoot> {
+    let [(a1, b1), (a2, b2)] = special_q.basis;
+    entries
+        .iter()
+        .map(|&(p, r, log)| {
+            let modulus = p as i128;
+            let (u, w) = if r == p {
+                ((b1 as i128).rem_euclid(modulus), (b2 as i128).rem_euclid(modulus))
+            } else {
+                ((a1 as i128 + b1 as i128 * r as i128).rem_euclid(modulus), (a2 as i128 + b2 as i128 * r as i128).rem_euclid(modulus))
+            };
+            let step = if u == 0 {
+                RowStep::WholeRow(w as u64)
+            } else {
+                RowStep::Residue((-w * inverse(u, modulus)).rem_euclid(modulus) as u64)
+            };
+            LatticeRoot { p, log, step }
+        })
+        .collect()
+}
+
+/// u⁻¹ mod p for u ≢ 0 and p prime, by the extended Euclidean algorithm.
+fn inverse(u: i128, p: i128) -> i128 {
+    let (mut old_r, mut r, mut old_s, mut s) = (u, p, 1i128, 0i128);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+    }
+    old_s.rem_euclid(p)
+}
+
+/// Adds the root's log to every cell of row j where its prime divides the norm.
+fn sieve_row(logs: &mut [u8], root: &LatticeRoot, j: u64) {
+    let p = root.p;
+    match root.step {
+        RowStep::WholeRow(w) => {
+            if (j as u128 * w as u128).is_multiple_of(p as u128) {
+                logs.iter_mut().for_each(|cell| *cell = cell.saturating_add(root.log));
+            }
+        }
+        RowStep::Residue(s) => {
+            let i = (j as u128 * s as u128 % p as u128) as u64;
+            let mut index = ((i + LATTICE_HALF_WIDTH as u64 % p) % p) as usize;
+            while index < logs.len() {
+                logs[index] = logs[index].saturating_add(root.log);
+                index += p as usize;
+            }
+        }
+    }
+}
+
+/// Bit lengths of the largest (rational, algebraic) norms at the ends and middle of row j,
+/// standing in for the size of every norm in the row.
+fn row_norm_bits(gnfs: &GNFS, special_q: &SpecialQ, j: i64) -> (u64, u64) {
+    [-LATTICE_HALF_WIDTH, 0, LATTICE_HALF_WIDTH - 1]
+        .into_iter()
+        .map(|i| {
+            let (a, b) = special_q.pair(i, j);
+            let (algebraic, rational) = Relation::norms(&gnfs.current_polynomial, &gnfs.rational_polynomial, &a, &b);
+            (rational.abs().bits(), algebraic.abs().bits())
+        })
+        .fold((0, 0), |(rational, algebraic), (r, a)| (rational.max(r), algebraic.max(a)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduced_basis_spans_the_special_q_lattice() {
+        let (q, r) = (10_007u64, 4_321u64);
+        let [(a1, b1), (a2, b2)] = reduce_basis(q, r);
+        assert_eq!((a1 as i128 * b2 as i128 - a2 as i128 * b1 as i128).abs(), q as i128);
+        for (a, b) in [(a1, b1), (a2, b2)] {
+            assert_eq!((a as i128 + b as i128 * r as i128).rem_euclid(q as i128), 0);
+        }
+        assert!(a1 * a1 + b1 * b1 <= 2 * q as i64);
+    }
+
+    #[test]
+    fn byte_logs_keep_every_smooth_pair_float_logs_find() {
+        use crate::core::cancellation_token::CancellationToken;
+        use crate::core::directory_location::DirectoryLocations;
+        use crate::core::gnfs_config::GnfsConfig;
+        use crate::integer_math::gcd::GCD;
+
+        let locations = DirectoryLocations::temporary("lattice_sieve").unwrap();
+        let n = BigInt::from(45113);
+        let gnfs = GNFS::with_save_locations(&CancellationToken::new(), locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let sieve = LatticeSieve::new(&gnfs);
+        // The exact logs of the factor-base primes dividing each norm, as a float sieve sums them.
+        let float_log = |entries: &[SieveEntry], a: &BigInt, b: &BigInt| -> f32 {
+            entries
+                .iter()
+                .filter(|&&(p, r, _)| if r == p { b.mod_floor(&BigInt::from(p)).is_zero() } else { (a + b * r).mod_floor(&BigInt::from(p)).is_zero() })
+                .map(|&(p, _, _)| (p as f32).log2())
+                .sum()
+        };
+
+        let (mut float_yield, mut byte_yield) = (0, 0);
+        for special_q in [41, 53].into_iter().flat_map(|q| SpecialQ::ideals(&gnfs.current_polynomial, q)) {
+            let candidates = sieve.candidates(&gnfs, &special_q);
+            for j in 1..=4 {
+                let (rational_bits, algebraic_bits) = row_norm_bits(&gnfs, &special_q, j);
+                for i in -LATTICE_HALF_WIDTH..LATTICE_HALF_WIDTH {
+                    let (a, b) = special_q.pair(i, j);
+                    if b.is_zero() || !GCD::are_coprime(&[a.clone(), b.clone()]) {
+                        continue;
+                    }
+                    let mut relation = Relation::new(&gnfs, &a, &b);
+                    relation.sieve(&gnfs);
+                    if !relation.is_smooth() {
+                        continue;
+                    }
+                    let float_passes = float_log(&sieve.rational, &a, &b) >= rational_bits as f32 - sieve.rational_slack as f32
+                        && float_log(&sieve.algebraic, &a, &b) >= algebraic_bits as f32 - sieve.algebraic_slack as f32;
+                    let byte_passes = candidates.contains(&(a.clone(), b.clone()));
+                    assert!(byte_passes || !float_passes, "smooth pair ({}, {}) was sieved out", a, b);
+                    float_yield += float_passes as usize;
+                    byte_yield += byte_passes as usize;
+                }
+            }
+        }
+        // Rounding up can only let more smooth pairs through.
+        assert!(float_yield > 0 && byte_yield >= float_yield);
+    }
+
+    #[test]
+    fn lattice_roots_mark_exactly_the_cells_the_prime_divides() {
+        let special_q = SpecialQ::new(10_007, 4_321);
+        let entries: Vec<SieveEntry> = [(3, 1), (7, 7), (101, 57), (257, 0), (10_007, 4_321)].into_iter().map(|(p, r)| (p, r, 1)).collect();
+        for root in lattice_roots(&entries, &special_q) {
+            let r = entries.iter().find(|entry| entry.0 == root.p).unwrap().1;
+            for j in 1..=12 {
+                let mut logs = vec![0u8; 2 * LATTICE_HALF_WIDTH as usize];
+                sieve_row(&mut logs, &root, j as u64);
+                for (index, &log) in logs.iter().enumerate() {
+                    let (a, b) = special_q.pair(index as i64 - LATTICE_HALF_WIDTH, j);
+                    let p = BigInt::from(root.p);
+                    let divides = if r == root.p { (&b % &p).is_zero() } else { ((&a + &b * r) % &p).is_zero() };
+                    assert_eq!(log == 1, divides, "p = {}, (a, b) = ({}, {})", root.p, a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lattice_relations_verify_under_their_original_pairs() {
+        use crate::core::cancellation_token::CancellationToken;
+        use crate::core::directory_location::DirectoryLocations;
+        use crate::core::gnfs_config::GnfsConfig;
+        use crate::integer_math::gcd::GCD;
+        use crate::relation_sieve::verifier;
+
+        let locations = DirectoryLocations::temporary("lattice_relations").unwrap();
+        let n = BigInt::from(45113);
+        let gnfs = GNFS::with_save_locations(&CancellationToken::new(), locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let sieve = LatticeSieve::new(&gnfs);
+        let (q_min, q_max) = gnfs.config.special_q_range(sieve.algebraic_factor_base_max);
+        let mut smooth = 0;
+        for special_q in special_q_primes(q_min, q_max).take(4).flat_map(|q| SpecialQ::ideals(&gnfs.current_polynomial, q)) {
+            for (a, b) in sieve.candidates(&gnfs, &special_q) {
+                // Every candidate lies on the special-q lattice, so q divides its algebraic norm.
+                assert!(((&a + &b * special_q.r) % special_q.q).is_zero());
+                if !GCD::are_coprime(&[a.clone(), b.clone()]) {
+                    continue;
+                }
+                let mut relation = Relation::new(&gnfs, &a, &b);
+                relation.sieve(&gnfs);
+                assert!((&relation.algebraic_norm % special_q.q).is_zero());
+                if relation.is_smooth() {
+                    assert_eq!(verifier::check(&gnfs, &relation, true), Ok(()), "({}, {})", a, b);
+                    smooth += 1;
+                }
+            }
+        }
+        assert!(smooth > 0);
+    }
+}
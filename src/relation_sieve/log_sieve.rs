@@ -0,0 +1,531 @@
+// src/relation_sieve/log_sieve.rs
+
+//! Line sieving with logarithms. Along a row of fixed b, a factor-base prime p divides the
+//! rational norm a + b·m exactly when a ≡ -b·m (mod p), and the algebraic norm through the ideal
+//! (p, r) exactly when a ≡ -b·r (mod p), so each prime hits one residue class of a. Rather than
+//! trial-dividing every pair, log₂ p is added to the cells of those classes, and only the pairs
+//! whose sums come close to the size of both norms are factored. Cells are single bytes holding
+//! whole bits, see [`scaled_log`], so an L1-sized block holds four times the cells it would as
+//! floats and the threshold pass compares bytes. The row is split into regions
+//! sized to L2, sieved in parallel, and each region a block at a time, each block small enough to
+//! stay in L1. Primes shorter than a block step through it directly; the longer ones hit a block
+//! at most once, so their (position, log) hits are sorted into per-block buckets for the whole
+//! region up front, and each block then applies its own bucket. The powers p^k of the small
+//! primes are sieved as entries of their own, through the roots of p lifted to p^k, each adding
+//! log p once more, so a norm divisible by p^k collects k·log p.
+//!
+//! With the `gpu` feature and `sieve_device = "gpu"`, the sums and the threshold scan run on the
+//! GPU instead, see [`GpuLineSieve`](crate::backends::gpu::GpuLineSieve); a row it fails on is
+//! sieved here.
+
+use log::warn;
+use num::{BigInt, Integer, Signed, ToPrimitive, Zero};
+use rayon::prelude::*;
+#[cfg(feature = "gpu")]
+use std::sync::{Mutex, PoisonError};
+#[cfg(feature = "gpu")]
+use crate::backends::gpu::GpuLineSieve;
+use crate::core::gnfs::GNFS;
+use crate::core::sieve_tuning::SieveTuning;
+use crate::factor::factor_pair_collection::FactorPairCollection;
+use crate::polynomial::polynomial::Polynomial;
+use crate::relation_sieve::relation::Relation;
+use crate::relation_sieve::SieveDevice;
+
+/// Bits a cell may fall short of its norm's size and still be factored, covering the rounding of
+/// the block's norm size and the powers left unsieved: squares of the larger primes and powers of
+/// roots that don't lift.
+pub(crate) const LOG_SLACK_BITS: u64 = 4;
+/// Primes below this have their powers sieved. Larger ones seldom divide a norm twice, and
+/// their squares would add an entry per prime.
+const PRIME_POWER_PRIME_BOUND: u64 = 1 << 10;
+/// Largest prime power sieved, beyond the norms of any row the line sieve can index cheaply.
+const PRIME_POWER_BOUND: u64 = 1 << 40;
+
+/// Factor-base entries as (p, r, scaled log p); r = p marks a projective root.
+pub(crate) type SieveEntry = (u64, u64, u8);
+
+pub(crate) fn sieve_entries(pairs: &FactorPairCollection) -> Vec<SieveEntry> {
+    pairs.0.iter().map(|pair| (pair.p, pair.r, scaled_log(pair.p))).collect()
+}
+
+/// Entries for the powers p^k ≤ `power_bound`, k ≥ 2, of the affine entries of `polynomial`
+/// with p < `prime_bound`, each adding log p again at the cells p^k divides. The roots are
+/// lifted by Hensel's lemma; a root where the derivative vanishes mod p doesn't lift to a single
+/// root and is skipped.
+pub(crate) fn prime_power_entries(entries: &[SieveEntry], polynomial: &Polynomial, prime_bound: u64, power_bound: u64) -> Vec<SieveEntry> {
+    let derivative = polynomial.derivative();
+    let mut powers = Vec::new();
+    for &(p, r, log) in entries.iter().filter(|&&(p, r, _)| r != p && p < prime_bound) {
+        let prime = BigInt::from(p);
+        let slope = derivative.evaluate(&BigInt::from(r)).mod_floor(&prime);
+        if slope.is_zero() {
+            continue;
+        }
+        let inverse = slope.modpow(&(&prime - 2), &prime);
+        let (mut power, mut root) = (prime.clone(), BigInt::from(r));
+        while let Some(next) = (&power * p).to_u64().filter(|&next| next <= power_bound) {
+            // ƒ(r + t·p^k) ≡ ƒ(r) + t·p^k·ƒ'(r) (mod p^(k+1)), which t cancels.
+            let t = (-(polynomial.evaluate(&root) / &power) * &inverse).mod_floor(&prime);
+            root += t * &power;
+            power *= p;
+            powers.push((next, root.to_u64().unwrap_or_default(), log));
+        }
+    }
+    powers
+}
+
+/// ⌈log₂ p⌉, the log a sieve cell gains from p. Rounding up means a cell's sum is never below
+/// the exact sum of its logs, and [`threshold`] rounds down, so every cell the exact logs would
+/// pass still passes; the rounding only lets a few more through to be factored.
+pub(crate) fn scaled_log(p: u64) -> u8 {
+    (u64::BITS - p.saturating_sub(1).leading_zeros()) as u8
+}
+
+/// The least cell sum worth factoring for norms of `norm_bits` bits. Cells add with saturation,
+/// so a threshold capped at 255 is still met by every cell whose exact sum reaches the uncapped one.
+pub(crate) fn threshold(norm_bits: u64, slack: u64) -> u8 {
+    norm_bits.saturating_sub(slack).min(u8::MAX as u64) as u8
+}
+/// Bits below a norm's size at which a cell still passes, allowing for a large prime left over
+/// when relations with one are kept.
+pub(crate) fn threshold_slack(gnfs: &GNFS, factor_base_max: &BigInt) -> u64 {
+    let large_prime_bits = if gnfs.config.large_prime_recycling() {
+        (factor_base_max * gnfs.config.large_prime_multiplier()).bits()
+    } else {
+        0
+    };
+    LOG_SLACK_BITS + large_prime_bits
+}
+
+const RATIONAL: u8 = 1;
+const ALGEBRAIC: u8 = 2;
+
+/// The factor bases in the form the line sieve walks them, with the block layout from
+/// [`SieveTuning`].
+pub struct LineSieve {
+    rational: SieveSide,
+    algebraic: SieveSide,
+    /// Cells per block.
+    block_cells: usize,
+    /// Blocks per region, the unit of work of one thread.
+    region_blocks: usize,
+    /// Both factor bases on the GPU, when sieving there.
+    #[cfg(feature = "gpu")]
+    gpu: Option<Mutex<GpuLineSieve>>,
+}
+
+struct SieveSide {
+    /// Sorted by p, which for a prime power entry is p^k.
+    entries: Vec<SieveEntry>,
+    /// Entries before this index have p below the block length and are sieved directly.
+    small: usize,
+    slack: u64,
+}
+
+impl LineSieve {
+    pub fn new(gnfs: &GNFS, tuning: &SieveTuning) -> Self {
+        let block_cells = tuning.block_length.max(1);
+        let side = |pairs: &FactorPairCollection, polynomial: &Polynomial, max: &BigInt| {
+            let mut entries = sieve_entries(pairs);
+            entries.extend(prime_power_entries(&entries, polynomial, PRIME_POWER_PRIME_BOUND, PRIME_POWER_BOUND));
+            entries.sort_by_key(|&(p, r, _)| (p, r));
+            let small = entries.partition_point(|&(p, _, _)| (p as usize) < block_cells);
+            SieveSide { entries, small, slack: threshold_slack(gnfs, max) }
+        };
+        let base = &gnfs.prime_factor_base;
+        let rational = side(&gnfs.rational_factor_pair_collection, &gnfs.rational_polynomial, &base.rational_factor_base_max);
+        let algebraic = side(&gnfs.algebraic_factor_pair_collection, &gnfs.current_polynomial, &base.algebraic_factor_base_max);
+        let on_gpu = gnfs.config.sieve_device() == SieveDevice::Gpu;
+        #[cfg(feature = "gpu")]
+        let gpu = on_gpu
+            .then(|| GpuLineSieve::new(&rational.entries, &algebraic.entries))
+            .and_then(|gpu| gpu.map_err(|e| warn!("Sieving on the CPU, the GPU is unavailable: {}", e)).ok())
+            .map(Mutex::new);
+        #[cfg(not(feature = "gpu"))]
+        if on_gpu {
+            warn!("Sieving on the CPU, this build has no GPU support; rebuild with --features gpu.");
+        }
+        LineSieve {
+            rational,
+            algebraic,
+            block_cells,
+            region_blocks: tuning.bucket_count.max(1),
+            #[cfg(feature = "gpu")]
+            gpu,
+        }
+    }
+
+    /// Sieves row b over -max_abs_a ≤ a ≤ max_abs_a. Returns `None` when the row is too wide to
+    /// index, in which case every pair has to be factored.
+    pub fn row(&self, gnfs: &GNFS, b: &BigInt, max_abs_a: &BigInt) -> Option<RowCandidates> {
+        let max = max_abs_a.to_i64().filter(|max| (0..i64::MAX / 2).contains(max))?;
+        let width = usize::try_from(2 * max + 1).ok()?;
+        #[cfg(feature = "gpu")]
+        if let Some(gpu) = &self.gpu {
+            let thresholds = self.block_thresholds(gnfs, b, max, width);
+            match gpu.lock().unwrap_or_else(PoisonError::into_inner).row(b, max, self.block_cells, &thresholds) {
+                Ok(flags) => return Some(RowCandidates { max, flags }),
+                Err(e) => warn!("Sieving row b = {} on the CPU: {}", b, e),
+            }
+        }
+        let rational = SideRow::start(&self.rational, b, max);
+        let algebraic = SideRow::start(&self.algebraic, b, max);
+        let mut flags = vec![0u8; width];
+
+        let region_cells = self.block_cells.saturating_mul(self.region_blocks);
+        flags.par_chunks_mut(region_cells).enumerate().for_each(|(region, flags)| {
+            let region_start = region * region_cells;
+            let region_end = region_start + flags.len();
+            let mut rational = rational.at(region_start);
+            let mut algebraic = algebraic.at(region_start);
+            let rational_buckets = rational.fill_buckets(region_start, region_end, self.block_cells);
+            let algebraic_buckets = algebraic.fill_buckets(region_start, region_end, self.block_cells);
+            let mut rational_logs = vec![0u8; self.block_cells];
+            let mut algebraic_logs = vec![0u8; self.block_cells];
+
+            for (block, block_start) in (region_start..region_end).step_by(self.block_cells).enumerate() {
+                let block_end = region_end.min(block_start + self.block_cells);
+                let cells = block_end - block_start;
+                rational.sieve_block(&mut rational_logs[..cells], block_start, &rational_buckets[block]);
+                algebraic.sieve_block(&mut algebraic_logs[..cells], block_start, &algebraic_buckets[block]);
+
+                let (rational_bits, algebraic_bits) = block_norm_bits(gnfs, b, max, block_start, block_end);
+                let rational_threshold = threshold(rational_bits, self.rational.slack);
+                let algebraic_threshold = threshold(algebraic_bits, self.algebraic.slack);
+                let block_flags = &mut flags[block_start - region_start..block_end - region_start];
+                for (index, flag) in block_flags.iter_mut().enumerate() {
+                    if rational_logs[index] >= rational_threshold {
+                        *flag |= RATIONAL;
+                    }
+                    if algebraic_logs[index] >= algebraic_threshold {
+                        *flag |= ALGEBRAIC;
+                    }
+                }
+            }
+        });
+        Some(RowCandidates { max, flags })
+    }
+
+    /// The (rational, algebraic) threshold of each block of row b, as the GPU compares them.
+    #[cfg(feature = "gpu")]
+    fn block_thresholds(&self, gnfs: &GNFS, b: &BigInt, max: i64, width: usize) -> Vec<(u8, u8)> {
+        (0..width)
+            .into_par_iter()
+            .step_by(self.block_cells)
+            .map(|block_start| {
+                let (rational_bits, algebraic_bits) = block_norm_bits(gnfs, b, max, block_start, width.min(block_start + self.block_cells));
+                (threshold(rational_bits, self.rational.slack), threshold(algebraic_bits, self.algebraic.slack))
+            })
+            .collect()
+    }
+}
+
+/// One side's sieve state along a row: the next cell each entry hits, and the log of the
+/// projective primes dividing b, which hit every cell.
+struct SideRow<'a> {
+    side: &'a SieveSide,
+    next: Vec<usize>,
+    constant: u8,
+}
+
+impl<'a> SideRow<'a> {
+    fn start(side: &'a SieveSide, b: &BigInt, max: i64) -> Self {
+        let mut constant = 0u8;
+        let next = side
+            .entries
+            .iter()
+            .map(|&(p, r, log)| {
+                let b = b.mod_floor(&BigInt::from(p)).to_u128().unwrap_or(0);
+                if r == p {
+                    if b == 0 {
+                        constant = constant.saturating_add(log);
+                    }
+                    return usize::MAX;
+                }
+                // The first cell at or after a = -max with a ≡ -b·r (mod p).
+                let (p, r) = (p as u128, r as u128);
+                let a = (p - b * r % p) % p;
+                ((a + max as u128) % p) as usize
+            })
+            .collect();
+        SideRow { side, next, constant }
+    }
+
+    /// The same row positioned at the first hit of each entry at or after `cell`, so a region
+    /// starting there can be sieved on its own.
+    fn at(&self, cell: usize) -> Self {
+        let next = self
+            .side
+            .entries
+            .iter()
+            .zip(&self.next)
+            .map(|(&(p, _, _), &next)| if next >= cell { next } else { next + (cell - next).div_ceil(p as usize) * p as usize })
+            .collect();
+        SideRow { side: self.side, next, constant: self.constant }
+    }
+
+    /// The hits of the large primes in cells [region_start, region_end), as (offset, log) per
+    /// block.
+    fn fill_buckets(&mut self, region_start: usize, region_end: usize, block_cells: usize) -> Vec<Vec<(u32, u8)>> {
+        let mut buckets = vec![Vec::new(); (region_end - region_start).div_ceil(block_cells)];
+        for (entry, next) in self.side.entries.iter().zip(self.next.iter_mut()).skip(self.side.small) {
+            let (p, _, log) = *entry;
+            while *next < region_end {
+                let offset = *next - region_start;
+                buckets[offset / block_cells].push(((offset % block_cells) as u32, log));
+                *next += p as usize;
+            }
+        }
+        buckets
+    }
+
+    /// Sums the logs of the block starting at cell `block_start` into `logs`.
+    fn sieve_block(&mut self, logs: &mut [u8], block_start: usize, bucket: &[(u32, u8)]) {
+        logs.fill(self.constant);
+        let block_end = block_start + logs.len();
+        for (entry, next) in self.side.entries[..self.side.small].iter().zip(self.next.iter_mut()) {
+            let (p, _, log) = *entry;
+            while *next < block_end {
+                let cell = &mut logs[*next - block_start];
+                *cell = cell.saturating_add(log);
+                *next += p as usize;
+            }
+        }
+        for &(offset, log) in bucket {
+            let cell = &mut logs[offset as usize];
+            *cell = cell.saturating_add(log);
+        }
+    }
+}
+
+/// Bit lengths of the smallest (rational, algebraic) norms at the ends and middle of a block,
+/// standing in for the size of every norm in it.
+fn block_norm_bits(gnfs: &GNFS, b: &BigInt, max: i64, block_start: usize, block_end: usize) -> (u64, u64) {
+    [block_start, (block_start + block_end) / 2, block_end - 1]
+        .into_iter()
+        .map(|cell| {
+            let a = BigInt::from(cell as i64 - max);
+            let (algebraic, rational) = Relation::norms(&gnfs.current_polynomial, &gnfs.rational_polynomial, &a, b);
+            (rational.abs().bits(), algebraic.abs().bits())
+        })
+        .fold((u64::MAX, u64::MAX), |(rational, algebraic), (r, a)| (rational.min(r), algebraic.min(a)))
+}
+
+/// The outcome of sieving one row: which cells passed on each side.
+pub struct RowCandidates {
+    max: i64,
+    flags: Vec<u8>,
+}
+
+impl RowCandidates {
+    /// Whether (a, b) is worth factoring: both sides passed, or just the rational side when
+    /// `rational_only`, as for pairs tried on companion polynomials whose algebraic side this row
+    /// never sieved.
+    pub fn passes(&self, a: &BigInt, rational_only: bool) -> bool {
+        let wanted = if rational_only { RATIONAL } else { RATIONAL | ALGEBRAIC };
+        a.to_i64()
+            .and_then(|a| usize::try_from(a + self.max).ok())
+            .and_then(|cell| self.flags.get(cell))
+            .is_some_and(|&flag| flag & wanted == wanted)
+    }
+
+    /// Cells that passed on both sides.
+    pub fn len(&self) -> usize {
+        self.flags.iter().filter(|&&flag| flag == RATIONAL | ALGEBRAIC).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_row_hits_the_residue_class_of_each_root() {
+        let side = SieveSide { entries: vec![(5, 3, 1), (7, 7, 2), (11, 4, 3)], small: 1, slack: 0 };
+        let (b, max) = (BigInt::from(2), 20i64);
+        let mut row = SideRow::start(&side, &b, max);
+        let width = (2 * max + 1) as usize;
+        let buckets = row.fill_buckets(0, width, 16);
+        let mut logs = Vec::new();
+        for (block, bucket) in buckets.iter().enumerate() {
+            let mut block_logs = vec![0u8; 16.min(width - block * 16)];
+            row.sieve_block(&mut block_logs, block * 16, bucket);
+            logs.extend(block_logs);
+        }
+        for (cell, &log) in logs.iter().enumerate() {
+            let a = cell as i64 - max;
+            let expected = [(5, 3, 1), (11, 4, 3)]
+                .into_iter()
+                .filter(|&(p, r, _)| (a + 2 * r).rem_euclid(p) == 0)
+                .map(|(_, _, log)| log)
+                .sum::<u8>();
+            assert_eq!(log, expected, "a = {}", a);
+        }
+    }
+
+    #[test]
+    fn regions_sieved_apart_match_the_whole_row() {
+        let side = SieveSide { entries: vec![(3, 1, 1), (5, 5, 2), (13, 6, 3), (29, 11, 4)], small: 2, slack: 0 };
+        let (b, max) = (BigInt::from(5), 50i64);
+        let width = (2 * max + 1) as usize;
+        let sieve = |row: &mut SideRow, start: usize, end: usize| {
+            let buckets = row.fill_buckets(start, end, 8);
+            (start..end).step_by(8).zip(&buckets).flat_map(|(block_start, bucket)| {
+                let mut logs = vec![0u8; 8.min(end - block_start)];
+                row.sieve_block(&mut logs, block_start, bucket);
+                logs
+            }).collect::<Vec<u8>>()
+        };
+        let row = SideRow::start(&side, &b, max);
+        let whole = sieve(&mut row.at(0), 0, width);
+        let regions: Vec<u8> = (0..width).step_by(24).flat_map(|start| sieve(&mut row.at(start), start, width.min(start + 24))).collect();
+        assert_eq!(regions, whole);
+    }
+
+    #[test]
+    fn scaled_logs_round_up() {
+        assert_eq!([2, 3, 4, 5, 1023, 1024, 1025].map(scaled_log), [1, 2, 2, 3, 10, 10, 11]);
+        assert_eq!((threshold(30, 4), threshold(3, 4), threshold(400, 4)), (26, 0, 255));
+    }
+
+    #[test]
+    fn every_smooth_pair_passes_the_row_sieve() {
+        use crate::core::cancellation_token::CancellationToken;
+        use crate::core::directory_location::DirectoryLocations;
+        use crate::core::gnfs_config::GnfsConfig;
+        use crate::integer_math::gcd::GCD;
+
+        let locations = DirectoryLocations::temporary("log_sieve").unwrap();
+        let n = BigInt::from(45113);
+        let gnfs = GNFS::with_save_locations(&CancellationToken::new(), locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let sieve = LineSieve::new(&gnfs, &SieveTuning { block_length: 64, bucket_count: 2, ..SieveTuning::default() });
+
+        let mut smooth = 0;
+        for b in (1..=8).map(BigInt::from) {
+            let candidates = sieve.row(&gnfs, &b, &BigInt::from(200)).unwrap();
+            for a in (-200..=200).map(BigInt::from).filter(|a| GCD::are_coprime(&[a.clone(), b.clone()])) {
+                let mut relation = Relation::new(&gnfs, &a, &b);
+                relation.sieve(&gnfs);
+                if relation.is_smooth() {
+                    smooth += 1;
+                    assert!(candidates.passes(&a, false), "smooth pair ({}, {}) was sieved out", a, b);
+                }
+            }
+        }
+        assert!(smooth > 0);
+    }
+
+    #[test]
+    fn a_sieve_set_to_the_gpu_finds_the_cpu_candidates() {
+        use crate::core::cancellation_token::CancellationToken;
+        use crate::core::directory_location::DirectoryLocations;
+        use crate::core::gnfs_config::GnfsConfig;
+
+        // Without the gpu feature or a usable device the sieve stays on the CPU; with both, the
+        // GPU's flags must be the same.
+        let locations = DirectoryLocations::temporary("gpu_fallback").unwrap();
+        let n = BigInt::from(45113);
+        let mut gnfs = GNFS::with_save_locations(&CancellationToken::new(), locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let tuning = SieveTuning { block_length: 64, bucket_count: 2, ..SieveTuning::default() };
+        let cpu = LineSieve::new(&gnfs, &tuning);
+        gnfs.config.sieve_device = Some(SieveDevice::Gpu);
+        let gpu = LineSieve::new(&gnfs, &tuning);
+        for b in (1..=8).map(BigInt::from) {
+            let max = BigInt::from(200);
+            assert_eq!(gpu.row(&gnfs, &b, &max).unwrap().flags, cpu.row(&gnfs, &b, &max).unwrap().flags, "b = {}", b);
+        }
+    }
+
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn gpu_rows_match_the_cpu_sieve() {
+        use crate::core::cancellation_token::CancellationToken;
+        use crate::core::directory_location::DirectoryLocations;
+        use crate::core::gnfs_config::GnfsConfig;
+        use crate::polynomial::polynomial::Polynomial;
+
+        let token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("gpu_sieve").unwrap();
+        let n = BigInt::from(45113);
+        let config = GnfsConfig { sieve_device: Some(SieveDevice::Gpu), ..GnfsConfig::default() };
+        let mut gnfs = GNFS::with_save_locations(&token, locations, config, &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let tuning = SieveTuning { block_length: 64, bucket_count: 2, ..SieveTuning::default() };
+        // A monic polynomial, then one with projective roots, whose logs the GPU adds on the host.
+        for (polynomial, m) in [(None, 31), (Some("2X^3 + X^2 + 15X + 5"), 28)] {
+            if let Some(polynomial) = polynomial {
+                gnfs.set_polynomial_pair(Polynomial::parse(polynomial), BigInt::from(m));
+                gnfs.rebuild_factor_bases(&token, &BigInt::from(100));
+            }
+            gnfs.config.sieve_device = Some(SieveDevice::Cpu);
+            let cpu = LineSieve::new(&gnfs, &tuning);
+            gnfs.config.sieve_device = Some(SieveDevice::Gpu);
+            let sieve = LineSieve::new(&gnfs, &tuning);
+            let Some(gpu) = &sieve.gpu else {
+                eprintln!("No usable OpenCL GPU; the CPU equivalence of the GPU sieve was not checked.");
+                return;
+            };
+            let max = 300i64;
+            let width = (2 * max + 1) as usize;
+            for b in (1..=24).map(BigInt::from) {
+                let thresholds = sieve.block_thresholds(&gnfs, &b, max, width);
+                let on_gpu = gpu.lock().unwrap().row(&b, max, sieve.block_cells, &thresholds).unwrap();
+                let on_cpu = cpu.row(&gnfs, &b, &BigInt::from(max)).unwrap().flags;
+                assert_eq!(on_gpu, on_cpu, "b = {}", b);
+            }
+        }
+    }
+
+    #[test]
+    fn prime_power_roots_are_lifted_from_simple_roots_only() {
+        // ƒ = (X - 3)²(X - 4): 3 is a double root mod 5, so only 4 lifts, and 13 is past the
+        // prime bound. The projective 7 has no root to lift.
+        let f = Polynomial::parse("X^3 - 10X^2 + 33X - 36");
+        let entries = vec![(2, 0, 1), (5, 3, 3), (5, 4, 3), (7, 7, 3), (13, 3, 4)];
+        let powers = prime_power_entries(&entries, &f, 10, 130);
+        assert_eq!(powers.iter().map(|&(q, _, log)| (q, log)).collect::<Vec<_>>(), [(4, 1), (8, 1), (16, 1), (32, 1), (64, 1), (128, 1), (25, 3), (125, 3)]);
+        for &(q, r, _) in &powers {
+            assert!((f.evaluate(&BigInt::from(r)) % q).is_zero(), "{} is not a root of ƒ mod {}", r, q);
+        }
+    }
+
+    #[test]
+    fn pairs_divisible_by_high_prime_powers_pass_without_large_prime_slack() {
+        use crate::core::cancellation_token::CancellationToken;
+        use crate::core::directory_location::DirectoryLocations;
+        use crate::core::gnfs_config::GnfsConfig;
+        use crate::integer_math::gcd::GCD;
+
+        // Without large primes the only slack is LOG_SLACK_BITS, which the powers below exceed.
+        let locations = DirectoryLocations::temporary("prime_powers").unwrap();
+        let config = GnfsConfig { large_prime_recycling: Some(false), ..GnfsConfig::default() };
+        let n = BigInt::from(45113);
+        let gnfs = GNFS::with_save_locations(&CancellationToken::new(), locations, config, &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let sieve = LineSieve::new(&gnfs, &SieveTuning { block_length: 64, bucket_count: 2, ..SieveTuning::default() });
+        let excess_bits = |relation: &Relation| {
+            [&relation.rational_factorization, &relation.algebraic_factorization]
+                .into_iter()
+                .flat_map(|factorization| factorization.iter().map(|(p, k)| (k as u64 - 1) * scaled_log(p.to_u64().unwrap()) as u64).collect::<Vec<_>>())
+                .max()
+                .unwrap_or(0)
+        };
+
+        let mut high_powers = 0;
+        for b in (1..=12).map(BigInt::from) {
+            let candidates = sieve.row(&gnfs, &b, &BigInt::from(200)).unwrap();
+            for a in (-200..=200).map(BigInt::from).filter(|a| GCD::are_coprime(&[a.clone(), b.clone()])) {
+                let mut relation = Relation::new(&gnfs, &a, &b);
+                relation.sieve(&gnfs);
+                if relation.is_smooth() && excess_bits(&relation) > LOG_SLACK_BITS {
+                    high_powers += 1;
+                    assert!(candidates.passes(&a, false), "({}, {}) with {} and {} was sieved out", a, b, relation.rational_factorization.to_string(), relation.algebraic_factorization.to_string());
+                }
+            }
+        }
+        assert!(high_powers > 0);
+    }
+}
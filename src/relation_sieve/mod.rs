@@ -2,4 +2,96 @@
 
 pub mod relation;
 pub mod relation_container;
-pub mod poly_relations_sieve_progress;
\ No newline at end of file
+pub mod partial_relation;
+pub mod cycle_finder;
+pub mod poly_relations_sieve_progress;
+pub mod bound_calibration;
+pub mod relation_store;
+pub mod compression;
+pub mod storage;
+pub mod row_norms;
+pub mod log_sieve;
+pub mod rough_store;
+pub mod yield_prediction;
+pub mod lattice_sieve;
+pub mod verifier;
+pub mod filter;
+
+use std::fmt::Display;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::core::error::GnfsError;
+
+/// How the relation sieve walks the (a, b) plane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SieveStrategy {
+    /// Row by row over b, every a in the value range.
+    #[default]
+    Line,
+    /// One special-q lattice at a time over the configured q range, then the line sieve once the
+    /// range is used up.
+    Lattice,
+}
+
+impl SieveStrategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SieveStrategy::Line => "line",
+            SieveStrategy::Lattice => "lattice",
+        }
+    }
+}
+
+impl Display for SieveStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SieveStrategy {
+    type Err = GnfsError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        [SieveStrategy::Line, SieveStrategy::Lattice]
+            .into_iter()
+            .find(|strategy| strategy.name() == input)
+            .ok_or_else(|| GnfsError::InvalidParameter(format!("unknown sieve strategy {:?}; expected line or lattice", input)))
+    }
+}
+
+/// Where the line sieve sums its logarithms and scans for candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SieveDevice {
+    #[default]
+    Cpu,
+    /// An OpenCL GPU, when built with the `gpu` feature; the CPU still factors the candidates.
+    Gpu,
+}
+
+impl SieveDevice {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SieveDevice::Cpu => "cpu",
+            SieveDevice::Gpu => "gpu",
+        }
+    }
+}
+
+impl Display for SieveDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SieveDevice {
+    type Err = GnfsError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        [SieveDevice::Cpu, SieveDevice::Gpu]
+            .into_iter()
+            .find(|device| device.name() == input)
+            .ok_or_else(|| GnfsError::InvalidParameter(format!("unknown sieve device {:?}; expected cpu or gpu", input)))
+    }
+}
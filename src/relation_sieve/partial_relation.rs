@@ -0,0 +1,184 @@
+// src/relation_sieve/partial_relation.rs
+
+//! Single large prime relations. A relation whose norms factor over the factor bases except for
+//! one prime above the bound on one side is a partial relation. Two partials sharing that prime
+//! multiply to a product in which it appears squared, so the pair stands in for one full relation
+//! in the matrix. Partials are keyed by their [`LargePrime`], the prime together with the ideal
+//! it names on the algebraic side, and matched through a hash map.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use num::{BigInt, Integer, One, Zero};
+use crate::core::gnfs::GNFS;
+use crate::integer_math::factorization_factory::FactorizationFactory;
+use crate::relation_sieve::relation::Relation;
+
+/// Large primes are kept up to this multiple of the factor base bound when no multiplier is
+/// configured.
+pub const DEFAULT_LARGE_PRIME_MULTIPLIER: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LargePrimeSide {
+    Rational,
+    Algebraic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LargePrime {
+    pub side: LargePrimeSide,
+    pub prime: BigInt,
+    /// r ≡ -a·b⁻¹ (mod p) for an algebraic prime, or p itself for the projective ideal when p
+    /// divides b. Two algebraic partials only cancel when they lie over the same ideal. Always 0
+    /// on the rational side, where p has just the one.
+    pub root: BigInt,
+}
+
+impl LargePrime {
+    /// The leftover quotient of a relation that is smooth on exactly one side. Whether the
+    /// quotient is prime and within the bound is left to [`PartialRelation::large_prime`].
+    pub fn of(relation: &Relation) -> Option<LargePrime> {
+        match (relation.is_rational_quotient_smooth(), relation.is_algebraic_quotient_smooth()) {
+            (true, false) => Self::on_side(relation, LargePrimeSide::Algebraic),
+            (false, true) => Self::on_side(relation, LargePrimeSide::Rational),
+            _ => None,
+        }
+    }
+
+    /// The leftover quotient on every side that isn't smooth: one large prime for a partial
+    /// relation, two for one with a large prime on each side.
+    pub fn all_of(relation: &Relation) -> Vec<LargePrime> {
+        [LargePrimeSide::Rational, LargePrimeSide::Algebraic].into_iter().filter_map(|side| Self::on_side(relation, side)).collect()
+    }
+
+    /// The leftover quotient on `side`, unless that side is smooth.
+    pub fn on_side(relation: &Relation, side: LargePrimeSide) -> Option<LargePrime> {
+        let prime = match side {
+            LargePrimeSide::Rational => &relation.rational_quotient,
+            LargePrimeSide::Algebraic => &relation.algebraic_quotient,
+        };
+        if prime.is_zero() || prime.is_one() {
+            return None;
+        }
+        let root = match side {
+            LargePrimeSide::Rational => BigInt::zero(),
+            LargePrimeSide::Algebraic => {
+                let b = relation.b.mod_floor(prime);
+                if b.is_zero() {
+                    prime.clone()
+                } else {
+                    (-&relation.a * b.modpow(&(prime - 2), prime)).mod_floor(prime)
+                }
+            }
+        };
+        Some(LargePrime { side, prime: prime.clone(), root })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PartialRelation {
+    pub relation: Relation,
+    pub large_prime: LargePrime,
+}
+
+impl PartialRelation {
+    /// Wraps `relation` when its one unsmooth quotient is a prime no larger than the configured
+    /// multiple of that side's factor base bound.
+    pub fn new(gnfs: &GNFS, relation: Relation) -> Option<PartialRelation> {
+        let large_prime = Self::large_prime(gnfs, &relation)?;
+        Some(PartialRelation { relation, large_prime })
+    }
+
+    pub fn large_prime(gnfs: &GNFS, relation: &Relation) -> Option<LargePrime> {
+        LargePrime::of(relation).filter(|large_prime| Self::is_within_bound(gnfs, large_prime))
+    }
+
+    /// The large primes of a relation left with one on each side, both checked as in
+    /// [`large_prime`](Self::large_prime). Such relations only combine through the
+    /// [`CycleFinder`](crate::relation_sieve::cycle_finder::CycleFinder).
+    pub fn large_prime_pair(gnfs: &GNFS, relation: &Relation) -> Option<(LargePrime, LargePrime)> {
+        let rational = LargePrime::on_side(relation, LargePrimeSide::Rational)?;
+        let algebraic = LargePrime::on_side(relation, LargePrimeSide::Algebraic)?;
+        (Self::is_within_bound(gnfs, &rational) && Self::is_within_bound(gnfs, &algebraic)).then_some((rational, algebraic))
+    }
+
+    fn is_within_bound(gnfs: &GNFS, large_prime: &LargePrime) -> bool {
+        let max = match large_prime.side {
+            LargePrimeSide::Rational => &gnfs.prime_factor_base.rational_factor_base_max,
+            LargePrimeSide::Algebraic => &gnfs.prime_factor_base.algebraic_factor_base_max,
+        };
+        if large_prime.prime > max * gnfs.config.large_prime_multiplier() {
+            return false;
+        }
+        // Every factor-base prime has been divided out, so a quotient below max² is prime.
+        large_prime.prime < max * max || FactorizationFactory::is_probable_prime(&large_prime.prime)
+    }
+}
+
+/// Partials waiting for a partner, keyed by large prime. The first partial seen with a prime stays
+/// as its representative and pairs with each later one, so k partials sharing a prime give k - 1
+/// combined relations.
+#[derive(Debug, Clone, Default)]
+pub struct PartialRelationStore {
+    waiting: HashMap<LargePrime, Relation>,
+}
+
+impl PartialRelationStore {
+    pub fn new() -> Self {
+        PartialRelationStore::default()
+    }
+
+    /// Adds `partial`, returning it paired with the waiting partial that shares its large prime.
+    /// A partial with no partner yet is kept; a repeat of the waiting one is dropped.
+    pub fn insert(&mut self, partial: PartialRelation) -> Option<Vec<Relation>> {
+        match self.waiting.entry(partial.large_prime) {
+            Entry::Occupied(waiting) if *waiting.get() != partial.relation => Some(vec![waiting.get().clone(), partial.relation]),
+            Entry::Occupied(_) => None,
+            Entry::Vacant(slot) => {
+                slot.insert(partial.relation);
+                None
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+
+    /// The partials still waiting, in (a, b) order.
+    pub fn into_relations(self) -> Vec<Relation> {
+        let mut relations: Vec<Relation> = self.waiting.into_values().collect();
+        relations.sort();
+        relations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::gnfs::GNFS;
+
+    fn partial(gnfs: &GNFS, a: i64, b: i64, rational_quotient: i64) -> PartialRelation {
+        let mut relation = Relation::new(gnfs, &BigInt::from(a), &BigInt::from(b));
+        relation.algebraic_quotient = BigInt::from(1);
+        relation.rational_quotient = BigInt::from(rational_quotient);
+        let large_prime = LargePrime::of(&relation).unwrap();
+        PartialRelation { relation, large_prime }
+    }
+
+    #[test]
+    fn partials_sharing_a_large_prime_pair_with_the_first() {
+        let gnfs = GNFS::default();
+        let mut store = PartialRelationStore::new();
+        assert_eq!(store.insert(partial(&gnfs, 1, 2, 1009)), None);
+        assert_eq!(store.insert(partial(&gnfs, 3, 2, 1013)), None);
+        assert_eq!(store.insert(partial(&gnfs, 1, 2, 1009)), None);
+        let pair = store.insert(partial(&gnfs, 5, 2, 1009)).unwrap();
+        assert_eq!((&pair[0].a, &pair[1].a), (&BigInt::from(1), &BigInt::from(5)));
+        assert!(store.insert(partial(&gnfs, 7, 2, 1009)).is_some());
+        assert_eq!(store.into_relations().len(), 2);
+    }
+}
@@ -1,48 +1,178 @@
 // src/relation_sieve/poly_relations_sieve_progress.rs
 
 
+use std::collections::VecDeque;
 use std::sync::{Arc, Weak};
-use log::{debug, info};
-use num::{BigInt, Integer};
+use std::time::Instant;
+use log::{debug, info, warn};
+use serde::{Serialize, Deserialize};
+use num::{BigInt, ToPrimitive};
 use crate::integer_math::gcd::GCD;
-use crate::core::sieve_range::SieveRange;
+use crate::core::sieve_range::{SieveRange, SieveRegion};
 use crate::core::gnfs::GNFS;
 use crate::relation_sieve::relation::Relation;
+use crate::relation_sieve::SieveStrategy;
+use crate::relation_sieve::cycle_finder::{CycleCounter, CycleFinder};
+use crate::relation_sieve::lattice_sieve::{self, LatticeSieve, SpecialQ};
+use crate::relation_sieve::log_sieve::LineSieve;
+use crate::relation_sieve::partial_relation::{LargePrime, LargePrimeSide, PartialRelation, PartialRelationStore};
 use crate::relation_sieve::relation_container::RelationContainer;
-use crate::core::serialization::save::relations::{smooth, free};
-use crate::integer_math::prime_factory::PrimeFactory;
+use crate::relation_sieve::row_norms::RowNorms;
+use crate::relation_sieve::rough_store::RoughRelationStore;
+use crate::polynomial::polynomial::Polynomial;
+use crate::core::serialization::save::relations::free;
 use crate::core::count_dictionary::CountDictionary;
 use crate::core::serialization::save;
 use crate::integer_math::factorization_factory::FactorizationFactory;
 use crate::core::cancellation_token::CancellationToken;
+use crate::core::sieve_tuning::SieveTuning;
 use crate::square_root::square_finder::is_square;
 
+/// Rows of b added to the lattice each time the current region is exhausted.
+const B_EXTENSION: u32 = 1000;
+
+/// Smallest slack added to the relation target, and the share of the column count used when larger.
+const MIN_RELATION_SLACK: usize = 10;
+const RELATION_SLACK_DIVISOR: usize = 20;
+
+/// Smooth relations needed before the matrix step: one per matrix column (rational and algebraic
+/// factor pairs plus quadratic characters) and some slack, so the matrix has more rows than
+/// columns and dependencies are guaranteed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelationTarget {
+    pub rational: usize,
+    pub algebraic: usize,
+    pub quadratic: usize,
+    pub slack: usize,
+}
+
+impl RelationTarget {
+    pub fn new(gnfs: &GNFS) -> Self {
+        let rational = gnfs.rational_factor_pair_collection.len();
+        let algebraic = gnfs.algebraic_factor_pair_collection.len();
+        let quadratic = gnfs.quadratic_factor_pair_collection.len();
+        let columns = rational + algebraic + quadratic;
+        let slack = gnfs.config.relation_slack
+            .unwrap_or_else(|| std::cmp::max(MIN_RELATION_SLACK, columns / RELATION_SLACK_DIVISOR));
+        RelationTarget { rational, algebraic, quadratic, slack }
+    }
+
+    pub fn total(&self) -> usize {
+        self.rational + self.algebraic + self.quadratic + self.slack
+    }
+}
+
+impl std::fmt::Display for RelationTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} = |RFB| {} + |AFB| {} + |QCB| {} + slack {}",
+            self.total(), self.rational, self.algebraic, self.quadratic, self.slack
+        )
+    }
+}
+
+/// Snapshot of sieving progress for status reporting and progress events.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SieveStatistics {
+    pub smooth_relations: usize,
+    pub rough_relations: usize,
+    pub free_relations: usize,
+    pub recycled_relations: usize,
+    pub target_quantity: usize,
+    /// Coprime (a, b) pairs whose norms were factored.
+    pub pairs_sieved: u64,
+    /// Share of sieved pairs that were smooth.
+    pub acceptance_rate: f64,
+    pub rows_sieved: u64,
+    pub relations_per_b: f64,
+    pub buffered_relations: usize,
+    pub buffer_capacity: usize,
+    /// Buffered relations as a share of the flush threshold.
+    pub buffer_occupancy: f64,
+}
+
+impl std::fmt::Display for SieveStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Sieve progress: {}/{} smooth ({} recycled), {} rough, {} free; {} pairs over {} rows ({:.4}% accepted, {:.3} relations per b); buffer {}/{} ({:.1}%)",
+            self.smooth_relations,
+            self.target_quantity,
+            self.recycled_relations,
+            self.rough_relations,
+            self.free_relations,
+            self.pairs_sieved,
+            self.rows_sieved,
+            self.acceptance_rate * 100.0,
+            self.relations_per_b,
+            self.buffered_relations,
+            self.buffer_capacity,
+            self.buffer_occupancy * 100.0
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PolyRelationsSieveProgress {
     pub a: BigInt,
     pub b: BigInt,
     pub smooth_relations_target_quantity: usize,
+    pub relation_target: RelationTarget,
     pub value_range: BigInt,
     pub relations: RelationContainer,
     pub max_b: BigInt,
+    /// Region being sieved; `a` and `b` point at the next pair to visit inside it.
+    pub region: SieveRegion,
+    /// Regions sieved completely, in the order they were finished.
+    pub sieved_regions: Vec<SieveRegion>,
+    /// Regions queued ahead of further b rows, such as the back-fill after widening the a range.
+    pub pending_regions: VecDeque<SieveRegion>,
+    /// The next special-q prime for the lattice sieve; below the configured range until it starts.
+    pub special_q: u64,
     pub smooth_relations_counter: usize,
     pub free_relations_counter: usize,
+    pub pairs_sieved: u64,
+    /// Smooth relations recovered by combining rough relations.
+    pub recycled_relations_counter: usize,
+    pub tuning: SieveTuning,
+    /// Rough relations spilled to disk; not on disk when spilling is disabled, in which case
+    /// they stay in `relations.rough_relations`.
+    pub rough_store: RoughRelationStore,
+    /// Cycles the rough relations found since the last recycling will close, so a batch ends as
+    /// soon as the relations it would combine reach the target.
+    pub pending_cycles: CycleCounter,
     pub gnfs: Weak<GNFS>,
 }
 
 impl PolyRelationsSieveProgress {
     pub fn new(gnfs: Weak<GNFS>, smooth_relations_target_quantity: isize, value_range: BigInt) -> Self {
         let mut progress = PolyRelationsSieveProgress {
-            a: BigInt::from(0),
-            b: BigInt::from(3),
+            a: BigInt::from(1),
+            b: BigInt::from(1),
             smooth_relations_target_quantity: 0,
-            value_range,
+            relation_target: RelationTarget::default(),
+            value_range: value_range.clone(),
             relations: RelationContainer::new(),
             max_b: BigInt::from(0),
+            region: SieveRegion::new(BigInt::from(1), value_range, BigInt::from(1), BigInt::from(0)),
+            sieved_regions: Vec::new(),
+            pending_regions: VecDeque::new(),
+            special_q: 0,
             smooth_relations_counter: 0,
             free_relations_counter: 0,
+            pairs_sieved: 0,
+            recycled_relations_counter: 0,
+            tuning: SieveTuning::default(),
+            rough_store: RoughRelationStore::default(),
+            pending_cycles: CycleCounter::new(),
             gnfs,
         };
+
+        if let Some(gnfs) = progress.gnfs.upgrade() {
+            progress.relation_target = RelationTarget::new(&gnfs);
+            info!("Relation target: {}", progress.relation_target);
+        }
     
         if smooth_relations_target_quantity == -1 {
             progress.smooth_relations_target_quantity = progress.smooth_relations_required_for_matrix_step();
@@ -58,110 +188,481 @@ impl PolyRelationsSieveProgress {
                 progress.max_b = gnfs.prime_factor_base.algebraic_factor_base_max.clone();
             }
         }
+        if progress.max_b < BigInt::from(1) {
+            progress.max_b = BigInt::from(B_EXTENSION);
+        }
+        progress.region.max_b = progress.max_b.clone();
     
         progress
     }
     
     pub fn smooth_relations_required_for_matrix_step(&self) -> usize {
-        if let Some(gnfs) = self.gnfs.upgrade() {
-            let mut prime_factory = PrimeFactory::new();
-            PrimeFactory::get_index_from_value(&mut prime_factory, &gnfs.prime_factor_base.rational_factor_base_max) as usize
-                + PrimeFactory::get_index_from_value(&mut prime_factory, &gnfs.prime_factor_base.algebraic_factor_base_max) as usize
-                + gnfs.quadratic_factor_pair_collection.0.len()
-                + 3
-        } else {
-            0
-        }
+        self.relation_target.total()
     }
 
+    /// Sieves the current region row by row, moving on to pending regions and then to new b rows
+    /// as each one is exhausted. Returns once the target is met, counting the combined relations the
+    /// rough relations will form when recycled, on cancellation, or when the buffer
+    /// should be written: at the end of a row once it holds the flush threshold or has waited the
+    /// flush interval, or mid-row at the memory high watermark. `a` and `b` always name the next
+    /// unvisited pair.
     pub fn generate_relations(&mut self, cancel_token: &CancellationToken) {
-        if let Some(gnfs) = self.gnfs.upgrade() {
-            let mut gnfs = (*gnfs).clone();
-            smooth::append(&mut gnfs);
-        }
-    
+        let gnfs = match self.gnfs.upgrade() {
+            Some(gnfs) => gnfs,
+            None => {
+                warn!("GenerateRelations: no GNFS instance attached to the sieve progress; nothing to sieve.");
+                return;
+            }
+        };
+
         self.smooth_relations_target_quantity = std::cmp::max(
             self.smooth_relations_target_quantity,
             self.smooth_relations_required_for_matrix_step(),
         );
-    
-        if self.a >= self.value_range {
-            self.value_range += BigInt::from(200);
-        }
-    
-        self.value_range = if self.value_range.is_even() {
-            &self.value_range + 1
-        } else {
-            self.value_range.clone()
-        };
-    
-        self.a = if self.a.is_even() {
-            &self.a + 1
-        } else {
-            self.a.clone()
-        };
-    
-        let start_a = self.a.clone();
-    
-        while &self.b >= &self.max_b {
-            self.max_b += 1000;
-        }
-    
-        
+
         debug!("{}", format!(
-            "GenerateRelations: TargetQuantity = {}, ValueRange = {}, A = {}, B = {}, Max B = {}",
-            self.smooth_relations_target_quantity, self.value_range, self.a, self.b, self.max_b
+            "GenerateRelations: TargetQuantity = {}, Region = {}, A = {}, B = {}",
+            self.smooth_relations_target_quantity, self.region, self.a, self.b
         ));
-        
-    
-        while self.smooth_relations_counter < self.smooth_relations_target_quantity {
+
+        let batch_started = Instant::now();
+        let high_watermark = self.tuning.relation_high_watermark();
+        let mut buffered = self.unpersisted_relations();
+        let companions: Vec<&Polynomial> = gnfs.polynomial_collection.iter().skip(1).take(self.relations.companion_relations.len()).collect();
+
+        if gnfs.config.sieve_strategy() == SieveStrategy::Lattice {
+            let algebraic_factor_base_max = gnfs.prime_factor_base.algebraic_factor_base_max.to_u64().unwrap_or(u64::MAX);
+            let (q_min, q_max) = gnfs.config.special_q_range(algebraic_factor_base_max);
+            if self.special_q <= q_max {
+                self.generate_lattice_relations(cancel_token, &gnfs, &companions, (q_min, q_max), batch_started, buffered);
+                return;
+            }
+        }
+
+        let line_sieve = gnfs.config.log_sieve().then(|| LineSieve::new(&gnfs, &self.tuning));
+        let mut recycled_at = batch_started;
+        while !self.target_reached() {
             if cancel_token.is_cancellation_requested() {
                 break;
             }
-    
-            if &self.b > &self.max_b {
-                break;
+
+            if self.b > self.region.max_b {
+                self.complete_region();
+                continue;
             }
-    
-            for a in SieveRange::get_sieve_range_continuation(&self.a, &self.value_range) {
+
+            let row_start = self.a.clone();
+            let mut row_norms = RowNorms::new(&gnfs.current_polynomial, &gnfs.rational_polynomial, &self.b);
+            let candidates = line_sieve.as_ref().and_then(|sieve| sieve.row(&gnfs, &self.b, &self.region.max_abs_a));
+            if let Some(candidates) = &candidates {
+                debug!("Row b = {}: {} candidates passed the log sieve.", self.b, candidates.len());
+            }
+            let mut spilled = false;
+            for a in SieveRange::get_sieve_range_continuation(&row_start, &self.region.max_abs_a) {
                 if cancel_token.is_cancellation_requested() {
                     break;
                 }
-    
-                self.a = a;
-    
-                if GCD::are_coprime(&[self.a.clone(), self.b.clone()]) {
-                    if let Some(gnfs) = self.gnfs.upgrade() {
-                        let mut rel = Relation::new(&gnfs, &self.a, &self.b);
-                        rel.sieve(&gnfs, self);
-                        let smooth = rel.is_smooth();
-                        if smooth {
-                            self.relations.smooth_relations.push(rel);
-                            self.smooth_relations_counter += 1;
-                        }
+
+                let norms = match &candidates {
+                    // Only the pairs the log sieve picked out are factored, so their norms are
+                    // computed directly rather than walked.
+                    Some(candidates) => candidates
+                        .passes(&a, !companions.is_empty())
+                        .then(|| Relation::norms(&gnfs.current_polynomial, &gnfs.rational_polynomial, &a, &self.b)),
+                    // Advanced for every a, coprime or not, so each walk moves one step at a time.
+                    None => Some(row_norms.norms(&a)),
+                };
+                if let Some((algebraic_norm, rational_norm)) = norms.filter(|_| GCD::are_coprime(&[a.clone(), self.b.clone()])) {
+                    let mut rel = Relation::new(&gnfs, &a, &self.b);
+                    rel.sieve_with_norms(&gnfs, algebraic_norm, rational_norm);
+                    if self.record_relation(&gnfs, &companions, rel) {
+                        buffered += 1;
                     }
                 }
+
+                self.a = SieveRange::next(&a);
+
+                if buffered >= high_watermark {
+                    spilled = true;
+                    break;
+                }
             }
-    
+
+            if spilled {
+                debug!("Relation buffer reached the high watermark of {} relations mid-row; spilling.", high_watermark);
+                break;
+            }
+
             if cancel_token.is_cancellation_requested() {
                 break;
             }
-    
+
             self.b += 1;
-            self.a = start_a.clone();
-    
-            
+            self.a = self.region.min_abs_a.clone();
+
             debug!("{}", &format!("B = {}", self.b));
             debug!("{}", &format!("SmoothRelations.Count: {}", self.relations.smooth_relations.len()));
-            
+            self.recycle_on_schedule(&gnfs, &mut recycled_at);
+
+            // Hand control back so the owner can write the buffered relations to disk.
+            if buffered >= self.tuning.relation_flush_threshold {
+                debug!("Relation buffer reached {} unsaved relations; flushing.", self.tuning.relation_flush_threshold);
+                break;
+            }
+            if buffered > 0 && batch_started.elapsed() >= self.tuning.relation_flush_interval {
+                debug!("Relation buffer is {} seconds old; flushing.", self.tuning.relation_flush_interval.as_secs());
+                break;
+            }
         }
-    
-        if let Some(gnfs) = self.gnfs.upgrade() {
-            let mut gnfs = (*gnfs).clone();
-            smooth::append(&mut gnfs);
+    }
+
+    /// Sieves one special-q at a time, each over all of its roots, from `special_q` up to the end of
+    /// the range. Returns on the same conditions as the line sieve, checked after each special-q,
+    /// or once the range is used up; the line sieve takes over from there.
+    fn generate_lattice_relations(
+        &mut self,
+        cancel_token: &CancellationToken,
+        gnfs: &GNFS,
+        companions: &[&Polynomial],
+        (q_min, q_max): (u64, u64),
+        batch_started: Instant,
+        mut buffered: usize,
+    ) {
+        self.special_q = self.special_q.max(q_min);
+        let sieve = LatticeSieve::new(gnfs);
+        let mut primes = lattice_sieve::special_q_primes(self.special_q, q_max);
+        let mut recycled_at = batch_started;
+        while !self.target_reached() {
+            if cancel_token.is_cancellation_requested() {
+                return;
+            }
+            let Some(q) = primes.next() else {
+                self.special_q = q_max + 1;
+                info!("Special-q range [{}, {}] exhausted; continuing with the line sieve.", q_min, q_max);
+                return;
+            };
+
+            for special_q in SpecialQ::ideals(&gnfs.current_polynomial, q) {
+                for (a, b) in sieve.candidates(gnfs, &special_q) {
+                    if !GCD::are_coprime(&[a.clone(), b.clone()]) {
+                        continue;
+                    }
+                    let mut rel = Relation::new(gnfs, &a, &b);
+                    rel.sieve(gnfs);
+                    if Self::found_by_earlier_special_q(&rel, q_min, q) {
+                        continue;
+                    }
+                    if self.record_relation(gnfs, companions, rel) {
+                        buffered += 1;
+                    }
+                }
+            }
+            self.special_q = q + 1;
+            debug!("Special-q {} sieved; {} relations.", q, self.smooth_relations_counter);
+            self.recycle_on_schedule(gnfs, &mut recycled_at);
+
+            if buffered >= self.tuning.relation_flush_threshold {
+                debug!("Relation buffer reached {} unsaved relations; flushing.", buffered);
+                return;
+            }
+            if buffered > 0 && batch_started.elapsed() >= self.tuning.relation_flush_interval {
+                debug!("Relation buffer is {} seconds old; flushing.", self.tuning.relation_flush_interval.as_secs());
+                return;
+            }
         }
     }
-    
+
+    /// Whether the lattice of a smaller special-q in the range also holds this pair, in which case
+    /// that special-q has already produced it. Only affine ideals are sieved as special-q.
+    pub(crate) fn found_by_earlier_special_q(relation: &Relation, q_min: u64, q: u64) -> bool {
+        let earlier = |p: u64| q_min <= p && p < q;
+        relation.algebraic_ideals().iter().any(|(ideal, _)| !ideal.is_projective() && earlier(ideal.p))
+            || LargePrime::on_side(relation, LargePrimeSide::Algebraic)
+                .filter(|large_prime| large_prime.root != large_prime.prime)
+                .and_then(|large_prime| large_prime.prime.to_u64())
+                .is_some_and(earlier)
+    }
+
+    /// Sorts a sieved relation into the buffers and returns whether it was kept: smooth ones count
+    /// toward the target, rough ones are kept for recycling when it is on. Companion polynomials
+    /// are tried on every relation.
+    fn record_relation(&mut self, gnfs: &GNFS, companions: &[&Polynomial], rel: Relation) -> bool {
+        self.pairs_sieved += 1;
+        for (index, polynomial) in companions.iter().enumerate() {
+            if let Some(companion) = rel.companion(gnfs, index + 1, polynomial) {
+                self.relations.companion_relations[index].push(companion);
+            }
+        }
+        if rel.is_smooth() {
+            self.relations.smooth_relations.push(rel);
+            self.smooth_relations_counter += 1;
+            true
+        } else if gnfs.config.large_prime_recycling() && rel.is_rough(gnfs) {
+            self.pending_cycles.add(self.matched_large_primes(gnfs, &rel));
+            self.relations.rough_relations.push(rel);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Combines the partials found so far once the recycle interval has passed since
+    /// `recycled_at`, independently of when the batch is flushed.
+    fn recycle_on_schedule(&mut self, gnfs: &GNFS, recycled_at: &mut Instant) {
+        if gnfs.config.large_prime_recycling() && recycled_at.elapsed() >= self.tuning.relation_recycle_interval {
+            self.recycle_rough_relations();
+            *recycled_at = Instant::now();
+        }
+    }
+
+    /// Whether the smooth relations and the combined relations waiting to be recycled reach the
+    /// target, or a companion polynomial has overtaken the current one.
+    fn target_reached(&self) -> bool {
+        self.smooth_relations_counter + self.pending_cycles.cycles() >= self.smooth_relations_target_quantity
+            || self.leading_companion().is_some()
+    }
+
+    /// The large primes [`recycle_rough_relations`](Self::recycle_rough_relations) matches a
+    /// rough relation on: both when double large primes are paired in memory, otherwise the one
+    /// large prime of a partial.
+    fn matched_large_primes(&self, gnfs: &GNFS, relation: &Relation) -> Vec<LargePrime> {
+        if gnfs.config.double_large_primes() && !self.rough_store.is_on_disk() {
+            LargePrime::all_of(relation)
+        } else {
+            LargePrime::of(relation).into_iter().collect()
+        }
+    }
+
+    /// Records the current region as sieved and moves to the next pending region, or to the next
+    /// band of b rows across the full a range when nothing is pending.
+    fn complete_region(&mut self) {
+        let finished = self.region.clone();
+        info!("Sieve region complete: {} ({} pairs)", finished, finished.pair_count());
+        self.sieved_regions.push(finished);
+
+        self.region = match self.pending_regions.pop_front() {
+            Some(region) => region,
+            None => {
+                let min_b = std::cmp::max(&self.max_b, &self.region.max_b) + 1;
+                let max_b = &min_b + B_EXTENSION - 1;
+                SieveRegion::new(BigInt::from(1), self.value_range.clone(), min_b, max_b)
+            }
+        };
+        if self.region.max_b > self.max_b {
+            self.max_b = self.region.max_b.clone();
+        }
+        self.a = self.region.min_abs_a.clone();
+        self.b = self.region.min_b.clone();
+        debug!("Sieving region {}", self.region);
+    }
+
+    /// Widens the a range to `value_range`. Rows already sieved at the old width are queued as a
+    /// back-fill region so the wider range is covered for every b, not just the rows still to come.
+    pub fn extend_value_range(&mut self, value_range: BigInt) {
+        if value_range <= self.value_range {
+            return;
+        }
+
+        let annulus_start = &self.value_range + 1;
+        let last_finished_row: BigInt = &self.b - 1;
+        if last_finished_row >= BigInt::from(1) {
+            self.pending_regions.push_back(SieveRegion::new(annulus_start, value_range.clone(), BigInt::from(1), last_finished_row.clone()));
+        }
+        if last_finished_row >= self.region.min_b {
+            let mut finished = self.region.clone();
+            finished.max_b = last_finished_row;
+            self.sieved_regions.push(finished);
+            self.region.min_b = self.b.clone();
+        }
+        if self.region.max_abs_a == self.value_range {
+            self.region.max_abs_a = value_range.clone();
+        }
+        self.value_range = value_range;
+    }
+
+    /// Makes row `b` the next one sieved, recording the rows of the current region before it as
+    /// sieved, as when they were covered by distributed work units.
+    pub fn skip_rows_before(&mut self, b: BigInt) {
+        if b <= self.b {
+            return;
+        }
+        let mut finished = self.region.clone();
+        finished.max_b = &b - 1;
+        self.sieved_regions.push(finished);
+        self.region.min_b = b.clone();
+        if self.region.max_b < b {
+            self.region.max_b = &b + B_EXTENSION - 1;
+        }
+        if self.region.max_b > self.max_b {
+            self.max_b = self.region.max_b.clone();
+        }
+        self.a = self.region.min_abs_a.clone();
+        self.b = b;
+    }
+
+    /// Extends the b rows to be sieved to at least `max_b`.
+    pub fn extend_max_b(&mut self, max_b: BigInt) {
+        if max_b > self.max_b {
+            if self.region.max_b == self.max_b && self.pending_regions.is_empty() {
+                self.region.max_b = max_b.clone();
+            }
+            self.max_b = max_b;
+        }
+    }
+
+    pub fn unpersisted_smooth_relations(&self) -> usize {
+        self.relations.smooth_relations.iter().filter(|rel| !rel.is_persisted).count()
+    }
+
+    /// Smooth and rough relations not yet written to disk.
+    pub fn unpersisted_relations(&self) -> usize {
+        self.unpersisted_smooth_relations() + self.relations.rough_relations.iter().filter(|rel| !rel.is_persisted).count()
+    }
+
+    pub fn smooth_relation_count(&self) -> usize {
+        self.smooth_relations_counter
+    }
+
+    pub fn rough_relation_count(&self) -> usize {
+        self.relations.rough_relations.len() + self.rough_store.len()
+    }
+
+    /// The companion polynomial, by its index in `GNFS::polynomial_collection`, that has reached
+    /// the relation target with more smooth relations than the current polynomial.
+    pub fn leading_companion(&self) -> Option<usize> {
+        self.relations
+            .companion_relations
+            .iter()
+            .enumerate()
+            .filter(|(_, relations)| relations.len() >= self.smooth_relations_target_quantity && relations.len() > self.smooth_relations_counter)
+            .max_by_key(|(_, relations)| relations.len())
+            .map(|(index, _)| index + 1)
+    }
+
+    pub fn free_relation_count(&self) -> usize {
+        self.relations.free_relations.len()
+    }
+
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.pairs_sieved == 0 {
+            return 0.0;
+        }
+        self.smooth_relations_counter as f64 / self.pairs_sieved as f64
+    }
+
+    /// Completed b rows across every region sieved so far, including the current one.
+    pub fn rows_sieved(&self) -> u64 {
+        let rows = |min_b: &BigInt, max_b: &BigInt| -> u64 {
+            if max_b < min_b {
+                0
+            } else {
+                (max_b - min_b + 1u32).to_u64().unwrap_or(u64::MAX)
+            }
+        };
+        let finished: u64 = self.sieved_regions.iter().map(|region| rows(&region.min_b, &region.max_b)).sum();
+        finished + rows(&self.region.min_b, &(&self.b - 1u32))
+    }
+
+    pub fn relations_per_b(&self) -> f64 {
+        let rows = self.rows_sieved();
+        if rows == 0 {
+            return 0.0;
+        }
+        self.smooth_relations_counter as f64 / rows as f64
+    }
+
+    pub fn buffer_occupancy(&self) -> f64 {
+        if self.tuning.relation_flush_threshold == 0 {
+            return 0.0;
+        }
+        self.unpersisted_smooth_relations() as f64 / self.tuning.relation_flush_threshold as f64
+    }
+
+    pub fn statistics(&self) -> SieveStatistics {
+        SieveStatistics {
+            smooth_relations: self.smooth_relation_count(),
+            rough_relations: self.rough_relation_count(),
+            free_relations: self.free_relation_count(),
+            recycled_relations: self.recycled_relations_counter,
+            target_quantity: self.smooth_relations_target_quantity,
+            pairs_sieved: self.pairs_sieved,
+            acceptance_rate: self.acceptance_rate(),
+            rows_sieved: self.rows_sieved(),
+            relations_per_b: self.relations_per_b(),
+            buffered_relations: self.unpersisted_smooth_relations(),
+            buffer_capacity: self.tuning.relation_flush_threshold,
+            buffer_occupancy: self.buffer_occupancy(),
+        }
+    }
+
+    /// Pairs the rough relations, all partials, that share a large prime. Each pair becomes a
+    /// combined relation, which the matrix treats as one full relation and which counts toward the
+    /// target. The first partial seen with a prime stays behind to pair with later ones; the rest
+    /// are consumed. When the rough store is on disk, the buffered rough relations are spilled to
+    /// it first and the pairs come from its index. With double large primes, the combined
+    /// relations are the cycles a [`CycleFinder`] closes instead, and the rough relations left are
+    /// its spanning forest. Returns how many combined relations were formed.
+    pub fn recycle_rough_relations(&mut self) -> usize {
+        let double_large_primes = self.gnfs.upgrade().is_some_and(|gnfs| gnfs.config.double_large_primes());
+        let mut rough_relations = std::mem::take(&mut self.relations.rough_relations);
+        let groups = if self.rough_store.is_on_disk() {
+            let spilled = self.rough_store.spill(&mut rough_relations).and_then(|_| self.rough_store.take_matches());
+            match spilled {
+                Ok(groups) => groups,
+                Err(e) => {
+                    warn!("Rough relation store unavailable ({}); keeping further rough relations in memory.", e);
+                    self.rough_store = RoughRelationStore::default();
+                    self.relations.rough_relations = rough_relations;
+                    return 0;
+                }
+            }
+        } else if double_large_primes {
+            let mut finder = CycleFinder::new();
+            let mut groups = Vec::new();
+            for relation in rough_relations.drain(..) {
+                let large_primes = LargePrime::all_of(&relation);
+                groups.extend(finder.add(relation, large_primes));
+            }
+            rough_relations = finder.into_relations();
+            groups
+        } else {
+            let mut store = PartialRelationStore::new();
+            let mut groups = Vec::new();
+            for relation in rough_relations.drain(..) {
+                if let Some(large_prime) = LargePrime::of(&relation) {
+                    groups.extend(store.insert(PartialRelation { relation, large_prime }));
+                }
+            }
+            rough_relations = store.into_relations();
+            groups
+        };
+        self.relations.rough_relations = rough_relations;
+        if self.rough_store.is_on_disk() {
+            self.pending_cycles.take_cycles();
+        } else if let Some(gnfs) = self.gnfs.upgrade() {
+            // What is left in memory closes no cycle, and also covers rough relations loaded
+            // from a checkpoint, which the counter never saw.
+            self.pending_cycles = CycleCounter::new();
+            for relation in &self.relations.rough_relations {
+                let large_primes = self.matched_large_primes(&gnfs, relation);
+                self.pending_cycles.add(large_primes);
+            }
+        }
+        if groups.is_empty() {
+            return 0;
+        }
+
+        let combined = groups.len();
+        self.relations.combined_relations.extend(groups);
+        self.smooth_relations_counter += combined;
+        self.recycled_relations_counter += combined;
+        info!(
+            "Large prime matching: {} sets of partial relations combined ({} in total).",
+            combined, self.recycled_relations_counter
+        );
+        combined
+    }
+
     pub fn increase_target_quantity(&mut self, amount: usize) {
         self.smooth_relations_target_quantity += amount;
         if let Some(gnfs) = self.gnfs.upgrade() {
@@ -287,14 +788,24 @@ impl ToString for PolyRelationsSieveProgress {
 impl Default for PolyRelationsSieveProgress {
     fn default() -> Self {
         PolyRelationsSieveProgress {
-            a: BigInt::from(0),
-            b: BigInt::from(3),
+            a: BigInt::from(1),
+            b: BigInt::from(1),
             smooth_relations_target_quantity: 0,
+            relation_target: RelationTarget::default(),
             value_range: BigInt::from(0),
             relations: RelationContainer::new(),
             max_b: BigInt::from(0),
+            region: SieveRegion::new(BigInt::from(1), BigInt::from(0), BigInt::from(1), BigInt::from(0)),
+            sieved_regions: Vec::new(),
+            pending_regions: VecDeque::new(),
+            special_q: 0,
             smooth_relations_counter: 0,
             free_relations_counter: 0,
+            pairs_sieved: 0,
+            recycled_relations_counter: 0,
+            tuning: SieveTuning::default(),
+            rough_store: RoughRelationStore::default(),
+            pending_cycles: CycleCounter::new(),
             gnfs: Weak::new(),
         }
     }
@@ -306,3 +817,52 @@ impl std::fmt::Display for Relation {
         write!(f, "Relation {{ a: {}, b: {}, algebraic_norm: {}, rational_norm: {} }}", self.a, self.b, self.algebraic_norm, self.rational_norm)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::gnfs_config::GnfsConfig;
+
+    #[test]
+    fn a_batch_ends_once_waiting_partials_reach_the_target() {
+        let token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("pending_cycles").unwrap();
+        let (n, m) = (BigInt::from(999962000357u64), BigInt::from(9999));
+        let mut gnfs = GNFS::with_save_locations(&token, locations, GnfsConfig::default(), &n, &m, 3, &BigInt::from(200), 50, 200, true).unwrap();
+        // Nothing but the target can end the batch before an hour has passed.
+        gnfs.set_config(GnfsConfig { relation_flush_interval_secs: Some(3600), relation_flush_threshold: Some(1 << 30), ..GnfsConfig::default() });
+
+        let started = Instant::now();
+        let snapshot = gnfs.attach_sieve_snapshot();
+        gnfs.current_relations_progress.generate_relations(&token);
+        drop(snapshot);
+        let progress = &mut gnfs.current_relations_progress;
+        let (smooth, pending, target) = (progress.smooth_relations_counter, progress.pending_cycles.cycles(), progress.smooth_relations_target_quantity);
+        assert!(smooth < target && smooth + pending >= target, "{} smooth and {} pending for a target of {}", smooth, pending, target);
+
+        assert_eq!(progress.recycle_rough_relations(), pending);
+        assert!(progress.smooth_relations_counter >= target);
+        assert_eq!(progress.pending_cycles.cycles(), 0);
+        assert!(started.elapsed() < Duration::from_secs(60));
+    }
+
+    #[test]
+    fn partials_are_combined_mid_batch_on_their_own_interval() {
+        let token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("recycle_interval").unwrap();
+        let (n, m) = (BigInt::from(999962000357u64), BigInt::from(9999));
+        let mut gnfs = GNFS::with_save_locations(&token, locations, GnfsConfig::default(), &n, &m, 3, &BigInt::from(200), 50, 200, true).unwrap();
+        let config = GnfsConfig { relation_flush_interval_secs: Some(3600), relation_recycle_interval_secs: Some(0), ..GnfsConfig::default() };
+        gnfs.set_config(config);
+        assert!(gnfs.current_relations_progress.tuning.relation_recycle_interval < gnfs.current_relations_progress.tuning.relation_flush_interval);
+
+        let snapshot = gnfs.attach_sieve_snapshot();
+        gnfs.current_relations_progress.generate_relations(&token);
+        drop(snapshot);
+        let progress = &gnfs.current_relations_progress;
+        assert!(progress.recycled_relations_counter > 0);
+        assert!(progress.smooth_relations_counter + progress.pending_cycles.cycles() >= progress.smooth_relations_target_quantity);
+    }
+}
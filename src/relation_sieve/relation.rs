@@ -1,12 +1,15 @@
 // src/realation_sieve/relation.rs
 
-use num::BigInt;
+use num::{BigInt, Integer, One, ToPrimitive, Zero};
 use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 use crate::core::gnfs::GNFS;
-use crate::relation_sieve::poly_relations_sieve_progress::PolyRelationsSieveProgress;
 use crate::integer_math::factorization_factory::FactorizationFactory;
+use crate::integer_math::normal::Normal;
 use crate::core::count_dictionary::CountDictionary;
+use crate::factor::factor_pair::FactorPair;
+use crate::polynomial::polynomial::Polynomial;
+use crate::relation_sieve::partial_relation::PartialRelation;
 
 #[derive(Debug, Clone)]
 pub struct Relation {
@@ -19,6 +22,9 @@ pub struct Relation {
     pub algebraic_factorization: CountDictionary,
     pub rational_factorization: CountDictionary,
     pub is_persisted: bool,
+    /// Index into `GNFS::polynomial_collection` of the polynomial the algebraic side belongs to;
+    /// 0 is the current polynomial.
+    pub polynomial: usize,
 }
 
 impl Relation {
@@ -33,6 +39,7 @@ impl Relation {
             algebraic_factorization: CountDictionary::new(),
             rational_factorization: CountDictionary::new(),
             is_persisted: false,
+            polynomial: 0,
         }
     }
 
@@ -41,46 +48,105 @@ impl Relation {
     }
 
     pub fn is_rational_quotient_smooth(&self) -> bool {
-        self.rational_quotient == BigInt::from(1) || self.rational_quotient == BigInt::from(0)
+        self.rational_quotient.is_one()
     }
 
     pub fn is_algebraic_quotient_smooth(&self) -> bool {
-        self.algebraic_quotient == BigInt::from(1) || self.algebraic_quotient == BigInt::from(0)
+        self.algebraic_quotient.is_one()
+    }
+
+    /// Not smooth, but with a single large prime that a partner relation can cancel, or one on
+    /// each side when double large primes are on; see
+    /// [`PartialRelation`](crate::relation_sieve::partial_relation::PartialRelation).
+    pub fn is_rough(&self, gnfs: &GNFS) -> bool {
+        PartialRelation::large_prime(gnfs, self).is_some()
+            || (gnfs.config.double_large_primes() && PartialRelation::large_prime_pair(gnfs, self).is_some())
     }
 
     pub fn apply(&self, x: &BigInt) -> BigInt {
         &self.a + &self.b * x
     }
 
-    pub fn sieve(&mut self, gnfs: &GNFS, progress: &mut PolyRelationsSieveProgress) {
-        let f_a = gnfs.current_polynomial.evaluate(&self.a);
-        let f_b = gnfs.current_polynomial.evaluate(&self.b);
+    /// Returns the (algebraic, rational) norms sieved for the pair (a, b): (-b)^d·ƒ(-a/b) and the
+    /// same form of the rational polynomial g, which is a + bm for g = X - m.
+    pub fn norms(polynomial: &Polynomial, rational_polynomial: &Polynomial, a: &BigInt, b: &BigInt) -> (BigInt, BigInt) {
+        (Normal::algebraic(a, b, polynomial), Normal::algebraic(a, b, rational_polynomial))
+    }
+
+    pub fn sieve(&mut self, gnfs: &GNFS) {
+        let (algebraic_norm, rational_norm) = Self::norms(&gnfs.current_polynomial, &gnfs.rational_polynomial, &self.a, &self.b);
+        self.sieve_with_norms(gnfs, algebraic_norm, rational_norm);
+    }
 
-        self.algebraic_norm = f_a.clone();
-        self.rational_norm = self.apply(&f_b);
+    /// Factors norms the caller has already computed for this pair, e.g. with
+    /// [`RowNorms`](crate::relation_sieve::row_norms::RowNorms).
+    pub fn sieve_with_norms(&mut self, gnfs: &GNFS, algebraic_norm: BigInt, rational_norm: BigInt) {
+        self.algebraic_norm = algebraic_norm;
+        self.rational_norm = rational_norm;
+
+        // A zero norm means a/b is a root of ƒ or equals -m; such a pair carries no information and
+        // is left with a zero quotient.
+        if self.algebraic_norm.is_zero() || self.rational_norm.is_zero() {
+            self.algebraic_quotient = BigInt::zero();
+            self.rational_quotient = BigInt::zero();
+            return;
+        }
 
-        let (algebraic_norm, algebraic_quotient) = FactorizationFactory::factor(&self.algebraic_norm);
-        let (rational_norm, rational_quotient) = FactorizationFactory::factor(&self.rational_norm);
+        let (algebraic_factorization, algebraic_quotient) =
+            FactorizationFactory::factor_with_base(&self.algebraic_norm, &gnfs.prime_factor_base.algebraic_factor_base);
+        let (rational_factorization, rational_quotient) =
+            FactorizationFactory::factor_with_base(&self.rational_norm, &gnfs.prime_factor_base.rational_factor_base);
 
-        self.algebraic_factorization = algebraic_norm;
-        self.rational_factorization = rational_norm;
+        self.algebraic_factorization = algebraic_factorization;
+        self.rational_factorization = rational_factorization;
 
         self.algebraic_quotient = algebraic_quotient;
         self.rational_quotient = rational_quotient;
+    }
 
-        self.algebraic_factorization
-            .retain(|prime, _| gnfs.prime_factor_base.algebraic_factor_base.contains(prime));
-        self.rational_factorization
-            .retain(|prime, _| gnfs.prime_factor_base.rational_factor_base.contains(prime));
-
-        let is_algebraic_quotient_smooth =
-            self.algebraic_quotient == BigInt::from(1) || self.algebraic_quotient == BigInt::from(0);
-        let is_rational_quotient_smooth =
-            self.rational_quotient == BigInt::from(1) || self.rational_quotient == BigInt::from(0);
-
-        if is_algebraic_quotient_smooth && is_rational_quotient_smooth {
-            progress.smooth_relations_counter += 1;
+    /// The relation for the same (a, b) on the companion polynomial at `index`, reusing this
+    /// relation's rational side: every polynomial in the collection shares the root m, so only the
+    /// algebraic norm has to be factored again. Returns it when both sides are smooth.
+    pub fn companion(&self, gnfs: &GNFS, index: usize, polynomial: &Polynomial) -> Option<Relation> {
+        if !self.is_rational_quotient_smooth() {
+            return None;
+        }
+        let algebraic_norm = Normal::algebraic(&self.a, &self.b, polynomial);
+        if algebraic_norm.is_zero() {
+            return None;
+        }
+        let (algebraic_factorization, algebraic_quotient) =
+            FactorizationFactory::factor_with_base(&algebraic_norm, &gnfs.prime_factor_base.algebraic_factor_base);
+        if !algebraic_quotient.is_one() {
+            return None;
         }
+        Some(Relation {
+            algebraic_norm,
+            algebraic_quotient,
+            algebraic_factorization,
+            is_persisted: false,
+            polynomial: index,
+            ..self.clone()
+        })
+    }
+
+    /// The first-degree prime ideals behind the algebraic factorization, with their exponents. For
+    /// coprime a and b each prime p of the norm belongs to exactly one ideal: the projective one
+    /// when p divides b, which can only happen when p divides the leading coefficient of ƒ, and
+    /// otherwise (p, r) with r ≡ -a·b⁻¹ (mod p).
+    pub fn algebraic_ideals(&self) -> Vec<(FactorPair, u32)> {
+        self.algebraic_factorization
+            .iter()
+            .filter_map(|(p, exponent)| {
+                let prime = p.to_u64()?;
+                if self.b.mod_floor(&p).is_zero() {
+                    return Some((FactorPair::projective(prime), exponent));
+                }
+                let b_inverse = self.b.mod_floor(&p).modpow(&(&p - 2), &p);
+                let r = (-&self.a * b_inverse).mod_floor(&p);
+                Some((FactorPair::new(prime, r.to_u64()?), exponent))
+            })
+            .collect()
     }
 }
 
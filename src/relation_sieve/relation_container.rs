@@ -9,6 +9,11 @@ pub struct RelationContainer {
     pub smooth_relations: Vec<Relation>,
     pub rough_relations: Vec<Relation>,
     pub free_relations: Vec<Vec<Relation>>,
+    /// Pairs of partial relations sharing a large prime; each counts as one full relation.
+    pub combined_relations: Vec<Vec<Relation>>,
+    /// Smooth relations found for the companion polynomials, one list per polynomial after the
+    /// current one in `GNFS::polynomial_collection`.
+    pub companion_relations: Vec<Vec<Relation>>,
 }
 
 impl RelationContainer {
@@ -17,6 +22,18 @@ impl RelationContainer {
             smooth_relations: Vec::new(),
             rough_relations: Vec::new(),
             free_relations: Vec::new(),
+            combined_relations: Vec::new(),
+            companion_relations: Vec::new(),
         }
     }
-}
\ No newline at end of file
+
+    /// The relation sets the matrix is built from, one column each: every smooth relation on its
+    /// own, then the combined relations.
+    pub fn matrix_columns(&self) -> Vec<Vec<Relation>> {
+        self.smooth_relations
+            .iter()
+            .map(|relation| vec![relation.clone()])
+            .chain(self.combined_relations.iter().cloned())
+            .collect()
+    }
+}
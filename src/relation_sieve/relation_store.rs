@@ -0,0 +1,282 @@
+// src/relation_sieve/relation_store.rs
+
+//! External merge sort over relation files. Relations are read in runs that fit in memory, each
+//! run is sorted by (a, b) and written to a temporary file, and the runs are merged into a single
+//! store with duplicates removed. The store holds the relations in (a, b) order, so later stages
+//! can stream it instead of loading every relation at once; [`sort_and_dedup`] writes it one JSON
+//! record per line, [`storage::sort_and_dedup`](crate::relation_sieve::storage::sort_and_dedup) in
+//! the binary format.
+
+use log::info;
+use num::BigInt;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Bytes, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::core::error::GnfsError;
+use crate::core::serialization::migration;
+use crate::core::serialization::types::SerializableRelation;
+use crate::relation_sieve::compression;
+
+pub const SORTED_RELATIONS_FILENAME: &str = "SortedRelations.jsonl";
+
+type RelationKey = (BigInt, BigInt);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortSummary {
+    pub records_read: usize,
+    pub unique_relations: usize,
+    pub runs: usize,
+}
+
+/// Streams the records of a relation file one at a time. Accepts the appended `{..},{..}` layout
+/// as well as bracketed arrays, and upgrades each record through [`migration::relation`].
+pub struct RelationFileReader<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+}
+
+impl RelationFileReader<Box<dyn Read>> {
+    /// Opens the relation file at `path`, decompressing it when it is compressed.
+    pub fn open(path: &Path) -> Result<Self, GnfsError> {
+        Ok(RelationFileReader { bytes: BufReader::new(compression::open(path)?).bytes() })
+    }
+}
+
+impl<R: Read> RelationFileReader<R> {
+    /// Returns the text of the next top-level JSON object, skipping separators between records.
+    fn next_record(&mut self) -> Result<Option<String>, GnfsError> {
+        let mut record = Vec::new();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        for byte in self.bytes.by_ref() {
+            let byte = byte?;
+            if depth == 0 {
+                if byte == b'{' {
+                    depth = 1;
+                    record.push(byte);
+                }
+                continue;
+            }
+
+            record.push(byte);
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' => in_string = true,
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(Some(String::from_utf8_lossy(&record).into_owned()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if depth > 0 {
+            return Err(GnfsError::InvalidParameter("relation file ends inside a record".to_string()));
+        }
+        Ok(None)
+    }
+}
+
+impl<R: Read> Iterator for RelationFileReader<R> {
+    type Item = Result<SerializableRelation, GnfsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_record() {
+            Ok(Some(text)) => Some(
+                serde_json::from_str(&text)
+                    .map_err(GnfsError::from)
+                    .and_then(migration::relation)
+                    .and_then(|value| serde_json::from_value(value).map_err(GnfsError::from)),
+            ),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+fn key_of(relation: &SerializableRelation) -> Result<RelationKey, GnfsError> {
+    let parse = |value: &str| {
+        BigInt::parse_bytes(value.as_bytes(), 10)
+            .ok_or_else(|| GnfsError::InvalidParameter(format!("relation coordinate '{}' is not an integer", value)))
+    };
+    Ok((parse(&relation.a)?, parse(&relation.b)?))
+}
+
+pub(crate) fn sibling_path(output: &Path, suffix: &str) -> PathBuf {
+    let mut name = output.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(suffix);
+    output.with_file_name(name)
+}
+
+fn run_path(output: &Path, index: usize) -> PathBuf {
+    sibling_path(output, &format!(".run{:04}", index))
+}
+
+fn write_lines<'a>(path: &Path, relations: impl Iterator<Item = &'a SerializableRelation>) -> Result<(), GnfsError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for relation in relations {
+        serde_json::to_writer(&mut writer, relation)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Sorts `run` by (a, b), drops duplicates within it and writes it as a temporary run file.
+fn write_run(run: &mut Vec<(RelationKey, SerializableRelation)>, path: &Path) -> Result<(), GnfsError> {
+    run.sort_by(|left, right| left.0.cmp(&right.0));
+    run.dedup_by(|later, earlier| later.0 == earlier.0);
+    write_lines(path, run.iter().map(|(_, relation)| relation))?;
+    run.clear();
+    Ok(())
+}
+
+/// Reads relations from `inputs` in runs of at most `run_length`, then merges the sorted runs into
+/// `output` with duplicate (a, b) pairs removed. The first occurrence of a pair is kept.
+pub fn sort_and_dedup(inputs: &[PathBuf], output: &Path, run_length: usize) -> Result<SortSummary, GnfsError> {
+    let staging = sibling_path(output, ".partial");
+    let mut writer = BufWriter::new(File::create(&staging)?);
+    let summary = merge_runs(inputs, output, run_length, |relation| {
+        serde_json::to_writer(&mut writer, &relation)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    })?;
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&staging, output)?;
+
+    info!(
+        "Sorted relation store written to {}: {} unique of {} relations read, {} runs merged.",
+        output.display(), summary.unique_relations, summary.records_read, summary.runs
+    );
+    Ok(summary)
+}
+
+/// The sort behind [`sort_and_dedup`], handing each unique relation to `emit` in (a, b) order
+/// instead of writing it. The temporary run files are named after `output` and removed when the
+/// merge is done.
+pub fn merge_runs(
+    inputs: &[PathBuf],
+    output: &Path,
+    run_length: usize,
+    mut emit: impl FnMut(SerializableRelation) -> Result<(), GnfsError>,
+) -> Result<SortSummary, GnfsError> {
+    let run_length = run_length.max(1);
+    let mut summary = SortSummary::default();
+    let mut run_paths = Vec::new();
+    let mut run = Vec::with_capacity(run_length);
+
+    for input in inputs.iter().filter(|input| input.exists()) {
+        for relation in RelationFileReader::open(input)? {
+            let relation = relation?;
+            run.push((key_of(&relation)?, relation));
+            summary.records_read += 1;
+            if run.len() >= run_length {
+                let path = run_path(output, run_paths.len());
+                write_run(&mut run, &path)?;
+                run_paths.push(path);
+            }
+        }
+    }
+    if !run.is_empty() {
+        let path = run_path(output, run_paths.len());
+        write_run(&mut run, &path)?;
+        run_paths.push(path);
+    }
+    summary.runs = run_paths.len();
+
+    let mut readers = run_paths
+        .iter()
+        .map(|path| File::open(path).map(|file| BufReader::new(file).lines()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut heads: Vec<Option<SerializableRelation>> = readers.iter().map(|_| None).collect();
+    let mut heap = BinaryHeap::new();
+
+    let mut advance = |index: usize, heads: &mut Vec<Option<SerializableRelation>>, heap: &mut BinaryHeap<Reverse<(RelationKey, usize)>>| -> Result<(), GnfsError> {
+        if let Some(line) = readers[index].next() {
+            let relation: SerializableRelation = serde_json::from_str(&line?)?;
+            heap.push(Reverse((key_of(&relation)?, index)));
+            heads[index] = Some(relation);
+        }
+        Ok(())
+    };
+    for index in 0..run_paths.len() {
+        advance(index, &mut heads, &mut heap)?;
+    }
+
+    let mut last_key: Option<RelationKey> = None;
+    while let Some(Reverse((key, index))) = heap.pop() {
+        let relation = heads[index].take().expect("heap entry without a pending relation");
+        if last_key.as_ref() != Some(&key) {
+            emit(relation)?;
+            summary.unique_relations += 1;
+            last_key = Some(key);
+        }
+        advance(index, &mut heads, &mut heap)?;
+    }
+
+    for path in &run_paths {
+        fs::remove_file(path)?;
+    }
+    Ok(summary)
+}
+
+/// Streams relations from a store written by [`sort_and_dedup`], in (a, b) order.
+pub fn read_sorted(path: &Path) -> Result<impl Iterator<Item = Result<SerializableRelation, GnfsError>>, GnfsError> {
+    let lines = BufReader::new(File::open(path)?).lines();
+    Ok(lines.filter_map(|line| match line {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(serde_json::from_str(&line).map_err(GnfsError::from)),
+        Err(e) => Some(Err(GnfsError::from(e))),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::directory_location::DirectoryLocations;
+
+    fn record(a: i64, b: i64) -> String {
+        format!(
+            r#"{{"a":"{}","b":"{}","algebraic_norm":"1","rational_norm":"1","algebraic_quotient":"1","rational_quotient":"1","algebraic_factorization":"","rational_factorization":"","is_persisted":true}}"#,
+            a, b
+        )
+    }
+
+    #[test]
+    fn merges_runs_in_order_without_duplicates() {
+        let locations = DirectoryLocations::temporary("relation_store").unwrap();
+        let directory = Path::new(&locations.save_directory);
+        let input = directory.join("SmoothRelations.json");
+        let pairs = [(3, 1), (-1, 2), (1, 1), (3, 1), (-2, 1), (1, 1), (2, 3)];
+        let records: Vec<String> = pairs.iter().map(|&(a, b)| record(a, b)).collect();
+        // Older builds bracketed the first record and appended the rest after it.
+        fs::write(&input, format!("[{}],{}", records[0], records[1..].join(","))).unwrap();
+
+        let output = directory.join(SORTED_RELATIONS_FILENAME);
+        let summary = sort_and_dedup(&[input], &output, 2).unwrap();
+        let keys: Vec<(String, String)> = read_sorted(&output).unwrap().map(|rel| rel.unwrap()).map(|rel| (rel.a, rel.b)).collect();
+
+        assert_eq!(summary.records_read, pairs.len());
+        assert_eq!(summary.runs, 4);
+        let expected: Vec<(String, String)> = [(-2, 1), (-1, 2), (1, 1), (2, 3), (3, 1)]
+            .iter()
+            .map(|&(a, b)| (a.to_string(), b.to_string()))
+            .collect();
+        assert_eq!(keys, expected);
+    }
+}
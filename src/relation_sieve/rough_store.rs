@@ -0,0 +1,356 @@
+// src/relation_sieve/rough_store.rs
+
+//! Disk-backed storage for rough relations. A partial relation only matters when another one shares
+//! its large prime, so most are never read again; keeping tens of millions of them in memory with
+//! their factorizations is the sieve's largest cost. The store appends each relation to a
+//! line-per-record file and keeps only an index in memory, from a hash of the relation's
+//! [`LargePrime`] to the byte offsets of the records carrying it. Recycling reads back just the
+//! records whose hash occurs twice.
+
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use log::{debug, warn};
+use crate::core::error::GnfsError;
+use crate::core::serialization::types::SerializableRelation;
+use crate::relation_sieve::partial_relation::LargePrime;
+use crate::relation_sieve::relation::Relation;
+
+pub const ROUGH_STORE_FILENAME: &str = "RoughRelations.jsonl";
+pub const ROUGH_INDEX_FILENAME: &str = "RoughRelations.idx";
+/// Relations kept on disk when no cap is configured.
+pub const DEFAULT_ROUGH_RELATION_CAP: usize = 10_000_000;
+
+fn large_prime_hash(relation: &Relation) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    LargePrime::of(relation).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RoughRelationStore {
+    directory: Option<PathBuf>,
+    cap: usize,
+    /// Large prime hash to the offsets of live records with that hash.
+    index: HashMap<u64, Vec<u64>>,
+    live: usize,
+    /// Records consumed by recycling but still taking space in the file.
+    dead: usize,
+    /// Relations turned away because the store was full.
+    dropped: usize,
+}
+
+impl RoughRelationStore {
+    /// Opens the store in `directory`, reading its index if one was saved. A store file without
+    /// an index is re-indexed in full, so relations recycled since the last save may be combined
+    /// again; the relation store's de-duplication removes the repeats.
+    pub fn open(directory: &Path, cap: usize) -> Result<Self, GnfsError> {
+        let mut store = RoughRelationStore { directory: Some(directory.to_path_buf()), cap, ..Default::default() };
+        let data = store.data_path();
+        if !data.exists() {
+            return Ok(store);
+        }
+
+        let index = store.index_path();
+        if index.exists() {
+            for line in BufReader::new(File::open(&index)?).lines() {
+                let line = line?;
+                let mut fields = line.split_whitespace();
+                match (fields.next(), fields.next(), fields.next()) {
+                    (Some("dead"), Some(dead), None) => store.dead = dead.parse().unwrap_or(0),
+                    (Some("dropped"), Some(dropped), None) => store.dropped = dropped.parse().unwrap_or(0),
+                    (Some(hash), Some(offset), None) => {
+                        let parsed = u64::from_str_radix(hash, 16).ok().zip(offset.parse().ok());
+                        let (hash, offset) = parsed.ok_or_else(|| GnfsError::InvalidParameter(format!("malformed rough relation index line {:?}", line)))?;
+                        store.index.entry(hash).or_default().push(offset);
+                        store.live += 1;
+                    }
+                    _ => {}
+                }
+            }
+            return Ok(store);
+        }
+
+        warn!("{} has no index; re-indexing every rough relation in it.", data.display());
+        let mut reader = BufReader::new(File::open(&data)?);
+        let mut offset = 0u64;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            if !line.trim().is_empty() {
+                let relation = Relation::from(serde_json::from_str::<SerializableRelation>(&line)?);
+                store.index.entry(large_prime_hash(&relation)).or_default().push(offset);
+                store.live += 1;
+            }
+            offset += read as u64;
+        }
+        Ok(store)
+    }
+
+    /// True when relations are spilled to disk rather than kept by the caller.
+    pub fn is_on_disk(&self) -> bool {
+        self.directory.is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.live
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live == 0
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    fn data_path(&self) -> PathBuf {
+        self.directory.as_deref().unwrap_or(Path::new(".")).join(ROUGH_STORE_FILENAME)
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.directory.as_deref().unwrap_or(Path::new(".")).join(ROUGH_INDEX_FILENAME)
+    }
+
+    /// Moves `relations` into the store, emptying the vector. Relations beyond the cap are
+    /// dropped. Does nothing for a store that isn't on disk.
+    pub fn spill(&mut self, relations: &mut Vec<Relation>) -> Result<(), GnfsError> {
+        if !self.is_on_disk() || relations.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(self.data_path())?;
+        let mut offset = file.seek(SeekFrom::End(0))?;
+        let mut writer = BufWriter::new(file);
+        let dropped_before = self.dropped;
+        for relation in relations.drain(..) {
+            if self.live >= self.cap {
+                self.dropped += 1;
+                continue;
+            }
+            let line = serde_json::to_string(&SerializableRelation::from(relation.clone()))? + "\n";
+            writer.write_all(line.as_bytes())?;
+            self.index.entry(large_prime_hash(&relation)).or_default().push(offset);
+            offset += line.len() as u64;
+            self.live += 1;
+        }
+        writer.flush()?;
+        if dropped_before == 0 && self.dropped > 0 {
+            warn!("The rough relation store is full at {} relations; further rough relations are discarded.", self.cap);
+        }
+        Ok(())
+    }
+
+    /// Returns every pair of stored partials sharing a large prime. As in
+    /// [`PartialRelationStore`](crate::relation_sieve::partial_relation::PartialRelationStore), the first partial with a prime stays in the store to pair with
+    /// later ones and the rest are removed. Relations whose hash matched but whose large primes
+    /// didn't stay in the store.
+    pub fn take_matches(&mut self) -> Result<Vec<Vec<Relation>>, GnfsError> {
+        let candidates: Vec<u64> = self.index.iter().filter(|(_, offsets)| offsets.len() > 1).map(|(hash, _)| *hash).collect();
+        if candidates.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut reader = BufReader::new(File::open(self.data_path())?);
+        let mut groups = Vec::new();
+        let mut consumed = 0;
+        let mut line = String::new();
+        for hash in candidates {
+            let mut offsets = self.index.remove(&hash).unwrap_or_default();
+            offsets.sort_unstable();
+            let mut kept = Vec::new();
+            let mut waiting: HashMap<LargePrime, (u64, Relation)> = HashMap::new();
+            for offset in offsets {
+                reader.seek(SeekFrom::Start(offset))?;
+                line.clear();
+                reader.read_line(&mut line)?;
+                let relation = Relation::from(serde_json::from_str::<SerializableRelation>(&line)?);
+                let Some(large_prime) = LargePrime::of(&relation) else {
+                    kept.push(offset);
+                    continue;
+                };
+                match waiting.entry(large_prime) {
+                    Entry::Occupied(first) => {
+                        if first.get().1 != relation {
+                            groups.push(vec![first.get().1.clone(), relation]);
+                        }
+                        consumed += 1;
+                    }
+                    Entry::Vacant(slot) => {
+                        slot.insert((offset, relation));
+                    }
+                }
+            }
+            kept.extend(waiting.into_values().map(|(offset, _)| offset));
+            self.index.insert(hash, kept);
+        }
+
+        self.live -= consumed;
+        self.dead += consumed;
+        debug!("Rough relation store: {} matching pairs taken, {} relations left.", groups.len(), self.live);
+        Ok(groups)
+    }
+
+    /// Writes the index so the store can be reopened, first compacting the file when consumed
+    /// records outnumber live ones.
+    pub fn save(&mut self) -> Result<(), GnfsError> {
+        if !self.is_on_disk() {
+            return Ok(());
+        }
+        if self.dead > self.live {
+            self.compact()?;
+        }
+        let mut writer = BufWriter::new(File::create(self.index_path())?);
+        writeln!(writer, "dead {}", self.dead)?;
+        writeln!(writer, "dropped {}", self.dropped)?;
+        for (hash, offsets) in &self.index {
+            for offset in offsets {
+                writeln!(writer, "{:016x} {}", hash, offset)?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Rewrites the file with only the live records.
+    fn compact(&mut self) -> Result<(), GnfsError> {
+        let data = self.data_path();
+        let staging = data.with_extension("jsonl.compacting");
+        let mut reader = BufReader::new(File::open(&data)?);
+        let mut writer = BufWriter::new(File::create(&staging)?);
+        let mut index = HashMap::with_capacity(self.index.len());
+        let mut written = 0u64;
+        let mut line = String::new();
+        for (hash, offsets) in &self.index {
+            for &offset in offsets {
+                reader.seek(SeekFrom::Start(offset))?;
+                line.clear();
+                reader.read_line(&mut line)?;
+                writer.write_all(line.as_bytes())?;
+                index.entry(*hash).or_insert_with(Vec::new).push(written);
+                written += line.len() as u64;
+            }
+        }
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&staging, &data)?;
+        debug!("Rough relation store compacted: {} consumed records removed.", self.dead);
+        self.index = index;
+        self.dead = 0;
+        Ok(())
+    }
+
+    /// Deletes the store files in `directory`, if any.
+    pub fn remove_files(directory: &Path) -> Result<(), GnfsError> {
+        for path in [directory.join(ROUGH_STORE_FILENAME), directory.join(ROUGH_INDEX_FILENAME)] {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the store's files and empties the index, e.g. when the factor bases change.
+    pub fn clear(&mut self) -> Result<(), GnfsError> {
+        if let Some(directory) = &self.directory {
+            Self::remove_files(directory)?;
+        }
+        self.index.clear();
+        self.live = 0;
+        self.dead = 0;
+        self.dropped = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::{BigInt, One};
+    use super::*;
+    use crate::core::count_dictionary::CountDictionary;
+    use crate::core::directory_location::DirectoryLocations;
+
+    /// A relation smooth on the algebraic side with `large_prime` left on the rational side.
+    fn partial(a: i64, large_prime: u64) -> Relation {
+        Relation {
+            a: BigInt::from(a),
+            b: BigInt::one(),
+            algebraic_norm: BigInt::from(6),
+            rational_norm: BigInt::from(large_prime * 2),
+            algebraic_quotient: BigInt::one(),
+            rational_quotient: BigInt::from(large_prime),
+            algebraic_factorization: CountDictionary::from_compact_string("2*3").unwrap(),
+            rational_factorization: CountDictionary::from_compact_string("2").unwrap(),
+            is_persisted: false,
+            polynomial: 0,
+        }
+    }
+
+    fn pairs(groups: &[Vec<Relation>]) -> Vec<(i64, i64)> {
+        let a = |relation: &Relation| relation.a.to_string().parse::<i64>().unwrap();
+        let mut pairs: Vec<(i64, i64)> = groups.iter().map(|group| (a(&group[0]), a(&group[1]))).collect();
+        pairs.sort();
+        pairs
+    }
+
+    #[test]
+    fn pairs_partials_sharing_a_large_prime_across_reopening() {
+        let locations = DirectoryLocations::temporary("rough_store").unwrap();
+        let directory = Path::new(&locations.save_directory);
+        let mut store = RoughRelationStore::open(directory, 100).unwrap();
+        let mut relations = vec![partial(1, 1009), partial(2, 1013), partial(3, 1009), partial(4, 1009), partial(5, 1019)];
+        store.spill(&mut relations).unwrap();
+        assert!(relations.is_empty() && store.is_on_disk());
+        assert_eq!(store.len(), 5);
+
+        // The first partial over 1009 stays to pair with later ones.
+        assert_eq!(pairs(&store.take_matches().unwrap()), vec![(1, 3), (1, 4)]);
+        assert_eq!(store.len(), 3);
+        assert!(store.take_matches().unwrap().is_empty());
+
+        // Two consumed records against three live ones: saved as is, then reopened from the index.
+        store.save().unwrap();
+        let mut reopened = RoughRelationStore::open(directory, 100).unwrap();
+        assert_eq!(reopened.len(), 3);
+        reopened.spill(&mut vec![partial(6, 1013), partial(7, 1019)]).unwrap();
+        assert_eq!(pairs(&reopened.take_matches().unwrap()), vec![(2, 6), (5, 7)]);
+
+        // Now consumed records outnumber live ones, so saving compacts the file.
+        reopened.save().unwrap();
+        let lines = fs::read_to_string(directory.join(ROUGH_STORE_FILENAME)).unwrap().lines().count();
+        assert_eq!(lines, 3);
+        let mut compacted = RoughRelationStore::open(directory, 100).unwrap();
+        compacted.spill(&mut vec![partial(8, 1009)]).unwrap();
+        assert_eq!(pairs(&compacted.take_matches().unwrap()), vec![(1, 8)]);
+
+        // Without its index the file is read through again.
+        fs::remove_file(directory.join(ROUGH_INDEX_FILENAME)).unwrap();
+        assert_eq!(RoughRelationStore::open(directory, 100).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn drops_relations_beyond_the_cap_and_keeps_nothing_when_off() {
+        let locations = DirectoryLocations::temporary("rough_store_cap").unwrap();
+        let directory = Path::new(&locations.save_directory);
+        let mut store = RoughRelationStore::open(directory, 3).unwrap();
+        store.spill(&mut (1..=5).map(|a| partial(a, 1009 + 4 * a as u64)).collect()).unwrap();
+        assert_eq!((store.len(), store.dropped()), (3, 2));
+        store.save().unwrap();
+        assert_eq!(RoughRelationStore::open(directory, 3).unwrap().dropped(), 2);
+
+        store.clear().unwrap();
+        assert!(store.is_empty() && !directory.join(ROUGH_STORE_FILENAME).exists());
+
+        let mut off = RoughRelationStore::default();
+        let mut relations = vec![partial(1, 1009), partial(2, 1009)];
+        off.spill(&mut relations).unwrap();
+        assert!(!off.is_on_disk() && off.is_empty());
+        assert_eq!(relations.len(), 2);
+    }
+}
@@ -0,0 +1,123 @@
+// src/relation_sieve/row_norms.rs
+
+//! Norms along one b row without re-evaluating them per pair. The sieve visits a = k, -k, k + 1,
+//! -(k + 1), …, so the row splits into two walks over k = |a|, one per sign. On each walk both
+//! norms are polynomials in k, of the degrees of ƒ and g, so a table of forward differences
+//! advances each with one addition per degree; for g = X - m the rational norm a + bm just moves
+//! by ±1. The only multiplications happen when a walk is seeded.
+
+use num::{BigInt, One, Signed};
+use crate::polynomial::polynomial::Polynomial;
+use crate::relation_sieve::relation::Relation;
+
+/// One sign's walk over k = |a|.
+#[derive(Debug, Clone)]
+struct NormWalk {
+    /// The |a| whose norms are currently held.
+    k: BigInt,
+    /// algebraic[0] is the algebraic norm at k, algebraic[i] its i-th forward difference.
+    algebraic: Vec<BigInt>,
+    /// The same for the rational norm.
+    rational: Vec<BigInt>,
+}
+
+impl NormWalk {
+    fn seed(polynomial: &Polynomial, rational_polynomial: &Polynomial, b: &BigInt, k: &BigInt, negative: bool) -> Self {
+        let a_at = |offset: usize| {
+            let k = k + offset;
+            if negative { -k } else { k }
+        };
+
+        let algebraic_degree = polynomial.degree();
+        let rational_degree = rational_polynomial.degree();
+        let norms: Vec<(BigInt, BigInt)> = (0..=algebraic_degree.max(rational_degree))
+            .map(|offset| Relation::norms(polynomial, rational_polynomial, &a_at(offset), b))
+            .collect();
+        let algebraic = forward_differences(norms.iter().take(algebraic_degree + 1).map(|(algebraic, _)| algebraic.clone()).collect());
+        let rational = forward_differences(norms.iter().take(rational_degree + 1).map(|(_, rational)| rational.clone()).collect());
+        NormWalk { k: k.clone(), algebraic, rational }
+    }
+
+    fn advance(&mut self) {
+        self.k += 1;
+        step(&mut self.algebraic);
+        step(&mut self.rational);
+    }
+}
+
+/// Turns consecutive values of a polynomial into its leading forward differences, in place.
+fn forward_differences(mut values: Vec<BigInt>) -> Vec<BigInt> {
+    let degree = values.len().saturating_sub(1);
+    for order in 1..=degree {
+        for i in (order..=degree).rev() {
+            values[i] = &values[i] - &values[i - 1];
+        }
+    }
+    values
+}
+
+/// Moves a forward difference table one step along.
+fn step(differences: &mut [BigInt]) {
+    for i in 0..differences.len().saturating_sub(1) {
+        let (lower, upper) = differences.split_at_mut(i + 1);
+        lower[i] += &upper[0];
+    }
+}
+
+/// Running (algebraic, rational) norms for the pairs of a single b row, in sieve order.
+#[derive(Debug, Clone)]
+pub struct RowNorms {
+    polynomial: Polynomial,
+    rational_polynomial: Polynomial,
+    b: BigInt,
+    positive: Option<NormWalk>,
+    negative: Option<NormWalk>,
+}
+
+impl RowNorms {
+    pub fn new(polynomial: &Polynomial, rational_polynomial: &Polynomial, b: &BigInt) -> Self {
+        RowNorms {
+            polynomial: polynomial.clone(),
+            rational_polynomial: rational_polynomial.clone(),
+            b: b.clone(),
+            positive: None,
+            negative: None,
+        }
+    }
+
+    /// The (algebraic, rational) norms of (a, b). Consecutive calls for the same sign should step
+    /// |a| by one; any other jump re-seeds that walk, so the result is always exact.
+    pub fn norms(&mut self, a: &BigInt) -> (BigInt, BigInt) {
+        let negative = a.is_negative();
+        let k = a.abs();
+        let walk = if negative { &mut self.negative } else { &mut self.positive };
+
+        match walk {
+            Some(current) if current.k == k => {}
+            Some(current) if current.k.clone() + BigInt::one() == k => current.advance(),
+            _ => *walk = Some(NormWalk::seed(&self.polynomial, &self.rational_polynomial, &self.b, &k, negative)),
+        }
+        let current = walk.as_ref().expect("walk seeded above");
+        (current.algebraic[0].clone(), current.rational[0].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sieve_range::SieveRange;
+
+    #[test]
+    fn running_norms_match_direct_evaluation() {
+        let f = Polynomial::parse("X^3 + 15X^2 + 29X + 8");
+        for g in [Polynomial::rational_side(&BigInt::from(31)), Polynomial::parse("3X^2 - 7X + 2")] {
+            for b in 1..=4 {
+                let b = BigInt::from(b);
+                let mut row = RowNorms::new(&f, &g, &b);
+                for a in SieveRange::get_sieve_range_continuation(&BigInt::from(-3), &BigInt::from(60)) {
+                    assert_eq!(row.norms(&a), Relation::norms(&f, &g, &a, &b), "a = {}, b = {}, g = {}", a, b, g);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,804 @@
+// src/relation_sieve/storage.rs
+
+//! The binary relation store. Each record holds a and b, the polynomial index, and per side the
+//! quotient and the factorization as factor base indices, all as LEB128 varints, so it is a
+//! fraction of the size of the JSON record and needs no text parsing. The norms are stored only
+//! when they aren't the product of the factorization and the quotient; otherwise just their signs
+//! are, in a flag byte.
+//!
+//! Layout, little-endian:
+//!
+//! ```text
+//! header   magic "GNFSREL" · version u8 · count u64 · index offset u64 · index interval u32
+//!          · rational base length u32 · algebraic base length u32
+//! records  length varint · record
+//! index    u64 offset of every `index interval`-th record
+//! ```
+//!
+//! The header is filled in by [`RelationWriter::finish`]; a file whose index offset is still zero
+//! was never finished. The factor base lengths record what the indices refer to; bases are only
+//! ever extended, so a store stays readable as long as the run's bases are at least that long.
+
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use memmap::Mmap;
+use std::str::FromStr;
+use log::info;
+use num::{BigInt, BigUint, One, Signed, ToPrimitive, Zero};
+use num::bigint::Sign;
+use serde::{Serialize, Deserialize};
+use crate::core::count_dictionary::CountDictionary;
+use crate::core::error::GnfsError;
+use crate::core::gnfs::GNFS;
+use crate::core::serialization::types::SerializableRelation;
+use crate::relation_sieve::relation::Relation;
+use crate::relation_sieve::relation_store::{self, RelationFileReader, SortSummary, SORTED_RELATIONS_FILENAME};
+
+pub const SORTED_RELATIONS_BINARY_FILENAME: &str = "SortedRelations.bin";
+
+const MAGIC: &[u8; 7] = b"GNFSREL";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_LENGTH: u64 = 36;
+/// Records between index entries; a seek reads past at most this many.
+pub const DEFAULT_INDEX_INTERVAL: u32 = 1024;
+
+const SIGN_BITS: u8 = 0b11;
+const RATIONAL_SIGN_SHIFT: u8 = 2;
+const PERSISTED: u8 = 1 << 4;
+/// The norms don't equal their factorization times the quotient and are stored as they are.
+const EXPLICIT_NORMS: u8 = 1 << 5;
+
+/// How the sorted relation store is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RelationFormat {
+    /// The compact format of this module.
+    #[default]
+    Binary,
+    /// One JSON record per line, as the relation files are; easy to read while debugging.
+    Jsonl,
+}
+
+impl RelationFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RelationFormat::Binary => "binary",
+            RelationFormat::Jsonl => "jsonl",
+        }
+    }
+
+    pub fn sorted_store_filename(&self) -> &'static str {
+        match self {
+            RelationFormat::Binary => SORTED_RELATIONS_BINARY_FILENAME,
+            RelationFormat::Jsonl => SORTED_RELATIONS_FILENAME,
+        }
+    }
+
+    /// JSONL for a `.jsonl` or `.json` path, binary otherwise.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("jsonl") | Some("json") => RelationFormat::Jsonl,
+            _ => RelationFormat::Binary,
+        }
+    }
+}
+
+impl Display for RelationFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for RelationFormat {
+    type Err = GnfsError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        [RelationFormat::Binary, RelationFormat::Jsonl]
+            .into_iter()
+            .find(|format| format.name() == input)
+            .ok_or_else(|| GnfsError::InvalidParameter(format!("unknown relation format {:?}; expected binary or jsonl", input)))
+    }
+}
+
+fn corrupt(path: &Path, reason: impl Display) -> GnfsError {
+    GnfsError::InvalidParameter(format!("{} is not a readable relation store: {}", path.display(), reason))
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_natural(out: &mut Vec<u8>, value: &BigUint) {
+    if let Some(value) = value.to_u64() {
+        return write_varint(out, value);
+    }
+    let mut value = value.clone();
+    let mask = BigUint::from(0x7fu8);
+    loop {
+        let low = (&value & &mask).to_u8().unwrap_or(0);
+        value >>= 7u32;
+        if value.is_zero() {
+            out.push(low);
+            return;
+        }
+        out.push(low | 0x80);
+    }
+}
+
+/// Zigzag: 0, -1, 1, -2, … become 0, 1, 2, 3, …
+fn write_integer(out: &mut Vec<u8>, value: &BigInt) {
+    let magnitude = value.magnitude();
+    let zigzag = if value.is_negative() { (magnitude << 1u32) - 1u32 } else { magnitude << 1u32 };
+    write_natural(out, &zigzag);
+}
+
+fn sign_bits(value: &BigInt) -> u8 {
+    match value.sign() {
+        Sign::NoSign => 0,
+        Sign::Plus => 1,
+        Sign::Minus => 2,
+    }
+}
+
+/// A record being decoded.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+}
+
+impl Cursor<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let (&first, rest) = self.bytes.split_first().ok_or("record ends early")?;
+        self.bytes = rest;
+        Ok(first)
+    }
+
+    fn natural(&mut self) -> Result<BigUint, String> {
+        let mut groups = Vec::new();
+        loop {
+            let byte = self.byte()?;
+            groups.push(byte & 0x7f);
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(groups.iter().rev().fold(BigUint::zero(), |value, &group| (value << 7u32) | BigUint::from(group)))
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        self.natural()?.to_u64().ok_or_else(|| "count out of range".to_string())
+    }
+
+    fn integer(&mut self) -> Result<BigInt, String> {
+        let zigzag = self.natural()?;
+        let magnitude = BigInt::from(&zigzag >> 1u32);
+        Ok(if (&zigzag & BigUint::one()).is_zero() { magnitude } else { -magnitude - 1 })
+    }
+}
+
+/// Writes relations in the binary format. The indices refer to the factor bases of the run the
+/// writer was created for.
+pub struct RelationWriter {
+    writer: BufWriter<File>,
+    rational_base: Vec<BigInt>,
+    algebraic_base: Vec<BigInt>,
+    index_interval: u32,
+    count: u64,
+    offset: u64,
+    index: Vec<u64>,
+    record: Vec<u8>,
+}
+
+impl RelationWriter {
+    pub fn create(path: &Path, gnfs: &GNFS) -> Result<Self, GnfsError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&[0; HEADER_LENGTH as usize])?;
+        Ok(RelationWriter {
+            writer,
+            rational_base: gnfs.prime_factor_base.rational_factor_base.clone(),
+            algebraic_base: gnfs.prime_factor_base.algebraic_factor_base.clone(),
+            index_interval: DEFAULT_INDEX_INTERVAL,
+            count: 0,
+            offset: HEADER_LENGTH,
+            index: Vec::new(),
+            record: Vec::new(),
+        })
+    }
+
+    pub fn write(&mut self, relation: &Relation) -> Result<(), GnfsError> {
+        if self.count.is_multiple_of(self.index_interval as u64) {
+            self.index.push(self.offset);
+        }
+        let record = &mut self.record;
+        record.clear();
+        write_integer(record, &relation.a);
+        write_integer(record, &relation.b);
+        write_varint(record, relation.polynomial as u64);
+        let explicit = factored_norm(&relation.algebraic_factorization, &relation.algebraic_quotient) != relation.algebraic_norm.abs()
+            || factored_norm(&relation.rational_factorization, &relation.rational_quotient) != relation.rational_norm.abs();
+        let mut flags = sign_bits(&relation.algebraic_norm) | sign_bits(&relation.rational_norm) << RATIONAL_SIGN_SHIFT;
+        if relation.is_persisted {
+            flags |= PERSISTED;
+        }
+        if explicit {
+            flags |= EXPLICIT_NORMS;
+        }
+        record.push(flags);
+        if explicit {
+            write_integer(record, &relation.algebraic_norm);
+            write_integer(record, &relation.rational_norm);
+        }
+        write_side(record, &self.algebraic_base, &relation.algebraic_factorization, &relation.algebraic_quotient);
+        write_side(record, &self.rational_base, &relation.rational_factorization, &relation.rational_quotient);
+
+        let mut length = Vec::with_capacity(4);
+        write_varint(&mut length, record.len() as u64);
+        self.writer.write_all(&length)?;
+        self.writer.write_all(record)?;
+        self.offset += (length.len() + record.len()) as u64;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Writes the index and the header and returns the number of relations written.
+    pub fn finish(mut self) -> Result<u64, GnfsError> {
+        for offset in &self.index {
+            self.writer.write_all(&offset.to_le_bytes())?;
+        }
+        let index_offset = self.offset;
+        let mut header = Vec::with_capacity(HEADER_LENGTH as usize);
+        header.extend_from_slice(MAGIC);
+        header.push(FORMAT_VERSION);
+        header.extend_from_slice(&self.count.to_le_bytes());
+        header.extend_from_slice(&index_offset.to_le_bytes());
+        header.extend_from_slice(&self.index_interval.to_le_bytes());
+        header.extend_from_slice(&(self.rational_base.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(self.algebraic_base.len() as u32).to_le_bytes());
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header)?;
+        self.writer.flush()?;
+        Ok(self.count)
+    }
+}
+
+/// |norm| as the factorization times the quotient; what [`FactorizationFactory::factor_with_base`](crate::integer_math::factorization_factory::FactorizationFactory::factor_with_base) leaves.
+fn factored_norm(factorization: &CountDictionary, quotient: &BigInt) -> BigInt {
+    factorization.iter().fold(quotient.clone(), |product, (prime, exponent)| product * prime.pow(exponent))
+}
+
+/// The quotient, then the factors found in `base` as index deltas with exponents, then any others
+/// as primes with exponents.
+fn write_side(record: &mut Vec<u8>, base: &[BigInt], factorization: &CountDictionary, quotient: &BigInt) {
+    write_natural(record, quotient.magnitude());
+    let mut indexed = Vec::with_capacity(factorization.len());
+    let mut outside = Vec::new();
+    for (prime, exponent) in factorization.iter() {
+        match base.binary_search(&prime) {
+            Ok(index) => indexed.push((index as u64, exponent)),
+            Err(_) => outside.push((prime, exponent)),
+        }
+    }
+    indexed.sort_unstable();
+    write_varint(record, indexed.len() as u64);
+    let mut previous = 0;
+    for (index, exponent) in indexed {
+        write_varint(record, index - previous);
+        write_varint(record, exponent as u64);
+        previous = index;
+    }
+    write_varint(record, outside.len() as u64);
+    for (prime, exponent) in outside {
+        write_natural(record, prime.magnitude());
+        write_varint(record, exponent as u64);
+    }
+}
+
+fn read_side(cursor: &mut Cursor, base: &[BigInt]) -> Result<(CountDictionary, BigInt), String> {
+    let quotient = BigInt::from(cursor.natural()?);
+    let mut factorization = CountDictionary::new();
+    let mut index = 0u64;
+    for _ in 0..cursor.varint()? {
+        index += cursor.varint()?;
+        let prime = base.get(index as usize).ok_or_else(|| format!("factor base index {} out of range", index))?;
+        factorization.add_count(prime, cursor.varint()? as u32);
+    }
+    for _ in 0..cursor.varint()? {
+        let prime = BigInt::from(cursor.natural()?);
+        factorization.add_count(&prime, cursor.varint()? as u32);
+    }
+    Ok((factorization, quotient))
+}
+
+fn signed(bits: u8, magnitude: BigInt) -> BigInt {
+    match bits {
+        2 => -magnitude,
+        1 => magnitude,
+        _ => BigInt::zero(),
+    }
+}
+
+/// What the header of a finished store says.
+struct Header {
+    count: u64,
+    index_offset: u64,
+    index_interval: u32,
+    rational_length: usize,
+    algebraic_length: usize,
+}
+
+impl Header {
+    fn parse(header: &[u8], path: &Path) -> Result<Self, GnfsError> {
+        if header.len() < HEADER_LENGTH as usize || &header[..7] != MAGIC {
+            return Err(corrupt(path, "not a binary relation store"));
+        }
+        if header[7] != FORMAT_VERSION {
+            return Err(corrupt(path, format!("format version {}, expected {}", header[7], FORMAT_VERSION)));
+        }
+        let u64_at = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+        let u32_at = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+        let header = Header {
+            count: u64_at(8),
+            index_offset: u64_at(16),
+            index_interval: u32_at(24).max(1),
+            rational_length: u32_at(28) as usize,
+            algebraic_length: u32_at(32) as usize,
+        };
+        if header.index_offset == 0 {
+            return Err(corrupt(path, "the store was never finished"));
+        }
+        Ok(header)
+    }
+
+    fn index_entries(&self) -> u64 {
+        self.count.div_ceil(self.index_interval as u64)
+    }
+
+    /// The (rational, algebraic) factor base prefixes of `gnfs` the store's indices refer to.
+    fn bases(&self, gnfs: &GNFS, path: &Path) -> Result<(Vec<BigInt>, Vec<BigInt>), GnfsError> {
+        let bases = &gnfs.prime_factor_base;
+        if self.rational_length > bases.rational_factor_base.len() || self.algebraic_length > bases.algebraic_factor_base.len() {
+            return Err(corrupt(path, "written for larger factor bases than the run has"));
+        }
+        Ok((bases.rational_factor_base[..self.rational_length].to_vec(), bases.algebraic_factor_base[..self.algebraic_length].to_vec()))
+    }
+}
+
+/// Streams the relations of a binary store in the order they were written.
+pub struct RelationReader {
+    reader: BufReader<File>,
+    rational_base: Vec<BigInt>,
+    algebraic_base: Vec<BigInt>,
+    count: u64,
+    index_interval: u32,
+    index: Vec<u64>,
+    /// The number of the record read next.
+    next: u64,
+    record: Vec<u8>,
+}
+
+impl RelationReader {
+    /// Opens the store at `path` for the run of `gnfs`, whose factor bases its indices refer to.
+    pub fn open(path: &Path, gnfs: &GNFS) -> Result<Self, GnfsError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut bytes = [0u8; HEADER_LENGTH as usize];
+        reader.read_exact(&mut bytes).map_err(|_| corrupt(path, "no header"))?;
+        let header = Header::parse(&bytes, path)?;
+        let (rational_base, algebraic_base) = header.bases(gnfs, path)?;
+
+        reader.seek(SeekFrom::Start(header.index_offset))?;
+        let mut index = Vec::with_capacity(header.index_entries() as usize);
+        let mut entry = [0u8; 8];
+        for _ in 0..header.index_entries() {
+            reader.read_exact(&mut entry).map_err(|_| corrupt(path, "the index is cut short"))?;
+            index.push(u64::from_le_bytes(entry));
+        }
+        reader.seek(SeekFrom::Start(HEADER_LENGTH))?;
+        Ok(RelationReader {
+            reader,
+            rational_base,
+            algebraic_base,
+            count: header.count,
+            index_interval: header.index_interval,
+            index,
+            next: 0,
+            record: Vec::new(),
+        })
+    }
+
+    /// The number of relations in the store.
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Moves to record `record`, so the next relation read is that one; past the end, nothing is
+    /// left to read.
+    pub fn seek(&mut self, record: u64) -> Result<(), GnfsError> {
+        if record >= self.count {
+            self.next = self.count;
+            return Ok(());
+        }
+        let entry = (record / self.index_interval as u64) as usize;
+        self.reader.seek(SeekFrom::Start(self.index[entry]))?;
+        self.next = entry as u64 * self.index_interval as u64;
+        while self.next < record {
+            let length = self.read_length()?;
+            self.reader.seek_relative(length as i64)?;
+            self.next += 1;
+        }
+        Ok(())
+    }
+
+    fn read_length(&mut self) -> Result<u64, GnfsError> {
+        let mut length = 0u64;
+        let mut byte = [0u8; 1];
+        for shift in (0..64).step_by(7) {
+            self.reader.read_exact(&mut byte)?;
+            length |= ((byte[0] & 0x7f) as u64) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(length);
+            }
+        }
+        Err(GnfsError::InvalidParameter(format!("relation record {} has a malformed length", self.next)))
+    }
+
+    fn read_relation(&mut self) -> Result<Relation, GnfsError> {
+        let length = self.read_length()?;
+        self.record.resize(length as usize, 0);
+        self.reader.read_exact(&mut self.record)?;
+        let mut cursor = Cursor { bytes: &self.record };
+        self.next += 1;
+        decode(&mut cursor, &self.algebraic_base, &self.rational_base)
+            .map_err(|reason| GnfsError::InvalidParameter(format!("relation record {}: {}", self.next - 1, reason)))
+    }
+}
+
+fn decode(cursor: &mut Cursor, algebraic_base: &[BigInt], rational_base: &[BigInt]) -> Result<Relation, String> {
+    let a = cursor.integer()?;
+    let b = cursor.integer()?;
+    let polynomial = cursor.varint()? as usize;
+    let flags = cursor.byte()?;
+    let explicit_norms = if flags & EXPLICIT_NORMS != 0 { Some((cursor.integer()?, cursor.integer()?)) } else { None };
+    let (algebraic_factorization, algebraic_quotient) = read_side(cursor, algebraic_base)?;
+    let (rational_factorization, rational_quotient) = read_side(cursor, rational_base)?;
+    let (algebraic_norm, rational_norm) = explicit_norms.unwrap_or_else(|| {
+        (
+            signed(flags & SIGN_BITS, factored_norm(&algebraic_factorization, &algebraic_quotient)),
+            signed(flags >> RATIONAL_SIGN_SHIFT & SIGN_BITS, factored_norm(&rational_factorization, &rational_quotient)),
+        )
+    });
+    Ok(Relation {
+        a,
+        b,
+        algebraic_norm,
+        rational_norm,
+        algebraic_quotient,
+        rational_quotient,
+        algebraic_factorization,
+        rational_factorization,
+        is_persisted: flags & PERSISTED != 0,
+        polynomial,
+    })
+}
+
+impl Iterator for RelationReader {
+    type Item = Result<Relation, GnfsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.count {
+            return None;
+        }
+        let relation = self.read_relation();
+        if relation.is_err() {
+            // Nothing after a broken record can be trusted to line up.
+            self.next = self.count;
+        }
+        Some(relation)
+    }
+}
+
+/// Decoded relations kept by a [`RelationStore`], least recently used evicted first.
+struct LruCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<usize, (Arc<Relation>, u64)>,
+    /// Record numbers by the tick they were last used at.
+    order: BTreeMap<u64, usize>,
+    hits: u64,
+    misses: u64,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        LruCache { capacity, tick: 0, entries: HashMap::new(), order: BTreeMap::new(), hits: 0, misses: 0 }
+    }
+
+    fn get(&mut self, index: usize) -> Option<Arc<Relation>> {
+        self.tick += 1;
+        let Some((relation, used)) = self.entries.get_mut(&index) else {
+            self.misses += 1;
+            return None;
+        };
+        self.order.remove(used);
+        *used = self.tick;
+        self.order.insert(self.tick, index);
+        self.hits += 1;
+        Some(relation.clone())
+    }
+
+    fn insert(&mut self, index: usize, relation: Arc<Relation>) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            let Some((_, evicted)) = self.order.pop_first() else { break };
+            self.entries.remove(&evicted);
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, index);
+        self.entries.insert(index, (relation, self.tick));
+    }
+}
+
+/// A binary store mapped into memory, for reading relations by record number without holding
+/// them all decoded. Only the offset of each record is kept, eight bytes a relation; up to
+/// `cache_capacity` decoded relations are kept besides, the least recently used going first.
+pub struct RelationStore {
+    map: Mmap,
+    path: PathBuf,
+    rational_base: Vec<BigInt>,
+    algebraic_base: Vec<BigInt>,
+    /// Where each record, from its length on, starts in the file.
+    offsets: Vec<u64>,
+    cache: Mutex<LruCache>,
+}
+
+impl std::fmt::Debug for RelationStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RelationStore").field("path", &self.path).field("relations", &self.offsets.len()).finish()
+    }
+}
+
+impl RelationStore {
+    /// Maps the store at `path` for the run of `gnfs`, whose factor bases its indices refer to.
+    pub fn open(path: &Path, gnfs: &GNFS, cache_capacity: usize) -> Result<Self, GnfsError> {
+        let file = File::open(path)?;
+        // The store is only ever replaced by renaming a new file over it, never written in place.
+        let map = unsafe { Mmap::map(&file)? };
+        let header = Header::parse(&map, path)?;
+        let (rational_base, algebraic_base) = header.bases(gnfs, path)?;
+
+        let records = map.get(..header.index_offset as usize).ok_or_else(|| corrupt(path, "the index is missing"))?;
+        if (map.len() as u64 - header.index_offset) / 8 < header.index_entries() {
+            return Err(corrupt(path, "the index is cut short"));
+        }
+        let mut offsets = Vec::with_capacity(header.count as usize);
+        let mut offset = HEADER_LENGTH as usize;
+        for _ in 0..header.count {
+            offsets.push(offset as u64);
+            let mut cursor = Cursor { bytes: records.get(offset..).unwrap_or_default() };
+            let length = cursor.varint().map_err(|reason| corrupt(path, reason))? as usize;
+            offset = records.len() - cursor.bytes.len() + length;
+            if offset > records.len() {
+                return Err(corrupt(path, "a record runs into the index"));
+            }
+        }
+        Ok(RelationStore { map, path: path.to_path_buf(), rational_base, algebraic_base, offsets, cache: Mutex::new(LruCache::new(cache_capacity)) })
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    fn decode_at(&self, index: usize) -> Result<Relation, GnfsError> {
+        let mut cursor = Cursor { bytes: &self.map[self.offsets[index] as usize..] };
+        let length = cursor.varint().map_err(|reason| corrupt(&self.path, reason))? as usize;
+        cursor.bytes = &cursor.bytes[..length.min(cursor.bytes.len())];
+        decode(&mut cursor, &self.algebraic_base, &self.rational_base)
+            .map_err(|reason| GnfsError::InvalidParameter(format!("{}: relation record {}: {}", self.path.display(), index, reason)))
+    }
+
+    /// Relation `index`, from the cache when it was read recently.
+    pub fn get(&self, index: usize) -> Result<Arc<Relation>, GnfsError> {
+        if index >= self.len() {
+            return Err(GnfsError::InvalidParameter(format!("relation {} of a store holding {}", index, self.len())));
+        }
+        if let Some(relation) = self.cache.lock().unwrap().get(index) {
+            return Ok(relation);
+        }
+        let relation = Arc::new(self.decode_at(index)?);
+        self.cache.lock().unwrap().insert(index, relation.clone());
+        Ok(relation)
+    }
+
+    /// Decodes every relation in order, past the cache, for a single pass over the store.
+    pub fn iter(&self) -> impl Iterator<Item = Result<Relation, GnfsError>> + '_ {
+        (0..self.len()).map(|index| self.decode_at(index))
+    }
+
+    /// Reads served from the cache and reads that had to decode, so far.
+    pub fn cache_statistics(&self) -> (u64, u64) {
+        let cache = self.cache.lock().unwrap();
+        (cache.hits, cache.misses)
+    }
+}
+
+/// Whether the file at `path` starts like a binary relation store.
+pub fn is_binary(path: &Path) -> bool {
+    let mut magic = [0u8; 7];
+    File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && &magic == MAGIC
+}
+
+/// [`relation_store::sort_and_dedup`], writing the store in the binary format for the run of `gnfs`.
+pub fn sort_and_dedup(gnfs: &GNFS, inputs: &[PathBuf], output: &Path, run_length: usize) -> Result<SortSummary, GnfsError> {
+    let staging = relation_store::sibling_path(output, ".partial");
+    let mut writer = RelationWriter::create(&staging, gnfs)?;
+    let summary = relation_store::merge_runs(inputs, output, run_length, |relation| writer.write(&Relation::from(relation)))?;
+    writer.finish()?;
+    fs::rename(&staging, output)?;
+
+    info!(
+        "Sorted relation store written to {}: {} unique of {} relations read, {} runs merged.",
+        output.display(), summary.unique_relations, summary.records_read, summary.runs
+    );
+    Ok(summary)
+}
+
+/// Rewrites the relations of `input`, a binary store or any relation file [`RelationFileReader`]
+/// reads, to `output` in `format`, and returns how many were written. The order is kept.
+pub fn convert(gnfs: &GNFS, input: &Path, output: &Path, format: RelationFormat) -> Result<u64, GnfsError> {
+    let relations: Box<dyn Iterator<Item = Result<Relation, GnfsError>>> = if is_binary(input) {
+        Box::new(RelationReader::open(input, gnfs)?)
+    } else {
+        Box::new(RelationFileReader::open(input)?.map(|record| record.map(Relation::from)))
+    };
+    let staging = relation_store::sibling_path(output, ".partial");
+    let written = match format {
+        RelationFormat::Binary => {
+            let mut writer = RelationWriter::create(&staging, gnfs)?;
+            for relation in relations {
+                writer.write(&relation?)?;
+            }
+            writer.finish()?
+        }
+        RelationFormat::Jsonl => {
+            let mut writer = BufWriter::new(File::create(&staging)?);
+            let mut written = 0;
+            for relation in relations {
+                serde_json::to_writer(&mut writer, &SerializableRelation::from(relation?))?;
+                writer.write_all(b"\n")?;
+                written += 1;
+            }
+            writer.flush()?;
+            written
+        }
+    };
+    fs::rename(&staging, output)?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cancellation_token::CancellationToken;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::gnfs_config::GnfsConfig;
+
+    #[test]
+    fn round_trips_relations_and_seeks_by_index() {
+        let cancel_token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("storage").unwrap();
+        let n = BigInt::from(45113);
+        let gnfs = GNFS::with_save_locations(&cancel_token, locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let directory = Path::new(&gnfs.save_locations.save_directory);
+
+        let mut relations = Vec::new();
+        for b in 1..40i64 {
+            for a in -60..60i64 {
+                let mut relation = Relation::new(&gnfs, &BigInt::from(a), &BigInt::from(b));
+                relation.sieve(&gnfs);
+                relations.push(relation);
+            }
+        }
+        // A factor outside the base and norms that don't match their factorization.
+        relations[0].algebraic_factorization.add(&BigInt::from(1_000_003));
+        relations[1].rational_norm += 1;
+
+        let path = directory.join(SORTED_RELATIONS_BINARY_FILENAME);
+        let mut writer = RelationWriter::create(&path, &gnfs).unwrap();
+        for relation in &relations {
+            writer.write(relation).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), relations.len() as u64);
+        assert!(is_binary(&path));
+
+        let same = |left: &Relation, right: &Relation| {
+            let text = |relation: &Relation| serde_json::to_string(&SerializableRelation::from(relation.clone())).unwrap();
+            text(left) == text(right)
+        };
+        let mut reader = RelationReader::open(&path, &gnfs).unwrap();
+        assert_eq!(reader.len(), relations.len() as u64);
+        let read: Vec<Relation> = reader.by_ref().map(Result::unwrap).collect();
+        assert!(read.iter().zip(&relations).all(|(left, right)| same(left, right)));
+        reader.seek(2500).unwrap();
+        assert!(same(&reader.next().unwrap().unwrap(), &relations[2500]));
+
+        let store = RelationStore::open(&path, &gnfs, 2).unwrap();
+        assert_eq!(store.len(), relations.len());
+        for index in [7, 4000, 7, 12, 7, 4000] {
+            assert!(same(&store.get(index).unwrap(), &relations[index]));
+        }
+        // Reading 7 again made 4000 the least recently used, so 12 evicted it.
+        assert_eq!(store.cache_statistics(), (2, 4));
+        assert!(store.iter().zip(&relations).all(|(left, right)| same(&left.unwrap(), right)));
+
+        let jsonl = directory.join("relations.jsonl");
+        assert_eq!(convert(&gnfs, &path, &jsonl, RelationFormat::Jsonl).unwrap(), relations.len() as u64);
+        let back = directory.join("relations.bin");
+        convert(&gnfs, &jsonl, &back, RelationFormat::Binary).unwrap();
+        assert!(std::fs::metadata(&back).unwrap().len() * 3 < std::fs::metadata(&jsonl).unwrap().len());
+        let converted: Vec<Relation> = RelationReader::open(&back, &gnfs).unwrap().map(Result::unwrap).collect();
+        assert!(converted.iter().zip(&relations).all(|(left, right)| same(left, right)));
+    }
+
+    #[test]
+    fn mapped_store_evicts_and_reloads_and_rejects_truncated_files() {
+        let cancel_token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("storage_eviction").unwrap();
+        let n = BigInt::from(45113);
+        let gnfs = GNFS::with_save_locations(&cancel_token, locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let directory = Path::new(&gnfs.save_locations.save_directory);
+
+        let relations: Vec<Relation> = (1..=20i64)
+            .map(|a| {
+                let mut relation = Relation::new(&gnfs, &BigInt::from(a), &BigInt::from(3));
+                relation.sieve(&gnfs);
+                relation
+            })
+            .collect();
+        let path = directory.join(SORTED_RELATIONS_BINARY_FILENAME);
+        let mut writer = RelationWriter::create(&path, &gnfs).unwrap();
+        for relation in &relations {
+            writer.write(relation).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let store = RelationStore::open(&path, &gnfs, 3).unwrap();
+        let first: Vec<Arc<Relation>> = (0..3).map(|index| store.get(index).unwrap()).collect();
+        assert!(Arc::ptr_eq(&store.get(0).unwrap(), &first[0]));
+        // 3 and 4 evict 1 and 2, the least recently used; 0 was just read and stays.
+        store.get(3).unwrap();
+        store.get(4).unwrap();
+        assert!(Arc::ptr_eq(&store.get(0).unwrap(), &first[0]));
+        let reloaded = store.get(1).unwrap();
+        assert!(!Arc::ptr_eq(&reloaded, &first[1]));
+        assert_eq!((reloaded.a.clone(), reloaded.b.clone()), (relations[1].a.clone(), relations[1].b.clone()));
+        assert_eq!(reloaded.algebraic_factorization, relations[1].algebraic_factorization);
+        assert_eq!(store.cache_statistics(), (2, 6));
+        assert!(store.get(relations.len()).is_err());
+        drop(store);
+
+        // Cut in the header, in the records and in the index: each is an error, never a panic.
+        let bytes = std::fs::read(&path).unwrap();
+        let truncated = directory.join("truncated.bin");
+        for length in [0, 5, HEADER_LENGTH as usize - 1, HEADER_LENGTH as usize + 10, bytes.len() / 2, bytes.len() - 1] {
+            std::fs::write(&truncated, &bytes[..length]).unwrap();
+            assert!(RelationStore::open(&truncated, &gnfs, 3).is_err(), "store cut to {} of {} bytes", length, bytes.len());
+            assert!(RelationReader::open(&truncated, &gnfs).is_err(), "reader cut to {} of {} bytes", length, bytes.len());
+        }
+    }
+}
@@ -0,0 +1,207 @@
+// src/relation_sieve/verifier.rs
+
+//! Re-checks stored relations before they reach the matrix. A relation read back from disk is
+//! trusted for its norms and factorizations, so a truncated write, a hand-edited file or relations
+//! saved under other parameters would otherwise end up as columns that can never combine into a
+//! square. Every relation is recomputed from (a, b) and dropped when anything disagrees, as are
+//! repeated (a, b) pairs, which would make the matrix find trivial dependencies.
+
+use std::collections::HashSet;
+use std::fmt::Display;
+use num::{BigInt, Integer, One, Signed};
+use crate::core::count_dictionary::CountDictionary;
+use crate::core::gnfs::GNFS;
+use crate::relation_sieve::relation::Relation;
+
+/// Why a relation was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Defect {
+    /// gcd(a, b) ≠ 1, or b ≤ 0.
+    NotCoprime,
+    /// A stored norm differs from the one recomputed from (a, b).
+    WrongNorm,
+    /// The factorization times the quotient is not the norm.
+    WrongFactorization,
+    /// A factor is missing from its side's factor base.
+    OutsideFactorBase,
+    /// A relation used on its own has a quotient other than 1.
+    NotSmooth,
+}
+
+/// What [`verify_relations`] checked and dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// Smooth relations plus combined relation sets examined.
+    pub checked: usize,
+    pub kept: usize,
+    pub duplicates: usize,
+    pub not_coprime: usize,
+    pub wrong_norm: usize,
+    pub wrong_factorization: usize,
+    pub outside_factor_base: usize,
+    pub not_smooth: usize,
+}
+
+impl VerificationReport {
+    pub fn dropped(&self) -> usize {
+        self.checked - self.kept
+    }
+
+    fn record(&mut self, defect: Defect) {
+        match defect {
+            Defect::NotCoprime => self.not_coprime += 1,
+            Defect::WrongNorm => self.wrong_norm += 1,
+            Defect::WrongFactorization => self.wrong_factorization += 1,
+            Defect::OutsideFactorBase => self.outside_factor_base += 1,
+            Defect::NotSmooth => self.not_smooth += 1,
+        }
+    }
+}
+
+impl Display for VerificationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Relation verification: {}/{} kept; dropped {} duplicate, {} not coprime, {} with a wrong norm, {} with a wrong factorization, {} outside the factor base, {} not smooth",
+            self.kept,
+            self.checked,
+            self.duplicates,
+            self.not_coprime,
+            self.wrong_norm,
+            self.wrong_factorization,
+            self.outside_factor_base,
+            self.not_smooth
+        )
+    }
+}
+
+/// Checks `relation` against the polynomials and factor bases of `gnfs`. The quotients must be 1
+/// when `smooth`; otherwise, as for the halves of a combined relation, they only have to complete
+/// the factorizations.
+pub fn check(gnfs: &GNFS, relation: &Relation, smooth: bool) -> Result<(), Defect> {
+    if !relation.b.is_positive() || !relation.a.gcd(&relation.b).is_one() {
+        return Err(Defect::NotCoprime);
+    }
+    let polynomial = match relation.polynomial {
+        0 => &gnfs.current_polynomial,
+        index => gnfs.polynomial_collection.get(index).ok_or(Defect::WrongNorm)?,
+    };
+    let norms = Relation::norms(polynomial, &gnfs.rational_polynomial, &relation.a, &relation.b);
+    if norms != (relation.algebraic_norm.clone(), relation.rational_norm.clone()) {
+        return Err(Defect::WrongNorm);
+    }
+    let base = &gnfs.prime_factor_base;
+    check_side(&relation.algebraic_norm, &relation.algebraic_factorization, &relation.algebraic_quotient, &base.algebraic_factor_base)?;
+    check_side(&relation.rational_norm, &relation.rational_factorization, &relation.rational_quotient, &base.rational_factor_base)?;
+    if smooth && !relation.is_smooth() {
+        return Err(Defect::NotSmooth);
+    }
+    Ok(())
+}
+
+fn check_side(norm: &BigInt, factorization: &CountDictionary, quotient: &BigInt, factor_base: &[BigInt]) -> Result<(), Defect> {
+    if factorization.iter().any(|(prime, _)| factor_base.binary_search(&prime).is_err()) {
+        return Err(Defect::OutsideFactorBase);
+    }
+    let product: BigInt = factorization.iter().map(|(prime, exponent)| prime.pow(exponent)).product();
+    if !quotient.is_positive() || product * quotient != norm.abs() {
+        return Err(Defect::WrongFactorization);
+    }
+    Ok(())
+}
+
+/// Drops every smooth relation and combined relation set of `gnfs` that fails [`check`] or
+/// repeats one kept earlier, and recounts the smooth relations.
+pub fn verify_relations(gnfs: &mut GNFS) -> VerificationReport {
+    let mut report = VerificationReport::default();
+    let mut relations = std::mem::take(&mut gnfs.current_relations_progress.relations);
+
+    let mut seen = HashSet::new();
+    relations.smooth_relations.retain(|relation| {
+        report.checked += 1;
+        if let Err(defect) = check(gnfs, relation, true) {
+            report.record(defect);
+            false
+        } else if !seen.insert((relation.a.clone(), relation.b.clone())) {
+            report.duplicates += 1;
+            false
+        } else {
+            true
+        }
+    });
+
+    let mut seen_sets = HashSet::new();
+    relations.combined_relations.retain(|set| {
+        report.checked += 1;
+        if let Some(defect) = set.iter().find_map(|relation| check(gnfs, relation, false).err()) {
+            report.record(defect);
+            return false;
+        }
+        let mut key: Vec<(BigInt, BigInt)> = set.iter().map(|relation| (relation.a.clone(), relation.b.clone())).collect();
+        key.sort();
+        if seen_sets.insert(key) {
+            true
+        } else {
+            report.duplicates += 1;
+            false
+        }
+    });
+
+    report.kept = relations.smooth_relations.len() + relations.combined_relations.len();
+    gnfs.current_relations_progress.smooth_relations_counter = report.kept;
+    gnfs.current_relations_progress.relations = relations;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polynomial::polynomial::Polynomial;
+
+    fn sieved_gnfs() -> GNFS {
+        let mut gnfs = GNFS {
+            current_polynomial: Polynomial::parse("X^3 + 15X^2 + 29X + 8"),
+            rational_polynomial: Polynomial::parse("X - 31"),
+            ..GNFS::default()
+        };
+        let primes: Vec<BigInt> = (2u32..200).filter(|p| (2..*p).all(|d| p % d != 0)).map(BigInt::from).collect();
+        gnfs.prime_factor_base.algebraic_factor_base = primes.clone();
+        gnfs.prime_factor_base.rational_factor_base = primes;
+
+        let mut smooth = Vec::new();
+        for a in -60i64..60 {
+            let mut relation = Relation::new(&gnfs, &BigInt::from(a), &BigInt::one());
+            relation.sieve(&gnfs);
+            if relation.is_smooth() {
+                smooth.push(relation);
+            }
+        }
+        gnfs.current_relations_progress.relations.smooth_relations = smooth;
+        gnfs
+    }
+
+    #[test]
+    fn keeps_sound_relations_and_drops_the_rest() {
+        let mut gnfs = sieved_gnfs();
+        let sound = gnfs.current_relations_progress.relations.smooth_relations.clone();
+        assert!(sound.len() > 4);
+
+        let relations = &mut gnfs.current_relations_progress.relations.smooth_relations;
+        relations.push(sound[0].clone());
+        let mut wrong_norm = sound[1].clone();
+        wrong_norm.rational_norm += 1;
+        relations.push(wrong_norm);
+        let mut wrong_factorization = sound[2].clone();
+        wrong_factorization.algebraic_factorization.add(&BigInt::from(2));
+        relations.push(wrong_factorization);
+        let mut outside = sound[3].clone();
+        outside.rational_factorization.add(&BigInt::from(4));
+        relations.push(outside);
+
+        let report = verify_relations(&mut gnfs);
+        assert_eq!(report.kept, sound.len());
+        assert_eq!((report.duplicates, report.wrong_norm, report.wrong_factorization, report.outside_factor_base), (1, 1, 1, 1));
+        assert_eq!(gnfs.current_relations_progress.relations.smooth_relations, sound);
+        assert_eq!(gnfs.current_relations_progress.smooth_relations_counter, sound.len());
+    }
+}
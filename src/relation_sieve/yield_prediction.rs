@@ -0,0 +1,404 @@
+// src/relation_sieve/yield_prediction.rs
+
+//! A forecast of the sieve before it starts. Smoothness probabilities come from Dickman's ρ applied
+//! to the norm sizes across the sieve region, corrected by Murphy's α for the root properties of
+//! each polynomial; with the region size and a timed sample of pairs they give relations per b row
+//! and a sieving time. After the first batch the forecast is checked against what the sieve
+//! actually found: a shortfall means the polynomial or the parameters are off and is warned about,
+//! while an overshoot only means the model was too cautious.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::time::Instant;
+use num::{BigInt, Integer, One, Signed, ToPrimitive, Zero};
+use crate::core::gnfs::GNFS;
+use crate::relation_sieve::log_sieve::LineSieve;
+use crate::relation_sieve::poly_relations_sieve_progress::SieveStatistics;
+use crate::relation_sieve::relation::Relation;
+
+/// Share of (a, b) pairs that are coprime, 6/π².
+const COPRIME_DENSITY: f64 = 0.607_927_101_854_026_6;
+/// Angles sampled around the region's boundary for the Murphy E integral.
+const MURPHY_E_SAMPLES: usize = 200;
+/// Grid points per axis when averaging smoothness over the region.
+const REGION_SAMPLES: i64 = 24;
+/// Primes above this contribute too little to α to be worth summing.
+const ALPHA_PRIME_BOUND: u64 = 2000;
+/// Pairs sieved to time the per-pair cost.
+const TIMING_SAMPLE_PAIRS: usize = 256;
+/// Realized yield below the prediction divided by this is a shortfall worth a warning.
+const YIELD_SHORTFALL_RATIO: f64 = 4.0;
+/// Realized yield above the prediction times this is reported too, since it means the model is
+/// off, but it costs nothing.
+const YIELD_OVERSHOOT_RATIO: f64 = 4.0;
+/// Step used to tabulate ρ.
+const RHO_STEP: f64 = 1.0 / 256.0;
+
+/// Dickman's ρ: the probability that a random integer x has no prime factor above x^(1/u).
+/// Tabulated from ρ'(u) = -ρ(u - 1)/u with the trapezoidal rule.
+pub fn dickman_rho(u: f64) -> f64 {
+    if u <= 1.0 {
+        return 1.0;
+    }
+    if u <= 2.0 {
+        return 1.0 - u.ln();
+    }
+    if u > 20.0 {
+        // ρ is below 10⁻²⁵ here; u^-u keeps the ordering without the table.
+        return u.powf(-u);
+    }
+
+    let per_unit = (1.0 / RHO_STEP) as usize;
+    let steps = (u / RHO_STEP).ceil() as usize;
+    let mut table = Vec::with_capacity(steps + 1);
+    for i in 0..=steps {
+        let x = i as f64 * RHO_STEP;
+        let value = if x <= 2.0 {
+            if x <= 1.0 { 1.0 } else { 1.0 - x.ln() }
+        } else {
+            let previous: f64 = table[i - 1];
+            let slope_before = table[i - 1 - per_unit] / (x - RHO_STEP);
+            let slope_after = table[i - per_unit] / x;
+            previous - RHO_STEP * (slope_before + slope_after) / 2.0
+        };
+        table.push(value.max(0.0));
+    }
+
+    let position = u / RHO_STEP;
+    let below = position.floor() as usize;
+    let fraction = position - below as f64;
+    let above = (below + 1).min(steps);
+    table[below] * (1.0 - fraction) + table[above] * fraction
+}
+
+/// Murphy's α for a polynomial with `roots[p]` roots mod p (projective ones included) over the
+/// given primes: the expected log-size advantage of its values over random integers. Negative is
+/// good; a linear polynomial comes out slightly positive.
+pub fn alpha<'a>(primes: impl IntoIterator<Item = &'a BigInt>, roots: &HashMap<u64, usize>) -> f64 {
+    primes
+        .into_iter()
+        .filter_map(|p| p.to_u64())
+        .take_while(|&p| p <= ALPHA_PRIME_BOUND)
+        .map(|p| {
+            let p_float = p as f64;
+            let root_count = roots.get(&p).copied().unwrap_or(0) as f64;
+            (1.0 - root_count * p_float / (p_float + 1.0)) * p_float.ln() / (p_float - 1.0)
+        })
+        .sum()
+}
+
+/// ln |x|, which stays finite for values far beyond f64's range.
+pub(crate) fn ln_abs(x: &BigInt) -> f64 {
+    let bits = x.bits();
+    if bits <= 1000 {
+        return x.abs().to_f64().unwrap_or(f64::MAX).ln();
+    }
+    let shift = bits - 64;
+    (x.abs() >> shift).to_f64().unwrap_or(f64::MAX).ln() + shift as f64 * std::f64::consts::LN_2
+}
+
+/// Probability that both norms of a pair are smooth over their bounds.
+fn smoothness_probability(algebraic: &BigInt, rational: &BigInt, alphas: (f64, f64), log_bounds: (f64, f64)) -> f64 {
+    if algebraic.is_zero() || rational.is_zero() {
+        return 0.0;
+    }
+    let u_algebraic = ((ln_abs(algebraic) + alphas.0) / log_bounds.0).max(0.0);
+    let u_rational = ((ln_abs(rational) + alphas.1) / log_bounds.1).max(0.0);
+    dickman_rho(u_algebraic) * dickman_rho(u_rational)
+}
+
+/// Smoothness probability at the pair nearest (a, b).
+fn probability_at(gnfs: &GNFS, a: f64, b: f64, alphas: (f64, f64), log_bounds: (f64, f64)) -> f64 {
+    let (a, b) = (BigInt::from(a.round() as i64), BigInt::from((b.round() as i64).max(1)));
+    let (algebraic, rational) = Relation::norms(&gnfs.current_polynomial, &gnfs.rational_polynomial, &a, &b);
+    smoothness_probability(&algebraic, &rational, alphas, log_bounds)
+}
+
+/// Mean smoothness probability over a grid covering |a| ≤ a_range, 1 ≤ b ≤ b_range.
+fn region_probability(gnfs: &GNFS, a_range: f64, b_range: f64, alphas: (f64, f64), log_bounds: (f64, f64)) -> f64 {
+    let mut total = 0.0;
+    for i in 0..REGION_SAMPLES {
+        for j in 0..REGION_SAMPLES {
+            let a = a_range * (2.0 * (i as f64 + 0.5) / REGION_SAMPLES as f64 - 1.0);
+            let b = 1.0 + (b_range - 1.0) * (j as f64 + 0.5) / REGION_SAMPLES as f64;
+            total += probability_at(gnfs, a, b, alphas, log_bounds);
+        }
+    }
+    total / (REGION_SAMPLES * REGION_SAMPLES) as f64
+}
+
+/// Smooth relations expected from the row b across |a| ≤ a_range.
+fn row_yield(gnfs: &GNFS, a_range: f64, b: f64, alphas: (f64, f64), log_bounds: (f64, f64)) -> f64 {
+    let probability = (0..REGION_SAMPLES)
+        .map(|i| probability_at(gnfs, a_range * (2.0 * (i as f64 + 0.5) / REGION_SAMPLES as f64 - 1.0), b, alphas, log_bounds))
+        .sum::<f64>()
+        / REGION_SAMPLES as f64;
+    2.0 * a_range * COPRIME_DENSITY * probability
+}
+
+/// Rows sieved upwards from b = 1 before `target` relations are expected. Yield falls with b, so
+/// the rows are walked in bands rather than priced at the region's average; past the planned
+/// rows the last band's yield carries on.
+fn rows_needed(gnfs: &GNFS, a_range: f64, b_range: f64, alphas: (f64, f64), log_bounds: (f64, f64), target: f64) -> f64 {
+    let band = (b_range / REGION_SAMPLES as f64).max(1.0);
+    let (mut rows, mut found, mut per_row) = (0.0, 0.0, 0.0);
+    while rows < b_range {
+        per_row = row_yield(gnfs, a_range, 1.0 + rows + band / 2.0, alphas, log_bounds);
+        if per_row > 0.0 && found + per_row * band >= target {
+            return rows + (target - found) / per_row;
+        }
+        found += per_row * band;
+        rows += band;
+    }
+    if per_row > 0.0 { rows + (target - found) / per_row } else { f64::INFINITY }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct YieldPrediction {
+    /// Murphy's E over the ellipse bounding the sieve region; only comparable between
+    /// polynomials for the same N and bounds.
+    pub murphy_e: f64,
+    pub alpha_algebraic: f64,
+    pub alpha_rational: f64,
+    /// Mean probability that a coprime pair in the region is smooth on both sides.
+    pub smooth_probability: f64,
+    pub relations_per_b: f64,
+    /// Smooth relations expected from the b rows currently planned.
+    pub expected_relations: f64,
+    pub target_quantity: usize,
+    pub seconds_per_pair: f64,
+    /// Time to sieve the rows that reach the target at the predicted yield; infinite when no pair
+    /// is expected to be smooth.
+    pub predicted_seconds: f64,
+    a_range: f64,
+    log_bounds: (f64, f64),
+}
+
+impl YieldPrediction {
+    /// Forecasts the sieve for the run's polynomial, factor bases and region.
+    pub fn predict(gnfs: &GNFS) -> Self {
+        let progress = &gnfs.current_relations_progress;
+        let a_range = progress.value_range.to_f64().unwrap_or(1.0).max(1.0);
+        let b_range = progress.max_b.to_f64().unwrap_or(1.0).max(1.0);
+        let bases = &gnfs.prime_factor_base;
+
+        let mut algebraic_roots = HashMap::new();
+        for pair in &gnfs.algebraic_factor_pair_collection.0 {
+            *algebraic_roots.entry(pair.p).or_insert(0) += 1;
+        }
+        let rational_roots = bases.rational_factor_base.iter().filter_map(|p| p.to_u64()).map(|p| (p, 1)).collect();
+        let alphas = (alpha(&bases.algebraic_factor_base, &algebraic_roots), alpha(&bases.rational_factor_base, &rational_roots));
+        let log_bounds = (
+            ln_abs(&bases.algebraic_factor_base_max).max(1.0),
+            ln_abs(&bases.rational_factor_base_max).max(1.0),
+        );
+
+        let murphy_e = (0..MURPHY_E_SAMPLES)
+            .map(|i| {
+                let theta = std::f64::consts::PI * (i as f64 + 0.5) / MURPHY_E_SAMPLES as f64;
+                probability_at(gnfs, a_range * theta.cos(), b_range * theta.sin(), alphas, log_bounds)
+            })
+            .sum::<f64>()
+            / MURPHY_E_SAMPLES as f64;
+
+        let smooth_probability = region_probability(gnfs, a_range, b_range, alphas, log_bounds);
+
+        let relations_per_b = 2.0 * a_range * COPRIME_DENSITY * smooth_probability;
+        let target_quantity = progress.smooth_relations_target_quantity;
+        let rows = if smooth_probability > 0.0 {
+            rows_needed(gnfs, a_range, b_range, alphas, log_bounds, target_quantity as f64)
+        } else {
+            f64::INFINITY
+        };
+        // The row halfway to the target stands in for the cost of all of them.
+        let sample_row = BigInt::from((rows.min(b_range) / 2.0).ceil().max(1.0) as i64);
+        let seconds_per_pair = Self::time_sample(gnfs, &sample_row);
+        let predicted_seconds = rows * 2.0 * a_range * seconds_per_pair;
+
+        YieldPrediction {
+            murphy_e,
+            alpha_algebraic: alphas.0,
+            alpha_rational: alphas.1,
+            smooth_probability,
+            relations_per_b,
+            expected_relations: relations_per_b * b_range,
+            target_quantity,
+            seconds_per_pair,
+            predicted_seconds,
+            a_range,
+            log_bounds,
+        }
+    }
+
+    /// Seconds to sieve one pair, from the first coprime pairs of row `b`. With the log sieve on
+    /// only its candidates are factored, so the whole row is timed instead and the cost spread over
+    /// every pair in it; rows near b = 1 have far more candidates than the rest.
+    fn time_sample(gnfs: &GNFS, b: &BigInt) -> f64 {
+        let progress = &gnfs.current_relations_progress;
+        if gnfs.config.log_sieve() {
+            let max_abs_a = &progress.region.max_abs_a;
+            let sieve = LineSieve::new(gnfs, &progress.tuning);
+            let started = Instant::now();
+            if let Some(candidates) = sieve.row(gnfs, b, max_abs_a) {
+                let mut a = -max_abs_a.clone();
+                while &a <= max_abs_a {
+                    if candidates.passes(&a, false) && a.gcd(b).is_one() {
+                        Relation::new(gnfs, &a, b).sieve(gnfs);
+                    }
+                    a += 1;
+                }
+                let pairs = max_abs_a.to_f64().unwrap_or(0.0) * 2.0 + 1.0;
+                return started.elapsed().as_secs_f64() / pairs;
+            }
+        }
+        let started = Instant::now();
+        let mut sampled = 0;
+        for a in 1..=TIMING_SAMPLE_PAIRS as i64 / 2 {
+            for a in [BigInt::from(a), BigInt::from(-a)] {
+                if !a.gcd(b).is_one() {
+                    continue;
+                }
+                let mut relation = Relation::new(gnfs, &a, b);
+                relation.sieve(gnfs);
+                sampled += 1;
+            }
+        }
+        started.elapsed().as_secs_f64() / sampled.max(1) as f64
+    }
+
+    /// Problems visible before sieving: no expected yield, or too little in the planned rows.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.smooth_probability <= 0.0 {
+            warnings.push("no pair in the sieve region is expected to be smooth; the polynomial or the factor base bounds are unusable".to_string());
+        } else if self.expected_relations < self.target_quantity as f64 {
+            warnings.push(format!(
+                "the planned b rows are expected to give {:.0} of the {} smooth relations needed; the sieve will have to extend the region",
+                self.expected_relations, self.target_quantity
+            ));
+        }
+        if self.alpha_algebraic > 1.0 {
+            warnings.push(format!("the polynomial has poor root properties (α = {:.2}); its values are less likely to be smooth than random integers of the same size", self.alpha_algebraic));
+        }
+        warnings
+    }
+
+    /// Compares the relations per b row found so far with the yield predicted for those same rows,
+    /// whose norms are smaller than the region's average, and describes a shortfall by more than
+    /// [`YIELD_SHORTFALL_RATIO`] or an overshoot by more than [`YIELD_OVERSHOOT_RATIO`]. Rows are
+    /// compared rather than pairs because the log sieve and the lattice sieve only factor the
+    /// pairs likely to be smooth; the lattice sieve completes no rows and is never compared.
+    pub fn compare(&self, gnfs: &GNFS, statistics: &SieveStatistics) -> Option<YieldMismatch> {
+        if statistics.rows_sieved == 0 {
+            return None;
+        }
+        let alphas = (self.alpha_algebraic, self.alpha_rational);
+        let expected = region_probability(gnfs, self.a_range, statistics.rows_sieved as f64, alphas, self.log_bounds);
+        if expected <= 0.0 {
+            return None;
+        }
+        let mismatch = YieldMismatch {
+            rows_sieved: statistics.rows_sieved,
+            // Relations recycled from partials are beyond what the model counts.
+            realized_per_b: statistics.smooth_relations.saturating_sub(statistics.recycled_relations) as f64 / statistics.rows_sieved as f64,
+            predicted_per_b: 2.0 * self.a_range * COPRIME_DENSITY * expected,
+        };
+        let ratio = mismatch.ratio();
+        (!(1.0 / YIELD_SHORTFALL_RATIO..=YIELD_OVERSHOOT_RATIO).contains(&ratio)).then_some(mismatch)
+    }
+}
+
+/// A realized yield far enough from the prediction to report, from [`YieldPrediction::compare`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct YieldMismatch {
+    pub rows_sieved: u64,
+    pub realized_per_b: f64,
+    pub predicted_per_b: f64,
+}
+
+impl YieldMismatch {
+    /// Realized over predicted yield.
+    pub fn ratio(&self) -> f64 {
+        self.realized_per_b / self.predicted_per_b
+    }
+
+    /// True when the sieve finds fewer relations than predicted, which means the polynomial or the
+    /// bounds may be pathological; otherwise the prediction was just too cautious.
+    pub fn is_shortfall(&self) -> bool {
+        self.ratio() < 1.0
+    }
+}
+
+impl Display for YieldMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "the first {} rows gave {:.3} relations per b against a predicted {:.3} ({:.2}× the prediction)",
+            self.rows_sieved, self.realized_per_b, self.predicted_per_b, self.ratio()
+        )?;
+        if self.is_shortfall() {
+            write!(f, "; the polynomial or bounds may be pathological")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for YieldPrediction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "E = {:.3e}, α = {:.2} / {:.2}, {:.3e} smooth per coprime pair, {:.3} relations per b, {:.0} expected in the planned rows, {} needed in about {:.1}s",
+            self.murphy_e,
+            self.alpha_algebraic,
+            self.alpha_rational,
+            self.smooth_probability,
+            self.relations_per_b,
+            self.expected_relations,
+            self.target_quantity,
+            self.predicted_seconds
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dickman_rho_matches_known_values() {
+        for (u, expected) in [(1.0, 1.0), (2.0, 0.306_852_8), (3.0, 0.048_608_4), (4.0, 0.004_910_9), (5.0, 0.000_354_7)] {
+            let rho = dickman_rho(u);
+            assert!((rho - expected).abs() < expected * 1e-2, "ρ({}) = {}, expected {}", u, rho, expected);
+        }
+    }
+
+    #[test]
+    fn compare_warns_on_shortfalls_only() {
+        use crate::core::cancellation_token::CancellationToken;
+        use crate::core::directory_location::DirectoryLocations;
+        use crate::core::gnfs_config::GnfsConfig;
+
+        let locations = DirectoryLocations::temporary("yield_prediction").unwrap();
+        let n = BigInt::from(45113);
+        let gnfs = GNFS::with_save_locations(&CancellationToken::new(), locations, GnfsConfig::default(), &n, &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let prediction = YieldPrediction::predict(&gnfs);
+        let statistics = |rows_sieved: u64, smooth_relations: usize| SieveStatistics { rows_sieved, smooth_relations, ..Default::default() };
+
+        assert_eq!(prediction.compare(&gnfs, &statistics(0, 0)), None);
+        let nothing = prediction.compare(&gnfs, &statistics(10, 0)).expect("no relations at all is a shortfall");
+        assert!(nothing.is_shortfall() && nothing.to_string().contains("pathological"));
+        let predicted = nothing.predicted_per_b * 10.0;
+        assert!(predicted > 0.0);
+
+        assert_eq!(prediction.compare(&gnfs, &statistics(10, predicted as usize)), None);
+        let shortfall = prediction.compare(&gnfs, &statistics(10, (predicted / 10.0) as usize)).unwrap();
+        assert!(shortfall.is_shortfall());
+
+        let overshoot = prediction.compare(&gnfs, &statistics(10, (predicted * 10.0) as usize)).unwrap();
+        assert!(!overshoot.is_shortfall() && overshoot.ratio() > YIELD_OVERSHOOT_RATIO);
+        assert!(!overshoot.to_string().contains("pathological"));
+        // Relations recycled from partials don't count against the prediction.
+        let recycled = SieveStatistics { recycled_relations: (predicted * 9.0) as usize, ..statistics(10, (predicted * 10.0) as usize) };
+        assert_eq!(prediction.compare(&gnfs, &recycled), None);
+    }
+}
@@ -0,0 +1,100 @@
+// src/square_root/character_filter.rs
+
+//! Cheap rejection of dependencies whose algebraic product cannot be a square. For a prime q and a
+//! root s of ƒ mod q, the map a + bθ ↦ (a + b·s | q) is a character, so a square product must have
+//! an even number of non-residues among its relations. Checking a handful of primes beyond the
+//! quadratic character base catches most bad dependencies before any polynomial arithmetic.
+
+use num::{BigInt, Integer, Zero};
+use crate::core::cancellation_token::CancellationToken;
+use crate::core::gnfs::GNFS;
+use crate::factor::factor_pair::FactorPair;
+use crate::integer_math::prime_factory::PrimeFactory;
+use crate::integer_math::quadratic_residue::QuadraticResidue;
+use crate::polynomial::polynomial::Polynomial;
+use crate::relation_sieve::relation::Relation;
+
+/// Character pairs checked when the config does not say otherwise.
+pub const DEFAULT_CHARACTER_COUNT: usize = 16;
+
+/// Picks `count` (q, s) pairs with q above the quadratic character base, ƒ(s) ≡ 0 and ƒ'(s) ≢ 0
+/// (mod q), so each character is defined and independent of the ones used in the matrix.
+pub fn character_pairs(cancel_token: &CancellationToken, gnfs: &GNFS, count: usize) -> Vec<FactorPair> {
+    let polynomial = &gnfs.current_polynomial;
+    let derivative = Polynomial::get_derivative_polynomial(polynomial);
+    let start = &gnfs.prime_factor_base.quadratic_factor_base_max + 1;
+    let prime_factory = PrimeFactory::new();
+    let mut pairs = Vec::with_capacity(count);
+
+    for q in prime_factory.get_primes_from(&start) {
+        if pairs.len() >= count || cancel_token.is_cancellation_requested() {
+            break;
+        }
+        let mut s = BigInt::zero();
+        while s < q && pairs.len() < count {
+            if polynomial.evaluate(&s).mod_floor(&q).is_zero() && !derivative.evaluate(&s).mod_floor(&q).is_zero() {
+                if let Ok(pair) = FactorPair::new_from_bigint(&q, &s) {
+                    pairs.push(pair);
+                }
+            }
+            s += 1;
+        }
+    }
+    pairs
+}
+
+/// Returns the first character pair under which the product of `relations` is a non-residue, or
+/// `None` when every character is consistent with a square. Pairs where some a + b·s ≡ 0 (mod q)
+/// say nothing about the product and are skipped.
+pub fn failing_character(relations: &[Relation], pairs: &[FactorPair]) -> Option<FactorPair> {
+    pairs.iter().copied().find(|pair| {
+        let q = pair.p_bigint();
+        let s = pair.r_bigint();
+        let mut non_residues = 0usize;
+        for relation in relations {
+            let value = (&relation.a + &relation.b * &s).mod_floor(&q);
+            if value.is_zero() {
+                return false;
+            }
+            if !QuadraticResidue::is_quadratic_residue(&value, &q) {
+                non_residues += 1;
+            }
+        }
+        non_residues % 2 == 1
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::gnfs_config::GnfsConfig;
+    use crate::square_root::square_finder::is_square;
+
+    #[test]
+    fn rejects_a_product_that_is_square_only_on_the_rational_side() {
+        let token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("character_filter").unwrap();
+        let gnfs = GNFS::with_save_locations(&token, locations, GnfsConfig::default(), &BigInt::from(45113), &BigInt::from(31), 3, &BigInt::from(100), 65, 200, true).unwrap();
+        let pairs = character_pairs(&token, &gnfs, DEFAULT_CHARACTER_COUNT);
+        assert_eq!(pairs.len(), DEFAULT_CHARACTER_COUNT);
+        let m = &gnfs.polynomial_base;
+
+        // (a, b) and (a - m, b + 1) share a + bm, so their product is a square on the rational side
+        // alone; a + bθ and a - m + (b + 1)θ are different algebraic integers.
+        let rational_squares = (1..=5).flat_map(|b| (1..=20).map(move |a| (BigInt::from(a), BigInt::from(b)))).map(|(a, b)| {
+            vec![Relation::new(&gnfs, &a, &b), Relation::new(&gnfs, &(&a - m), &(&b + 1))]
+        });
+        let mut rejected = 0;
+        for relations in rational_squares {
+            let rational: BigInt = relations.iter().map(|relation| relation.apply(m)).product();
+            assert!(is_square(&rational));
+            if failing_character(&relations, &pairs).is_some() {
+                rejected += 1;
+                let doubled = [relations.clone(), relations].concat();
+                assert_eq!(failing_character(&doubled, &pairs), None);
+            }
+        }
+        assert!(rejected >= 90, "only {} of 100 rejected", rejected);
+    }
+}
@@ -4,50 +4,61 @@ use num::BigInt;
 use num::Integer;
 use num::{One, Zero};
 use std::cmp::Ordering;
-use crate::integer_math::legendre::Legendre;
 use crate::polynomial::polynomial::Polynomial;
-
-pub fn square_root(start_polynomial: &Polynomial, f: &Polynomial, p: &BigInt, degree: i32, m: &BigInt) -> Polynomial {
+use crate::core::error::GnfsError;
+use crate::square_root::frobenius::FrobeniusMap;
+
+/// A square root of `start_polynomial` in 𝔽_q = 𝔽ₚ[X]/(ƒ), q = p^degree, by Tonelli–Shanks. ƒ must
+/// be irreducible mod p. The non-residue is the first X + m + k, k ≥ 1, whose (q - 1)/2-th power
+/// isn't 1. If `start_polynomial` isn't a square the result is not a root either, and the caller's
+/// gcd check rejects it.
+pub fn square_root(start_polynomial: &Polynomial, frobenius: &FrobeniusMap, degree: i32, m: &BigInt) -> Result<Polynomial, GnfsError> {
+    let p = frobenius.prime();
     let q = p.pow(degree as u32);
-    let mut s: BigInt = &q - 1;
+    let one = Polynomial::one();
 
+    let mut s: BigInt = &q - 1;
     let mut r = 0;
     while s.is_even() {
         s /= 2;
         r += 1;
     }
 
-    let half_s = (&s + 1) / 2;
-    let half_s = if r == 1 && q.mod_floor(&BigInt::from(4)) == BigInt::from(3) {
-        (&q + 1) / 4
-    } else {
-        half_s
+    let half_order = (&q - 1) / 2;
+    let mut offset: BigInt = m + 1;
+    let non_residue = loop {
+        let candidate = Polynomial::from_term(BigInt::one(), 1) + Polynomial::from_term(offset.mod_floor(p), 0);
+        if frobenius.exponentiate(&candidate, &half_order)? != one {
+            break candidate;
+        }
+        offset += 1;
     };
 
-    let quadratic_non_residue = Legendre::symbol_search(&(m + 1), &q, &BigInt::from(-1));
-    let theta = quadratic_non_residue;
-    let minus_one = theta.modpow(&((&q - 1) / 2), p);
-
-    let mut omega_poly = Polynomial::exponentiate_mod(start_polynomial, &half_s, f, p);
-
-    let mut lambda = minus_one;
-
-    let mut i = 0;
-    loop {
-        i += 1;
-
-        let zeta = theta.modpow(&(&i * &s), p);  // Declare zeta here since it's used right after
-
-        lambda = (&lambda * &zeta.pow((2u32.pow((r - i) as u32)) as u32)).mod_floor(p);
-
-        omega_poly = Polynomial::multiply(&omega_poly, &Polynomial::from_term(zeta.pow(2u32.pow((r - i - 1) as u32) as u32), 0));
+    let mut c = frobenius.exponentiate(&non_residue, &s)?;
+    let mut root = frobenius.exponentiate(start_polynomial, &((&s + 1) / 2))?;
+    let mut t = frobenius.exponentiate(start_polynomial, &s)?;
+    while t != one {
+        // The least i with t^(2^i) = 1; reaching r means the input was not a square.
+        let mut i = 0;
+        let mut t_power = t.clone();
+        while t_power != one {
+            t_power = frobenius.multiply(&t_power, &t_power)?;
+            i += 1;
+            if i == r {
+                return Ok(root);
+            }
+        }
 
-        if lambda == BigInt::one() || i > r {
-            break;
+        let mut b = c;
+        for _ in 0..r - i - 1 {
+            b = frobenius.multiply(&b, &b)?;
         }
+        root = frobenius.multiply(&root, &b)?;
+        c = frobenius.multiply(&b, &b)?;
+        t = frobenius.multiply(&t, &c)?;
+        r = i;
     }
-
-    omega_poly
+    Ok(root)
 }
 
 pub fn modular_multiplicative_inverse(a: &BigInt, p: &BigInt) -> Option<BigInt> {
@@ -97,17 +108,17 @@ pub fn chinese_remainder(primes: &[BigInt], values: &[BigInt]) -> Option<BigInt>
     Some(&z - r_p) // Return the result wrapped in Some
 }
 
-pub fn mod_mod(to_reduce: &Polynomial, mod_poly: &Polynomial, prime_modulus: &BigInt) -> Polynomial {
+pub fn mod_mod(to_reduce: &Polynomial, mod_poly: &Polynomial, prime_modulus: &BigInt) -> Result<Polynomial, GnfsError> {
     match mod_poly.cmp(to_reduce) {
-        Ordering::Greater => to_reduce.clone(),
-        Ordering::Equal => Polynomial::zero(),
+        Ordering::Greater => Ok(to_reduce.clone()),
+        Ordering::Equal => Ok(Polynomial::zero()),
         Ordering::Less => remainder(to_reduce, mod_poly, prime_modulus),
     }
 }
 
-pub fn remainder(left: &Polynomial, right: &Polynomial, mod_: &BigInt) -> Polynomial {
+pub fn remainder(left: &Polynomial, right: &Polynomial, mod_: &BigInt) -> Result<Polynomial, GnfsError> {
     if right.degree() > left.degree() || right.cmp(left) == Ordering::Greater {
-        return Polynomial::zero();
+        return Ok(Polynomial::zero());
     }
 
     let right_degree = right.degree();
@@ -115,7 +126,7 @@ pub fn remainder(left: &Polynomial, right: &Polynomial, mod_: &BigInt) -> Polyno
 
     let leading_coefficient = right[right_degree].mod_floor(mod_);
     if leading_coefficient != BigInt::one() {
-        panic!("This method was expecting only monomials (leading coefficient is 1) for the right-hand-side polynomial.");
+        return Err(GnfsError::InvalidParameter("remainder expects a monic right-hand-side polynomial".to_string()));
     }
 
     let mut rem = left.clone();
@@ -131,5 +142,5 @@ pub fn remainder(left: &Polynomial, right: &Polynomial, mod_: &BigInt) -> Polyno
         }
     }
 
-    rem
+    Ok(rem)
 }
@@ -0,0 +1,161 @@
+// src/square_root/frobenius.rs
+
+//! The Frobenius map a ↦ a^p on 𝔽ₚ[X]/(ƒ). Because it is a ring homomorphism that fixes the
+//! coefficients, a^p = Σ aᵢ·(X^p)ⁱ, so once X^{i·p} mod ƒ is known for each i below deg ƒ, raising
+//! to the p-th power is a single linear combination instead of an exponentiation. The same table
+//! answers the irreducibility screen (X^p - X mod ƒ) and speeds up the finite-field square root,
+//! whose exponents are written in base p.
+
+use num::{BigInt, Integer, One, Zero};
+use crate::core::error::GnfsError;
+use crate::polynomial::polynomial::Polynomial;
+
+/// Reduces modulo ƒ and p and drops the zero coefficients the division leaves behind, so
+/// `degree()` and equality see the true polynomial.
+fn reduce(polynomial: &Polynomial, modulus: &Polynomial, prime: &BigInt) -> Result<Polynomial, GnfsError> {
+    let mut reduced = Polynomial::mod_mod(polynomial, modulus, prime)?.field_modulus(prime);
+    reduced.remove_zeros();
+    Ok(reduced)
+}
+
+#[derive(Debug, Clone)]
+pub struct FrobeniusMap {
+    prime: BigInt,
+    modulus: Polynomial,
+    /// X^p mod ƒ.
+    x_to_the_p: Polynomial,
+    /// X^{i·p} mod ƒ for i in 0..deg ƒ.
+    powers: Vec<Polynomial>,
+}
+
+impl FrobeniusMap {
+    /// Precomputes the map for the monic polynomial `modulus` over 𝔽ₚ.
+    pub fn new(modulus: &Polynomial, prime: &BigInt) -> Result<Self, GnfsError> {
+        let degree = modulus.degree();
+        if degree == 0 || !modulus[degree].mod_floor(prime).is_one() {
+            return Err(GnfsError::InvalidParameter(format!("the Frobenius map needs a monic modulus, got {}", modulus)));
+        }
+
+        let reduce = |polynomial: &Polynomial| reduce(polynomial, modulus, prime);
+        let x = Polynomial::from_term(BigInt::one(), 1);
+        let x_to_the_p = reduce(&Polynomial::exponentiate_mod(&x, prime, modulus, prime)?)?;
+
+        let mut powers = Vec::with_capacity(degree);
+        powers.push(Polynomial::one());
+        for i in 1..degree {
+            let next = reduce(&Polynomial::multiply(&powers[i - 1], &x_to_the_p))?;
+            powers.push(next);
+        }
+
+        Ok(FrobeniusMap { prime: prime.clone(), modulus: modulus.clone(), x_to_the_p, powers })
+    }
+
+    pub fn prime(&self) -> &BigInt {
+        &self.prime
+    }
+
+    pub fn modulus(&self) -> &Polynomial {
+        &self.modulus
+    }
+
+    fn reduce(&self, polynomial: &Polynomial) -> Result<Polynomial, GnfsError> {
+        reduce(polynomial, &self.modulus, &self.prime)
+    }
+
+    /// X^p - X reduced modulo ƒ and p; ƒ has a root mod p exactly when its gcd with ƒ is non-trivial.
+    pub fn x_pow_p_minus_x(&self) -> Result<Polynomial, GnfsError> {
+        let x = Polynomial::from_term(BigInt::one(), 1);
+        self.reduce(&(self.x_to_the_p.clone() - x))
+    }
+
+    /// Ben-Or's test: ƒ is irreducible over 𝔽ₚ when gcd(X^{p^i} - X, ƒ) = 1 for every i up to
+    /// deg ƒ / 2. A trivial gcd at i = 1 only rules out linear factors, which is enough for degree
+    /// 2 and 3; a quartic can still split into two quadratics.
+    pub fn is_irreducible(&self) -> Result<bool, GnfsError> {
+        let x = Polynomial::from_term(BigInt::one(), 1);
+        let mut x_to_the_p_i = self.x_to_the_p.clone();
+        for i in 1..=self.modulus.degree() / 2 {
+            if i > 1 {
+                x_to_the_p_i = self.apply(&x_to_the_p_i)?;
+            }
+            let difference = self.reduce(&(x_to_the_p_i.clone() - x.clone()))?;
+            if Polynomial::field_gcd(&difference, &self.modulus, &self.prime) != Polynomial::one() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// a·b mod (ƒ, p).
+    pub fn multiply(&self, a: &Polynomial, b: &Polynomial) -> Result<Polynomial, GnfsError> {
+        self.reduce(&Polynomial::multiply(a, b))
+    }
+
+    /// a^p mod (ƒ, p).
+    pub fn apply(&self, a: &Polynomial) -> Result<Polynomial, GnfsError> {
+        let a = self.reduce(a)?;
+        let mut result = Polynomial::zero();
+        for (i, power) in self.powers.iter().enumerate() {
+            let coefficient = &a[i];
+            if !coefficient.is_zero() {
+                result = result + Polynomial::multiply(power, &Polynomial::from_term(coefficient.clone(), 0));
+            }
+        }
+        self.reduce(&result)
+    }
+
+    /// base^exponent mod (ƒ, p), with the exponent split into base-p digits dₖ so that
+    /// base^e = ∏ φᵏ(base^dₖ) and only the digits need ordinary exponentiation.
+    pub fn exponentiate(&self, base: &Polynomial, exponent: &BigInt) -> Result<Polynomial, GnfsError> {
+        let mut digits = Vec::new();
+        let mut remaining = exponent.clone();
+        while remaining > BigInt::zero() {
+            let (quotient, digit) = remaining.div_rem(&self.prime);
+            digits.push(digit);
+            remaining = quotient;
+        }
+
+        let base = self.reduce(base)?;
+        let mut result = Polynomial::one();
+        for digit in digits.iter().rev() {
+            result = self.apply(&result)?;
+            if !digit.is_zero() {
+                let term = Polynomial::exponentiate_mod(&base, digit, &self.modulus, &self.prime)?;
+                result = self.reduce(&Polynomial::multiply(&result, &term))?;
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponentiation_matches_square_and_multiply() {
+        let f = Polynomial::parse("X^3 + 15X^2 + 29X + 8");
+        let p = BigInt::from(11);
+        let frobenius = FrobeniusMap::new(&f, &p).unwrap();
+        let base = Polynomial::parse("3X^2 + 5X + 7");
+
+        for exponent in [1u32, 10, 11, 23, 121] {
+            let exponent = BigInt::from(exponent);
+            let expected = reduce(&Polynomial::exponentiate_mod(&base, &exponent, &f, &p).unwrap(), &f, &p).unwrap();
+            assert_eq!(frobenius.exponentiate(&base, &exponent).unwrap(), expected, "exponent {}", exponent);
+        }
+        // ƒ is irreducible mod 11, so the multiplicative group of 𝔽ₚ[X]/(ƒ) has order 11³ - 1.
+        assert_eq!(frobenius.exponentiate(&base, &BigInt::from(1330)).unwrap(), Polynomial::one());
+    }
+
+    #[test]
+    fn irreducibility_catches_quadratic_factors() {
+        let p = BigInt::from(7);
+        assert!(FrobeniusMap::new(&Polynomial::parse("X^3 + 15X^2 + 29X + 8"), &BigInt::from(11)).unwrap().is_irreducible().unwrap());
+        assert!(FrobeniusMap::new(&Polynomial::parse("X^2 + 1"), &p).unwrap().is_irreducible().unwrap());
+        // No roots mod 7, but X⁴ + 1 = (X² + 3X + 1)(X² + 4X + 1) there.
+        let quartic = FrobeniusMap::new(&Polynomial::parse("X^4 + 1"), &p).unwrap();
+        assert_eq!(Polynomial::field_gcd(&quartic.x_pow_p_minus_x().unwrap(), quartic.modulus(), &p), Polynomial::one());
+        assert!(!quartic.is_irreducible().unwrap());
+    }
+}
@@ -1,4 +1,51 @@
 // src/square_root/mod.rs
 
 pub mod square_finder;
-pub mod finite_field_arithmetic;
\ No newline at end of file
+pub mod finite_field_arithmetic;
+pub mod character_filter;
+pub mod frobenius;
+pub mod montgomery;
+
+use std::fmt::Display;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::core::error::GnfsError;
+
+/// How the square root stage takes the algebraic square root of a dependency.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SquareRootMethod {
+    /// The exact square root of [`montgomery`], falling back to [`SquareRootMethod::InertPrimes`]
+    /// for even degree and whenever it cannot settle on a root.
+    #[default]
+    Montgomery,
+    /// γ(m) modulo a few inert primes combined by the Chinese remainder theorem, retried with
+    /// fresh primes a fixed number of times.
+    InertPrimes,
+}
+
+impl SquareRootMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SquareRootMethod::Montgomery => "montgomery",
+            SquareRootMethod::InertPrimes => "inert-primes",
+        }
+    }
+}
+
+impl Display for SquareRootMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SquareRootMethod {
+    type Err = GnfsError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        [SquareRootMethod::Montgomery, SquareRootMethod::InertPrimes]
+            .into_iter()
+            .find(|method| method.name() == input)
+            .ok_or_else(|| GnfsError::InvalidParameter(format!("unknown square root method {:?}; expected montgomery or inert-primes", input)))
+    }
+}
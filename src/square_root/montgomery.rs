@@ -0,0 +1,201 @@
+// src/square_root/montgomery.rs
+
+//! An exact algebraic square root, Couveignes' method. For odd degree ƒ, the square root γ of
+//! δ ∈ ℤ[θ] is computed modulo primes p that stay inert in ℤ[θ], where 𝔽ₚ[X]/(ƒ) is a field. Each
+//! γ mod p is only known up to sign, but N(-γ) = -N(γ) when the degree is odd, so comparing the norm
+//! of each candidate with the exactly known N(γ) picks the same γ at every prime. The coefficients
+//! of γ are then recovered by the Chinese remainder theorem once they stop changing and γ² = δ is
+//! confirmed in ℤ[θ], and γ(m) mod N follows. Unlike the inert-prime CRT in
+//! [`square_finder`](crate::square_root::square_finder), nothing is left to chance, so a dependency
+//! either yields its square root or is shown not to be a square.
+
+use log::{debug, trace};
+use num::{BigInt, Integer, One, Signed, Zero};
+use crate::core::cancellation_token::CancellationToken;
+use crate::core::error::GnfsError;
+use crate::integer_math::prime_factory::PrimeFactory;
+use crate::polynomial::polynomial::{Polynomial, Term};
+use crate::square_root::finite_field_arithmetic;
+use crate::square_root::frobenius::FrobeniusMap;
+
+/// Inert primes are searched upwards from here; larger primes mean fewer of them per coefficient bit.
+const FIRST_PRIME: u64 = 1 << 24;
+/// Bits beyond those of δ's coefficients the primes' product may reach before giving up.
+const EXTRA_BITS: u64 = 256;
+
+/// Whether the method applies to ƒ: it must be monic, so that norms are the sieved algebraic
+/// norms, and of odd degree, so that the norm fixes the sign of γ.
+pub fn applies(polynomial: &Polynomial) -> bool {
+    let degree = polynomial.degree();
+    degree.is_odd() && polynomial[degree].is_one()
+}
+
+/// N(β) for β ∈ ℤ[θ], the determinant of multiplication by β on the basis 1, θ, …, θ^(d-1).
+/// Fails when ƒ isn't monic.
+pub fn norm(polynomial: &Polynomial, element: &Polynomial) -> Result<BigInt, GnfsError> {
+    let degree = polynomial.degree();
+    let columns: Vec<Polynomial> = (0..degree)
+        .map(|i| Polynomial::multiply(element, &Polynomial::from_term(BigInt::one(), i)).field_modulus_from_polynomial(polynomial))
+        .collect::<Result<_, _>>()?;
+    let matrix = (0..degree).map(|row| columns.iter().map(|column| column[row].clone()).collect()).collect();
+    Ok(determinant(matrix))
+}
+
+/// Fraction-free Gaussian elimination (Bareiss), exact over the integers.
+fn determinant(mut matrix: Vec<Vec<BigInt>>) -> BigInt {
+    let size = matrix.len();
+    let mut sign = BigInt::one();
+    let mut previous_pivot = BigInt::one();
+    for k in 0..size {
+        if matrix[k][k].is_zero() {
+            let Some(swap) = (k + 1..size).find(|&i| !matrix[i][k].is_zero()) else {
+                return BigInt::zero();
+            };
+            matrix.swap(k, swap);
+            sign = -sign;
+        }
+        for i in k + 1..size {
+            for j in k + 1..size {
+                matrix[i][j] = (&matrix[i][j] * &matrix[k][k] - &matrix[i][k] * &matrix[k][j]) / &previous_pivot;
+            }
+        }
+        previous_pivot = matrix[k][k].clone();
+    }
+    if size == 0 { sign } else { sign * &matrix[size - 1][size - 1] }
+}
+
+/// γ(m) mod N for the square root γ of `delta` in ℤ[θ] = ℤ[X]/(ƒ) whose norm is `norm`; -γ is
+/// the root with norm -`norm`. None when `delta` turns out not to be a square. ƒ must satisfy
+/// [`applies`].
+pub fn square_root(
+    cancel_token: &CancellationToken,
+    polynomial: &Polynomial,
+    delta: &Polynomial,
+    norm: &BigInt,
+    m: &BigInt,
+    n: &BigInt,
+) -> Result<Option<BigInt>, GnfsError> {
+    let degree = polynomial.degree();
+    let delta_bits = (0..degree).map(|i| delta[i].bits()).max().unwrap_or(0);
+    let bit_limit = delta_bits + EXTRA_BITS;
+
+    // γ's coefficients mod the product of the primes so far, in the symmetric range.
+    let mut coefficients = vec![BigInt::zero(); degree];
+    let mut modulus = BigInt::one();
+    let mut prime = BigInt::from(FIRST_PRIME);
+    let mut primes_used = 0usize;
+    while modulus.bits() <= bit_limit {
+        if cancel_token.is_cancellation_requested() {
+            return Err(GnfsError::Cancelled);
+        }
+        prime = PrimeFactory::get_next_prime(&prime);
+        let target = norm.mod_floor(&prime);
+        if target.is_zero() {
+            continue;
+        }
+        let frobenius = FrobeniusMap::new(polynomial, &prime)?;
+        if !frobenius.is_irreducible()? {
+            continue;
+        }
+
+        let mut root = finite_field_arithmetic::square_root(delta, &frobenius, degree as i32, m)?;
+        if !same_residues(&frobenius.multiply(&root, &root)?, delta, degree, &prime) {
+            debug!("δ is not a square modulo the inert prime {}.", prime);
+            return Ok(None);
+        }
+        let root_norm = field_norm(&frobenius, &root, degree)?;
+        if root_norm == (-&target).mod_floor(&prime) {
+            root = (Polynomial::zero() - root).field_modulus(&prime);
+        } else if root_norm != target {
+            debug!("No square root of δ modulo {} has norm {} there.", prime, target);
+            return Ok(None);
+        }
+
+        // Garner's step: fold the residues mod p into the coefficients mod the running product.
+        let inverse = modulus.modpow(&(&prime - 2), &prime);
+        let mut changed = false;
+        for (i, coefficient) in coefficients.iter_mut().enumerate() {
+            let step = ((&root[i] - &*coefficient) * &inverse).mod_floor(&prime);
+            if !step.is_zero() {
+                changed = true;
+                *coefficient += &modulus * step;
+            }
+        }
+        modulus *= &prime;
+        primes_used += 1;
+        let half = &modulus >> 1;
+        for coefficient in coefficients.iter_mut() {
+            if *coefficient > half {
+                *coefficient -= &modulus;
+            } else if *coefficient < -&half {
+                *coefficient += &modulus;
+            }
+        }
+        trace!("γ mod a {}-bit product of {} inert primes: {:?}", modulus.bits(), primes_used, coefficients);
+
+        if !changed {
+            let gamma = Polynomial::new(coefficients.iter().enumerate().map(|(i, c)| Term::new(c.clone(), i)).collect());
+            if same_coefficients(&gamma.square().field_modulus_from_polynomial(polynomial)?, delta, degree) {
+                debug!("γ recovered from {} inert primes; its largest coefficient has {} bits.", primes_used, coefficients.iter().map(|c| c.abs().bits()).max().unwrap_or(0));
+                return Ok(Some(gamma.evaluate(m).mod_floor(n)));
+            }
+        }
+    }
+    debug!("γ did not settle within {} bits of inert primes.", bit_limit);
+    Ok(None)
+}
+
+/// N(β) mod p as the product of β's conjugates β^(p^i), i < d.
+fn field_norm(frobenius: &FrobeniusMap, element: &Polynomial, degree: usize) -> Result<BigInt, GnfsError> {
+    let mut conjugate = element.clone();
+    let mut product = element.clone();
+    for _ in 1..degree {
+        conjugate = frobenius.apply(&conjugate)?;
+        product = frobenius.multiply(&product, &conjugate)?;
+    }
+    Ok(product[0].mod_floor(frobenius.prime()))
+}
+
+fn same_residues(left: &Polynomial, right: &Polynomial, degree: usize, prime: &BigInt) -> bool {
+    (0..degree).all(|i| left[i].mod_floor(prime) == right[i].mod_floor(prime))
+}
+
+fn same_coefficients(left: &Polynomial, right: &Polynomial, degree: usize) -> bool {
+    (0..degree).all(|i| left[i] == right[i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_the_root_with_the_given_norm() {
+        let f = Polynomial::parse("X^3 + 15X^2 + 29X + 8");
+        let (m, n) = (BigInt::from(31), BigInt::from(45113));
+        let gamma = Polynomial::parse("123456789X^2 - 987654321X + 55555");
+        let delta = gamma.square().field_modulus_from_polynomial(&f).unwrap();
+        let gamma_norm = norm(&f, &gamma).unwrap();
+
+        let token = CancellationToken::new();
+        let expected = gamma.evaluate(&m).mod_floor(&n);
+        assert_eq!(square_root(&token, &f, &delta, &gamma_norm, &m, &n).unwrap(), Some(expected.clone()));
+        assert_eq!(square_root(&token, &f, &delta, &-gamma_norm, &m, &n).unwrap(), Some((-expected).mod_floor(&n)));
+    }
+
+    #[test]
+    fn norms_of_linear_elements_match_the_sieved_norms() {
+        let f = Polynomial::parse("X^3 + 15X^2 + 29X + 8");
+        let (a, b) = (BigInt::from(-7), BigInt::from(3));
+        let element = Polynomial::parse("3X - 7");
+        assert_eq!(norm(&f, &element).unwrap(), crate::integer_math::normal::Normal::algebraic(&a, &b, &f));
+    }
+
+    #[test]
+    fn rejects_non_squares() {
+        let f = Polynomial::parse("X^3 + 15X^2 + 29X + 8");
+        let delta = Polynomial::parse("2X + 1");
+        let token = CancellationToken::new();
+        let result = square_root(&token, &f, &delta, &norm(&f, &delta).unwrap(), &BigInt::from(31), &BigInt::from(45113)).unwrap();
+        assert_eq!(result, None);
+    }
+}
@@ -1,21 +1,60 @@
 // src/square_root/square_finder.rs
 
-use log::info;
-use num::bigint::ToBigInt;
-use num::{BigInt, Zero, One, Integer, ToPrimitive};
+use log::{debug, info, trace, warn};
+use num::{BigInt, Zero, One, Integer, Signed};
 use crate::polynomial::polynomial::Polynomial;
 use crate::core::gnfs::GNFS;
 use crate::relation_sieve::relation::Relation;
-use std::cmp::Ordering;
+use std::time::Instant;
 use crate::core::count_dictionary::CountDictionary;
 use crate::polynomial::polynomial::Term;
 use crate::integer_math::gcd::GCD;
 use crate::integer_math::prime_factory::PrimeFactory;
-use crate::core::static_random::StaticRandom;
+use crate::core::gnfs_config::random_stream;
 use crate::square_root::finite_field_arithmetic;
+use crate::square_root::character_filter;
+use crate::square_root::frobenius::FrobeniusMap;
+use crate::square_root::montgomery;
+use crate::square_root::SquareRootMethod;
+use std::collections::HashMap;
 use crate::core::cancellation_token::CancellationToken;
+use crate::core::error::GnfsError;
+use crate::core::serialization::{load, save};
+use crate::core::events::{DependencyOutcome, GnfsEvent};
+use crate::core::stage::Stage;
 use crate::polynomial::algorithms;
 
+/// Factors a + bθ multiplied together between cancellation checks when forming ∏ Sᵢ.
+const CANCELLATION_CHECK_FACTORS: usize = 32;
+
+/// Dependencies already tried and where the irreducible-prime search stopped. Saved after every
+/// attempt so a restarted square root stage doesn't repeat failing sets.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SquareRootProgress {
+    /// Identifies the dependency list the indices refer to; a new matrix solve invalidates them.
+    pub dependency_fingerprint: u64,
+    pub tried_dependencies: Vec<usize>,
+    pub last_prime: Option<BigInt>,
+}
+
+impl SquareRootProgress {
+    /// FNV-1a over the (a, b) pairs of every dependency, stable across builds and platforms.
+    pub fn fingerprint(dependencies: &[Vec<Relation>]) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for dependency in dependencies {
+            for relation in dependency {
+                for byte in format!("{},{};", relation.a, relation.b).bytes() {
+                    hash ^= byte as u64;
+                    hash = hash.wrapping_mul(0x0100_0000_01b3);
+                }
+            }
+            hash ^= b'|' as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+}
+
 pub struct SquareFinder {
     pub rational_product: BigInt,
     pub rational_square: BigInt,
@@ -41,12 +80,16 @@ pub struct SquareFinder {
     pub polynomial_ring_elements: Vec<Polynomial>,
 
     pub polynomial_base: BigInt,
+    /// cm, the root mod N of the monic F(X) = c^(d-1) ƒ(X/c) that the algebraic side works with,
+    /// c being ƒ's leading coefficient.
+    pub monic_polynomial_base: BigInt,
+    /// The rational polynomial g whose norms make up δᵣ.
+    pub rational_polynomial: Polynomial,
     pub monic_polynomial: Polynomial,
     pub polynomial_derivative: Polynomial,
     pub monic_polynomial_derivative: Polynomial,
 
     pub polynomial_derivative_squared: Polynomial,
-    pub polynomial_derivative_squared_in_field: Polynomial,
 
     pub polynomial_derivative_value: BigInt,
     pub polynomial_derivative_value_squared: BigInt,
@@ -57,7 +100,14 @@ pub struct SquareFinder {
     pub monic_polynomial_derivative_value: BigInt,
     pub monic_polynomial_derivative_value_squared: BigInt,
 
-    gnfs: GNFS,
+    polynomial: Polynomial,
+    /// c, the leading coefficient of ƒ.
+    leading_coefficient: BigInt,
+    method: SquareRootMethod,
+    /// Search cursor for primes p with ƒ irreducible mod p; starts above the quadratic character base.
+    last_prime: BigInt,
+    /// Frobenius tables for the primes found by the search, reused by the square root itself.
+    frobenius_maps: HashMap<BigInt, FrobeniusMap>,
     rational_norms: Vec<BigInt>,
     algebraic_norm_collection: Vec<BigInt>,
     relations_set: Vec<Relation>,
@@ -65,7 +115,7 @@ pub struct SquareFinder {
 }
 
 impl SquareFinder {
-    pub fn new(sieve: &GNFS) -> Self {
+    pub fn new(sieve: &GNFS) -> Result<Self, GnfsError> {
         let sieve_ref = sieve;
 
         let mut square_finder = SquareFinder {
@@ -90,18 +140,25 @@ impl SquareFinder {
             polynomial_ring: Polynomial::zero(),
             polynomial_ring_elements: Vec::new(),
             polynomial_base: sieve_ref.polynomial_base.clone(),
+            monic_polynomial_base: BigInt::zero(),
+            rational_polynomial: sieve_ref.rational_polynomial.clone(),
             monic_polynomial: Polynomial::zero(),
             polynomial_derivative: Polynomial::zero(),
             monic_polynomial_derivative: Polynomial::zero(),
             polynomial_derivative_squared: Polynomial::zero(),
-            polynomial_derivative_squared_in_field: Polynomial::zero(),
             polynomial_derivative_value: BigInt::zero(),
             polynomial_derivative_value_squared: BigInt::zero(),
             monic_polynomial_derivative_squared: Polynomial::zero(),
             monic_polynomial_derivative_squared_in_field: Polynomial::zero(),
             monic_polynomial_derivative_value: BigInt::zero(),
             monic_polynomial_derivative_value_squared: BigInt::zero(),
-            gnfs: sieve.clone(),
+            polynomial: sieve.current_polynomial.clone(),
+            leading_coefficient: sieve.current_polynomial[sieve.current_polynomial.degree()].clone(),
+            method: sieve.config.square_root_method(),
+            last_prime: sieve.quadratic_factor_pair_collection.0.last()
+                .map(|pair| pair.p_bigint())
+                .unwrap_or_else(|| sieve.prime_factor_base.quadratic_factor_base_max.clone()),
+            frobenius_maps: HashMap::new(),
             rational_norms: Vec::new(),
             algebraic_norm_collection: Vec::new(),
             relations_set: Vec::new(),
@@ -109,41 +166,45 @@ impl SquareFinder {
 
         square_finder.polynomial_derivative = Polynomial::get_derivative_polynomial(&sieve.current_polynomial);
         square_finder.polynomial_derivative_squared = Polynomial::square(&square_finder.polynomial_derivative);
-        square_finder.polynomial_derivative_squared_in_field =
-            Polynomial::field_modulus_from_polynomial(&square_finder.polynomial_derivative_squared, &sieve.current_polynomial);
 
-        info!("{}", "".to_string());
-        info!("{}", format!("ƒ'(θ) = {}", square_finder.polynomial_derivative));
-        info!("{}", format!("ƒ'(θ)² = {}", square_finder.polynomial_derivative_squared));
-        info!("{}", format!("ƒ'(θ)² ∈ ℤ[θ] = {}", square_finder.polynomial_derivative_squared_in_field));
+        debug!("{}", "".to_string());
+        debug!("{}", format!("ƒ'(θ) = {}", square_finder.polynomial_derivative));
+        debug!("{}", format!("ƒ'(θ)² = {}", square_finder.polynomial_derivative_squared));
 
         square_finder.polynomial_derivative_value = square_finder.polynomial_derivative.evaluate(&sieve.polynomial_base);
         square_finder.polynomial_derivative_value_squared = square_finder.polynomial_derivative_value.pow(2);
 
-        info!("{}", "".to_string());
-        info!("{}", format!("ƒ'(m) = {}", square_finder.polynomial_derivative_value));
-        info!("{}", format!("ƒ'(m)² = {}", square_finder.polynomial_derivative_value_squared));
+        debug!("{}", "".to_string());
+        debug!("{}", format!("ƒ'(m) = {}", square_finder.polynomial_derivative_value));
+        debug!("{}", format!("ƒ'(m)² = {}", square_finder.polynomial_derivative_value_squared));
 
-        let monic_polynomial = Polynomial::make_monic(&sieve.current_polynomial, &sieve.polynomial_base);
-        square_finder.monic_polynomial = monic_polynomial;
+        // ℤ[θ] is only a ring of integers' order for monic ƒ, so the square root is taken in ℤ[ω],
+        // ω = cθ, instead; for monic ƒ nothing changes.
+        square_finder.monic_polynomial = Polynomial::make_monic(&sieve.current_polynomial);
+        square_finder.monic_polynomial_base = &square_finder.leading_coefficient * &sieve.polynomial_base;
         square_finder.monic_polynomial_derivative = Polynomial::get_derivative_polynomial(&square_finder.monic_polynomial);
         square_finder.monic_polynomial_derivative_squared = Polynomial::square(&square_finder.monic_polynomial_derivative);
         square_finder.monic_polynomial_derivative_squared_in_field =
-            Polynomial::field_modulus_from_polynomial(&square_finder.monic_polynomial_derivative_squared, &square_finder.monic_polynomial);
+            Polynomial::field_modulus_from_polynomial(&square_finder.monic_polynomial_derivative_squared, &square_finder.monic_polynomial)?;
 
-        square_finder.monic_polynomial_derivative_value = square_finder.monic_polynomial_derivative.evaluate(&sieve.polynomial_base);
-        square_finder.monic_polynomial_derivative_value_squared = square_finder.monic_polynomial_derivative_squared.evaluate(&sieve.polynomial_base);
+        square_finder.monic_polynomial_derivative_value = square_finder.monic_polynomial_derivative.evaluate(&square_finder.monic_polynomial_base);
+        square_finder.monic_polynomial_derivative_value_squared = square_finder.monic_polynomial_derivative_squared.evaluate(&square_finder.monic_polynomial_base);
 
-        info!("{}", "".to_string());
-        info!("{}", format!("MonicPolynomial: {}", square_finder.monic_polynomial));
-        info!("{}", format!("MonicPolynomialDerivative: {}", square_finder.monic_polynomial_derivative));
-        info!("{}", format!("MonicPolynomialDerivativeSquared: {}", square_finder.monic_polynomial_derivative_squared));
-        info!("{}", format!("MonicPolynomialDerivativeSquaredInField: {}", square_finder.monic_polynomial_derivative_squared_in_field));
+        debug!("{}", "".to_string());
+        debug!("{}", format!("MonicPolynomial: {}", square_finder.monic_polynomial));
+        debug!("{}", format!("MonicPolynomialDerivative: {}", square_finder.monic_polynomial_derivative));
+        debug!("{}", format!("MonicPolynomialDerivativeSquared: {}", square_finder.monic_polynomial_derivative_squared));
+        debug!("{}", format!("MonicPolynomialDerivativeSquaredInField: {}", square_finder.monic_polynomial_derivative_squared_in_field));
 
-        square_finder
+        Ok(square_finder)
     }
 
-    pub fn calculate_rational_side(&mut self, cancel_token: &CancellationToken, relations: Vec<Relation>) {
+    pub fn calculate_rational_side(&mut self, cancel_token: &CancellationToken, relations: Vec<Relation>) -> Result<(), GnfsError> {
+        // A nonlinear g would need its own square root in a second number field.
+        if self.rational_polynomial.degree() != 1 {
+            let reason = format!("the rational polynomial g = {} is not linear", self.rational_polynomial);
+            return Err(GnfsError::stage_failed("Rational square root", reason));
+        }
         self.relations_set = relations;
         self.rational_norms = self.relations_set.iter().map(|rel| rel.rational_norm.clone()).collect();
 
@@ -154,311 +215,404 @@ impl SquareFinder {
 
         let rational_square_factorization_string = rational_square_factorization.format_string_as_factorization();
 
-        info!("{}", "".to_string());
-        info!("{}", "Rational Square Dependency:".to_string());
-        info!("{}", rational_square_factorization_string);
+        debug!("{}", "".to_string());
+        debug!("{}", "Rational Square Dependency:".to_string());
+        debug!("{}", rational_square_factorization_string);
 
         if cancel_token.is_cancellation_requested() {
-            return;
+            return Err(GnfsError::Cancelled);
         }
 
         self.rational_product = self.rational_norms.iter().product();
 
-        info!("{}", "".to_string());
-        info!("{}", format!("δᵣ = {} = {}", self.rational_product, self.rational_norms.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(" * ")));
+        debug!("{}", "".to_string());
+        debug!("{}", format!("δᵣ = {} = {}", self.rational_product, self.rational_norms.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(" * ")));
+
+        // The sign column keeps the count of negative a + bm even; an odd count would make δᵣ
+        // negative, and it has no integer square root.
+        self.is_rational_square = is_square(&self.rational_product);
+        if !self.is_rational_square {
+            // Should never happen for a correct dependency; it points at a bug upstream in sieving or the matrix step.
+            let reason = if self.rational_product.is_negative() {
+                format!("δᵣ = {} is negative; the dependency has an odd number of negative rational norms", self.rational_product)
+            } else {
+                format!("δᵣ = {} is not a perfect square", self.rational_product)
+            };
+            return Err(GnfsError::stage_failed("Rational square root", reason));
+        }
 
         let rational_product_square_root = self.rational_product.sqrt();
 
-        let product = &self.polynomial_derivative_value * &rational_product_square_root;
+        // Each norm of g = g₁X + g₀ is g₁(a + bm) mod N, so the even number of them carries
+        // g₁^(k/2) into the square root; divide it back out to get ∏ (a + bm).
+        // The algebraic side squares F'(ω)² ∏ (ca + bω), with an extra c for an odd number of
+        // factors, which maps to F'(cm)² c^(2⌈k/2⌉) ∏ (a + bm); see calculate_algebraic_side.
+        let mut product = &self.monic_polynomial_derivative_value * &rational_product_square_root;
+        if !self.leading_coefficient.is_one() {
+            product *= self.leading_coefficient.modpow(&BigInt::from(self.relations_set.len().div_ceil(2)), &self.n);
+        }
+        let leading = &self.rational_polynomial[1];
+        if !leading.is_one() {
+            let leading_power = leading.modpow(&BigInt::from(self.relations_set.len() / 2), &self.n);
+            let extended = leading_power.extended_gcd(&self.n);
+            if !extended.gcd.is_one() {
+                let reason = format!("the leading coefficient {} of g shares the factor {} with N", leading, extended.gcd);
+                return Err(GnfsError::stage_failed("Rational square root", reason));
+            }
+            product *= extended.x;
+        }
 
         self.rational_square_root_residue = product.mod_floor(&self.n);
 
-        info!("{}", "".to_string());
-        info!("{}", format!("δᵣ = {}^2 = {}", rational_product_square_root, self.rational_product));
-        info!("{}", format!("χ  = {} ≡ {} * {} (mod {})", self.rational_square_root_residue, self.polynomial_derivative_value, rational_product_square_root, self.n));
-        info!("{}", "".to_string());
-
-        self.is_rational_square = is_square(&self.rational_product);
-        if !self.is_rational_square {
-            panic!("is_rational_square evaluated to false. This is a sign that there is a bug in the implementation, as this should never be the case if the algorithm has been correctly implemented.");
-        }
+        debug!("{}", "".to_string());
+        debug!("{}", format!("δᵣ = {}^2 = {}", rational_product_square_root, self.rational_product));
+        debug!("{}", format!("χ  = {} ≡ {} * {} (mod {})", self.rational_square_root_residue, self.monic_polynomial_derivative_value, rational_product_square_root, self.n));
+        debug!("{}", "".to_string());
+        Ok(())
     }
 
-    pub fn calculate_algebraic_side(&mut self, cancel_token: &CancellationToken) -> (BigInt, BigInt) {
+    pub fn calculate_algebraic_side(&mut self, cancel_token: &CancellationToken) -> Result<(BigInt, BigInt), GnfsError> {
         for rel in &self.relations_set {
             self.roots_of_s.push((rel.a.clone(), rel.b.clone()));
         }
 
         if cancel_token.is_cancellation_requested() {
-            return (BigInt::one(), BigInt::one());
+            return Err(GnfsError::Cancelled);
         }
 
+        // a + bθ = (ca + bω) / c, with ω = cθ the root of the monic F.
         self.polynomial_ring_elements.clear();
         for rel in &self.relations_set {
             let new_poly = Polynomial::new(vec![
                 Term::new(rel.b.clone(), 1),
-                Term::new(rel.a.clone(), 0),
+                Term::new(&self.leading_coefficient * &rel.a, 0),
             ]);
             self.polynomial_ring_elements.push(new_poly);
         }
 
-        if cancel_token.is_cancellation_requested() {
-            return (BigInt::one(), BigInt::one());
+        // The product's degree grows with every factor, so it is formed in batches with a
+        // cancellation check between each.
+        let mut polynomial_ring = Polynomial::one();
+        for batch in self.polynomial_ring_elements.chunks(CANCELLATION_CHECK_FACTORS) {
+            if cancel_token.is_cancellation_requested() {
+                return Err(GnfsError::Cancelled);
+            }
+            polynomial_ring = polynomial_ring * Polynomial::product(batch);
         }
+        self.polynomial_ring = polynomial_ring;
+        let polynomial_ring_in_field = Polynomial::field_modulus_from_polynomial(&self.polynomial_ring, &self.monic_polynomial)?;
 
-        self.polynomial_ring = Polynomial::product(&self.polynomial_ring_elements);
-        let polynomial_ring_in_field = Polynomial::field_modulus_from_polynomial(&self.polynomial_ring, &self.monic_polynomial);
-
-        info!("{}", "".to_string());
-        info!("{}", format!("∏ Sᵢ = {}", self.polynomial_ring));
-        info!("{}", "".to_string());
-        info!("{}", format!("∏ Sᵢ = {}", polynomial_ring_in_field));
-        info!("{}", " in ℤ".to_string());
-        info!("{}", "".to_string());
+        debug!("{}", "".to_string());
+        debug!("{}", format!("∏ Sᵢ = {}", self.polynomial_ring));
+        debug!("{}", "".to_string());
+        debug!("{}", format!("∏ Sᵢ = {}", polynomial_ring_in_field));
+        debug!("{}", " in ℤ".to_string());
+        debug!("{}", "".to_string());
 
         if cancel_token.is_cancellation_requested() {
-            return (BigInt::one(), BigInt::one());
+            return Err(GnfsError::Cancelled);
         }
 
+        // c^k ∏ (a + bθ) is only a square for even k; an odd k takes one more c.
         self.total_s = Polynomial::multiply(&self.polynomial_ring, &self.monic_polynomial_derivative_squared);
-        self.s = Polynomial::field_modulus_from_polynomial(&self.total_s, &self.monic_polynomial);
-
-        info!("{}", "".to_string());
-        info!("{}", format!("δᵨ = {}", self.total_s));
-        info!("{}", format!("δᵨ = {}", self.s));
-        info!("{}", " in ℤ".to_string());
-
-        let mut solution_found = false;
+        if self.relations_set.len().is_odd() {
+            self.total_s = Polynomial::multiply(&self.total_s, &Polynomial::from_term(self.leading_coefficient.clone(), 0));
+        }
+        self.s = Polynomial::field_modulus_from_polynomial(&self.total_s, &self.monic_polynomial)?;
+
+        debug!("{}", "".to_string());
+        debug!("{}", format!("δᵨ = {}", self.total_s));
+        debug!("{}", format!("δᵨ = {}", self.s));
+        debug!("{}", " in ℤ".to_string());
+
+        if self.method == SquareRootMethod::Montgomery && montgomery::applies(&self.monic_polynomial) {
+            if let Some(residue) = self.exact_square_root(cancel_token)? {
+                self.algebraic_square_root_residue = residue;
+                debug!("{}", format!("γ = {}", self.algebraic_square_root_residue));
+                return Ok(self.split_n().unwrap_or((BigInt::one(), BigInt::one())));
+            }
+            debug!("The exact square root gave no result; falling back to the inert prime CRT.");
+        }
 
         let degree = self.monic_polynomial.degree();
         let f = &self.monic_polynomial;
 
-        // In this block, we need to evaluate using BigInt wrapper in a struct to derive the next prime number
-        // Copy, Serialize, Deserialize are not implemented for BigInt, so this is a workaround
-        let mut primes = Vec::new();
-        let mut values = Vec::new();
+        // Every attempt takes fresh primes, at least d of them and enough that their product
+        // exceeds N, and one square root modulo each, so the two lists always line up.
+        let mut primes: Vec<BigInt> = Vec::new();
+        let mut values: Vec<BigInt> = Vec::new();
         let mut attempts = 7;
 
-        while !solution_found && attempts > 0 {
-            if !primes.is_empty() && !values.is_empty() {
-                primes.clear();
-                values.clear();
-            }
+        while attempts > 0 {
+            primes.clear();
+            values.clear();
 
-            loop {
+            let mut prime_product = BigInt::one();
+            while primes.len() < degree || prime_product < self.n {
                 if cancel_token.is_cancellation_requested() {
-                    return (BigInt::one(), BigInt::one());
+                    return Err(GnfsError::Cancelled);
                 }
 
-                let last_p = self.gnfs.quadratic_factor_pair_collection.clone().last().unwrap().p;
-                let mut last_p_i128 = last_p.clone() as i128;
-                last_p_i128 = PrimeFactory::get_next_prime_from_i128(last_p_i128 + 1).to_i128().unwrap();
-                let last_p = last_p_i128.to_bigint().unwrap();
-
-                let g = Polynomial::parse(&format!("X^{} - X", last_p));
-                let h = finite_field_arithmetic::mod_mod(&g, f, &last_p);
-                let gcd = Polynomial::field_gcd(&h, f, &last_p);
-                let is_irreducible = gcd.cmp(&Polynomial::one()) == Ordering::Equal;
+                let last_p = PrimeFactory::get_next_prime(&self.last_prime);
+                self.last_prime = last_p.clone();
 
-                if !is_irreducible {
+                let frobenius = FrobeniusMap::new(f, &last_p)?;
+                if !frobenius.is_irreducible()? {
                     continue;
                 }
 
-                primes.push(last_p.clone());
-
-                if primes.len() >= degree as usize {
-                    break;
-                }
-            }
-
-            if primes.len() > degree as usize {
-                primes.remove(0);
-                values.remove(0);
+                self.frobenius_maps.insert(last_p.clone(), frobenius);
+                prime_product *= &last_p;
+                primes.push(last_p);
             }
 
-            let prime_product: BigInt = primes.iter().product();
-
-            if &prime_product < &self.n {
-                continue;
-            }
+            self.frobenius_maps.retain(|p, _| primes.contains(p));
 
             if cancel_token.is_cancellation_requested() {
-                return (BigInt::one(), BigInt::one());
+                return Err(GnfsError::Cancelled);
             }
 
             let mut take_inverse = false;
             for p in &primes {
-                let chosen_poly = finite_field_arithmetic::square_root(&self.s, f, p, degree.try_into().unwrap(), &self.gnfs.polynomial_base);
-                let eval = chosen_poly.evaluate(&self.gnfs.polynomial_base);
+                if cancel_token.is_cancellation_requested() {
+                    return Err(GnfsError::Cancelled);
+                }
+                let frobenius = match self.frobenius_maps.get(p) {
+                    Some(frobenius) => frobenius,
+                    None => &FrobeniusMap::new(f, p)?,
+                };
+                let chosen_poly = finite_field_arithmetic::square_root(&self.s, frobenius, degree.try_into().unwrap(), &self.monic_polynomial_base)?;
+                let eval = chosen_poly.evaluate(&self.monic_polynomial_base);
                 let x = eval.mod_floor(p);
 
                 values.push(x.clone());
 
-                info!("{}", "".to_string());
-                info!("{}", format!(" β = {}", chosen_poly));
-                info!("{}", format!("xi = {}", x.clone()));
-                info!("{}", format!(" p = {}", p));
-                info!("{}", format!("{}", &prime_product / p));
-                info!("{}", "".to_string());
+                trace!("{}", "".to_string());
+                trace!("{}", format!(" β = {}", chosen_poly));
+                trace!("{}", format!("xi = {}", x.clone()));
+                trace!("{}", format!(" p = {}", p));
+                trace!("{}", format!("{}", &prime_product / p));
+                trace!("{}", "".to_string());
 
                 take_inverse = !take_inverse;
             }
 
-            let common_modulus = algorithms::chinese_remainder_theorem(&primes, &values);
-            self.algebraic_square_root_residue = common_modulus.mod_floor(&self.n);
-
-            info!("{}", "".to_string());
-
-            for (i, p) in primes.iter().enumerate() {
-                let tv = &values[i];
-                let p = p.clone(); // Clone the value of p
-                info!("{}", format!("{} ≡ {} (mod {})", p, tv, self.algebraic_square_root_residue));
-            }
-
-            info!("{}", "".to_string());
-            info!("{}", format!("γ = {}", self.algebraic_square_root_residue));
-
-            let min = BigInt::min(self.rational_square_root_residue.clone(), self.algebraic_square_root_residue.clone());
-            let max = BigInt::max(self.rational_square_root_residue.clone(), self.algebraic_square_root_residue.clone());
-
-
-            let a = &max + &min;
-            let b = &max - &min;
-
-            let u = GCD::find_gcd(&[self.n.clone(), a.clone()]);
-            let v = GCD::find_gcd(&[self.n.clone(), b.clone()]);
+            // Each βᵢ is only determined up to sign. Odd degree keeps the roots as computed, since
+            // N(-β) = -N(β) lets the norm tell the signs apart. For even degree N(-β) = N(β) gives
+            // nothing to compare against, so every combination of signs is tried, with the first
+            // fixed because γ and -γ give the same gcds.
+            let sign_choices = if degree.is_multiple_of(2) { 1usize << (primes.len() - 1) } else { 1 };
+            for signs in 0..sign_choices {
+                if cancel_token.is_cancellation_requested() {
+                    return Err(GnfsError::Cancelled);
+                }
+                let signed_values: Vec<BigInt> = values
+                    .iter()
+                    .zip(&primes)
+                    .enumerate()
+                    .map(|(i, (x, p))| if i > 0 && (signs >> (i - 1)) & 1 == 1 { (p - x).mod_floor(p) } else { x.clone() })
+                    .collect();
+
+                let common_modulus = algorithms::chinese_remainder_theorem(&primes, &signed_values);
+                self.algebraic_square_root_residue = common_modulus.mod_floor(&self.n);
+
+                trace!("{}", "".to_string());
+
+                for (i, p) in primes.iter().enumerate() {
+                    let tv = &signed_values[i];
+                    let p = p.clone(); // Clone the value of p
+                    trace!("{}", format!("{} ≡ {} (mod {})", p, tv, self.algebraic_square_root_residue));
+                }
 
-            let mut p = BigInt::zero();
-            if &u > &BigInt::one() && &u != &self.n {
-                p = u;
-                solution_found = true;
-            } else if &v > &BigInt::one() && &v != &self.n {
-                p = v;
-                solution_found = true;
-            }
+                trace!("{}", "".to_string());
+                trace!("{}", format!("γ = {}", self.algebraic_square_root_residue));
 
-            if solution_found {
-                let (q, rem) = self.n.div_rem(&p);
-                if rem.is_zero() {
-                    self.algebraic_results = values;
+                if let Some(factors) = self.split_n() {
+                    self.algebraic_results = signed_values;
                     self.algebraic_primes = primes;
-                    return (p, q);
-                } else {
-                    solution_found = false;
+                    return Ok(factors);
                 }
             }
 
-            if !solution_found {
-                info!("{}", format!("No solution found amongst the algebraic square roots {{ {} }} mod primes {{ {} }}",
-                    values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "),
-                    primes.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")));
-                attempts -= 1;
+            debug!("{}", format!("No solution found amongst the algebraic square roots {{ {} }} mod primes {{ {} }}",
+                values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "),
+                primes.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")));
+            attempts -= 1;
+        }
+
+        Ok((BigInt::one(), BigInt::one()))
+    }
+
+    /// γ(cm) mod N by [`montgomery::square_root`] in ℤ[ω], with N(γ) = √(∏ N(ca + bω)) · N(F'(ω)).
+    /// Each N(ca + bω) is c^(d-1) times the sieved norm, and an odd number of them takes the extra
+    /// c's norm c^d. None when the product of the norms isn't a square or no root was found.
+    fn exact_square_root(&self, cancel_token: &CancellationToken) -> Result<Option<BigInt>, GnfsError> {
+        let degree = self.monic_polynomial.degree() as u32;
+        let count = self.relations_set.len();
+        let mut norm_product: BigInt = self.relations_set.iter().map(|rel| &rel.algebraic_norm).product();
+        if !self.leading_coefficient.is_one() {
+            norm_product *= self.leading_coefficient.pow((degree - 1) * count as u32);
+            if count.is_odd() {
+                norm_product *= self.leading_coefficient.pow(degree);
             }
         }
+        if !is_square(&norm_product) {
+            debug!("∏ N(ca + bω) = {} is not a square.", norm_product);
+            return Ok(None);
+        }
+        let norm = norm_product.sqrt() * montgomery::norm(&self.monic_polynomial, &self.monic_polynomial_derivative)?;
+        montgomery::square_root(cancel_token, &self.monic_polynomial, &self.s, &norm, &self.monic_polynomial_base, &self.n)
+    }
+
+    /// The split of N by gcd(N, γ ± χ), if either gives a proper factor.
+    fn split_n(&self) -> Option<(BigInt, BigInt)> {
+        let min = BigInt::min(self.rational_square_root_residue.clone(), self.algebraic_square_root_residue.clone());
+        let max = BigInt::max(self.rational_square_root_residue.clone(), self.algebraic_square_root_residue.clone());
 
-        (BigInt::one(), BigInt::one())
+        [&max + &min, &max - &min]
+            .iter()
+            .map(|value| GCD::find_gcd(&[self.n.clone(), value.clone()]))
+            .find(|p| p > &BigInt::one() && p != &self.n)
+            .and_then(|p| {
+                let (q, rem) = self.n.div_rem(&p);
+                rem.is_zero().then_some((p, q))
+            })
     }
 
     pub fn solve(cancel_token: &CancellationToken, gnfs: &mut GNFS) -> bool {
-        let mut tried_free_relation_indices = Vec::new();
-    
         let poly_base = gnfs.polynomial_base.clone();
-        let free_relations = gnfs.current_relations_progress.relations.free_relations.clone();
-        let mut square_root_finder = SquareFinder::new(gnfs);
-    
+        // Borrowed out of the progress for the duration of the search instead of cloned.
+        let free_relations = std::mem::take(&mut gnfs.current_relations_progress.relations.free_relations);
+        let mut square_root_finder = match SquareFinder::new(gnfs) {
+            Ok(square_root_finder) => square_root_finder,
+            Err(e) => {
+                warn!("The square root stage can't start: {}", e);
+                gnfs.current_relations_progress.relations.free_relations = free_relations;
+                return false;
+            }
+        };
+
+        let fingerprint = SquareRootProgress::fingerprint(&free_relations);
+        let mut progress = load::square_root_progress(gnfs)
+            .filter(|saved| saved.dependency_fingerprint == fingerprint)
+            .unwrap_or(SquareRootProgress { dependency_fingerprint: fingerprint, ..Default::default() });
+        if let Some(last_prime) = &progress.last_prime {
+            square_root_finder.last_prime = last_prime.clone();
+        }
+        if !progress.tried_dependencies.is_empty() {
+            info!("Resuming the square root stage: {} of {} dependencies already tried.", progress.tried_dependencies.len(), free_relations.len());
+        }
+        // The dependency being attempted; it only counts as tried once the attempt runs to completion.
+        let mut in_flight: Option<usize> = None;
+
         let mut free_relation_index = 0;
         let mut solution_found = false;
+        let mut static_random = gnfs.config.random(random_stream::FREE_RELATION_ORDER);
+        gnfs.start_stage("square_root");
+        let started = Instant::now();
+        let tracker = gnfs.progress.tracker(Stage::SquareRoot, "dependencies", progress.tried_dependencies.len() as u64, free_relations.len() as u64);
+        let character_count = gnfs.config.quadratic_character_checks.unwrap_or(character_filter::DEFAULT_CHARACTER_COUNT);
+        let character_pairs = character_filter::character_pairs(cancel_token, gnfs, character_count);
     
         while !solution_found {
+            if let Some(finished) = in_flight.take() {
+                progress.tried_dependencies.push(finished);
+                progress.last_prime = Some(square_root_finder.last_prime.clone());
+                save::square_root_progress(gnfs, &progress);
+                tracker.update(progress.tried_dependencies.len() as u64);
+            }
+
             if cancel_token.is_cancellation_requested() {
-                return solution_found;
+                gnfs.log_event(GnfsEvent::Cancelled { stage: "square_root".to_string() });
+                break;
             }
     
-            if tried_free_relation_indices.len() == free_relations.len() {
-                gnfs.log_message("ERROR: ALL RELATION SETS HAVE BEEN TRIED...?".to_string());
-                gnfs.log_message(format!("If the number of solution sets ({}) is low, you may need to sieve some more and then re-run the matrix solving step.", free_relations.len()));
-                gnfs.log_message("If there are many solution sets, and you have tried them all without finding non-trivial factors, then something is wrong...".to_string());
-                gnfs.log_message("".to_string());
+            if progress.tried_dependencies.len() >= free_relations.len() {
+                gnfs.log_event(GnfsEvent::DependenciesExhausted { count: free_relations.len() });
+                info!("If the number of solution sets ({}) is low, you may need to sieve some more and then re-run the matrix solving step.", free_relations.len());
                 break;
             }
     
-            let mut static_random = StaticRandom::new();
             loop {
                 free_relation_index = static_random.next_range(0, free_relations.len() as u32);
-                if !tried_free_relation_indices.contains(&free_relation_index) {
+                if !progress.tried_dependencies.contains(&(free_relation_index as usize)) {
                     break;
                 }
             }
     
-            tried_free_relation_indices.push(free_relation_index);
+            let index = free_relation_index as usize;
+            in_flight = Some(index);
     
-            let selected_relation_set: &_ = &free_relations[free_relation_index as usize];
+            let selected_relation_set: &_ = &free_relations[index];
     
-            gnfs.log_message("".to_string());
-            gnfs.log_message(format!("Selected solution set index # {}", free_relation_index + 1));
-            gnfs.log_message("".to_string());
-            gnfs.log_message("Calculating Rational Square Root β ∈ ℤ[θ] ...".to_string());
-            gnfs.log_message("".to_string());
-            square_root_finder.calculate_rational_side(cancel_token, selected_relation_set.clone() as Vec<Relation>);
-    
-            if cancel_token.is_cancellation_requested() {
-                gnfs.log_message("Abort: Task canceled by user!".to_string());
-                break;
+            debug!("Selected solution set index # {}", index + 1);
+            if let Some(pair) = character_filter::failing_character(selected_relation_set, &character_pairs) {
+                gnfs.log_event(GnfsEvent::DependencyAttempt { index, outcome: DependencyOutcome::NotASquare { q: pair.p, s: pair.r } });
+                continue;
             }
-    
-            gnfs.log_message("SquareFinder.CalculateRationalSide() Completed.".to_string());
-            gnfs.log_message("".to_string());
-            gnfs.log_message("Calculating Algebraic Square Root...".to_string());
-            gnfs.log_message("                    y ∈ ℤ ...".to_string());
-            gnfs.log_message("δ in a finite field 𝔽ᵨ(θᵨ) ...".to_string());
-            gnfs.log_message("".to_string());
-    
-            let found_factors = square_root_finder.calculate_algebraic_side(cancel_token);
-    
-            if cancel_token.is_cancellation_requested() {
-                gnfs.log_message("Abort: Task canceled by user!".to_string());
-                break;
+
+            debug!("Calculating Rational Square Root β ∈ ℤ[θ] ...");
+            match square_root_finder.calculate_rational_side(cancel_token, selected_relation_set.clone() as Vec<Relation>) {
+                Ok(()) => {}
+                Err(GnfsError::Cancelled) => {
+                    gnfs.log_event(GnfsEvent::Cancelled { stage: "square_root".to_string() });
+                    break;
+                }
+                Err(e) => {
+                    gnfs.log_event(GnfsEvent::DependencyAttempt { index, outcome: DependencyOutcome::Failed(e.to_string()) });
+                    continue;
+                }
             }
     
-            gnfs.log_message("SquareFinder.CalculateAlgebraicSide() Completed.".to_string());
-            gnfs.log_message("".to_string());
-            gnfs.log_message(format!("{}² ≡ {}² (mod {})", square_root_finder.algebraic_square_root_residue, square_root_finder.rational_square_root_residue, square_root_finder.n));
-            gnfs.log_message("".to_string());
+            debug!("Calculating Algebraic Square Root y ∈ ℤ, δ in a finite field 𝔽ᵨ(θᵨ) ...");
+            let found_factors = match square_root_finder.calculate_algebraic_side(cancel_token) {
+                Ok(found_factors) => found_factors,
+                Err(GnfsError::Cancelled) => {
+                    gnfs.log_event(GnfsEvent::Cancelled { stage: "square_root".to_string() });
+                    break;
+                }
+                Err(e) => {
+                    gnfs.log_event(GnfsEvent::DependencyAttempt { index, outcome: DependencyOutcome::Failed(e.to_string()) });
+                    continue;
+                }
+            };
+    
+            debug!("{}² ≡ {}² (mod {})", square_root_finder.algebraic_square_root_residue, square_root_finder.rational_square_root_residue, square_root_finder.n);
     
             let p = found_factors.0;
             let q = found_factors.1;
     
             let non_trivial_factors_found = &p != &BigInt::one() || &q != &BigInt::one();
             if non_trivial_factors_found {
-                solution_found = gnfs.set_factorization_solution(&p, &q);
-    
-                gnfs.log_message(format!("Selected solution set index # {}", free_relation_index + 1));
-                gnfs.log_message("".to_string());
+                gnfs.log_event(GnfsEvent::DependencyAttempt { index, outcome: DependencyOutcome::Factored });
+                gnfs.run_metadata.dependency_index = Some(index);
+                gnfs.complete_stage("square_root", started.elapsed());
+                let report = square_root_finder.to_string();
+                solution_found = gnfs.set_factorization_solution_with_report(&p, &q, Some(report.clone()));
     
                 if solution_found {
-                    gnfs.log_message("NON-TRIVIAL FACTORS FOUND!".to_string());
-                    gnfs.log_message("".to_string());
-                    gnfs.log_message(square_root_finder.to_string());
-                    gnfs.log_message("".to_string());
-                    gnfs.log_message("".to_string());
-                    match &gnfs.factorization {
-                        Some(solution) => gnfs.log_message(solution.to_string()),
-                        None => gnfs.log_message("No solution found.".to_string()),
-                    }
-                    gnfs.log_message("".to_string());
+                    gnfs.log_event(GnfsEvent::FactorsFound { p: p.to_string(), q: q.to_string() });
+                    debug!("{}", report);
                 }
                 break;
             } else if cancel_token.is_cancellation_requested() {
-                gnfs.log_message("Abort: Task canceled by user!".to_string());
+                gnfs.log_event(GnfsEvent::Cancelled { stage: "square_root".to_string() });
                 break;
             } else {
-                gnfs.log_message("Trying a different solution set...".to_string());
-                gnfs.log_message("".to_string());
-                gnfs.log_message("Unable to locate a square root in solution set!".to_string());
-                gnfs.log_message("".to_string());
+                gnfs.log_event(GnfsEvent::DependencyAttempt { index, outcome: DependencyOutcome::Trivial });
             }
         }
-    
+
+        if cancel_token.is_cancellation_requested() {
+            // The dependency in flight is retried on resume, but the primes already searched are kept.
+            progress.last_prime = Some(square_root_finder.last_prime.clone());
+            save::square_root_progress(gnfs, &progress);
+        }
+
+        gnfs.current_relations_progress.relations.free_relations = free_relations;
+        if !solution_found {
+            gnfs.complete_stage("square_root", started.elapsed());
+        }
         solution_found
     }
 
@@ -474,7 +628,7 @@ impl SquareFinder {
         result.push_str("∏ Sᵢ =\n");
         result.push_str(&format!("{}\n", self.polynomial_ring));
         result.push_str("\n");
-        result.push_str(&format!("ƒ         = {}\n", self.gnfs.current_polynomial));
+        result.push_str(&format!("ƒ         = {}\n", self.polynomial));
         result.push_str(&format!("ƒ(m)      = {}\n", self.monic_polynomial));
         result.push_str(&format!("ƒ'(m)     = {}\n", self.monic_polynomial_derivative));
         result.push_str(&format!("ƒ'(m)^2   = {}\n", self.monic_polynomial_derivative_squared));
@@ -556,15 +710,18 @@ impl SquareFinder {
 
 }
 
-pub fn algebraic_square_root(f: &Polynomial, m: &BigInt, degree: i32, dd: &Polynomial, p: &BigInt) -> (BigInt, BigInt) {
+/// The two square roots of `dd` mod (ƒ, p) evaluated at m, or zeros when they don't check out.
+/// Fails when ƒ isn't monic.
+pub fn algebraic_square_root(f: &Polynomial, m: &BigInt, degree: i32, dd: &Polynomial, p: &BigInt) -> Result<(BigInt, BigInt), GnfsError> {
     let start_polynomial = Polynomial::field_modulus(dd, p);
     //let start_inverse_polynomial = modular_inverse(&start_polynomial, p);
 
-    let result_poly1 = finite_field_arithmetic::square_root(&start_polynomial, f, p, degree, m);
+    let frobenius = FrobeniusMap::new(f, p)?;
+    let result_poly1 = finite_field_arithmetic::square_root(&start_polynomial, &frobenius, degree, m)?;
     let result_poly2 = modular_inverse(&result_poly1, p);
 
-    let result_squared1 = Polynomial::mod_mod(&Polynomial::square(&result_poly1), f, p);
-    let result_squared2 = Polynomial::mod_mod(&Polynomial::square(&result_poly2), f, p);
+    let result_squared1 = Polynomial::mod_mod(&Polynomial::square(&result_poly1), f, p)?;
+    let result_squared2 = Polynomial::mod_mod(&Polynomial::square(&result_poly2), f, p)?;
 
     let both_results_agree = result_squared1 == result_squared2;
 
@@ -575,9 +732,9 @@ pub fn algebraic_square_root(f: &Polynomial, m: &BigInt, degree: i32, dd: &Polyn
     let test_evaluations_are_modular_inverses = inverse_prime == result2;
 
     if both_results_agree && test_evaluations_are_modular_inverses {
-        (result1, result2)
+        Ok((result1, result2))
     } else {
-        (BigInt::zero(), BigInt::zero())
+        Ok((BigInt::zero(), BigInt::zero()))
     }
 }
 
@@ -588,31 +745,80 @@ pub fn modular_inverse(poly: &Polynomial, mod_: &BigInt) -> Polynomial {
     Polynomial { terms }
 }
 
+/// Whether `n` is the square of an integer; negative numbers never are.
 pub fn is_square(n: &BigInt) -> bool {
-    let zero = BigInt::from(0);
-    let one = BigInt::from(1);
-    let two = BigInt::from(2);
-
-    if n < &zero {
+    if n.is_negative() {
         return false;
     }
+    let root = n.sqrt();
+    &root * &root == *n
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Factorizer;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::gnfs_config::GnfsConfig;
+    use crate::core::stage::StageOutcome;
+
+    /// Runs every stage for N with ƒ and m in place of the selected pair.
+    fn factor_with_pair(n: u64, f: &str, m: u64, config: GnfsConfig) -> GNFS {
+        let token = CancellationToken::new();
+        let locations = DirectoryLocations::temporary("square_finder").unwrap();
+        let (n, m, bound) = (BigInt::from(n), BigInt::from(m), BigInt::from(100));
+        let mut gnfs = GNFS::with_save_locations(&token, locations, config, &n, &m, 3, &bound, 65, 200, true).unwrap();
+        gnfs.set_polynomial_pair(Polynomial::parse(f), m);
+        gnfs.polynomial_collection = vec![gnfs.current_polynomial.clone()];
+        gnfs.rebuild_factor_bases(&token, &bound);
+
+        let outcome = Factorizer::new(n).run_stages(&token, &mut gnfs, Stage::Sieve);
+        assert_eq!(outcome, StageOutcome::Completed);
+        gnfs
+    }
 
-    let mut x = n.clone();
-    while &x % &two == zero {
-        x /= &two;
+    #[test]
+    fn factors_with_a_non_monic_polynomial() {
+        // 2·28³ + 28² + 15·28 + 5 = 45113 = 197 · 229.
+        let gnfs = factor_with_pair(45113, "2X^3 + X^2 + 15X + 5", 28, GnfsConfig::default());
+        assert_eq!(gnfs.current_polynomial[3], BigInt::from(2));
+        let solution = gnfs.factorization.expect("the square root stage should split N");
+        assert_eq!(solution.p * solution.q, BigInt::from(45113));
     }
 
-    if &x == &one {
-        return true;
+    #[test]
+    fn montgomery_falls_back_to_inert_primes_for_even_degree() {
+        // 13⁴ + 7·13³ + 6·13² + 12·13 + 3 = 45113; N(-γ) = N(γ) in degree 4 leaves the exact
+        // square root nothing to pick the sign of γ by.
+        let f = "X^4 + 7X^3 + 6X^2 + 12X + 3";
+        assert!(!montgomery::applies(&Polynomial::parse(f)));
+        let config = GnfsConfig { square_root_method: Some(SquareRootMethod::Montgomery), ..GnfsConfig::default() };
+        let gnfs = factor_with_pair(45113, f, 13, config);
+        assert_eq!(gnfs.current_polynomial.degree(), 4);
+        let solution = gnfs.factorization.expect("the inert prime CRT should split N");
+        assert_eq!(solution.p * solution.q, BigInt::from(45113));
     }
 
-    let mut i = BigInt::from(3);
-    while &i * &i <= x {
-        if &x % &i == zero {
-            return false;
+    #[test]
+    fn inert_primes_stay_paired_with_their_square_roots() {
+        // The first d inert primes multiply to less than N here, which once left the primes and
+        // their square roots out of step. A few dependencies are enough to go through the search.
+        let n = BigInt::from(1000000016000000063u64);
+        let config = GnfsConfig { square_root_method: Some(SquareRootMethod::InertPrimes), ..GnfsConfig::default() };
+        let token = CancellationToken::new();
+        let stop = token.clone();
+        let result = Factorizer::new(n.clone())
+            .with_config(config)
+            .with_save_locations(DirectoryLocations::temporary("inert_primes").unwrap())
+            .with_progress_reporter(move |update: &crate::core::progress::ProgressUpdate| {
+                if update.stage == Stage::SquareRoot && update.completed >= 3 {
+                    stop.cancel();
+                }
+            })
+            .run(&token)
+            .unwrap();
+        match result.solution {
+            Some(solution) => assert_eq!(solution.p * solution.q, n),
+            None => assert_eq!(result.outcome, StageOutcome::Cancelled),
         }
-        i += &two;
     }
-
-    true
-}
\ No newline at end of file
+}
@@ -0,0 +1,253 @@
+// src/tui.rs
+
+//! Terminal dashboard for long runs, behind the `tui` feature. It follows the run through
+//! `events.jsonl`, the same record the status and report commands read, so it needs nothing from
+//! the pipeline beyond the events it already logs. Pressing q or Esc cancels the run, which then
+//! checkpoints as usual.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Sparkline};
+use ratatui::Frame;
+use crate::core::cancellation_token::CancellationToken;
+use crate::core::error::GnfsError;
+use crate::core::events::{EventRecord, GnfsEvent};
+use crate::core::memory_usage::{format_bytes, MemoryUsage};
+
+/// How often the dashboard redraws and checks for new events and key presses.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+/// Relation-rate samples kept for the sparkline.
+const RATE_HISTORY: usize = 120;
+/// Event lines kept for the log pane.
+const LOG_LINES: usize = 200;
+
+/// Everything the dashboard shows, folded from the event stream.
+#[derive(Debug, Default)]
+pub struct DashboardState {
+    pub stage: Option<String>,
+    pub smooth_relations: usize,
+    pub target_quantity: usize,
+    /// Smooth relations per second between consecutive sieve progress events.
+    pub rates: VecDeque<u64>,
+    pub log: VecDeque<String>,
+    last_progress: Option<(DateTime<Utc>, usize)>,
+}
+
+impl DashboardState {
+    pub fn apply(&mut self, record: &EventRecord) {
+        match &record.event {
+            GnfsEvent::StageStarted { stage } => self.stage = Some(stage.clone()),
+            GnfsEvent::SieveProgress(statistics) => {
+                if let Some((at, smooth)) = self.last_progress {
+                    let seconds = (record.timestamp - at).num_milliseconds().max(1) as f64 / 1000.0;
+                    let gained = statistics.smooth_relations.saturating_sub(smooth) as f64;
+                    self.rates.push_back((gained / seconds).round() as u64);
+                    if self.rates.len() > RATE_HISTORY {
+                        self.rates.pop_front();
+                    }
+                }
+                self.last_progress = Some((record.timestamp, statistics.smooth_relations));
+                self.smooth_relations = statistics.smooth_relations;
+                self.target_quantity = statistics.target_quantity;
+            }
+            _ => {}
+        }
+        self.log.push_back(format!("{} {}", record.timestamp.format("%H:%M:%S"), record.event));
+        if self.log.len() > LOG_LINES {
+            self.log.pop_front();
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, progress, rate, log] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(6),
+            Constraint::Min(5),
+        ])
+        .areas(frame.area());
+
+        let memory = MemoryUsage::resident().map_or("unknown".to_string(), format_bytes);
+        let status = format!(
+            "Stage: {}    Memory: {}    (q to cancel and checkpoint)",
+            self.stage.as_deref().unwrap_or("starting"),
+            memory
+        );
+        frame.render_widget(Paragraph::new(status).block(Block::default().borders(Borders::ALL).title("GNFS")), header);
+
+        let ratio = if self.target_quantity == 0 {
+            0.0
+        } else {
+            (self.smooth_relations as f64 / self.target_quantity as f64).min(1.0)
+        };
+        let gauge = Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title("Smooth relations"))
+            .gauge_style(Style::default().fg(Color::Green))
+            .ratio(ratio)
+            .label(format!("{}/{}", self.smooth_relations, self.target_quantity));
+        frame.render_widget(gauge, progress);
+
+        let rates: Vec<u64> = self.rates.iter().copied().collect();
+        let title = format!("Relations/s ({})", rates.last().copied().unwrap_or(0));
+        let sparkline = Sparkline::default()
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .style(Style::default().fg(Color::Cyan))
+            .data(&rates);
+        frame.render_widget(sparkline, rate);
+
+        let visible = log.height.saturating_sub(2) as usize;
+        let items: Vec<ListItem> = self.log.iter().skip(self.log.len().saturating_sub(visible)).map(|line| ListItem::new(Line::from(line.as_str()))).collect();
+        frame.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title("Events")), log);
+    }
+}
+
+/// Reads the records appended to `events.jsonl` since the previous call.
+struct EventTail {
+    path: PathBuf,
+    offset: u64,
+}
+
+impl EventTail {
+    /// Starts at the current end of the file, so events from earlier runs aren't replayed.
+    fn new(path: &Path) -> Self {
+        let offset = std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+        EventTail { path: path.to_path_buf(), offset }
+    }
+
+    fn read_new(&mut self) -> Result<Vec<EventRecord>, GnfsError> {
+        let Ok(mut file) = File::open(&self.path) else {
+            return Ok(Vec::new());
+        };
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut reader = BufReader::new(file);
+        let mut records = Vec::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            // Stop at a partially written line and pick it up on the next refresh.
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+            self.offset += read as u64;
+            if !line.trim().is_empty() {
+                records.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Shows the dashboard until `finished` returns true. Quitting early cancels `cancel_token`
+/// and keeps the dashboard up until the run has stopped.
+pub fn run(events_path: &Path, cancel_token: &CancellationToken, finished: impl Fn() -> bool) -> Result<(), GnfsError> {
+    let mut tail = EventTail::new(events_path);
+    let mut state = DashboardState::default();
+    let mut terminal = ratatui::init();
+
+    let result = (|| -> Result<(), GnfsError> {
+        while !finished() {
+            for record in tail.read_new()? {
+                state.apply(&record);
+            }
+            terminal.draw(|frame| state.draw(frame))?;
+
+            if event::poll(REFRESH_INTERVAL)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                        cancel_token.cancel();
+                    }
+                }
+            }
+        }
+        Ok(())
+    })();
+
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use chrono::TimeDelta;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use super::*;
+    use crate::core::directory_location::DirectoryLocations;
+    use crate::core::events;
+    use crate::relation_sieve::poly_relations_sieve_progress::SieveStatistics;
+
+    fn progress(at: DateTime<Utc>, smooth_relations: usize) -> EventRecord {
+        let statistics = SieveStatistics { smooth_relations, target_quantity: 200, ..SieveStatistics::default() };
+        EventRecord { timestamp: at, event: GnfsEvent::SieveProgress(statistics) }
+    }
+
+    #[test]
+    fn folds_stage_progress_and_rates_from_events() {
+        let start = Utc::now();
+        let mut state = DashboardState::default();
+        state.apply(&EventRecord { timestamp: start, event: GnfsEvent::StageStarted { stage: "Sieve".to_string() } });
+        state.apply(&progress(start, 10));
+        state.apply(&progress(start + TimeDelta::seconds(2), 50));
+        state.apply(&progress(start + TimeDelta::seconds(4), 50));
+
+        assert_eq!(state.stage.as_deref(), Some("Sieve"));
+        assert_eq!((state.smooth_relations, state.target_quantity), (50, 200));
+        assert_eq!(state.rates, [20, 0]);
+        assert_eq!(state.log.len(), 4);
+
+        for _ in 0..LOG_LINES + RATE_HISTORY {
+            state.apply(&progress(start, 50));
+        }
+        assert_eq!((state.log.len(), state.rates.len()), (LOG_LINES, RATE_HISTORY));
+    }
+
+    #[test]
+    fn tails_only_complete_lines_written_after_it_started() {
+        let locations = DirectoryLocations::temporary("tui_tail").unwrap();
+        let path = Path::new(&locations.save_directory).join("events.jsonl");
+        events::append(&path, &GnfsEvent::StageStarted { stage: "Earlier run".to_string() }).unwrap();
+
+        let mut tail = EventTail::new(&path);
+        assert!(tail.read_new().unwrap().is_empty());
+        events::append(&path, &GnfsEvent::StageStarted { stage: "Sieve".to_string() }).unwrap();
+        let record = serde_json::to_string(&progress(Utc::now(), 7)).unwrap();
+        let (written, pending) = record.split_at(record.len() / 2);
+        File::options().append(true).open(&path).unwrap().write_all(written.as_bytes()).unwrap();
+
+        let records = tail.read_new().unwrap();
+        assert_eq!(records.len(), 1);
+        assert!(matches!(&records[0].event, GnfsEvent::StageStarted { stage } if stage == "Sieve"));
+
+        writeln!(File::options().append(true).open(&path).unwrap(), "{}", pending).unwrap();
+        let records = tail.read_new().unwrap();
+        assert!(matches!(&records[..], [EventRecord { event: GnfsEvent::SieveProgress(statistics), .. }] if statistics.smooth_relations == 7));
+        assert!(tail.read_new().unwrap().is_empty());
+    }
+
+    #[test]
+    fn draws_the_stage_progress_and_latest_events() {
+        let start = Utc::now();
+        let mut state = DashboardState::default();
+        state.apply(&EventRecord { timestamp: start, event: GnfsEvent::StageStarted { stage: "Sieve".to_string() } });
+        state.apply(&progress(start, 40));
+        state.apply(&progress(start + TimeDelta::seconds(1), 90));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| state.draw(frame)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("Stage: Sieve"));
+        assert!(screen.contains("90/200"));
+        assert!(screen.contains("Relations/s (50)"));
+        assert!(screen.contains("Stage started: Sieve"));
+    }
+}